@@ -74,6 +74,11 @@ extern "C"
         struct sockaddr_in addr; /**< Remote address of accepted connection.              */
     } demi_accept_result_t;
 
+    /**
+     * @brief Flag of a pop that reached the end of the stream, so no more data follows.
+     */
+#define DEMI_QR_EOF (1ULL << 0)
+
     /**
      * @brief Result value for an asynchronous I/O operation.
      */
@@ -92,6 +97,21 @@ extern "C"
             demi_sgarray_t sga;        /**< Pushed/popped scatter-gather array. */
             demi_accept_result_t ares; /**< Accept result.                      */
         } qr_value;
+        uint64_t qr_flags; /**< Flags of the completed operation, such as DEMI_QR_EOF. */
+
+        /*
+         * Timestamps of the operation, in nanoseconds since a process-wide origin of a monotonic clock. These are
+         * zero if operation timing is disabled in the configuration file. Fields are only ever appended to this
+         * structure, so that the layout of the fields above stays the same across versions.
+         */
+        uint64_t qr_start_ns; /**< When the operation started.   */
+        uint64_t qr_end_ns;   /**< When the operation completed. */
+
+        /*
+         * When the data that a pop operation returns was received, in nanoseconds since the same origin as the
+         * timestamps above. This is zero for other operations, and for libOSes that do not stamp received data.
+         */
+        uint64_t qr_rx_ns;
     } demi_qresult_t;
 
 #ifdef __cplusplus
@@ -0,0 +1,91 @@
+#!/bin/bash
+
+# Copyright (c) Microsoft Corporation.
+# Licensed under the MIT license.
+
+# Runs the TCP ping-pong server over a TAP interface, with the catnip-tap LibOS, and talks to it from a client that uses
+# the network stack of the host kernel. Everything runs in a throwaway network namespace, so no NIC is needed, and the
+# host network is left alone. Must run as root, after building the examples with LIBOS=catnip-tap.
+#
+# Usage: tap-test.sh [BINDIR]
+
+BINDIR=${1:-$(pwd)/bin}
+
+# Network Parameters
+NETNS=demikernel-tap
+TAP=demitap0
+HOST_IPV4=10.77.0.1
+DEMIKERNEL_IPV4=10.77.0.2
+DEMIKERNEL_LINK_ADDR="02:00:00:00:00:02"
+PORT=12345
+
+# Test Parameters
+BUFFER_SIZE=64
+NROUNDS=1024
+TIMEOUT=60
+
+#===============================================================================
+
+set -e
+
+CONFIG_PATH=$(mktemp)
+
+cleanup() {
+	ip netns del $NETNS 2>/dev/null || true
+	rm -f $CONFIG_PATH
+}
+trap cleanup EXIT
+
+# Create a namespace with a persistent TAP interface, whose kernel end is the host side of the link.
+ip netns add $NETNS
+ip netns exec $NETNS ip link set lo up
+ip netns exec $NETNS ip tuntap add dev $TAP mode tap
+ip netns exec $NETNS ip addr add $HOST_IPV4/24 dev $TAP
+ip netns exec $NETNS ip link set $TAP up
+
+cat > $CONFIG_PATH <<EOF
+catnip:
+  my_ipv4_addr: $DEMIKERNEL_IPV4
+  my_link_addr: "$DEMIKERNEL_LINK_ADDR"
+  my_interface_name: "$TAP"
+EOF
+
+# Demikernel end of the link.
+CONFIG_PATH=$CONFIG_PATH LIBOS=catnip-tap timeout $TIMEOUT \
+	ip netns exec $NETNS $BINDIR/examples/rust/tcp-ping-pong.elf --server $DEMIKERNEL_IPV4:$PORT &
+SERVER_PID=$!
+
+# Host kernel end of the link. Sends the same data as the ping-pong client, and checks that it comes back.
+timeout $TIMEOUT ip netns exec $NETNS python3 - $DEMIKERNEL_IPV4 $PORT $BUFFER_SIZE $NROUNDS <<'EOF'
+import socket, sys, time
+
+host, port, size, nrounds = sys.argv[1], int(sys.argv[2]), int(sys.argv[3]), int(sys.argv[4])
+
+# The server may still be starting up.
+while True:
+    try:
+        sock = socket.create_connection((host, port), timeout=1)
+        break
+    except OSError:
+        time.sleep(0.1)
+sock.settimeout(10)
+
+for i in range(nrounds):
+    fill, data = i % 254 + 1, bytearray()
+    for _ in range(size):
+        data.append(fill)
+        fill = fill % 254 + 1
+    sock.sendall(data)
+    echo = bytearray()
+    while len(echo) < size:
+        chunk = sock.recv(size - len(echo))
+        if not chunk:
+            sys.exit("connection closed after %d rounds" % i)
+        echo += chunk
+    if echo != data:
+        sys.exit("echo mismatch in round %d" % i)
+sock.close()
+print("%d rounds of %d bytes echoed over %s" % (nrounds, size, host))
+EOF
+
+wait $SERVER_PID
@@ -26,6 +26,7 @@ use ::std::{
         Context,
         Poll,
     },
+    time::Instant,
 };
 
 //==============================================================================
@@ -38,6 +39,8 @@ pub struct AcceptFuture {
     fd: RawFd,
     /// Socket address of accept connection.
     saddr: SockAddr,
+    /// Deadline for the operation, if any.
+    deadline: Option<Instant>,
 }
 
 //==============================================================================
@@ -46,11 +49,13 @@ pub struct AcceptFuture {
 
 /// Associate Functions for Accept Operation Descriptors
 impl AcceptFuture {
-    /// Creates a descriptor for an accept operation.
-    pub fn new(fd: RawFd) -> Self {
+    /// Creates a descriptor for an accept operation. If a `deadline` is given, the operation fails with `ETIMEDOUT`
+    /// if no connection request arrives before it.
+    pub fn new(fd: RawFd, deadline: Option<Instant>) -> Self {
         Self {
             fd,
             saddr: unsafe { mem::zeroed() },
+            deadline,
         }
     }
 }
@@ -100,6 +105,11 @@ impl Future for AcceptFuture {
 
                 // Operation in progress.
                 if errno == libc::EWOULDBLOCK || errno == libc::EAGAIN {
+                    if let Some(deadline) = self_.deadline {
+                        if Instant::now() >= deadline {
+                            return Poll::Ready(Err(Fail::new(libc::ETIMEDOUT, "accept timed out")));
+                        }
+                    }
                     ctx.waker().wake_by_ref();
                     return Poll::Pending;
                 }
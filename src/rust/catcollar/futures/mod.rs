@@ -11,3 +11,7 @@ pub mod connect;
 pub mod pop;
 pub mod push;
 pub mod pushto;
+#[cfg(target_os = "linux")]
+pub mod recvmmsg;
+#[cfg(target_os = "linux")]
+pub mod sendmmsg;
@@ -24,6 +24,10 @@ use ::std::{
         Context,
         Poll,
     },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 //==============================================================================
@@ -38,6 +42,8 @@ pub struct PopFuture {
     fd: RawFd,
     /// Associated receive buffer.
     buf: DemiBuffer,
+    /// Deadline for the operation, if any.
+    deadline: Option<Instant>,
 }
 
 //==============================================================================
@@ -46,9 +52,10 @@ pub struct PopFuture {
 
 /// Associate Functions for Pop Operation Descriptors
 impl PopFuture {
-    /// Creates a descriptor for a pop operation.
-    pub fn new(rt: IoUringRuntime, fd: RawFd, buf: DemiBuffer) -> Self {
-        Self { rt, fd, buf }
+    /// Creates a descriptor for a pop operation. If a `deadline` is given, the operation fails with `ETIMEDOUT` if
+    /// no data is received before it.
+    pub fn new(rt: IoUringRuntime, fd: RawFd, buf: DemiBuffer, deadline: Option<Instant>) -> Self {
+        Self { rt, fd, buf, deadline }
     }
 }
 
@@ -63,7 +70,10 @@ impl Future for PopFuture {
     /// Polls the underlying pop operation.
     fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
         let self_: &mut PopFuture = self.get_mut();
-        let request_id: RequestId = self_.rt.pop(self_.fd, self_.buf.clone())?;
+        let timeout: Option<Duration> = self_
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let request_id: RequestId = self_.rt.pop(self_.fd, self_.buf.clone(), timeout)?;
         match self_.rt.peek(request_id) {
             // Operation completed.
             Ok((addr, size)) if size >= 0 => {
@@ -76,6 +86,10 @@ impl Future for PopFuture {
             // Operation not completed, thus parse errno to find out what happened.
             Ok((None, size)) if size < 0 => {
                 let errno: i32 = -size;
+                // Operation cancelled by the linked timeout.
+                if errno == libc::ECANCELED && self_.deadline.is_some() {
+                    return Poll::Ready(Err(Fail::new(libc::ETIMEDOUT, "pop timed out")));
+                }
                 // Operation in progress.
                 if errno == libc::EWOULDBLOCK || errno == libc::EAGAIN {
                     ctx.waker().wake_by_ref();
@@ -67,6 +67,15 @@ impl Future for PushFuture {
             // Operation completed.
             Ok((_, size)) if size >= 0 => {
                 trace!("data pushed ({:?} bytes)", size);
+
+                // Stream sockets may accept only part of the data, thus push the remaining bytes.
+                let size: usize = size as usize;
+                if size < self_.buf.len() {
+                    self_.buf.adjust(size)?;
+                    ctx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+
                 Poll::Ready(Ok(()))
             },
             // Operation not completed, thus parse errno to find out what happened.
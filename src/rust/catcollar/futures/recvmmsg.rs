@@ -0,0 +1,94 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::{
+    catcollar::IoUringRuntime,
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+    },
+};
+use ::std::{
+    future::Future,
+    net::SocketAddrV4,
+    os::fd::RawFd,
+    pin::Pin,
+    task::{
+        Context,
+        Poll,
+    },
+    time::Instant,
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Batched Pop Operation Descriptor
+pub struct RecvmmsgFuture {
+    /// Underlying runtime.
+    rt: IoUringRuntime,
+    /// Associated file descriptor.
+    fd: RawFd,
+    /// Maximum number of bytes to pop.
+    size: usize,
+    /// Deadline for the operation, if any.
+    deadline: Option<Instant>,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate Functions for Batched Pop Operation Descriptors
+impl RecvmmsgFuture {
+    /// Creates a descriptor for a pop operation on a UDP socket. Datagrams that are larger than `size` are truncated.
+    /// If a `deadline` is given, the operation fails with `ETIMEDOUT` if no datagram is received before it.
+    pub fn new(rt: IoUringRuntime, fd: RawFd, size: usize, deadline: Option<Instant>) -> Self {
+        Self { rt, fd, size, deadline }
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Future Trait Implementation for Batched Pop Operation Descriptors
+impl Future for RecvmmsgFuture {
+    type Output = Result<(Option<SocketAddrV4>, DemiBuffer), Fail>;
+
+    /// Polls the underlying pop operation.
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+        let self_: &mut RecvmmsgFuture = self.get_mut();
+        match self_.rt.recvmmsg(self_.fd) {
+            // Operation completed.
+            Ok(Some((addr, mut buf))) => {
+                trace!("data received ({:?} bytes)", buf.len());
+                if buf.len() > self_.size {
+                    let trim_size: usize = buf.len() - self_.size;
+                    buf.trim(trim_size)?;
+                }
+                Poll::Ready(Ok((Some(addr), buf)))
+            },
+            // Operation in progress.
+            Ok(None) => {
+                if let Some(deadline) = self_.deadline {
+                    if Instant::now() >= deadline {
+                        return Poll::Ready(Err(Fail::new(libc::ETIMEDOUT, "pop timed out")));
+                    }
+                }
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            },
+            // Operation failed.
+            Err(e) => {
+                warn!("pop failed ({:?})", e);
+                Poll::Ready(Err(e))
+            },
+        }
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::{
+    catcollar::IoUringRuntime,
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+    },
+};
+use ::std::{
+    future::Future,
+    net::SocketAddrV4,
+    os::fd::RawFd,
+    pin::Pin,
+    task::{
+        Context,
+        Poll,
+    },
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Batched Pushto Operation Descriptor
+pub struct SendmmsgFuture {
+    /// Underlying runtime.
+    rt: IoUringRuntime,
+    /// Associated file descriptor.
+    fd: RawFd,
+    /// Destination address.
+    addr: SocketAddrV4,
+    /// Associated send buffer, until it is queued.
+    buf: Option<DemiBuffer>,
+    /// Identifier of the queued datagram.
+    id: Option<u64>,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate Functions for Batched Pushto Operation Descriptors
+impl SendmmsgFuture {
+    /// Creates a descriptor for a pushto operation on a UDP socket.
+    pub fn new(rt: IoUringRuntime, fd: RawFd, addr: SocketAddrV4, buf: DemiBuffer) -> Self {
+        Self {
+            rt,
+            fd,
+            addr,
+            buf: Some(buf),
+            id: None,
+        }
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Future Trait Implementation for Batched Pushto Operation Descriptors
+impl Future for SendmmsgFuture {
+    type Output = Result<(), Fail>;
+
+    /// Polls the target [SendmmsgFuture].
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+        let self_: &mut SendmmsgFuture = self.get_mut();
+
+        // Queue the datagram on the first poll, and send it on the next one, so that the datagrams of all pushto
+        // operations that are polled in between go out in the same batch.
+        if let Some(buf) = self_.buf.take() {
+            self_.id = Some(self_.rt.sendmmsg(self_.fd, self_.addr, buf));
+            ctx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let id: u64 = self_.id.expect("datagram should have been queued");
+        match self_.rt.poll_sendmmsg(self_.fd, id) {
+            // Operation completed.
+            Some(Ok(())) => {
+                trace!("data pushed");
+                Poll::Ready(Ok(()))
+            },
+            // Operation failed.
+            Some(Err(e)) => {
+                warn!("pushto failed ({:?})", e);
+                Poll::Ready(Err(e))
+            },
+            // Operation in progress.
+            None => {
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            },
+        }
+    }
+}
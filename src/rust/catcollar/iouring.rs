@@ -36,6 +36,7 @@ use ::std::{
         self,
         null_mut,
     },
+    time::Duration,
 };
 
 //==============================================================================
@@ -163,8 +164,14 @@ impl IoUring {
         }
     }
 
-    /// Pops a buffer from the target IO user ring.
-    pub fn pop(&mut self, sockfd: RawFd, buf: DemiBuffer) -> Result<*mut liburing::msghdr, Fail> {
+    /// Pops a buffer from the target IO user ring. If a `timeout` is given, the operation is linked to a timeout
+    /// request, so that it is cancelled by the kernel if it does not complete in time.
+    pub fn pop(
+        &mut self,
+        sockfd: RawFd,
+        buf: DemiBuffer,
+        timeout: Option<Duration>,
+    ) -> Result<*mut liburing::msghdr, Fail> {
         let len: usize = buf.len();
         let data_ptr: *const u8 = buf.as_ptr();
         let io_uring: &mut liburing::io_uring = &mut self.io_uring;
@@ -196,7 +203,29 @@ impl IoUring {
             let msg_ptr: *mut liburing::msghdr = Box::into_raw(msg);
             liburing::io_uring_sqe_set_data(sqe, msg_ptr as *mut c_void);
             liburing::io_uring_prep_recvmsg(sqe, sockfd, msg_ptr as *mut liburing::msghdr, 0);
-            if liburing::io_uring_submit(io_uring) != 1 {
+
+            // Link a timeout to the operation. The kernel copies the timespec when the request is submitted, and the
+            // completion of the timeout request itself carries no user data.
+            let nrequests: c_int = match timeout {
+                Some(timeout) => {
+                    liburing::io_uring_sqe_set_flags(sqe, 1 << liburing::IOSQE_IO_LINK_BIT);
+                    let timeout_sqe: *mut liburing::io_uring_sqe = liburing::io_uring_get_sqe(io_uring);
+                    if timeout_sqe.is_null() {
+                        let errno: libc::c_int = *libc::__errno_location();
+                        error!("pop(): failed to get sqe for timeout (errno={:?})", errno);
+                        return Err(Fail::new(errno, "operation failed"));
+                    }
+                    let mut ts: liburing::__kernel_timespec = liburing::__kernel_timespec {
+                        tv_sec: timeout.as_secs() as i64,
+                        tv_nsec: timeout.subsec_nanos() as i64,
+                    };
+                    liburing::io_uring_prep_link_timeout(timeout_sqe, &mut ts, 0);
+                    liburing::io_uring_sqe_set_data(timeout_sqe, ptr::null_mut());
+                    2
+                },
+                None => 1,
+            };
+            if liburing::io_uring_submit(io_uring) != nrequests {
                 return Err(Fail::new(libc::EIO, "failed to submit pop operation"));
             }
 
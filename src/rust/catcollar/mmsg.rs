@@ -0,0 +1,227 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    pal::{
+        data_structures::{
+            SockAddr,
+            SockAddrIn,
+            Socklen,
+        },
+        linux,
+    },
+    runtime::{
+        fail::Fail,
+        limits,
+        memory::DemiBuffer,
+    },
+};
+use ::std::{
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    ffi::c_void,
+    mem,
+    net::SocketAddrV4,
+    os::unix::prelude::RawFd,
+    ptr,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Maximum number of datagrams that are moved in a single system call.
+pub const MMSG_BATCH_SIZE: usize = 32;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Batches I/O on UDP sockets, so that a single `recvmmsg(2)` or `sendmmsg(2)` system call serves multiple pop and
+/// pushto operations.
+#[derive(Default)]
+pub struct MmsgBatcher {
+    /// Datagrams that were received but not popped yet, per socket.
+    received: HashMap<RawFd, VecDeque<(SocketAddrV4, DemiBuffer)>>,
+    /// Datagrams that were pushed but not sent yet, per socket.
+    outgoing: HashMap<RawFd, VecDeque<(u64, SocketAddrV4, DemiBuffer)>>,
+    /// Outcome of datagrams that were sent, but not collected yet, as zero or an error number.
+    sent: HashMap<u64, i32>,
+    /// Buffers to receive the next batch of datagrams into.
+    spare: Vec<DemiBuffer>,
+    /// Identifier of the next pushed datagram.
+    next_id: u64,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl MmsgBatcher {
+    /// Pops a datagram that was received on a socket. If none is pending, receives a new batch of datagrams without
+    /// blocking. Returns `None` if no datagram is available.
+    pub fn pop(&mut self, fd: RawFd) -> Result<Option<(SocketAddrV4, DemiBuffer)>, Fail> {
+        if let Some(datagram) = self.received.get_mut(&fd).and_then(|queue| queue.pop_front()) {
+            return Ok(Some(datagram));
+        }
+        self.recvmmsg(fd)?;
+        Ok(self.received.get_mut(&fd).and_then(|queue| queue.pop_front()))
+    }
+
+    /// Queues a datagram to be sent on a socket with the next batch. Returns an identifier to collect the outcome with.
+    pub fn pushto(&mut self, fd: RawFd, addr: SocketAddrV4, buf: DemiBuffer) -> u64 {
+        let id: u64 = self.next_id;
+        self.next_id += 1;
+        self.outgoing.entry(fd).or_default().push_back((id, addr, buf));
+        id
+    }
+
+    /// Collects the outcome of a queued datagram. If it was not sent yet, sends all datagrams that are queued on the
+    /// socket without blocking. Returns `None` if the datagram is still queued.
+    pub fn poll_pushto(&mut self, fd: RawFd, id: u64) -> Option<Result<(), Fail>> {
+        if !self.sent.contains_key(&id) {
+            self.sendmmsg(fd);
+        }
+        match self.sent.remove(&id)? {
+            0 => Some(Ok(())),
+            errno => {
+                let cause: String = format!("sendmmsg(): operation failed (errno={:?})", errno);
+                error!("poll_pushto(): {}", cause);
+                Some(Err(Fail::new(errno, &cause)))
+            },
+        }
+    }
+
+    /// Drops all datagrams that are pending on a socket, which is about to be closed.
+    pub fn forget(&mut self, fd: RawFd) {
+        self.received.remove(&fd);
+        if let Some(queue) = self.outgoing.remove(&fd) {
+            for (id, _, _) in queue {
+                self.sent.insert(id, libc::EBADF);
+            }
+        }
+    }
+
+    /// Receives a batch of datagrams on a socket, without blocking.
+    fn recvmmsg(&mut self, fd: RawFd) -> Result<(), Fail> {
+        while self.spare.len() < MMSG_BATCH_SIZE {
+            self.spare.push(DemiBuffer::new(limits::RECVBUF_SIZE_MAX as u16));
+        }
+
+        let mut addrs: Vec<SockAddrIn> = vec![unsafe { mem::zeroed::<SockAddrIn>() }; MMSG_BATCH_SIZE];
+        let mut iovecs: Vec<libc::iovec> = self
+            .spare
+            .iter_mut()
+            .map(|buf| libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut c_void,
+                iov_len: buf.len(),
+            })
+            .collect();
+        let mut mmsgs: Vec<libc::mmsghdr> = Vec::with_capacity(MMSG_BATCH_SIZE);
+        for (addr, iovec) in addrs.iter_mut().zip(iovecs.iter_mut()) {
+            let mut mmsg: libc::mmsghdr = unsafe { mem::zeroed() };
+            mmsg.msg_hdr.msg_name = addr as *mut SockAddrIn as *mut c_void;
+            mmsg.msg_hdr.msg_namelen = mem::size_of::<SockAddrIn>() as Socklen;
+            mmsg.msg_hdr.msg_iov = iovec as *mut libc::iovec;
+            mmsg.msg_hdr.msg_iovlen = 1;
+            mmsgs.push(mmsg);
+        }
+
+        let nreceived: libc::c_int = unsafe {
+            libc::recvmmsg(
+                fd,
+                mmsgs.as_mut_ptr(),
+                MMSG_BATCH_SIZE as libc::c_uint,
+                libc::MSG_DONTWAIT,
+                ptr::null_mut(),
+            )
+        };
+        if nreceived < 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            if errno == libc::EWOULDBLOCK || errno == libc::EAGAIN {
+                return Ok(());
+            }
+            let cause: String = format!("operation failed (fd={:?}, errno={:?})", fd, errno);
+            error!("recvmmsg(): {}", cause);
+            return Err(Fail::new(errno, &cause));
+        }
+
+        // Hand over the buffers that were filled, and keep the others for the next batch.
+        let nreceived: usize = nreceived as usize;
+        let queue: &mut VecDeque<(SocketAddrV4, DemiBuffer)> = self.received.entry(fd).or_default();
+        for (i, mut buf) in self.spare.drain(..nreceived).enumerate() {
+            buf.trim(buf.len() - mmsgs[i].msg_len as usize)?;
+            let saddr: *const SockAddr = &addrs[i] as *const SockAddrIn as *const SockAddr;
+            queue.push_back((linux::sockaddr_to_socketaddrv4(unsafe { &*saddr }), buf));
+        }
+        trace!("recvmmsg(): received {:?} datagrams (fd={:?})", nreceived, fd);
+
+        Ok(())
+    }
+
+    /// Sends the datagrams that are queued on a socket, without blocking. Datagrams that the socket cannot take yet stay
+    /// queued.
+    fn sendmmsg(&mut self, fd: RawFd) {
+        let queue: &mut VecDeque<(u64, SocketAddrV4, DemiBuffer)> = match self.outgoing.get_mut(&fd) {
+            Some(queue) if !queue.is_empty() => queue,
+            _ => return,
+        };
+
+        while !queue.is_empty() {
+            let count: usize = queue.len().min(MMSG_BATCH_SIZE);
+            let mut addrs: Vec<SockAddr> = queue
+                .iter()
+                .take(count)
+                .map(|(_, addr, _)| linux::socketaddrv4_to_sockaddr(addr))
+                .collect();
+            let mut iovecs: Vec<libc::iovec> = queue
+                .iter()
+                .take(count)
+                .map(|(_, _, buf)| libc::iovec {
+                    iov_base: buf.as_ptr() as *mut c_void,
+                    iov_len: buf.len(),
+                })
+                .collect();
+            let mut mmsgs: Vec<libc::mmsghdr> = Vec::with_capacity(count);
+            for (addr, iovec) in addrs.iter_mut().zip(iovecs.iter_mut()) {
+                let mut mmsg: libc::mmsghdr = unsafe { mem::zeroed() };
+                mmsg.msg_hdr.msg_name = addr as *mut SockAddr as *mut c_void;
+                mmsg.msg_hdr.msg_namelen = mem::size_of::<SockAddrIn>() as Socklen;
+                mmsg.msg_hdr.msg_iov = iovec as *mut libc::iovec;
+                mmsg.msg_hdr.msg_iovlen = 1;
+                mmsgs.push(mmsg);
+            }
+
+            let nsent: libc::c_int =
+                unsafe { libc::sendmmsg(fd, mmsgs.as_mut_ptr(), count as libc::c_uint, libc::MSG_DONTWAIT) };
+            if nsent < 0 {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                if errno == libc::EWOULDBLOCK || errno == libc::EAGAIN {
+                    return;
+                }
+                // The error refers to the first datagram in the batch, thus fail it and retry the others.
+                warn!("sendmmsg(): operation failed (fd={:?}, errno={:?})", fd, errno);
+                if let Some((id, _, _)) = queue.pop_front() {
+                    self.sent.insert(id, errno);
+                }
+                continue;
+            }
+
+            for (id, _, _) in queue.drain(..nsent as usize) {
+                self.sent.insert(id, 0);
+            }
+            trace!("sendmmsg(): sent {:?} datagrams (fd={:?})", nsent, fd);
+
+            // The socket did not take all datagrams, thus keep the rest for later.
+            if (nsent as usize) < count {
+                return;
+            }
+        }
+    }
+}
@@ -3,6 +3,8 @@
 
 mod futures;
 mod iouring;
+#[cfg(target_os = "linux")]
+mod mmsg;
 mod queue;
 mod runtime;
 
@@ -27,6 +29,11 @@ use self::futures::{
     push::PushFuture,
     pushto::PushtoFuture,
 };
+#[cfg(target_os = "linux")]
+use self::futures::{
+    recvmmsg::RecvmmsgFuture,
+    sendmmsg::SendmmsgFuture,
+};
 use crate::{
     demikernel::config::Config,
     pal::{
@@ -44,7 +51,9 @@ use crate::{
             DemiBuffer,
             MemoryRuntime,
         },
+        network::consts::UDP_MAX_PAYLOAD_SIZE,
         queue::{
+            IoQueue,
             IoQueueTable,
             Operation,
             OperationResult,
@@ -52,6 +61,7 @@ use crate::{
             QDesc,
             QToken,
             QType,
+            QueueInfo,
         },
         types::{
             demi_accept_result_t,
@@ -59,9 +69,13 @@ use crate::{
             demi_qr_value_t,
             demi_qresult_t,
             demi_sgarray_t,
+            DEMI_QR_EOF,
         },
     },
-    scheduler::TaskHandle,
+    scheduler::{
+        Scheduler,
+        TaskHandle,
+    },
 };
 use ::std::{
     cell::{
@@ -69,10 +83,17 @@ use ::std::{
         RefMut,
     },
     mem,
-    net::SocketAddrV4,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
     os::unix::prelude::RawFd,
     pin::Pin,
     rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 //======================================================================================================================
@@ -94,10 +115,15 @@ pub struct CatcollarLibOS {
 /// Associate Functions for Catcollar LibOS
 impl CatcollarLibOS {
     /// Instantiates a Catcollar LibOS.
-    pub fn new(_config: &Config) -> Self {
+    pub fn new(config: &Config) -> Self {
         let qtable: Rc<RefCell<IoQueueTable<CatcollarQueue>>> =
             Rc::new(RefCell::new(IoQueueTable::<CatcollarQueue>::new()));
-        let runtime: IoUringRuntime = IoUringRuntime::new();
+        qtable.borrow_mut().set_max_queues(config.max_open_queues());
+        let scheduler: Scheduler = match config.scheduler_capacity() {
+            Some(capacity) => Scheduler::with_capacity(capacity),
+            None => Scheduler::default(),
+        };
+        let runtime: IoUringRuntime = IoUringRuntime::new(scheduler);
         Self { qtable, runtime }
     }
 
@@ -145,7 +171,14 @@ impl CatcollarLibOS {
                 trace!("socket: {:?}, domain: {:?}, typ: {:?}", fd, domain, typ);
                 let mut queue: CatcollarQueue = CatcollarQueue::new(qtype);
                 queue.set_fd(fd);
-                Ok(self.qtable.borrow_mut().alloc(queue))
+                match self.qtable.borrow_mut().alloc(queue) {
+                    Ok(qd) => Ok(qd),
+                    Err(e) => {
+                        // Do not leak the socket if we are out of queue descriptors.
+                        unsafe { libc::close(fd) };
+                        Err(e)
+                    },
+                }
             },
             _ => {
                 let errno: libc::c_int = unsafe { *libc::__errno_location() };
@@ -230,9 +263,49 @@ impl CatcollarLibOS {
         }
     }
 
+    /// Duplicates a queue descriptor. This is not supported on this LibOS.
+    pub fn dup(&mut self, qd: QDesc) -> Result<QDesc, Fail> {
+        trace!("dup(): qd={:?}", qd);
+        let cause: String = format!("queue descriptor duplication is not supported (qd={:?})", qd);
+        error!("dup(): {}", cause);
+        Err(Fail::new(libc::ENOTSUP, &cause))
+    }
+
     /// Accepts connections on a socket.
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("accept(): qd={:?}", qd);
+        self.do_accept(qd, None)
+    }
+
+    /// Accepts connections on a socket, failing with `ETIMEDOUT` if no connection request arrives within `timeout`.
+    pub fn accept_timeout(&mut self, qd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("accept_timeout(): qd={:?}, timeout={:?}", qd, timeout);
+        self.do_accept(qd, Some(Instant::now() + timeout))
+    }
+
+    /// Stops accepting connections on a listening socket. The kernel resets connections that were not yet accepted,
+    /// and pending and future accepts fail. The socket remains open until it is closed.
+    pub fn stop_listening(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("stop_listening(): qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => match queue.get_fd() {
+                Some(fd) => {
+                    if unsafe { libc::shutdown(fd, libc::SHUT_RD) } != 0 {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        let cause: String = format!("failed to shut down listening socket (errno={:?})", errno);
+                        error!("stop_listening(): {}", cause);
+                        return Err(Fail::new(errno, &cause));
+                    }
+                    Ok(())
+                },
+                None => unreachable!("CatcollarQueue has invalid underlying file descriptor"),
+            },
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Schedules a coroutine that accepts a connection on a socket, with an optional deadline.
+    fn do_accept(&mut self, qd: QDesc, deadline: Option<Instant>) -> Result<QToken, Fail> {
         let mut qtable: RefMut<IoQueueTable<CatcollarQueue>> = self.qtable.borrow_mut();
 
         let fd: RawFd = match qtable.get(&qd) {
@@ -244,8 +317,8 @@ impl CatcollarLibOS {
         };
 
         // Issue accept operation.
-        let new_qd: QDesc = qtable.alloc(CatcollarQueue::new(QType::TcpSocket));
-        let future: AcceptFuture = AcceptFuture::new(fd);
+        let new_qd: QDesc = qtable.alloc(CatcollarQueue::new(QType::TcpSocket))?;
+        let future: AcceptFuture = AcceptFuture::new(fd, deadline);
         let qtable_ptr: Rc<RefCell<IoQueueTable<CatcollarQueue>>> = self.qtable.clone();
         let coroutine: Pin<Box<Operation>> = Box::pin(async move {
             // Wait for the accept routine to complete.
@@ -311,6 +384,65 @@ impl CatcollarLibOS {
         }
     }
 
+    /// Binds a socket to a local endpoint, unless it is already bound, and establishes a connection to a remote
+    /// endpoint. A wildcard port in `local` selects an ephemeral port.
+    pub fn connect_from(&mut self, qd: QDesc, local: SocketAddrV4, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        trace!("connect_from() qd={:?}, local={:?}, remote={:?}", qd, local, remote);
+
+        let bound: Option<SocketAddrV4> = match self.qtable.borrow().get(&qd) {
+            Some(queue) => queue.get_addr(),
+            None => {
+                let cause: String = format!("invalid queue descriptor {:?}", qd);
+                error!("connect_from(): {}", cause);
+                return Err(Fail::new(libc::EBADF, &cause));
+            },
+        };
+
+        match bound {
+            // Check if the socket is bound to the requested address.
+            Some(addr) => {
+                if (!local.ip().is_unspecified() && local.ip() != addr.ip())
+                    || (local.port() != 0 && local.port() != addr.port())
+                {
+                    let cause: String = format!("socket is bound to a different address (qd={:?})", qd);
+                    error!("connect_from(): {}", cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                }
+            },
+            None if local.port() != 0 => self.bind(qd, local)?,
+            None => self.bind_ephemeral(qd, local)?,
+        }
+
+        self.connect(qd, remote)
+    }
+
+    /// Binds a socket to an ephemeral port on a local address.
+    fn bind_ephemeral(&mut self, qd: QDesc, local: SocketAddrV4) -> Result<(), Fail> {
+        let mut qtable: RefMut<IoQueueTable<CatcollarQueue>> = self.qtable.borrow_mut();
+        let queue: &mut CatcollarQueue = match qtable.get_mut(&qd) {
+            Some(queue) => queue,
+            None => return Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        };
+
+        // Get reference to the underlying file descriptor.
+        // It is safe to unwrap because when creating a queue we assigned it a valid file descritor.
+        let fd: RawFd = queue.get_fd().expect("queue should have a file descriptor");
+
+        // Bind underlying socket and retrieve the port that was selected for it.
+        let mut saddr: SockAddr = linux::socketaddrv4_to_sockaddr(&local);
+        let mut addrlen: Socklen = mem::size_of::<SockAddrIn>() as Socklen;
+        if unsafe { libc::bind(fd, &saddr as *const SockAddr, addrlen) } != 0
+            || unsafe { libc::getsockname(fd, &mut saddr as *mut SockAddr, &mut addrlen) } != 0
+        {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to bind socket (errno={:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+
+        queue.set_addr(linux::sockaddr_to_socketaddrv4(&saddr));
+        Ok(())
+    }
+
     /// Closes a socket.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         trace!("close() qd={:?}", qd);
@@ -318,7 +450,10 @@ impl CatcollarLibOS {
         match qtable.get(&qd) {
             Some(queue) => match queue.get_fd() {
                 Some(fd) => match unsafe { libc::close(fd) } {
-                    stats if stats == 0 => (),
+                    stats if stats == 0 => {
+                        #[cfg(target_os = "linux")]
+                        self.runtime.forget_batched(fd);
+                    },
                     _ => {
                         let errno: libc::c_int = unsafe { *libc::__errno_location() };
                         error!("failed to close socket (fd={:?}, errno={:?})", fd, errno);
@@ -342,6 +477,8 @@ impl CatcollarLibOS {
                 Some(fd) => {
                     let future: CloseFuture = CloseFuture::new(fd);
                     let qtable_ptr: Rc<RefCell<IoQueueTable<CatcollarQueue>>> = self.qtable.clone();
+                    #[cfg(target_os = "linux")]
+                    let runtime: IoUringRuntime = self.runtime.clone();
                     let coroutine: Pin<Box<Operation>> = Box::pin(async move {
                         // Wait for close to complete.
                         let result: Result<(), Fail> = future.await;
@@ -349,6 +486,8 @@ impl CatcollarLibOS {
                         // close was successful.
                         match result {
                             Ok(()) => {
+                                #[cfg(target_os = "linux")]
+                                runtime.forget_batched(fd);
                                 let mut qtable_ = qtable_ptr.borrow_mut();
                                 qtable_.free(&qd);
                                 (qd, OperationResult::Close)
@@ -384,6 +523,7 @@ impl CatcollarLibOS {
         match self.qtable.borrow().get(&qd) {
             Some(queue) => match queue.get_fd() {
                 Some(fd) => {
+                    check_push_size(queue.get_qtype(), buf.len())?;
                     // Issue operation.
                     let future: PushFuture = PushFuture::new(self.runtime.clone(), fd, buf);
                     let coroutine: Pin<Box<Operation>> = Box::pin(async move {
@@ -415,25 +555,41 @@ impl CatcollarLibOS {
 
         match self.runtime.clone_sgarray(sga) {
             Ok(buf) => {
-                if buf.len() == 0 {
-                    return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-                }
-
                 // Issue pushto operation.
                 match self.qtable.borrow().get(&qd) {
                     Some(queue) => match queue.get_fd() {
                         Some(fd) => {
+                            check_push_size(queue.get_qtype(), buf.len())?;
                             // Issue operation.
-                            let future: PushtoFuture = PushtoFuture::new(self.runtime.clone(), fd, remote, buf);
-                            let coroutine: Pin<Box<Operation>> = Box::pin(async move {
-                                // Wait for pushto to complete.
-                                let result: Result<(), Fail> = future.await;
-                                // Handle result.
-                                match result {
-                                    Ok(()) => (qd, OperationResult::Push),
-                                    Err(e) => (qd, OperationResult::Failed(e)),
-                                }
-                            });
+                            let coroutine: Pin<Box<Operation>> = match queue.get_qtype() {
+                                // Datagrams are sent in batches, so that a single system call serves multiple pushes.
+                                #[cfg(target_os = "linux")]
+                                QType::UdpSocket => {
+                                    let future: SendmmsgFuture =
+                                        SendmmsgFuture::new(self.runtime.clone(), fd, remote, buf);
+                                    Box::pin(async move {
+                                        // Wait for pushto to complete.
+                                        let result: Result<(), Fail> = future.await;
+                                        // Handle result.
+                                        match result {
+                                            Ok(()) => (qd, OperationResult::Push),
+                                            Err(e) => (qd, OperationResult::Failed(e)),
+                                        }
+                                    })
+                                },
+                                _ => {
+                                    let future: PushtoFuture = PushtoFuture::new(self.runtime.clone(), fd, remote, buf);
+                                    Box::pin(async move {
+                                        // Wait for pushto to complete.
+                                        let result: Result<(), Fail> = future.await;
+                                        // Handle result.
+                                        match result {
+                                            Ok(()) => (qd, OperationResult::Push),
+                                            Err(e) => (qd, OperationResult::Failed(e)),
+                                        }
+                                    })
+                                },
+                            };
                             let task_id: String = format!("Catcollar::pushto for qd={:?}", qd);
                             let task: OperationTask = OperationTask::new(task_id, coroutine);
                             let handle: TaskHandle = match self.runtime.scheduler.insert(task) {
@@ -454,29 +610,59 @@ impl CatcollarLibOS {
     /// Pops data from a socket.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         trace!("pop() qd={:?}, size={:?}", qd, size);
+        self.do_pop(qd, size, None)
+    }
+
+    /// Pops data from a socket, failing with `ETIMEDOUT` if no data arrives within `timeout`.
+    pub fn pop_timeout(&mut self, qd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("pop_timeout() qd={:?}, size={:?}, timeout={:?}", qd, size, timeout);
+        self.do_pop(qd, size, Some(Instant::now() + timeout))
+    }
 
+    /// Schedules a coroutine that pops data from a socket, with an optional deadline.
+    fn do_pop(&mut self, qd: QDesc, size: Option<usize>, deadline: Option<Instant>) -> Result<QToken, Fail> {
         // We just assert 'size' here, because it was previously checked at PDPIX layer.
         debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
 
-        let buf: DemiBuffer = {
-            let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
-            DemiBuffer::new(size as u16)
-        };
+        let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
 
         // Issue pop operation.
         match self.qtable.borrow().get(&qd) {
             Some(queue) => match queue.get_fd() {
                 Some(fd) => {
-                    let future: PopFuture = PopFuture::new(self.runtime.clone(), fd, buf);
-                    let coroutine: Pin<Box<Operation>> = Box::pin(async move {
-                        // Wait for pop to complete.
-                        let result: Result<(Option<SocketAddrV4>, DemiBuffer), Fail> = future.await;
-                        // Handle the result: if successful, return the addr and buffer.
-                        match result {
-                            Ok((addr, buf)) => (qd, OperationResult::Pop(addr, buf)),
-                            Err(e) => (qd, OperationResult::Failed(e)),
-                        }
-                    });
+                    let coroutine: Pin<Box<Operation>> = match queue.get_qtype() {
+                        // Datagrams are received in batches, so that a single system call serves multiple pops.
+                        #[cfg(target_os = "linux")]
+                        QType::UdpSocket => {
+                            let future: RecvmmsgFuture = RecvmmsgFuture::new(self.runtime.clone(), fd, size, deadline);
+                            Box::pin(async move {
+                                // Wait for pop to complete.
+                                let result: Result<(Option<SocketAddrV4>, DemiBuffer), Fail> = future.await;
+                                // Handle the result: if successful, return the addr and buffer.
+                                match result {
+                                    Ok((addr, buf)) => (qd, OperationResult::Pop(addr, buf, false)),
+                                    Err(e) => (qd, OperationResult::Failed(e)),
+                                }
+                            })
+                        },
+                        _ => {
+                            let buf: DemiBuffer = DemiBuffer::new(size as u16);
+                            let future: PopFuture = PopFuture::new(self.runtime.clone(), fd, buf, deadline);
+                            Box::pin(async move {
+                                // Wait for pop to complete.
+                                let result: Result<(Option<SocketAddrV4>, DemiBuffer), Fail> = future.await;
+                                // Handle the result: if successful, return the addr and buffer.
+                                match result {
+                                    Ok((addr, buf)) => {
+                                        // Only the end of the stream yields an empty buffer.
+                                        let eof: bool = buf.is_empty();
+                                        (qd, OperationResult::Pop(addr, buf, eof))
+                                    },
+                                    Err(e) => (qd, OperationResult::Failed(e)),
+                                }
+                            })
+                        },
+                    };
                     let task_id: String = format!("Catcollar::pop for qd={:?}", qd);
                     let task: OperationTask = OperationTask::new(task_id, coroutine);
                     let handle: TaskHandle = match self.runtime.scheduler.insert(task) {
@@ -492,6 +678,153 @@ impl CatcollarLibOS {
         }
     }
 
+    /// Returns the number of bytes that may be popped right away from a socket. For a UDP socket, this is the size of
+    /// the next datagram. For a listening socket, this is the number of connections that may be accepted right away
+    /// instead.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("bytes_available() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => match queue.get_fd() {
+                Some(fd) => {
+                    let mut nbytes: libc::c_int = 0;
+                    if unsafe { libc::ioctl(fd, libc::FIONREAD, &mut nbytes as *mut libc::c_int) } == 0 {
+                        return Ok(nbytes as usize);
+                    }
+                    let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                    if errno != libc::EINVAL {
+                        let cause: String = format!("failed to query receive queue (errno={:?})", errno);
+                        error!("bytes_available(): {}", cause);
+                        return Err(Fail::new(errno, &cause));
+                    }
+
+                    // The kernel does not report the accept queue of a listening socket through FIONREAD, but through
+                    // the number of unacknowledged segments in TCP_INFO.
+                    let mut info: libc::tcp_info = unsafe { mem::zeroed() };
+                    let mut len: Socklen = mem::size_of::<libc::tcp_info>() as Socklen;
+                    if unsafe {
+                        libc::getsockopt(
+                            fd,
+                            libc::IPPROTO_TCP,
+                            libc::TCP_INFO,
+                            &mut info as *mut libc::tcp_info as *mut libc::c_void,
+                            &mut len,
+                        )
+                    } != 0
+                    {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        let cause: String = format!("failed to query accept queue (errno={:?})", errno);
+                        error!("bytes_available(): {}", cause);
+                        return Err(Fail::new(errno, &cause));
+                    }
+                    Ok(info.tcpi_unacked as usize)
+                },
+                None => unreachable!("CatcollarQueue has invalid underlying file descriptor"),
+            },
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single push transfers as a unit on a socket. Pushes to a UDP socket
+    /// that exceed this size fail with `EMSGSIZE`, whereas pushes to a TCP socket are split by the kernel.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("max_push_size() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => match queue.get_qtype() {
+                QType::UdpSocket => Ok(UDP_MAX_PAYLOAD_SIZE),
+                _ => Ok(limits::RECVBUF_SIZE_MAX),
+            },
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Takes a snapshot of every open queue descriptor.
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        self.qtable.borrow().queues()
+    }
+
+    /// Takes a snapshot of the queue referred to by `qd`.
+    pub fn query_queue(&self, qd: QDesc) -> Result<QueueInfo, Fail> {
+        trace!("query_queue(): qd={:?}", qd);
+        match self.qtable.borrow().query(&qd) {
+            Some(info) => Ok(info),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the local address that a socket is bound to. Sockets that are not bound yet report the unspecified
+    /// address and port zero.
+    pub fn getsockname(&self, qd: QDesc) -> Result<SocketAddrV4, Fail> {
+        trace!("getsockname() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => Ok(queue.get_addr().unwrap_or(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))),
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Makes a UDP socket join a multicast group, receiving datagrams from any source.
+    pub fn join_multicast(&mut self, qd: QDesc, group: Ipv4Addr) -> Result<(), Fail> {
+        trace!("join_multicast() qd={:?} group={:?}", qd, group);
+        self.set_multicast_option(qd, "join_multicast", |fd| unsafe {
+            linux::set_ip_add_membership(fd, &group)
+        })
+    }
+
+    /// Makes a UDP socket receive the datagrams that `src` sends to a multicast group (RFC 4607).
+    pub fn join_source_specific_multicast(&mut self, qd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        trace!(
+            "join_source_specific_multicast() qd={:?} group={:?} src={:?}",
+            qd,
+            group,
+            src
+        );
+        self.set_multicast_option(qd, "join_source_specific_multicast", |fd| unsafe {
+            linux::set_ip_add_source_membership(fd, &group, &src)
+        })
+    }
+
+    /// Stops a UDP socket from receiving the datagrams that `src` sends to a multicast group.
+    pub fn block_source(&mut self, qd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        trace!("block_source() qd={:?} group={:?} src={:?}", qd, group, src);
+        self.set_multicast_option(qd, "block_source", |fd| unsafe {
+            linux::set_ip_block_source(fd, &group, &src)
+        })
+    }
+
+    /// Sets a multicast option on the underlying file descriptor of a UDP socket.
+    fn set_multicast_option<F: FnOnce(RawFd) -> i32>(
+        &self,
+        qd: QDesc,
+        caller: &str,
+        setsockopt: F,
+    ) -> Result<(), Fail> {
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) if queue.get_qtype() == QType::UdpSocket => match queue.get_fd() {
+                Some(fd) => {
+                    if setsockopt(fd) != 0 {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        let cause: String = format!("failed to set multicast option (errno={:?})", errno);
+                        error!("{}(): {}", caller, cause);
+                        return Err(Fail::new(errno, &cause));
+                    }
+                    Ok(())
+                },
+                None => unreachable!("CatcollarQueue has invalid underlying file descriptor"),
+            },
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Forces transmission of all data that is buffered on a socket. Pushed data is handed over to the kernel as soon
+    /// as possible, thus this only checks if `qd` refers to a valid socket.
+    pub fn flush(&self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(_) => Ok(()),
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
     pub fn poll(&self) {
         self.runtime.scheduler.poll()
     }
@@ -535,6 +868,16 @@ impl CatcollarLibOS {
 // Standalone Functions
 //======================================================================================================================
 
+/// Checks if a buffer of `len` bytes may be pushed to a queue of type `qtype`.
+fn check_push_size(qtype: QType, len: usize) -> Result<(), Fail> {
+    if qtype == QType::UdpSocket && len > UDP_MAX_PAYLOAD_SIZE {
+        let cause: String = format!("datagram is too large (len={:?}, max={:?})", len, UDP_MAX_PAYLOAD_SIZE);
+        error!("check_push_size(): {}", cause);
+        return Err(Fail::new(libc::EMSGSIZE, &cause));
+    }
+    Ok(())
+}
+
 /// Packs a [OperationResult] into a [demi_qresult_t].
 fn pack_result(rt: &IoUringRuntime, result: OperationResult, qd: QDesc, qt: u64) -> demi_qresult_t {
     match result {
@@ -544,6 +887,10 @@ fn pack_result(rt: &IoUringRuntime, result: OperationResult, qd: QDesc, qt: u64)
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
         OperationResult::Accept((new_qd, addr)) => {
             let saddr: SockAddr = linux::socketaddrv4_to_sockaddr(&addr);
@@ -559,6 +906,10 @@ fn pack_result(rt: &IoUringRuntime, result: OperationResult, qd: QDesc, qt: u64)
                 qr_qt: qt,
                 qr_ret: 0,
                 qr_value,
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
         OperationResult::Push => demi_qresult_t {
@@ -567,8 +918,12 @@ fn pack_result(rt: &IoUringRuntime, result: OperationResult, qd: QDesc, qt: u64)
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
-        OperationResult::Pop(addr, bytes) => match rt.into_sgarray(bytes) {
+        OperationResult::Pop(addr, bytes, eof) => match rt.into_sgarray(bytes) {
             Ok(mut sga) => {
                 if let Some(addr) = addr {
                     sga.sga_addr = linux::socketaddrv4_to_sockaddr(&addr);
@@ -580,6 +935,10 @@ fn pack_result(rt: &IoUringRuntime, result: OperationResult, qd: QDesc, qt: u64)
                     qr_qt: qt,
                     qr_ret: 0,
                     qr_value,
+                    qr_flags: if eof { DEMI_QR_EOF } else { 0 },
+                    qr_start_ns: 0,
+                    qr_end_ns: 0,
+                    qr_rx_ns: 0,
                 }
             },
             Err(e) => {
@@ -590,15 +949,52 @@ fn pack_result(rt: &IoUringRuntime, result: OperationResult, qd: QDesc, qt: u64)
                     qr_qt: qt,
                     qr_ret: e.errno as i64,
                     qr_value: unsafe { mem::zeroed() },
+                    qr_flags: 0,
+                    qr_start_ns: 0,
+                    qr_end_ns: 0,
+                    qr_rx_ns: 0,
                 }
             },
         },
+        OperationResult::PopBatch(_) => {
+            warn!("Operation Failed: batched pop results cannot be packed into a single result");
+            demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: libc::ENOTSUP as i64,
+                qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
+            }
+        },
+        #[cfg(feature = "dhcp")]
+        OperationResult::Dhcp(_) => {
+            warn!("Operation Failed: DHCP leases cannot be packed into a result");
+            demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: libc::ENOTSUP as i64,
+                qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
+            }
+        },
         OperationResult::Close => demi_qresult_t {
             qr_opcode: demi_opcode_t::DEMI_OPC_CLOSE,
             qr_qd: qd.into(),
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
         OperationResult::Failed(e) => {
             warn!("Operation Failed: {:?}", e);
@@ -608,6 +1004,10 @@ fn pack_result(rt: &IoUringRuntime, result: OperationResult, qd: QDesc, qt: u64)
                 qr_qt: qt,
                 qr_ret: e.errno as i64,
                 qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
     }
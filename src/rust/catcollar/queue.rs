@@ -6,7 +6,10 @@
 //======================================================================================================================
 
 use crate::runtime::{
-    queue::IoQueue,
+    queue::{
+        IoQueue,
+        QueueInfo,
+    },
     QType,
 };
 use ::std::{
@@ -69,4 +72,8 @@ impl IoQueue for CatcollarQueue {
     fn get_qtype(&self) -> QType {
         self.qtype
     }
+
+    fn describe(&self, info: &mut QueueInfo) {
+        info.local = self.addr;
+    }
 }
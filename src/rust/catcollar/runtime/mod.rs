@@ -8,6 +8,8 @@ mod network;
 //==============================================================================
 
 use super::iouring::IoUring;
+#[cfg(target_os = "linux")]
+use super::mmsg::MmsgBatcher;
 use crate::{
     pal::{
         data_structures::SockAddr,
@@ -33,6 +35,7 @@ use ::std::{
     net::SocketAddrV4,
     os::unix::prelude::RawFd,
     rc::Rc,
+    time::Duration,
 };
 
 //==============================================================================
@@ -61,6 +64,9 @@ pub struct IoUringRuntime {
     pending: HashSet<RequestId>,
     /// Completed requests.
     completed: HashMap<RequestId, (Option<SocketAddrV4>, i32)>,
+    /// Batched I/O on UDP sockets.
+    #[cfg(target_os = "linux")]
+    mmsg: Rc<RefCell<MmsgBatcher>>,
 }
 
 //==============================================================================
@@ -70,13 +76,15 @@ pub struct IoUringRuntime {
 /// Associate Functions for I/O User Ring Runtime
 impl IoUringRuntime {
     /// Creates an I/O user ring runtime.
-    pub fn new() -> Self {
+    pub fn new(scheduler: Scheduler) -> Self {
         let io_uring: IoUring = IoUring::new(CATCOLLAR_NUM_RINGS).expect("cannot create io_uring");
         Self {
-            scheduler: Scheduler::default(),
+            scheduler,
             io_uring: Rc::new(RefCell::new(io_uring)),
             pending: HashSet::new(),
             completed: HashMap::new(),
+            #[cfg(target_os = "linux")]
+            mmsg: Rc::new(RefCell::new(MmsgBatcher::default())),
         }
     }
 
@@ -96,14 +104,41 @@ impl IoUringRuntime {
         Ok(request_id)
     }
 
-    /// Pops a buffer from the target I/O user ring.
-    pub fn pop(&mut self, sockfd: RawFd, buf: DemiBuffer) -> Result<RequestId, Fail> {
-        let msg_ptr: *const liburing::msghdr = self.io_uring.borrow_mut().pop(sockfd, buf)?;
+    /// Pops a buffer from the target I/O user ring, optionally bounded by a `timeout`.
+    pub fn pop(&mut self, sockfd: RawFd, buf: DemiBuffer, timeout: Option<Duration>) -> Result<RequestId, Fail> {
+        let msg_ptr: *const liburing::msghdr = self.io_uring.borrow_mut().pop(sockfd, buf, timeout)?;
         let request_id: RequestId = RequestId(msg_ptr);
         self.pending.insert(request_id);
         Ok(request_id)
     }
 
+    /// Pops a datagram from a UDP socket. Datagrams are received in batches with `recvmmsg(2)`, and the ones that are
+    /// not popped right away are kept for later pops. Returns `None` if no datagram is available.
+    #[cfg(target_os = "linux")]
+    pub fn recvmmsg(&self, sockfd: RawFd) -> Result<Option<(SocketAddrV4, DemiBuffer)>, Fail> {
+        self.mmsg.borrow_mut().pop(sockfd)
+    }
+
+    /// Queues a datagram to be sent on a UDP socket with the next `sendmmsg(2)` batch. Returns an identifier to poll
+    /// the outcome with.
+    #[cfg(target_os = "linux")]
+    pub fn sendmmsg(&self, sockfd: RawFd, addr: SocketAddrV4, buf: DemiBuffer) -> u64 {
+        self.mmsg.borrow_mut().pushto(sockfd, addr, buf)
+    }
+
+    /// Polls the outcome of a datagram that was queued with [IoUringRuntime::sendmmsg]. Returns `None` if the datagram
+    /// was not sent yet.
+    #[cfg(target_os = "linux")]
+    pub fn poll_sendmmsg(&self, sockfd: RawFd, id: u64) -> Option<Result<(), Fail>> {
+        self.mmsg.borrow_mut().poll_pushto(sockfd, id)
+    }
+
+    /// Drops the datagrams that are batched on a socket, which is about to be closed.
+    #[cfg(target_os = "linux")]
+    pub fn forget_batched(&self, sockfd: RawFd) {
+        self.mmsg.borrow_mut().forget(sockfd)
+    }
+
     /// Peeks for the completion of an operation in the target I/O user ring.
     pub fn peek(&mut self, request_id: RequestId) -> Result<(Option<SocketAddrV4>, i32), Fail> {
         // Check if pending request has completed.
@@ -114,6 +149,8 @@ impl IoUringRuntime {
             None => {
                 // Peek the underlying io_uring.
                 match self.io_uring.borrow_mut().wait() {
+                    // A linked timeout has completed. The outcome is reported by the operation that it is linked to.
+                    Ok((other_request_id, _)) if other_request_id.is_null() => Ok((None, -libc::EAGAIN)),
                     // Some operation has completed.
                     Ok((other_request_id, size)) => {
                         let msg: Box<liburing::msghdr> = unsafe { Box::from_raw(other_request_id) };
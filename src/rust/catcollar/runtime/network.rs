@@ -11,23 +11,28 @@ use crate::runtime::{
     network::{
         NetworkRuntime,
         PacketBuf,
+        ReceiveBatch,
     },
 };
-use ::arrayvec::ArrayVec;
 
 //==============================================================================
 // Trait Implementations
 //==============================================================================
 
 /// Network Runtime Trait Implementation for I/O User Ring Runtime
-impl<const N: usize> NetworkRuntime<N> for IoUringRuntime {
+impl NetworkRuntime for IoUringRuntime {
     // TODO: Rely on a default implementation for this.
     fn transmit(&self, _pkt: Box<dyn PacketBuf>) {
         unreachable!()
     }
 
     // TODO: Rely on a default implementation for this.
-    fn receive(&self) -> ArrayVec<DemiBuffer, N> {
+    fn receive(&self) -> ReceiveBatch {
+        unreachable!()
+    }
+
+    // TODO: Rely on a default implementation for this.
+    fn next_ipv4_id(&self) -> u16 {
         unreachable!()
     }
 }
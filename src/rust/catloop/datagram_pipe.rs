@@ -0,0 +1,86 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    catmem::CatmemLibOS,
+    runtime::{
+        fail::Fail,
+        QDesc,
+    },
+};
+use ::std::{
+    cell::RefCell,
+    collections::HashMap,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    rc::Rc,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// A set of pipes for exchanging datagrams.
+///
+/// Each datagram socket receives on a single simplex pipe that is named after its local address, and it transmits on
+/// the receiving pipes of its destinations, which are opened on first use. Datagrams travel as records of the
+/// underlying pipes, thus their boundaries are preserved. Much like the control pipe of listening sockets, a receiving
+/// pipe is not meant to be written by multiple processes at once.
+pub struct DatagramPipe {
+    /// Underlying Catmem LibOS.
+    catmem: Rc<RefCell<CatmemLibOS>>,
+    /// Simplex pipe used for receiving data.
+    rx: QDesc,
+    /// Simplex pipes used for transmitting data, indexed by destination.
+    tx: RefCell<HashMap<SocketAddrV4, QDesc>>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl DatagramPipe {
+    /// Returns the queue descriptor that is associated with the underlying receiving simplex pipe.
+    pub fn rx(&self) -> QDesc {
+        self.rx
+    }
+
+    /// Returns the queue descriptor that is associated with the transmitting simplex pipe to `remote`, opening it if
+    /// needed.
+    pub fn tx(&self, remote: SocketAddrV4) -> Result<QDesc, Fail> {
+        if let Some(qd) = self.tx.borrow().get(&remote) {
+            return Ok(*qd);
+        }
+        let qd: QDesc = self
+            .catmem
+            .borrow_mut()
+            .open_pipe(&format!("{}:{}:dgram", remote.ip(), remote.port()))?;
+        self.tx.borrow_mut().insert(remote, qd);
+        Ok(qd)
+    }
+
+    /// Creates a datagram pipe.
+    pub fn create_datagram_pipe(catmem: Rc<RefCell<CatmemLibOS>>, ipv4: &Ipv4Addr, port: u16) -> Result<Self, Fail> {
+        let rx: QDesc = catmem.borrow_mut().create_pipe(&format!("{}:{}:dgram", ipv4, port))?;
+        Ok(Self {
+            catmem,
+            rx,
+            tx: RefCell::new(HashMap::default()),
+        })
+    }
+
+    /// Closes a datagram pipe. EoF is not pushed to destinations, because they may still receive from other sockets.
+    pub fn close(&self) -> Result<(), Fail> {
+        self.catmem.borrow_mut().shutdown(self.rx)?;
+        for (_, qd) in self.tx.borrow_mut().drain() {
+            self.catmem.borrow_mut().shutdown(qd)?;
+        }
+        Ok(())
+    }
+}
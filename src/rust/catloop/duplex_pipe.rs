@@ -4,7 +4,13 @@
 use crate::{
     catmem::CatmemLibOS,
     demi_sgarray_t,
-    runtime::fail::Fail,
+    runtime::{
+        fail::Fail,
+        types::{
+            demi_opcode_t,
+            demi_qresult_t,
+        },
+    },
     scheduler::TaskHandle,
     QDesc,
     QToken,
@@ -59,7 +65,15 @@ impl DuplexPipe {
     pub fn open_duplex_pipe(catmem: Rc<RefCell<CatmemLibOS>>, ipv4: &Ipv4Addr, port: u16) -> Result<Self, Fail> {
         // Note: the rx and tx are intentionally flipped in the formatting string below.
         let rx: QDesc = catmem.borrow_mut().open_pipe(&format!("{}:{}:tx", ipv4, port))?;
-        let tx: QDesc = catmem.borrow_mut().open_pipe(&format!("{}:{}:rx", ipv4, port))?;
+        let tx: Result<QDesc, Fail> = catmem.borrow_mut().open_pipe(&format!("{}:{}:rx", ipv4, port));
+        let tx: QDesc = match tx {
+            Ok(tx) => tx,
+            Err(e) => {
+                // Release the receiving pipe, so that the caller may retry.
+                catmem.borrow_mut().shutdown(rx)?;
+                return Err(e);
+            },
+        };
         Ok(Self { catmem, rx, tx })
     }
 
@@ -98,4 +112,26 @@ impl DuplexPipe {
 
         Ok(Some(handle))
     }
+
+    /// Drops the outcome of an operation on a duplex pipe that was cancelled, so that the task that ran it does not
+    /// linger in the scheduler. Operations that are still pending are left alone.
+    pub fn discard(catmem: &Rc<RefCell<CatmemLibOS>>, qt: QToken) {
+        catmem.borrow().poll();
+        if let Ok(Some(handle)) = Self::poll(catmem, qt) {
+            let qr: Result<demi_qresult_t, Fail> = catmem.borrow_mut().pack_result(handle, qt);
+            match qr {
+                // Data may have come in before the operation was cancelled.
+                Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP => {
+                    if let Err(e) = catmem.borrow().free_sgarray(unsafe { qr.qr_value.sga }) {
+                        warn!(
+                            "discard(): failed to release scatter-gather array (qt={:?}): {:?}",
+                            qt, e
+                        );
+                    }
+                },
+                Ok(_) => {},
+                Err(e) => warn!("discard(): failed to retrieve result (qt={:?}): {:?}", qt, e),
+            }
+        }
+    }
 }
@@ -11,9 +11,13 @@ use crate::{
         DuplexPipe,
     },
     catmem::CatmemLibOS,
-    demi_sgarray_t,
     runtime::{
         fail::Fail,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+            SgaGuard,
+        },
         types::{
             demi_opcode_t,
             demi_qresult_t,
@@ -23,7 +27,10 @@ use crate::{
     QToken,
 };
 use ::std::{
-    cell::RefCell,
+    cell::{
+        Cell,
+        RefCell,
+    },
     future::Future,
     mem,
     net::{
@@ -32,7 +39,6 @@ use ::std::{
     },
     pin::Pin,
     rc::Rc,
-    slice,
     task::{
         Context,
         Poll,
@@ -56,6 +62,7 @@ enum ServerState {
         remote: SocketAddrV4,
         duplex_pipe: Rc<DuplexPipe>,
     },
+    Accepted,
 }
 
 //======================================================================================================================
@@ -73,6 +80,8 @@ pub struct AcceptFuture {
     new_port: u16,
     // State in the connection establishment protocol.
     state: ServerState,
+    // Whether the listening socket was closed.
+    cancelled: Rc<Cell<bool>>,
 }
 
 //======================================================================================================================
@@ -86,6 +95,7 @@ impl AcceptFuture {
         catmem: Rc<RefCell<CatmemLibOS>>,
         control_duplex_pipe: Rc<DuplexPipe>,
         new_port: u16,
+        cancelled: Rc<Cell<bool>>,
     ) -> Result<Self, Fail> {
         // Issue first pop. Note that we intentionally issue an unbound
         // pop() because the connection establishment protocol requires that
@@ -97,6 +107,7 @@ impl AcceptFuture {
             control_duplex_pipe,
             new_port,
             state: ServerState::ListenAndAccept { qt_rx },
+            cancelled,
         })
     }
 }
@@ -115,6 +126,13 @@ impl Future for AcceptFuture {
         // Poll Catmem to make progress on ongoing operations.
         self_.catmem.borrow_mut().poll();
 
+        // Give up if the listening socket was closed in the meantime.
+        if self_.cancelled.get() {
+            let cause: String = format!("listening socket was closed (port={:?})", self_.new_port);
+            warn!("poll(): {}", cause);
+            return Poll::Ready(Err(Fail::new(libc::ECANCELED, &cause)));
+        }
+
         // Act according to the state in the connection establishment protocol.
         match &self_.state {
             ServerState::ListenAndAccept { qt_rx } => listen_and_accept(self_, ctx, *qt_rx),
@@ -128,7 +146,9 @@ impl Future for AcceptFuture {
                     match check_connect_request(&self_.catmem, handle, *qt_close) {
                         Ok(_) => {
                             debug!("connection accepted!");
-                            return Poll::Ready(Ok((*remote, duplex_pipe.clone())));
+                            let result: (SocketAddrV4, Rc<DuplexPipe>) = (*remote, duplex_pipe.clone());
+                            self_.state = ServerState::Accepted;
+                            return Poll::Ready(Ok(result));
                         },
                         Err(e) => return Poll::Ready(Err(e)),
                     }
@@ -136,6 +156,36 @@ impl Future for AcceptFuture {
                 ctx.waker().wake_by_ref();
                 return Poll::Pending;
             },
+            ServerState::Accepted => unreachable!("accept operation has completed already"),
+        }
+    }
+}
+
+/// Drop Trait Implementation for Accept Operation Descriptors
+impl Drop for AcceptFuture {
+    /// Releases the duplex pipe of a connection that was not accepted. If the listening socket was closed, the
+    /// operations that got cancelled are dropped as well.
+    fn drop(&mut self) {
+        let (qt, duplex_pipe): (QToken, Option<Rc<DuplexPipe>>) =
+            match mem::replace(&mut self.state, ServerState::Accepted) {
+                ServerState::ListenAndAccept { qt_rx } => (qt_rx, None),
+                ServerState::Connect { qt_tx, duplex_pipe } => (qt_tx, Some(duplex_pipe)),
+                ServerState::Connected {
+                    qt_close, duplex_pipe, ..
+                } => (qt_close, Some(duplex_pipe)),
+                ServerState::Accepted => return,
+            };
+        if let Some(duplex_pipe) = duplex_pipe {
+            if let Err(e) = duplex_pipe.shutdown() {
+                warn!(
+                    "drop(): failed to release duplex pipe (port={:?}): {:?}",
+                    self.new_port, e
+                );
+            }
+        }
+        // Operations that failed on their own were already consumed.
+        if self.cancelled.get() {
+            DuplexPipe::discard(&self.catmem, qt);
         }
     }
 }
@@ -172,11 +222,10 @@ fn check_connect_request(catmem: &Rc<RefCell<CatmemLibOS>>, handle: TaskHandle,
     }
 
     // Extract scatter-gather array from operation result.
-    let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+    let sga: SgaGuard = SgaGuard::new(unsafe { qr.qr_value.sga });
 
     // Parse and check request.
-    let passed: bool = CatloopLibOS::is_magic_connect(&sga);
-    catmem.borrow_mut().free_sgarray(sga)?;
+    let passed: bool = CatloopLibOS::is_magic_connect(catmem, &sga)?;
     if !passed {
         warn!("failed to establish connection (invalid request)");
     }
@@ -190,14 +239,9 @@ fn send_port_number(
     control_duplex_pipe: Rc<DuplexPipe>,
     port: u16,
 ) -> Result<QToken, Fail> {
-    let sga: demi_sgarray_t = catmem.borrow_mut().alloc_sgarray(mem::size_of_val(&port))?;
-    let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
-    let len: usize = sga.sga_segs[0].sgaseg_len as usize;
-    let slice: &mut [u8] = unsafe { slice::from_raw_parts_mut(ptr, len) };
-    slice.copy_from_slice(&port.to_ne_bytes());
-    let qt_tx: QToken = control_duplex_pipe.push(&sga)?;
-    catmem.borrow_mut().free_sgarray(sga)?;
-    Ok(qt_tx)
+    let buf: DemiBuffer = DemiBuffer::from_slice(&port.to_ne_bytes())?;
+    let sga: SgaGuard = SgaGuard::new(catmem.borrow().into_sgarray(buf)?);
+    control_duplex_pipe.push(&sga)
 }
 
 /// Waits for a connection request to arrive.
@@ -223,7 +267,16 @@ fn listen_and_accept(
                 )?);
 
                 // Send port number.
-                let qt_tx: QToken = send_port_number(&self_.catmem, self_.control_duplex_pipe.clone(), self_.new_port)?;
+                let qt_tx: QToken =
+                    match send_port_number(&self_.catmem, self_.control_duplex_pipe.clone(), self_.new_port) {
+                        Ok(qt_tx) => qt_tx,
+                        Err(e) => {
+                            if let Err(e) = duplex_pipe.shutdown() {
+                                warn!("listen_and_accept(): failed to release duplex pipe: {:?}", e);
+                            }
+                            return Poll::Ready(Err(e));
+                        },
+                    };
 
                 // Advance to next state in the connection establishment protocol.
                 self_.state = ServerState::Connect {
@@ -14,6 +14,11 @@ use crate::{
     demi_sgarray_t,
     runtime::{
         fail::Fail,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+            SgaGuard,
+        },
         types::{
             demi_opcode_t,
             demi_qresult_t,
@@ -22,7 +27,10 @@ use crate::{
     QToken,
 };
 use ::std::{
-    cell::RefCell,
+    cell::{
+        Cell,
+        RefCell,
+    },
     future::Future,
     mem,
     net::{
@@ -31,11 +39,14 @@ use ::std::{
     },
     pin::Pin,
     rc::Rc,
-    slice,
     task::{
         Context,
         Poll,
     },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 //======================================================================================================================
@@ -46,6 +57,15 @@ use ::std::{
 /// This was chosen arbitrarily.
 const MAX_ACK_RECEIVED_ATTEMPTS: usize = 1024;
 
+/// Maximum number of attempts to open the duplex pipe of a connection, while the remote may still be creating it.
+const MAX_OPEN_ATTEMPTS: u32 = 8;
+
+/// Delay before the second attempt to open the duplex pipe of a connection. The delay doubles on every attempt.
+const OPEN_RETRY_BACKOFF: Duration = Duration::from_millis(1);
+
+/// Size of the port number in a connect request ack message.
+const PORT_NUMBER_SIZE: usize = mem::size_of::<u16>();
+
 //======================================================================================================================
 // Enumerations
 //======================================================================================================================
@@ -63,11 +83,17 @@ enum ClientState {
         attempt: usize,
         qt_rx: QToken,
     },
+    OpenDuplexPipe {
+        port: u16,
+        attempt: u32,
+        retry_at: Instant,
+    },
     Connected {
         qt_tx: QToken,
         remote: SocketAddrV4,
         duplex_pipe: Rc<DuplexPipe>,
     },
+    Done,
 }
 
 //======================================================================================================================
@@ -80,10 +106,16 @@ pub struct ConnectFuture {
     catmem: Rc<RefCell<CatmemLibOS>>,
     /// Remote IPv4 address.
     ipv4: Ipv4Addr,
-    /// Control duplex pipe used for establishing a the connection.
-    control_duplex_pipe: Rc<DuplexPipe>,
+    /// Port number of the control duplex pipe.
+    control_port: u16,
+    /// Control duplex pipe used for establishing a the connection, until the remote tells which port to connect to.
+    control_duplex_pipe: Option<Rc<DuplexPipe>>,
     // State in the connection establishment protocol.
     state: ClientState,
+    // Bytes of the connect request ack that were received so far, since the ack may come in over several pops.
+    ack: Vec<u8>,
+    // Whether the connecting socket was closed.
+    cancelled: Rc<Cell<bool>>,
 }
 
 //======================================================================================================================
@@ -93,7 +125,11 @@ pub struct ConnectFuture {
 /// Associate Functions for Connect Operation Descriptors
 impl ConnectFuture {
     /// Creates a descriptor for a push operation.
-    pub fn new(catmem: Rc<RefCell<CatmemLibOS>>, remote: SocketAddrV4) -> Result<Self, Fail> {
+    pub fn new(
+        catmem: Rc<RefCell<CatmemLibOS>>,
+        remote: SocketAddrV4,
+        cancelled: Rc<Cell<bool>>,
+    ) -> Result<Self, Fail> {
         let ipv4: &Ipv4Addr = remote.ip();
         let port: u16 = remote.port().into();
         let control_duplex_pipe: Rc<DuplexPipe> = Rc::new(DuplexPipe::open_duplex_pipe(catmem.clone(), ipv4, port)?);
@@ -101,10 +137,28 @@ impl ConnectFuture {
         Ok(ConnectFuture {
             catmem,
             ipv4: ipv4.clone(),
-            control_duplex_pipe,
+            control_port: port,
+            control_duplex_pipe: Some(control_duplex_pipe),
             state: ClientState::InitiateConnectRequest { qt_rx: None },
+            ack: Vec::with_capacity(PORT_NUMBER_SIZE),
+            cancelled,
         })
     }
+
+    /// Returns the control duplex pipe, which is held until the remote tells which port to connect to.
+    fn control_duplex_pipe(&self) -> &DuplexPipe {
+        self.control_duplex_pipe
+            .as_ref()
+            .expect("control duplex pipe should be held during connection request")
+    }
+
+    /// Releases the control duplex pipe.
+    fn release_control_duplex_pipe(&mut self) -> Result<(), Fail> {
+        match self.control_duplex_pipe.take() {
+            Some(control_duplex_pipe) => control_duplex_pipe.shutdown(),
+            None => Ok(()),
+        }
+    }
 }
 
 //======================================================================================================================
@@ -122,18 +176,31 @@ impl Future for ConnectFuture {
         // Poll Catmem LibOS to make progress on ongoing operations.
         self_.catmem.borrow_mut().poll();
 
+        // Give up if the connecting socket was closed in the meantime.
+        if self_.cancelled.get() {
+            let cause: String = format!("connecting socket was closed (remote={:?})", self_.ipv4);
+            warn!("poll(): {}", cause);
+            return Poll::Ready(Err(Fail::new(libc::ECANCELED, &cause)));
+        }
+
         // Act according to the state in the connection establishment protocol.
         //
         //  CLIENT                                                       SERVER
         //    InitiateConnectRequest   --- ( msg: connect-request ) --->   ListenAndAccept
         //    ConnectRequestSent       <----- ( ack: port-number ) -----   Connect
         //    ConnectAckReceived                                           Connected
+        //    OpenDuplexPipe
         //    Connected
         //
         match &self_.state {
             ClientState::InitiateConnectRequest { qt_rx } => setup(self_, ctx, *qt_rx),
             ClientState::ConnectRequestSent { qt_tx, qt_rx } => connect_request_sent(self_, ctx, *qt_tx, *qt_rx),
             ClientState::ConnectAckReceived { qt_rx, attempt } => connect_ack_received(self_, ctx, *qt_rx, *attempt),
+            ClientState::OpenDuplexPipe {
+                port,
+                attempt,
+                retry_at,
+            } => open_duplex_pipe(self_, ctx, *port, *attempt, *retry_at),
             ClientState::Connected {
                 qt_tx,
                 remote,
@@ -161,13 +228,49 @@ impl Future for ConnectFuture {
                             unreachable!("unexpected operation on control duplex pipe")
                         },
                     }
-                    return Poll::Ready(Ok((*remote, duplex_pipe.clone())));
+                    let result: (SocketAddrV4, Rc<DuplexPipe>) = (*remote, duplex_pipe.clone());
+                    self_.state = ClientState::Done;
+                    return Poll::Ready(Ok(result));
                 }
 
                 // Re-schedule co-routine for later execution.
                 ctx.waker().wake_by_ref();
                 return Poll::Pending;
             },
+            ClientState::Done => unreachable!("connect operation has completed already"),
+        }
+    }
+}
+
+/// Drop Trait Implementation for Connect Operation Descriptors
+impl Drop for ConnectFuture {
+    /// Releases the duplex pipes of a connection that was not established. If the connecting socket was closed, the
+    /// operations that got cancelled are dropped as well.
+    fn drop(&mut self) {
+        let (qts, duplex_pipe): (Vec<QToken>, Option<Rc<DuplexPipe>>) =
+            match mem::replace(&mut self.state, ClientState::Done) {
+                ClientState::InitiateConnectRequest { qt_rx } => (qt_rx.into_iter().collect(), None),
+                ClientState::ConnectRequestSent { qt_tx, qt_rx } => {
+                    (Some(qt_tx).into_iter().chain(qt_rx).collect(), None)
+                },
+                ClientState::ConnectAckReceived { qt_rx, .. } => (vec![qt_rx], None),
+                ClientState::OpenDuplexPipe { .. } => (vec![], None),
+                ClientState::Connected { qt_tx, duplex_pipe, .. } => (vec![qt_tx], Some(duplex_pipe)),
+                ClientState::Done => return,
+            };
+        if let Err(e) = self.release_control_duplex_pipe() {
+            warn!("drop(): failed to release control duplex pipe: {:?}", e);
+        }
+        if let Some(duplex_pipe) = duplex_pipe {
+            if let Err(e) = duplex_pipe.shutdown() {
+                warn!("drop(): failed to release duplex pipe: {:?}", e);
+            }
+        }
+        // Operations that failed on their own were already consumed.
+        if self.cancelled.get() {
+            for qt in qts {
+                DuplexPipe::discard(&self.catmem, qt);
+            }
         }
     }
 }
@@ -183,9 +286,8 @@ fn setup(
     qt_rx: Option<QToken>,
 ) -> Poll<Result<(SocketAddrV4, Rc<DuplexPipe>), Fail>> {
     // Send connection request.
-    let sga: demi_sgarray_t = CatloopLibOS::cook_magic_connect(&self_.catmem)?;
-    let qt_tx: QToken = self_.control_duplex_pipe.push(&sga)?;
-    self_.catmem.borrow_mut().free_sgarray(sga)?;
+    let sga: SgaGuard = SgaGuard::new(CatloopLibOS::cook_magic_connect(&self_.catmem)?);
+    let qt_tx: QToken = self_.control_duplex_pipe().push(&sga)?;
 
     // Transition to the next state in the connection establishment protocol.
     self_.state = ClientState::ConnectRequestSent { qt_tx, qt_rx };
@@ -227,12 +329,11 @@ fn connect_request_sent(
         }
 
         // Issue receive operation to wait for connect request ack.
-        let size: usize = mem::size_of::<u16>();
         let qt_rx: QToken = if let Some(qt_rx) = qt_rx {
             qt_rx
         } else {
             warn!("connect_request_sent(): qt_rx is None, allocating new qtoken");
-            self_.control_duplex_pipe.pop(Some(size))?
+            self_.control_duplex_pipe().pop(Some(PORT_NUMBER_SIZE))?
         };
 
         // Transition to the next state in the connection establishment protocol.
@@ -276,33 +377,40 @@ fn connect_ack_received(
         }
 
         // Extract scatter-gather array from operation result.
-        let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        let sga: SgaGuard = SgaGuard::new(unsafe { qr.qr_value.sga });
 
         // Extract port number.
-        let port: u16 = {
-            let port: Result<u16, Fail> = extract_port_number(&sga);
-            self_.catmem.borrow_mut().free_sgarray(sga)?;
-            self_.control_duplex_pipe.shutdown()?;
+        let port: Option<u16> = {
+            let port: Result<Option<u16>, Fail> =
+                extract_port_number(&self_.catmem, &sga, &mut self_.ack, self_.control_port);
+            drop(sga);
+            if port.is_err() {
+                self_.release_control_duplex_pipe()?;
+            }
             port?
         };
 
-        // Open underlying pipes.
-        let remote: SocketAddrV4 = SocketAddrV4::new(self_.ipv4, port);
-        let duplex_pipe: Rc<DuplexPipe> =
-            Rc::new(DuplexPipe::open_duplex_pipe(self_.catmem.clone(), &self_.ipv4, port)?);
-
-        let sga: demi_sgarray_t = CatloopLibOS::cook_magic_connect(&self_.catmem)?;
-        let qt_tx: QToken = duplex_pipe.push(&sga)?;
-        self_.catmem.borrow_mut().free_sgarray(sga)?;
+        match port {
+            Some(port) => {
+                self_.release_control_duplex_pipe()?;
 
-        // Transition to the next state in the connection establishment protocol.
-        self_.state = ClientState::Connected {
-            qt_tx,
-            remote,
-            duplex_pipe,
-        };
+                // Transition to the next state in the connection establishment protocol.
+                self_.state = ClientState::OpenDuplexPipe {
+                    port,
+                    attempt: 0,
+                    retry_at: Instant::now(),
+                };
+            },
+            // Only part of the ack came in, thus wait for the rest of it.
+            None => {
+                let size: usize = PORT_NUMBER_SIZE - self_.ack.len();
+                let qt_rx: QToken = self_.control_duplex_pipe().pop(Some(size))?;
+                self_.state = ClientState::ConnectAckReceived { qt_rx, attempt: 0 };
+            },
+        }
     } else {
-        if attempt > MAX_ACK_RECEIVED_ATTEMPTS {
+        // Do not send the connect request again once part of the ack came in, otherwise the remote acks it twice.
+        if attempt > MAX_ACK_RECEIVED_ATTEMPTS && self_.ack.is_empty() {
             // Connection timeout, retry.
             debug!(
                 "connect_ack_received(): connection timeout, retrying (qt_rx={:?})",
@@ -322,16 +430,96 @@ fn connect_ack_received(
     return Poll::Pending;
 }
 
-/// Extracts port number from connect request ack message.
-fn extract_port_number(sga: &demi_sgarray_t) -> Result<u16, Fail> {
-    let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
-    let len: usize = sga.sga_segs[0].sgaseg_len as usize;
-    if len != 2 {
-        let e: Fail = Fail::new(libc::EAGAIN, "hashsake failed");
-        error!("failed to establish connection ({:?})", e);
-        return Err(e);
+/// Runs the "Open Duplex Pipe" state in the connection establishment protocol.
+fn open_duplex_pipe(
+    self_: &mut ConnectFuture,
+    ctx: &mut Context<'_>,
+    port: u16,
+    attempt: u32,
+    retry_at: Instant,
+) -> Poll<Result<(SocketAddrV4, Rc<DuplexPipe>), Fail>> {
+    if Instant::now() >= retry_at {
+        // Open underlying pipes.
+        match DuplexPipe::open_duplex_pipe(self_.catmem.clone(), &self_.ipv4, port) {
+            Ok(duplex_pipe) => {
+                let remote: SocketAddrV4 = SocketAddrV4::new(self_.ipv4, port);
+                let duplex_pipe: Rc<DuplexPipe> = Rc::new(duplex_pipe);
+                let qt_tx: Result<QToken, Fail> = CatloopLibOS::cook_magic_connect(&self_.catmem)
+                    .and_then(|sga| duplex_pipe.push(&SgaGuard::new(sga)));
+                let qt_tx: QToken = match qt_tx {
+                    Ok(qt_tx) => qt_tx,
+                    Err(e) => {
+                        if let Err(e) = duplex_pipe.shutdown() {
+                            warn!("open_duplex_pipe(): failed to release duplex pipe: {:?}", e);
+                        }
+                        return Poll::Ready(Err(e));
+                    },
+                };
+
+                // Transition to the next state in the connection establishment protocol.
+                self_.state = ClientState::Connected {
+                    qt_tx,
+                    remote,
+                    duplex_pipe,
+                };
+            },
+            // The remote may not have created the pipes yet, thus back off and retry.
+            Err(e) if e.errno == libc::ENOENT && attempt + 1 < MAX_OPEN_ATTEMPTS => {
+                let backoff: Duration = OPEN_RETRY_BACKOFF * 2u32.pow(attempt);
+                debug!(
+                    "open_duplex_pipe(): duplex pipe not found, retrying (port={:?}, backoff={:?})",
+                    port, backoff
+                );
+                self_.state = ClientState::OpenDuplexPipe {
+                    port,
+                    attempt: attempt + 1,
+                    retry_at: Instant::now() + backoff,
+                };
+            },
+            Err(e) => {
+                error!("open_duplex_pipe(): failed to establish connection ({:?})", e);
+                return Poll::Ready(Err(e));
+            },
+        }
+    }
+
+    // Re-schedule co-routine for later execution.
+    ctx.waker().wake_by_ref();
+    return Poll::Pending;
+}
+
+/// Extracts port number from connect request ack message. The bytes in `sga` are appended to those in `ack`, which
+/// were received earlier on. If the message is not complete yet, `None` is returned.
+fn extract_port_number(
+    catmem: &Rc<RefCell<CatmemLibOS>>,
+    sga: &demi_sgarray_t,
+    ack: &mut Vec<u8>,
+    control_port: u16,
+) -> Result<Option<u16>, Fail> {
+    let buf: DemiBuffer = catmem.borrow().clone_sgarray(sga)?;
+    // An empty pop means that the remote closed the control duplex pipe.
+    if buf.is_empty() {
+        let cause: String = format!("connection closed by remote before ack (port={:?})", control_port);
+        error!("extract_port_number(): {}", cause);
+        return Err(Fail::new(libc::ECONNREFUSED, &cause));
+    }
+    ack.extend_from_slice(buf.as_bytes());
+    if ack.len() < PORT_NUMBER_SIZE {
+        return Ok(None);
+    }
+
+    let port: u16 = match <[u8; PORT_NUMBER_SIZE]>::try_from(ack.as_slice()) {
+        Ok(array) => u16::from_ne_bytes(array),
+        Err(_) => {
+            let cause: String = format!("malformed connect request ack (len={:?})", ack.len());
+            error!("extract_port_number(): {}", cause);
+            return Err(Fail::new(libc::EBADMSG, &cause));
+        },
+    };
+    if port == 0 || port == control_port {
+        let cause: String = format!("invalid port number in connect request ack (port={:?})", port);
+        error!("extract_port_number(): {}", cause);
+        return Err(Fail::new(libc::EBADMSG, &cause));
     }
-    let slice: &mut [u8] = unsafe { slice::from_raw_parts_mut(ptr, len) };
-    let array: [u8; 2] = [slice[0], slice[1]];
-    Ok(u16::from_ne_bytes(array))
+    Ok(Some(port))
 }
@@ -5,6 +5,7 @@
 // Exports
 //======================================================================================================================
 
+mod datagram_pipe;
 mod duplex_pipe;
 mod futures;
 mod queue;
@@ -14,6 +15,7 @@ mod queue;
 //======================================================================================================================
 
 use self::{
+    datagram_pipe::DatagramPipe,
     duplex_pipe::DuplexPipe,
     futures::OperationResult,
     queue::CatloopQueue,
@@ -32,12 +34,25 @@ use crate::{
     runtime::{
         fail::Fail,
         limits,
-        queue::IoQueueTable,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+            SgaGuard,
+        },
+        queue::{
+            ConnectionInfo,
+            ConnectionState,
+            IoQueue,
+            IoQueueTable,
+            QueueInfo,
+            QueueState,
+        },
         types::{
             demi_accept_result_t,
             demi_opcode_t,
             demi_qr_value_t,
             demi_qresult_t,
+            DEMI_QR_EOF,
         },
         QDesc,
         QToken,
@@ -51,6 +66,8 @@ use crate::{
 };
 use ::std::{
     cell::{
+        Cell,
+        Ref,
         RefCell,
         RefMut,
     },
@@ -63,7 +80,7 @@ use ::std::{
     },
     pin::Pin,
     rc::Rc,
-    slice,
+    time::Duration,
 };
 
 //======================================================================================================================
@@ -86,7 +103,7 @@ pub enum Socket {
 
 /// A LibOS that exposes exposes sockets semantics on a memory queue.
 pub struct CatloopLibOS {
-    /// Next ephemeral port available. TODO: we want to change this to the ephemeral port allocator.
+    /// Next port number to hand out, both for sockets bound to port zero and for pipes of accepted connections.
     next_port: u16,
     /// Table of queue descriptors. This table has one entry for each existing queue descriptor in Catloop LibOS.
     qtable: Rc<RefCell<IoQueueTable<CatloopQueue>>>,
@@ -98,6 +115,8 @@ pub struct CatloopLibOS {
     catmem_qts: HashMap<QToken, (demi_opcode_t, QDesc)>,
     /// Underlying reference to Catmem LibOS.
     catmem: Rc<RefCell<CatmemLibOS>>,
+    /// Connections that were accepted and are still open.
+    connections: Rc<RefCell<HashMap<QDesc, ConnectionInfo>>>,
 }
 
 //======================================================================================================================
@@ -105,6 +124,9 @@ pub struct CatloopLibOS {
 //======================================================================================================================
 
 impl CatloopLibOS {
+    /// Size of the header that precedes the payload of datagrams on a memory queue. It carries the IPv4 address and
+    /// port number of the sender, so that the receiver can tell where each datagram came from.
+    const DATAGRAM_HEADER_SIZE: usize = 6;
     /// Magic payload used to identify connect requests.  It must be a single
     /// byte to ensure atomicity while keeping the connection establishment
     /// protocol. The rationale for this lies on the fact that a pipe in Catmem
@@ -126,6 +148,7 @@ impl CatloopLibOS {
             catmem_qts: HashMap::default(),
             catloop_qts: HashMap::default(),
             catmem: Rc::new(RefCell::new(CatmemLibOS::new())),
+            connections: Rc::new(RefCell::new(HashMap::default())),
         }
     }
 
@@ -152,23 +175,22 @@ impl CatloopLibOS {
         };
 
         // Create fake socket.
-        let qd: QDesc = self.qtable.borrow_mut().alloc(CatloopQueue::new(qtype));
+        let qd: QDesc = self.qtable.borrow_mut().alloc(CatloopQueue::new(qtype))?;
         Ok(qd)
     }
 
     /// Binds a socket to a local endpoint.
-    pub fn bind(&mut self, qd: QDesc, local: SocketAddrV4) -> Result<(), Fail> {
+    pub fn bind(&mut self, qd: QDesc, mut local: SocketAddrV4) -> Result<(), Fail> {
         trace!("bind() qd={:?}, local={:?}", qd, local);
 
-        let mut qtable: RefMut<IoQueueTable<CatloopQueue>> = self.qtable.borrow_mut();
-
         // Check if we are binding to the wildcard port.
         if local.port() == 0 {
-            let cause: String = format!("cannot bind to port 0 (qd={:?})", qd);
-            error!("bind(): {}", cause);
-            return Err(Fail::new(libc::ENOTSUP, &cause));
+            let port: u16 = self.alloc_ephemeral_port(local.ip())?;
+            local.set_port(port);
         }
 
+        let mut qtable: RefMut<IoQueueTable<CatloopQueue>> = self.qtable.borrow_mut();
+
         // Check if queue descriptor is valid.
         if qtable.get(&qd).is_none() {
             let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
@@ -199,7 +221,7 @@ impl CatloopLibOS {
         }
 
         // Make sure the queue is not already bound to a pipe.
-        if queue.get_pipe().is_some() {
+        if queue.get_pipe().is_some() || queue.get_datagram_pipe().is_some() {
             let cause: String = format!("socket is already bound to an address (qd={:?})", qd);
             error!("bind(): {}", &cause);
             return Err(Fail::new(libc::EINVAL, &cause));
@@ -208,12 +230,144 @@ impl CatloopLibOS {
         // Create underlying memory channels.
         let ipv4: &Ipv4Addr = local.ip();
         let port: u16 = local.port();
-        let duplex_pipe: Rc<DuplexPipe> = Rc::new(DuplexPipe::create_duplex_pipe(self.catmem.clone(), &ipv4, port)?);
-        queue.set_pipe(duplex_pipe);
+        match queue.get_qtype() {
+            QType::UdpSocket => {
+                let datagram_pipe: Rc<DatagramPipe> =
+                    Rc::new(DatagramPipe::create_datagram_pipe(self.catmem.clone(), ipv4, port)?);
+                queue.set_datagram_pipe(datagram_pipe);
+            },
+            _ => {
+                let duplex_pipe: Rc<DuplexPipe> =
+                    Rc::new(DuplexPipe::create_duplex_pipe(self.catmem.clone(), ipv4, port)?);
+                queue.set_pipe(duplex_pipe);
+            },
+        }
         queue.set_socket(Socket::Active(Some(local)));
+        queue.set_local(local);
         Ok(())
     }
 
+    /// Creates a pair of connected stream sockets, like `socketpair(2)` does, without going through the connection
+    /// establishment protocol. Data pushed to either socket is popped from the other one. Both sockets are named after
+    /// a port number on the loopback address that no socket is bound to.
+    pub fn socketpair(&mut self) -> Result<(QDesc, QDesc), Fail> {
+        trace!("socketpair()");
+        let ipv4: Ipv4Addr = Ipv4Addr::LOCALHOST;
+        let port: u16 = self.alloc_ephemeral_port(&ipv4)?;
+        let addr: SocketAddrV4 = SocketAddrV4::new(ipv4, port);
+
+        // Create the pipes on one end, and open them on the other end.
+        let duplex_pipe: Rc<DuplexPipe> = Rc::new(DuplexPipe::create_duplex_pipe(self.catmem.clone(), &ipv4, port)?);
+        let peer_duplex_pipe: Rc<DuplexPipe> = match DuplexPipe::open_duplex_pipe(self.catmem.clone(), &ipv4, port) {
+            Ok(duplex_pipe) => Rc::new(duplex_pipe),
+            Err(e) => {
+                if let Err(e) = duplex_pipe.shutdown() {
+                    warn!("socketpair(): failed to release duplex pipe: {:?}", e);
+                }
+                return Err(e);
+            },
+        };
+
+        let duplex_pipes: [Rc<DuplexPipe>; 2] = [duplex_pipe, peer_duplex_pipe];
+        let mut qtable: RefMut<IoQueueTable<CatloopQueue>> = self.qtable.borrow_mut();
+        let mut qds: Vec<QDesc> = Vec::with_capacity(2);
+        for duplex_pipe in duplex_pipes.iter() {
+            let mut queue: CatloopQueue = CatloopQueue::new(QType::TcpSocket);
+            queue.set_socket(Socket::Active(Some(addr)));
+            queue.set_pipe(duplex_pipe.clone());
+            match qtable.alloc(queue) {
+                Ok(qd) => qds.push(qd),
+                Err(e) => {
+                    for qd in qds {
+                        qtable.free(&qd);
+                    }
+                    for duplex_pipe in duplex_pipes.iter() {
+                        if let Err(e) = duplex_pipe.shutdown() {
+                            warn!("socketpair(): failed to release duplex pipe: {:?}", e);
+                        }
+                    }
+                    return Err(e);
+                },
+            }
+        }
+        // Reserve the port number, so that it does not name another pair of pipes.
+        if let Some(queue) = qtable.get_mut(&qds[0]) {
+            queue.set_local(addr);
+        }
+
+        Ok((qds[0], qds[1]))
+    }
+
+    /// Selects a port number that no socket is bound to on a local address. Port numbers are drawn from the same
+    /// counter that names the pipes of accepted connections.
+    fn alloc_ephemeral_port(&mut self, ipv4: &Ipv4Addr) -> Result<u16, Fail> {
+        let qtable: Ref<IoQueueTable<CatloopQueue>> = self.qtable.borrow();
+        for _ in 0..u16::MAX {
+            let port: u16 = self.next_port;
+            self.next_port = self.next_port.wrapping_add(1);
+            if port == 0 {
+                continue;
+            }
+            let in_use: bool = qtable
+                .get_values()
+                .any(|(_, queue)| queue.get_local() == Some(SocketAddrV4::new(*ipv4, port)));
+            if !in_use {
+                return Ok(port);
+            }
+        }
+        let cause: &str = "all port numbers are currently in use";
+        error!("bind(): {}", cause);
+        Err(Fail::new(libc::EADDRINUSE, cause))
+    }
+
+    /// Takes a snapshot of every open queue descriptor.
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        self.qtable.borrow().queues()
+    }
+
+    /// Takes a snapshot of the queue referred to by `qd`.
+    pub fn query_queue(&self, qd: QDesc) -> Result<QueueInfo, Fail> {
+        trace!("query_queue(): qd={:?}", qd);
+        match self.qtable.borrow().query(&qd) {
+            Some(info) => Ok(info),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Takes a snapshot of every connection that was accepted and is still open.
+    pub fn list_connections(&self) -> Vec<ConnectionInfo> {
+        let mut connections: Vec<ConnectionInfo> = self.connections.borrow().values().cloned().collect();
+        connections.sort_by_key(|info| Into::<u32>::into(info.qd));
+        connections
+    }
+
+    /// Lists the sockets that accept connections, along with the local address of each one.
+    pub fn list_listeners(&self) -> Vec<(QDesc, SocketAddrV4)> {
+        self.qtable
+            .borrow()
+            .queues()
+            .into_iter()
+            .filter_map(|info| match (info.state, info.local) {
+                (Some(QueueState::Listening), Some(local)) => Some((info.qd, local)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the local address that a socket is bound to. Sockets that are not bound yet report the unspecified
+    /// address and port zero.
+    pub fn getsockname(&self, qd: QDesc) -> Result<SocketAddrV4, Fail> {
+        trace!("getsockname() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => Ok(queue.get_local().unwrap_or(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))),
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("getsockname(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
     /// Sets a socket as a passive one.
     // FIXME: https://github.com/demikernel/demikernel/issues/697
     pub fn listen(&mut self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
@@ -224,6 +378,11 @@ impl CatloopLibOS {
 
         // Check if the queue descriptor is registered in the sockets table.
         match self.qtable.borrow_mut().get_mut(&qd) {
+            Some(queue) if queue.get_qtype() == QType::UdpSocket => {
+                let cause: String = format!("cannot call listen on a datagram socket (qd={:?})", qd);
+                error!("listen(): {}", &cause);
+                Err(Fail::new(libc::EOPNOTSUPP, &cause))
+            },
             Some(queue) => match queue.get_socket() {
                 Socket::Active(Some(local)) => {
                     queue.set_socket(Socket::Passive(local));
@@ -248,6 +407,31 @@ impl CatloopLibOS {
         }
     }
 
+    /// Accepts connections on a socket with a timeout. This is not supported on this LibOS, because an accept that
+    /// gives up would leave behind a pending pop on the control pipe, which could steal a later connection request.
+    pub fn accept_timeout(&mut self, qd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("accept_timeout() qd={:?}, timeout={:?}", qd, timeout);
+        let cause: String = format!("accept with timeout is not supported (qd={:?})", qd);
+        error!("accept_timeout(): {}", cause);
+        Err(Fail::new(libc::ENOTSUP, &cause))
+    }
+
+    /// Stops accepting connections on a listening socket. This is not supported on this LibOS.
+    pub fn stop_listening(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("stop_listening() qd={:?}", qd);
+        let cause: String = format!("stop listening is not supported (qd={:?})", qd);
+        error!("stop_listening(): {}", cause);
+        Err(Fail::new(libc::ENOTSUP, &cause))
+    }
+
+    /// Duplicates a queue descriptor. This is not supported on this LibOS.
+    pub fn dup(&mut self, qd: QDesc) -> Result<QDesc, Fail> {
+        trace!("dup() qd={:?}", qd);
+        let cause: String = format!("queue descriptor duplication is not supported (qd={:?})", qd);
+        error!("dup(): {}", cause);
+        Err(Fail::new(libc::ENOTSUP, &cause))
+    }
+
     /// Accepts connections on a socket.
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("accept() qd={:?}", qd);
@@ -265,14 +449,23 @@ impl CatloopLibOS {
                             return Err(Fail::new(libc::EINVAL, &cause));
                         },
                     };
-                    let new_qd: QDesc = qtable.alloc(CatloopQueue::new(QType::TcpSocket));
+                    let cancelled: Rc<Cell<bool>> = queue.get_cancelled();
+                    // Port zero is not a valid port number in a connect request ack, and the port of the listening
+                    // socket already names its control duplex pipe.
+                    let mut new_port: u16 = self.next_port;
+                    while new_port == 0 || new_port == local.port() {
+                        new_port = new_port.wrapping_add(1);
+                    }
+                    let new_qd: QDesc = qtable.alloc(CatloopQueue::new(QType::TcpSocket))?;
                     let future: AcceptFuture = AcceptFuture::new(
                         local.ip(),
                         self.catmem.clone(),
                         control_duplex_pipe.clone(),
-                        self.next_port,
+                        new_port,
+                        cancelled,
                     )?;
                     let qtable_ptr: Rc<RefCell<IoQueueTable<CatloopQueue>>> = self.qtable.clone();
+                    let connections_ptr: Rc<RefCell<HashMap<QDesc, ConnectionInfo>>> = self.connections.clone();
                     let coroutine: Pin<Box<Operation>> = Box::pin(async move {
                         // Wait for the accept to complete.
                         let result: Result<(SocketAddrV4, Rc<DuplexPipe>), Fail> = future.await;
@@ -285,6 +478,9 @@ impl CatloopLibOS {
                                     .expect("New qd should have been already allocated");
                                 queue.set_socket(Socket::Active(Some(remote)));
                                 queue.set_pipe(duplex_pipe.clone());
+                                connections_ptr
+                                    .borrow_mut()
+                                    .insert(new_qd, ConnectionInfo::new(new_qd, remote));
                                 (qd, OperationResult::Accept(new_qd, remote))
                             },
                             Err(e) => {
@@ -293,7 +489,7 @@ impl CatloopLibOS {
                             },
                         }
                     });
-                    self.next_port += 1;
+                    self.next_port = new_port.wrapping_add(1);
                     let task_id: String = format!("Catloop::accept for qd={:?}", qd);
                     let task: OperationTask = OperationTask::new(task_id, coroutine);
                     let handle: TaskHandle = match self.scheduler.insert(task) {
@@ -337,9 +533,14 @@ impl CatloopLibOS {
 
         // Issue connect operation.
         match self.qtable.borrow().get(&qd) {
+            Some(queue) if queue.get_qtype() == QType::UdpSocket => {
+                let cause: String = format!("cannot call connect on a datagram socket (qd={:?})", qd);
+                error!("connect(): {}", &cause);
+                Err(Fail::new(libc::EOPNOTSUPP, &cause))
+            },
             Some(queue) => match queue.get_socket() {
                 Socket::Active(_) => {
-                    let future: ConnectFuture = ConnectFuture::new(self.catmem.clone(), remote)?;
+                    let future: ConnectFuture = ConnectFuture::new(self.catmem.clone(), remote, queue.get_cancelled())?;
                     let qtable_ptr: Rc<RefCell<IoQueueTable<CatloopQueue>>> = self.qtable.clone();
                     let coroutine: Pin<Box<Operation>> = Box::pin(async move {
                         let result: Result<(SocketAddrV4, Rc<DuplexPipe>), Fail> = future.await;
@@ -348,7 +549,12 @@ impl CatloopLibOS {
                                 let mut qtable_: RefMut<IoQueueTable<CatloopQueue>> = qtable_ptr.borrow_mut();
                                 let queue: &mut CatloopQueue =
                                     qtable_.get_mut(&qd).expect("New qd should have been already allocated");
-                                // TODO: check whether we need to close the original control duplex pipe allocated on bind().
+                                // Release the duplex pipe that was created on bind(), since the connection replaces it.
+                                if let Some(pipe) = queue.get_pipe() {
+                                    if let Err(e) = pipe.shutdown() {
+                                        warn!("connect(): failed to release duplex pipe (qd={:?}): {:?}", qd, e);
+                                    }
+                                }
                                 queue.set_socket(Socket::Active(Some(remote)));
                                 queue.set_pipe(duplex_pipe.clone());
                                 (qd, OperationResult::Connect)
@@ -392,6 +598,40 @@ impl CatloopLibOS {
         }
     }
 
+    /// Binds a socket to a local endpoint, unless it is already bound, and establishes a connection to a remote
+    /// endpoint.
+    pub fn connect_from(&mut self, qd: QDesc, local: SocketAddrV4, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        trace!("connect_from() qd={:?}, local={:?}, remote={:?}", qd, local, remote);
+
+        let bound: Option<SocketAddrV4> = match self.qtable.borrow().get(&qd) {
+            Some(queue) => match queue.get_socket() {
+                Socket::Active(addr) => addr,
+                Socket::Passive(addr) => Some(addr),
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("connect_from(): {}", cause);
+                return Err(Fail::new(libc::EBADF, &cause));
+            },
+        };
+
+        match bound {
+            // Check if the socket is bound to the requested address.
+            Some(addr) => {
+                if (!local.ip().is_unspecified() && local.ip() != addr.ip())
+                    || (local.port() != 0 && local.port() != addr.port())
+                {
+                    let cause: String = format!("socket is bound to a different address (qd={:?})", qd);
+                    error!("connect_from(): {}", cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                }
+            },
+            None => self.bind(qd, local)?,
+        }
+
+        self.connect(qd, remote)
+    }
+
     /// Closes a socket.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         trace!("close() qd={:?}", qd);
@@ -401,9 +641,14 @@ impl CatloopLibOS {
         match qtable.get(&qd) {
             // Socket is not bound to a duplex pipe.
             Some(queue) => {
+                // Cancel any accept or connect operation that is still running on this socket.
+                queue.cancel_pending_ops();
                 if let Some(duplex_pipe) = queue.get_pipe() {
                     duplex_pipe.close()?;
                 }
+                if let Some(datagram_pipe) = queue.get_datagram_pipe() {
+                    datagram_pipe.close()?;
+                }
             },
             None => {
                 let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
@@ -412,6 +657,7 @@ impl CatloopLibOS {
             },
         };
         qtable.free(&qd);
+        self.connections.borrow_mut().remove(&qd);
         Ok(())
     }
 
@@ -420,6 +666,11 @@ impl CatloopLibOS {
         trace!("push() qd={:?}", qd);
 
         let catmem_qd: QDesc = match self.qtable.borrow().get(&qd) {
+            Some(queue) if queue.get_qtype() == QType::UdpSocket => {
+                let cause: String = format!("datagram socket requires a destination address (qd={:?})", qd);
+                error!("push(): {}", cause);
+                return Err(Fail::new(libc::EDESTADDRREQ, &cause));
+            },
             Some(queue) => match queue.get_pipe() {
                 Some(duplex_pipe) => duplex_pipe.tx(),
                 None => unreachable!("push() an unconnected queue"),
@@ -433,6 +684,65 @@ impl CatloopLibOS {
 
         let qt: QToken = self.catmem.borrow_mut().push(catmem_qd, sga)?;
         self.catmem_qts.insert(qt, (demi_opcode_t::DEMI_OPC_PUSH, qd));
+        if let Some(info) = self.connections.borrow_mut().get_mut(&qd) {
+            info.tx_bytes += sga.sga_segs[0].sgaseg_len as u64;
+        }
+
+        Ok(Self::shift_qtoken(qt))
+    }
+
+    /// Pushes a scatter-gather array to a datagram socket, as a single datagram. Sockets that are not bound yet are
+    /// bound to an ephemeral port on the address of the destination. Datagrams that do not fit in the underlying
+    /// memory queue fail with `EMSGSIZE`.
+    pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        trace!("pushto() qd={:?}, remote={:?}", qd, remote);
+
+        // Check if the socket is a datagram socket that is bound to a local address.
+        let bound: bool = match self.qtable.borrow().get(&qd) {
+            Some(queue) if queue.get_qtype() == QType::UdpSocket => queue.get_local().is_some(),
+            Some(_) => {
+                let cause: String = format!("cannot call pushto on a stream socket (qd={:?})", qd);
+                error!("pushto(): {}", cause);
+                return Err(Fail::new(libc::EOPNOTSUPP, &cause));
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("pushto(): {}", cause);
+                return Err(Fail::new(libc::EBADF, &cause));
+            },
+        };
+        if !bound {
+            self.bind(qd, SocketAddrV4::new(*remote.ip(), 0))?;
+        }
+
+        let (local, datagram_pipe): (SocketAddrV4, Rc<DatagramPipe>) = {
+            let qtable: Ref<IoQueueTable<CatloopQueue>> = self.qtable.borrow();
+            let queue: &CatloopQueue = qtable.get(&qd).expect("queue descriptor should be in queue table");
+            match (queue.get_local(), queue.get_datagram_pipe()) {
+                (Some(local), Some(datagram_pipe)) => (local, datagram_pipe),
+                _ => unreachable!("pushto() an unbound datagram socket"),
+            }
+        };
+        let catmem_qd: QDesc = datagram_pipe.tx(remote)?;
+
+        // Check if the datagram fits in the underlying memory queue.
+        let payload: DemiBuffer = self.catmem.borrow().clone_sgarray(sga)?;
+        let len: usize = Self::DATAGRAM_HEADER_SIZE + payload.len();
+        if len > self.catmem.borrow().max_record_size(catmem_qd)? {
+            let cause: String = format!("datagram is too large (qd={:?}, len={:?})", qd, payload.len());
+            error!("pushto(): {}", cause);
+            return Err(Fail::new(libc::EMSGSIZE, &cause));
+        }
+
+        // Prepend the address of the sender.
+        let mut buf: DemiBuffer = DemiBuffer::new(len as u16);
+        buf[0..4].copy_from_slice(&local.ip().octets());
+        buf[4..6].copy_from_slice(&local.port().to_be_bytes());
+        buf[Self::DATAGRAM_HEADER_SIZE..].copy_from_slice(&payload[..]);
+
+        let record: SgaGuard = SgaGuard::new(self.catmem.borrow().into_sgarray(buf)?);
+        let qt: QToken = self.catmem.borrow_mut().push_record(catmem_qd, &record)?;
+        self.catmem_qts.insert(qt, (demi_opcode_t::DEMI_OPC_PUSH, qd));
 
         Ok(Self::shift_qtoken(qt))
     }
@@ -440,10 +750,45 @@ impl CatloopLibOS {
     /// Pops data from a socket.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         trace!("pop() qd={:?}, size={:?}", qd, size);
+        self.do_pop(qd, size, None)
+    }
 
+    /// Pops data from a socket, failing with `ETIMEDOUT` if no data arrives within `timeout`.
+    pub fn pop_timeout(&mut self, qd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("pop_timeout() qd={:?}, size={:?}, timeout={:?}", qd, size, timeout);
+        self.do_pop(qd, size, Some(timeout))
+    }
+
+    /// Pops data from the underlying memory queue of a socket, with an optional timeout.
+    fn do_pop(&mut self, qd: QDesc, size: Option<usize>, timeout: Option<Duration>) -> Result<QToken, Fail> {
         // We just assert 'size' here, because it was previously checked at PDPIX layer.
         debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
 
+        let datagram_pipe: Option<Rc<DatagramPipe>> = match self.qtable.borrow().get(&qd) {
+            Some(queue) if queue.get_qtype() == QType::UdpSocket => match queue.get_datagram_pipe() {
+                Some(datagram_pipe) => Some(datagram_pipe),
+                None => {
+                    let cause: String = format!("datagram socket is not bound (qd={:?})", qd);
+                    error!("pop(): {}", cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                },
+            },
+            Some(_) => None,
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("pop(): {}", cause);
+                return Err(Fail::new(libc::EBADF, &cause));
+            },
+        };
+
+        // Pop exactly one datagram from datagram sockets, along with the address of its sender.
+        if let Some(datagram_pipe) = datagram_pipe {
+            let size: Option<usize> = size.map(|size| size + Self::DATAGRAM_HEADER_SIZE);
+            let qt: QToken = self.catmem.borrow_mut().pop_record(datagram_pipe.rx(), size, timeout)?;
+            self.catmem_qts.insert(qt, (demi_opcode_t::DEMI_OPC_POP, qd));
+            return Ok(Self::shift_qtoken(qt));
+        }
+
         let catmem_qd: QDesc = match self.qtable.borrow().get(&qd) {
             Some(queue) => match queue.get_pipe() {
                 Some(duplex_pipe) => duplex_pipe.rx(),
@@ -456,12 +801,105 @@ impl CatloopLibOS {
             },
         };
 
-        let qt: QToken = self.catmem.borrow_mut().pop(catmem_qd, size)?;
+        let qt: QToken = match timeout {
+            Some(timeout) => self.catmem.borrow_mut().pop_timeout(catmem_qd, size, timeout)?,
+            None => self.catmem.borrow_mut().pop(catmem_qd, size)?,
+        };
         self.catmem_qts.insert(qt, (demi_opcode_t::DEMI_OPC_POP, qd));
 
         Ok(Self::shift_qtoken(qt))
     }
 
+    /// Returns the number of bytes that may be popped right away from a socket. For a datagram socket, this is the size
+    /// of the next datagram. For a listening socket, this is the number of connection requests that may be accepted
+    /// right away instead.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("bytes_available() qd={:?}", qd);
+
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) if queue.get_qtype() == QType::UdpSocket => match queue.get_datagram_pipe() {
+                Some(datagram_pipe) => {
+                    let len: usize = self.catmem.borrow().record_bytes_available(datagram_pipe.rx())?;
+                    Ok(len.saturating_sub(Self::DATAGRAM_HEADER_SIZE))
+                },
+                None => Ok(0),
+            },
+            Some(queue) => match (queue.get_socket(), queue.get_pipe()) {
+                // Each connection request is a single magic byte on the control pipe.
+                (Socket::Passive(_), Some(control_duplex_pipe)) => {
+                    let len: usize = self.catmem.borrow().bytes_available(control_duplex_pipe.rx())?;
+                    Ok(len / mem::size_of_val(&Self::MAGIC_CONNECT))
+                },
+                (_, Some(duplex_pipe)) => self.catmem.borrow().bytes_available(duplex_pipe.rx()),
+                (_, None) => Ok(0),
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("bytes_available(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single push transfers as a unit on a socket. Larger pushes are
+    /// accepted and split across multiple operations on the underlying memory queue, except on datagram sockets.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("max_push_size() qd={:?}", qd);
+
+        let catmem_qd: QDesc = match self.qtable.borrow().get(&qd) {
+            Some(queue) if queue.get_qtype() == QType::UdpSocket => match queue.get_datagram_pipe() {
+                // All memory queues have the same capacity, so ask the receiving one.
+                Some(datagram_pipe) => {
+                    let max_record_size: usize = self.catmem.borrow().max_record_size(datagram_pipe.rx())?;
+                    return Ok(max_record_size - Self::DATAGRAM_HEADER_SIZE);
+                },
+                None => {
+                    let cause: String = format!("socket is not bound (qd={:?})", qd);
+                    error!("max_push_size(): {}", cause);
+                    return Err(Fail::new(libc::ENOTCONN, &cause));
+                },
+            },
+            Some(queue) => match queue.get_pipe() {
+                Some(duplex_pipe) => duplex_pipe.tx(),
+                None => {
+                    let cause: String = format!("socket is not bound (qd={:?})", qd);
+                    error!("max_push_size(): {}", cause);
+                    return Err(Fail::new(libc::ENOTCONN, &cause));
+                },
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("max_push_size(): {}", cause);
+                return Err(Fail::new(libc::EBADF, &cause));
+            },
+        };
+
+        self.catmem.borrow().max_push_size(catmem_qd)
+    }
+
+    /// Forces transmission of all data that is buffered on a socket.
+    pub fn flush(&self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush() qd={:?}", qd);
+
+        let catmem_qd: QDesc = match self.qtable.borrow().get(&qd) {
+            Some(queue) => match queue.get_pipe() {
+                Some(duplex_pipe) => duplex_pipe.tx(),
+                None => {
+                    let cause: String = format!("socket is not bound (qd={:?})", qd);
+                    error!("flush(): {}", cause);
+                    return Err(Fail::new(libc::ENOTCONN, &cause));
+                },
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("flush(): {}", cause);
+                return Err(Fail::new(libc::EBADF, &cause));
+            },
+        };
+
+        self.catmem.borrow().flush(catmem_qd)
+    }
+
     /// Allocates a scatter-gather array.
     pub fn sgaalloc(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
         self.catmem.borrow_mut().alloc_sgarray(size)
@@ -556,6 +994,30 @@ impl CatloopLibOS {
             // because we only distribute to the user queue descriptors that are managed by Catloop LibLOS.
             qr.qr_qd = catloop_qd.to_owned().into();
 
+            // Datagrams carry the address of their sender ahead of the payload.
+            let is_datagram: bool = match self.qtable.borrow().get(catloop_qd) {
+                Some(queue) => queue.get_qtype() == QType::UdpSocket,
+                None => false,
+            };
+            if is_datagram && qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP {
+                if let Err(e) = self.unpack_datagram(&mut qr) {
+                    warn!("Operation Failed: {:?}", e);
+                    qr.qr_opcode = demi_opcode_t::DEMI_OPC_FAILED;
+                    qr.qr_ret = e.errno as i64;
+                    qr.qr_value = unsafe { mem::zeroed() };
+                }
+            }
+
+            // Account popped data on accepted connections.
+            if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP {
+                if let Some(info) = self.connections.borrow_mut().get_mut(catloop_qd) {
+                    info.rx_bytes += unsafe { qr.qr_value.sga.sga_segs[0].sgaseg_len } as u64;
+                    if qr.qr_flags & DEMI_QR_EOF != 0 {
+                        info.state = ConnectionState::RemoteClosed;
+                    }
+                }
+            }
+
             return Ok(qr);
         }
 
@@ -581,33 +1043,39 @@ impl CatloopLibOS {
         task.get_result().expect("The coroutine has not finished")
     }
 
-    /// Cooks a magic connect message.
-    pub fn cook_magic_connect(catmem: &Rc<RefCell<CatmemLibOS>>) -> Result<demi_sgarray_t, Fail> {
-        let sga: demi_sgarray_t = catmem
-            .borrow_mut()
-            .alloc_sgarray(mem::size_of_val(&CatloopLibOS::MAGIC_CONNECT))?;
-
-        let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
-        unsafe {
-            *ptr = CatloopLibOS::MAGIC_CONNECT;
+    /// Strips the address of the sender from a datagram in an operation result, and stores it in the scatter-gather
+    /// array instead.
+    fn unpack_datagram(&self, qr: &mut demi_qresult_t) -> Result<(), Fail> {
+        let catmem: Ref<CatmemLibOS> = self.catmem.borrow();
+        let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        let mut buf: DemiBuffer = catmem.clone_sgarray(&sga)?;
+        catmem.free_sgarray(sga)?;
+
+        if buf.len() < Self::DATAGRAM_HEADER_SIZE {
+            let cause: String = format!("malformed datagram (len={:?})", buf.len());
+            error!("unpack_datagram(): {}", cause);
+            return Err(Fail::new(libc::EBADMSG, &cause));
         }
+        let ipv4: Ipv4Addr = Ipv4Addr::new(buf[0], buf[1], buf[2], buf[3]);
+        let port: u16 = u16::from_be_bytes([buf[4], buf[5]]);
+        buf.adjust(Self::DATAGRAM_HEADER_SIZE)?;
 
-        Ok(sga)
+        let mut sga: demi_sgarray_t = catmem.into_sgarray(buf)?;
+        sga.sga_addr = linux::socketaddrv4_to_sockaddr(&SocketAddrV4::new(ipv4, port));
+        qr.qr_value = demi_qr_value_t { sga };
+        Ok(())
     }
 
-    /// Checks for a magic connect message.
-    pub fn is_magic_connect(sga: &demi_sgarray_t) -> bool {
-        let len: usize = sga.sga_segs[0].sgaseg_len as usize;
-        if len == mem::size_of_val(&CatloopLibOS::MAGIC_CONNECT) {
-            let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
-            let slice: &mut [u8] = unsafe { slice::from_raw_parts_mut(ptr, len) };
-            let bytes = CatloopLibOS::MAGIC_CONNECT.to_ne_bytes();
-            if slice[..] == bytes[..] {
-                return true;
-            }
-        }
+    /// Cooks a magic connect message.
+    pub fn cook_magic_connect(catmem: &Rc<RefCell<CatmemLibOS>>) -> Result<demi_sgarray_t, Fail> {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&CatloopLibOS::MAGIC_CONNECT.to_ne_bytes())?;
+        catmem.borrow().into_sgarray(buf)
+    }
 
-        false
+    /// Checks for a magic connect message.
+    pub fn is_magic_connect(catmem: &Rc<RefCell<CatmemLibOS>>, sga: &demi_sgarray_t) -> Result<bool, Fail> {
+        let buf: DemiBuffer = catmem.borrow().clone_sgarray(sga)?;
+        Ok(buf.as_bytes() == CatloopLibOS::MAGIC_CONNECT.to_ne_bytes())
     }
 
     /// Shifts a queue token by a certain amount.
@@ -641,6 +1109,10 @@ fn pack_result(result: OperationResult, qd: QDesc, qt: u64) -> demi_qresult_t {
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
         OperationResult::Accept(new_qd, addr) => {
             let saddr: SockAddr = linux::socketaddrv4_to_sockaddr(&addr);
@@ -656,6 +1128,10 @@ fn pack_result(result: OperationResult, qd: QDesc, qt: u64) -> demi_qresult_t {
                 qr_qt: qt,
                 qr_ret: 0,
                 qr_value,
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
         OperationResult::Failed(e) => {
@@ -666,7 +1142,802 @@ fn pack_result(result: OperationResult, qd: QDesc, qt: u64) -> demi_qresult_t {
                 qr_qt: qt,
                 qr_ret: e.errno as i64,
                 qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CatloopLibOS,
+        DuplexPipe,
+    };
+    use crate::{
+        catmem::CatmemLibOS,
+        collections::shared_ring::SharedRingBuffer,
+        pal::{
+            data_structures::SockAddr,
+            linux,
+        },
+        runtime::{
+            fail::Fail,
+            memory::{
+                DemiBuffer,
+                MemoryRuntime,
+            },
+            types::{
+                demi_opcode_t,
+                demi_qresult_t,
+                demi_sgarray_t,
+                DEMI_QR_EOF,
+            },
+            ConnectionInfo,
+            ConnectionState,
+            QDesc,
+            QToken,
+        },
+        scheduler::TaskHandle,
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        cell::RefCell,
+        net::{
+            Ipv4Addr,
+            SocketAddrV4,
+        },
+        rc::Rc,
+        time::{
+            Duration,
+            Instant,
+        },
+    };
+
+    /// Address used in tests.
+    const LOCALHOST: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
+
+    /// Polls the target LibOS until the operation associated with `qt` completes.
+    fn wait(libos: &mut CatloopLibOS, qt: QToken) -> Result<demi_qresult_t> {
+        let handle: TaskHandle = match libos.schedule(qt) {
+            Ok(handle) => handle,
+            Err(e) => anyhow::bail!("schedule() failed: {:?}", e),
+        };
+        while !handle.has_completed() {
+            libos.poll();
+        }
+        match libos.pack_result(handle, qt) {
+            Ok(qr) => Ok(qr),
+            Err(e) => anyhow::bail!("pack_result() failed: {:?}", e),
+        }
+    }
+
+    /// Creates a datagram socket and binds it to `local`.
+    fn udp_socket(libos: &mut CatloopLibOS, local: SocketAddrV4) -> Result<QDesc> {
+        let qd: QDesc = match libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("socket() failed: {:?}", e),
+        };
+        if let Err(e) = libos.bind(qd, local) {
+            anyhow::bail!("bind() failed: {:?}", e);
+        }
+        Ok(qd)
+    }
+
+    /// Pushes a datagram of `size` bytes from `qd` to `remote`, and waits for the push to complete.
+    fn pushto(libos: &mut CatloopLibOS, qd: QDesc, size: usize, remote: SocketAddrV4) -> Result<()> {
+        let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
+        for (i, x) in buf.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+        let sga: demi_sgarray_t = match libos.catmem.borrow().into_sgarray(buf) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("into_sgarray() failed: {:?}", e),
+        };
+        let result: Result<QToken, Fail> = libos.pushto(qd, &sga, remote);
+        if let Err(e) = libos.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        let qt: QToken = match result {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pushto() failed: {:?}", e),
+        };
+        let qr: demi_qresult_t = wait(libos, qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        Ok(())
+    }
+
+    /// Pops a datagram from `qd`, and checks that it has `size` bytes and that it came from `remote`.
+    fn pop(libos: &mut CatloopLibOS, qd: QDesc, size: usize, remote: SocketAddrV4) -> Result<()> {
+        let qt: QToken = match libos.pop(qd, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        let qr: demi_qresult_t = wait(libos, qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        let addr: SockAddr = sga.sga_addr;
+        crate::ensure_eq!(linux::sockaddr_to_socketaddrv4(&addr), remote);
+        let buf: DemiBuffer = match libos.catmem.borrow().clone_sgarray(&sga) {
+            Ok(buf) => buf,
+            Err(e) => anyhow::bail!("clone_sgarray() failed: {:?}", e),
+        };
+        crate::ensure_eq!(buf.len(), size);
+        for (i, x) in buf.iter().enumerate() {
+            crate::ensure_eq!(*x, i as u8);
+        }
+        if let Err(e) = libos.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        Ok(())
+    }
+
+    /// Establishes a connection from `client` to the socket `listen_qd` of `server`, which listens on `remote`. Returns
+    /// the queue descriptors of the server and client ends of the connection.
+    fn tcp_connect(
+        server: &mut CatloopLibOS,
+        client: &mut CatloopLibOS,
+        listen_qd: QDesc,
+        remote: SocketAddrV4,
+    ) -> Result<(QDesc, QDesc)> {
+        let qt_accept: QToken = match server.accept(listen_qd) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("accept() failed: {:?}", e),
+        };
+        let client_qd: QDesc = match client.socket(libc::AF_INET, libc::SOCK_STREAM, 0) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("socket() failed: {:?}", e),
+        };
+        let qt_connect: QToken = match client.connect(client_qd, remote) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("connect() failed: {:?}", e),
+        };
+
+        // Both ends take part in the connection establishment, so poll them in turns.
+        let (handle_accept, handle_connect): (TaskHandle, TaskHandle) =
+            match (server.schedule(qt_accept), client.schedule(qt_connect)) {
+                (Ok(handle_accept), Ok(handle_connect)) => (handle_accept, handle_connect),
+                _ => anyhow::bail!("schedule() failed"),
+            };
+        while !handle_accept.has_completed() || !handle_connect.has_completed() {
+            server.poll();
+            client.poll();
+        }
+        let server_qd: QDesc = match server.pack_result(handle_accept, qt_accept) {
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_ACCEPT => unsafe { qr.qr_value.ares.qd.into() },
+            Ok(qr) => anyhow::bail!("accept() failed: {:?}", qr.qr_ret),
+            Err(e) => anyhow::bail!("pack_result() failed: {:?}", e),
+        };
+        match client.pack_result(handle_connect, qt_connect) {
+            Ok(qr) => crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_CONNECT),
+            Err(e) => anyhow::bail!("pack_result() failed: {:?}", e),
+        };
+
+        Ok((server_qd, client_qd))
+    }
+
+    /// Tests if datagram sockets can be bound to non-ephemeral, ephemeral, and wildcard ports, and closed.
+    #[ignore]
+    #[test]
+    fn udp_setup() -> Result<()> {
+        let mut libos: CatloopLibOS = CatloopLibOS::new();
+        for port in [20001, 49152] {
+            let qd: QDesc = udp_socket(&mut libos, SocketAddrV4::new(LOCALHOST, port))?;
+            if let Err(e) = libos.close(qd) {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+        }
+
+        // Check that an ephemeral port was selected.
+        let qd: QDesc = udp_socket(&mut libos, SocketAddrV4::new(LOCALHOST, 0))?;
+        match libos.getsockname(qd) {
+            Ok(addr) if addr.port() != 0 => (),
+            Ok(addr) => anyhow::bail!("getsockname() returned port zero: {:?}", addr),
+            Err(e) => anyhow::bail!("getsockname() failed: {:?}", e),
+        };
+        if let Err(e) = libos.close(qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Tests if datagrams are echoed back and forth between two instances, and whether they carry their sender.
+    #[ignore]
+    #[test]
+    fn udp_loopback() -> Result<()> {
+        let alice_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20002);
+        let bob_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20003);
+        let mut alice: CatloopLibOS = CatloopLibOS::new();
+        let mut bob: CatloopLibOS = CatloopLibOS::new();
+        let alice_qd: QDesc = udp_socket(&mut alice, alice_addr)?;
+        let bob_qd: QDesc = udp_socket(&mut bob, bob_addr)?;
+
+        pushto(&mut alice, alice_qd, 32, bob_addr)?;
+        pop(&mut bob, bob_qd, 32, alice_addr)?;
+        pushto(&mut bob, bob_qd, 32, alice_addr)?;
+        pop(&mut alice, alice_qd, 32, bob_addr)?;
+
+        for (libos, qd) in [(&mut alice, alice_qd), (&mut bob, bob_qd)] {
+            if let Err(e) = libos.close(qd) {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests if each push becomes exactly one pop, if unbound senders are bound on demand, and if datagrams that do
+    /// not fit in a memory queue are rejected.
+    #[ignore]
+    #[test]
+    fn udp_datagram_boundaries() -> Result<()> {
+        let bob_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20004);
+        let mut alice: CatloopLibOS = CatloopLibOS::new();
+        let mut bob: CatloopLibOS = CatloopLibOS::new();
+        let alice_qd: QDesc = match alice.socket(libc::AF_INET, libc::SOCK_DGRAM, 0) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("socket() failed: {:?}", e),
+        };
+        let bob_qd: QDesc = udp_socket(&mut bob, bob_addr)?;
+
+        // Push datagrams back to back, from an unbound socket.
+        for size in [16, 0, 8] {
+            pushto(&mut alice, alice_qd, size, bob_addr)?;
+        }
+        let alice_addr: SocketAddrV4 = match alice.getsockname(alice_qd) {
+            Ok(addr) if addr.port() != 0 => addr,
+            Ok(addr) => anyhow::bail!("getsockname() returned port zero: {:?}", addr),
+            Err(e) => anyhow::bail!("getsockname() failed: {:?}", e),
+        };
+        for size in [16, 0, 8] {
+            pop(&mut bob, bob_qd, size, alice_addr)?;
+        }
+
+        // Datagrams that are larger than a memory queue should fail.
+        let max_push_size: usize = match alice.max_push_size(alice_qd) {
+            Ok(size) => size,
+            Err(e) => anyhow::bail!("max_push_size() failed: {:?}", e),
+        };
+        let sga: demi_sgarray_t = match alice.sgaalloc(max_push_size + 1) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("sgaalloc() failed: {:?}", e),
+        };
+        match alice.pushto(alice_qd, &sga, bob_addr) {
+            Err(e) if e.errno == libc::EMSGSIZE => (),
+            _ => anyhow::bail!("pushto() of an oversized datagram should fail with EMSGSIZE"),
+        }
+        if let Err(e) = alice.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        pushto(&mut alice, alice_qd, max_push_size, bob_addr)?;
+
+        for (libos, qd) in [(&mut alice, alice_qd), (&mut bob, bob_qd)] {
+            if let Err(e) = libos.close(qd) {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests if the bytes available on a datagram socket are the size of the next datagram, without its header.
+    #[ignore]
+    #[test]
+    fn udp_bytes_available() -> Result<()> {
+        let alice_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20010);
+        let bob_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20011);
+        let mut alice: CatloopLibOS = CatloopLibOS::new();
+        let mut bob: CatloopLibOS = CatloopLibOS::new();
+        let alice_qd: QDesc = udp_socket(&mut alice, alice_addr)?;
+        let bob_qd: QDesc = udp_socket(&mut bob, bob_addr)?;
+        crate::ensure_eq!(bob.bytes_available(bob_qd)?, 0);
+
+        for size in [16, 8] {
+            pushto(&mut alice, alice_qd, size, bob_addr)?;
+        }
+        for size in [16, 8] {
+            crate::ensure_eq!(bob.bytes_available(bob_qd)?, size);
+            pop(&mut bob, bob_qd, size, alice_addr)?;
+        }
+        crate::ensure_eq!(bob.bytes_available(bob_qd)?, 0);
+
+        for (libos, qd) in [(&mut alice, alice_qd), (&mut bob, bob_qd)] {
+            if let Err(e) = libos.close(qd) {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests if a client still connects when the pipes of the connection are created only after the server sent their
+    /// port number, thus after the client first attempts to open them.
+    #[ignore]
+    #[test]
+    fn tcp_connect_retries_open() -> Result<()> {
+        let listen_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20005);
+        let new_port: u16 = 20006;
+        let server: CatloopLibOS = CatloopLibOS::new();
+        let mut client: CatloopLibOS = CatloopLibOS::new();
+
+        // Stand in for a listening socket, so that we control when the pipes of the connection are created.
+        let control_duplex_pipe: DuplexPipe =
+            match DuplexPipe::create_duplex_pipe(server.catmem.clone(), listen_addr.ip(), listen_addr.port()) {
+                Ok(duplex_pipe) => duplex_pipe,
+                Err(e) => anyhow::bail!("create_duplex_pipe() failed: {:?}", e),
+            };
+        let qt_rx: QToken = match control_duplex_pipe.pop(None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+
+        // Issue connect.
+        let qd: QDesc = match client.socket(libc::AF_INET, libc::SOCK_STREAM, 0) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("socket() failed: {:?}", e),
+        };
+        let qt_connect: QToken = match client.connect(qd, listen_addr) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("connect() failed: {:?}", e),
+        };
+        let handle: TaskHandle = match client.schedule(qt_connect) {
+            Ok(handle) => handle,
+            Err(e) => anyhow::bail!("schedule() failed: {:?}", e),
+        };
+
+        // Receive the connect request, and reply with the port number of the connection.
+        let handle_rx: TaskHandle = loop {
+            client.poll();
+            server.catmem.borrow().poll();
+            match DuplexPipe::poll(&server.catmem, qt_rx) {
+                Ok(Some(handle)) => break handle,
+                Ok(None) => (),
+                Err(e) => anyhow::bail!("poll() failed: {:?}", e),
+            }
+        };
+        let qr: demi_qresult_t = match server.catmem.borrow_mut().pack_result(handle_rx, qt_rx) {
+            Ok(qr) => qr,
+            Err(e) => anyhow::bail!("pack_result() failed: {:?}", e),
+        };
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        if let Err(e) = server.sgafree(unsafe { qr.qr_value.sga }) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        let sga: demi_sgarray_t = match DemiBuffer::from_slice(&new_port.to_ne_bytes())
+            .and_then(|buf| server.catmem.borrow().into_sgarray(buf))
+        {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("into_sgarray() failed: {:?}", e),
+        };
+        let result: Result<QToken, Fail> = control_duplex_pipe.push(&sga);
+        if let Err(e) = server.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        if let Err(e) = result {
+            anyhow::bail!("push() failed: {:?}", e);
+        }
+
+        // The client cannot open the pipes of the connection yet.
+        let deadline: Instant = Instant::now() + Duration::from_millis(20);
+        while Instant::now() < deadline {
+            client.poll();
+            server.catmem.borrow().poll();
+            crate::ensure_eq!(handle.has_completed(), false);
+        }
+
+        // Once the pipes exist, the client connects.
+        let duplex_pipe: DuplexPipe = match DuplexPipe::create_duplex_pipe(server.catmem.clone(), &LOCALHOST, new_port)
+        {
+            Ok(duplex_pipe) => duplex_pipe,
+            Err(e) => anyhow::bail!("create_duplex_pipe() failed: {:?}", e),
+        };
+        while !handle.has_completed() {
+            client.poll();
+        }
+        match client.pack_result(handle, qt_connect) {
+            Ok(qr) => crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_CONNECT),
+            Err(e) => anyhow::bail!("pack_result() failed: {:?}", e),
+        };
+
+        if let Err(e) = client.close(qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+        for duplex_pipe in [duplex_pipe, control_duplex_pipe] {
+            if let Err(e) = duplex_pipe.close() {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests if accepted connections are listed until they are closed, along with the bytes that went through them,
+    /// and if listening sockets are listed.
+    #[ignore]
+    #[test]
+    fn tcp_list_connections() -> Result<()> {
+        const NUM_CONNECTIONS: usize = 3;
+        const PUSH_SIZE: usize = 32;
+        let listen_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20007);
+        let mut server: CatloopLibOS = CatloopLibOS::new();
+        let mut client: CatloopLibOS = CatloopLibOS::new();
+
+        let listen_qd: QDesc = match server.socket(libc::AF_INET, libc::SOCK_STREAM, 0) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("socket() failed: {:?}", e),
+        };
+        if let Err(e) = server.bind(listen_qd, listen_addr) {
+            anyhow::bail!("bind() failed: {:?}", e);
+        }
+        if let Err(e) = server.listen(listen_qd, 16) {
+            anyhow::bail!("listen() failed: {:?}", e);
+        }
+        crate::ensure_eq!(server.list_listeners(), vec![(listen_qd, listen_addr)]);
+        crate::ensure_eq!(server.list_connections().is_empty(), true);
+
+        // Accept connections.
+        let mut qds: Vec<(QDesc, QDesc)> = Vec::with_capacity(NUM_CONNECTIONS);
+        for _ in 0..NUM_CONNECTIONS {
+            qds.push(tcp_connect(&mut server, &mut client, listen_qd, listen_addr)?);
+        }
+        let connections: Vec<ConnectionInfo> = server.list_connections();
+        crate::ensure_eq!(connections.len(), NUM_CONNECTIONS);
+        for (info, (server_qd, _)) in connections.iter().zip(qds.iter()) {
+            crate::ensure_eq!(info.qd, *server_qd);
+            crate::ensure_eq!(info.state, ConnectionState::Established);
+            crate::ensure_eq!(info.tx_bytes, 0);
+            crate::ensure_eq!(info.rx_bytes, 0);
+        }
+
+        // Transfer data over the first connection.
+        let (server_qd, client_qd): (QDesc, QDesc) = qds[0];
+        let sga: demi_sgarray_t = match client.sgaalloc(PUSH_SIZE) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("sgaalloc() failed: {:?}", e),
+        };
+        let result: Result<QToken, Fail> = client.push(client_qd, &sga);
+        if let Err(e) = client.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        let qt: QToken = match result {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        crate::ensure_eq!(wait(&mut client, qt)?.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        let mut nbytes: usize = 0;
+        while nbytes < PUSH_SIZE {
+            let qt: QToken = match server.pop(server_qd, None) {
+                Ok(qt) => qt,
+                Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+            };
+            let qr: demi_qresult_t = wait(&mut server, qt)?;
+            crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+            let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+            nbytes += sga.sga_segs[0].sgaseg_len as usize;
+            if let Err(e) = server.sgafree(sga) {
+                anyhow::bail!("sgafree() failed: {:?}", e);
+            }
+        }
+        crate::ensure_eq!(server.list_connections()[0].rx_bytes, PUSH_SIZE as u64);
+
+        // Closed connections are no longer listed.
+        if let Err(e) = server.close(server_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+        let connections: Vec<ConnectionInfo> = server.list_connections();
+        crate::ensure_eq!(connections.len(), NUM_CONNECTIONS - 1);
+        crate::ensure_eq!(connections.iter().any(|info| info.qd == server_qd), false);
+
+        for (server_qd, client_qd) in qds.into_iter().skip(1) {
+            if let Err(e) = server.close(server_qd) {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+            if let Err(e) = client.close(client_qd) {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+        }
+        if let Err(e) = client.close(client_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+        if let Err(e) = server.close(listen_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Creates a stream socket that listens on `local`.
+    fn tcp_listen(libos: &mut CatloopLibOS, local: SocketAddrV4) -> Result<QDesc> {
+        let qd: QDesc = match libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("socket() failed: {:?}", e),
+        };
+        if let Err(e) = libos.bind(qd, local) {
+            anyhow::bail!("bind() failed: {:?}", e);
+        }
+        if let Err(e) = libos.listen(qd, 16) {
+            anyhow::bail!("listen() failed: {:?}", e);
+        }
+        Ok(qd)
+    }
+
+    /// Tests if closing a connection pushes EoF to the remote end, and if no pipe is left behind once both ends and
+    /// the listening socket are closed.
+    #[ignore]
+    #[test]
+    fn tcp_close_releases_pipes() -> Result<()> {
+        let listen_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20008);
+        let new_port: u16 = 20100;
+        let mut server: CatloopLibOS = CatloopLibOS::new();
+        let mut client: CatloopLibOS = CatloopLibOS::new();
+        server.next_port = new_port;
+
+        let listen_qd: QDesc = tcp_listen(&mut server, listen_addr)?;
+        let (server_qd, client_qd): (QDesc, QDesc) = tcp_connect(&mut server, &mut client, listen_qd, listen_addr)?;
+
+        // The server end sees EoF once the client end is closed.
+        if let Err(e) = client.close(client_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+        let qt: QToken = match server.pop(server_qd, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        let qr: demi_qresult_t = wait(&mut server, qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        crate::ensure_eq!(qr.qr_flags & DEMI_QR_EOF, DEMI_QR_EOF);
+        if let Err(e) = server.sgafree(unsafe { qr.qr_value.sga }) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+
+        if let Err(e) = server.close(server_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+        if let Err(e) = server.close(listen_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+
+        // No pipe is left open on either end, and the pipes of the connection are gone.
+        crate::ensure_eq!(server.catmem.borrow().queues().is_empty(), true);
+        crate::ensure_eq!(client.catmem.borrow().queues().is_empty(), true);
+        crate::ensure_eq!(
+            DuplexPipe::open_duplex_pipe(client.catmem.clone(), &LOCALHOST, new_port).is_err(),
+            true
+        );
+
+        Ok(())
+    }
+
+    /// Pushes `size` bytes to the connection `qd`, and waits for the push to complete.
+    fn tcp_push(libos: &mut CatloopLibOS, qd: QDesc, size: usize) -> Result<()> {
+        let sga: demi_sgarray_t = match libos.sgaalloc(size) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("sgaalloc() failed: {:?}", e),
+        };
+        let result: Result<QToken, Fail> = libos.push(qd, &sga);
+        if let Err(e) = libos.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        let qt: QToken = match result {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        crate::ensure_eq!(wait(libos, qt)?.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        Ok(())
+    }
+
+    /// Pops up to `size` bytes from the connection `qd`, and returns how many were popped.
+    fn tcp_pop(libos: &mut CatloopLibOS, qd: QDesc, size: usize) -> Result<usize> {
+        let qt: QToken = match libos.pop(qd, Some(size)) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        let qr: demi_qresult_t = wait(libos, qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+        if let Err(e) = libos.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        Ok(len)
+    }
+
+    /// Tests if sized pops on a connection return the bytes that are available when there are fewer of them than
+    /// requested, and no more than requested otherwise.
+    #[ignore]
+    #[test]
+    fn tcp_sized_pops() -> Result<()> {
+        const PUSH_SIZE: usize = 100;
+        let listen_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20011);
+        let mut server: CatloopLibOS = CatloopLibOS::new();
+        let mut client: CatloopLibOS = CatloopLibOS::new();
+        server.next_port = 20200;
+
+        // Connection establishment already goes through sized pops of the port number.
+        let listen_qd: QDesc = tcp_listen(&mut server, listen_addr)?;
+        let (server_qd, client_qd): (QDesc, QDesc) = tcp_connect(&mut server, &mut client, listen_qd, listen_addr)?;
+
+        // A pop that asks for more than what is available takes what is there.
+        tcp_push(&mut client, client_qd, PUSH_SIZE)?;
+        crate::ensure_eq!(tcp_pop(&mut server, server_qd, 4096)?, PUSH_SIZE);
+
+        // A pop that asks for less than what is available leaves the rest for the next one.
+        tcp_push(&mut client, client_qd, PUSH_SIZE)?;
+        crate::ensure_eq!(tcp_pop(&mut server, server_qd, 2)?, 2);
+        crate::ensure_eq!(tcp_pop(&mut server, server_qd, PUSH_SIZE)?, PUSH_SIZE - 2);
+
+        for qd in [server_qd, listen_qd] {
+            if let Err(e) = server.close(qd) {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+        }
+        if let Err(e) = client.close(client_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Tests if closing a listening socket cancels a pending accept.
+    #[ignore]
+    #[test]
+    fn tcp_close_cancels_accept() -> Result<()> {
+        let listen_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20009);
+        let mut server: CatloopLibOS = CatloopLibOS::new();
+
+        let listen_qd: QDesc = tcp_listen(&mut server, listen_addr)?;
+        let qt: QToken = match server.accept(listen_qd) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("accept() failed: {:?}", e),
+        };
+        server.poll();
+        if let Err(e) = server.close(listen_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+
+        let qr: demi_qresult_t = wait(&mut server, qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_FAILED);
+        crate::ensure_eq!(qr.qr_ret, libc::ECANCELED as i64);
+        crate::ensure_eq!(server.catmem.borrow().queues().is_empty(), true);
+        crate::ensure_eq!(server.queues().is_empty(), true);
+
+        Ok(())
+    }
+
+    /// Tests if closing a socket cancels a pending connect, and releases the pipes that it opened.
+    #[ignore]
+    #[test]
+    fn tcp_close_cancels_connect() -> Result<()> {
+        let listen_addr: SocketAddrV4 = SocketAddrV4::new(LOCALHOST, 20010);
+        let mut server: CatloopLibOS = CatloopLibOS::new();
+        let mut client: CatloopLibOS = CatloopLibOS::new();
+
+        // The server never accepts, so the connection request goes unanswered.
+        let listen_qd: QDesc = tcp_listen(&mut server, listen_addr)?;
+        let qd: QDesc = match client.socket(libc::AF_INET, libc::SOCK_STREAM, 0) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("socket() failed: {:?}", e),
+        };
+        let qt: QToken = match client.connect(qd, listen_addr) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("connect() failed: {:?}", e),
+        };
+        for _ in 0..16 {
+            client.poll();
+        }
+        if let Err(e) = client.close(qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+
+        let qr: demi_qresult_t = wait(&mut client, qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_FAILED);
+        crate::ensure_eq!(qr.qr_ret, libc::ECANCELED as i64);
+        crate::ensure_eq!(client.catmem.borrow().queues().is_empty(), true);
+
+        if let Err(e) = server.close(listen_qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+        crate::ensure_eq!(server.catmem.borrow().queues().is_empty(), true);
+
+        Ok(())
+    }
+
+    /// Tests if a connect request ack that comes in one byte at a time still establishes the connection. The remote end
+    /// is played by hand, writing the ack straight into the ring of the control duplex pipe.
+    #[ignore]
+    #[test]
+    fn tcp_connect_split_ack() -> Result<()> {
+        let control_port: u16 = 20012;
+        let port: u16 = 20013;
+        let remote: Rc<RefCell<CatmemLibOS>> = Rc::new(RefCell::new(CatmemLibOS::new()));
+        let mut client: CatloopLibOS = CatloopLibOS::new();
+
+        let control_duplex_pipe: DuplexPipe =
+            match DuplexPipe::create_duplex_pipe(remote.clone(), &LOCALHOST, control_port) {
+                Ok(duplex_pipe) => duplex_pipe,
+                Err(e) => anyhow::bail!("create_duplex_pipe() failed: {:?}", e),
+            };
+        let ring: SharedRingBuffer<u16> =
+            match SharedRingBuffer::<u16>::attach(&format!("{}:{}:tx", LOCALHOST, control_port)) {
+                Ok(ring) => ring,
+                Err(e) => anyhow::bail!("attach() failed: {:?}", e),
+            };
+
+        let qd: QDesc = match client.socket(libc::AF_INET, libc::SOCK_STREAM, 0) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("socket() failed: {:?}", e),
+        };
+        let qt: QToken = match client.connect(qd, SocketAddrV4::new(LOCALHOST, control_port)) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("connect() failed: {:?}", e),
+        };
+        let handle: TaskHandle = match client.schedule(qt) {
+            Ok(handle) => handle,
+            Err(e) => anyhow::bail!("schedule() failed: {:?}", e),
+        };
+
+        // Feed the ack one byte at a time, and let the client pop each of them on its own.
+        for byte in port.to_ne_bytes() {
+            for _ in 0..16 {
+                client.poll();
+            }
+            crate::ensure_eq!(handle.has_completed(), false);
+            crate::ensure_eq!(ring.try_enqueue(byte as u16).is_ok(), true);
+        }
+        let duplex_pipe: DuplexPipe = match DuplexPipe::create_duplex_pipe(remote.clone(), &LOCALHOST, port) {
+            Ok(duplex_pipe) => duplex_pipe,
+            Err(e) => anyhow::bail!("create_duplex_pipe() failed: {:?}", e),
+        };
+
+        let qr: demi_qresult_t = wait(&mut client, qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_CONNECT);
+
+        if let Err(e) = client.close(qd) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+        for duplex_pipe in [control_duplex_pipe, duplex_pipe] {
+            if let Err(e) = duplex_pipe.shutdown() {
+                anyhow::bail!("shutdown() failed: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests if data pushed to either socket of a pair is popped from the other one, and if no pipe is left behind once
+    /// both sockets are closed.
+    #[ignore]
+    #[test]
+    fn tcp_socketpair() -> Result<()> {
+        const PUSH_SIZE: usize = 100;
+        let mut libos: CatloopLibOS = CatloopLibOS::new();
+        libos.next_port = 20300;
+
+        let (qd1, qd2): (QDesc, QDesc) = match libos.socketpair() {
+            Ok(qds) => qds,
+            Err(e) => anyhow::bail!("socketpair() failed: {:?}", e),
+        };
+
+        tcp_push(&mut libos, qd1, PUSH_SIZE)?;
+        crate::ensure_eq!(tcp_pop(&mut libos, qd2, 4096)?, PUSH_SIZE);
+        tcp_push(&mut libos, qd2, PUSH_SIZE)?;
+        crate::ensure_eq!(tcp_pop(&mut libos, qd1, 4096)?, PUSH_SIZE);
+
+        for qd in [qd1, qd2] {
+            if let Err(e) = libos.close(qd) {
+                anyhow::bail!("close() failed: {:?}", e);
+            }
+        }
+        crate::ensure_eq!(libos.catmem.borrow().queues().is_empty(), true);
+
+        Ok(())
+    }
+}
@@ -6,14 +6,23 @@
 //======================================================================================================================
 
 use super::{
+    datagram_pipe::DatagramPipe,
     duplex_pipe::DuplexPipe,
     Socket,
 };
 use crate::runtime::{
-    queue::IoQueue,
+    queue::{
+        IoQueue,
+        QueueInfo,
+        QueueState,
+    },
     QType,
 };
-use ::std::rc::Rc;
+use ::std::{
+    cell::Cell,
+    net::SocketAddrV4,
+    rc::Rc,
+};
 
 //======================================================================================================================
 // Structures
@@ -23,7 +32,11 @@ use ::std::rc::Rc;
 pub struct CatloopQueue {
     qtype: QType,
     socket: Socket,
+    local: Option<SocketAddrV4>,
     pipe: Option<Rc<DuplexPipe>>,
+    datagram_pipe: Option<Rc<DatagramPipe>>,
+    // Set once the queue is closed, so that the accept or connect operations that are still running on it give up.
+    cancelled: Rc<Cell<bool>>,
 }
 
 //======================================================================================================================
@@ -35,7 +48,10 @@ impl CatloopQueue {
         Self {
             qtype: qtype,
             socket: Socket::Active(None),
+            local: None,
             pipe: None,
+            datagram_pipe: None,
+            cancelled: Rc::new(Cell::new(false)),
         }
     }
 
@@ -49,6 +65,16 @@ impl CatloopQueue {
         self.socket = socket;
     }
 
+    /// Get local address that this queue is bound to.
+    pub fn get_local(&self) -> Option<SocketAddrV4> {
+        self.local
+    }
+
+    /// Set local address that this queue is bound to.
+    pub fn set_local(&mut self, local: SocketAddrV4) {
+        self.local = Some(local);
+    }
+
     /// Get underlying bi-directional pipe.
     pub fn get_pipe(&self) -> Option<Rc<DuplexPipe>> {
         match &self.pipe {
@@ -61,6 +87,26 @@ impl CatloopQueue {
     pub fn set_pipe(&mut self, pipe: Rc<DuplexPipe>) {
         self.pipe = Some(pipe.clone());
     }
+
+    /// Get underlying datagram pipe.
+    pub fn get_datagram_pipe(&self) -> Option<Rc<DatagramPipe>> {
+        self.datagram_pipe.clone()
+    }
+
+    /// Set underlying datagram pipe.
+    pub fn set_datagram_pipe(&mut self, datagram_pipe: Rc<DatagramPipe>) {
+        self.datagram_pipe = Some(datagram_pipe);
+    }
+
+    /// Get the flag that tells whether the operations running on this queue were cancelled.
+    pub fn get_cancelled(&self) -> Rc<Cell<bool>> {
+        self.cancelled.clone()
+    }
+
+    /// Cancels the accept or connect operations that are still running on this queue.
+    pub fn cancel_pending_ops(&self) {
+        self.cancelled.set(true);
+    }
 }
 
 //======================================================================================================================
@@ -71,4 +117,19 @@ impl IoQueue for CatloopQueue {
     fn get_qtype(&self) -> QType {
         self.qtype
     }
+
+    fn describe(&self, info: &mut QueueInfo) {
+        info.local = self.local;
+        info.state = Some(match self.socket {
+            Socket::Passive(_) => QueueState::Listening,
+            // Connected sockets record their remote address, whereas bound ones record their local address.
+            Socket::Active(Some(remote)) if self.pipe.is_some() => {
+                info.remote = Some(remote);
+                QueueState::Established
+            },
+            Socket::Active(Some(_)) => QueueState::Bound,
+            Socket::Active(None) if self.local.is_some() => QueueState::Bound,
+            Socket::Active(None) => QueueState::Unbound,
+        });
+    }
 }
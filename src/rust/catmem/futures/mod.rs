@@ -6,6 +6,7 @@
 //======================================================================================================================
 
 pub mod close;
+pub mod peek;
 pub mod pop;
 pub mod push;
 
@@ -22,7 +23,8 @@ use crate::runtime::{
 /// Operation Result
 pub enum OperationResult {
     Push,
-    Pop(DemiBuffer),
+    /// Data that was popped, and whether the end of the pipe was reached.
+    Pop(DemiBuffer, bool),
     Close,
     Failed(Fail),
 }
@@ -0,0 +1,84 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    catmem::{
+        futures::pop::peek_items,
+        SharedRingBuffer,
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+    },
+};
+use ::std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{
+        Context,
+        Poll,
+    },
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Peek Operation Descriptor
+pub struct PeekFuture {
+    /// Underlying ring buffer of the pipe.
+    ring: Rc<SharedRingBuffer<u16>>,
+    /// Maximum number of bytes to peek.
+    size: usize,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+/// Associated Functions for Peek Operation Descriptors
+impl PeekFuture {
+    /// Creates a descriptor for a peek operation.
+    pub fn new(ring: Rc<SharedRingBuffer<u16>>, size: usize) -> Self {
+        Self { ring, size }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+/// Debug Trait Implementation for Peek Operation Descriptors
+impl fmt::Debug for PeekFuture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PeekFuture({:?})", self.size)
+    }
+}
+
+/// Future Trait Implementation for Peek Operation Descriptors
+impl Future for PeekFuture {
+    type Output = Result<DemiBuffer, Fail>;
+
+    /// Copies up to `size` bytes from the front of the pipe without removing them, once some data is available. If the
+    /// other end has closed the pipe, this completes with an empty buffer.
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let self_: &mut PeekFuture = self.get_mut();
+        match peek_items(&self_.ring, self_.size) {
+            Some(buf) => {
+                trace!("data peeked ({:?}/{:?} bytes)", buf.len(), self_.size);
+                Poll::Ready(Ok(buf))
+            },
+            None => {
+                // The other end does not notify us when it pushes data, so ask to be polled again.
+                ctx.waker().wake_by_ref();
+                Poll::Pending
+            },
+        }
+    }
+}
@@ -6,7 +6,10 @@
 //======================================================================================================================
 
 use crate::{
-    catmem::SharedRingBuffer,
+    catmem::{
+        SharedRingBuffer,
+        RECORD_HEADER_SIZE,
+    },
     runtime::{
         fail::Fail,
         limits,
@@ -14,20 +17,37 @@ use crate::{
     },
     scheduler::yielder::Yielder,
 };
-use ::std::rc::Rc;
+use ::std::{
+    rc::Rc,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of bytes that a pop stages in an inline array before it allocates a buffer that fits the whole pop. Pops that
+/// receive no more than this, such as small control messages, get a single allocation of the exact size of their data.
+const SMALL_POP_SIZE_MAX: usize = 64;
 
 //======================================================================================================================
 // Standalone Functions
 //======================================================================================================================
 
-/// Polls `try_dequeue()` on `ring` until some data is received and placed in `buf`.
+/// Polls `try_dequeue()` on `ring` until some data is received and placed in `buf`. If a `deadline` is given and it
+/// passes before any data is received, this fails with `ETIMEDOUT`. If `nonblocking` is set and no data is available,
+/// this fails with `EWOULDBLOCK` instead of waiting.
 pub async fn pop_coroutine(
     ring: Rc<SharedRingBuffer<u16>>,
     size: Option<usize>,
+    deadline: Option<Instant>,
+    nonblocking: bool,
     yielder: Yielder,
 ) -> Result<(DemiBuffer, bool), Fail> {
     let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
-    let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
+    // The first bytes go to `small`, and a buffer for `size` bytes is only allocated if more data arrives.
+    let mut small: [u8; SMALL_POP_SIZE_MAX] = [0; SMALL_POP_SIZE_MAX];
+    let mut large: Option<DemiBuffer> = None;
     let mut eof: bool = false;
     let mut index: usize = 0;
     loop {
@@ -35,28 +55,40 @@ pub async fn pop_coroutine(
             Some(x) => {
                 let (high, low): (u8, u8) = (((x >> 8) & 0xff) as u8, (x & 0xff) as u8);
                 if high != 0 {
-                    buf.trim(size - index)
-                        .expect("cannot trim more bytes than the buffer has");
                     eof = true;
                     break;
                 } else {
-                    buf[index] = low;
+                    if index < SMALL_POP_SIZE_MAX {
+                        small[index] = low;
+                    } else {
+                        let buf: &mut DemiBuffer = large.get_or_insert_with(|| {
+                            let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
+                            buf[..SMALL_POP_SIZE_MAX].copy_from_slice(&small);
+                            buf
+                        });
+                        buf[index] = low;
+                    }
                     index += 1;
 
                     // Check if we read enough bytes.
                     if index >= size {
-                        buf.trim(size - index)
-                            .expect("cannot trim more bytes than the buffer has");
                         break;
                     }
                 }
             },
             None => {
                 if index > 0 {
-                    buf.trim(size - index)
-                        .expect("cannot trim more bytes than the buffer has");
                     break;
                 } else {
+                    if nonblocking {
+                        return Err(Fail::new(libc::EWOULDBLOCK, "pop would block"));
+                    }
+                    // Data that is already in the ring wins over an expired deadline, so only check it here.
+                    if let Some(deadline) = deadline {
+                        if Instant::now() >= deadline {
+                            return Err(Fail::new(libc::ETIMEDOUT, "pop timed out"));
+                        }
+                    }
                     // Operation in progress. Check if cancelled.
                     match yielder.yield_once().await {
                         Ok(()) => continue,
@@ -66,6 +98,152 @@ pub async fn pop_coroutine(
             },
         }
     }
+    let buf: DemiBuffer = match large {
+        Some(mut buf) => {
+            // The buffer was allocated for the requested size, of which only `index` bytes may have been filled.
+            buf.trim(size - index)
+                .expect("cannot trim more bytes than the buffer has");
+            buf
+        },
+        None => DemiBuffer::from_slice(&small[..index]).expect("small pops should fit in a buffer"),
+    };
     trace!("data read ({:?}/{:?} bytes, eof={:?})", buf.len(), size, eof);
     Ok((buf, eof))
 }
+
+/// Polls `ring` until some data is available, and then copies up to `size` bytes of it without removing them, so that
+/// a later pop returns the same data. If the other end has closed the pipe, an empty buffer is returned. The end of
+/// file flag is never set, because the end of file marker is left in the ring as well. Deadlines and `nonblocking` are
+/// handled as in [pop_coroutine].
+pub async fn peek_coroutine(
+    ring: Rc<SharedRingBuffer<u16>>,
+    size: Option<usize>,
+    deadline: Option<Instant>,
+    nonblocking: bool,
+    yielder: Yielder,
+) -> Result<(DemiBuffer, bool), Fail> {
+    let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
+    loop {
+        if let Some(buf) = peek_items(&ring, size) {
+            trace!("data peeked ({:?}/{:?} bytes)", buf.len(), size);
+            return Ok((buf, false));
+        }
+        if nonblocking {
+            return Err(Fail::new(libc::EWOULDBLOCK, "pop would block"));
+        }
+        if let Some(deadline) = deadline {
+            if Instant::now() >= deadline {
+                return Err(Fail::new(libc::ETIMEDOUT, "pop timed out"));
+            }
+        }
+        // Operation in progress. Check if cancelled.
+        yielder.yield_once().await?;
+    }
+}
+
+/// Copies up to `size` bytes from the front of `ring` without removing them, stopping at the end of file marker, if
+/// any. Returns `None` if `ring` is empty.
+pub fn peek_items(ring: &SharedRingBuffer<u16>, size: usize) -> Option<DemiBuffer> {
+    let mut items: Vec<u16> = vec![0; usize::min(size, ring.len())];
+    let count: usize = ring.peek_into(&mut items);
+    if count == 0 {
+        return None;
+    }
+
+    // Stop at the end of file marker, if any.
+    let len: usize = items[..count]
+        .iter()
+        .position(|x| (x >> 8) & 0xff != 0)
+        .unwrap_or(count);
+    let mut buf: DemiBuffer = DemiBuffer::new(len as u16);
+    for (byte, item) in buf.iter_mut().zip(&items[..len]) {
+        *byte = (item & 0xff) as u8;
+    }
+    Some(buf)
+}
+
+/// Counts the bytes at the front of `ring` that precede the end of file marker, if any.
+pub fn count_items(ring: &SharedRingBuffer<u16>) -> usize {
+    let mut items: Vec<u16> = vec![0; ring.len()];
+    let count: usize = ring.peek_into(&mut items);
+    items[..count]
+        .iter()
+        .position(|x| (x >> 8) & 0xff != 0)
+        .unwrap_or(count)
+}
+
+/// Returns the length of the record that [push_record_coroutine] wrote at the front of `ring`, or `None` if no whole
+/// record is available yet.
+///
+/// [push_record_coroutine]: super::push::push_record_coroutine
+pub fn peek_record_len(ring: &SharedRingBuffer<u16>) -> Option<usize> {
+    let mut header: [u16; RECORD_HEADER_SIZE] = [0; RECORD_HEADER_SIZE];
+    let count: usize = ring.peek_into(&mut header);
+    if count < RECORD_HEADER_SIZE || header.iter().any(|x| (x >> 8) & 0xff != 0) {
+        return None;
+    }
+    let len: usize = u16::from_be_bytes([header[0] as u8, header[1] as u8]) as usize;
+    if ring.len() >= RECORD_HEADER_SIZE + len {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+/// Polls `ring` until a whole record that was written by [push_record_coroutine] is available, and then removes it.
+/// Only the first `size` bytes of the record are returned, if given, and the remaining ones are discarded. If the other
+/// end has closed the pipe, an empty buffer is returned along with the end of file flag. Deadlines and `nonblocking`
+/// are handled as in [pop_coroutine].
+///
+/// [push_record_coroutine]: super::push::push_record_coroutine
+pub async fn pop_record_coroutine(
+    ring: Rc<SharedRingBuffer<u16>>,
+    size: Option<usize>,
+    deadline: Option<Instant>,
+    nonblocking: bool,
+    yielder: Yielder,
+) -> Result<(DemiBuffer, bool), Fail> {
+    let mut header: [u16; RECORD_HEADER_SIZE] = [0; RECORD_HEADER_SIZE];
+    loop {
+        let count: usize = ring.peek_into(&mut header);
+
+        // Check for the end of file marker, which is never part of a record.
+        if header[..count].iter().any(|x| (x >> 8) & 0xff != 0) {
+            trace!("record read (eof=true)");
+            return Ok((DemiBuffer::new(0), true));
+        }
+
+        // Check if the whole record is available. The writer commits its items one at a time, so we may see part
+        // of it before the rest.
+        if count == RECORD_HEADER_SIZE {
+            let len: usize = u16::from_be_bytes([header[0] as u8, header[1] as u8]) as usize;
+            if ring.len() >= RECORD_HEADER_SIZE + len {
+                break;
+            }
+        }
+
+        if nonblocking {
+            return Err(Fail::new(libc::EWOULDBLOCK, "pop would block"));
+        }
+        if let Some(deadline) = deadline {
+            if Instant::now() >= deadline {
+                return Err(Fail::new(libc::ETIMEDOUT, "pop timed out"));
+            }
+        }
+        // Operation in progress. Check if cancelled.
+        yielder.yield_once().await?;
+    }
+
+    // Remove the record from the ring.
+    let len: usize = u16::from_be_bytes([header[0] as u8, header[1] as u8]) as usize;
+    let size: usize = size.map_or(len, |size| size.min(len));
+    let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
+    for i in 0..(RECORD_HEADER_SIZE + len) {
+        let x: u16 = ring.try_dequeue().expect("ring buffer should hold the whole record");
+        if i >= RECORD_HEADER_SIZE && i - RECORD_HEADER_SIZE < size {
+            buf[i - RECORD_HEADER_SIZE] = (x & 0xff) as u8;
+        }
+    }
+    trace!("record read ({:?}/{:?} bytes)", size, len);
+    Ok((buf, false))
+}
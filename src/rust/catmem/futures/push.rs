@@ -6,7 +6,10 @@
 //======================================================================================================================
 
 use crate::{
-    catmem::SharedRingBuffer,
+    catmem::{
+        SharedRingBuffer,
+        RECORD_HEADER_SIZE,
+    },
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
@@ -19,24 +22,56 @@ use ::std::rc::Rc;
 // Structures
 //======================================================================================================================
 
-/// Polls `try_enqueue()` on `ring` until all the data in the `buf` is sent.
-pub async fn push_coroutine(ring: Rc<SharedRingBuffer<u16>>, buf: DemiBuffer, yielder: Yielder) -> Result<(), Fail> {
+/// Polls `try_enqueue()` on `ring` until all the data in the `buf` is sent. Buffers that are larger than the ring
+/// are transferred in chunks, as the other end drains the ring. If `nonblocking` is set and the ring is full before any
+/// data is sent, this fails with `EWOULDBLOCK` instead of waiting.
+pub async fn push_coroutine(
+    ring: Rc<SharedRingBuffer<u16>>,
+    buf: DemiBuffer,
+    nonblocking: bool,
+    yielder: Yielder,
+) -> Result<(), Fail> {
     let mut index: usize = 0;
-    loop {
-        for low in &buf[index..] {
-            let x: u16 = (low & 0xff) as u16;
-            match ring.try_enqueue(x) {
-                Ok(()) => index += 1,
-                Err(_) => {
-                    // Operation not completed. Check if it was cancelled.
-                    match yielder.yield_once().await {
-                        Ok(()) => continue,
-                        Err(cause) => return Err(cause),
-                    }
-                },
-            }
+    while index < buf.len() {
+        let x: u16 = (buf[index] & 0xff) as u16;
+        match ring.try_enqueue(x) {
+            Ok(()) => index += 1,
+            Err(_) => {
+                if nonblocking && index == 0 {
+                    return Err(Fail::new(libc::EWOULDBLOCK, "push would block"));
+                }
+                // Operation not completed. Check if it was cancelled.
+                // Note that we retry the same byte, so as to not drop data when the ring is full.
+                match yielder.yield_once().await {
+                    Ok(()) => continue,
+                    Err(cause) => return Err(cause),
+                }
+            },
+        }
+    }
+    trace!("data written ({:?}/{:?} bytes)", index, buf.len());
+    Ok(())
+}
+
+/// Writes `buf` to `ring` as a single record: a two-byte length prefix followed by the data. Nothing is written until
+/// the ring has room for the whole record, so records of concurrent pushes never interleave, even if they come from
+/// different writers of a multi-producer ring. If `nonblocking` is set and the ring lacks room, this fails with
+/// `EWOULDBLOCK` instead of waiting.
+pub async fn push_record_coroutine(
+    ring: Rc<SharedRingBuffer<u16>>,
+    buf: DemiBuffer,
+    nonblocking: bool,
+    yielder: Yielder,
+) -> Result<(), Fail> {
+    let header: [u8; RECORD_HEADER_SIZE] = (buf.len() as u16).to_be_bytes();
+    let record: Vec<u16> = header.iter().chain(buf.iter()).map(|byte| *byte as u16).collect();
+    while !ring.try_enqueue_all(&record) {
+        if nonblocking {
+            return Err(Fail::new(libc::EWOULDBLOCK, "push would block"));
         }
-        trace!("data written ({:?}/{:?} bytes)", index, buf.len());
-        return Ok(());
+        // Operation not completed. Check if it was cancelled.
+        yielder.yield_once().await?;
     }
+    trace!("record written ({:?} bytes)", buf.len());
+    Ok(())
 }
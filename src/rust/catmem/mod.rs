@@ -20,10 +20,23 @@ use crate::{
             close_coroutine,
             push_eof,
         },
-        pop::pop_coroutine,
-        push::push_coroutine,
+        peek::PeekFuture,
+        pop::{
+            count_items,
+            peek_coroutine,
+            peek_record_len,
+            pop_coroutine,
+            pop_record_coroutine,
+        },
+        push::{
+            push_coroutine,
+            push_record_coroutine,
+        },
+    },
+    collections::{
+        ring::RingMode,
+        shared_ring::SharedRingBuffer,
     },
-    collections::shared_ring::SharedRingBuffer,
     runtime::{
         fail::Fail,
         limits,
@@ -31,12 +44,17 @@ use crate::{
             DemiBuffer,
             MemoryRuntime,
         },
-        queue::IoQueueTable,
+        network::types::SocketOption,
+        queue::{
+            IoQueueTable,
+            QueueInfo,
+        },
         types::{
             demi_opcode_t,
             demi_qr_value_t,
             demi_qresult_t,
             demi_sgarray_t,
+            DEMI_QR_EOF,
         },
         QDesc,
         QToken,
@@ -58,6 +76,10 @@ use ::std::{
     mem,
     pin::Pin,
     rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 //======================================================================================================================
@@ -69,10 +91,24 @@ use ::std::{
 /// padding. Still, this is intentionally set so as the effective capacity is large enough to hold 16 KB of data.
 const RING_BUFFER_CAPACITY: usize = 65536;
 
+/// Size of the length prefix of records, in bytes.
+const RECORD_HEADER_SIZE: usize = 2;
+
 //======================================================================================================================
 // Types
 //======================================================================================================================
 
+/// Ways of popping data from a pipe.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PopMode {
+    /// Pop data as a byte stream.
+    Stream,
+    /// Pop a single record that was pushed with [CatmemLibOS::push_record].
+    Record,
+    /// Copy data as a byte stream, but leave it in the pipe.
+    Peek,
+}
+
 // TODO: Remove this once we unify return types.
 type Operation = dyn Future<Output = (QDesc, OperationResult)>;
 type OperationTask = TaskWithResult<(QDesc, OperationResult)>;
@@ -112,7 +148,21 @@ impl CatmemLibOS {
         trace!("create_pipe() name={:?}", name);
 
         let ring: SharedRingBuffer<u16> = SharedRingBuffer::<u16>::create(name, RING_BUFFER_CAPACITY)?;
-        let qd: QDesc = self.qtable.borrow_mut().alloc(CatmemQueue::new(ring));
+        let qd: QDesc = self.qtable.borrow_mut().alloc(CatmemQueue::new(ring))?;
+
+        Ok(qd)
+    }
+
+    /// Creates a new memory queue that multiple processes may push to, while a single one pops from it. Each writer's
+    /// data comes out in the order that it went in, but data of different writers interleaves. Records pushed with
+    /// [CatmemLibOS::push_record] are never split up, whereas byte streams may interleave at any byte, and an end of
+    /// stream from any writer ends the stream for the reader.
+    pub fn create_pipe_mpsc(&mut self, name: &str) -> Result<QDesc, Fail> {
+        trace!("create_pipe_mpsc() name={:?}", name);
+
+        let ring: SharedRingBuffer<u16> =
+            SharedRingBuffer::<u16>::create_with_mode(name, RING_BUFFER_CAPACITY, RingMode::MultiProducer)?;
+        let qd: QDesc = self.qtable.borrow_mut().alloc(CatmemQueue::new(ring))?;
 
         Ok(qd)
     }
@@ -122,7 +172,7 @@ impl CatmemLibOS {
         trace!("open_pipe() name={:?}", name);
 
         let ring: SharedRingBuffer<u16> = SharedRingBuffer::<u16>::open(name, RING_BUFFER_CAPACITY)?;
-        let qd: QDesc = self.qtable.borrow_mut().alloc(CatmemQueue::new(ring));
+        let qd: QDesc = self.qtable.borrow_mut().alloc(CatmemQueue::new(ring))?;
 
         Ok(qd)
     }
@@ -147,6 +197,20 @@ impl CatmemLibOS {
         Ok(())
     }
 
+    /// Takes a snapshot of every open queue descriptor.
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        self.qtable.borrow().queues()
+    }
+
+    /// Takes a snapshot of the queue referred to by `qd`.
+    pub fn query_queue(&self, qd: QDesc) -> Result<QueueInfo, Fail> {
+        trace!("query_queue(): qd={:?}", qd);
+        match self.qtable.borrow().query(&qd) {
+            Some(info) => Ok(info),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
     /// Closes a memory queue.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         trace!("close() qd={:?}", qd);
@@ -238,18 +302,69 @@ impl CatmemLibOS {
         }
     }
 
+    /// Sets whether operations on a memory queue that cannot complete right away fail with `EWOULDBLOCK` instead of
+    /// pending, much like `O_NONBLOCK`. Queues are blocking by default.
+    pub fn set_nonblocking(&mut self, qd: QDesc, nonblocking: bool) -> Result<(), Fail> {
+        trace!("set_nonblocking() qd={:?}, nonblocking={:?}", qd, nonblocking);
+        match self.qtable.borrow_mut().get_mut(&qd) {
+            Some(queue) => {
+                queue.set_nonblocking(nonblocking);
+                Ok(())
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("set_nonblocking(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
+    /// Sets an option on a memory queue.
+    pub fn setsockopt(&mut self, qd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        trace!("setsockopt() qd={:?}, option={:?}", qd, option);
+        match self.qtable.borrow_mut().get_mut(&qd) {
+            Some(queue) => {
+                match option {
+                    SocketOption::MaxOpsPerSec(max_ops) => queue.set_max_ops((max_ops > 0).then_some(max_ops)),
+                }
+                Ok(())
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("setsockopt(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
     /// Pushes a scatter-gather array to a socket.
     /// TODO: Enforce semantics on the pipe.
     pub fn push(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
         trace!("push() qd={:?}", qd);
+        self.do_push(qd, sga, false)
+    }
 
+    /// Pushes a scatter-gather array to a pipe as a single record, which a [CatmemLibOS::pop_record] at the other end
+    /// returns as a whole. Records that do not fit in the ring buffer fail with `EMSGSIZE`.
+    pub fn push_record(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        trace!("push_record() qd={:?}", qd);
+        self.do_push(qd, sga, true)
+    }
+
+    /// Schedules a coroutine that pushes data to a pipe, either as a byte stream or as a single record.
+    fn do_push(&mut self, qd: QDesc, sga: &demi_sgarray_t, record: bool) -> Result<QToken, Fail> {
         match self.clone_sgarray(sga) {
             Ok(buf) => {
-                if buf.len() == 0 {
+                if buf.len() == 0 && !record {
                     let cause: String = format!("zero-length buffer (qd={:?})", qd);
                     error!("push(): {}", cause);
                     return Err(Fail::new(libc::EINVAL, &cause));
                 }
+                if record && buf.len() > self.max_record_size(qd)? {
+                    let cause: String = format!("record does not fit in the pipe (qd={:?}, len={:?})", qd, buf.len());
+                    error!("push(): {}", cause);
+                    return Err(Fail::new(libc::EMSGSIZE, &cause));
+                }
 
                 // Issue push operation.
                 match self.qtable.borrow_mut().get_mut(&qd) {
@@ -262,15 +377,21 @@ impl CatmemLibOS {
                         if pipe.eof() {
                             unreachable!("push() called on a closed pipe");
                         }
+                        queue.check_max_ops(demi_opcode_t::DEMI_OPC_PUSH)?;
 
                         // Create co-routine.
                         let ring: Rc<SharedRingBuffer<u16>> = pipe.buffer();
+                        let nonblocking: bool = queue.is_nonblocking();
                         let yielder: Yielder = Yielder::new();
                         let yielder_handle: YielderHandle = yielder.get_handle();
                         let coroutine: Pin<Box<Operation>> = {
                             Box::pin(async move {
                                 // Wait for push to complete.
-                                let result: Result<(), Fail> = push_coroutine(ring, buf, yielder).await;
+                                let result: Result<(), Fail> = if record {
+                                    push_record_coroutine(ring, buf, nonblocking, yielder).await
+                                } else {
+                                    push_coroutine(ring, buf, nonblocking, yielder).await
+                                };
                                 // Handle result.
                                 match result {
                                     Ok(()) => (qd, OperationResult::Push),
@@ -288,7 +409,7 @@ impl CatmemLibOS {
                                 return Err(Fail::new(libc::EAGAIN, &cause));
                             },
                         };
-                        queue.add_pending_op(&handle, &yielder_handle);
+                        queue.add_pending_op(&handle, demi_opcode_t::DEMI_OPC_PUSH, &yielder_handle);
                         let qt: QToken = handle.get_task_id().into();
                         trace!("push() qt={:?}", qt);
                         Ok(qt)
@@ -308,15 +429,55 @@ impl CatmemLibOS {
     /// TODO: Enforce semantics on the pipe.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         trace!("pop() qd={:?}, size={:?}", qd, size);
+        self.do_pop(qd, size, None, PopMode::Stream)
+    }
+
+    /// Pops data from a pipe without removing it, as `MSG_PEEK` does, so that the next pop returns the same bytes.
+    pub fn pop_peek(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        trace!("pop_peek() qd={:?}, size={:?}", qd, size);
+        self.do_pop(qd, size, None, PopMode::Peek)
+    }
+
+    /// Pops data from a pipe, failing with `ETIMEDOUT` if no data arrives within `timeout`.
+    pub fn pop_timeout(&mut self, qd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("pop_timeout() qd={:?}, size={:?}, timeout={:?}", qd, size, timeout);
+        self.do_pop(qd, size, Some(Instant::now() + timeout), PopMode::Stream)
+    }
 
+    /// Pops a single record that was pushed with [CatmemLibOS::push_record] from a pipe, failing with `ETIMEDOUT` if
+    /// none arrives within `timeout`, if given. Only the first `size` bytes of the record are returned, if given.
+    pub fn pop_record(&mut self, qd: QDesc, size: Option<usize>, timeout: Option<Duration>) -> Result<QToken, Fail> {
+        trace!("pop_record() qd={:?}, size={:?}, timeout={:?}", qd, size, timeout);
+        self.do_pop(
+            qd,
+            size,
+            timeout.map(|timeout| Instant::now() + timeout),
+            PopMode::Record,
+        )
+    }
+
+    /// Schedules a coroutine that pops data from a pipe in the given `mode`, with an optional deadline.
+    fn do_pop(
+        &mut self,
+        qd: QDesc,
+        size: Option<usize>,
+        deadline: Option<Instant>,
+        mode: PopMode,
+    ) -> Result<QToken, Fail> {
         // We just assert 'size' here, because it was previously checked at PDPIX layer.
-        debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
+        debug_assert!(
+            mode == PopMode::Record
+                || size.is_none()
+                || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX))
+        );
 
         // Issue pop operation.
         match self.qtable.borrow_mut().get_mut(&qd) {
             Some(queue) => {
+                queue.check_max_ops(demi_opcode_t::DEMI_OPC_POP)?;
                 let pipe: &Pipe = queue.get_pipe();
                 let ring: Rc<SharedRingBuffer<u16>> = pipe.buffer();
+                let nonblocking: bool = queue.is_nonblocking();
                 let yielder: Yielder = Yielder::new();
                 let yielder_handle: YielderHandle = yielder.get_handle();
                 let coroutine: Pin<Box<Operation>> = if pipe.eof() {
@@ -330,7 +491,11 @@ impl CatmemLibOS {
                     let qtable_ptr: Rc<RefCell<IoQueueTable<CatmemQueue>>> = self.qtable.clone();
                     Box::pin(async move {
                         // Wait for pop to complete.
-                        let result: Result<(DemiBuffer, bool), Fail> = pop_coroutine(ring, size, yielder).await;
+                        let result: Result<(DemiBuffer, bool), Fail> = match mode {
+                            PopMode::Stream => pop_coroutine(ring, size, deadline, nonblocking, yielder).await,
+                            PopMode::Record => pop_record_coroutine(ring, size, deadline, nonblocking, yielder).await,
+                            PopMode::Peek => peek_coroutine(ring, size, deadline, nonblocking, yielder).await,
+                        };
                         // Process the result.
                         match result {
                             Ok((buf, eof)) => {
@@ -347,7 +512,7 @@ impl CatmemLibOS {
                                     let pipe: &mut Pipe = queue.get_mut_pipe();
                                     pipe.set_eof();
                                 }
-                                (qd, OperationResult::Pop(buf))
+                                (qd, OperationResult::Pop(buf, eof))
                             },
                             Err(e) => (qd, OperationResult::Failed(e)),
                         }
@@ -364,7 +529,7 @@ impl CatmemLibOS {
                         return Err(Fail::new(libc::EAGAIN, &cause));
                     },
                 };
-                queue.add_pending_op(&handle, &yielder_handle);
+                queue.add_pending_op(&handle, demi_opcode_t::DEMI_OPC_POP, &yielder_handle);
                 let qt: QToken = handle.get_task_id().into();
                 trace!("pop() qt={:?}", qt);
                 Ok(qt)
@@ -377,6 +542,109 @@ impl CatmemLibOS {
         }
     }
 
+    /// Reads up to `size` bytes from a pipe without removing them, so that a later pop returns the same data. The
+    /// returned future completes once some data is available, and with an empty buffer if the other end has closed the
+    /// pipe.
+    pub fn peek(&self, qd: QDesc, size: usize) -> Result<PeekFuture, Fail> {
+        trace!("peek() qd={:?}, size={:?}", qd, size);
+
+        // Check if size is valid.
+        if size == 0 || size > limits::POP_SIZE_MAX {
+            let cause: String = format!("invalid peek size (size={:?})", size);
+            error!("peek(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => {
+                let pipe: &Pipe = queue.get_pipe();
+                if pipe.eof() {
+                    let cause: String = format!("connection reset (qd={:?})", qd);
+                    error!("peek(): {}", cause);
+                    return Err(Fail::new(libc::ECONNRESET, &cause));
+                }
+                Ok(PeekFuture::new(pipe.buffer(), size))
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("peek(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
+    /// Returns the number of bytes that may be popped right away from a memory queue.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("bytes_available() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => Ok(count_items(&queue.get_pipe().buffer())),
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("bytes_available(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
+    /// Returns the number of bytes in the next record that may be popped right away from a memory queue, or zero if no
+    /// whole record is available yet.
+    pub fn record_bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("record_bytes_available() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => Ok(peek_record_len(&queue.get_pipe().buffer()).unwrap_or(0)),
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("record_bytes_available(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single push transfers as a unit on a memory queue. Larger pushes
+    /// are accepted and split across multiple ring buffer operations.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("max_push_size() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(_) => Ok(limits::RECVBUF_SIZE_MAX),
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("max_push_size(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single record may carry on a memory queue, which is bounded by the
+    /// capacity of the underlying ring buffer.
+    pub fn max_record_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("max_record_size() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => {
+                let capacity: usize = queue.get_pipe().buffer().capacity() - RECORD_HEADER_SIZE;
+                Ok(capacity.min(u16::MAX as usize))
+            },
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("max_record_size(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
+    /// Forces transmission of all data that is buffered on a memory queue. Pushed data is written to the underlying
+    /// ring buffer as soon as possible, thus this only checks if `qd` refers to a valid memory queue.
+    pub fn flush(&self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(_) => Ok(()),
+            None => {
+                let cause: String = format!("invalid queue descriptor (qd={:?})", qd);
+                error!("flush(): {}", cause);
+                Err(Fail::new(libc::EBADF, &cause))
+            },
+        }
+    }
+
     /// Allocates a scatter-gather array.
     pub fn alloc_sgarray(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
         MemoryRuntime::alloc_sgarray(self, size)
@@ -424,8 +692,12 @@ impl CatmemLibOS {
                 qr_qt: qt.into(),
                 qr_ret: 0,
                 qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             },
-            OperationResult::Pop(bytes) => match self.into_sgarray(bytes) {
+            OperationResult::Pop(bytes, eof) => match self.into_sgarray(bytes) {
                 Ok(sga) => {
                     let qr_value: demi_qr_value_t = demi_qr_value_t { sga };
                     demi_qresult_t {
@@ -434,6 +706,10 @@ impl CatmemLibOS {
                         qr_qt: qt.into(),
                         qr_ret: 0,
                         qr_value,
+                        qr_flags: if eof { DEMI_QR_EOF } else { 0 },
+                        qr_start_ns: 0,
+                        qr_end_ns: 0,
+                        qr_rx_ns: 0,
                     }
                 },
                 Err(e) => {
@@ -444,6 +720,10 @@ impl CatmemLibOS {
                         qr_qt: qt.into(),
                         qr_ret: e.errno as i64,
                         qr_value: unsafe { mem::zeroed() },
+                        qr_flags: 0,
+                        qr_start_ns: 0,
+                        qr_end_ns: 0,
+                        qr_rx_ns: 0,
                     }
                 },
             },
@@ -453,6 +733,10 @@ impl CatmemLibOS {
                 qr_qt: qt.into(),
                 qr_ret: 0,
                 qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             },
             OperationResult::Failed(e) => {
                 warn!("Operation Failed: {:?}", e);
@@ -462,6 +746,10 @@ impl CatmemLibOS {
                     qr_qt: qt.into(),
                     qr_ret: e.errno as i64,
                     qr_value: unsafe { mem::zeroed() },
+                    qr_flags: 0,
+                    qr_start_ns: 0,
+                    qr_end_ns: 0,
+                    qr_rx_ns: 0,
                 }
             },
         };
@@ -472,3 +760,765 @@ impl CatmemLibOS {
         self.scheduler.poll()
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod test {
+    use super::{
+        futures::{
+            peek::PeekFuture,
+            pop::pop_coroutine,
+        },
+        CatmemLibOS,
+        SocketOption,
+        RING_BUFFER_CAPACITY,
+    };
+    use crate::{
+        collections::shared_ring::SharedRingBuffer,
+        runtime::{
+            limits,
+            memory::{
+                counting_allocator::{
+                    num_allocated_bytes,
+                    num_allocations,
+                },
+                DemiBuffer,
+                MemoryRuntime,
+            },
+            types::{
+                demi_opcode_t,
+                demi_qresult_t,
+                demi_sgarray_t,
+            },
+            QDesc,
+            QToken,
+        },
+        scheduler::{
+            TaskHandle,
+            Yielder,
+        },
+    };
+    use ::anyhow::Result;
+    use ::futures::task::noop_waker_ref;
+    use ::std::{
+        future::Future,
+        pin::{
+            pin,
+            Pin,
+        },
+        rc::Rc,
+        task::{
+            Context,
+            Poll,
+        },
+    };
+
+    /// Polls the target LibOS until the operation associated with `qt` completes.
+    fn wait(libos: &mut CatmemLibOS, qt: QToken) -> Result<demi_qresult_t> {
+        let handle = match libos.schedule(qt) {
+            Ok(handle) => handle,
+            Err(e) => anyhow::bail!("schedule() failed: {:?}", e),
+        };
+        while !handle.has_completed() {
+            libos.poll();
+        }
+        match libos.pack_result(handle, qt) {
+            Ok(qr) => Ok(qr),
+            Err(e) => anyhow::bail!("pack_result() failed: {:?}", e),
+        }
+    }
+
+    /// Pushes `size` bytes to `tx` and pops them back from `rx`.
+    fn push_pop(libos: &mut CatmemLibOS, tx: QDesc, rx: QDesc, size: usize) -> Result<()> {
+        // Push data.
+        let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
+        for (i, x) in buf.as_bytes_mut().iter_mut().enumerate() {
+            *x = (i % 251) as u8;
+        }
+        let sga: demi_sgarray_t = match libos.into_sgarray(buf) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("into_sgarray() failed: {:?}", e),
+        };
+        let push_qt: QToken = match libos.push(tx, &sga) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+
+        // Pop data until we get all of it back.
+        let mut nbytes: usize = 0;
+        while nbytes < size {
+            let pop_qt: QToken = match libos.pop(rx, None) {
+                Ok(qt) => qt,
+                Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+            };
+            let qr: demi_qresult_t = wait(libos, pop_qt)?;
+            crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+            let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+            let buf: DemiBuffer = match libos.clone_sgarray(&sga) {
+                Ok(buf) => buf,
+                Err(e) => anyhow::bail!("clone_sgarray() failed: {:?}", e),
+            };
+            let len: usize = buf.len();
+            crate::ensure_eq!(len <= limits::RECVBUF_SIZE_MAX, true);
+            for (i, x) in buf.as_bytes().iter().enumerate() {
+                crate::ensure_eq!(*x, ((nbytes + i) % 251) as u8);
+            }
+            nbytes += len;
+            if let Err(e) = libos.free_sgarray(sga) {
+                anyhow::bail!("free_sgarray() failed: {:?}", e);
+            }
+        }
+        crate::ensure_eq!(nbytes, size);
+
+        // The push should have completed by now.
+        let qr: demi_qresult_t = wait(libos, push_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+
+        Ok(())
+    }
+
+    /// Tests if pushes around and above the maximum push size are transferred without loss.
+    #[ignore]
+    #[test]
+    fn push_size_limits() -> Result<()> {
+        let mut libos: CatmemLibOS = CatmemLibOS::new();
+        let name: &str = "shm-test-catmem-push-size-limits";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+
+        let max_push_size: usize = match libos.max_push_size(tx) {
+            Ok(size) => size,
+            Err(e) => anyhow::bail!("max_push_size() failed: {:?}", e),
+        };
+        for size in [max_push_size - 1, max_push_size, max_push_size + 1] {
+            push_pop(&mut libos, tx, rx, size)?;
+        }
+
+        // Pushes that are larger than the effective capacity of the underlying ring buffer should be chunked.
+        push_pop(&mut libos, tx, rx, u16::MAX as usize)?;
+
+        Ok(())
+    }
+
+    /// Tests if a pop with no data available fails right away in nonblocking mode, and pends in blocking mode.
+    #[ignore]
+    #[test]
+    fn nonblocking_pop() -> Result<()> {
+        let mut libos: CatmemLibOS = CatmemLibOS::new();
+        let name: &str = "shm-test-catmem-nonblocking-pop";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+
+        // Nonblocking mode: the pop should fail right away.
+        if let Err(e) = libos.set_nonblocking(rx, true) {
+            anyhow::bail!("set_nonblocking() failed: {:?}", e);
+        }
+        let pop_qt: QToken = match libos.pop(rx, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        let qr: demi_qresult_t = wait(&mut libos, pop_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_FAILED);
+        crate::ensure_eq!(qr.qr_ret, libc::EWOULDBLOCK as i64);
+
+        // Blocking mode: the pop should pend until data arrives.
+        if let Err(e) = libos.set_nonblocking(rx, false) {
+            anyhow::bail!("set_nonblocking() failed: {:?}", e);
+        }
+        let pop_qt: QToken = match libos.pop(rx, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        let handle: TaskHandle = match libos.schedule(pop_qt) {
+            Ok(handle) => handle,
+            Err(e) => anyhow::bail!("schedule() failed: {:?}", e),
+        };
+        for _ in 0..16 {
+            libos.poll();
+        }
+        crate::ensure_eq!(handle.has_completed(), false);
+        push_pop_pending(&mut libos, tx, pop_qt)?;
+
+        Ok(())
+    }
+
+    /// Tests if peeking a pipe returns its leading bytes without removing them.
+    #[test]
+    fn peek_then_pop() -> Result<()> {
+        let mut libos: CatmemLibOS = CatmemLibOS::new();
+        let name: &str = "shm-test-catmem-peek-then-pop";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+
+        // Peeking an empty pipe should wait for data.
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+        let mut peek: PeekFuture = match libos.peek(rx, 4) {
+            Ok(peek) => peek,
+            Err(e) => anyhow::bail!("peek() failed: {:?}", e),
+        };
+        crate::ensure_eq!(Pin::new(&mut peek).poll(&mut ctx).is_pending(), true);
+
+        // Push a message.
+        const SIZE: usize = 16;
+        let mut buf: DemiBuffer = DemiBuffer::new(SIZE as u16);
+        for (i, x) in buf.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+        let sga: demi_sgarray_t = match libos.into_sgarray(buf) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("into_sgarray() failed: {:?}", e),
+        };
+        let push_qt: QToken = match libos.push(tx, &sga) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+        let qr: demi_qresult_t = wait(&mut libos, push_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+
+        // The pending peek completes with the header, and so does a second one.
+        match Pin::new(&mut peek).poll(&mut ctx) {
+            Poll::Ready(Ok(buf)) => crate::ensure_eq!(&buf[..], &[0, 1, 2, 3]),
+            _ => anyhow::bail!("peek should complete once data is available"),
+        }
+        let mut peek: PeekFuture = match libos.peek(rx, 4) {
+            Ok(peek) => peek,
+            Err(e) => anyhow::bail!("peek() failed: {:?}", e),
+        };
+        match Pin::new(&mut peek).poll(&mut ctx) {
+            Poll::Ready(Ok(buf)) => crate::ensure_eq!(&buf[..], &[0, 1, 2, 3]),
+            _ => anyhow::bail!("peek should complete once data is available"),
+        }
+
+        // The ring still holds all data.
+        crate::ensure_eq!(libos.bytes_available(rx)?, SIZE);
+
+        // Pop the full message.
+        let pop_qt: QToken = match libos.pop(rx, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        let qr: demi_qresult_t = wait(&mut libos, pop_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        let buf: DemiBuffer = match libos.clone_sgarray(&sga) {
+            Ok(buf) => buf,
+            Err(e) => anyhow::bail!("clone_sgarray() failed: {:?}", e),
+        };
+        crate::ensure_eq!(buf.len(), SIZE);
+        for (i, x) in buf.iter().enumerate() {
+            crate::ensure_eq!(*x, i as u8);
+        }
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Tests if the bytes available on a pipe follow the data that is pushed and popped, and leave out the end of file.
+    #[ignore]
+    #[test]
+    fn bytes_available() -> Result<()> {
+        let mut libos: CatmemLibOS = CatmemLibOS::new();
+        let name: &str = "shm-test-catmem-bytes-available";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+        crate::ensure_eq!(libos.bytes_available(rx)?, 0);
+
+        // Push a message.
+        const SIZE: usize = 16;
+        let sga: demi_sgarray_t = match libos.into_sgarray(DemiBuffer::new(SIZE as u16)) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("into_sgarray() failed: {:?}", e),
+        };
+        let push_qt: QToken = match libos.push(tx, &sga) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+        let qr: demi_qresult_t = wait(&mut libos, push_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        crate::ensure_eq!(libos.bytes_available(rx)?, SIZE);
+
+        // Pop part of the message, and then the rest of it.
+        for (size, remaining) in [(4, SIZE - 4), (SIZE, 0)] {
+            let pop_qt: QToken = match libos.pop(rx, Some(size)) {
+                Ok(qt) => qt,
+                Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+            };
+            let qr: demi_qresult_t = wait(&mut libos, pop_qt)?;
+            crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+            if let Err(e) = libos.free_sgarray(unsafe { qr.qr_value.sga }) {
+                anyhow::bail!("free_sgarray() failed: {:?}", e);
+            }
+            crate::ensure_eq!(libos.bytes_available(rx)?, remaining);
+        }
+
+        // The end of file marker is not data.
+        if let Err(e) = libos.close(tx) {
+            anyhow::bail!("close() failed: {:?}", e);
+        }
+        crate::ensure_eq!(libos.bytes_available(rx)?, 0);
+
+        Ok(())
+    }
+
+    /// Tests if a peeking pop completes with the leading bytes of a pipe, and leaves them for the next pop.
+    #[ignore]
+    #[test]
+    fn pop_peek_then_pop() -> Result<()> {
+        let mut libos: CatmemLibOS = CatmemLibOS::new();
+        let name: &str = "shm-test-catmem-pop-peek-then-pop";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+
+        // Issue a peeking pop before any data arrives.
+        let peek_qt: QToken = match libos.pop_peek(rx, Some(4)) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop_peek() failed: {:?}", e),
+        };
+
+        // Push a message.
+        const SIZE: usize = 16;
+        let mut buf: DemiBuffer = DemiBuffer::new(SIZE as u16);
+        for (i, x) in buf.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+        let sga: demi_sgarray_t = match libos.into_sgarray(buf) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("into_sgarray() failed: {:?}", e),
+        };
+        let push_qt: QToken = match libos.push(tx, &sga) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+        let qr: demi_qresult_t = wait(&mut libos, push_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+
+        // Peek at the header twice, and then pop the full message.
+        let mut qts: Vec<QToken> = vec![peek_qt];
+        qts.push(match libos.pop_peek(rx, Some(4)) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop_peek() failed: {:?}", e),
+        });
+        qts.push(match libos.pop(rx, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        });
+        let mut expected: Vec<Vec<u8>> = vec![vec![0, 1, 2, 3]; 2];
+        expected.push((0..SIZE as u8).collect());
+        for (qt, expected) in qts.into_iter().zip(expected) {
+            let qr: demi_qresult_t = wait(&mut libos, qt)?;
+            crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+            let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+            let buf: DemiBuffer = match libos.clone_sgarray(&sga) {
+                Ok(buf) => buf,
+                Err(e) => anyhow::bail!("clone_sgarray() failed: {:?}", e),
+            };
+            crate::ensure_eq!(&buf[..], &expected[..]);
+            if let Err(e) = libos.free_sgarray(sga) {
+                anyhow::bail!("free_sgarray() failed: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tests if records keep their boundaries, are truncated to the pop size, and are rejected if they are too large.
+    #[ignore]
+    #[test]
+    fn push_pop_records() -> Result<()> {
+        let mut libos: CatmemLibOS = CatmemLibOS::new();
+        let name: &str = "shm-test-catmem-push-pop-records";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+
+        // Push records back to back, including an empty one. The last one fills the whole ring buffer, thus it only
+        // completes once the others are popped.
+        let mut pending_qt: Option<QToken> = None;
+        let max_record_size: usize = match libos.max_record_size(tx) {
+            Ok(size) => size,
+            Err(e) => anyhow::bail!("max_record_size() failed: {:?}", e),
+        };
+        let sizes: [usize; 4] = [8, 0, 3, max_record_size];
+        for size in sizes {
+            let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
+            for (i, x) in buf.iter_mut().enumerate() {
+                *x = (i % 251) as u8;
+            }
+            let sga: demi_sgarray_t = match libos.into_sgarray(buf) {
+                Ok(sga) => sga,
+                Err(e) => anyhow::bail!("into_sgarray() failed: {:?}", e),
+            };
+            let push_qt: QToken = match libos.push_record(tx, &sga) {
+                Ok(qt) => qt,
+                Err(e) => anyhow::bail!("push_record() failed: {:?}", e),
+            };
+            if size < max_record_size {
+                let qr: demi_qresult_t = wait(&mut libos, push_qt)?;
+                crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+            } else {
+                pending_qt = Some(push_qt);
+            }
+            if let Err(e) = libos.free_sgarray(sga) {
+                anyhow::bail!("free_sgarray() failed: {:?}", e);
+            }
+        }
+
+        // Each pop should return exactly one record, truncated to the pop size.
+        for (size, expected) in [(None, 8), (None, 0), (Some(2), 2), (None, max_record_size)] {
+            let pop_qt: QToken = match libos.pop_record(rx, size, None) {
+                Ok(qt) => qt,
+                Err(e) => anyhow::bail!("pop_record() failed: {:?}", e),
+            };
+            let qr: demi_qresult_t = wait(&mut libos, pop_qt)?;
+            crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+            let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+            let buf: DemiBuffer = match libos.clone_sgarray(&sga) {
+                Ok(buf) => buf,
+                Err(e) => anyhow::bail!("clone_sgarray() failed: {:?}", e),
+            };
+            crate::ensure_eq!(buf.len(), expected);
+            for (i, x) in buf.iter().enumerate() {
+                crate::ensure_eq!(*x, (i % 251) as u8);
+            }
+            if let Err(e) = libos.free_sgarray(sga) {
+                anyhow::bail!("free_sgarray() failed: {:?}", e);
+            }
+        }
+        if let Some(push_qt) = pending_qt {
+            let qr: demi_qresult_t = wait(&mut libos, push_qt)?;
+            crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        }
+
+        // Records that do not fit in the ring buffer should be rejected.
+        let sga: demi_sgarray_t = match libos.alloc_sgarray(max_record_size + 1) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("alloc_sgarray() failed: {:?}", e),
+        };
+        match libos.push_record(tx, &sga) {
+            Err(e) if e.errno == libc::EMSGSIZE => {},
+            _ => anyhow::bail!("push_record() of an oversized record should fail with EMSGSIZE"),
+        }
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Tests if records that two writers push to a multi-producer pipe all reach the reader whole.
+    #[ignore]
+    #[test]
+    fn push_pop_records_multi_producer() -> Result<()> {
+        const RECORD_SIZE: usize = 1000;
+        const NRECORDS: usize = 8;
+        let mut libos: CatmemLibOS = CatmemLibOS::new();
+        let name: &str = "shm-test-catmem-push-pop-records-multi-producer";
+        let rx: QDesc = match libos.create_pipe_mpsc(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe_mpsc() failed: {:?}", e),
+        };
+        let mut writers: Vec<QDesc> = Vec::new();
+        for _ in 0..2 {
+            match libos.open_pipe(name) {
+                Ok(qd) => writers.push(qd),
+                Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+            }
+        }
+
+        // Push records from both writers at once, each filled with the number of its writer.
+        let mut push_qts: Vec<QToken> = Vec::new();
+        for i in 0..NRECORDS {
+            let mut buf: DemiBuffer = DemiBuffer::new(RECORD_SIZE as u16);
+            buf.fill((i % writers.len()) as u8);
+            let sga: demi_sgarray_t = match libos.into_sgarray(buf) {
+                Ok(sga) => sga,
+                Err(e) => anyhow::bail!("into_sgarray() failed: {:?}", e),
+            };
+            match libos.push_record(writers[i % writers.len()], &sga) {
+                Ok(qt) => push_qts.push(qt),
+                Err(e) => anyhow::bail!("push_record() failed: {:?}", e),
+            }
+            if let Err(e) = libos.free_sgarray(sga) {
+                anyhow::bail!("free_sgarray() failed: {:?}", e);
+            }
+        }
+        for push_qt in push_qts {
+            let qr: demi_qresult_t = wait(&mut libos, push_qt)?;
+            crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        }
+
+        // Each record should come out whole, and as many records as were pushed should come out of each writer.
+        let mut counts: [usize; 2] = [0; 2];
+        for _ in 0..NRECORDS {
+            let pop_qt: QToken = match libos.pop_record(rx, None, None) {
+                Ok(qt) => qt,
+                Err(e) => anyhow::bail!("pop_record() failed: {:?}", e),
+            };
+            let qr: demi_qresult_t = wait(&mut libos, pop_qt)?;
+            crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+            let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+            let buf: DemiBuffer = match libos.clone_sgarray(&sga) {
+                Ok(buf) => buf,
+                Err(e) => anyhow::bail!("clone_sgarray() failed: {:?}", e),
+            };
+            crate::ensure_eq!(buf.len(), RECORD_SIZE);
+            let writer: u8 = buf[0];
+            crate::ensure_eq!(buf.iter().all(|x| *x == writer), true);
+            counts[writer as usize] += 1;
+            if let Err(e) = libos.free_sgarray(sga) {
+                anyhow::bail!("free_sgarray() failed: {:?}", e);
+            }
+        }
+        crate::ensure_eq!(counts, [NRECORDS / 2; 2]);
+
+        Ok(())
+    }
+
+    /// Pushes one byte to `tx` and waits for the pending pop associated with `pop_qt` to get it.
+    fn push_pop_pending(libos: &mut CatmemLibOS, tx: QDesc, pop_qt: QToken) -> Result<()> {
+        let sga: demi_sgarray_t = match libos.alloc_sgarray(1) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("alloc_sgarray() failed: {:?}", e),
+        };
+        let push_qt: QToken = match libos.push(tx, &sga) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+        let qr: demi_qresult_t = wait(libos, push_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        let qr: demi_qresult_t = wait(libos, pop_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+        crate::ensure_eq!(len, 1);
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Tests if small pops allocate a buffer that fits their data, rather than one that fits the largest pop.
+    #[ignore]
+    #[test]
+    fn small_pops_allocate_little() -> Result<()> {
+        const NUM_POPS: usize = 1024;
+        const MSG_SIZE: usize = 8;
+        let ring: Rc<SharedRingBuffer<u16>> =
+            match SharedRingBuffer::<u16>::create("shm-test-catmem-small-pops", RING_BUFFER_CAPACITY) {
+                Ok(ring) => Rc::new(ring),
+                Err(e) => anyhow::bail!("create() failed: {:?}", e),
+            };
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+
+        // Create yielders beforehand, as they allocate.
+        let mut yielders: Vec<Yielder> = (0..NUM_POPS).map(|_| Yielder::new()).collect();
+
+        let num_allocations_before: usize = num_allocations();
+        let num_allocated_bytes_before: usize = num_allocated_bytes();
+        for yielder in yielders.drain(..) {
+            for i in 0..MSG_SIZE {
+                crate::ensure_eq!(ring.try_enqueue(i as u16).is_ok(), true);
+            }
+            let mut coroutine = pin!(pop_coroutine(ring.clone(), None, None, true, yielder));
+            match Future::poll(coroutine.as_mut(), &mut ctx) {
+                Poll::Ready(Ok((buf, eof))) => {
+                    crate::ensure_eq!(eof, false);
+                    crate::ensure_eq!(buf.len(), MSG_SIZE);
+                    for (i, x) in buf.as_bytes().iter().enumerate() {
+                        crate::ensure_eq!(*x, i as u8);
+                    }
+                },
+                Poll::Ready(Err(e)) => anyhow::bail!("pop failed: {:?}", e),
+                Poll::Pending => anyhow::bail!("pop should have completed"),
+            }
+        }
+
+        // Each pop should allocate once, and a lot less than the largest pop would take.
+        crate::ensure_eq!(num_allocations() - num_allocations_before, NUM_POPS);
+        crate::ensure_eq!(
+            num_allocated_bytes() - num_allocated_bytes_before < NUM_POPS * limits::RECVBUF_SIZE_MAX / 16,
+            true
+        );
+
+        Ok(())
+    }
+
+    /// Tests if sized pops that drain a partially filled ring return the bytes that were there, whether they fit in
+    /// the inline array or not, and if pops that fill up their size exactly return all of it.
+    #[ignore]
+    #[test]
+    fn sized_pops_drain_partially_filled_ring() -> Result<()> {
+        let ring: Rc<SharedRingBuffer<u16>> =
+            match SharedRingBuffer::<u16>::create("shm-test-catmem-sized-pops", RING_BUFFER_CAPACITY) {
+                Ok(ring) => Rc::new(ring),
+                Err(e) => anyhow::bail!("create() failed: {:?}", e),
+            };
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+
+        // (requested size, bytes in the ring, bytes expected back)
+        let cases: [(usize, usize, usize); 6] = [
+            (2, 1, 1),
+            (2, 2, 2),
+            (100, 10, 10),
+            (1024, 100, 100),
+            (1024, 1024, 1024),
+            (limits::POP_SIZE_MAX, limits::POP_SIZE_MAX - 1, limits::POP_SIZE_MAX - 1),
+        ];
+        for (size, available, expected) in cases {
+            for i in 0..available {
+                crate::ensure_eq!(ring.try_enqueue((i % 251) as u16).is_ok(), true);
+            }
+            let mut coroutine = pin!(pop_coroutine(ring.clone(), Some(size), None, true, Yielder::new()));
+            match Future::poll(coroutine.as_mut(), &mut ctx) {
+                Poll::Ready(Ok((buf, eof))) => {
+                    crate::ensure_eq!(eof, false);
+                    crate::ensure_eq!(buf.len(), expected);
+                    for (i, x) in buf.as_bytes().iter().enumerate() {
+                        crate::ensure_eq!(*x, (i % 251) as u8);
+                    }
+                },
+                Poll::Ready(Err(e)) => anyhow::bail!("pop failed: {:?}", e),
+                Poll::Pending => anyhow::bail!("pop should have completed"),
+            }
+            crate::ensure_eq!(ring.is_empty(), true);
+        }
+
+        // A sized pop leaves the bytes past its size in the ring.
+        for i in 0..10 {
+            crate::ensure_eq!(ring.try_enqueue(i as u16).is_ok(), true);
+        }
+        let mut coroutine = pin!(pop_coroutine(ring.clone(), Some(4), None, true, Yielder::new()));
+        match Future::poll(coroutine.as_mut(), &mut ctx) {
+            Poll::Ready(Ok((buf, _))) => crate::ensure_eq!(buf.as_bytes(), &[0, 1, 2, 3]),
+            Poll::Ready(Err(e)) => anyhow::bail!("pop failed: {:?}", e),
+            Poll::Pending => anyhow::bail!("pop should have completed"),
+        }
+        crate::ensure_eq!(ring.len(), 6);
+
+        Ok(())
+    }
+
+    /// Tests if operations past the limit of pending operations on a memory queue fail with `EBUSY` right away, and
+    /// if operations of other types are not held back by that limit.
+    #[ignore]
+    #[test]
+    fn max_pending_ops() -> Result<()> {
+        let mut libos: CatmemLibOS = CatmemLibOS::new();
+        let name: &str = "shm-test-catmem-max-pending-ops";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+        if let Err(e) = libos.setsockopt(rx, SocketOption::MaxOpsPerSec(2)) {
+            anyhow::bail!("setsockopt() failed: {:?}", e);
+        }
+
+        // Only the first two pops should be accepted.
+        let mut pop_qts: Vec<QToken> = Vec::new();
+        let mut nbusy: usize = 0;
+        for _ in 0..5 {
+            match libos.pop(rx, None) {
+                Ok(qt) => pop_qts.push(qt),
+                Err(e) if e.errno == libc::EBUSY => nbusy += 1,
+                Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+            }
+        }
+        crate::ensure_eq!(pop_qts.len(), 2);
+        crate::ensure_eq!(nbusy, 3);
+
+        // Once a pop completes and its result is taken, another one should be accepted.
+        let handles: Vec<TaskHandle> = pop_qts
+            .iter()
+            .map(|qt| libos.schedule(*qt))
+            .collect::<Result<Vec<TaskHandle>, _>>()
+            .map_err(|e| anyhow::anyhow!("schedule() failed: {:?}", e))?;
+        let sga: demi_sgarray_t = match libos.alloc_sgarray(1) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("alloc_sgarray() failed: {:?}", e),
+        };
+        let push_qt: QToken = match libos.push(tx, &sga) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        if let Err(e) = libos.free_sgarray(sga) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+        let qr: demi_qresult_t = wait(&mut libos, push_qt)?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        let i: usize = loop {
+            libos.poll();
+            if let Some(i) = handles.iter().position(|handle| handle.has_completed()) {
+                break i;
+            }
+        };
+        let qr: demi_qresult_t = wait(&mut libos, pop_qts[i])?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        if let Err(e) = libos.free_sgarray(unsafe { qr.qr_value.sga }) {
+            anyhow::bail!("free_sgarray() failed: {:?}", e);
+        }
+        crate::ensure_eq!(libos.pop(rx, None).is_ok(), true);
+        crate::ensure_eq!(libos.pop(rx, None).err().map(|e| e.errno), Some(libc::EBUSY));
+
+        Ok(())
+    }
+}
@@ -10,7 +10,12 @@ use crate::{
     collections::shared_ring::SharedRingBuffer,
     runtime::{
         fail::Fail,
-        queue::IoQueue,
+        queue::{
+            IoQueue,
+            QueueInfo,
+            QueueState,
+        },
+        types::demi_opcode_t,
         QType,
     },
     scheduler::{
@@ -27,7 +32,11 @@ use ::std::collections::HashMap;
 /// Per-queue metadata: reference to the shared pipe.
 pub struct CatmemQueue {
     pipe: Pipe,
-    pending_ops: HashMap<TaskHandle, YielderHandle>,
+    pending_ops: HashMap<TaskHandle, (demi_opcode_t, YielderHandle)>,
+    /// Indicates whether operations that cannot complete right away fail with `EWOULDBLOCK` instead of pending.
+    nonblocking: bool,
+    /// Largest number of operations of each type that may be pending at once, if any.
+    max_ops: Option<u32>,
 }
 
 //======================================================================================================================
@@ -38,10 +47,44 @@ impl CatmemQueue {
     pub fn new(ring: SharedRingBuffer<u16>) -> Self {
         Self {
             pipe: Pipe::new(ring),
-            pending_ops: HashMap::<TaskHandle, YielderHandle>::new(),
+            pending_ops: HashMap::<TaskHandle, (demi_opcode_t, YielderHandle)>::new(),
+            nonblocking: false,
+            max_ops: None,
         }
     }
 
+    /// Sets whether operations on this queue that cannot complete right away fail with `EWOULDBLOCK`.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    /// Checks whether operations on this queue that cannot complete right away fail with `EWOULDBLOCK`.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    /// Limits how many operations of each type may be pending at once on this queue, if given.
+    pub fn set_max_ops(&mut self, max_ops: Option<u32>) {
+        self.max_ops = max_ops;
+    }
+
+    /// Checks whether another operation of type `opcode` may be issued on this queue, failing with `EBUSY` if as many
+    /// are pending as allowed.
+    pub fn check_max_ops(&self, opcode: demi_opcode_t) -> Result<(), Fail> {
+        if let Some(max_ops) = self.max_ops {
+            let nops: usize = self.pending_ops.values().filter(|(op, _)| *op == opcode).count();
+            if nops >= max_ops as usize {
+                let cause: String = format!(
+                    "too many pending operations (opcode={:?}, max_ops={:?})",
+                    opcode, max_ops
+                );
+                error!("check_max_ops(): {}", cause);
+                return Err(Fail::new(libc::EBUSY, &cause));
+            }
+        }
+        Ok(())
+    }
+
     /// Get underlying uni-directional pipe.
     pub fn get_pipe(&self) -> &Pipe {
         &self.pipe
@@ -53,8 +96,9 @@ impl CatmemQueue {
     }
 
     /// Adds a new operation to the list of pending operations on this queue.
-    pub fn add_pending_op(&mut self, handle: &TaskHandle, yielder_handle: &YielderHandle) {
-        self.pending_ops.insert(handle.clone(), yielder_handle.clone());
+    pub fn add_pending_op(&mut self, handle: &TaskHandle, opcode: demi_opcode_t, yielder_handle: &YielderHandle) {
+        self.pending_ops
+            .insert(handle.clone(), (opcode, yielder_handle.clone()));
     }
 
     /// Removes an operation from the list of pending operations on this queue.
@@ -66,7 +110,7 @@ impl CatmemQueue {
 
     /// Cancels all pending operations on this queue.
     pub fn cancel_pending_ops(&mut self, cause: Fail) {
-        for (handle, mut yielder_handle) in self.pending_ops.drain() {
+        for (handle, (_, mut yielder_handle)) in self.pending_ops.drain() {
             if !handle.has_completed() {
                 yielder_handle.wake_with(Err(cause.clone()));
             }
@@ -82,4 +126,13 @@ impl IoQueue for CatmemQueue {
     fn get_qtype(&self) -> QType {
         QType::MemoryQueue
     }
+
+    fn describe(&self, info: &mut QueueInfo) {
+        info.state = Some(if self.pipe.eof() {
+            QueueState::Closing
+        } else {
+            QueueState::Established
+        });
+        info.pending_ops = self.pending_ops.len();
+    }
 }
@@ -21,10 +21,15 @@ use ::std::{
     mem,
     net::SocketAddrV4,
     os::unix::prelude::RawFd,
+    time::Instant,
 };
 
 /// This function polls accept on a listening socket until it receives a new accepted connection back.
-pub async fn accept_coroutine(fd: RawFd, yielder: Yielder) -> Result<(RawFd, SocketAddrV4), Fail> {
+pub async fn accept_coroutine(
+    fd: RawFd,
+    deadline: Option<Instant>,
+    yielder: Yielder,
+) -> Result<(RawFd, SocketAddrV4), Fail> {
     let mut saddr: SockAddr = unsafe { mem::zeroed() };
     let mut address_len: Socklen = mem::size_of::<SockAddrIn>() as u32;
 
@@ -58,6 +63,12 @@ pub async fn accept_coroutine(fd: RawFd, yielder: Yielder) -> Result<(RawFd, Soc
                 let errno: libc::c_int = unsafe { *libc::__errno_location() };
 
                 if errno == libc::EWOULDBLOCK || errno == libc::EAGAIN {
+                    // A pending connection wins over an expired deadline, so only check it here.
+                    if let Some(deadline) = deadline {
+                        if Instant::now() >= deadline {
+                            return Err(Fail::new(libc::ETIMEDOUT, "accept timed out"));
+                        }
+                    }
                     // Operation in progress. Check if cancelled.
                     match yielder.yield_once().await {
                         Ok(()) => continue,
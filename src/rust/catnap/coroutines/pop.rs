@@ -25,16 +25,19 @@ use ::std::{
     mem,
     net::SocketAddrV4,
     os::unix::prelude::RawFd,
+    time::Instant,
 };
 
 //==============================================================================
 // Constants
 //==============================================================================
 
-/// This function polls read until it receives some data or an error and then returns the data to pop.
+/// This function polls read until it receives some data or an error and then returns the data to pop. If a `deadline`
+/// is given and it passes before any data is received, this fails with `ETIMEDOUT`.
 pub async fn pop_coroutine(
     fd: RawFd,
     size: Option<usize>,
+    deadline: Option<Instant>,
     yielder: Yielder,
 ) -> Result<(Option<SocketAddrV4>, DemiBuffer), Fail> {
     let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
@@ -71,6 +74,12 @@ pub async fn pop_coroutine(
 
                 // Operation in progress.
                 if errno == libc::EWOULDBLOCK || errno == libc::EAGAIN {
+                    // Data that is already in the socket wins over an expired deadline, so only check it here.
+                    if let Some(deadline) = deadline {
+                        if Instant::now() >= deadline {
+                            return Err(Fail::new(libc::ETIMEDOUT, "pop timed out"));
+                        }
+                    }
                     // Operation in progress. Check if cancelled.
                     match yielder.yield_once().await {
                         Ok(()) => continue,
@@ -87,3 +96,43 @@ pub async fn pop_coroutine(
         }
     }
 }
+
+/// This function waits until it receives some data or an error, and then keeps receiving data that is ready without
+/// blocking, until `max_msgs` messages were received in total or no more data is ready.
+pub async fn pop_multiple_coroutine(
+    fd: RawFd,
+    max_msgs: usize,
+    yielder: Yielder,
+) -> Result<Vec<(Option<SocketAddrV4>, DemiBuffer)>, Fail> {
+    let mut msgs: Vec<(Option<SocketAddrV4>, DemiBuffer)> = Vec::with_capacity(max_msgs);
+    msgs.push(pop_coroutine(fd, None, None, yielder).await?);
+
+    while msgs.len() < max_msgs {
+        let mut buf: DemiBuffer = DemiBuffer::new(limits::RECVBUF_SIZE_MAX as u16);
+        let mut saddr: SockAddr = unsafe { mem::zeroed() };
+        let mut addrlen: Socklen = mem::size_of::<SockAddrIn>() as u32;
+        match unsafe {
+            libc::recvfrom(
+                fd,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                limits::RECVBUF_SIZE_MAX,
+                libc::MSG_DONTWAIT,
+                &mut saddr as *mut SockAddr,
+                &mut addrlen as *mut u32,
+            )
+        } {
+            // Received a message. A zero-length one may signal the end of a stream, so stop there.
+            nbytes if nbytes >= 0 => {
+                buf.trim(limits::RECVBUF_SIZE_MAX - nbytes as usize)?;
+                msgs.push((Some(linux::sockaddr_to_socketaddrv4(&saddr)), buf));
+                if nbytes == 0 {
+                    break;
+                }
+            },
+            // No more data is ready, or receiving failed. Failures surface on the next pop.
+            _ => break,
+        }
+    }
+
+    Ok(msgs)
+}
@@ -30,7 +30,7 @@ use ::std::{
 /// This function polls write until all the data in the push is sent.
 pub async fn push_coroutine(
     fd: RawFd,
-    buf: DemiBuffer,
+    mut buf: DemiBuffer,
     addr: Option<SocketAddrV4>,
     yielder: Yielder,
 ) -> Result<(), Fail> {
@@ -60,6 +60,14 @@ pub async fn push_coroutine(
             // Operation completed.
             nbytes if nbytes >= 0 => {
                 trace!("data pushed ({:?}/{:?} bytes)", nbytes, buf.len());
+
+                // Stream sockets may accept only part of the data, thus push the remaining bytes.
+                let nbytes: usize = nbytes as usize;
+                if nbytes < buf.len() {
+                    buf.adjust(nbytes)?;
+                    continue;
+                }
+
                 return Ok(());
             },
 
@@ -85,3 +93,53 @@ pub async fn push_coroutine(
         }
     }
 }
+
+/// Sends a batch of datagrams with a single call to `sendmmsg()`, without blocking. Upon success, the number of
+/// datagrams that were sent is returned, and these are always the first ones in the batch.
+pub fn sendmmsg_datagrams(fd: RawFd, datagrams: &[(DemiBuffer, SocketAddrV4)]) -> Result<usize, Fail> {
+    if datagrams.is_empty() {
+        return Ok(0);
+    }
+
+    // Note that the headers point into these vectors, so they must outlive the call to sendmmsg().
+    let mut saddrs: Vec<SockAddr> = datagrams
+        .iter()
+        .map(|(_, addr)| linux::socketaddrv4_to_sockaddr(addr))
+        .collect();
+    let mut iovecs: Vec<libc::iovec> = datagrams
+        .iter()
+        .map(|(buf, _)| libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        })
+        .collect();
+    let mut msgs: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .zip(saddrs.iter_mut())
+        .map(|(iovec, saddr)| {
+            let mut msg_hdr: libc::msghdr = unsafe { mem::zeroed() };
+            msg_hdr.msg_name = saddr as *mut SockAddr as *mut libc::c_void;
+            msg_hdr.msg_namelen = mem::size_of::<SockAddrIn>() as Socklen;
+            msg_hdr.msg_iov = iovec as *mut libc::iovec;
+            msg_hdr.msg_iovlen = 1;
+            libc::mmsghdr { msg_hdr, msg_len: 0 }
+        })
+        .collect();
+
+    match unsafe { libc::sendmmsg(fd, msgs.as_mut_ptr(), msgs.len() as libc::c_uint, libc::MSG_DONTWAIT) } {
+        nmsgs if nmsgs >= 0 => {
+            trace!("datagrams pushed ({:?}/{:?})", nmsgs, msgs.len());
+            Ok(nmsgs as usize)
+        },
+        _ => {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            if errno == libc::EWOULDBLOCK || errno == libc::EAGAIN {
+                Ok(0)
+            } else {
+                let message: String = format!("sendmmsg(): operation failed (errno={:?})", errno);
+                error!("{}", message);
+                Err(Fail::new(errno, &message))
+            }
+        },
+    }
+}
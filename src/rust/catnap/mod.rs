@@ -24,8 +24,14 @@ use self::{
         accept::accept_coroutine,
         close::close_coroutine,
         connect::connect_coroutine,
-        pop::pop_coroutine,
-        push::push_coroutine,
+        pop::{
+            pop_coroutine,
+            pop_multiple_coroutine,
+        },
+        push::{
+            push_coroutine,
+            sendmmsg_datagrams,
+        },
     },
     socket::Socket,
 };
@@ -46,11 +52,17 @@ use crate::{
             DemiBuffer,
             MemoryRuntime,
         },
+        network::{
+            consts::UDP_MAX_PAYLOAD_SIZE,
+            types::SocketOption,
+        },
         queue::{
+            IoQueue,
             IoQueueTable,
             Operation,
             OperationResult,
             OperationTask,
+            QueueInfo,
         },
         types::{
             demi_accept_result_t,
@@ -58,12 +70,14 @@ use crate::{
             demi_qr_value_t,
             demi_qresult_t,
             demi_sgarray_t,
+            DEMI_QR_EOF,
         },
         QDesc,
         QToken,
         QType,
     },
     scheduler::{
+        Scheduler,
         TaskHandle,
         Yielder,
         YielderHandle,
@@ -82,6 +96,10 @@ use ::std::{
     os::unix::prelude::RawFd,
     pin::Pin,
     rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 //======================================================================================================================
@@ -107,9 +125,14 @@ pub struct CatnapLibOS {
 /// Associate Functions for Catnap LibOS
 impl CatnapLibOS {
     /// Instantiates a Catnap LibOS.
-    pub fn new(_config: &Config) -> Self {
+    pub fn new(config: &Config) -> Self {
         let qtable: Rc<RefCell<IoQueueTable<CatnapQueue>>> = Rc::new(RefCell::new(IoQueueTable::<CatnapQueue>::new()));
-        let runtime: PosixRuntime = PosixRuntime::new();
+        qtable.borrow_mut().set_max_queues(config.max_open_queues());
+        let scheduler: Scheduler = match config.scheduler_capacity() {
+            Some(capacity) => Scheduler::with_capacity(capacity),
+            None => Scheduler::default(),
+        };
+        let runtime: PosixRuntime = PosixRuntime::new(scheduler);
         Self { qtable, runtime }
     }
 
@@ -155,8 +178,14 @@ impl CatnapLibOS {
                 }
 
                 trace!("socket: {:?}, domain: {:?}, typ: {:?}", fd, domain, typ);
-                let qd: QDesc = self.qtable.borrow_mut().alloc(CatnapQueue::new(qtype, Some(fd)));
-                Ok(qd)
+                match self.qtable.borrow_mut().alloc(CatnapQueue::new(qtype, Some(fd))) {
+                    Ok(qd) => Ok(qd),
+                    Err(e) => {
+                        // Do not leak the socket if we are out of queue descriptors.
+                        unsafe { libc::close(fd) };
+                        Err(e)
+                    },
+                }
             },
             _ => {
                 let errno: libc::c_int = unsafe { *libc::__errno_location() };
@@ -272,6 +301,44 @@ impl CatnapLibOS {
     /// Accepts connections on a socket.
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("accept(): qd={:?}", qd);
+        self.do_accept(qd, None)
+    }
+
+    /// Accepts connections on a socket, failing with `ETIMEDOUT` if no connection request arrives within `timeout`.
+    pub fn accept_timeout(&mut self, qd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("accept_timeout(): qd={:?}, timeout={:?}", qd, timeout);
+        self.do_accept(qd, Some(Instant::now() + timeout))
+    }
+
+    /// Stops accepting connections on a listening socket. The kernel resets connections that were not yet accepted,
+    /// and pending accepts complete with `ECONNABORTED`. The socket remains open until it is closed.
+    pub fn stop_listening(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("stop_listening(): qd={:?}", qd);
+        match self.qtable.borrow_mut().get_mut(&qd) {
+            Some(queue) => match queue.get_fd() {
+                Some(_) if !queue.get_socket().is_listening() => {
+                    let cause: String = format!("socket is not listening (qd={:?})", qd);
+                    error!("stop_listening(): {}", cause);
+                    Err(Fail::new(libc::EINVAL, &cause))
+                },
+                Some(fd) => {
+                    if unsafe { libc::shutdown(fd, libc::SHUT_RD) } != 0 {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        let cause: String = format!("failed to shut down listening socket (errno={:?})", errno);
+                        error!("stop_listening(): {}", cause);
+                        return Err(Fail::new(errno, &cause));
+                    }
+                    queue.cancel_pending_ops(Fail::new(libc::ECONNABORTED, "listening socket was shut down"));
+                    Ok(())
+                },
+                None => unreachable!("CatnapQueue has invalid underlying file descriptor"),
+            },
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Schedules a coroutine that accepts a connection on a socket, with an optional deadline.
+    fn do_accept(&mut self, qd: QDesc, deadline: Option<Instant>) -> Result<QToken, Fail> {
         let mut qtable: RefMut<IoQueueTable<CatnapQueue>> = self.qtable.borrow_mut();
         let fd: RawFd = match qtable.get_mut(&qd) {
             Some(queue) => match queue.get_fd() {
@@ -289,13 +356,13 @@ impl CatnapLibOS {
             None => return Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
         };
 
-        let new_qd: QDesc = qtable.alloc(CatnapQueue::new(QType::TcpSocket, None));
+        let new_qd: QDesc = qtable.alloc(CatnapQueue::new(QType::TcpSocket, None))?;
         let qtable_ptr: Rc<RefCell<IoQueueTable<CatnapQueue>>> = self.qtable.clone();
         let yielder: Yielder = Yielder::new();
         let yielder_handle: YielderHandle = yielder.get_handle();
         let coroutine: Pin<Box<Operation>> = Box::pin(async move {
             // Wait for the accept operation to complete.
-            let result: Result<(RawFd, SocketAddrV4), Fail> = accept_coroutine(fd, yielder).await;
+            let result: Result<(RawFd, SocketAddrV4), Fail> = accept_coroutine(fd, deadline, yielder).await;
             // Handle result: Borrow the queue table to either set the socket fd and addr or free the queue
             // metadata on error.
             match result {
@@ -327,7 +394,7 @@ impl CatnapLibOS {
 
                     // Update listening socket.
                     {
-                        let queue: &mut CatnapQueue = match qtable_.get_mut(&qd) {
+                        let queue: &mut CatnapQueue = match qtable_.get_resolved_mut(&qd) {
                             Some(queue) => queue,
                             None => {
                                 let cause: String = format!("invalid queue descriptor {:?}", qd);
@@ -349,8 +416,15 @@ impl CatnapLibOS {
                 },
                 Err(e) => {
                     warn!("accept() listening_qd={:?} new_qd={:?}: {:?}", qd, new_qd, &e);
+                    let mut qtable_: RefMut<IoQueueTable<CatnapQueue>> = qtable_ptr.borrow_mut();
                     // assert definitely no pending ops on new_qd
-                    qtable_ptr.borrow_mut().free(&new_qd);
+                    qtable_.free(&new_qd);
+                    // Move the listening socket out of the accepting state, so that it can accept again.
+                    if let Some(queue) = qtable_.get_resolved_mut(&qd) {
+                        if let Ok(listening_socket) = queue.get_socket().accepted() {
+                            queue.set_socket(&listening_socket);
+                        }
+                    }
                     (qd, OperationResult::Failed(e))
                 },
             }
@@ -361,7 +435,10 @@ impl CatnapLibOS {
             Some(handle) => {
                 // Borrow the scheduler handle and yielder handle to register a way to wake the coroutine.
                 // Safe to unwrap here because we have a linear flow from the last time that we looked up the queue.
-                qtable.get_mut(&qd).unwrap().add_pending_op(&handle, &yielder_handle);
+                qtable
+                    .get_mut(&qd)
+                    .unwrap()
+                    .add_pending_op(&handle, demi_opcode_t::DEMI_OPC_ACCEPT, &yielder_handle);
                 Ok(handle.get_task_id().into())
             },
             None => {
@@ -395,7 +472,8 @@ impl CatnapLibOS {
                             Ok(()) => {
                                 // Succeeded to connect, thus set socket as "connected".
                                 let mut qtable_: RefMut<IoQueueTable<CatnapQueue>> = qtable_ptr.borrow_mut();
-                                let queue: &mut CatnapQueue = qtable_.get_mut(&qd).expect("queue cannot be None");
+                                let queue: &mut CatnapQueue =
+                                    qtable_.get_resolved_mut(&qd).expect("queue cannot be None");
                                 let connecting_socket: &Socket = queue.get_socket();
                                 let connected_socket: Socket = match connecting_socket.connected(remote) {
                                     Ok(socket) => socket,
@@ -414,7 +492,8 @@ impl CatnapLibOS {
                                 // Failed to connect, check if that happened due to other reason than cancellation.
                                 if e.errno != libc::ECANCELED {
                                     let mut qtable_: RefMut<IoQueueTable<CatnapQueue>> = qtable_ptr.borrow_mut();
-                                    let queue: &mut CatnapQueue = qtable_.get_mut(&qd).expect("queue cannot be None");
+                                    let queue: &mut CatnapQueue =
+                                        qtable_.get_resolved_mut(&qd).expect("queue cannot be None");
                                     let socket: &Socket = queue.get_socket();
 
                                     // Failed to connect, rollback only if the operation was not cancelled.
@@ -440,7 +519,7 @@ impl CatnapLibOS {
                     };
 
                     // Borrow the scheduler handle and yielder handle to register a way to wake the coroutine.
-                    queue.add_pending_op(&handle, &yielder_handle);
+                    queue.add_pending_op(&handle, demi_opcode_t::DEMI_OPC_CONNECT, &yielder_handle);
                     // Update socket.
                     queue.set_socket(&connecting_socket);
                     Ok(handle.get_task_id().into())
@@ -451,10 +530,85 @@ impl CatnapLibOS {
         }
     }
 
+    /// Binds a socket to a local endpoint, unless it is already bound, and establishes a connection to a remote
+    /// endpoint. A wildcard port in `local` selects an ephemeral port.
+    pub fn connect_from(&mut self, qd: QDesc, local: SocketAddrV4, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        trace!("connect_from() qd={:?}, local={:?}, remote={:?}", qd, local, remote);
+
+        let bound: Option<SocketAddrV4> = match self.qtable.borrow().get(&qd) {
+            Some(queue) => queue.get_socket().local(),
+            None => {
+                let cause: String = format!("invalid queue descriptor {:?}", qd);
+                error!("connect_from(): {}", cause);
+                return Err(Fail::new(libc::EBADF, &cause));
+            },
+        };
+
+        match bound {
+            // Check if the socket is bound to the requested address.
+            Some(addr) => {
+                if (!local.ip().is_unspecified() && local.ip() != addr.ip())
+                    || (local.port() != 0 && local.port() != addr.port())
+                {
+                    let cause: String = format!("socket is bound to a different address (qd={:?})", qd);
+                    error!("connect_from(): {}", cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                }
+            },
+            None if local.port() != 0 => self.bind(qd, local)?,
+            None => self.bind_ephemeral(qd, local)?,
+        }
+
+        self.connect(qd, remote)
+    }
+
+    /// Binds a socket to an ephemeral port on a local address.
+    fn bind_ephemeral(&mut self, qd: QDesc, local: SocketAddrV4) -> Result<(), Fail> {
+        let mut qtable: RefMut<IoQueueTable<CatnapQueue>> = self.qtable.borrow_mut();
+        let queue: &mut CatnapQueue = match qtable.get_mut(&qd) {
+            Some(queue) => queue,
+            None => return Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        };
+
+        // Check if the socket may be bound at all.
+        queue.get_socket().bind(local)?;
+
+        // Get reference to the underlying file descriptor.
+        // The following call to expect() is safe because when creating a queue we assigned it a valid file descritor.
+        let fd: RawFd = queue.get_fd().expect("queue should have a file descriptor");
+
+        // Bind underlying socket and retrieve the port that was selected for it.
+        let mut saddr: SockAddr = linux::socketaddrv4_to_sockaddr(&local);
+        let mut addrlen: Socklen = mem::size_of::<SockAddrIn>() as Socklen;
+        if unsafe { libc::bind(fd, &saddr as *const SockAddr, addrlen) } != 0
+            || unsafe { libc::getsockname(fd, &mut saddr as *mut SockAddr, &mut addrlen) } != 0
+        {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to bind socket (errno={:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+
+        // Update socket.
+        let bound_socket: Socket = queue.get_socket().bind(linux::sockaddr_to_socketaddrv4(&saddr))?;
+        queue.set_socket(&bound_socket);
+        Ok(())
+    }
+
+    /// Duplicates a queue descriptor. The underlying socket is only closed once all queue descriptors that refer to it
+    /// are closed.
+    pub fn dup(&mut self, qd: QDesc) -> Result<QDesc, Fail> {
+        trace!("dup() qd={:?}", qd);
+        self.qtable.borrow_mut().dup(&qd)
+    }
+
     /// Closes a socket.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         trace!("close() qd={:?}", qd);
         let mut qtable: RefMut<IoQueueTable<CatnapQueue>> = self.qtable.borrow_mut();
+        // Other queue descriptors still refer to this socket, so leave it open.
+        if qtable.close_duplicate(&qd) == Some(true) {
+            return Ok(());
+        }
         match qtable.get_mut(&qd) {
             Some(queue) => match queue.get_fd() {
                 Some(fd) => {
@@ -493,6 +647,18 @@ impl CatnapLibOS {
         trace!("async_close() qd={:?}", qd);
         let mut qtable: RefMut<IoQueueTable<CatnapQueue>> = self.qtable.borrow_mut();
 
+        // Other queue descriptors still refer to this socket, so leave it open.
+        if qtable.close_duplicate(&qd) == Some(true) {
+            let coroutine: Pin<Box<Operation>> = Box::pin(async move { (qd, OperationResult::Close) });
+            let task_id: String = format!("Catnap::close for duplicate qd={:?}", qd);
+            let task: OperationTask = OperationTask::new(task_id, coroutine);
+            let handle: TaskHandle = match self.runtime.scheduler.insert(task) {
+                Some(handle) => handle,
+                None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+            };
+            return Ok(handle.get_task_id().into());
+        }
+
         match qtable.get_mut(&qd) {
             Some(queue) => match queue.get_fd() {
                 Some(fd) => {
@@ -571,6 +737,8 @@ impl CatnapLibOS {
                 match self.qtable.borrow_mut().get_mut(&qd) {
                     Some(queue) => match queue.get_fd() {
                         Some(fd) => {
+                            check_push_size(queue.get_qtype(), buf.len())?;
+                            queue.check_max_ops(demi_opcode_t::DEMI_OPC_PUSH)?;
                             let yielder: Yielder = Yielder::new();
                             let yielder_handle: YielderHandle = yielder.get_handle();
                             let coroutine: Pin<Box<Operation>> = Box::pin(async move {
@@ -592,7 +760,7 @@ impl CatnapLibOS {
                                 None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
                             };
                             // Borrow the scheduler handle and yielder handle to register a way to wake the coroutine.
-                            queue.add_pending_op(&handle, &yielder_handle);
+                            queue.add_pending_op(&handle, demi_opcode_t::DEMI_OPC_PUSH, &yielder_handle);
 
                             Ok(handle.get_task_id().into())
                         },
@@ -610,52 +778,120 @@ impl CatnapLibOS {
         trace!("pushto() qd={:?}", qd);
 
         match self.runtime.clone_sgarray(sga) {
-            Ok(buf) => {
-                if buf.len() == 0 {
-                    return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-                }
+            Ok(buf) => self.do_pushto(qd, buf, remote),
+            Err(e) => Err(e),
+        }
+    }
 
-                // Issue pushto operation.
-                match self.qtable.borrow_mut().get_mut(&qd) {
-                    Some(queue) => match queue.get_fd() {
-                        Some(fd) => {
-                            let yielder: Yielder = Yielder::new();
-                            let yielder_handle: YielderHandle = yielder.get_handle();
-                            let coroutine: Pin<Box<Operation>> = Box::pin(async move {
-                                // Wait for pushto to complete.
-                                let result: Result<(), Fail> = push_coroutine(fd, buf, Some(remote), yielder).await;
-                                // Process result.
-                                match result {
-                                    Ok(()) => (qd, OperationResult::Push),
-                                    Err(e) => {
-                                        warn!("pushto() qd={:?}: {:?}", qd, &e);
-                                        (qd, OperationResult::Failed(e))
-                                    },
-                                }
-                            });
-                            let task_id: String = format!("Catnap::pushto for qd={:?}", qd);
-                            let task: OperationTask = OperationTask::new(task_id, Box::pin(coroutine));
-                            let handle: TaskHandle = match self.runtime.scheduler.insert(task) {
-                                Some(handle) => handle,
-                                None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
-                            };
-                            // Borrow the scheduler handle and yielder handle to register a way to wake the coroutine.
-                            queue.add_pending_op(&handle, &yielder_handle);
-                            Ok(handle.get_task_id().into())
-                        },
-                        None => unreachable!("CatnapQueue has invalid underlying file descriptor"),
-                    },
-                    None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+    /// Pushes a batch of scatter-gather arrays to a socket, each one to its own remote address. As many datagrams as
+    /// possible are sent right away with a single call to `sendmmsg()`, and the remaining ones are pushed one by one.
+    /// Each datagram completes on its own queue token.
+    pub fn pushto_batch(
+        &mut self,
+        qd: QDesc,
+        datagrams: &[(demi_sgarray_t, SocketAddrV4)],
+    ) -> Result<Vec<QToken>, Fail> {
+        trace!("pushto_batch() qd={:?}, count={:?}", qd, datagrams.len());
+
+        let mut bufs: Vec<(DemiBuffer, SocketAddrV4)> = Vec::with_capacity(datagrams.len());
+        for (sga, remote) in datagrams {
+            let buf: DemiBuffer = self.runtime.clone_sgarray(sga)?;
+            bufs.push((buf, *remote));
+        }
+
+        let (fd, qtype): (RawFd, QType) = match self.qtable.borrow().get(&qd) {
+            Some(queue) => match queue.get_fd() {
+                Some(fd) => (fd, queue.get_qtype()),
+                None => unreachable!("CatnapQueue has invalid underlying file descriptor"),
+            },
+            None => return Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        };
+
+        // Send the leading datagrams that fit in a single packet right away. Whatever is left is pushed one by one, so
+        // that failures are reported on the datagrams that caused them.
+        let nbatch: usize = bufs
+            .iter()
+            .take_while(|(buf, _)| check_push_size(qtype, buf.len()).is_ok())
+            .count();
+        let nsent: usize = sendmmsg_datagrams(fd, &bufs[..nbatch]).unwrap_or(0);
+
+        let mut qts: Vec<QToken> = Vec::with_capacity(bufs.len());
+        for (i, (buf, remote)) in bufs.into_iter().enumerate() {
+            let qt: QToken = if i < nsent {
+                self.insert_completed(qd, OperationResult::Push)?
+            } else {
+                match self.do_pushto(qd, buf, remote) {
+                    Ok(qt) => qt,
+                    Err(e) => self.insert_completed(qd, OperationResult::Failed(e))?,
                 }
+            };
+            qts.push(qt);
+        }
+        Ok(qts)
+    }
+
+    /// Schedules a coroutine that pushes a buffer to a remote address.
+    fn do_pushto(&mut self, qd: QDesc, buf: DemiBuffer, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        match self.qtable.borrow_mut().get_mut(&qd) {
+            Some(queue) => match queue.get_fd() {
+                Some(fd) => {
+                    check_push_size(queue.get_qtype(), buf.len())?;
+                    queue.check_max_ops(demi_opcode_t::DEMI_OPC_PUSH)?;
+                    let yielder: Yielder = Yielder::new();
+                    let yielder_handle: YielderHandle = yielder.get_handle();
+                    let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+                        // Wait for pushto to complete.
+                        let result: Result<(), Fail> = push_coroutine(fd, buf, Some(remote), yielder).await;
+                        // Process result.
+                        match result {
+                            Ok(()) => (qd, OperationResult::Push),
+                            Err(e) => {
+                                warn!("pushto() qd={:?}: {:?}", qd, &e);
+                                (qd, OperationResult::Failed(e))
+                            },
+                        }
+                    });
+                    let task_id: String = format!("Catnap::pushto for qd={:?}", qd);
+                    let task: OperationTask = OperationTask::new(task_id, Box::pin(coroutine));
+                    let handle: TaskHandle = match self.runtime.scheduler.insert(task) {
+                        Some(handle) => handle,
+                        None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+                    };
+                    // Borrow the scheduler handle and yielder handle to register a way to wake the coroutine.
+                    queue.add_pending_op(&handle, demi_opcode_t::DEMI_OPC_PUSH, &yielder_handle);
+                    Ok(handle.get_task_id().into())
+                },
+                None => unreachable!("CatnapQueue has invalid underlying file descriptor"),
             },
-            Err(e) => Err(e),
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Schedules an operation that has already completed with `result`.
+    fn insert_completed(&mut self, qd: QDesc, result: OperationResult) -> Result<QToken, Fail> {
+        let coroutine: Pin<Box<Operation>> = Box::pin(async move { (qd, result) });
+        let task_id: String = format!("Catnap::pushto for qd={:?}", qd);
+        let task: OperationTask = OperationTask::new(task_id, coroutine);
+        match self.runtime.scheduler.insert(task) {
+            Some(handle) => Ok(handle.get_task_id().into()),
+            None => Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
         }
     }
 
     /// Pops data from a socket.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         trace!("pop() qd={:?}, size={:?}", qd, size);
+        self.do_pop(qd, size, None)
+    }
+
+    /// Pops data from a socket, failing with `ETIMEDOUT` if no data arrives within `timeout`.
+    pub fn pop_timeout(&mut self, qd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("pop_timeout() qd={:?}, size={:?}, timeout={:?}", qd, size, timeout);
+        self.do_pop(qd, size, Some(Instant::now() + timeout))
+    }
 
+    /// Schedules a coroutine that pops data from a socket, with an optional deadline.
+    fn do_pop(&mut self, qd: QDesc, size: Option<usize>, deadline: Option<Instant>) -> Result<QToken, Fail> {
         // We just assert 'size' here, because it was previously checked at PDPIX layer.
         debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
 
@@ -663,15 +899,21 @@ impl CatnapLibOS {
         match self.qtable.borrow_mut().get_mut(&qd) {
             Some(queue) => match queue.get_fd() {
                 Some(fd) => {
+                    queue.check_max_ops(demi_opcode_t::DEMI_OPC_POP)?;
+                    let stream: bool = queue.get_qtype() == QType::TcpSocket;
                     let yielder: Yielder = Yielder::new();
                     let yielder_handle: YielderHandle = yielder.get_handle();
                     let coroutine: Pin<Box<Operation>> = Box::pin(async move {
                         // Wait for pop to complete.
                         let result: Result<(Option<SocketAddrV4>, DemiBuffer), Fail> =
-                            pop_coroutine(fd, size, yielder).await;
+                            pop_coroutine(fd, size, deadline, yielder).await;
                         // Process result.
                         match result {
-                            Ok((addr, buf)) => (qd, OperationResult::Pop(addr, buf)),
+                            Ok((addr, buf)) => {
+                                // Only the end of a stream yields an empty buffer, as datagrams may be empty.
+                                let eof: bool = stream && buf.is_empty();
+                                (qd, OperationResult::Pop(addr, buf, eof))
+                            },
                             Err(e) => {
                                 warn!("pop() qd={:?}: {:?}", qd, &e);
                                 (qd, OperationResult::Failed(e))
@@ -685,7 +927,7 @@ impl CatnapLibOS {
                         None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
                     };
                     // Borrow the scheduler handle and yielder handle to register a way to wake the coroutine.
-                    queue.add_pending_op(&handle, &yielder_handle);
+                    queue.add_pending_op(&handle, demi_opcode_t::DEMI_OPC_POP, &yielder_handle);
                     let qt: QToken = handle.get_task_id().into();
                     Ok(qt)
                 },
@@ -695,6 +937,162 @@ impl CatnapLibOS {
         }
     }
 
+    /// Pops up to `max_msgs` messages from a socket at once. The operation completes as soon as at least one message is
+    /// available, with all messages that can be received at that time without blocking.
+    pub fn pop_multiple(&mut self, qd: QDesc, max_msgs: usize) -> Result<QToken, Fail> {
+        trace!("pop_multiple() qd={:?}, max_msgs={:?}", qd, max_msgs);
+
+        if max_msgs == 0 {
+            let cause: String = format!("cannot pop zero messages (qd={:?})", qd);
+            error!("pop_multiple(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        // Issue pop operation.
+        match self.qtable.borrow_mut().get_mut(&qd) {
+            Some(queue) => match queue.get_fd() {
+                Some(fd) => {
+                    queue.check_max_ops(demi_opcode_t::DEMI_OPC_POP)?;
+                    let yielder: Yielder = Yielder::new();
+                    let yielder_handle: YielderHandle = yielder.get_handle();
+                    let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+                        // Wait for pop to complete.
+                        let result: Result<Vec<(Option<SocketAddrV4>, DemiBuffer)>, Fail> =
+                            pop_multiple_coroutine(fd, max_msgs, yielder).await;
+                        // Process result.
+                        match result {
+                            Ok(msgs) => (qd, OperationResult::PopBatch(msgs)),
+                            Err(e) => {
+                                warn!("pop_multiple() qd={:?}: {:?}", qd, e);
+                                (qd, OperationResult::Failed(e))
+                            },
+                        }
+                    });
+                    let task_id: String = format!("Catnap::pop_multiple for qd={:?}", qd);
+                    let task: OperationTask = OperationTask::new(task_id, coroutine);
+                    let handle: TaskHandle = match self.runtime.scheduler.insert(task) {
+                        Some(handle) => handle,
+                        None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+                    };
+                    // Borrow the scheduler handle and yielder handle to register a way to wake the coroutine.
+                    queue.add_pending_op(&handle, demi_opcode_t::DEMI_OPC_POP, &yielder_handle);
+                    Ok(handle.get_task_id().into())
+                },
+                None => unreachable!("CatnapQueue has invalid underlying file descriptor"),
+            },
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Returns the number of bytes that may be popped right away from a socket. For a UDP socket, this is the size of
+    /// the next datagram. For a listening socket, this is the number of connections that may be accepted right away
+    /// instead.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("bytes_available() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => match queue.get_fd() {
+                // The kernel does not report the accept queue of a listening socket through FIONREAD, but through the
+                // number of unacknowledged segments in TCP_INFO.
+                Some(fd) if queue.get_socket().is_listening() => {
+                    let mut info: libc::tcp_info = unsafe { mem::zeroed() };
+                    let mut len: Socklen = mem::size_of::<libc::tcp_info>() as Socklen;
+                    if unsafe {
+                        libc::getsockopt(
+                            fd,
+                            libc::IPPROTO_TCP,
+                            libc::TCP_INFO,
+                            &mut info as *mut libc::tcp_info as *mut libc::c_void,
+                            &mut len,
+                        )
+                    } != 0
+                    {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        let cause: String = format!("failed to query accept queue (errno={:?})", errno);
+                        error!("bytes_available(): {}", cause);
+                        return Err(Fail::new(errno, &cause));
+                    }
+                    Ok(info.tcpi_unacked as usize)
+                },
+                Some(fd) => {
+                    let mut nbytes: libc::c_int = 0;
+                    if unsafe { libc::ioctl(fd, libc::FIONREAD, &mut nbytes as *mut libc::c_int) } != 0 {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        let cause: String = format!("failed to query receive queue (errno={:?})", errno);
+                        error!("bytes_available(): {}", cause);
+                        return Err(Fail::new(errno, &cause));
+                    }
+                    Ok(nbytes as usize)
+                },
+                None => unreachable!("CatnapQueue has invalid underlying file descriptor"),
+            },
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single push transfers as a unit on a socket. Pushes to a UDP socket
+    /// that exceed this size fail with `EMSGSIZE`, whereas pushes to a TCP socket are split by the kernel.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("max_push_size() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => match queue.get_qtype() {
+                QType::UdpSocket => Ok(UDP_MAX_PAYLOAD_SIZE),
+                _ => Ok(limits::RECVBUF_SIZE_MAX),
+            },
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Takes a snapshot of every open queue descriptor.
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        self.qtable.borrow().queues()
+    }
+
+    /// Takes a snapshot of the queue referred to by `qd`.
+    pub fn query_queue(&self, qd: QDesc) -> Result<QueueInfo, Fail> {
+        trace!("query_queue(): qd={:?}", qd);
+        match self.qtable.borrow().query(&qd) {
+            Some(info) => Ok(info),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the local address that a socket is bound to. Sockets that are not bound yet report the unspecified
+    /// address and port zero.
+    pub fn getsockname(&self, qd: QDesc) -> Result<SocketAddrV4, Fail> {
+        trace!("getsockname() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(queue) => Ok(queue
+                .get_socket()
+                .local()
+                .unwrap_or(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))),
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Forces transmission of all data that is buffered on a socket. Pushed data is handed over to the kernel as soon
+    /// as possible, thus this only checks if `qd` refers to a valid socket.
+    pub fn flush(&self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush() qd={:?}", qd);
+        match self.qtable.borrow().get(&qd) {
+            Some(_) => Ok(()),
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Sets an option on a socket.
+    pub fn setsockopt(&mut self, qd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        trace!("setsockopt() qd={:?}, option={:?}", qd, option);
+        match self.qtable.borrow_mut().get_mut(&qd) {
+            Some(queue) => {
+                match option {
+                    SocketOption::MaxOpsPerSec(max_ops) => queue.set_max_ops((max_ops > 0).then_some(max_ops)),
+                }
+                Ok(())
+            },
+            None => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
     pub fn poll(&self) {
         self.runtime.scheduler.poll()
     }
@@ -711,6 +1109,12 @@ impl CatnapLibOS {
         Ok(pack_result(&self.runtime, r, qd, qt.into()))
     }
 
+    /// Packs the result of a batched pop into one scatter-gather array per message.
+    pub fn pack_batch_result(&mut self, handle: TaskHandle) -> Result<Vec<demi_sgarray_t>, Fail> {
+        let (_, r): (QDesc, OperationResult) = self.take_result(handle);
+        pack_batch_result(&self.runtime, r)
+    }
+
     /// Allocates a scatter-gather array.
     pub fn sgaalloc(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
         trace!("sgalloc() size={:?}", size);
@@ -735,7 +1139,7 @@ impl CatnapLibOS {
         match result {
             OperationResult::Close => {},
             _ => {
-                match self.qtable.borrow_mut().get_mut(&qd) {
+                match self.qtable.borrow_mut().get_resolved_mut(&qd) {
                     Some(queue) => queue.remove_pending_op(&handle),
                     None => debug!("Catnap::take_result() qd={:?}, This queue was closed", qd),
                 };
@@ -769,6 +1173,16 @@ impl Drop for CatnapLibOS {
 // Standalone Functions
 //==============================================================================
 
+/// Checks if a buffer of `len` bytes may be pushed to a queue of type `qtype`.
+fn check_push_size(qtype: QType, len: usize) -> Result<(), Fail> {
+    if qtype == QType::UdpSocket && len > UDP_MAX_PAYLOAD_SIZE {
+        let cause: String = format!("datagram is too large (len={:?}, max={:?})", len, UDP_MAX_PAYLOAD_SIZE);
+        error!("check_push_size(): {}", cause);
+        return Err(Fail::new(libc::EMSGSIZE, &cause));
+    }
+    Ok(())
+}
+
 /// Packs a [OperationResult] into a [demi_qresult_t].
 fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -> demi_qresult_t {
     match result {
@@ -778,6 +1192,10 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
         OperationResult::Accept((new_qd, addr)) => {
             let saddr: SockAddr = linux::socketaddrv4_to_sockaddr(&addr);
@@ -793,6 +1211,10 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
                 qr_qt: qt,
                 qr_ret: 0,
                 qr_value,
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
         OperationResult::Push => demi_qresult_t {
@@ -801,8 +1223,12 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
-        OperationResult::Pop(addr, bytes) => match rt.into_sgarray(bytes) {
+        OperationResult::Pop(addr, bytes, eof) => match rt.into_sgarray(bytes) {
             Ok(mut sga) => {
                 if let Some(addr) = addr {
                     sga.sga_addr = linux::socketaddrv4_to_sockaddr(&addr);
@@ -814,6 +1240,10 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
                     qr_qt: qt,
                     qr_ret: 0,
                     qr_value,
+                    qr_flags: if eof { DEMI_QR_EOF } else { 0 },
+                    qr_start_ns: 0,
+                    qr_end_ns: 0,
+                    qr_rx_ns: 0,
                 }
             },
             Err(e) => {
@@ -824,15 +1254,52 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
                     qr_qt: qt,
                     qr_ret: e.errno as i64,
                     qr_value: unsafe { mem::zeroed() },
+                    qr_flags: 0,
+                    qr_start_ns: 0,
+                    qr_end_ns: 0,
+                    qr_rx_ns: 0,
                 }
             },
         },
+        OperationResult::PopBatch(_) => {
+            warn!("Operation Failed: batched pop results cannot be packed into a single result");
+            demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: libc::ENOTSUP as i64,
+                qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
+            }
+        },
+        #[cfg(feature = "dhcp")]
+        OperationResult::Dhcp(_) => {
+            warn!("Operation Failed: DHCP leases cannot be packed into a result");
+            demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: libc::ENOTSUP as i64,
+                qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
+            }
+        },
         OperationResult::Close => demi_qresult_t {
             qr_opcode: demi_opcode_t::DEMI_OPC_CLOSE,
             qr_qd: qd.into(),
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
         OperationResult::Failed(e) => {
             warn!("Operation Failed: {:?}", e);
@@ -842,7 +1309,41 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
                 qr_qt: qt,
                 qr_ret: e.errno as i64,
                 qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
     }
 }
+
+/// Packs the messages of an [OperationResult::PopBatch] into scatter-gather arrays, one per message.
+pub fn pack_batch_result(rt: &PosixRuntime, result: OperationResult) -> Result<Vec<demi_sgarray_t>, Fail> {
+    let msgs: Vec<(Option<SocketAddrV4>, DemiBuffer)> = match result {
+        OperationResult::PopBatch(msgs) => msgs,
+        OperationResult::Failed(e) => return Err(e),
+        _ => return Err(Fail::new(libc::EINVAL, "not a batched pop operation")),
+    };
+    let mut sgas: Vec<demi_sgarray_t> = Vec::with_capacity(msgs.len());
+    for (addr, bytes) in msgs {
+        match rt.into_sgarray(bytes) {
+            Ok(mut sga) => {
+                if let Some(addr) = addr {
+                    sga.sga_addr = linux::socketaddrv4_to_sockaddr(&addr);
+                }
+                sgas.push(sga);
+            },
+            Err(e) => {
+                // Release the scatter-gather arrays that were packed so far.
+                for sga in sgas {
+                    if let Err(e) = rt.free_sgarray(sga) {
+                        warn!("leaking scatter-gather array: {:?}", e);
+                    }
+                }
+                return Err(e);
+            },
+        }
+    }
+    Ok(sgas)
+}
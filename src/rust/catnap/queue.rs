@@ -12,7 +12,9 @@ use crate::{
         queue::{
             IoQueue,
             QType,
+            QueueInfo,
         },
+        types::demi_opcode_t,
     },
     scheduler::{
         TaskHandle,
@@ -33,7 +35,9 @@ pub struct CatnapQueue {
     qtype: QType,
     fd: Option<RawFd>,
     socket: Socket,
-    pending_ops: HashMap<TaskHandle, YielderHandle>,
+    pending_ops: HashMap<TaskHandle, (demi_opcode_t, YielderHandle)>,
+    /// Largest number of operations of each type that may be pending at once, if any.
+    max_ops: Option<u32>,
 }
 
 //======================================================================================================================
@@ -46,7 +50,8 @@ impl CatnapQueue {
             qtype,
             fd,
             socket: Socket::new(),
-            pending_ops: HashMap::<TaskHandle, YielderHandle>::new(),
+            pending_ops: HashMap::<TaskHandle, (demi_opcode_t, YielderHandle)>::new(),
+            max_ops: None,
         }
     }
 
@@ -70,9 +75,32 @@ impl CatnapQueue {
         &self.socket
     }
 
+    /// Limits how many operations of each type may be pending at once on this queue, if given.
+    pub fn set_max_ops(&mut self, max_ops: Option<u32>) {
+        self.max_ops = max_ops;
+    }
+
+    /// Checks whether another operation of type `opcode` may be issued on this queue, failing with `EBUSY` if as many
+    /// are pending as allowed.
+    pub fn check_max_ops(&self, opcode: demi_opcode_t) -> Result<(), Fail> {
+        if let Some(max_ops) = self.max_ops {
+            let nops: usize = self.pending_ops.values().filter(|(op, _)| *op == opcode).count();
+            if nops >= max_ops as usize {
+                let cause: String = format!(
+                    "too many pending operations (opcode={:?}, max_ops={:?})",
+                    opcode, max_ops
+                );
+                error!("check_max_ops(): {}", cause);
+                return Err(Fail::new(libc::EBUSY, &cause));
+            }
+        }
+        Ok(())
+    }
+
     /// Adds a new operation to the list of pending operations on this queue.
-    pub fn add_pending_op(&mut self, handle: &TaskHandle, yielder_handle: &YielderHandle) {
-        self.pending_ops.insert(handle.clone(), yielder_handle.clone());
+    pub fn add_pending_op(&mut self, handle: &TaskHandle, opcode: demi_opcode_t, yielder_handle: &YielderHandle) {
+        self.pending_ops
+            .insert(handle.clone(), (opcode, yielder_handle.clone()));
     }
 
     /// Removes an operation from the list of pending operations on this queue. This function should only be called if
@@ -84,7 +112,7 @@ impl CatnapQueue {
     /// Cancel all currently pending operations on this queue. If the operation is not complete and the coroutine has
     /// yielded, wake the coroutine with an error.
     pub fn cancel_pending_ops(&mut self, cause: Fail) {
-        for (handle, mut yielder_handle) in self.pending_ops.drain() {
+        for (handle, (_, mut yielder_handle)) in self.pending_ops.drain() {
             if !handle.has_completed() {
                 yielder_handle.wake_with(Err(cause.clone()));
             }
@@ -100,4 +128,11 @@ impl IoQueue for CatnapQueue {
     fn get_qtype(&self) -> QType {
         self.qtype
     }
+
+    fn describe(&self, info: &mut QueueInfo) {
+        info.state = Some(self.socket.state());
+        info.local = self.socket.local();
+        info.remote = self.socket.remote();
+        info.pending_ops = self.pending_ops.len();
+    }
 }
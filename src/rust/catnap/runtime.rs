@@ -30,10 +30,8 @@ pub struct PosixRuntime {
 
 /// Associate Functions for POSIX Runtime
 impl PosixRuntime {
-    pub fn new() -> Self {
-        Self {
-            scheduler: Scheduler::default(),
-        }
+    pub fn new(scheduler: Scheduler) -> Self {
+        Self { scheduler }
     }
 }
 
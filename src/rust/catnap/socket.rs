@@ -5,7 +5,10 @@
 // Imports
 //======================================================================================================================
 
-use crate::runtime::fail::Fail;
+use crate::runtime::{
+    fail::Fail,
+    queue::QueueState,
+};
 use ::std::net::SocketAddrV4;
 
 //======================================================================================================================
@@ -321,16 +324,32 @@ impl Socket {
         self.local
     }
 
-    #[allow(dead_code)]
     /// Returns the `remote` address tot which [self] is connected.
     pub fn remote(&self) -> Option<SocketAddrV4> {
         self.remote
     }
 
+    /// Returns the state of [self], as reported by queue introspection.
+    pub fn state(&self) -> QueueState {
+        match self.state {
+            SocketState::NotBound => QueueState::Unbound,
+            SocketState::Bound => QueueState::Bound,
+            SocketState::Listening | SocketState::Accepting => QueueState::Listening,
+            SocketState::Connecting => QueueState::Connecting,
+            SocketState::Connected => QueueState::Established,
+            SocketState::Closing | SocketState::Closed => QueueState::Closing,
+        }
+    }
+
     /// Asserts if [self] is `Connecting`.
     pub fn is_connecting(&self) -> bool {
         self.state == SocketState::Connecting
     }
+
+    /// Asserts if [self] is `Listening` or `Accepting`.
+    pub fn is_listening(&self) -> bool {
+        self.state == SocketState::Listening || self.state == SocketState::Accepting
+    }
 }
 
 //======================================================================================================================
@@ -122,7 +122,11 @@ impl Operation {
             Operation::Pop(FutureResult {
                 future,
                 done: Some(Ok((addr, buf))),
-            }) => (future.get_qd(), None, None, OperationResult::Pop(addr, buf)),
+            }) => {
+                // Only the end of a stream yields an empty buffer, as datagrams may be empty.
+                let eof: bool = future.is_stream() && buf.is_empty();
+                (future.get_qd(), None, None, OperationResult::Pop(addr, buf, eof))
+            },
             Operation::Pop(FutureResult {
                 future,
                 done: Some(Err(e)),
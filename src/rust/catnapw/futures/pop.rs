@@ -10,7 +10,10 @@ use crate::runtime::{
     memory::DemiBuffer,
     QDesc,
 };
-use ::socket2::Socket;
+use ::socket2::{
+    Socket,
+    Type,
+};
 use ::std::{
     cell::RefCell,
     future::Future,
@@ -62,6 +65,11 @@ impl PopFuture {
     pub fn get_qd(&self) -> QDesc {
         self.qd
     }
+
+    /// Checks whether the target [PopFuture] pops data from a stream socket.
+    pub fn is_stream(&self) -> bool {
+        self.socket.borrow().r#type().ok() == Some(Type::STREAM)
+    }
 }
 
 //==============================================================================
@@ -40,6 +40,7 @@ use crate::{
             DemiBuffer,
             MemoryRuntime,
         },
+        network::consts::UDP_MAX_PAYLOAD_SIZE,
         queue::IoQueueTable,
         types::{
             demi_accept_result_t,
@@ -47,12 +48,16 @@ use crate::{
             demi_qr_value_t,
             demi_qresult_t,
             demi_sgarray_t,
+            DEMI_QR_EOF,
         },
         QDesc,
         QToken,
         QType,
     },
-    scheduler::SchedulerHandle,
+    scheduler::{
+        Scheduler,
+        SchedulerHandle,
+    },
 };
 use ::libc::{
     c_int,
@@ -78,7 +83,9 @@ use ::std::{
         Shutdown,
         SocketAddrV4,
     },
+    os::windows::io::AsRawSocket,
     rc::Rc,
+    time::Duration,
 };
 use ::windows::Win32::{
     Foundation::CHAR,
@@ -89,6 +96,13 @@ use ::windows::Win32::{
     },
 };
 
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Maximum number of bytes that are transferred as a unit on a TCP socket. This matches the size of a pop operation.
+const TCP_MAX_PUSH_SIZE: usize = 9216;
+
 //==============================================================================
 // Structures
 //==============================================================================
@@ -110,10 +124,14 @@ pub struct CatnapWLibOS {
 /// Associate Functions for CatnapW LibOS
 impl CatnapWLibOS {
     /// Instantiates a CatnapW LibOS.
-    pub fn new(_config: &Config) -> Self {
+    pub fn new(config: &Config) -> Self {
         let qtable: IoQueueTable = IoQueueTable::new();
         let sockets: HashMap<QDesc, Rc<RefCell<Socket>>> = HashMap::new();
-        let runtime: PosixRuntime = PosixRuntime::new();
+        let scheduler: Scheduler = match config.scheduler_capacity() {
+            Some(capacity) => Scheduler::with_capacity(capacity),
+            None => Scheduler::default(),
+        };
+        let runtime: PosixRuntime = PosixRuntime::new(scheduler);
         Self {
             qtable,
             sockets,
@@ -187,6 +205,27 @@ impl CatnapWLibOS {
         }
     }
 
+    /// Accepts connections on a socket with a timeout. This is not supported on this LibOS.
+    pub fn accept_timeout(&mut self, qd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("accept_timeout(): qd={:?}, timeout={:?}", qd, timeout);
+        Err(Fail::new(libc::ENOTSUP, "accept with timeout is not supported"))
+    }
+
+    /// Stops accepting connections on a listening socket. This is not supported on this LibOS.
+    pub fn stop_listening(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("stop_listening(): qd={:?}", qd);
+        Err(Fail::new(libc::ENOTSUP, "stop listening is not supported"))
+    }
+
+    /// Duplicates a queue descriptor. This is not supported on this LibOS.
+    pub fn dup(&mut self, qd: QDesc) -> Result<QDesc, Fail> {
+        trace!("dup(): qd={:?}", qd);
+        Err(Fail::new(
+            libc::ENOTSUP,
+            "queue descriptor duplication is not supported",
+        ))
+    }
+
     /// Accepts connections on a socket.
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("accept(): qd={:?}", qd);
@@ -274,8 +313,12 @@ impl CatnapWLibOS {
 
     /// Handles a pushto operation.
     fn do_pushto(&mut self, qd: QDesc, buf: DemiBuffer, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        let max_push_size: usize = self.max_push_size(qd)?;
         match self.sockets.get(&qd) {
             Some(socket) => {
+                if buf.len() > max_push_size && socket.borrow().r#type().ok() == Some(Type::DGRAM) {
+                    return Err(Fail::new(libc::EMSGSIZE, "datagram is too large"));
+                }
                 let addr: SockAddr = parse_addr(remote);
                 let future: Operation = Operation::from(PushtoFuture::new(qd, socket.clone(), addr, buf));
                 let handle: SchedulerHandle = match self.runtime.scheduler.insert(future) {
@@ -294,10 +337,6 @@ impl CatnapWLibOS {
 
         match self.runtime.clone_sgarray(sga) {
             Ok(buf) => {
-                if buf.len() == 0 {
-                    return Err(Fail::new(EINVAL, "zero-length buffer"));
-                }
-
                 // Issue pushto operation.
                 self.do_pushto(qd, buf, remote)
             },
@@ -305,6 +344,70 @@ impl CatnapWLibOS {
         }
     }
 
+    /// Returns the number of bytes that may be popped right away from a socket. For a UDP socket, this is the size of
+    /// the next datagram. Windows does not report the accept queue of listening sockets, so these report zero.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("bytes_available() qd={:?}", qd);
+        match self.sockets.get(&qd) {
+            Some(socket) => {
+                let raw_socket: WinSock::SOCKET = WinSock::SOCKET(socket.borrow().as_raw_socket() as usize);
+                let mut nbytes: u32 = 0;
+                if unsafe { WinSock::ioctlsocket(raw_socket, WinSock::FIONREAD, &mut nbytes) } != 0 {
+                    let errno: i32 = unsafe { WinSock::WSAGetLastError() }.0;
+                    let cause: String = format!("failed to query receive queue (errno={:?})", errno);
+                    error!("bytes_available(): {}", cause);
+                    return Err(Fail::new(errno, &cause));
+                }
+                Ok(nbytes as usize)
+            },
+            _ => Err(Fail::new(EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single push transfers as a unit on a socket. Pushes to a UDP socket
+    /// that exceed this size fail with `EMSGSIZE`, whereas pushes to a TCP socket are split by the kernel.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("max_push_size() qd={:?}", qd);
+        match self.sockets.get(&qd) {
+            Some(socket) => match socket.borrow().r#type() {
+                Ok(Type::DGRAM) => Ok(UDP_MAX_PAYLOAD_SIZE),
+                _ => Ok(TCP_MAX_PUSH_SIZE),
+            },
+            _ => Err(Fail::new(EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Returns the local address that a socket is bound to.
+    pub fn getsockname(&self, qd: QDesc) -> Result<SocketAddrV4, Fail> {
+        trace!("getsockname() qd={:?}", qd);
+        match self.sockets.get(&qd) {
+            Some(socket) => match socket.borrow().local_addr() {
+                Ok(addr) => match addr.as_socket_ipv4() {
+                    Some(addr) => Ok(addr),
+                    None => Err(Fail::new(EINVAL, "socket is not bound to an IPv4 address")),
+                },
+                Err(e) => Err(Fail::new(e.kind() as i32, "unable to get socket name")),
+            },
+            _ => Err(Fail::new(EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Forces transmission of all data that is buffered on a socket. Pushed data is handed over to the kernel as soon
+    /// as possible, thus this only checks if `qd` refers to a valid socket.
+    pub fn flush(&self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush() qd={:?}", qd);
+        match self.sockets.get(&qd) {
+            Some(_) => Ok(()),
+            _ => Err(Fail::new(EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Pops data from a socket with a timeout. This is not supported on this LibOS.
+    pub fn pop_timeout(&mut self, qd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("pop_timeout() qd={:?}, size={:?}, timeout={:?}", qd, size, timeout);
+        Err(Fail::new(libc::ENOTSUP, "pop with timeout is not supported"))
+    }
+
     /// Pops data from a socket.
     pub fn pop(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("pop() qd={:?}", qd);
@@ -395,6 +498,10 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
             qr_qd: qd.into(),
             qr_qt: qt,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
         OperationResult::Accept((new_qd, addr)) => {
             let saddr: SockAddrIn = {
@@ -422,6 +529,10 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
                 qr_qd: qd.into(),
                 qr_qt: qt,
                 qr_value,
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
         OperationResult::Push => demi_qresult_t {
@@ -429,8 +540,12 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
             qr_qd: qd.into(),
             qr_qt: qt,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
-        OperationResult::Pop(addr, bytes) => match rt.into_sgarray(bytes) {
+        OperationResult::Pop(addr, bytes, eof) => match rt.into_sgarray(bytes) {
             Ok(mut sga) => {
                 if let Some(endpoint) = addr {
                     let saddr: SockAddrIn = {
@@ -454,6 +569,10 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
                     qr_qd: qd.into(),
                     qr_qt: qt,
                     qr_value,
+                    qr_flags: if eof { DEMI_QR_EOF } else { 0 },
+                    qr_start_ns: 0,
+                    qr_end_ns: 0,
+                    qr_rx_ns: 0,
                 }
             },
             Err(e) => {
@@ -463,6 +582,10 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
                     qr_qd: qd.into(),
                     qr_qt: qt,
                     qr_value: unsafe { mem::zeroed() },
+                    qr_flags: 0,
+                    qr_start_ns: 0,
+                    qr_end_ns: 0,
+                    qr_rx_ns: 0,
                 }
             },
         },
@@ -473,6 +596,10 @@ fn pack_result(rt: &PosixRuntime, result: OperationResult, qd: QDesc, qt: u64) -
                 qr_qd: qd.into(),
                 qr_qt: qt,
                 qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
     }
@@ -6,8 +6,16 @@
 //======================================================================================================================
 
 use crate::{
+    catnip::runtime::{
+        DEFAULT_RX_FREE_THRESH,
+        DEFAULT_RX_RING_SIZE,
+        DEFAULT_TX_RING_SIZE,
+    },
     demikernel::config::Config,
-    runtime::network::types::MacAddress,
+    runtime::{
+        fail::Fail,
+        network::types::MacAddress,
+    },
 };
 use ::anyhow::Error;
 use ::std::{
@@ -64,6 +72,52 @@ impl Config {
         }
     }
 
+    /// Reads the "RX ring size" parameter from the underlying configuration file, which is the number of descriptors in
+    /// the receive ring of the NIC. If this parameter is not present, then the default ring size is returned.
+    pub fn rx_ring_size(&self) -> Result<u16, Fail> {
+        self.dpdk_descriptor_count("rx_ring_size", DEFAULT_RX_RING_SIZE)
+    }
+
+    /// Reads the "TX ring size" parameter from the underlying configuration file, which is the number of descriptors in
+    /// the transmit ring of the NIC. If this parameter is not present, then the default ring size is returned.
+    pub fn tx_ring_size(&self) -> Result<u16, Fail> {
+        self.dpdk_descriptor_count("tx_ring_size", DEFAULT_TX_RING_SIZE)
+    }
+
+    /// Reads the "RX free threshold" parameter from the underlying configuration file, which is the number of used
+    /// receive descriptors that the NIC accumulates before refilling them. Lower values refill more often, which keeps
+    /// the receive ring from running dry under sustained load at the expense of more work per packet. If this
+    /// parameter is not present, then the default threshold is returned.
+    pub fn rx_free_thresh(&self) -> Result<u16, Fail> {
+        self.dpdk_descriptor_count("rx_free_thresh", DEFAULT_RX_FREE_THRESH)
+    }
+
+    /// Reads the "promiscuous" parameter from the underlying configuration file, which tells whether the NIC should take
+    /// in frames that are not addressed to it. If this parameter is not present, then the NIC runs in promiscuous mode.
+    ///
+    /// Beware that in promiscuous mode the LibOS gets to see all traffic on the link, including that of other hosts,
+    /// and spends cycles on dropping it. Unless the NIC can filter multicast frames, turning promiscuous mode off
+    /// keeps the LibOS from receiving multicast traffic, though.
+    pub fn promiscuous(&self) -> bool {
+        self.0["dpdk"]["promiscuous"].as_bool().unwrap_or(true)
+    }
+
+    /// Reads a descriptor count from the "dpdk" section of the underlying configuration file. Counts that do not fit in
+    /// 16 bits or are zero are rejected with `EINVAL`.
+    fn dpdk_descriptor_count(&self, key: &str, default: u16) -> Result<u16, Fail> {
+        match self.0["dpdk"][key].as_i64() {
+            Some(count) => match u16::try_from(count) {
+                Ok(count) if count > 0 => Ok(count),
+                _ => {
+                    let cause: String = format!("invalid {} in config ({:?})", key, count);
+                    error!("dpdk_descriptor_count(): {}", cause);
+                    Err(Fail::new(libc::EINVAL, &cause))
+                },
+            },
+            None => Ok(default),
+        }
+    }
+
     /// Reads the "ARP Disable" parameter from the underlying configuration file.
     pub fn disable_arp(&self) -> bool {
         // TODO: this should be unified with arp_table().
@@ -15,19 +15,27 @@ use crate::{
         },
     },
     runtime::{
-        memory::MemoryRuntime,
+        fail::Fail,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+        },
         types::{
             demi_accept_result_t,
             demi_opcode_t,
             demi_qr_value_t,
             demi_qresult_t,
+            demi_sgarray_t,
+            DEMI_QR_EOF,
         },
         QDesc,
     },
+    scheduler::TaskTiming,
     OperationResult,
 };
 use ::std::{
     mem,
+    net::SocketAddrV4,
     rc::Rc,
 };
 
@@ -39,6 +47,10 @@ pub fn pack_result(rt: Rc<DPDKRuntime>, result: OperationResult, qd: QDesc, qt:
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
         OperationResult::Accept((new_qd, addr)) => {
             let saddr: SockAddrIn = {
@@ -61,6 +73,10 @@ pub fn pack_result(rt: Rc<DPDKRuntime>, result: OperationResult, qd: QDesc, qt:
                 qr_qt: qt,
                 qr_ret: 0,
                 qr_value,
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
         OperationResult::Push => demi_qresult_t {
@@ -69,39 +85,83 @@ pub fn pack_result(rt: Rc<DPDKRuntime>, result: OperationResult, qd: QDesc, qt:
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
-        OperationResult::Pop(addr, bytes) => match rt.into_sgarray(bytes) {
-            Ok(mut sga) => {
-                if let Some(endpoint) = addr {
-                    let saddr: SockAddrIn = {
-                        SockAddrIn {
-                            sin_family: AF_INET,
-                            sin_port: endpoint.port().into(),
-                            sin_addr: create_sin_addr(&endpoint.ip().octets()),
-                            sin_zero: create_sin_zero(),
-                        }
-                    };
-                    sga.sga_addr = unsafe { mem::transmute::<SockAddrIn, SockAddr>(saddr) };
-                }
-                let qr_value = demi_qr_value_t { sga };
-                demi_qresult_t {
-                    qr_opcode: demi_opcode_t::DEMI_OPC_POP,
-                    qr_qd: qd.into(),
-                    qr_qt: qt,
-                    qr_ret: 0,
-                    qr_value,
-                }
-            },
-            Err(e) => {
-                warn!("Operation Failed: {:?}", e);
-                demi_qresult_t {
-                    qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
-                    qr_qd: qd.into(),
-                    qr_qt: qt,
-                    qr_ret: e.errno as i64,
-                    qr_value: unsafe { mem::zeroed() },
-                }
-            },
+        OperationResult::Pop(addr, bytes, eof) => {
+            let rx_ns: u64 = bytes.sw_timestamp().map_or(0, TaskTiming::to_nanos);
+            match rt.into_sgarray(bytes) {
+                Ok(mut sga) => {
+                    if let Some(endpoint) = addr {
+                        let saddr: SockAddrIn = {
+                            SockAddrIn {
+                                sin_family: AF_INET,
+                                sin_port: endpoint.port().into(),
+                                sin_addr: create_sin_addr(&endpoint.ip().octets()),
+                                sin_zero: create_sin_zero(),
+                            }
+                        };
+                        sga.sga_addr = unsafe { mem::transmute::<SockAddrIn, SockAddr>(saddr) };
+                    }
+                    let qr_value = demi_qr_value_t { sga };
+                    demi_qresult_t {
+                        qr_opcode: demi_opcode_t::DEMI_OPC_POP,
+                        qr_qd: qd.into(),
+                        qr_qt: qt,
+                        qr_ret: 0,
+                        qr_value,
+                        qr_flags: if eof { DEMI_QR_EOF } else { 0 },
+                        qr_start_ns: 0,
+                        qr_end_ns: 0,
+                        qr_rx_ns: rx_ns,
+                    }
+                },
+                Err(e) => {
+                    warn!("Operation Failed: {:?}", e);
+                    demi_qresult_t {
+                        qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
+                        qr_qd: qd.into(),
+                        qr_qt: qt,
+                        qr_ret: e.errno as i64,
+                        qr_value: unsafe { mem::zeroed() },
+                        qr_flags: 0,
+                        qr_start_ns: 0,
+                        qr_end_ns: 0,
+                        qr_rx_ns: 0,
+                    }
+                },
+            }
+        },
+        OperationResult::PopBatch(_) => {
+            warn!("Operation Failed: batched pop results cannot be packed into a single result");
+            demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: libc::ENOTSUP as i64,
+                qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
+            }
+        },
+        #[cfg(feature = "dhcp")]
+        OperationResult::Dhcp(_) => {
+            warn!("Operation Failed: DHCP leases cannot be packed into a result");
+            demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: libc::ENOTSUP as i64,
+                qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
+            }
         },
         OperationResult::Close => demi_qresult_t {
             qr_opcode: demi_opcode_t::DEMI_OPC_CLOSE,
@@ -109,6 +169,10 @@ pub fn pack_result(rt: Rc<DPDKRuntime>, result: OperationResult, qd: QDesc, qt:
             qr_qt: qt,
             qr_ret: 0,
             qr_value: unsafe { mem::zeroed() },
+            qr_flags: 0,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
         },
         OperationResult::Failed(e) => {
             warn!("Operation Failed: {:?}", e);
@@ -118,7 +182,49 @@ pub fn pack_result(rt: Rc<DPDKRuntime>, result: OperationResult, qd: QDesc, qt:
                 qr_qt: qt,
                 qr_ret: e.errno as i64,
                 qr_value: unsafe { mem::zeroed() },
+                qr_flags: 0,
+                qr_start_ns: 0,
+                qr_end_ns: 0,
+                qr_rx_ns: 0,
             }
         },
     }
 }
+
+/// Packs the messages of an [OperationResult::PopBatch] into scatter-gather arrays, one per message.
+pub fn pack_batch_result(rt: Rc<DPDKRuntime>, result: OperationResult) -> Result<Vec<demi_sgarray_t>, Fail> {
+    let msgs: Vec<(Option<SocketAddrV4>, DemiBuffer)> = match result {
+        OperationResult::PopBatch(msgs) => msgs,
+        OperationResult::Failed(e) => return Err(e),
+        _ => return Err(Fail::new(libc::EINVAL, "not a batched pop operation")),
+    };
+    let mut sgas: Vec<demi_sgarray_t> = Vec::with_capacity(msgs.len());
+    for (addr, bytes) in msgs {
+        match rt.into_sgarray(bytes) {
+            Ok(mut sga) => {
+                if let Some(endpoint) = addr {
+                    let saddr: SockAddrIn = {
+                        SockAddrIn {
+                            sin_family: AF_INET,
+                            sin_port: endpoint.port().into(),
+                            sin_addr: create_sin_addr(&endpoint.ip().octets()),
+                            sin_zero: create_sin_zero(),
+                        }
+                    };
+                    sga.sga_addr = unsafe { mem::transmute::<SockAddrIn, SockAddr>(saddr) };
+                }
+                sgas.push(sga);
+            },
+            Err(e) => {
+                // Release the scatter-gather arrays that were packed so far.
+                for sga in sgas {
+                    if let Err(e) = rt.free_sgarray(sga) {
+                        warn!("leaking scatter-gather array: {:?}", e);
+                    }
+                }
+                return Err(e);
+            },
+        }
+    }
+    Ok(sgas)
+}
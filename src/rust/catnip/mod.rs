@@ -10,8 +10,14 @@ pub mod runtime;
 //==============================================================================
 
 use self::{
-    interop::pack_result,
-    runtime::DPDKRuntime,
+    interop::{
+        pack_batch_result,
+        pack_result,
+    },
+    runtime::{
+        DPDKRuntime,
+        RxTuning,
+    },
 };
 use crate::{
     demikernel::config::Config,
@@ -19,8 +25,11 @@ use crate::{
     runtime::{
         fail::Fail,
         libdpdk::load_mlx_driver,
-        memory::MemoryRuntime,
-        network::consts::RECEIVE_BATCH_SIZE,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+        },
+        network::types::DpdkPortStats,
         timer::{
             Timer,
             TimerRc,
@@ -45,7 +54,10 @@ use ::std::{
         DerefMut,
     },
     rc::Rc,
-    time::Instant,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 #[cfg(feature = "profiler")]
@@ -58,7 +70,7 @@ use crate::timer;
 /// Catnip LibOS
 pub struct CatnipLibOS {
     scheduler: Scheduler,
-    inetstack: InetStack<RECEIVE_BATCH_SIZE>,
+    inetstack: InetStack,
     rt: Rc<DPDKRuntime>,
 }
 
@@ -68,7 +80,7 @@ pub struct CatnipLibOS {
 
 /// Associate Functions for Catnip LibOS
 impl CatnipLibOS {
-    pub fn new(config: &Config) -> Self {
+    pub fn new(config: &Config) -> Result<Self, Fail> {
         load_mlx_driver();
         let rt: Rc<DPDKRuntime> = Rc::new(DPDKRuntime::new(
             config.local_ipv4_addr(),
@@ -80,12 +92,21 @@ impl CatnipLibOS {
             config.mss(),
             config.tcp_checksum_offload(),
             config.udp_checksum_offload(),
-        ));
+            config.receive_batch_size()?,
+            config.gro_max_size()?,
+            config.rx_ring_size()?,
+            config.tx_ring_size()?,
+            config.rx_free_thresh()?,
+            config.promiscuous(),
+        )?);
         let now: Instant = Instant::now();
         let clock: TimerRc = TimerRc(Rc::new(Timer::new(now)));
-        let scheduler: Scheduler = Scheduler::default();
+        let scheduler: Scheduler = match config.scheduler_capacity() {
+            Some(capacity) => Scheduler::with_capacity(capacity),
+            None => Scheduler::default(),
+        };
         let rng_seed: [u8; 32] = [0; 32];
-        let inetstack: InetStack<RECEIVE_BATCH_SIZE> = InetStack::new(
+        let mut inetstack: InetStack = InetStack::new(
             rt.clone(),
             scheduler.clone(),
             clock,
@@ -95,13 +116,14 @@ impl CatnipLibOS {
             rt.tcp_options.clone(),
             rng_seed,
             rt.arp_options.clone(),
-        )
-        .unwrap();
-        CatnipLibOS {
+            None,
+        )?;
+        inetstack.set_max_open_queues(config.max_open_queues());
+        Ok(CatnipLibOS {
             inetstack,
             scheduler,
             rt,
-        }
+        })
     }
 
     /// Create a push request for Demikernel to asynchronously write data from `sga` to the
@@ -128,15 +150,31 @@ impl CatnipLibOS {
         }
     }
 
+    /// Same as [Self::push], but the operation completes only once the peer acknowledges all data in `sga`. It fails
+    /// with `ETIMEDOUT` if that does not happen within `timeout`, in which case the connection is aborted.
+    pub fn push_timeout(&mut self, qd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("catnip::push_timeout");
+        trace!("push_timeout(): qd={:?}, timeout={:?}", qd, timeout);
+        let buf: DemiBuffer = self.rt.clone_sgarray(sga)?;
+        if buf.len() == 0 {
+            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
+        }
+        let future = self.do_push_timeout(qd, buf, timeout)?;
+        let handle: TaskHandle = match self.scheduler.insert(future) {
+            Some(handle) => handle,
+            None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+        };
+        let qt: QToken = handle.get_task_id().into();
+        Ok(qt)
+    }
+
     pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, to: SocketAddrV4) -> Result<QToken, Fail> {
         #[cfg(feature = "profiler")]
         timer!("catnip::pushto");
         trace!("pushto2(): qd={:?}", qd);
         match self.rt.clone_sgarray(sga) {
             Ok(buf) => {
-                if buf.len() == 0 {
-                    return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-                }
                 let future = self.do_pushto(qd, buf, to)?;
                 let handle: TaskHandle = match self.scheduler.insert(future) {
                     Some(handle) => handle,
@@ -149,6 +187,32 @@ impl CatnipLibOS {
         }
     }
 
+    /// Pushes a batch of scatter-gather arrays to a UDP socket, each one to its own remote address. Each datagram
+    /// completes on its own queue token.
+    pub fn pushto_batch(
+        &mut self,
+        qd: QDesc,
+        datagrams: &[(demi_sgarray_t, SocketAddrV4)],
+    ) -> Result<Vec<QToken>, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("catnip::pushto_batch");
+        trace!("pushto_batch(): qd={:?}, count={:?}", qd, datagrams.len());
+        let mut bufs: Vec<(DemiBuffer, SocketAddrV4)> = Vec::with_capacity(datagrams.len());
+        for (sga, to) in datagrams {
+            let buf: DemiBuffer = self.rt.clone_sgarray(sga)?;
+            bufs.push((buf, *to));
+        }
+        let mut qts: Vec<QToken> = Vec::with_capacity(bufs.len());
+        for task in self.do_pushto_batch(qd, bufs)? {
+            let handle: TaskHandle = match self.scheduler.insert(task) {
+                Some(handle) => handle,
+                None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+            };
+            qts.push(handle.get_task_id().into());
+        }
+        Ok(qts)
+    }
+
     pub fn schedule(&mut self, qt: QToken) -> Result<TaskHandle, Fail> {
         match self.scheduler.from_task_id(qt.into()) {
             Some(handle) => Ok(handle),
@@ -161,6 +225,12 @@ impl CatnipLibOS {
         Ok(pack_result(self.rt.clone(), r, qd, qt.into()))
     }
 
+    /// Packs the result of a batched pop into one scatter-gather array per message.
+    pub fn pack_batch_result(&mut self, handle: TaskHandle) -> Result<Vec<demi_sgarray_t>, Fail> {
+        let (_, r): (QDesc, OperationResult) = self.take_operation(handle);
+        pack_batch_result(self.rt.clone(), r)
+    }
+
     /// Allocates a scatter-gather array.
     pub fn sgaalloc(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
         self.rt.alloc_sgarray(size)
@@ -170,6 +240,21 @@ impl CatnipLibOS {
     pub fn sgafree(&self, sga: demi_sgarray_t) -> Result<(), Fail> {
         self.rt.free_sgarray(sga)
     }
+
+    /// Tunes the receive path of the NIC, trading latency for CPU efficiency.
+    pub fn configure_rx(&self, tuning: RxTuning) -> Result<(), Fail> {
+        self.rt.configure_rx(tuning)
+    }
+
+    /// Returns the statistics that the NIC keeps for the port of this LibOS.
+    pub fn port_stats(&self) -> Result<DpdkPortStats, Fail> {
+        self.rt.port_stats()
+    }
+
+    /// Resets the statistics that the NIC keeps for the port of this LibOS.
+    pub fn reset_port_stats(&self) -> Result<(), Fail> {
+        self.rt.reset_port_stats()
+    }
 }
 
 //==============================================================================
@@ -178,7 +263,7 @@ impl CatnipLibOS {
 
 /// De-Reference Trait Implementation for Catnip LibOS
 impl Deref for CatnipLibOS {
-    type Target = InetStack<RECEIVE_BATCH_SIZE>;
+    type Target = InetStack;
 
     fn deref(&self) -> &Self::Target {
         &self.inetstack
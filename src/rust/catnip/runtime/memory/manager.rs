@@ -18,7 +18,12 @@ use crate::{
             rte_mbuf,
             rte_mempool,
         },
-        memory::DemiBuffer,
+        memory::{
+            check_sgarray,
+            track_sgarray,
+            untrack_sgarray,
+            DemiBuffer,
+        },
         types::{
             demi_sgarray_t,
             demi_sgaseg_t,
@@ -91,12 +96,14 @@ impl MemoryManager {
         };
 
         // Create and return a new scatter-gather array (which inherits the DemiBuffer's reference).
-        Ok(demi_sgarray_t {
+        let sga: demi_sgarray_t = demi_sgarray_t {
             sga_buf: buf.into_raw().as_ptr() as *mut c_void,
             sga_numsegs: 1,
             sga_segs: [sga_seg],
             sga_addr: unsafe { mem::zeroed() },
-        })
+        };
+        track_sgarray(&sga);
+        Ok(sga)
     }
 
     /// Allocates a header mbuf.
@@ -142,12 +149,14 @@ impl MemoryManager {
         };
 
         // Create and return a new scatter-gather array (which inherits the DemiBuffer's reference).
-        Ok(demi_sgarray_t {
+        let sga: demi_sgarray_t = demi_sgarray_t {
             sga_buf: buf.into_raw().as_ptr() as *mut c_void,
             sga_numsegs: 1,
             sga_segs: [sga_seg],
             sga_addr: unsafe { mem::zeroed() },
-        })
+        };
+        track_sgarray(&sga);
+        Ok(sga)
     }
 
     /// Releases a scatter-gather array.
@@ -161,6 +170,7 @@ impl MemoryManager {
         if sga.sga_buf == ptr::null_mut() {
             return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid DemiBuffer token"));
         }
+        untrack_sgarray(&sga);
 
         // Convert back to a DemiBuffer and drop it.
         // Safety: The `NonNull::new_unchecked()` call is safe, as we verified `sga.sga_buf` is not null above.
@@ -184,6 +194,7 @@ impl MemoryManager {
         if sga.sga_buf == ptr::null_mut() {
             return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid DemiBuffer token"));
         }
+        check_sgarray(sga);
 
         // Convert back to a DemiBuffer.
         // Safety: The `NonNull::new_unchecked()` call is safe, as we verified `sga.sga_buf` is not null above.
@@ -32,7 +32,7 @@ use crate::runtime::{
 
 /// Memory Runtime Trait Implementation for DPDK Runtime
 impl MemoryRuntime for DPDKRuntime {
-    /// Casts a [DPDKBuf] into an [demi_sgarray_t].
+    /// Casts a [DemiBuffer] into an [demi_sgarray_t].
     fn into_sgarray(&self, buf: DemiBuffer) -> Result<demi_sgarray_t, Fail> {
         self.mm.into_sgarray(buf)
     }
@@ -3,6 +3,13 @@
 
 pub mod memory;
 mod network;
+mod rxtuning;
+
+//==============================================================================
+// Exports
+//==============================================================================
+
+pub use self::rxtuning::RxTuning;
 
 //==============================================================================
 // Imports
@@ -12,66 +19,124 @@ use self::memory::{
     consts::DEFAULT_MAX_BODY_SIZE,
     MemoryManager,
 };
-use crate::runtime::{
-    libdpdk::{
-        rte_delay_us_block,
-        rte_eal_init,
-        rte_eth_conf,
-        rte_eth_dev_configure,
-        rte_eth_dev_count_avail,
-        rte_eth_dev_get_mtu,
-        rte_eth_dev_info_get,
-        rte_eth_dev_is_valid_port,
-        rte_eth_dev_set_mtu,
-        rte_eth_dev_start,
-        rte_eth_find_next_owned_by,
-        rte_eth_link,
-        rte_eth_link_get_nowait,
-        rte_eth_macaddr_get,
-        rte_eth_promiscuous_enable,
-        rte_eth_rss_ip,
-        rte_eth_rx_mq_mode_RTE_ETH_MQ_RX_RSS as RTE_ETH_MQ_RX_RSS,
-        rte_eth_rx_offload_tcp_cksum,
-        rte_eth_rx_offload_udp_cksum,
-        rte_eth_rx_queue_setup,
-        rte_eth_rxconf,
-        rte_eth_tx_mq_mode_RTE_ETH_MQ_TX_NONE as RTE_ETH_MQ_TX_NONE,
-        rte_eth_tx_offload_multi_segs,
-        rte_eth_tx_offload_tcp_cksum,
-        rte_eth_tx_offload_udp_cksum,
-        rte_eth_tx_queue_setup,
-        rte_eth_txconf,
-        rte_ether_addr,
-        RTE_ETHER_MAX_JUMBO_FRAME_LEN,
-        RTE_ETHER_MAX_LEN,
-        RTE_ETH_DEV_NO_OWNER,
-        RTE_ETH_LINK_FULL_DUPLEX,
-        RTE_ETH_LINK_UP,
-        RTE_PKTMBUF_HEADROOM,
+use crate::{
+    inetstack::protocols::{
+        ethernet2::ETHERNET2_HEADER_SIZE,
+        ipv4::IPV4_HEADER_MIN_SIZE,
+        tcp::MIN_TCP_HEADER_SIZE,
     },
-    network::{
-        config::{
-            ArpConfig,
-            TcpConfig,
-            UdpConfig,
+    runtime::{
+        fail::Fail,
+        libdpdk::{
+            rte_delay_us_block,
+            rte_eal_init,
+            rte_eth_conf,
+            rte_eth_dev_adjust_nb_rx_tx_desc,
+            rte_eth_dev_configure,
+            rte_eth_dev_count_avail,
+            rte_eth_dev_get_mtu,
+            rte_eth_dev_info_get,
+            rte_eth_dev_is_valid_port,
+            rte_eth_dev_set_mtu,
+            rte_eth_dev_start,
+            rte_eth_find_next_owned_by,
+            rte_eth_link,
+            rte_eth_link_get_nowait,
+            rte_eth_macaddr_get,
+            rte_eth_promiscuous_disable,
+            rte_eth_promiscuous_enable,
+            rte_eth_promiscuous_get,
+            rte_eth_rss_ip,
+            rte_eth_rx_mq_mode_RTE_ETH_MQ_RX_RSS as RTE_ETH_MQ_RX_RSS,
+            rte_eth_rx_offload_tcp_cksum,
+            rte_eth_rx_offload_udp_cksum,
+            rte_eth_rx_queue_setup,
+            rte_eth_rxconf,
+            rte_eth_stats,
+            rte_eth_stats_get,
+            rte_eth_stats_reset,
+            rte_eth_timesync_enable,
+            rte_eth_timesync_read_tx_timestamp,
+            rte_eth_tx_mq_mode_RTE_ETH_MQ_TX_NONE as RTE_ETH_MQ_TX_NONE,
+            rte_eth_tx_offload_multi_segs,
+            rte_eth_tx_offload_tcp_cksum,
+            rte_eth_tx_offload_udp_cksum,
+            rte_eth_tx_queue_setup,
+            rte_eth_txconf,
+            rte_ether_addr,
+            rte_mbuf_dynfield,
+            rte_mbuf_dynfield_register_offset,
+            timespec,
+            RTE_ETHER_MAX_JUMBO_FRAME_LEN,
+            RTE_ETHER_MAX_LEN,
+            RTE_ETH_DEV_NO_OWNER,
+            RTE_ETH_LINK_FULL_DUPLEX,
+            RTE_ETH_LINK_UP,
+            RTE_PKTMBUF_HEADROOM,
+        },
+        memory::{
+            HW_TIMESTAMP_DYNFIELD_OFFSET,
+            SW_TIMESTAMP_DYNFIELD_OFFSET,
         },
-        types::MacAddress,
+        network::{
+            check_receive_batch_size,
+            config::{
+                ArpConfig,
+                TcpConfig,
+                UdpConfig,
+            },
+            types::{
+                DpdkPortStats,
+                Ipv4IdGenerator,
+                MacAddress,
+                NicStats,
+            },
+            NetworkRuntime,
+        },
+        Runtime,
     },
-    Runtime,
 };
 use ::anyhow::{
     bail,
     format_err,
     Error,
 };
+use ::rand::{
+    prelude::SmallRng,
+    SeedableRng,
+};
 use ::std::{
+    cell::Cell,
     collections::HashMap,
     ffi::CString,
-    mem::MaybeUninit,
+    mem::{
+        self,
+        MaybeUninit,
+    },
     net::Ipv4Addr,
+    rc::Rc,
     time::Duration,
 };
 
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Name of the MBuf dynamic field that holds IEEE 1588 receive timestamps.
+const HW_TIMESTAMP_DYNFIELD_NAME: &str = "demikernel_dynfield_hw_timestamp";
+
+/// Name of the MBuf dynamic field that holds software receive timestamps.
+const SW_TIMESTAMP_DYNFIELD_NAME: &str = "demikernel_dynfield_sw_timestamp";
+
+/// Default number of descriptors in the receive ring of the NIC.
+pub const DEFAULT_RX_RING_SIZE: u16 = 2048;
+
+/// Default number of descriptors in the transmit ring of the NIC.
+pub const DEFAULT_TX_RING_SIZE: u16 = 2048;
+
+/// Default number of used receive descriptors that the NIC accumulates before refilling them in one go.
+pub const DEFAULT_RX_FREE_THRESH: u16 = 32;
+
 //==============================================================================
 // Macros
 //==============================================================================
@@ -87,6 +152,22 @@ macro_rules! expect_zero {
     }};
 }
 
+//==============================================================================
+// Foreign Functions
+//==============================================================================
+
+// Virtual function management of the ixgbe poll mode driver (see rte_pmd_ixgbe.h). These fail with `-ENOTSUP` when
+// the port is not driven by the ixgbe PMD. DPDK only exports them from the ixgbe PMD library, which is linked in with
+// the `ixgbe` feature.
+#[cfg(feature = "ixgbe")]
+extern "C" {
+    fn rte_pmd_ixgbe_set_vf_mac_addr(port: u16, vf: u16, mac_addr: *mut rte_ether_addr) -> libc::c_int;
+    fn rte_pmd_ixgbe_set_vf_vlan_insert(port: u16, vf: u16, vlan_id: u16) -> libc::c_int;
+    fn rte_pmd_ixgbe_set_vf_vlan_stripq(port: u16, vf: u16, on: u8) -> libc::c_int;
+    fn rte_pmd_ixgbe_set_vf_mac_anti_spoof(port: u16, vf: u16, on: u8) -> libc::c_int;
+    fn rte_pmd_ixgbe_set_vf_vlan_anti_spoof(port: u16, vf: u16, on: u8) -> libc::c_int;
+}
+
 //==============================================================================
 // Structures
 //==============================================================================
@@ -101,6 +182,15 @@ pub struct DPDKRuntime {
     pub arp_options: ArpConfig,
     pub tcp_options: TcpConfig,
     pub udp_options: UdpConfig,
+    receive_batch_size: usize,
+    /// Maximum number of packets taken from the NIC per receive call.
+    rx_burst_size: Rc<Cell<usize>>,
+    /// Number of TSC cycles to keep polling the NIC before returning an empty burst.
+    rx_timeout_cycles: Rc<Cell<u64>>,
+    /// Counters for the frames transmitted and received on the NIC.
+    stats: Rc<Cell<NicStats>>,
+    /// Identification of the IPv4 datagrams transmitted on the NIC.
+    ipv4_id: Ipv4IdGenerator,
 }
 
 //==============================================================================
@@ -119,38 +209,63 @@ impl DPDKRuntime {
         mss: usize,
         tcp_checksum_offload: bool,
         udp_checksum_offload: bool,
-    ) -> DPDKRuntime {
+        receive_batch_size: usize,
+        gro_max_size: usize,
+        rx_ring_size: u16,
+        tx_ring_size: u16,
+        rx_free_thresh: u16,
+        promiscuous: bool,
+    ) -> Result<DPDKRuntime, Fail> {
+        // Reject a bad batch size before initializing DPDK, as a batch size of zero would silently stall the receive
+        // path and a larger one than the receive batch can hold would overflow it.
+        let receive_batch_size: usize = check_receive_batch_size(receive_batch_size)?;
+
+        // Likewise, reject descriptor counts that the NIC cannot work with.
+        if rx_ring_size == 0 || tx_ring_size == 0 || rx_free_thresh == 0 || rx_free_thresh >= rx_ring_size {
+            let cause: String = format!(
+                "invalid descriptor counts (rx_ring_size={:?}, tx_ring_size={:?}, rx_free_thresh={:?})",
+                rx_ring_size, tx_ring_size, rx_free_thresh
+            );
+            error!("new(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
         let (mm, port_id, link_addr) = Self::initialize_dpdk(
             eal_init_args,
             use_jumbo_frames,
             mtu,
             tcp_checksum_offload,
             udp_checksum_offload,
+            rx_ring_size,
+            tx_ring_size,
+            rx_free_thresh,
+            promiscuous,
         )
         .unwrap();
 
-        let arp_options = ArpConfig::new(
-            Some(Duration::from_secs(15)),
-            Some(Duration::from_secs(20)),
-            Some(5),
-            Some(arp_table),
-            Some(disable_arp),
-        );
-
-        let tcp_options = TcpConfig::new(
-            Some(mss),
-            None,
-            None,
-            Some(0xffff),
-            Some(0),
-            None,
-            Some(tcp_checksum_offload),
-            Some(tcp_checksum_offload),
-        );
-
-        let udp_options = UdpConfig::new(Some(udp_checksum_offload), Some(udp_checksum_offload));
-
-        Self {
+        // Every received packet is stamped with the time it was received at.
+        Self::register_timestamp_dynfield(SW_TIMESTAMP_DYNFIELD_NAME, SW_TIMESTAMP_DYNFIELD_OFFSET)?;
+
+        let arp_options: ArpConfig = ArpConfig::builder()
+            .cache_ttl(Duration::from_secs(15))
+            .request_timeout(Duration::from_secs(20))
+            .max_retries(5)
+            .static_table(arp_table)
+            .disable(disable_arp)
+            .build();
+
+        let tcp_options = TcpConfig::builder()
+            .advertised_mss(mss)
+            .receive_window_size(0xffff)
+            .window_scale(0)
+            .rx_checksum_offload(tcp_checksum_offload)
+            .tx_checksum_offload(tcp_checksum_offload)
+            .gro_max_size(gro_max_size)
+            .build();
+
+        let udp_options = UdpConfig::new(Some(udp_checksum_offload), Some(udp_checksum_offload), None);
+
+        let mut runtime: DPDKRuntime = Self {
             mm,
             port_id,
             link_addr,
@@ -158,6 +273,58 @@ impl DPDKRuntime {
             arp_options,
             tcp_options,
             udp_options,
+            receive_batch_size,
+            rx_burst_size: Rc::new(Cell::new(receive_batch_size)),
+            rx_timeout_cycles: Rc::new(Cell::new(0)),
+            stats: Rc::new(Cell::new(NicStats::default())),
+            ipv4_id: Ipv4IdGenerator::new(&mut SmallRng::from_entropy()),
+        };
+
+        // Full-sized segments must fit in the MTU of the NIC, along with their headers.
+        let max_mss: usize = runtime
+            .get_mtu()
+            .saturating_sub(IPV4_HEADER_MIN_SIZE as usize + MIN_TCP_HEADER_SIZE);
+        runtime.tcp_options.clamp_advertised_mss(max_mss);
+
+        Ok(runtime)
+    }
+
+    /// Sets the number of TSC cycles to keep polling the NIC before returning an empty burst, which trades latency for
+    /// CPU efficiency. Zero returns right away.
+    pub fn set_rx_timeout_cycles(&self, n: u64) {
+        self.rx_timeout_cycles.set(n);
+    }
+
+    /// Applies receive tuning parameters. Parameters that are not set keep their current value.
+    pub fn configure_rx(&self, tuning: RxTuning) -> Result<(), Fail> {
+        if let Some(n) = tuning.get_burst_size() {
+            self.set_rx_burst_size(n)?;
+        }
+        if let Some(n) = tuning.get_timeout_cycles() {
+            self.set_rx_timeout_cycles(n);
+        }
+        Ok(())
+    }
+
+    /// Returns whether the NIC runs in promiscuous mode, thus taking in frames that are not addressed to it.
+    pub fn is_promiscuous(&self) -> bool {
+        unsafe { rte_eth_promiscuous_get(self.port_id) == 1 }
+    }
+
+    /// Returns the MTU of the NIC, as bounded by the largest frame that the NIC takes in.
+    pub fn get_mtu(&self) -> usize {
+        let dev_info: dpdk_rs::rte_eth_dev_info = unsafe {
+            let mut d: MaybeUninit<dpdk_rs::rte_eth_dev_info> = MaybeUninit::zeroed();
+            rte_eth_dev_info_get(self.port_id, d.as_mut_ptr());
+            d.assume_init()
+        };
+        let max_mtu: usize = (dev_info.max_rx_pktlen as usize).saturating_sub(ETHERNET2_HEADER_SIZE);
+
+        // Prefer the MTU that the port was configured with, if the NIC reports it.
+        let mut mtu: u16 = 0;
+        match unsafe { rte_eth_dev_get_mtu(self.port_id, &mut mtu) } {
+            0 => (mtu as usize).min(max_mtu),
+            _ => max_mtu,
         }
     }
 
@@ -168,6 +335,10 @@ impl DPDKRuntime {
         mtu: u16,
         tcp_checksum_offload: bool,
         udp_checksum_offload: bool,
+        rx_ring_size: u16,
+        tx_ring_size: u16,
+        rx_free_thresh: u16,
+        promiscuous: bool,
     ) -> Result<(MemoryManager, u16, MacAddress), Error> {
         std::env::set_var("MLX5_SHUT_UP_BF", "1");
         std::env::set_var("MLX5_SINGLE_THREADED", "1");
@@ -201,6 +372,10 @@ impl DPDKRuntime {
             mtu,
             tcp_checksum_offload,
             udp_checksum_offload,
+            rx_ring_size,
+            tx_ring_size,
+            rx_free_thresh,
+            promiscuous,
         )?;
 
         // TODO: Where is this function?
@@ -221,6 +396,26 @@ impl DPDKRuntime {
         Ok((memory_manager, port_id, local_link_addr))
     }
 
+    /// Turns promiscuous mode of a DPDK port on or off. Some NICs cannot run in promiscuous mode, which is fine as long
+    /// as the frames that are addressed to us get through. A NIC that is required to stay out of promiscuous mode but
+    /// does not is an error, though.
+    fn set_promiscuous_mode(port_id: u16, promiscuous: bool) -> Result<(), Error> {
+        if promiscuous {
+            let ret: libc::c_int = unsafe { rte_eth_promiscuous_enable(port_id) };
+            if ret != 0 {
+                warn!("cannot enable promiscuous mode (port_id={:?}, ret={:?})", port_id, ret);
+            }
+        } else {
+            unsafe {
+                expect_zero!(rte_eth_promiscuous_disable(port_id))?;
+            }
+            if unsafe { rte_eth_promiscuous_get(port_id) } != 0 {
+                bail!("Failed to disable promiscuous mode on port {}", port_id);
+            }
+        }
+        Ok(())
+    }
+
     /// Initializes a DPDK port.
     fn initialize_dpdk_port(
         port_id: u16,
@@ -229,13 +424,15 @@ impl DPDKRuntime {
         mtu: u16,
         tcp_checksum_offload: bool,
         udp_checksum_offload: bool,
+        rx_ring_size: u16,
+        tx_ring_size: u16,
+        rx_free_thresh: u16,
+        promiscuous: bool,
     ) -> Result<(), Error> {
         let rx_rings: u16 = 1;
         let tx_rings: u16 = 1;
-        let rx_ring_size: u16 = 2048;
-        let tx_ring_size: u16 = 2048;
-        let nb_rxd: u16 = rx_ring_size;
-        let nb_txd: u16 = tx_ring_size;
+        let mut nb_rxd: u16 = rx_ring_size;
+        let mut nb_txd: u16 = tx_ring_size;
 
         let rx_pthresh: u8 = 8;
         let rx_hthresh: u8 = 8;
@@ -280,7 +477,7 @@ impl DPDKRuntime {
         rx_conf.rx_thresh.pthresh = rx_pthresh;
         rx_conf.rx_thresh.hthresh = rx_hthresh;
         rx_conf.rx_thresh.wthresh = rx_wthresh;
-        rx_conf.rx_free_thresh = 32;
+        rx_conf.rx_free_thresh = rx_free_thresh;
 
         let mut tx_conf: rte_eth_txconf = unsafe { MaybeUninit::zeroed().assume_init() };
         tx_conf.tx_thresh.pthresh = tx_pthresh;
@@ -306,6 +503,24 @@ impl DPDKRuntime {
             }
         }
 
+        // Round the descriptor counts to what the NIC supports.
+        unsafe {
+            expect_zero!(rte_eth_dev_adjust_nb_rx_tx_desc(port_id, &mut nb_rxd, &mut nb_txd))?;
+        }
+        if nb_rxd != rx_ring_size || nb_txd != tx_ring_size {
+            warn!(
+                "adjusted descriptor counts to the limits of the NIC (nb_rxd={:?}, nb_txd={:?})",
+                nb_rxd, nb_txd
+            );
+        }
+        if rx_free_thresh >= nb_rxd {
+            bail!(
+                "Receive refill threshold {} does not fit in {} receive descriptors",
+                rx_free_thresh,
+                nb_rxd
+            );
+        }
+
         let socket_id: u32 = 0;
 
         unsafe {
@@ -329,8 +544,8 @@ impl DPDKRuntime {
                 ))?;
             }
             expect_zero!(rte_eth_dev_start(port_id))?;
-            rte_eth_promiscuous_enable(port_id);
         }
+        Self::set_promiscuous_mode(port_id, promiscuous)?;
 
         if unsafe { rte_eth_dev_is_valid_port(port_id) } == 0 {
             bail!("Invalid port");
@@ -366,6 +581,182 @@ impl DPDKRuntime {
 
         Ok(())
     }
+
+    /// Enables IEEE 1588 hardware timestamping on the port. Once enabled, received packets that the NIC timestamped
+    /// carry their timestamp (see `DemiBuffer::hw_timestamp()`), and transmit timestamps may be read back with
+    /// [DPDKRuntime::read_tx_timestamp].
+    pub fn enable_hardware_timestamps(&self) -> Result<(), Fail> {
+        // Reserve the MBuf dynamic field where we store receive timestamps, so that nobody else uses it.
+        Self::register_timestamp_dynfield(HW_TIMESTAMP_DYNFIELD_NAME, HW_TIMESTAMP_DYNFIELD_OFFSET)?;
+
+        match unsafe { rte_eth_timesync_enable(self.port_id) } {
+            0 => Ok(()),
+            ret if ret == -libc::ENOTSUP => {
+                let cause: String = format!("hardware timestamps not supported (port_id={:?})", self.port_id);
+                error!("enable_hardware_timestamps(): {}", cause);
+                Err(Fail::new(libc::EOPNOTSUPP, &cause))
+            },
+            ret => {
+                let cause: String = format!("failed to enable hardware timestamps (ret={:?})", ret);
+                error!("enable_hardware_timestamps(): {}", cause);
+                Err(Fail::new(-ret, &cause))
+            },
+        }
+    }
+
+    /// Reserves the MBuf dynamic field `name`, which holds a 64-bit timestamp at `offset`, so that nobody else uses it.
+    fn register_timestamp_dynfield(name: &str, offset: usize) -> Result<(), Fail> {
+        let mut params: rte_mbuf_dynfield = unsafe { mem::zeroed() };
+        for (dst, src) in params.name.iter_mut().zip(name.as_bytes()) {
+            *dst = *src as _;
+        }
+        params.size = mem::size_of::<u64>();
+        params.align = mem::align_of::<u64>();
+        if unsafe { rte_mbuf_dynfield_register_offset(&params, offset) } < 0 {
+            let rte_errno: libc::c_int = unsafe { dpdk_rs::rte_errno() };
+            let cause: String = format!("failed to register {} field (rte_errno={:?})", name, rte_errno);
+            error!("register_timestamp_dynfield(): {}", cause);
+            return Err(Fail::new(libc::EBUSY, &cause));
+        }
+        Ok(())
+    }
+
+    /// Configures the SR-IOV virtual function `vf_id` of the physical function port `pf_port_id`: assigns it the link
+    /// address `mac` and, unless `vlan` is zero, inserts `vlan` as VLAN tag on transmission and strips it on reception.
+    /// The physical function must be driven by this DPDK instance, separately from the port of this runtime. Fails
+    /// with `EOPNOTSUPP` if the poll mode driver of the physical function does not support virtual function
+    /// configuration.
+    #[cfg(feature = "ixgbe")]
+    pub fn configure_vf(&self, pf_port_id: u16, vf_id: u16, mac: MacAddress, vlan: u16) -> Result<(), Fail> {
+        trace!(
+            "configure_vf(): pf_port_id={:?}, vf_id={:?}, mac={:?}, vlan={:?}",
+            pf_port_id,
+            vf_id,
+            mac,
+            vlan
+        );
+        if !mac.is_unicast() {
+            let cause: String = format!("virtual function link address must be unicast (mac={:?})", mac);
+            error!("configure_vf(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        let mut addr: rte_ether_addr = rte_ether_addr {
+            addr_bytes: mac.octets(),
+        };
+        vf_result("rte_pmd_ixgbe_set_vf_mac_addr", unsafe {
+            rte_pmd_ixgbe_set_vf_mac_addr(pf_port_id, vf_id, &mut addr)
+        })?;
+        // A VLAN identifier of zero disables tag insertion.
+        vf_result("rte_pmd_ixgbe_set_vf_vlan_insert", unsafe {
+            rte_pmd_ixgbe_set_vf_vlan_insert(pf_port_id, vf_id, vlan)
+        })?;
+        vf_result("rte_pmd_ixgbe_set_vf_vlan_stripq", unsafe {
+            rte_pmd_ixgbe_set_vf_vlan_stripq(pf_port_id, vf_id, (vlan != 0) as u8)
+        })
+    }
+
+    /// Turns MAC and VLAN anti-spoofing on or off for the SR-IOV virtual function `vf_id` of the physical function
+    /// port `pf_port_id`. With anti-spoofing off, the virtual function may send frames with any source link address
+    /// and VLAN tag, as needed for virtual functions to talk to each other. Fails with `EOPNOTSUPP` if the poll mode
+    /// driver of the physical function does not support virtual function configuration.
+    #[cfg(feature = "ixgbe")]
+    pub fn set_vf_anti_spoof(&self, pf_port_id: u16, vf_id: u16, enable: bool) -> Result<(), Fail> {
+        trace!(
+            "set_vf_anti_spoof(): pf_port_id={:?}, vf_id={:?}, enable={:?}",
+            pf_port_id,
+            vf_id,
+            enable
+        );
+        vf_result("rte_pmd_ixgbe_set_vf_mac_anti_spoof", unsafe {
+            rte_pmd_ixgbe_set_vf_mac_anti_spoof(pf_port_id, vf_id, enable as u8)
+        })?;
+        vf_result("rte_pmd_ixgbe_set_vf_vlan_anti_spoof", unsafe {
+            rte_pmd_ixgbe_set_vf_vlan_anti_spoof(pf_port_id, vf_id, enable as u8)
+        })
+    }
+
+    /// Reads the IEEE 1588 hardware timestamp (in nanoseconds) of the last packet that was transmitted with a
+    /// timestamp request (see `DemiBuffer::request_tx_hw_timestamp()`), if the NIC has one available.
+    pub fn read_tx_timestamp(&self) -> Option<u64> {
+        let mut ts: timespec = unsafe { mem::zeroed() };
+        match unsafe { rte_eth_timesync_read_tx_timestamp(self.port_id, &mut ts) } {
+            0 => Some(timespec_to_nanos(&ts)),
+            _ => None,
+        }
+    }
+
+    /// Queries the NIC for the statistics that it keeps for the port of this runtime. These count frames since the
+    /// port was started, or since they were last reset with [DPDKRuntime::reset_port_stats].
+    pub fn port_stats(&self) -> Result<DpdkPortStats, Fail> {
+        let mut eth_stats: rte_eth_stats = unsafe { mem::zeroed() };
+        match unsafe { rte_eth_stats_get(self.port_id, &mut eth_stats) } {
+            0 => Ok(DpdkPortStats {
+                rx_packets: eth_stats.ipackets,
+                rx_bytes: eth_stats.ibytes,
+                rx_missed: eth_stats.imissed,
+                rx_errors: eth_stats.ierrors,
+                tx_packets: eth_stats.opackets,
+                tx_bytes: eth_stats.obytes,
+                tx_errors: eth_stats.oerrors,
+            }),
+            ret => {
+                let cause: String = format!(
+                    "failed to query port statistics (port_id={:?}, ret={:?})",
+                    self.port_id, ret
+                );
+                error!("port_stats(): {}", cause);
+                Err(Fail::new(-ret, &cause))
+            },
+        }
+    }
+
+    /// Resets the statistics that the NIC keeps for the port of this runtime.
+    pub fn reset_port_stats(&self) -> Result<(), Fail> {
+        match unsafe { rte_eth_stats_reset(self.port_id) } {
+            0 => Ok(()),
+            ret if ret == -libc::ENOTSUP => {
+                let cause: String = format!("resetting port statistics not supported (port_id={:?})", self.port_id);
+                error!("reset_port_stats(): {}", cause);
+                Err(Fail::new(libc::EOPNOTSUPP, &cause))
+            },
+            ret => {
+                let cause: String = format!(
+                    "failed to reset port statistics (port_id={:?}, ret={:?})",
+                    self.port_id, ret
+                );
+                error!("reset_port_stats(): {}", cause);
+                Err(Fail::new(-ret, &cause))
+            },
+        }
+    }
+}
+
+//==============================================================================
+// Standalone Functions
+//==============================================================================
+
+/// Converts the return value of a virtual function management call of a poll mode driver into a result.
+#[cfg(feature = "ixgbe")]
+fn vf_result(name: &str, ret: libc::c_int) -> Result<(), Fail> {
+    match ret {
+        0 => Ok(()),
+        ret if ret == -libc::ENOTSUP => {
+            let cause: String = format!("virtual function configuration not supported ({})", name);
+            error!("{}", cause);
+            Err(Fail::new(libc::EOPNOTSUPP, &cause))
+        },
+        ret => {
+            let cause: String = format!("{} failed (ret={:?})", name, ret);
+            error!("{}", cause);
+            Err(Fail::new(-ret, &cause))
+        },
+    }
+}
+
+/// Converts a timestamp reported by the NIC into nanoseconds.
+fn timespec_to_nanos(ts: &timespec) -> u64 {
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
 }
 
 //==============================================================================
@@ -373,3 +764,242 @@ impl DPDKRuntime {
 //==============================================================================
 
 impl Runtime for DPDKRuntime {}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "ixgbe")]
+    use super::vf_result;
+    use super::DPDKRuntime;
+    use crate::{
+        demikernel::config::Config,
+        runtime::{
+            memory::DemiBuffer,
+            network::{
+                types::{
+                    DpdkPortStats,
+                    MacAddress,
+                    NicStats,
+                },
+                NetworkRuntime,
+                PacketBuf,
+            },
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        env,
+        net::Ipv4Addr,
+        thread,
+        time::Duration,
+    };
+
+    /// Number of descriptors in the receive ring of the runtime under test.
+    const SMALL_RX_RING_SIZE: u16 = 64;
+
+    /// A raw ARP request, which is enough to have the peer answer.
+    struct ArpRequest {
+        frame: Vec<u8>,
+    }
+
+    impl ArpRequest {
+        fn new(local_link_addr: MacAddress, local_ipv4_addr: Ipv4Addr, remote_ipv4_addr: Ipv4Addr) -> Self {
+            let mut frame: Vec<u8> = Vec::with_capacity(42);
+            frame.extend_from_slice(&MacAddress::broadcast().octets());
+            frame.extend_from_slice(&local_link_addr.octets());
+            frame.extend_from_slice(&[0x08, 0x06, 0x00, 0x01, 0x08, 0x00, 6, 4, 0x00, 0x01]);
+            frame.extend_from_slice(&local_link_addr.octets());
+            frame.extend_from_slice(&local_ipv4_addr.octets());
+            frame.extend_from_slice(&[0; 6]);
+            frame.extend_from_slice(&remote_ipv4_addr.octets());
+            Self { frame }
+        }
+    }
+
+    impl PacketBuf for ArpRequest {
+        fn header_size(&self) -> usize {
+            self.frame.len()
+        }
+
+        fn write_header(&self, buf: &mut [u8]) {
+            buf[..self.frame.len()].copy_from_slice(&self.frame);
+        }
+
+        fn body_size(&self) -> usize {
+            0
+        }
+
+        fn take_body(&self) -> Option<DemiBuffer> {
+            None
+        }
+    }
+
+    /// Tests if frames that the NIC drops for lack of receive descriptors show up in the statistics. This configures a
+    /// small receive ring and has the peer in the ARP table of the configuration file answer many more ARP requests
+    /// than the ring holds, without ever receiving. Thus, it needs a DPDK port wired to that peer.
+    #[ignore]
+    #[test]
+    fn rx_missed_on_small_ring() -> Result<()> {
+        let config: Config = Config::new(env::var("CONFIG_PATH")?);
+        let local_ipv4_addr: Ipv4Addr = config.local_ipv4_addr();
+        let remote_ipv4_addr: Ipv4Addr = match config.arp_table().into_keys().find(|addr| *addr != local_ipv4_addr) {
+            Some(addr) => addr,
+            None => anyhow::bail!("the ARP table in the configuration file should name a peer"),
+        };
+        let rt: DPDKRuntime = match DPDKRuntime::new(
+            local_ipv4_addr,
+            &config.eal_init_args(),
+            config.arp_table(),
+            true,
+            false,
+            1500,
+            1460,
+            false,
+            false,
+            config.receive_batch_size()?,
+            config.gro_max_size()?,
+            SMALL_RX_RING_SIZE,
+            config.tx_ring_size()?,
+            SMALL_RX_RING_SIZE / 2,
+            true,
+        ) {
+            Ok(rt) => rt,
+            Err(e) => anyhow::bail!("DPDKRuntime::new() failed: {:?}", e),
+        };
+
+        // Have the peer answer more requests than fit in the receive ring.
+        let before: NicStats = rt.nic_stats();
+        for _ in 0..(16 * SMALL_RX_RING_SIZE as usize) {
+            rt.transmit(Box::new(ArpRequest::new(
+                rt.link_addr,
+                local_ipv4_addr,
+                remote_ipv4_addr,
+            )));
+        }
+        thread::sleep(Duration::from_millis(500));
+        let after: NicStats = rt.nic_stats();
+
+        crate::ensure_eq!(after.rx_missed > before.rx_missed, true);
+
+        Ok(())
+    }
+
+    /// Tests if the port statistics of the NIC count the frames that we transmit, starting over once they are reset.
+    /// This transmits ARP requests to the peer in the ARP table of the configuration file, thus it needs a DPDK port.
+    #[ignore]
+    #[test]
+    fn port_stats_count_transmitted_frames() -> Result<()> {
+        const NUM_FRAMES: u64 = 128;
+        let config: Config = Config::new(env::var("CONFIG_PATH")?);
+        let local_ipv4_addr: Ipv4Addr = config.local_ipv4_addr();
+        let remote_ipv4_addr: Ipv4Addr = match config.arp_table().into_keys().find(|addr| *addr != local_ipv4_addr) {
+            Some(addr) => addr,
+            None => anyhow::bail!("the ARP table in the configuration file should name a peer"),
+        };
+        let rt: DPDKRuntime = match DPDKRuntime::new(
+            local_ipv4_addr,
+            &config.eal_init_args(),
+            config.arp_table(),
+            true,
+            false,
+            1500,
+            1460,
+            false,
+            false,
+            config.receive_batch_size()?,
+            config.gro_max_size()?,
+            config.rx_ring_size()?,
+            config.tx_ring_size()?,
+            config.rx_free_thresh()?,
+            true,
+        ) {
+            Ok(rt) => rt,
+            Err(e) => anyhow::bail!("DPDKRuntime::new() failed: {:?}", e),
+        };
+
+        if let Err(e) = rt.reset_port_stats() {
+            anyhow::bail!("reset_port_stats() failed: {:?}", e);
+        }
+        let frame_len: usize = ArpRequest::new(rt.link_addr, local_ipv4_addr, remote_ipv4_addr)
+            .frame
+            .len();
+        for _ in 0..NUM_FRAMES {
+            rt.transmit(Box::new(ArpRequest::new(
+                rt.link_addr,
+                local_ipv4_addr,
+                remote_ipv4_addr,
+            )));
+        }
+        // The NIC updates its counters on its own schedule.
+        thread::sleep(Duration::from_millis(500));
+        let stats: DpdkPortStats = match rt.port_stats() {
+            Ok(stats) => stats,
+            Err(e) => anyhow::bail!("port_stats() failed: {:?}", e),
+        };
+
+        crate::ensure_eq!(stats.tx_packets, NUM_FRAMES);
+        crate::ensure_eq!(stats.tx_bytes >= NUM_FRAMES * frame_len as u64, true);
+        crate::ensure_eq!(stats.tx_errors, 0);
+
+        Ok(())
+    }
+
+    /// Tests if the NIC runs in promiscuous mode only when asked to. This starts with promiscuous mode off, contrary to
+    /// the default, and turns it on and off again. Thus, it needs a DPDK port whose NIC supports promiscuous mode.
+    #[ignore]
+    #[test]
+    fn promiscuous_mode_follows_flag() -> Result<()> {
+        let config: Config = Config::new(env::var("CONFIG_PATH")?);
+        let rt: DPDKRuntime = match DPDKRuntime::new(
+            config.local_ipv4_addr(),
+            &config.eal_init_args(),
+            config.arp_table(),
+            true,
+            false,
+            1500,
+            1460,
+            false,
+            false,
+            config.receive_batch_size()?,
+            config.gro_max_size()?,
+            config.rx_ring_size()?,
+            config.tx_ring_size()?,
+            config.rx_free_thresh()?,
+            false,
+        ) {
+            Ok(rt) => rt,
+            Err(e) => anyhow::bail!("DPDKRuntime::new() failed: {:?}", e),
+        };
+        crate::ensure_eq!(rt.is_promiscuous(), false);
+
+        DPDKRuntime::set_promiscuous_mode(rt.port_id, true)?;
+        crate::ensure_eq!(rt.is_promiscuous(), true);
+
+        DPDKRuntime::set_promiscuous_mode(rt.port_id, false)?;
+        crate::ensure_eq!(rt.is_promiscuous(), false);
+
+        Ok(())
+    }
+
+    /// Tests if the return values of virtual function management calls map to the errors that they stand for. Drivers
+    /// that do not manage virtual functions report `-ENOTSUP`, which maps to `EOPNOTSUPP`.
+    #[cfg(feature = "ixgbe")]
+    #[test]
+    fn vf_result_maps_errno() -> Result<()> {
+        crate::ensure_eq!(vf_result("test", 0).is_ok(), true);
+        for (ret, errno) in [
+            (-libc::ENOTSUP, libc::EOPNOTSUPP),
+            (-libc::EINVAL, libc::EINVAL),
+            (-libc::ENODEV, libc::ENODEV),
+        ] {
+            match vf_result("test", ret) {
+                Ok(()) => anyhow::bail!("call should have failed (ret={:?})", ret),
+                Err(e) => crate::ensure_eq!(e.errno, errno),
+            }
+        }
+        Ok(())
+    }
+}
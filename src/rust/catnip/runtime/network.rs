@@ -5,42 +5,81 @@
 // Imports
 //==============================================================================
 
-use super::DPDKRuntime;
+use super::{
+    timespec_to_nanos,
+    DPDKRuntime,
+};
 use crate::{
     inetstack::protocols::ethernet2::MIN_PAYLOAD_SIZE,
     runtime::{
+        fail::Fail,
         libdpdk::{
+            rte_eth_dev_set_mc_addr_list,
+            rte_eth_link,
+            rte_eth_link_get_nowait,
             rte_eth_rx_burst,
+            rte_eth_stats,
+            rte_eth_stats_get,
+            rte_eth_timesync_read_rx_timestamp,
             rte_eth_tx_burst,
+            rte_ether_addr,
             rte_mbuf,
             rte_pktmbuf_chain,
+            rte_pktmbuf_free,
+            timespec,
+            RTE_ETH_LINK_UP,
+            RTE_MBUF_F_RX_IEEE1588_TMST,
         },
         memory::DemiBuffer,
         network::{
+            consts::MAX_RECEIVE_BATCH_SIZE,
+            types::{
+                LinkStatus,
+                MacAddress,
+                NicStats,
+            },
             NetworkRuntime,
             PacketBuf,
+            ReceiveBatch,
         },
     },
 };
-use ::arrayvec::ArrayVec;
-use ::std::mem;
+use ::std::{
+    mem,
+    time::Instant,
+};
+use ::x86::time::rdtsc;
 
 #[cfg(feature = "profiler")]
 use crate::timer;
 
 //==============================================================================
-// Trait Implementations
+// Associated Functions
 //==============================================================================
 
-/// Network Runtime Trait Implementation for DPDK Runtime
-impl<const N: usize> NetworkRuntime<N> for DPDKRuntime {
-    fn transmit(&self, buf: Box<dyn PacketBuf>) {
-        // TODO: Consider an important optimization here: If there is data in this packet (i.e. not just headers), and
-        // that data is in a DPDK-owned mbuf, and there is "headroom" in that mbuf to hold the packet headers, just
-        // prepend the headers into that mbuf and save the extra header mbuf allocation that we currently always do.
-
+impl DPDKRuntime {
+    /// Serializes a [PacketBuf] into a chain of MBufs that is ready to be handed over to the NIC.
+    fn cook_mbuf(&self, buf: Box<dyn PacketBuf>) -> *mut rte_mbuf {
         // TODO: cleanup unwrap() and expect() from this code when this function returns a Result.
 
+        // If the headers were prepended to the body, then the body holds the whole frame already.  If it is stored in
+        // an mbuf too, hand it over to the NIC as is, padding it up to the minimum frame size if it is too short.
+        let header_size: usize = buf.header_size();
+        let body: Option<DemiBuffer> = match buf.take_body() {
+            Some(mut frame) if header_size == 0 && frame.is_dpdk_allocated() => {
+                let padding_bytes: usize = MIN_PAYLOAD_SIZE.saturating_sub(frame.len());
+                match frame.append(padding_bytes) {
+                    Ok(padding_buf) => {
+                        padding_buf.fill(0);
+                        return frame.into_mbuf().expect("'frame' should be DPDK-allocated");
+                    },
+                    // There is no room to pad the frame, so copy it into a header mbuf instead.
+                    Err(_) => Some(frame),
+                }
+            },
+            body => body,
+        };
+
         // Alloc header mbuf, check header size.
         // Serialize header.
         // Decide if we can inline the data --
@@ -48,8 +87,8 @@ impl<const N: usize> NetworkRuntime<N> for DPDKRuntime {
         //   2) Is the body small enough?
         // If we can inline, copy and return.
         // If we can't inline...
-        //   1) See if the body is managed => take
-        //   2) Not managed => alloc body
+        //   1) See if the body is stored in an mbuf => take
+        //   2) Stored on the heap => alloc body mbuf
         // Chain body buffer.
 
         // First, allocate a header mbuf and write the header into it.
@@ -57,11 +96,15 @@ impl<const N: usize> NetworkRuntime<N> for DPDKRuntime {
             Ok(mbuf) => mbuf,
             Err(e) => panic!("failed to allocate header mbuf: {:?}", e.cause),
         };
-        let header_size = buf.header_size();
         assert!(header_size <= header_mbuf.len());
         buf.write_header(&mut header_mbuf[..header_size]);
 
-        if let Some(body) = buf.take_body() {
+        if let Some(body) = body {
+            // Carry a request for a transmit timestamp over to the first segment of the packet.
+            if body.tx_hw_timestamp_requested() {
+                header_mbuf.request_tx_hw_timestamp();
+            }
+
             // Next, see how much space we have remaining and inline the body if we have room.
             let inline_space = header_mbuf.len() - header_size;
 
@@ -77,7 +120,7 @@ impl<const N: usize> NetworkRuntime<N> for DPDKRuntime {
                     // The body is already stored in an MBuf, just extract it from the DemiBuffer.
                     body.into_mbuf().expect("'body' should be DPDK-allocated")
                 } else {
-                    // The body is not dpdk-allocated, allocate a DPDKBuffer and copy the body into it.
+                    // The body is not dpdk-allocated, allocate an mbuf and copy the body into it.
                     let mut mbuf: DemiBuffer = match self.mm.alloc_body_mbuf() {
                         Ok(mbuf) => mbuf,
                         Err(e) => panic!("failed to allocate body mbuf: {:?}", e.cause),
@@ -88,14 +131,18 @@ impl<const N: usize> NetworkRuntime<N> for DPDKRuntime {
                     mbuf.into_mbuf().expect("mbuf should not be empty")
                 };
 
-                let mut header_mbuf_ptr: *mut rte_mbuf = header_mbuf.into_mbuf().expect("mbuf should not be empty");
+                // The headers were prepended to the body, so there is nothing to chain the body to.
+                if header_size == 0 {
+                    return body_mbuf;
+                }
+
+                let header_mbuf_ptr: *mut rte_mbuf = header_mbuf.into_mbuf().expect("mbuf should not be empty");
                 // Safety: rte_pktmbuf_chain is a FFI that is safe to call as both of its args are valid MBuf pointers.
                 unsafe {
                     // Attach the body MBuf onto the header MBuf's buffer chain.
                     assert_eq!(rte_pktmbuf_chain(header_mbuf_ptr, body_mbuf), 0);
                 }
-                let num_sent = unsafe { rte_eth_tx_burst(self.port_id, 0, &mut header_mbuf_ptr, 1) };
-                assert_eq!(num_sent, 1);
+                header_mbuf_ptr
             }
             // Otherwise, write in the inline space.
             else {
@@ -113,9 +160,7 @@ impl<const N: usize> NetworkRuntime<N> for DPDKRuntime {
                 let frame_size = std::cmp::max(header_size + body.len(), MIN_PAYLOAD_SIZE);
                 header_mbuf.trim(header_mbuf.len() - frame_size).unwrap();
 
-                let mut header_mbuf_ptr: *mut rte_mbuf = header_mbuf.into_mbuf().expect("mbuf cannot be empty");
-                let num_sent = unsafe { rte_eth_tx_burst(self.port_id, 0, &mut header_mbuf_ptr, 1) };
-                assert_eq!(num_sent, 1);
+                header_mbuf.into_mbuf().expect("mbuf cannot be empty")
             }
         }
         // No body on our packet, just send the headers.
@@ -129,34 +174,174 @@ impl<const N: usize> NetworkRuntime<N> for DPDKRuntime {
             }
             let frame_size = std::cmp::max(header_size, MIN_PAYLOAD_SIZE);
             header_mbuf.trim(header_mbuf.len() - frame_size).unwrap();
-            let mut header_mbuf_ptr: *mut rte_mbuf = header_mbuf.into_mbuf().expect("mbuf cannot be empty");
-            let num_sent = unsafe { rte_eth_tx_burst(self.port_id, 0, &mut header_mbuf_ptr, 1) };
-            assert_eq!(num_sent, 1);
+            header_mbuf.into_mbuf().expect("mbuf cannot be empty")
         }
     }
+}
 
-    fn receive(&self) -> ArrayVec<DemiBuffer, N> {
-        let mut out = ArrayVec::new();
+//==============================================================================
+// Trait Implementations
+//==============================================================================
 
-        let mut packets: [*mut rte_mbuf; N] = unsafe { mem::zeroed() };
-        let nb_rx = unsafe {
-            #[cfg(feature = "profiler")]
-            timer!("catnip_libos::receive::rte_eth_rx_burst");
+/// Network Runtime Trait Implementation for DPDK Runtime
+impl NetworkRuntime for DPDKRuntime {
+    fn transmit(&self, buf: Box<dyn PacketBuf>) {
+        let mut mbuf_ptr: *mut rte_mbuf = self.cook_mbuf(buf);
+        let len: usize = unsafe { (*mbuf_ptr).pkt_len } as usize;
+        let num_sent = unsafe { rte_eth_tx_burst(self.port_id, 0, &mut mbuf_ptr, 1) };
+        let mut stats: NicStats = self.stats.get();
+        if num_sent == 1 {
+            stats.record_tx(len);
+        } else {
+            // The transmit ring is full, so drop the packet.
+            warn!("dropping packet: transmit ring is full");
+            unsafe { rte_pktmbuf_free(mbuf_ptr) };
+            stats.record_tx_drop();
+        }
+        self.stats.set(stats);
+    }
+
+    fn transmit_batch(&self, bufs: Vec<Box<dyn PacketBuf>>) {
+        let mut mbuf_ptrs: Vec<*mut rte_mbuf> = bufs.into_iter().map(|buf| self.cook_mbuf(buf)).collect();
+
+        // The NIC may take fewer packets than we hand over to it, so keep going until all of them are taken.
+        let mut stats: NicStats = self.stats.get();
+        for &mbuf_ptr in &mbuf_ptrs {
+            stats.record_tx(unsafe { (*mbuf_ptr).pkt_len } as usize);
+        }
+        let mut index: usize = 0;
+        while index < mbuf_ptrs.len() {
+            let count: u16 = (mbuf_ptrs.len() - index).min(u16::MAX as usize) as u16;
+            let num_sent: u16 = unsafe { rte_eth_tx_burst(self.port_id, 0, mbuf_ptrs[index..].as_mut_ptr(), count) };
+            index += num_sent as usize;
+        }
+        self.stats.set(stats);
+    }
 
-            rte_eth_rx_burst(self.port_id, 0, packets.as_mut_ptr(), N as u16)
+    fn next_ipv4_id(&self) -> u16 {
+        self.ipv4_id.next_id()
+    }
+
+    fn receive(&self) -> ReceiveBatch {
+        let mut out = ReceiveBatch::new();
+
+        let mut packets: [*mut rte_mbuf; MAX_RECEIVE_BATCH_SIZE] = unsafe { mem::zeroed() };
+        let rx_burst_size: usize = self.rx_burst_size.get();
+        let rx_timeout_cycles: u64 = self.rx_timeout_cycles.get();
+        // Safety: reading the TSC has no side effects.
+        let start: u64 = if rx_timeout_cycles > 0 { unsafe { rdtsc() } } else { 0 };
+        let nb_rx = loop {
+            let nb_rx = unsafe {
+                #[cfg(feature = "profiler")]
+                timer!("catnip_libos::receive::rte_eth_rx_burst");
+
+                rte_eth_rx_burst(self.port_id, 0, packets.as_mut_ptr(), rx_burst_size as u16)
+            };
+            // Keep polling an idle NIC until the receive timeout expires.
+            if nb_rx > 0 || rx_timeout_cycles == 0 || unsafe { rdtsc() }.wrapping_sub(start) >= rx_timeout_cycles {
+                break nb_rx;
+            }
         };
-        assert!(nb_rx as usize <= N);
+        assert!(nb_rx as usize <= rx_burst_size);
+        // Stamp the packets of this burst with the time they were received at.
+        let now: Instant = Instant::now();
 
         {
             #[cfg(feature = "profiler")]
             timer!("catnip_libos:receive::for");
+            let mut stats: NicStats = self.stats.get();
             for &packet in &packets[..nb_rx as usize] {
                 // Safety: `packet` is a valid pointer to a properly initialized `rte_mbuf` struct.
-                let buf: DemiBuffer = unsafe { DemiBuffer::from_mbuf(packet) };
+                stats.record_rx(unsafe { (*packet).pkt_len } as usize);
+                let mut buf: DemiBuffer = unsafe { DemiBuffer::from_mbuf(packet) };
+                buf.set_sw_timestamp(Some(now));
+
+                // If the NIC timestamped this packet, read the timestamp and annotate the buffer with it.
+                if unsafe { (*packet).ol_flags } & RTE_MBUF_F_RX_IEEE1588_TMST as u64 != 0 {
+                    let mut ts: timespec = unsafe { mem::zeroed() };
+                    let timesync: u32 = unsafe { (*packet).timesync } as u32;
+                    match unsafe { rte_eth_timesync_read_rx_timestamp(self.port_id, &mut ts, timesync) } {
+                        0 => buf.set_hw_timestamp(Some(timespec_to_nanos(&ts))),
+                        _ => buf.set_hw_timestamp(None),
+                    }
+                }
+
                 out.push(buf);
             }
+            self.stats.set(stats);
         }
 
         out
     }
+
+    fn nic_stats(&self) -> NicStats {
+        let mut stats: NicStats = self.stats.get();
+
+        // Frames that never made it into a receive descriptor are only known to the NIC.
+        let mut eth_stats: rte_eth_stats = unsafe { mem::zeroed() };
+        match unsafe { rte_eth_stats_get(self.port_id, &mut eth_stats) } {
+            0 => {
+                stats.rx_missed = eth_stats.imissed;
+                stats.rx_no_mbuf = eth_stats.rx_nombuf;
+            },
+            ret => warn!(
+                "nic_stats(): failed to query NIC statistics (port_id={:?}, ret={:?})",
+                self.port_id, ret
+            ),
+        }
+
+        stats
+    }
+
+    fn link_status(&self) -> LinkStatus {
+        let mut link: rte_eth_link = unsafe { mem::zeroed() };
+        // Do not wait for the link to settle, as this may be polled on the data path.
+        if unsafe { rte_eth_link_get_nowait(self.port_id, &mut link) } != 0 {
+            warn!(
+                "link_status(): failed to query link status (port_id={:?})",
+                self.port_id
+            );
+            return LinkStatus::down();
+        }
+        if link.link_status() as u32 == RTE_ETH_LINK_UP {
+            LinkStatus::up(link.link_speed)
+        } else {
+            LinkStatus::down()
+        }
+    }
+
+    fn mtu(&self) -> usize {
+        self.get_mtu()
+    }
+
+    fn set_rx_burst_size(&self, n: usize) -> Result<(), Fail> {
+        if n == 0 || n > self.receive_batch_size {
+            let cause: String = format!(
+                "invalid receive burst size (n={:?}, max={:?})",
+                n, self.receive_batch_size
+            );
+            error!("set_rx_burst_size(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        self.rx_burst_size.set(n);
+        Ok(())
+    }
+
+    fn set_multicast_filter(&self, addrs: &[MacAddress]) {
+        let mut mc_addrs: Vec<rte_ether_addr> = addrs
+            .iter()
+            .map(|addr| rte_ether_addr {
+                addr_bytes: addr.octets(),
+            })
+            .collect();
+        // Unless promiscuous mode is turned off, multicast frames still get through if the NIC cannot filter them.
+        let ret: libc::c_int =
+            unsafe { rte_eth_dev_set_mc_addr_list(self.port_id, mc_addrs.as_mut_ptr(), mc_addrs.len() as u32) };
+        if ret != 0 {
+            warn!(
+                "set_multicast_filter(): cannot set multicast filter (port_id={:?}, ret={:?})",
+                self.port_id, ret
+            );
+        }
+    }
 }
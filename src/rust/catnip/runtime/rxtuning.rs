@@ -0,0 +1,46 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Receive Tuning Parameters for the DPDK Runtime
+///
+/// Trades latency for CPU efficiency on the receive path. Parameters that are not set keep their current value.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RxTuning {
+    /// Maximum number of packets taken from the NIC per receive call.
+    burst_size: Option<usize>,
+    /// Number of TSC cycles to keep polling the NIC before returning an empty burst.
+    timeout_cycles: Option<u64>,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate Functions for Receive Tuning Parameters
+impl RxTuning {
+    /// Sets the maximum number of packets taken from the NIC per receive call.
+    pub fn burst_size(mut self, n: usize) -> Self {
+        self.burst_size = Some(n);
+        self
+    }
+
+    /// Sets the number of TSC cycles to keep polling the NIC before returning an empty burst. Zero returns right away.
+    pub fn timeout_cycles(mut self, n: u64) -> Self {
+        self.timeout_cycles = Some(n);
+        self
+    }
+
+    /// Gets the maximum number of packets taken from the NIC per receive call, if set.
+    pub fn get_burst_size(&self) -> Option<usize> {
+        self.burst_size
+    }
+
+    /// Gets the number of TSC cycles to keep polling the NIC before returning an empty burst, if set.
+    pub fn get_timeout_cycles(&self) -> Option<u64> {
+        self.timeout_cycles
+    }
+}
@@ -10,7 +10,10 @@ pub mod runtime;
 //==============================================================================
 
 use self::{
-    interop::pack_result,
+    interop::{
+        pack_batch_result,
+        pack_result,
+    },
     runtime::LinuxRuntime,
 };
 use crate::{
@@ -18,8 +21,10 @@ use crate::{
     inetstack::InetStack,
     runtime::{
         fail::Fail,
-        memory::MemoryRuntime,
-        network::consts::RECEIVE_BATCH_SIZE,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+        },
         timer::{
             Timer,
             TimerRc,
@@ -45,7 +50,10 @@ use ::std::{
         DerefMut,
     },
     rc::Rc,
-    time::Instant,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 #[cfg(feature = "profiler")]
@@ -58,7 +66,7 @@ use crate::timer;
 /// Catpowder LibOS
 pub struct CatpowderLibOS {
     scheduler: Scheduler,
-    inetstack: InetStack<RECEIVE_BATCH_SIZE>,
+    inetstack: InetStack,
     rt: Rc<LinuxRuntime>,
 }
 
@@ -69,18 +77,43 @@ pub struct CatpowderLibOS {
 /// Associate Functions for Catpowder LibOS
 impl CatpowderLibOS {
     /// Instantiates a Catpowder LibOS.
-    pub fn new(config: &Config) -> Self {
+    pub fn new(config: &Config) -> Result<Self, Fail> {
         let rt: Rc<LinuxRuntime> = Rc::new(LinuxRuntime::new(
             config.local_link_addr(),
             config.local_ipv4_addr(),
             &config.local_interface_name(),
             HashMap::default(),
-        ));
+            config.receive_batch_size()?,
+            config.gro_max_size()?,
+        )?);
+        Self::with_runtime(config, rt)
+    }
+
+    /// Instantiates a Catpowder LibOS that runs over a TAP interface instead of a NIC. The interface is named after
+    /// the local interface name in the configuration file, and is created if it does not exist yet.
+    #[cfg(feature = "catnip-tap-libos")]
+    pub fn new_tap(config: &Config) -> Result<Self, Fail> {
+        let rt: Rc<LinuxRuntime> = Rc::new(LinuxRuntime::new_tap(
+            config.local_link_addr(),
+            config.local_ipv4_addr(),
+            &config.local_interface_name(),
+            HashMap::default(),
+            config.receive_batch_size()?,
+            config.gro_max_size()?,
+        )?);
+        Self::with_runtime(config, rt)
+    }
+
+    /// Instantiates a Catpowder LibOS on top of a Linux runtime.
+    fn with_runtime(config: &Config, rt: Rc<LinuxRuntime>) -> Result<Self, Fail> {
         let now: Instant = Instant::now();
-        let scheduler: Scheduler = Scheduler::default();
+        let scheduler: Scheduler = match config.scheduler_capacity() {
+            Some(capacity) => Scheduler::with_capacity(capacity),
+            None => Scheduler::default(),
+        };
         let clock: TimerRc = TimerRc(Rc::new(Timer::new(now)));
         let rng_seed: [u8; 32] = [0; 32];
-        let inetstack: InetStack<RECEIVE_BATCH_SIZE> = InetStack::new(
+        let mut inetstack: InetStack = InetStack::new(
             rt.clone(),
             scheduler.clone(),
             clock,
@@ -90,13 +123,14 @@ impl CatpowderLibOS {
             rt.tcp_options.clone(),
             rng_seed,
             rt.arp_options.clone(),
-        )
-        .unwrap();
-        CatpowderLibOS {
+            None,
+        )?;
+        inetstack.set_max_open_queues(config.max_open_queues());
+        Ok(CatpowderLibOS {
             scheduler,
             inetstack,
             rt,
-        }
+        })
     }
 
     /// Create a push request for Demikernel to asynchronously write data from `sga` to the
@@ -123,15 +157,31 @@ impl CatpowderLibOS {
         }
     }
 
+    /// Same as [Self::push], but the operation completes only once the peer acknowledges all data in `sga`. It fails
+    /// with `ETIMEDOUT` if that does not happen within `timeout`, in which case the connection is aborted.
+    pub fn push_timeout(&mut self, qd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("catpowder::push_timeout");
+        trace!("push_timeout(): qd={:?}, timeout={:?}", qd, timeout);
+        let buf: DemiBuffer = self.rt.clone_sgarray(sga)?;
+        if buf.len() == 0 {
+            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
+        }
+        let future = self.do_push_timeout(qd, buf, timeout)?;
+        let handle: TaskHandle = match self.scheduler.insert(future) {
+            Some(handle) => handle,
+            None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+        };
+        let qt: QToken = handle.get_task_id().into();
+        Ok(qt)
+    }
+
     pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, to: SocketAddrV4) -> Result<QToken, Fail> {
         #[cfg(feature = "profiler")]
         timer!("catnip::pushto");
         trace!("pushto2(): qd={:?}", qd);
         match self.rt.clone_sgarray(sga) {
             Ok(buf) => {
-                if buf.len() == 0 {
-                    return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-                }
                 let future = self.do_pushto(qd, buf, to)?;
                 let handle: TaskHandle = match self.scheduler.insert(future) {
                     Some(handle) => handle,
@@ -144,6 +194,32 @@ impl CatpowderLibOS {
         }
     }
 
+    /// Pushes a batch of scatter-gather arrays to a UDP socket, each one to its own remote address. Each datagram
+    /// completes on its own queue token.
+    pub fn pushto_batch(
+        &mut self,
+        qd: QDesc,
+        datagrams: &[(demi_sgarray_t, SocketAddrV4)],
+    ) -> Result<Vec<QToken>, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("catpowder::pushto_batch");
+        trace!("pushto_batch(): qd={:?}, count={:?}", qd, datagrams.len());
+        let mut bufs: Vec<(DemiBuffer, SocketAddrV4)> = Vec::with_capacity(datagrams.len());
+        for (sga, to) in datagrams {
+            let buf: DemiBuffer = self.rt.clone_sgarray(sga)?;
+            bufs.push((buf, *to));
+        }
+        let mut qts: Vec<QToken> = Vec::with_capacity(bufs.len());
+        for task in self.do_pushto_batch(qd, bufs)? {
+            let handle: TaskHandle = match self.scheduler.insert(task) {
+                Some(handle) => handle,
+                None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+            };
+            qts.push(handle.get_task_id().into());
+        }
+        Ok(qts)
+    }
+
     pub fn schedule(&mut self, qt: QToken) -> Result<TaskHandle, Fail> {
         match self.scheduler.from_task_id(qt.into()) {
             Some(handle) => Ok(handle),
@@ -156,6 +232,12 @@ impl CatpowderLibOS {
         Ok(pack_result(self.rt.clone(), r, qd, qt.into()))
     }
 
+    /// Packs the result of a batched pop into one scatter-gather array per message.
+    pub fn pack_batch_result(&mut self, handle: TaskHandle) -> Result<Vec<demi_sgarray_t>, Fail> {
+        let (_, r): (QDesc, OperationResult) = self.take_operation(handle);
+        pack_batch_result(self.rt.clone(), r)
+    }
+
     /// Allocates a scatter-gather array.
     pub fn sgaalloc(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
         self.rt.alloc_sgarray(size)
@@ -173,7 +255,7 @@ impl CatpowderLibOS {
 
 /// De-Reference Trait Implementation for Catpowder LibOS
 impl Deref for CatpowderLibOS {
-    type Target = InetStack<RECEIVE_BATCH_SIZE>;
+    type Target = InetStack;
 
     fn deref(&self) -> &Self::Target {
         &self.inetstack
@@ -3,6 +3,8 @@
 
 mod network;
 mod rawsocket;
+#[cfg(feature = "catnip-tap-libos")]
+mod tap;
 
 //==============================================================================
 // Imports
@@ -12,20 +14,30 @@ use self::rawsocket::{
     RawSocket,
     RawSocketAddr,
 };
+#[cfg(feature = "catnip-tap-libos")]
+use self::tap::TapDevice;
 use crate::runtime::{
+    fail::Fail,
     memory::MemoryRuntime,
     network::{
+        check_receive_batch_size,
         config::{
             ArpConfig,
             TcpConfig,
             UdpConfig,
         },
-        types::MacAddress,
+        types::{
+            Ipv4IdGenerator,
+            MacAddress,
+        },
     },
     Runtime,
 };
+use ::rand::{
+    prelude::SmallRng,
+    SeedableRng,
+};
 use ::std::{
-    cell::RefCell,
     collections::HashMap,
     fs,
     net::Ipv4Addr,
@@ -38,6 +50,15 @@ use ::std::{
 // Constants & Structures
 //==============================================================================
 
+/// Link Device That Frames Go Through
+enum LinkDevice {
+    /// Raw socket bound to the network interface with the given index.
+    RawSocket(RawSocket, i32),
+    /// TAP device, whose other end is a network interface of the host kernel.
+    #[cfg(feature = "catnip-tap-libos")]
+    Tap(TapDevice),
+}
+
 /// Linux Runtime
 #[derive(Clone)]
 pub struct LinuxRuntime {
@@ -46,8 +67,9 @@ pub struct LinuxRuntime {
     pub arp_options: ArpConfig,
     pub link_addr: MacAddress,
     pub ipv4_addr: Ipv4Addr,
-    ifindex: i32,
-    socket: Rc<RefCell<RawSocket>>,
+    device: Rc<LinkDevice>,
+    receive_batch_size: usize,
+    ipv4_id: Ipv4IdGenerator,
 }
 
 //==============================================================================
@@ -56,15 +78,18 @@ pub struct LinuxRuntime {
 
 /// Associate Functions for Linux Runtime
 impl LinuxRuntime {
-    /// Instantiates a Linux Runtime.
-    pub fn new(link_addr: MacAddress, ipv4_addr: Ipv4Addr, ifname: &str, arp: HashMap<Ipv4Addr, MacAddress>) -> Self {
-        let arp_options: ArpConfig = ArpConfig::new(
-            Some(Duration::from_secs(600)),
-            Some(Duration::from_secs(1)),
-            Some(2),
-            Some(arp),
-            Some(false),
-        );
+    /// Instantiates a Linux Runtime that sends and receives frames through a raw socket bound to the network interface
+    /// named `ifname`.
+    pub fn new(
+        link_addr: MacAddress,
+        ipv4_addr: Ipv4Addr,
+        ifname: &str,
+        arp: HashMap<Ipv4Addr, MacAddress>,
+        receive_batch_size: usize,
+        gro_max_size: usize,
+    ) -> Result<Self, Fail> {
+        // Reject a bad batch size before acquiring any resources.
+        let receive_batch_size: usize = check_receive_batch_size(receive_batch_size)?;
 
         // TODO: Make this constructor return a Result and drop expect() calls below.
         let mac_addr: [u8; 6] = [0; 6];
@@ -73,14 +98,70 @@ impl LinuxRuntime {
         let sockaddr: RawSocketAddr = RawSocketAddr::new(ifindex, &mac_addr);
         socket.bind(&sockaddr).expect("could not bind raw socket");
 
+        Ok(Self::with_device(
+            link_addr,
+            ipv4_addr,
+            LinkDevice::RawSocket(socket, ifindex),
+            arp,
+            receive_batch_size,
+            gro_max_size,
+        ))
+    }
+
+    /// Instantiates a Linux Runtime that sends and receives frames through the TAP interface named `ifname`, which is
+    /// created if it does not exist yet. The host kernel sees our frames as if they came from a peer on that interface,
+    /// so no NIC is needed at all.
+    #[cfg(feature = "catnip-tap-libos")]
+    pub fn new_tap(
+        link_addr: MacAddress,
+        ipv4_addr: Ipv4Addr,
+        ifname: &str,
+        arp: HashMap<Ipv4Addr, MacAddress>,
+        receive_batch_size: usize,
+        gro_max_size: usize,
+    ) -> Result<Self, Fail> {
+        // Reject a bad batch size before acquiring any resources.
+        let receive_batch_size: usize = check_receive_batch_size(receive_batch_size)?;
+        let tap: TapDevice = TapDevice::new(ifname)?;
+
+        Ok(Self::with_device(
+            link_addr,
+            ipv4_addr,
+            LinkDevice::Tap(tap),
+            arp,
+            receive_batch_size,
+            gro_max_size,
+        ))
+    }
+
+    /// Instantiates a Linux Runtime on top of a link device.
+    fn with_device(
+        link_addr: MacAddress,
+        ipv4_addr: Ipv4Addr,
+        device: LinkDevice,
+        arp: HashMap<Ipv4Addr, MacAddress>,
+        receive_batch_size: usize,
+        gro_max_size: usize,
+    ) -> Self {
+        let arp_options: ArpConfig = ArpConfig::builder()
+            .cache_ttl(Duration::from_secs(600))
+            .request_timeout(Duration::from_secs(1))
+            .max_retries(2)
+            .static_table(arp)
+            .disable(false)
+            .build();
+
+        let tcp_options: TcpConfig = TcpConfig::builder().gro_max_size(gro_max_size).build();
+
         Self {
-            tcp_options: TcpConfig::default(),
+            tcp_options,
             udp_options: UdpConfig::default(),
             arp_options,
             link_addr,
             ipv4_addr,
-            ifindex,
-            socket: Rc::new(RefCell::new(socket)),
+            device: Rc::new(device),
+            receive_batch_size,
+            ipv4_id: Ipv4IdGenerator::new(&mut SmallRng::from_entropy()),
         }
     }
 
@@ -5,8 +5,14 @@
 // Imports
 //==============================================================================
 
+#[cfg(feature = "catnip-tap-libos")]
+use super::tap::TapDevice;
 use super::{
-    rawsocket::RawSocketAddr,
+    rawsocket::{
+        RawSocket,
+        RawSocketAddr,
+    },
+    LinkDevice,
     LinuxRuntime,
 };
 use crate::{
@@ -17,21 +23,80 @@ use crate::{
         network::{
             NetworkRuntime,
             PacketBuf,
+            ReceiveBatch,
         },
     },
 };
-use ::arrayvec::ArrayVec;
 use ::std::mem::{
     self,
     MaybeUninit,
 };
 
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate Functions for Linux Runtime
+impl LinuxRuntime {
+    /// Receives a batch of [DemiBuffer] from a raw socket.
+    fn receive_from_socket(&self, socket: &RawSocket) -> ReceiveBatch {
+        // TODO: This routine contains an extra copy of the entire incoming packet that could potentially be removed.
+
+        // TODO: change this function to operate directly on DemiBuffer rather than on MaybeUninit<u8>.
+
+        let mut ret: ReceiveBatch = ReceiveBatch::new();
+        while ret.len() < self.receive_batch_size {
+            // This use-case is an example for MaybeUninit in the docs.
+            let mut out: [MaybeUninit<u8>; limits::RECVBUF_SIZE_MAX] =
+                [unsafe { MaybeUninit::uninit().assume_init() }; limits::RECVBUF_SIZE_MAX];
+            match socket.recvfrom(&mut out[..]) {
+                Ok((nbytes, _origin_addr)) => unsafe {
+                    let bytes: [u8; limits::RECVBUF_SIZE_MAX] = mem::transmute::<
+                        [MaybeUninit<u8>; limits::RECVBUF_SIZE_MAX],
+                        [u8; limits::RECVBUF_SIZE_MAX],
+                    >(out);
+                    let mut dbuf: DemiBuffer = DemiBuffer::from_slice(&bytes).expect("'bytes' should fit");
+                    dbuf.trim(limits::RECVBUF_SIZE_MAX - nbytes)
+                        .expect("'bytes' <= RECVBUF_SIZE_MAX");
+                    ret.push(dbuf);
+                },
+                // No more packets to receive.
+                Err(_) => break,
+            }
+        }
+        ret
+    }
+
+    /// Receives a batch of [DemiBuffer] from a TAP device. Frames are read straight into the buffers.
+    #[cfg(feature = "catnip-tap-libos")]
+    fn receive_from_tap(&self, tap: &TapDevice) -> ReceiveBatch {
+        let mut ret: ReceiveBatch = ReceiveBatch::new();
+        while ret.len() < self.receive_batch_size {
+            let mut dbuf: DemiBuffer = DemiBuffer::new(limits::RECVBUF_SIZE_MAX as u16);
+            match tap.read(&mut dbuf[..]) {
+                Ok(Some(nbytes)) => {
+                    dbuf.trim(limits::RECVBUF_SIZE_MAX - nbytes)
+                        .expect("'nbytes' <= RECVBUF_SIZE_MAX");
+                    ret.push(dbuf);
+                },
+                // No more packets to receive.
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("failed to receive packet: {:?}", e);
+                    break;
+                },
+            }
+        }
+        ret
+    }
+}
+
 //==============================================================================
 // Trait Implementations
 //==============================================================================
 
 /// Network Runtime Trait Implementation for Linux Runtime
-impl<const N: usize> NetworkRuntime<N> for LinuxRuntime {
+impl NetworkRuntime for LinuxRuntime {
     /// Transmits a single [PacketBuf].
     fn transmit(&self, pkt: Box<dyn PacketBuf>) {
         let header_size: usize = pkt.header_size();
@@ -45,42 +110,43 @@ impl<const N: usize> NetworkRuntime<N> for LinuxRuntime {
             buf[header_size..].copy_from_slice(&body[..]);
         }
 
-        let (header, _) = Ethernet2Header::parse(buf.clone()).unwrap();
-        let dest_addr_arr: [u8; 6] = header.dst_addr().to_array();
-        let dest_sockaddr: RawSocketAddr = RawSocketAddr::new(self.ifindex, &dest_addr_arr);
+        match self.device.as_ref() {
+            LinkDevice::RawSocket(socket, ifindex) => {
+                let (header, _) = Ethernet2Header::parse(buf.clone()).unwrap();
+                let dest_addr_arr: [u8; 6] = header.dst_addr().to_array();
+                let dest_sockaddr: RawSocketAddr = RawSocketAddr::new(*ifindex, &dest_addr_arr);
 
-        // Send packet.
-        match self.socket.borrow().sendto(&buf, &dest_sockaddr) {
-            // Operation succeeded.
-            Ok(_) => (),
-            // Operation failed, drop packet.
-            Err(e) => warn!("dropping packet: {:?}", e),
-        };
+                // Send packet.
+                match socket.sendto(&buf, &dest_sockaddr) {
+                    // Operation succeeded.
+                    Ok(_) => (),
+                    // Operation failed, drop packet.
+                    Err(e) => warn!("dropping packet: {:?}", e),
+                };
+            },
+            #[cfg(feature = "catnip-tap-libos")]
+            LinkDevice::Tap(tap) => match tap.write(&buf) {
+                // Operation succeeded.
+                Ok(true) => (),
+                // The queue of the TAP interface is full, drop packet as a busy NIC would.
+                Ok(false) => warn!("dropping packet: TAP device is busy"),
+                // Operation failed, drop packet.
+                Err(e) => warn!("dropping packet: {:?}", e),
+            },
+        }
     }
 
-    /// Receives a batch of [DemiBuffer].
-    // TODO: This routine currently only tries to receive a single packet buffer, not a batch of them.
-    fn receive(&self) -> ArrayVec<DemiBuffer, N> {
-        // TODO: This routine contains an extra copy of the entire incoming packet that could potentially be removed.
-
-        // TODO: change this function to operate directly on DemiBuffer rather than on MaybeUninit<u8>.
+    /// Returns the identification field for the next IPv4 datagram.
+    fn next_ipv4_id(&self) -> u16 {
+        self.ipv4_id.next_id()
+    }
 
-        // This use-case is an example for MaybeUninit in the docs.
-        let mut out: [MaybeUninit<u8>; limits::RECVBUF_SIZE_MAX] =
-            [unsafe { MaybeUninit::uninit().assume_init() }; limits::RECVBUF_SIZE_MAX];
-        if let Ok((nbytes, _origin_addr)) = self.socket.borrow().recvfrom(&mut out[..]) {
-            let mut ret: ArrayVec<DemiBuffer, N> = ArrayVec::new();
-            unsafe {
-                let bytes: [u8; limits::RECVBUF_SIZE_MAX] =
-                    mem::transmute::<[MaybeUninit<u8>; limits::RECVBUF_SIZE_MAX], [u8; limits::RECVBUF_SIZE_MAX]>(out);
-                let mut dbuf: DemiBuffer = DemiBuffer::from_slice(&bytes).expect("'bytes' should fit");
-                dbuf.trim(limits::RECVBUF_SIZE_MAX - nbytes)
-                    .expect("'bytes' <= RECVBUF_SIZE_MAX");
-                ret.push(dbuf);
-            }
-            ret
-        } else {
-            ArrayVec::new()
+    /// Receives a batch of [DemiBuffer].
+    fn receive(&self) -> ReceiveBatch {
+        match self.device.as_ref() {
+            LinkDevice::RawSocket(socket, _) => self.receive_from_socket(socket),
+            #[cfg(feature = "catnip-tap-libos")]
+            LinkDevice::Tap(tap) => self.receive_from_tap(tap),
         }
     }
 }
@@ -0,0 +1,124 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::fail::Fail;
+use ::libc;
+use ::std::{
+    ffi::CStr,
+    mem,
+};
+
+//======================================================================================================================
+// Constants & Structures
+//======================================================================================================================
+
+/// Path to the clone device of the TUN/TAP driver.
+const TUN_CLONE_DEVICE: &[u8] = b"/dev/net/tun\0";
+
+/// Request that attaches a file descriptor of the clone device to a TUN/TAP interface (`_IOW('T', 202, int)`).
+const TUNSETIFF: libc::c_ulong = 0x400454ca;
+
+/// Interface request, as taken by [TUNSETIFF]. Only the flags of the request union are used.
+#[repr(C)]
+struct IfReq {
+    ifr_name: [libc::c_char; libc::IFNAMSIZ],
+    ifr_flags: libc::c_short,
+    _pad: [u8; 22],
+}
+
+/// TAP device.
+pub struct TapDevice(libc::c_int);
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+/// Associated functions for TAP devices.
+impl TapDevice {
+    /// Attaches to the TAP interface named `ifname`, creating it if it does not exist yet. Frames carry no packet
+    /// information header, and reads and writes never block.
+    pub fn new(ifname: &str) -> Result<Self, Fail> {
+        if ifname.is_empty() || ifname.len() >= libc::IFNAMSIZ {
+            let cause: String = format!("invalid interface name (ifname={:?})", ifname);
+            error!("new(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        let path: &CStr = CStr::from_bytes_with_nul(TUN_CLONE_DEVICE).expect("path should be nul-terminated");
+        let fd: libc::c_int = unsafe { libc::open(path.as_ptr(), libc::O_RDWR | libc::O_NONBLOCK | libc::O_CLOEXEC) };
+        if fd == -1 {
+            let errno: libc::c_int = Self::errno();
+            let cause: String = format!("failed to open TUN/TAP clone device (errno={:?})", errno);
+            error!("new(): {}", cause);
+            return Err(Fail::new(errno, &cause));
+        }
+        let device: TapDevice = TapDevice(fd);
+
+        let mut ifr: IfReq = unsafe { mem::zeroed() };
+        for (dst, src) in ifr.ifr_name.iter_mut().zip(ifname.bytes()) {
+            *dst = src as libc::c_char;
+        }
+        ifr.ifr_flags = (libc::IFF_TAP | libc::IFF_NO_PI) as libc::c_short;
+        if unsafe { libc::ioctl(device.0, TUNSETIFF as _, &mut ifr as *mut IfReq) } == -1 {
+            let errno: libc::c_int = Self::errno();
+            let cause: String = format!(
+                "failed to attach to TAP interface (ifname={:?}, errno={:?})",
+                ifname, errno
+            );
+            error!("new(): {}", cause);
+            return Err(Fail::new(errno, &cause));
+        }
+
+        Ok(device)
+    }
+
+    /// Writes a frame to the TAP device. Returns `false` if the device cannot take the frame right now.
+    pub fn write(&self, buf: &[u8]) -> Result<bool, Fail> {
+        let nbytes: isize = unsafe { libc::write(self.0, buf.as_ptr() as *const libc::c_void, buf.len()) };
+        if nbytes == -1 {
+            let errno: libc::c_int = Self::errno();
+            if errno == libc::EAGAIN || errno == libc::EWOULDBLOCK {
+                return Ok(false);
+            }
+            return Err(Fail::new(errno, "failed to write frame to TAP device"));
+        }
+
+        // The TAP driver takes whole frames only.
+        debug_assert_eq!(nbytes as usize, buf.len());
+        Ok(true)
+    }
+
+    /// Reads a frame from the TAP device into `buf`, returning its size. Returns `None` if no frame is pending.
+    pub fn read(&self, buf: &mut [u8]) -> Result<Option<usize>, Fail> {
+        let nbytes: isize = unsafe { libc::read(self.0, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if nbytes == -1 {
+            let errno: libc::c_int = Self::errno();
+            if errno == libc::EAGAIN || errno == libc::EWOULDBLOCK {
+                return Ok(None);
+            }
+            return Err(Fail::new(errno, "failed to read frame from TAP device"));
+        }
+        Ok(Some(nbytes as usize))
+    }
+
+    /// Returns the error number of the last failed system call.
+    fn errno() -> libc::c_int {
+        unsafe { *libc::__errno_location() }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+/// Drop trait implementation for TAP devices.
+impl Drop for TapDevice {
+    /// Detaches from the TAP interface. The interface goes away with us, unless it was made persistent.
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0) };
+    }
+}
@@ -13,24 +13,57 @@ use crate::{
 };
 use ::core::{
     alloc::Layout,
+    hint,
     mem,
+    slice,
     sync::atomic::{
         self,
         AtomicUsize,
     },
 };
-use ::std::alloc;
+use ::std::{
+    alloc,
+    thread,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of times that a writer spins while it waits for the writers ahead of it to commit, before it starts yielding.
+const MAX_COMMIT_SPINS: usize = 1024;
 
 //======================================================================================================================
 // Structures
 //======================================================================================================================
 
-/// A lock-free, single writer and single reader, fixed-size circular buffer.
+/// Number of writers that a ring buffer admits.
+///
+/// Items of a single writer come out in the order that they went in. Items of different writers interleave in the order
+/// that the writers reserved room for them, and items that go in through a single call to
+/// [RingBuffer::try_enqueue_all] are never split up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(usize)]
+pub enum RingMode {
+    /// A single writer. This is the fastest mode, as writers need no atomic read-modify-write operations.
+    SingleProducer = 0,
+    /// Multiple writers, possibly in different processes. Writers reserve room with a compare-and-swap, then commit
+    /// their items in the order of their reservations. A writer that stalls between the two steps holds back the items
+    /// of the writers that reserved room after it.
+    MultiProducer = 1,
+}
+
+/// A lock-free, fixed-size circular buffer with a single reader, and either a single writer or multiple writers.
 pub struct RingBuffer<T> {
     // Indexes the first empty slot after the item in the back of the ring buffer.
     back_ptr: *mut usize,
     // Indexes the first item in the front of the ring buffer.
     front_ptr: *mut usize,
+    // Counts the slots that writers reserved so far, in multi-producer mode. This wraps around, rather than being
+    // masked, so that compare-and-swap operations on it cannot be fooled by a writer that laps the ring buffer.
+    reserve_ptr: *mut usize,
+    // Number of writers.
+    mode: RingMode,
     // Underlying buffer.
     buffer: raw_array::RawArray<T>,
     // Pre-computed capacity mask for the buffer.
@@ -48,9 +81,15 @@ impl<T> RingBuffer<T>
 where
     T: Copy,
 {
-    /// Creates a ring buffer.
+    /// Creates a ring buffer with a single writer.
     #[allow(unused)]
     pub fn new(capacity: usize) -> Result<RingBuffer<T>, Fail> {
+        Self::new_with_mode(capacity, RingMode::SingleProducer)
+    }
+
+    /// Creates a ring buffer that admits as many writers as `mode` tells.
+    #[allow(unused)]
+    pub fn new_with_mode(capacity: usize, mode: RingMode) -> Result<RingBuffer<T>, Fail> {
         // Check if capacity is invalid.
         if !capacity.is_power_of_two() {
             return Err(Fail::new(
@@ -79,17 +118,29 @@ where
             ptr
         };
 
+        let reserve_ptr: *mut usize = unsafe {
+            let ptr: *mut usize = alloc::alloc(layout) as *mut usize;
+            if ptr.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+            *ptr = 0;
+            ptr
+        };
+
         Ok(RingBuffer {
             back_ptr,
             front_ptr,
+            reserve_ptr,
+            mode,
             buffer: raw_array::RawArray::<T>::new(capacity)?,
             mask: capacity - 1,
             is_managed: true,
         })
     }
 
-    /// Constructs a ring buffer from raw parts.
-    pub fn from_raw_parts(init: bool, mut ptr: *mut u8, size: usize) -> Result<RingBuffer<T>, Fail> {
+    /// Constructs a ring buffer from raw parts. If `init` is set, the ring buffer is initialized to admit as many writers
+    /// as it tells. Otherwise, the memory region should hold an initialized ring buffer, whose mode is taken over.
+    pub fn from_raw_parts(init: Option<RingMode>, mut ptr: *mut u8, size: usize) -> Result<RingBuffer<T>, Fail> {
         // Check if we have a valid pointer.
         if ptr.is_null() {
             return Err(Fail::new(
@@ -109,13 +160,17 @@ where
 
         const SIZE_OF_USIZE: usize = mem::size_of::<usize>();
         let size_of_t: usize = mem::size_of::<T>();
-        let mut size_of_ring: usize = SIZE_OF_USIZE + SIZE_OF_USIZE;
+        let mut size_of_ring: usize = 4 * SIZE_OF_USIZE;
 
         // Compute pointers and required padding.
         let front_ptr: *mut usize = ptr as *mut usize;
         unsafe { ptr = ptr.add(SIZE_OF_USIZE) };
         let back_ptr: *mut usize = ptr as *mut usize;
         unsafe { ptr = ptr.add(SIZE_OF_USIZE) };
+        let reserve_ptr: *mut usize = ptr as *mut usize;
+        unsafe { ptr = ptr.add(SIZE_OF_USIZE) };
+        let mode_ptr: *mut usize = ptr as *mut usize;
+        unsafe { ptr = ptr.add(SIZE_OF_USIZE) };
         let buffer_ptr: *mut u8 = {
             let padding: usize = ptr.align_offset(size_of_t);
             size_of_ring += padding;
@@ -137,29 +192,60 @@ where
             1 << maxlen.ilog2()
         };
 
-        // Initialize back and front pointers only if requested.
-        if init {
-            unsafe {
-                *back_ptr = 0;
-                *front_ptr = 0;
-            }
-        }
+        // Initialize the header only if requested. Otherwise, take over the mode that it holds.
+        let mode: RingMode = match init {
+            Some(mode) => {
+                unsafe {
+                    *back_ptr = 0;
+                    *front_ptr = 0;
+                    *reserve_ptr = 0;
+                    *mode_ptr = mode as usize;
+                }
+                mode
+            },
+            None => match unsafe { *mode_ptr } {
+                x if x == RingMode::SingleProducer as usize => RingMode::SingleProducer,
+                x if x == RingMode::MultiProducer as usize => RingMode::MultiProducer,
+                _ => {
+                    return Err(Fail::new(
+                        libc::EINVAL,
+                        "memory region does not hold a ring buffer of a known mode",
+                    ))
+                },
+            },
+        };
 
         Ok(RingBuffer {
             back_ptr,
             front_ptr,
+            reserve_ptr,
+            mode,
             buffer: raw_array::RawArray::<T>::from_raw_parts(buffer_ptr as *mut T, len)?,
             mask: len - 1,
             is_managed: false,
         })
     }
 
+    /// Returns the number of writers that the target ring buffer admits.
+    #[allow(unused)]
+    pub fn mode(&self) -> RingMode {
+        self.mode
+    }
+
     /// Returns the effective capacity of the target ring buffer.
     #[allow(unused)]
     pub fn capacity(&self) -> usize {
         self.buffer.capacity() - 1
     }
 
+    /// Peeks the target ring buffer and returns the number of items that it holds.
+    pub fn len(&self) -> usize {
+        let front_cached: usize = self.get_front();
+        let back_cached: usize = self.get_back();
+
+        back_cached.wrapping_sub(front_cached) & self.mask
+    }
+
     /// Peeks the target ring buffer and checks if it is full.
     #[allow(unused)]
     pub fn is_full(&self) -> bool {
@@ -190,6 +276,14 @@ where
 
     /// Attempts to insert an item at the back of the target ring buffer.
     pub fn try_enqueue(&self, item: T) -> Result<(), T> {
+        if self.mode == RingMode::MultiProducer {
+            return if self.try_enqueue_all(slice::from_ref(&item)) {
+                Ok(())
+            } else {
+                Err(item)
+            };
+        }
+
         let front_cached: usize = self.get_front();
         let back_cached: usize = self.get_back();
 
@@ -210,6 +304,72 @@ where
         Ok(())
     }
 
+    /// Attempts to insert all `items` at the back of the target ring buffer, one after the other. Returns `false`, and
+    /// inserts nothing, if the ring buffer lacks room for all of them.
+    pub fn try_enqueue_all(&self, items: &[T]) -> bool {
+        let start: usize = match self.mode {
+            RingMode::SingleProducer => {
+                if self.capacity() - self.len() < items.len() {
+                    return false;
+                }
+                self.get_back()
+            },
+            RingMode::MultiProducer => match self.reserve(items.len()) {
+                Some(start) => start,
+                None => return false,
+            },
+        };
+
+        // Write.
+        unsafe {
+            let data: &mut [T] = self.buffer.get_mut();
+            for (i, item) in items.iter().enumerate() {
+                data[(start + i) & self.mask] = *item;
+            }
+        }
+
+        // Commit write. Writers commit in the order of their reservations, so that the reader never sees a slot that
+        // is reserved but not written yet. A writer that keeps us waiting for long has likely been preempted, so we
+        // then give up the CPU for it to catch up.
+        if self.mode == RingMode::MultiProducer {
+            let mut spins: usize = 0;
+            while self.get_back() != start & self.mask {
+                if spins < MAX_COMMIT_SPINS {
+                    hint::spin_loop();
+                    spins += 1;
+                } else {
+                    thread::yield_now();
+                }
+            }
+        }
+        self.set_back((start + items.len()) & self.mask);
+
+        true
+    }
+
+    /// Reserves `count` slots for a writer in multi-producer mode. Returns the reservation count at which the slots
+    /// start, or `None` if the ring buffer lacks room for them.
+    fn reserve(&self, count: usize) -> Option<usize> {
+        let reserve: &AtomicUsize = AtomicUsize::from_mut(unsafe { &mut *self.reserve_ptr });
+        let mut reserve_cached: usize = reserve.load(atomic::Ordering::Acquire);
+        loop {
+            // The reader may only free up room in the meantime, so a stale `front` index errs on the safe side.
+            let used: usize = reserve_cached.wrapping_sub(self.get_front()) & self.mask;
+            if self.capacity() - used < count {
+                return None;
+            }
+            match reserve.compare_exchange_weak(
+                reserve_cached,
+                reserve_cached.wrapping_add(count),
+                atomic::Ordering::AcqRel,
+                atomic::Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(reserve_cached),
+                Err(actual) => reserve_cached = actual,
+            }
+        }
+    }
+
     /// Inserts an item at the back of the target ring buffer. This function may block (spin).
     #[allow(unused)]
     pub fn enqueue(&self, item: T) {
@@ -242,6 +402,24 @@ where
         Some(item)
     }
 
+    /// Copies up to `buf.len()` items from the front of the target ring buffer into `buf`, without removing them.
+    /// Returns the number of items that were copied.
+    pub fn peek_into(&self, buf: &mut [T]) -> usize {
+        let mut front_cached: usize = self.get_front();
+        let back_cached: usize = self.get_back();
+
+        // Read, but do not commit.
+        let mut count: usize = 0;
+        let data: &[T] = unsafe { self.buffer.get() };
+        while count < buf.len() && front_cached != back_cached {
+            buf[count] = data[front_cached];
+            front_cached = (front_cached + 1) & self.mask;
+            count += 1;
+        }
+
+        count
+    }
+
     /// Removes the item from the front of the target ring buffer. This function may block (spin).
     #[allow(unused)]
     pub fn dequeue(&self) -> T {
@@ -252,30 +430,34 @@ where
         }
     }
 
+    // The reader and the writer may run in different processes, so indexes are published with release semantics and
+    // read with acquire semantics. This ensures that an item is visible to the reader before the `back` index that
+    // covers it, and that the writer does not reuse a slot before the reader is done with it.
+
     /// Atomically gets the `front` index.
     fn get_front(&self) -> usize {
         let front: &mut AtomicUsize = AtomicUsize::from_mut(unsafe { &mut *self.front_ptr });
-        let front_cached: usize = front.load(atomic::Ordering::Relaxed);
+        let front_cached: usize = front.load(atomic::Ordering::Acquire);
         front_cached
     }
 
     /// Atomically sets the `front` index.
     fn set_front(&self, val: usize) {
         let front: &AtomicUsize = AtomicUsize::from_mut(unsafe { &mut *self.front_ptr });
-        front.store(val, atomic::Ordering::Relaxed);
+        front.store(val, atomic::Ordering::Release);
     }
 
     /// Atomically gets the `back` index.
     fn get_back(&self) -> usize {
         let back: &mut AtomicUsize = AtomicUsize::from_mut(unsafe { &mut *self.back_ptr });
-        let back_cached: usize = back.load(atomic::Ordering::Relaxed);
+        let back_cached: usize = back.load(atomic::Ordering::Acquire);
         back_cached
     }
 
     /// Atomically sets the `back` index.
     fn set_back(&self, val: usize) {
         let back: &AtomicUsize = AtomicUsize::from_mut(unsafe { &mut *self.back_ptr });
-        back.store(val, atomic::Ordering::Relaxed);
+        back.store(val, atomic::Ordering::Release);
     }
 }
 
@@ -299,6 +481,7 @@ impl<T> Drop for RingBuffer<T> {
             unsafe {
                 alloc::dealloc(self.back_ptr as *mut u8, layout);
                 alloc::dealloc(self.front_ptr as *mut u8, layout);
+                alloc::dealloc(self.reserve_ptr as *mut u8, layout);
             }
             self.is_managed = false;
         }
@@ -311,7 +494,10 @@ impl<T> Drop for RingBuffer<T> {
 
 #[cfg(test)]
 mod test {
-    use super::RingBuffer;
+    use super::{
+        RingBuffer,
+        RingMode,
+    };
     use ::anyhow::Result;
     use ::core::mem;
     use ::std::thread;
@@ -338,7 +524,7 @@ mod test {
 
     /// Constructs a ring buffer from raw parts.
     fn do_from_raw(ptr: *mut u8, size: usize) -> Result<RingBuffer<u32>> {
-        let ring: RingBuffer<u32> = match RingBuffer::<u32>::from_raw_parts(true, ptr, size) {
+        let ring: RingBuffer<u32> = match RingBuffer::<u32>::from_raw_parts(Some(RingMode::SingleProducer), ptr, size) {
             Ok(ring) => ring,
             Err(e) => anyhow::bail!("creating a ring buffer with valid capcity should be possible {:?}", e),
         };
@@ -387,7 +573,7 @@ mod test {
     /// Tests if we succeed to construct a ring buffer from raw parts.
     #[test]
     fn from_raw_parts() -> Result<()> {
-        const LENGTH: usize = RING_BUFFER_CAPACITY + 2 * mem::size_of::<usize>();
+        const LENGTH: usize = RING_BUFFER_CAPACITY + 4 * mem::size_of::<usize>();
         const SIZE: usize = LENGTH * mem::size_of::<u32>();
         let mut array: [u32; LENGTH] = [0; LENGTH];
         do_from_raw(array.as_mut_ptr() as *mut u8, SIZE)?;
@@ -405,7 +591,7 @@ mod test {
     /// Tets if we succeed to sequentially enqueue and dequeue elements to/from a constructed ring buffer.
     #[test]
     fn enqueue_dequeue_sequential_raw() -> Result<()> {
-        const LENGTH: usize = RING_BUFFER_CAPACITY + 2 * mem::size_of::<usize>();
+        const LENGTH: usize = RING_BUFFER_CAPACITY + 4 * mem::size_of::<usize>();
         const SIZE: usize = LENGTH * mem::size_of::<u32>();
         let mut array: [u32; LENGTH] = [0; LENGTH];
         let mut ring: RingBuffer<u32> = do_from_raw(array.as_mut_ptr() as *mut u8, SIZE)?;
@@ -413,6 +599,33 @@ mod test {
         do_enqueue_dequeue(&mut ring)
     }
 
+    /// Tests if peeking copies items from the front of a ring buffer without removing them.
+    #[test]
+    fn peek_into() -> Result<()> {
+        let ring: RingBuffer<u32> = do_new()?;
+        let mut buf: [u32; 4] = [0; 4];
+
+        // Peeking an empty ring buffer should copy nothing.
+        crate::ensure_eq!(ring.peek_into(&mut buf), 0);
+
+        for i in 0..3 {
+            ring.enqueue(i);
+        }
+        crate::ensure_eq!(ring.len(), 3);
+
+        // Peeking should copy at most the available items, and leave them in place.
+        crate::ensure_eq!(ring.peek_into(&mut buf[..2]), 2);
+        crate::ensure_eq!(buf[..2], [0, 1]);
+        crate::ensure_eq!(ring.peek_into(&mut buf), 3);
+        crate::ensure_eq!(buf[..3], [0, 1, 2]);
+        for i in 0..3 {
+            crate::ensure_eq!(ring.dequeue(), i);
+        }
+        crate::ensure_eq!(ring.is_empty(), true);
+
+        Ok(())
+    }
+
     /// Tests if we fail to create ring buffer with an invalid capacity.
     #[test]
     fn bad_new() -> Result<()> {
@@ -448,4 +661,73 @@ mod test {
 
         result
     }
+
+    /// Tests if items of multiple writers all come out exactly once, and in order for each writer.
+    #[test]
+    fn enqueue_dequeue_multi_producer() -> Result<()> {
+        const NWRITERS: u32 = 2;
+        const NITEMS: u32 = 2 * RING_BUFFER_CAPACITY as u32;
+        let ring: RingBuffer<u32> =
+            match RingBuffer::<u32>::new_with_mode(RING_BUFFER_CAPACITY, RingMode::MultiProducer) {
+                Ok(ring) => ring,
+                Err(_) => anyhow::bail!("creating a ring buffer with valid capcity should be possible"),
+            };
+        crate::ensure_eq!(ring.mode(), RingMode::MultiProducer);
+        let mut result: Result<()> = Ok(());
+
+        thread::scope(|s| {
+            // Each writer tags its items with its identifier in the upper bits, and a sequence number in the lower bits.
+            let ring: &RingBuffer<u32> = &ring;
+            for writer in 0..NWRITERS {
+                s.spawn(move || {
+                    for i in 0..NITEMS {
+                        ring.enqueue((writer << 24) | i);
+                    }
+                });
+            }
+            let reader: thread::ScopedJoinHandle<Result<()>> = s.spawn(move || {
+                let mut next: [u32; NWRITERS as usize] = [0; NWRITERS as usize];
+                for _ in 0..(NWRITERS * NITEMS) {
+                    let item: u32 = ring.dequeue();
+                    let writer: usize = (item >> 24) as usize;
+                    crate::ensure_eq!(item & 0xffffff, next[writer]);
+                    next[writer] += 1;
+                }
+                crate::ensure_eq!(next, [NITEMS; NWRITERS as usize]);
+                Ok(())
+            });
+
+            result = reader.join().unwrap();
+        });
+        crate::ensure_eq!(ring.is_empty(), true);
+
+        result
+    }
+
+    /// Tests if items that go in together stay together, and if nothing goes in when they do not fit.
+    #[test]
+    fn enqueue_all() -> Result<()> {
+        for mode in [RingMode::SingleProducer, RingMode::MultiProducer] {
+            let ring: RingBuffer<u32> = match RingBuffer::<u32>::new_with_mode(RING_BUFFER_CAPACITY, mode) {
+                Ok(ring) => ring,
+                Err(_) => anyhow::bail!("creating a ring buffer with valid capcity should be possible"),
+            };
+            let items: Vec<u32> = (0..ring.capacity() as u32).collect();
+
+            crate::ensure_eq!(ring.try_enqueue_all(&items[..3]), true);
+            crate::ensure_eq!(ring.len(), 3);
+            crate::ensure_eq!(ring.try_enqueue_all(&items[2..]), false);
+            crate::ensure_eq!(ring.len(), 3);
+            for i in 0..3 {
+                crate::ensure_eq!(ring.dequeue(), i);
+            }
+            crate::ensure_eq!(ring.try_enqueue_all(&items), true);
+            crate::ensure_eq!(ring.is_full(), true);
+            for i in 0..ring.capacity() {
+                crate::ensure_eq!(ring.dequeue(), i as u32);
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -7,7 +7,10 @@
 // Imports
 //======================================================================================================================
 use crate::{
-    collections::ring::RingBuffer,
+    collections::ring::{
+        RingBuffer,
+        RingMode,
+    },
     pal::linux::shm::SharedMemory,
     runtime::fail::Fail,
 };
@@ -20,7 +23,8 @@ use ::std::ops::Deref;
 /// A ring buffer that may be shared across processes.
 ///
 /// This structure resides on a shared memory region and it is lock-free.
-/// This abstraction ensures the correct concurrent access by a single writer and a single reader.
+/// This abstraction ensures the correct concurrent access by a single reader, and either a single writer or multiple
+/// writers, as chosen by the creator of the ring buffer.
 pub struct SharedRingBuffer<T: Copy> {
     #[allow(unused)]
     shm: SharedMemory,
@@ -33,19 +37,42 @@ pub struct SharedRingBuffer<T: Copy> {
 
 /// Associated functions for shared ring buffers.
 impl<T: Copy> SharedRingBuffer<T> {
-    /// Creates a new shared ring buffer.
+    /// Creates a new shared ring buffer with a single writer.
     pub fn create(name: &str, capacity: usize) -> Result<SharedRingBuffer<T>, Fail> {
+        Self::create_with_mode(name, capacity, RingMode::SingleProducer)
+    }
+
+    /// Creates a new shared ring buffer that admits as many writers as `mode` tells. Processes that open it later on
+    /// take over the mode.
+    pub fn create_with_mode(name: &str, capacity: usize, mode: RingMode) -> Result<SharedRingBuffer<T>, Fail> {
         let mut shm: SharedMemory = SharedMemory::create(&name, capacity)?;
-        let ring: RingBuffer<T> = RingBuffer::<T>::from_raw_parts(true, shm.as_mut_ptr(), shm.len())?;
+        let ring: RingBuffer<T> = RingBuffer::<T>::from_raw_parts(Some(mode), shm.as_mut_ptr(), shm.len())?;
         Ok(SharedRingBuffer { shm, ring })
     }
 
     /// Opens an existing shared ring buffer.
     pub fn open(name: &str, capacity: usize) -> Result<SharedRingBuffer<T>, Fail> {
         let mut shm: SharedMemory = SharedMemory::open(&name, capacity)?;
-        let ring: RingBuffer<T> = RingBuffer::<T>::from_raw_parts(false, shm.as_mut_ptr(), shm.len())?;
+        let ring: RingBuffer<T> = RingBuffer::<T>::from_raw_parts(None, shm.as_mut_ptr(), shm.len())?;
         Ok(SharedRingBuffer { shm, ring })
     }
+
+    /// Attaches to an existing shared ring buffer, which may have been created by another process. The capacity is
+    /// taken from the underlying shared memory region, so it does not need to be known beforehand.
+    #[allow(unused)]
+    pub fn attach(name: &str) -> Result<SharedRingBuffer<T>, Fail> {
+        let mut shm: SharedMemory = SharedMemory::attach(name)?;
+        let ring: RingBuffer<T> = RingBuffer::<T>::from_raw_parts(None, shm.as_mut_ptr(), shm.len())?;
+        Ok(SharedRingBuffer { shm, ring })
+    }
+
+    /// Removes the name of a shared ring buffer, so that no other process can open it. Processes that have it open may
+    /// keep using it. The creator of a ring buffer removes its name on drop, so this is meant for cleaning up after
+    /// processes that exit without dropping their ring buffers.
+    #[allow(unused)]
+    pub fn unlink(name: &str) -> Result<(), Fail> {
+        SharedMemory::unlink_name(name)
+    }
 }
 
 //======================================================================================================================
@@ -67,9 +94,20 @@ impl<T: Copy> Deref for SharedRingBuffer<T> {
 
 #[cfg(test)]
 mod test {
-    use super::SharedRingBuffer;
+    use super::{
+        RingMode,
+        SharedRingBuffer,
+    };
     use ::anyhow::Result;
     use std::{
+        env,
+        process::{
+            self,
+            Child,
+            Command,
+            ExitStatus,
+            Stdio,
+        },
         thread::{
             self,
             ScopedJoinHandle,
@@ -79,6 +117,10 @@ mod test {
 
     const RING_BUFFER_CAPACITY: usize = 4096;
 
+    /// Environment variable that hands the name of the shared ring buffer to the reader process of
+    /// [ring_buffer_on_shm_cross_process].
+    const READER_SHM_NAME: &str = "DEMIKERNEL_TEST_RING_BUFFER_READER";
+
     /// Tests if we succeed to perform sequential accesses to a shared ring buffer.
     #[ignore]
     #[test]
@@ -151,4 +193,118 @@ mod test {
 
         result
     }
+
+    /// Tests if two writers that open a multi-producer shared ring buffer on their own get all of their items across to
+    /// the reader exactly once, and in order for each writer.
+    #[ignore]
+    #[test]
+    fn ring_buffer_on_shm_multi_producer() -> Result<()> {
+        const NWRITERS: u32 = 2;
+        let shm_name: String = format!("shm-test-ring-buffer-multi-producer-{}", process::id());
+        let ring: SharedRingBuffer<u32> =
+            match SharedRingBuffer::<u32>::create_with_mode(&shm_name, RING_BUFFER_CAPACITY, RingMode::MultiProducer) {
+                Ok(ring) => ring,
+                Err(_) => anyhow::bail!("creating a shared ring buffer should be possible"),
+            };
+        let nitems: u32 = 4 * ring.capacity() as u32;
+        let mut result: Result<()> = Ok(());
+
+        thread::scope(|s| {
+            let mut writers: Vec<ScopedJoinHandle<Result<()>>> = Vec::new();
+            for writer in 0..NWRITERS {
+                let shm_name: &str = &shm_name;
+                writers.push(s.spawn(move || {
+                    let ring: SharedRingBuffer<u32> =
+                        match SharedRingBuffer::<u32>::open(shm_name, RING_BUFFER_CAPACITY) {
+                            Ok(ring) => ring,
+                            Err(_) => anyhow::bail!("opening a shared ring buffer should be possible"),
+                        };
+                    crate::ensure_eq!(ring.mode(), RingMode::MultiProducer);
+                    for i in 0..nitems {
+                        ring.enqueue((writer << 24) | i);
+                    }
+                    Ok(())
+                }));
+            }
+
+            // Each writer tags its items with its identifier in the upper bits, and a sequence number in the lower bits.
+            let mut next: [u32; NWRITERS as usize] = [0; NWRITERS as usize];
+            for _ in 0..(NWRITERS * nitems) {
+                let item: u32 = ring.dequeue();
+                let writer: usize = (item >> 24) as usize;
+                if item & 0xffffff != next[writer] {
+                    result = Err(anyhow::anyhow!(
+                        "writer {} sent {} after {}",
+                        writer,
+                        item & 0xffffff,
+                        next[writer]
+                    ));
+                }
+                next[writer] += 1;
+            }
+            for writer in writers {
+                result = std::mem::replace(&mut result, Ok(())).and(writer.join().unwrap());
+            }
+        });
+        crate::ensure_eq!(ring.is_empty(), true);
+
+        result
+    }
+
+    /// Tests if two processes can exchange data through a shared ring buffer. The reader process runs this test binary
+    /// again, with a filter that selects [ring_buffer_on_shm_reader].
+    #[ignore]
+    #[test]
+    fn ring_buffer_on_shm_cross_process() -> Result<()> {
+        let shm_name: String = format!("shm-test-ring-buffer-cross-process-{}", process::id());
+        let ring: SharedRingBuffer<u8> = match SharedRingBuffer::<u8>::create(&shm_name, RING_BUFFER_CAPACITY) {
+            Ok(ring) => ring,
+            Err(_) => anyhow::bail!("creating a shared ring buffer should be possible"),
+        };
+
+        let mut reader: Child = Command::new(env::current_exe()?)
+            .args([
+                "--ignored",
+                "--exact",
+                "collections::shared_ring::test::ring_buffer_on_shm_reader",
+            ])
+            .env(READER_SHM_NAME, &shm_name)
+            .stdout(Stdio::null())
+            .spawn()?;
+
+        // Write more than the ring buffer holds, so that the writer has to wait for the reader.
+        for i in 0..(4 * ring.capacity()) {
+            while ring.try_enqueue((i & 255) as u8).is_err() {
+                if let Some(status) = reader.try_wait()? {
+                    anyhow::bail!("reader exited before reading all data (status={:?})", status);
+                }
+            }
+        }
+
+        let status: ExitStatus = reader.wait()?;
+        crate::ensure_eq!(status.success(), true);
+        crate::ensure_eq!(ring.is_empty(), true);
+
+        Ok(())
+    }
+
+    /// Reader side of [ring_buffer_on_shm_cross_process]. This does nothing unless it runs in the reader process.
+    #[ignore]
+    #[test]
+    fn ring_buffer_on_shm_reader() -> Result<()> {
+        let shm_name: String = match env::var(READER_SHM_NAME) {
+            Ok(shm_name) => shm_name,
+            Err(_) => return Ok(()),
+        };
+        let ring: SharedRingBuffer<u8> = match SharedRingBuffer::<u8>::attach(&shm_name) {
+            Ok(ring) => ring,
+            Err(_) => anyhow::bail!("attaching to a shared ring buffer should be possible"),
+        };
+        for i in 0..(4 * ring.capacity()) {
+            let item: u8 = ring.dequeue();
+            crate::ensure_eq!(item, (i & 255) as u8);
+        }
+
+        Ok(())
+    }
 }
@@ -5,6 +5,8 @@
 // Imports
 //======================================================================================================================
 
+#[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+use crate::runtime::fail::Fail;
 use ::std::{
     fs::File,
     io::Read,
@@ -59,4 +61,144 @@ impl Config {
         }
         local_ipv4_addr
     }
+
+    /// Reads the "receive batch size" parameter from the underlying configuration file. If this parameter is not
+    /// present, then the default receive batch size is returned. A batch size that the network runtimes cannot
+    /// receive with, such as zero, is rejected with `EINVAL`.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    pub fn receive_batch_size(&self) -> Result<usize, Fail> {
+        use crate::runtime::network::{
+            check_receive_batch_size,
+            consts::RECEIVE_BATCH_SIZE,
+        };
+
+        // FIXME: Change the follow key from "catnip" to "demikernel".
+        match self.0["catnip"]["receive_batch_size"].as_i64() {
+            Some(receive_batch_size) => match usize::try_from(receive_batch_size) {
+                Ok(receive_batch_size) => check_receive_batch_size(receive_batch_size),
+                Err(_) => {
+                    let cause: String = format!("invalid receive_batch_size in config ({:?})", receive_batch_size);
+                    error!("receive_batch_size(): {}", cause);
+                    Err(Fail::new(libc::EINVAL, &cause))
+                },
+            },
+            None => Ok(RECEIVE_BATCH_SIZE),
+        }
+    }
+
+    /// Reads the "GRO max size" parameter from the underlying configuration file. This is the largest payload, in
+    /// bytes, of a TCP segment that receive coalescing merges back-to-back segments into. If this parameter is not
+    /// present, then receive coalescing is off. A size larger than an IPv4 datagram can carry is rejected with `EINVAL`.
+    #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos"))]
+    pub fn gro_max_size(&self) -> Result<usize, Fail> {
+        use crate::runtime::network::consts::MAX_GRO_SIZE;
+
+        // FIXME: Change the follow key from "catnip" to "demikernel".
+        match self.0["catnip"]["gro_max_size"].as_i64() {
+            Some(gro_max_size) => match usize::try_from(gro_max_size) {
+                Ok(gro_max_size) if gro_max_size <= MAX_GRO_SIZE => Ok(gro_max_size),
+                _ => {
+                    let cause: String = format!("invalid gro_max_size in config ({:?})", gro_max_size);
+                    error!("gro_max_size(): {}", cause);
+                    Err(Fail::new(libc::EINVAL, &cause))
+                },
+            },
+            None => Ok(0),
+        }
+    }
+
+    /// Reads the "scheduler capacity" parameter from the underlying configuration file. If this parameter is present,
+    /// the scheduler preallocates room for that many tasks and refuses to hold more. Otherwise, it grows on demand.
+    pub fn scheduler_capacity(&self) -> Option<usize> {
+        // FIXME: this function should return a result.
+        self.0["demikernel"]["scheduler_capacity"]
+            .as_i64()
+            .map(|scheduler_capacity| {
+                scheduler_capacity
+                    .try_into()
+                    .map_err(|_| anyhow::format_err!("Invalid scheduler_capacity in config"))
+                    .unwrap()
+            })
+    }
+
+    /// Reads the "operation timing" parameter from the underlying configuration file. If this parameter is set to
+    /// false, operations do not record when they start and complete. Otherwise, they do.
+    pub fn operation_timing(&self) -> bool {
+        self.0["demikernel"]["operation_timing"].as_bool().unwrap_or(true)
+    }
+
+    /// Gets the maximum number of queue descriptors that may be open at once, if any.
+    pub fn max_open_queues(&self) -> Option<usize> {
+        // FIXME: this function should return a result.
+        self.0["demikernel"]["max_open_queues"].as_i64().map(|max_open_queues| {
+            max_open_queues
+                .try_into()
+                .map_err(|_| anyhow::format_err!("Invalid max_open_queues in config"))
+                .unwrap()
+        })
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(all(test, any(feature = "catnip-libos", feature = "catpowder-libos")))]
+mod tests {
+    use super::Config;
+    use crate::runtime::network::consts::{
+        MAX_GRO_SIZE,
+        RECEIVE_BATCH_SIZE,
+    };
+    use ::anyhow::Result;
+    use ::yaml_rust::YamlLoader;
+
+    /// Builds a configuration object from a YAML string.
+    fn config_from_str(s: &str) -> Config {
+        Config(YamlLoader::load_from_str(s).expect("configuration should be valid YAML")[0].clone())
+    }
+
+    /// Tests that the default receive batch size is used when the configuration does not set one.
+    #[test]
+    fn receive_batch_size_default() -> Result<()> {
+        let config: Config = config_from_str("catnip:\n  mtu: 1500\n");
+        crate::ensure_eq!(config.receive_batch_size().ok(), Some(RECEIVE_BATCH_SIZE));
+        Ok(())
+    }
+
+    /// Tests that receive batch sizes that would stall or overflow the receive path are rejected.
+    #[test]
+    fn receive_batch_size_invalid() -> Result<()> {
+        for batch_size in ["0", "-1", "3", "65536"] {
+            let config: Config = config_from_str(&format!("catnip:\n  receive_batch_size: {}\n", batch_size));
+            match config.receive_batch_size() {
+                Ok(batch_size) => anyhow::bail!("receive batch size should be rejected (batch_size={:?})", batch_size),
+                Err(e) => crate::ensure_eq!(e.errno, libc::EINVAL),
+            }
+        }
+        Ok(())
+    }
+
+    /// Tests that receive coalescing is off unless the configuration turns it on.
+    #[test]
+    fn gro_max_size() -> Result<()> {
+        let config: Config = config_from_str("catnip:\n  mtu: 1500\n");
+        crate::ensure_eq!(config.gro_max_size().ok(), Some(0));
+        let config: Config = config_from_str("catnip:\n  gro_max_size: 16384\n");
+        crate::ensure_eq!(config.gro_max_size().ok(), Some(16384));
+        Ok(())
+    }
+
+    /// Tests that coalesced segments larger than an IPv4 datagram can carry are rejected.
+    #[test]
+    fn gro_max_size_invalid() -> Result<()> {
+        for gro_max_size in [-1, MAX_GRO_SIZE as i64 + 1] {
+            let config: Config = config_from_str(&format!("catnip:\n  gro_max_size: {}\n", gro_max_size));
+            match config.gro_max_size() {
+                Ok(gro_max_size) => anyhow::bail!("GRO max size should be rejected (gro_max_size={:?})", gro_max_size),
+                Err(e) => crate::ensure_eq!(e.errno, libc::EINVAL),
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,178 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    demikernel::libos::LibOS,
+    runtime::{
+        fail::Fail,
+        types::{
+            demi_sgarray_t,
+            demi_sgaseg_t,
+            DEMI_SGARRAY_MAXLEN,
+        },
+        QDesc,
+        QToken,
+    },
+};
+use ::std::{
+    mem,
+    net::SocketAddrV4,
+    ptr,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Holds back the pushes to an I/O queue, so that they go out together. On a TCP socket, the data of all pushes is sent
+/// in a single push, like `TCP_CORK` does on Linux. On a UDP socket, the data of all pushes is sent in a single
+/// datagram. The guard borrows the LibOS, so that no other operation gets in between.
+///
+/// The pushes only go out once the guard is flushed with [CorkGuard::flush], which returns the queue token to wait on.
+/// Dropping the guard without flushing it discards them. The scatter-gather arrays that are pushed must stay valid until
+/// then, and the caller remains responsible for releasing them.
+#[must_use = "held back pushes are discarded unless the guard is flushed"]
+pub struct CorkGuard<'a> {
+    libos: &'a mut LibOS,
+    qd: QDesc,
+    // Remote address of the datagram that is held back, if the pushes are meant for a UDP socket.
+    remote: Option<SocketAddrV4>,
+    sgas: Vec<demi_sgarray_t>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl<'a> CorkGuard<'a> {
+    /// Creates a guard that holds back the pushes to the I/O queue referred to by `qd`.
+    pub(super) fn new(libos: &'a mut LibOS, qd: QDesc) -> Self {
+        Self {
+            libos,
+            qd,
+            remote: None,
+            sgas: Vec::new(),
+        }
+    }
+
+    /// Holds back a push of a scatter-gather array (see [LibOS::push]).
+    pub fn push(&mut self, sga: &demi_sgarray_t) -> Result<(), Fail> {
+        if let Some(remote) = self.remote {
+            let cause: String = format!("pushes to {:?} are held back already (qd={:?})", remote, self.qd);
+            error!("push(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        Self::check_sga(sga)?;
+        self.sgas.push(*sga);
+        Ok(())
+    }
+
+    /// Holds back a push of a scatter-gather array to a remote address (see [LibOS::pushto]). All pushes that are held
+    /// back together must go to the same remote address, since they end up in the same datagram.
+    pub fn pushto(&mut self, sga: &demi_sgarray_t, to: SocketAddrV4) -> Result<(), Fail> {
+        match self.remote {
+            Some(remote) if remote != to => {
+                let cause: String = format!("pushes to {:?} are held back already (qd={:?})", remote, self.qd);
+                error!("pushto(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+            None if !self.sgas.is_empty() => {
+                let cause: String = format!(
+                    "pushes without a remote address are held back already (qd={:?})",
+                    self.qd
+                );
+                error!("pushto(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+            _ => {},
+        }
+        Self::check_sga(sga)?;
+        self.remote = Some(to);
+        self.sgas.push(*sga);
+        Ok(())
+    }
+
+    /// Sends the data of all pushes that were held back in a single push. Upon success, the queue token of that push is
+    /// returned.
+    ///
+    /// A push takes a scatter-gather array with a single segment, so the data of all pushes is copied into a fresh one
+    /// rather than chained. This fails if the data adds up to more than [LibOS::sgaalloc] can allocate, which is 64 KiB.
+    pub fn flush(mut self) -> Result<QToken, Fail> {
+        let sgas: Vec<demi_sgarray_t> = mem::take(&mut self.sgas);
+        if sgas.is_empty() {
+            let cause: String = format!("no pushes were held back (qd={:?})", self.qd);
+            error!("flush(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        let segments: Vec<demi_sgaseg_t> = sgas
+            .iter()
+            .flat_map(|sga| {
+                let segs: [demi_sgaseg_t; DEMI_SGARRAY_MAXLEN] = sga.sga_segs;
+                segs.into_iter().take(sga.sga_numsegs as usize)
+            })
+            .collect();
+        let len: usize = segments.iter().map(|seg| seg.sgaseg_len as usize).sum();
+        let sga: demi_sgarray_t = self.libos.sgaalloc(len)?;
+        let mut dst: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
+        for seg in segments {
+            // Safety: The segments were checked when they were pushed, and the caller keeps them valid until now. The
+            // scatter-gather array that we just allocated holds all of them.
+            unsafe {
+                ptr::copy_nonoverlapping(seg.sgaseg_buf as *const u8, dst, seg.sgaseg_len as usize);
+                dst = dst.add(seg.sgaseg_len as usize);
+            }
+        }
+
+        let result: Result<QToken, Fail> = match self.remote {
+            Some(to) => self.libos.pushto(self.qd, &sga, to),
+            None => self.libos.push(self.qd, &sga),
+        };
+        // The LibOS takes its own reference to the data, so we can let go of ours right away.
+        if let Err(e) = self.libos.sgafree(sga) {
+            warn!(
+                "flush(): failed to release scatter-gather array (qd={:?}, error={:?})",
+                self.qd, e
+            );
+        }
+        result
+    }
+
+    /// Checks that a scatter-gather array describes data that we can copy out later on.
+    fn check_sga(sga: &demi_sgarray_t) -> Result<(), Fail> {
+        let numsegs: usize = sga.sga_numsegs as usize;
+        let segs: [demi_sgaseg_t; DEMI_SGARRAY_MAXLEN] = sga.sga_segs;
+        if numsegs == 0 || numsegs > DEMI_SGARRAY_MAXLEN {
+            return Err(Fail::new(libc::EINVAL, "invalid segment count"));
+        }
+        if segs[..numsegs]
+            .iter()
+            .any(|seg| seg.sgaseg_buf.is_null() && seg.sgaseg_len != 0)
+        {
+            return Err(Fail::new(libc::EINVAL, "invalid segment"));
+        }
+        Ok(())
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+/// Drop trait implementation for cork guards.
+impl Drop for CorkGuard<'_> {
+    /// Discards the pushes that are still held back, since nobody could wait on their outcome.
+    fn drop(&mut self) {
+        if !self.sgas.is_empty() {
+            warn!(
+                "drop(): discarding {:?} held back pushes that were not flushed (qd={:?})",
+                self.sgas.len(),
+                self.qd
+            );
+        }
+    }
+}
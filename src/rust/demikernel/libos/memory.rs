@@ -8,15 +8,18 @@
 use crate::{
     runtime::{
         fail::Fail,
+        network::types::SocketOption,
         types::{
             demi_qresult_t,
             demi_sgarray_t,
         },
         QDesc,
         QToken,
+        QueueInfo,
     },
     scheduler::TaskHandle,
 };
+use ::std::time::Duration;
 
 #[cfg(feature = "catmem-libos")]
 use crate::catmem::CatmemLibOS;
@@ -67,6 +70,26 @@ impl MemoryLibOS {
         }
     }
 
+    /// Takes a snapshot of every open memory queue.
+    #[allow(unreachable_patterns)]
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.queues(),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Takes a snapshot of a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn query_queue(&self, memqd: QDesc) -> Result<QueueInfo, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.query_queue(memqd),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     /// Asynchronously closes a memory queue.
     #[allow(unreachable_patterns, unused_variables)]
     pub fn async_close(&mut self, memqd: QDesc) -> Result<QToken, Fail> {
@@ -87,6 +110,56 @@ impl MemoryLibOS {
         }
     }
 
+    /// Returns the number of bytes that may be popped right away from a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn bytes_available(&self, memqd: QDesc) -> Result<usize, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.bytes_available(memqd),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single push transfers as a unit on a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn max_push_size(&self, memqd: QDesc) -> Result<usize, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.max_push_size(memqd),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Forces transmission of all data that is buffered on a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn flush(&self, memqd: QDesc) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.flush(memqd),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Sets whether operations on a memory queue that cannot complete right away fail with `EWOULDBLOCK`.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn set_nonblocking(&mut self, memqd: QDesc, nonblocking: bool) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.set_nonblocking(memqd, nonblocking),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Sets an option on a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn setsockopt(&mut self, memqd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.setsockopt(memqd, option),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     /// Pops data from a memory queue.
     #[allow(unreachable_patterns, unused_variables)]
     pub fn pop(&mut self, memqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
@@ -97,6 +170,26 @@ impl MemoryLibOS {
         }
     }
 
+    /// Pops data from a memory queue without removing it.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn pop_peek(&mut self, memqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.pop_peek(memqd, size),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Pops data from a memory queue, failing with `ETIMEDOUT` if no data arrives within `timeout`.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn pop_timeout(&mut self, memqd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem(libos) => libos.pop_timeout(memqd, size, timeout),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     /// Allocates a scatter-gather array.
     #[allow(unreachable_patterns, unused_variables)]
     pub fn sgaalloc(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
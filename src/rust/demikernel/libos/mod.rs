@@ -1,37 +1,78 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+pub mod cork;
 pub mod memory;
 pub mod name;
 pub mod network;
+pub mod resolver;
 
 //======================================================================================================================
 // Imports
 //======================================================================================================================
 
 use self::{
+    cork::CorkGuard,
     memory::MemoryLibOS,
     name::LibOSName,
     network::NetworkLibOS,
+    resolver::{
+        NumericResolver,
+        Resolver,
+    },
 };
 use crate::{
-    demikernel::config::Config,
+    demikernel::{
+        bindings::sockaddr_to_socketaddrv4,
+        config::Config,
+    },
+    pal::data_structures::SockAddr,
     runtime::{
         fail::Fail,
         limits,
         logging,
+        memory::{
+            DemiBuffer,
+            SgaGuard,
+        },
+        network::types::{
+            ByteStats,
+            ConnEvent,
+            DpdkPortStats,
+            LinkStatus,
+            MacAddress,
+            NicStats,
+            PeerStats,
+            PushCompletion,
+            SocketOption,
+        },
         types::{
+            demi_accept_result_t,
+            demi_opcode_t,
             demi_qresult_t,
             demi_sgarray_t,
+            DEMI_QR_EOF,
         },
+        ConnectionInfo,
+        OperationResult,
         QDesc,
         QToken,
+        QueueInfo,
+        QueueState,
+    },
+    scheduler::{
+        TaskHandle,
+        TaskTiming,
     },
-    scheduler::TaskHandle,
 };
+use ::libc::sockaddr;
 use ::std::{
     env,
-    net::SocketAddrV4,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    slice,
     time::{
         Duration,
         Instant,
@@ -54,6 +95,17 @@ use crate::catnip::CatnipLibOS;
 #[cfg(feature = "catpowder-libos")]
 use crate::catpowder::CatpowderLibOS;
 
+//======================================================================================================================
+// Thread Locals
+//======================================================================================================================
+
+#[cfg(test)]
+thread_local! {
+    /// Number of times that a LibOS polled its scheduler on this thread. This is kept per thread, so that tests that run
+    /// side by side do not count each other's polls.
+    static POLL_COUNT: ::std::cell::Cell<usize> = ::std::cell::Cell::new(0);
+}
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -87,6 +139,7 @@ impl LibOS {
             },
         };
         let config: Config = Config::new(config_path);
+        TaskTiming::set_enabled(config.operation_timing());
 
         // Instantiate LibOS.
         #[allow(unreachable_patterns)]
@@ -98,9 +151,11 @@ impl LibOS {
             #[cfg(feature = "catcollar-libos")]
             LibOSName::Catcollar => Self::NetworkLibOS(NetworkLibOS::Catcollar(CatcollarLibOS::new(&config))),
             #[cfg(feature = "catpowder-libos")]
-            LibOSName::Catpowder => Self::NetworkLibOS(NetworkLibOS::Catpowder(CatpowderLibOS::new(&config))),
+            LibOSName::Catpowder => Self::NetworkLibOS(NetworkLibOS::Catpowder(CatpowderLibOS::new(&config)?)),
             #[cfg(feature = "catnip-libos")]
-            LibOSName::Catnip => Self::NetworkLibOS(NetworkLibOS::Catnip(CatnipLibOS::new(&config))),
+            LibOSName::Catnip => Self::NetworkLibOS(NetworkLibOS::Catnip(CatnipLibOS::new(&config)?)),
+            #[cfg(feature = "catnip-tap-libos")]
+            LibOSName::CatnipTap => Self::NetworkLibOS(NetworkLibOS::Catpowder(CatpowderLibOS::new_tap(&config)?)),
             #[cfg(feature = "catmem-libos")]
             LibOSName::Catmem => Self::MemoryLibOS(MemoryLibOS::Catmem(CatmemLibOS::new())),
             #[cfg(feature = "catloop-libos")]
@@ -154,6 +209,22 @@ impl LibOS {
         result
     }
 
+    /// Creates a pair of connected stream sockets, like `socketpair(2)` does. Data pushed to either socket can be popped
+    /// from the other one right away, without connecting them first. This is supported on Catloop only.
+    pub fn socketpair(&mut self) -> Result<(QDesc, QDesc), Fail> {
+        let result: Result<(QDesc, QDesc), Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.socketpair(),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "socketpair() is not supported on memory liboses",
+            )),
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Binds a socket to a local address.
     pub fn bind(&mut self, sockqd: QDesc, local: SocketAddrV4) -> Result<(), Fail> {
         let result: Result<(), Fail> = match self {
@@ -166,6 +237,47 @@ impl LibOS {
         result
     }
 
+    /// Returns the local address that a socket is bound to. After binding to port zero, this reports the ephemeral
+    /// port that was selected.
+    pub fn getsockname(&self, sockqd: QDesc) -> Result<SocketAddrV4, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.getsockname(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "getsockname() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Takes a snapshot of every open queue descriptor, telling its type, the state and addresses of the socket
+    /// behind it, how many operations are pending on it and how long ago it was created.
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.queues(),
+            LibOS::MemoryLibOS(libos) => libos.queues(),
+        }
+    }
+
+    /// Takes a snapshot of the queue referred to by a queue descriptor (see [LibOS::queues]).
+    pub fn query_queue(&self, qd: QDesc) -> Result<QueueInfo, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.query_queue(qd),
+            LibOS::MemoryLibOS(libos) => libos.query_queue(qd),
+        }
+    }
+
+    /// Takes a snapshot of every connection that was accepted and is still open, telling its remote address, its state
+    /// and how many bytes went through it. Only the catloop LibOS keeps track of its connections.
+    pub fn list_connections(&self) -> Result<Vec<ConnectionInfo>, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.list_connections(),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "list_connections() is not supported on memory liboses",
+            )),
+        }
+    }
+
     /// Marks a socket as a passive one.
     pub fn listen(&mut self, sockqd: QDesc, backlog: usize) -> Result<(), Fail> {
         let result: Result<(), Fail> = match self {
@@ -190,6 +302,51 @@ impl LibOS {
         result
     }
 
+    /// Accepts an incoming connection on a TCP socket. The operation completes with `ETIMEDOUT` if no connection
+    /// request arrives within `timeout`, in which case the socket remains usable.
+    pub fn accept_timeout(&mut self, sockqd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.accept_timeout(sockqd, timeout),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "accept_timeout() is not supported on memory liboses",
+            )),
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Stops accepting incoming connections on a listening TCP socket, without closing it. Connections that were not
+    /// yet accepted are reset, and pending accepts are aborted. Established connections are not affected.
+    pub fn stop_listening(&mut self, sockqd: QDesc) -> Result<(), Fail> {
+        let result: Result<(), Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.stop_listening(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "stop_listening() is not supported on memory liboses",
+            )),
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Duplicates a queue descriptor. The underlying queue is only closed once all queue descriptors that refer to it
+    /// are closed.
+    pub fn dup(&mut self, qd: QDesc) -> Result<QDesc, Fail> {
+        let result: Result<QDesc, Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.dup(qd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "dup() is not supported on memory liboses")),
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Initiates a connection with a remote TCP socket.
     pub fn connect(&mut self, sockqd: QDesc, remote: SocketAddrV4) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = match self {
@@ -202,6 +359,34 @@ impl LibOS {
         result
     }
 
+    /// Binds a socket to a local address and initiates a connection with a remote TCP socket. If the port of `local`
+    /// is zero, an ephemeral port is selected. If the socket is already bound, `local` must match its address.
+    pub fn connect_from(&mut self, sockqd: QDesc, local: SocketAddrV4, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.connect_from(sockqd, local, remote),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "connect_from() is not supported on memory liboses",
+            )),
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Initiates a connection with a remote TCP socket that is named by `host`, which has the form `name:port`. Only
+    /// dotted-quad names are understood, see [LibOS::connect_host_with] to resolve other names.
+    pub fn connect_host(&mut self, sockqd: QDesc, host: &str) -> Result<QToken, Fail> {
+        self.connect_host_with(sockqd, host, &NumericResolver)
+    }
+
+    /// Initiates a connection with a remote TCP socket that is named by `host`, which has the form `name:port`. The
+    /// name is translated by `resolver`, and the candidate addresses are tried in order until a connect is issued.
+    pub fn connect_host_with(&mut self, sockqd: QDesc, host: &str, resolver: &dyn Resolver) -> Result<QToken, Fail> {
+        resolver::connect_host(host, resolver, |remote| self.connect(sockqd, remote))
+    }
+
     /// Closes an I/O queue.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         let result: Result<(), Fail> = match self {
@@ -237,6 +422,24 @@ impl LibOS {
         result
     }
 
+    /// Pushes a scatter-gather array to a TCP socket. Unlike [LibOS::push], the operation completes only once the peer
+    /// acknowledges all data in `sga`, rather than once the data is queued for transmission. It fails with `ETIMEDOUT`
+    /// if that does not happen within `timeout`. As data that was sent cannot be taken back, the connection is then
+    /// aborted, and further operations on it fail.
+    pub fn push_timeout(&mut self, qd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.push_timeout(qd, sga, timeout),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "push_timeout() is not supported on memory liboses",
+            )),
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Pushes a scatter-gather array to a UDP socket.
     pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, to: SocketAddrV4) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = match self {
@@ -249,6 +452,251 @@ impl LibOS {
         result
     }
 
+    /// Pushes a batch of scatter-gather arrays to a UDP socket, each one to its own remote address, in a single pass.
+    /// Upon success, one queue token is returned for each datagram, in order.
+    pub fn pushto_batch(
+        &mut self,
+        qd: QDesc,
+        datagrams: &[(demi_sgarray_t, SocketAddrV4)],
+    ) -> Result<Vec<QToken>, Fail> {
+        let result: Result<Vec<QToken>, Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.pushto_batch(qd, datagrams),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "pushto_batch() is not supported on memory liboses",
+            )),
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Holds back the pushes to an I/O queue until the returned guard is flushed, so that they go out as a single TCP
+    /// push or UDP datagram (see [CorkGuard]).
+    pub fn cork(&mut self, qd: QDesc) -> Result<CorkGuard<'_>, Fail> {
+        // LibOSes that cannot tell about their queues leave it to the push to check the queue descriptor.
+        match self.query_queue(qd) {
+            Ok(_) => {},
+            Err(e) if e.errno == libc::ENOTSUP => {},
+            Err(e) => return Err(e),
+        }
+        Ok(CorkGuard::new(self, qd))
+    }
+
+    /// Returns the number of bytes that may be popped from an I/O queue right away, so that applications may size their
+    /// pops and skip them while no data is ready. For a datagram socket, this is the size of the next datagram. For a
+    /// listening socket, this is the number of connections that may be accepted right away instead.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.bytes_available(qd),
+            LibOS::MemoryLibOS(libos) => libos.bytes_available(qd),
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single push transfers as a unit on an I/O queue. Pushes to a
+    /// datagram socket that exceed this size fail with `EMSGSIZE`, whereas pushes to a stream socket or to a memory
+    /// queue are split into multiple units by the underlying LibOS.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.max_push_size(qd),
+            LibOS::MemoryLibOS(libos) => libos.max_push_size(qd),
+        }
+    }
+
+    /// Returns the packet, byte and drop counters of the NIC that the underlying LibOS drives.
+    pub fn nic_stats(&self) -> Result<NicStats, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.nic_stats(),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "nic_stats() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Returns the packet, byte, error and drop counters that the NIC keeps in hardware for the port that the underlying
+    /// LibOS drives. These reveal drops that happen on the NIC, before frames reach the LibOS.
+    pub fn network_stats(&self) -> Result<DpdkPortStats, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.network_stats(),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "network_stats() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Returns the status of the link of the NIC that the underlying LibOS drives, so that applications may react to a
+    /// link going down. While the link is down, pushes fail with `ENETDOWN`.
+    pub fn link_status(&self) -> Result<LinkStatus, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.link_status(),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "link_status() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Returns the number of bytes that the connection referred to by `qd` sent, received and retransmitted since it was
+    /// established. The counters remain available until the socket is closed.
+    pub fn byte_stats(&self, qd: QDesc) -> Result<ByteStats, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.byte_stats(qd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "byte_stats() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Returns the telemetry on the remote hosts that the LibOS connected to, aggregated across all TCP connections to
+    /// each of them: smoothed round-trip time, retransmitted bytes and failed connection attempts. Remote hosts are
+    /// forgotten once they go without connections and updates for the retention time in the TCP configuration.
+    pub fn peer_telemetry(&self) -> Result<Vec<(Ipv4Addr, PeerStats)>, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.peer_telemetry(),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "peer_telemetry() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Takes the oldest TCP connection event, waiting up to `timeout` for one to come up: a connection that was
+    /// established, reset or closed, or a listening socket that turned away a connection because its accept queue was
+    /// full. Returns `None` if the timeout expires first. Events are held in a bounded queue, so an application that
+    /// falls behind on draining it loses events and gets a [ConnEvent::Overflow] in their place.
+    pub fn next_event(&mut self, timeout: Option<Duration>) -> Result<Option<ConnEvent>, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.next_event(timeout),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "next_event() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Changes the local IPv4 and link addresses of the underlying LibOS, as needed when the local IPv4 address moves to
+    /// a different NIC port. New connections use the new addresses, while established connections keep the old ones.
+    pub fn set_local_identity(&mut self, ipv4_addr: Ipv4Addr, link_addr: MacAddress) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.set_local_identity(ipv4_addr, link_addr),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_local_identity() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Broadcasts an ARP request for `target` right away, so that the ARP cache may be warmed up before connections are
+    /// opened. The reply, if any, updates the ARP cache. Success means that the request went out, not that a reply came
+    /// back.
+    pub fn send_arp_request(&mut self, target: Ipv4Addr) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.send_arp_request(target),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "send_arp_request() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Broadcasts an ARP request for `target`, and waits up to `timeout` for the reply. Returns the link address of
+    /// `target`, or fails with `ETIMEDOUT` if no reply comes back in time.
+    pub fn wait_arp(&mut self, target: Ipv4Addr, timeout: Duration) -> Result<MacAddress, Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.wait_arp(target, timeout),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "wait_arp() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Makes a UDP socket join a multicast group, receiving datagrams from any source. Sources may then be filtered out
+    /// with [LibOS::block_source].
+    pub fn join_multicast(&mut self, qd: QDesc, group: Ipv4Addr) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.join_multicast(qd, group),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "join_multicast() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Makes a UDP socket receive the datagrams that `src` sends to a multicast group, as in source-specific multicast
+    /// (RFC 4607). Datagrams from other sources are filtered out, unless they were joined in the same way.
+    pub fn join_source_specific_multicast(&mut self, qd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.join_source_specific_multicast(qd, group, src),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "join_source_specific_multicast() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Stops a UDP socket from receiving the datagrams that `src` sends to a multicast group, which the socket should
+    /// have joined with [LibOS::join_multicast].
+    pub fn block_source(&mut self, qd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.block_source(qd, group, src),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "block_source() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Forces transmission of all data that is buffered on an I/O queue, including data that is held back by
+    /// delayed acknowledgements or by segment coalescing. This is useful before a latency-critical wait.
+    pub fn flush(&self, qd: QDesc) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.flush(qd),
+            LibOS::MemoryLibOS(libos) => libos.flush(qd),
+        }
+    }
+
+    /// Sets when pushes on a socket complete: as soon as their data is queued for transmission (the default), or only
+    /// once the peer has acknowledged all of it. The latter is only supported on TCP sockets.
+    pub fn set_push_completion(&mut self, qd: QDesc, push_completion: PushCompletion) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.set_push_completion(qd, push_completion),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_push_completion() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Sets an option on an I/O queue. See [SocketOption] for the options that are available. This is supported on
+    /// memory queues and on Catnap sockets.
+    pub fn setsockopt(&mut self, qd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.setsockopt(qd, option),
+            LibOS::MemoryLibOS(libos) => libos.setsockopt(qd, option),
+        }
+    }
+
+    /// Sets whether operations on an I/O queue that cannot complete right away, such as a pop with no data available,
+    /// fail with `EWOULDBLOCK` instead of pending. This mirrors `O_NONBLOCK`. I/O queues are blocking by default.
+    pub fn set_nonblocking(&mut self, qd: QDesc, nonblocking: bool) -> Result<(), Fail> {
+        let result: Result<(), Fail> = match self {
+            LibOS::NetworkLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_nonblocking() is not supported on network liboses",
+            )),
+            LibOS::MemoryLibOS(libos) => libos.set_nonblocking(qd, nonblocking),
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Pops data from a an I/O queue.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         // Check if this is a fixed-size pop.
@@ -271,8 +719,82 @@ impl LibOS {
         result
     }
 
+    /// Pops data from an I/O queue without removing it, as `MSG_PEEK` does. The operation completes like [LibOS::pop],
+    /// but with a copy of the data, so that the next pop returns the same bytes. This is supported on memory queues and
+    /// on TCP sockets of LibOSes that run their own network stack.
+    pub fn pop_peek(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        // Check if this is a fixed-size pop.
+        if let Some(size) = size {
+            // Check if size is valid.
+            if !((size > 0) && (size <= limits::POP_SIZE_MAX)) {
+                let cause: String = format!("invalid pop size (size={:?})", size);
+                error!("pop_peek(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+        }
+
+        let result: Result<QToken, Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.pop_peek(qd, size),
+            LibOS::MemoryLibOS(libos) => libos.pop_peek(qd, size),
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Pops up to `max_msgs` datagrams or stream chunks from a socket in a single operation. The operation completes as
+    /// soon as some data is available, with as much of the data that is ready at that time as fits. Its result must be
+    /// retrieved with [LibOS::wait_pop_multiple].
+    pub fn pop_multiple(&mut self, qd: QDesc, max_msgs: usize) -> Result<QToken, Fail> {
+        if max_msgs == 0 {
+            let cause: String = format!("cannot pop zero messages (qd={:?})", qd);
+            error!("pop_multiple(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        let result: Result<QToken, Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.pop_multiple(qd, max_msgs),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "pop_multiple() is not supported on memory liboses",
+            )),
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Pops data from an I/O queue. The operation completes with `ETIMEDOUT` if no data arrives within `timeout`, in
+    /// which case the I/O queue remains usable.
+    pub fn pop_timeout(&mut self, qd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        // Check if this is a fixed-size pop.
+        if let Some(size) = size {
+            // Check if size is valid.
+            if !((size > 0) && (size <= limits::POP_SIZE_MAX)) {
+                let cause: String = format!("invalid pop size (size={:?})", size);
+                error!("pop_timeout(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+        }
+
+        let result: Result<QToken, Fail> = match self {
+            LibOS::NetworkLibOS(libos) => libos.pop_timeout(qd, size, timeout),
+            LibOS::MemoryLibOS(libos) => libos.pop_timeout(qd, size, timeout),
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Waits for a pending I/O operation to complete or a timeout to expire.
     /// This is just a single-token convenience wrapper for wait_any().
+    ///
+    /// A timeout of [Duration::ZERO] makes this a non-blocking check: the scheduler is polled exactly once, and the
+    /// wait fails with `ETIMEDOUT` if the operation did not complete in that poll. Otherwise, polls never block, so the
+    /// wait fails no earlier than `timeout` after it started, and no later than one poll past that.
     pub fn wait(&mut self, qt: QToken, timeout: Option<Duration>) -> Result<demi_qresult_t, Fail> {
         trace!("wait(): qt={:?}, timeout={:?}", qt, timeout);
 
@@ -285,6 +807,49 @@ impl LibOS {
         Ok(qr)
     }
 
+    /// Waits for an I/O operation to complete or a timeout to expire, like [LibOS::wait], but also returns when the
+    /// operation started and completed. No timestamps are returned if operation timing is disabled.
+    pub fn wait_with_timing(
+        &mut self,
+        qt: QToken,
+        timeout: Option<Duration>,
+    ) -> Result<(demi_qresult_t, Option<TaskTiming>), Fail> {
+        trace!("wait_with_timing(): qt={:?}, timeout={:?}", qt, timeout);
+
+        let qr: demi_qresult_t = self.wait(qt, timeout)?;
+        let timing: Option<TaskTiming> = if qr.qr_start_ns != 0 {
+            Some(TaskTiming {
+                created: TaskTiming::from_nanos(qr.qr_start_ns),
+                completed: Some(TaskTiming::from_nanos(qr.qr_end_ns)),
+            })
+        } else {
+            None
+        };
+        Ok((qr, timing))
+    }
+
+    /// Waits for a pop operation to complete or a timeout to expire. The popped scatter-gather array is handed back in
+    /// a guard that releases it once dropped, so the caller does not have to call [LibOS::sgafree] on it. Waiting on a
+    /// token of any other operation consumes its result and fails with `EINVAL`.
+    pub fn wait_pop(&mut self, qt: QToken, timeout: Option<Duration>) -> Result<SgaGuard, Fail> {
+        trace!("wait_pop(): qt={:?}, timeout={:?}", qt, timeout);
+
+        let qr: demi_qresult_t = self.wait(qt, timeout)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_POP => Ok(SgaGuard::new(unsafe { qr.qr_value.sga })),
+            demi_opcode_t::DEMI_OPC_FAILED => {
+                let cause: String = format!("pop failed (qt={:?}, errno={:?})", qt, qr.qr_ret);
+                error!("wait_pop(): {}", cause);
+                Err(Fail::new(qr.qr_ret as i32, &cause))
+            },
+            opcode => {
+                let cause: String = format!("not a pop operation (qt={:?}, opcode={:?})", qt, opcode);
+                error!("wait_pop(): {}", cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
     /// Waits for an I/O operation to complete or a timeout to expire.
     pub fn timedwait(&mut self, qt: QToken, abstime: Option<SystemTime>) -> Result<demi_qresult_t, Fail> {
         trace!("timedwait() qt={:?}, timeout={:?}", qt, abstime);
@@ -307,36 +872,112 @@ impl LibOS {
         }
     }
 
-    /// Waits for any of the given pending I/O operations to complete or a timeout to expire.
+    /// Waits for any of the given pending I/O operations to complete or a timeout to expire. A timeout of
+    /// [Duration::ZERO] polls the scheduler exactly once (see [LibOS::wait]).
     pub fn wait_any(&mut self, qts: &[QToken], timeout: Option<Duration>) -> Result<(usize, demi_qresult_t), Fail> {
         trace!("wait_any(): qts={:?}, timeout={:?}", qts, timeout);
 
-        // Get the wait start time, but only if we have a timeout.  We don't care when we started if we wait forever.
-        let start: Option<Instant> = if timeout.is_none() { None } else { Some(Instant::now()) };
+        // Retrieve associated schedule handles once, so that each iteration only polls and checks them.
+        let handles: Vec<TaskHandle> = qts
+            .iter()
+            .map(|&qt| self.schedule(qt))
+            .collect::<Result<Vec<TaskHandle>, Fail>>()?;
+        let deadline: Option<Instant> = Self::deadline(timeout);
 
         loop {
             // Poll first, so as to give pending operations a chance to complete.
             self.poll();
 
-            // Search for any operation that has completed.
-            for (i, &qt) in qts.iter().enumerate() {
-                // Retrieve associated schedule handle.
-                // TODO: move this out of the loop.
-                let handle: TaskHandle = self.schedule(qt)?;
+            // Found one that has completed, so extract the result and return.
+            if let Some(i) = handles.iter().position(|handle| handle.has_completed()) {
+                return Ok((i, self.pack_result(handles[i].clone(), qts[i])?));
+            }
+
+            Self::check_deadline(deadline)?;
+        }
+    }
+
+    /// Waits for any of the given pending I/O operations to complete or a timeout to expire, like [LibOS::wait_any], but
+    /// returns the result as an [OperationResult] along with the I/O queue that the operation was issued on.
+    pub fn wait2_any(
+        &mut self,
+        qts: &[QToken],
+        timeout: Option<Duration>,
+    ) -> Result<(usize, QDesc, OperationResult), Fail> {
+        trace!("wait2_any(): qts={:?}, timeout={:?}", qts, timeout);
+        let (offset, qr): (usize, demi_qresult_t) = self.wait_any(qts, timeout)?;
+        let (qd, result): (QDesc, OperationResult) = self.qresult_to_operation_result(qr)?;
+        Ok((offset, qd, result))
+    }
 
-                // Found one, so extract the result and return.
-                if handle.has_completed() {
-                    return Ok((i, self.pack_result(handle, qt)?));
+    /// Converts the result of an I/O operation into an [OperationResult]. The data of a pop is copied out of its
+    /// scatter-gather array, which is released afterwards.
+    pub fn qresult_to_operation_result(&mut self, qr: demi_qresult_t) -> Result<(QDesc, OperationResult), Fail> {
+        let qd: QDesc = QDesc::from(qr.qr_qd);
+        let result: OperationResult = match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_PUSH => OperationResult::Push,
+            demi_opcode_t::DEMI_OPC_CONNECT => OperationResult::Connect,
+            demi_opcode_t::DEMI_OPC_CLOSE => OperationResult::Close,
+            demi_opcode_t::DEMI_OPC_ACCEPT => {
+                let ares: demi_accept_result_t = unsafe { qr.qr_value.ares };
+                let addr: SockAddr = ares.addr;
+                let remote: SocketAddrV4 = sockaddr_to_socketaddrv4(&addr as *const SockAddr as *const sockaddr)?;
+                OperationResult::Accept((QDesc::from(ares.qd as u32), remote))
+            },
+            demi_opcode_t::DEMI_OPC_POP => {
+                let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+                let mut data: Vec<u8> = Vec::new();
+                let numsegs: usize = sga.sga_numsegs as usize;
+                for i in 0..numsegs {
+                    let seg_ptr: *const u8 = sga.sga_segs[i].sgaseg_buf as *const u8;
+                    let seg_len: usize = sga.sga_segs[i].sgaseg_len as usize;
+                    data.extend_from_slice(unsafe { slice::from_raw_parts(seg_ptr, seg_len) });
                 }
-            }
+                // Only pops on sockets carry the address of the remote peer.
+                let addr: SockAddr = sga.sga_addr;
+                let remote: Option<SocketAddrV4> =
+                    sockaddr_to_socketaddrv4(&addr as *const SockAddr as *const sockaddr).ok();
+                // Copy the data into a buffer before releasing the scatter-gather array, which is released either way.
+                let buf: Result<DemiBuffer, Fail> = DemiBuffer::from_slice(&data);
+                self.sgafree(sga)?;
+                let eof: bool = qr.qr_flags & DEMI_QR_EOF != 0;
+                OperationResult::Pop(remote, buf?, eof)
+            },
+            demi_opcode_t::DEMI_OPC_FAILED => OperationResult::Failed(Fail::new(qr.qr_ret as i32, "operation failed")),
+            demi_opcode_t::DEMI_OPC_INVALID => {
+                let cause: String = format!("invalid operation result (qd={:?})", qd);
+                error!("qresult_to_operation_result(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        };
+        Ok((qd, result))
+    }
 
-            // If we have a timeout, check for expiration.
-            if timeout.is_some()
-                && Instant::now().duration_since(start.expect("start should be set if timeout is"))
-                    > timeout.expect("timeout should still be set")
-            {
-                return Err(Fail::new(libc::ETIMEDOUT, "timer expired"));
+    /// Waits for a pending [LibOS::pop_multiple] to complete or a timeout to expire. Upon success, one scatter-gather
+    /// array is returned for each message that was popped, in order.
+    pub fn wait_pop_multiple(&mut self, qt: QToken, timeout: Option<Duration>) -> Result<Vec<demi_sgarray_t>, Fail> {
+        trace!("wait_pop_multiple(): qt={:?}, timeout={:?}", qt, timeout);
+
+        // Retrieve associated schedule handle.
+        let handle: TaskHandle = self.schedule(qt)?;
+        let deadline: Option<Instant> = Self::deadline(timeout);
+
+        loop {
+            // Poll first, so as to give pending operations a chance to complete.
+            self.poll();
+
+            // The operation has completed, so extract the result and return.
+            if handle.has_completed() {
+                return match self {
+                    LibOS::NetworkLibOS(libos) => libos.pack_batch_result(handle),
+                    LibOS::MemoryLibOS(_) => Err(Fail::new(
+                        libc::ENOTSUP,
+                        "wait_pop_multiple() is not supported on memory liboses",
+                    )),
+                };
             }
+
+            Self::check_deadline(deadline)?;
         }
     }
 
@@ -373,16 +1014,753 @@ impl LibOS {
     }
 
     fn pack_result(&mut self, handle: TaskHandle, qt: QToken) -> Result<demi_qresult_t, Fail> {
-        match self {
-            LibOS::NetworkLibOS(libos) => libos.pack_result(handle, qt),
-            LibOS::MemoryLibOS(libos) => libos.pack_result(handle, qt),
+        let timing: Option<TaskTiming> = handle.get_timing();
+        let mut qr: demi_qresult_t = match self {
+            LibOS::NetworkLibOS(libos) => libos.pack_result(handle, qt)?,
+            LibOS::MemoryLibOS(libos) => libos.pack_result(handle, qt)?,
+        };
+
+        // Stamp the result with the timestamps of the operation. These are read from the scheduler before the task is
+        // removed from it, so they include the completion time, however the operation completed.
+        if let Some(TaskTiming {
+            created,
+            completed: Some(completed),
+        }) = timing
+        {
+            qr.qr_start_ns = TaskTiming::to_nanos(created);
+            qr.qr_end_ns = TaskTiming::to_nanos(completed);
+        }
+
+        Ok(qr)
+    }
+
+    /// Computes the deadline of a wait that starts now. There is none if the wait has no timeout, or one too far out to
+    /// be represented.
+    fn deadline(timeout: Option<Duration>) -> Option<Instant> {
+        timeout.and_then(|timeout| Instant::now().checked_add(timeout))
+    }
+
+    /// Fails with `ETIMEDOUT` once the deadline of a wait is reached. This is checked after every poll, so a wait with a
+    /// zero timeout polls exactly once.
+    fn check_deadline(deadline: Option<Instant>) -> Result<(), Fail> {
+        match deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(Fail::new(libc::ETIMEDOUT, "timer expired")),
+            _ => Ok(()),
         }
     }
 
     fn poll(&mut self) {
+        #[cfg(test)]
+        POLL_COUNT.with(|count| count.set(count.get() + 1));
+
         match self {
             LibOS::NetworkLibOS(libos) => libos.poll(),
             LibOS::MemoryLibOS(libos) => libos.poll(),
         }
     }
 }
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+/// Drop trait implementation for LibOS.
+impl Drop for LibOS {
+    /// Closes the I/O queues that the application left open, warning about each of them, so that leaks do not go
+    /// unnoticed. Connected sockets are shut down as they would be by [LibOS::close].
+    fn drop(&mut self) {
+        for queue in self.queues() {
+            // Queues that are closing already were closed by the application.
+            if queue.state == Some(QueueState::Closing) {
+                continue;
+            }
+            warn!(
+                "drop(): queue was not closed, closing it now (qd={:?}, qtype={:?})",
+                queue.qd, queue.qtype
+            );
+            if queue.state == Some(QueueState::Listening) {
+                if let Err(e) = self.stop_listening(queue.qd) {
+                    debug!("drop(): failed to stop listening (qd={:?}, error={:?})", queue.qd, e);
+                }
+            }
+            if let Err(e) = self.close(queue.qd) {
+                warn!("drop(): failed to close queue (qd={:?}, error={:?})", queue.qd, e);
+            }
+        }
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod test {
+    use super::LibOS;
+    use ::anyhow::Result;
+
+    #[cfg(feature = "catmem-libos")]
+    use super::memory::MemoryLibOS;
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    use super::network::NetworkLibOS;
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    use super::POLL_COUNT;
+    #[cfg(feature = "catmem-libos")]
+    use crate::catmem::CatmemLibOS;
+    #[cfg(feature = "catmem-libos")]
+    use crate::runtime::memory::{
+        counting_allocator::num_deallocations,
+        SgaGuard,
+    };
+    #[cfg(any(feature = "catmem-libos", all(feature = "catnap-libos", target_os = "linux")))]
+    use crate::runtime::QDesc;
+    #[cfg(any(feature = "catmem-libos", all(feature = "catnap-libos", target_os = "linux")))]
+    use crate::runtime::{
+        types::{
+            demi_opcode_t,
+            demi_qresult_t,
+            demi_sgarray_t,
+        },
+        QToken,
+    };
+    #[cfg(any(feature = "catmem-libos", all(feature = "catnap-libos", target_os = "linux")))]
+    use crate::scheduler::TaskTiming;
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    use crate::{
+        catnap::CatnapLibOS,
+        demikernel::config::Config,
+        pal::linux::socketaddrv4_to_sockaddr,
+        runtime::{
+            logging,
+            types::{
+                demi_accept_result_t,
+                demi_qr_value_t,
+                DEMI_QR_EOF,
+            },
+            OperationResult,
+        },
+    };
+    #[cfg(any(feature = "catmem-libos", all(feature = "catnap-libos", target_os = "linux")))]
+    use ::std::thread;
+    #[cfg(any(feature = "catmem-libos", all(feature = "catnap-libos", target_os = "linux")))]
+    use ::std::time::Duration;
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    use ::std::{
+        io::{
+            self,
+            ErrorKind,
+            Read,
+        },
+        mem,
+        net::{
+            Ipv4Addr,
+            SocketAddrV4,
+            TcpListener,
+            TcpStream,
+            UdpSocket,
+        },
+        slice,
+        time::Instant,
+    };
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    use ::yaml_rust::YamlLoader;
+
+    /// Tests that the scatter-gather array handed back by wait_pop() is released once its guard is dropped.
+    #[cfg(feature = "catmem-libos")]
+    #[ignore]
+    #[test]
+    fn wait_pop_frees_sga_on_drop() -> Result<()> {
+        let mut libos: LibOS = LibOS::MemoryLibOS(MemoryLibOS::Catmem(CatmemLibOS::new()));
+        let name: &str = "shm-test-libos-wait-pop";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+
+        // Push some data.
+        let sga: demi_sgarray_t = match libos.sgaalloc(64) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("sgaalloc() failed: {:?}", e),
+        };
+        let data: &mut [u8] = unsafe {
+            std::slice::from_raw_parts_mut(
+                sga.sga_segs[0].sgaseg_buf as *mut u8,
+                sga.sga_segs[0].sgaseg_len as usize,
+            )
+        };
+        for (i, x) in data.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+        let result: Result<QToken, _> = libos.push(tx, &sga);
+        if let Err(e) = libos.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        let qt: QToken = match result {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        let qr: demi_qresult_t = match libos.wait(qt, None) {
+            Ok(qr) => qr,
+            Err(e) => anyhow::bail!("wait() failed: {:?}", e),
+        };
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+
+        // Pop it back and read it through the guard.
+        let qt: QToken = match libos.pop(rx, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        let guard: SgaGuard = match libos.wait_pop(qt, None) {
+            Ok(guard) => guard,
+            Err(e) => anyhow::bail!("wait_pop() failed: {:?}", e),
+        };
+        crate::ensure_eq!(guard.as_bytes().len(), 64);
+        for (i, x) in guard.as_bytes().iter().enumerate() {
+            crate::ensure_eq!(*x, i as u8);
+        }
+
+        // Dropping the guard should release the underlying buffer. This may take more than one deallocation, as the
+        // popped buffer may be an indirect one.
+        let before: usize = num_deallocations();
+        drop(guard);
+        crate::ensure_neq!(num_deallocations(), before);
+
+        Ok(())
+    }
+
+    /// Tests that the result of a pop that had to wait for data carries timestamps that show how long it waited.
+    #[cfg(feature = "catmem-libos")]
+    #[ignore]
+    #[test]
+    fn wait_with_timing_shows_delay() -> Result<()> {
+        const DELAY: Duration = Duration::from_millis(20);
+        let mut libos: LibOS = LibOS::MemoryLibOS(MemoryLibOS::Catmem(CatmemLibOS::new()));
+        let name: &str = "shm-test-libos-wait-with-timing";
+        let tx: QDesc = match libos.create_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("create_pipe() failed: {:?}", e),
+        };
+        let rx: QDesc = match libos.open_pipe(name) {
+            Ok(qd) => qd,
+            Err(e) => anyhow::bail!("open_pipe() failed: {:?}", e),
+        };
+
+        // Start a pop, and only push data for it after a while.
+        let qt_pop: QToken = match libos.pop(rx, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        thread::sleep(DELAY);
+        let sga: demi_sgarray_t = match libos.sgaalloc(64) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("sgaalloc() failed: {:?}", e),
+        };
+        let result: Result<QToken, _> = libos.push(tx, &sga);
+        if let Err(e) = libos.sgafree(sga) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+        let qt_push: QToken = match result {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        let (qr, timing): (demi_qresult_t, Option<TaskTiming>) = match libos.wait_with_timing(qt_push, None) {
+            Ok(result) => result,
+            Err(e) => anyhow::bail!("wait_with_timing() failed: {:?}", e),
+        };
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        crate::ensure_eq!(qr.qr_end_ns >= qr.qr_start_ns, true);
+        crate::ensure_eq!(timing.is_some(), true);
+
+        // The pop should have completed no earlier than the delay after it started.
+        let (qr, timing): (demi_qresult_t, Option<TaskTiming>) = match libos.wait_with_timing(qt_pop, None) {
+            Ok(result) => result,
+            Err(e) => anyhow::bail!("wait_with_timing() failed: {:?}", e),
+        };
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        crate::ensure_eq!(qr.qr_end_ns - qr.qr_start_ns >= DELAY.as_nanos() as u64, true);
+        match timing.and_then(|timing| timing.elapsed()) {
+            Some(elapsed) => crate::ensure_eq!(elapsed >= DELAY, true),
+            None => anyhow::bail!("pop should have timestamps"),
+        }
+        if let Err(e) = libos.sgafree(unsafe { qr.qr_value.sga }) {
+            anyhow::bail!("sgafree() failed: {:?}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Tests that waiting with a zero timeout polls the scheduler exactly once, whether the operation completes in that
+    /// poll or not.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn wait_with_zero_timeout_polls_once() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+        let addr: SocketAddrV4 = SocketAddrV4::new(
+            Ipv4Addr::LOCALHOST,
+            UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port(),
+        );
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        libos.bind(qd, addr)?;
+
+        // Nothing was sent yet, so the pop is not ready.
+        let qt: QToken = libos.pop(qd, None)?;
+        POLL_COUNT.with(|count| count.set(0));
+        match libos.wait(qt, Some(Duration::ZERO)) {
+            Err(e) => crate::ensure_eq!(e.errno, libc::ETIMEDOUT),
+            Ok(_) => anyhow::bail!("wait() should have timed out"),
+        }
+        crate::ensure_eq!(POLL_COUNT.with(|count| count.get()), 1);
+
+        // Operations that complete in that single poll are reported as usual. Datagrams sent over the loopback
+        // interface are received right away.
+        let data: [u8; 64] = [1; 64];
+        UdpSocket::bind("127.0.0.1:0")?.send_to(&data, addr)?;
+        POLL_COUNT.with(|count| count.set(0));
+        let qr: demi_qresult_t = libos.wait(qt, Some(Duration::ZERO))?;
+        crate::ensure_eq!(POLL_COUNT.with(|count| count.get()), 1);
+        match libos.qresult_to_operation_result(qr)? {
+            (_, OperationResult::Pop(_, buf, _)) => crate::ensure_eq!(&buf[..], &data[..]),
+            _ => anyhow::bail!("wait() should have returned a pop"),
+        }
+
+        libos.close(qd)?;
+
+        Ok(())
+    }
+
+    /// Tests that dropping a LibOS closes the queues that were left open, and warns about each of them.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn drop_closes_leaked_queues() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+
+        // Leak a listening socket and a datagram socket, on ports that are free.
+        let tcp_addr: SocketAddrV4 = SocketAddrV4::new(
+            Ipv4Addr::LOCALHOST,
+            TcpListener::bind("127.0.0.1:0")?.local_addr()?.port(),
+        );
+        let tcp_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(tcp_qd, tcp_addr)?;
+        libos.listen(tcp_qd, 8)?;
+        let udp_addr: SocketAddrV4 = SocketAddrV4::new(
+            Ipv4Addr::LOCALHOST,
+            UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port(),
+        );
+        let udp_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        libos.bind(udp_qd, udp_addr)?;
+
+        // Properly close another one, which should not be reported.
+        let closed_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        libos.close(closed_qd)?;
+
+        // Drop the LibOS.
+        let warnings: Vec<String> = logging::capture_warnings(|| drop(libos));
+        let leaks: Vec<&String> = warnings.iter().filter(|w| w.contains("was not closed")).collect();
+        crate::ensure_eq!(leaks.len(), 2);
+        crate::ensure_eq!(leaks.iter().any(|w| w.contains(&format!("{:?}", tcp_qd))), true);
+        crate::ensure_eq!(leaks.iter().any(|w| w.contains(&format!("{:?}", udp_qd))), true);
+
+        // The addresses of the leaked sockets should be free again.
+        crate::ensure_eq!(TcpListener::bind(tcp_addr).is_ok(), true);
+        crate::ensure_eq!(UdpSocket::bind(udp_addr).is_ok(), true);
+
+        Ok(())
+    }
+
+    /// Returns how many times the calling thread was preempted so far.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    fn num_preemptions() -> Result<i64> {
+        let mut usage: libc::rusage = unsafe { mem::zeroed() };
+        if unsafe { libc::getrusage(libc::RUSAGE_THREAD, &mut usage) } != 0 {
+            anyhow::bail!("getrusage() failed: {:?}", io::Error::last_os_error());
+        }
+        Ok(usage.ru_nivcsw)
+    }
+
+    /// Waits on `qt` for `timeout` until `NUM_WAITS` waits ran without being preempted, and returns how long those took,
+    /// sorted. Waits that were preempted are left out, because preemption delays them by whole time slices.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    fn time_unpreempted_waits(libos: &mut LibOS, qt: QToken, timeout: Duration) -> Result<Vec<Duration>> {
+        const NUM_WAITS: usize = 11;
+        const MAX_ATTEMPTS: usize = 1000;
+        let mut elapsed: Vec<Duration> = Vec::with_capacity(NUM_WAITS);
+        for _ in 0..MAX_ATTEMPTS {
+            let preemptions: i64 = num_preemptions()?;
+            let start: Instant = Instant::now();
+            match libos.wait(qt, Some(timeout)) {
+                Err(e) => crate::ensure_eq!(e.errno, libc::ETIMEDOUT),
+                Ok(_) => anyhow::bail!("wait() should have timed out"),
+            }
+            let duration: Duration = start.elapsed();
+            if num_preemptions()? == preemptions {
+                elapsed.push(duration);
+                if elapsed.len() == NUM_WAITS {
+                    elapsed.sort();
+                    return Ok(elapsed);
+                }
+            }
+        }
+        anyhow::bail!("waits kept being preempted (timeout={:?})", timeout)
+    }
+
+    /// Tests that waits on an idle LibOS time out no earlier than asked for, and overrun their timeout by no more than
+    /// one poll, down to sub-millisecond timeouts. The duration of a poll is measured with waits that have a zero
+    /// timeout, which poll exactly once.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn wait_timeout_accuracy() -> Result<()> {
+        // Slack for reading the clock and returning from a wait.
+        const SLACK: Duration = Duration::from_micros(10);
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+
+        // Nothing is ever sent to this socket, so its pop never completes.
+        let addr: SocketAddrV4 = SocketAddrV4::new(
+            Ipv4Addr::LOCALHOST,
+            UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port(),
+        );
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        libos.bind(qd, addr)?;
+        let qt: QToken = libos.pop(qd, None)?;
+
+        let polls: Vec<Duration> = time_unpreempted_waits(&mut libos, qt, Duration::ZERO)?;
+        let tolerance: Duration = polls[polls.len() / 2] + SLACK;
+        for timeout in [
+            Duration::from_micros(50),
+            Duration::from_micros(500),
+            Duration::from_millis(2),
+        ] {
+            let elapsed: Vec<Duration> = time_unpreempted_waits(&mut libos, qt, timeout)?;
+            crate::ensure_eq!(elapsed[0] >= timeout, true);
+            crate::ensure_eq!(elapsed[elapsed.len() / 2] < timeout + tolerance, true);
+        }
+
+        libos.close(qd)?;
+
+        Ok(())
+    }
+
+    /// Tests that a pop that completes while [LibOS::wait_with_timing] polls for it carries timestamps that show how
+    /// long it waited.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn wait_with_timing_completes_during_wait() -> Result<()> {
+        const DELAY: Duration = Duration::from_millis(20);
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+        let addr: SocketAddrV4 = SocketAddrV4::new(
+            Ipv4Addr::LOCALHOST,
+            UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port(),
+        );
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        libos.bind(qd, addr)?;
+
+        // Start a pop, and have another thread send data for it only once the wait is underway.
+        let qt: QToken = libos.pop(qd, None)?;
+        let sender: thread::JoinHandle<io::Result<usize>> = thread::spawn(move || {
+            thread::sleep(DELAY);
+            UdpSocket::bind("127.0.0.1:0")?.send_to(b"ping", addr)
+        });
+        let (qr, timing): (demi_qresult_t, Option<TaskTiming>) =
+            libos.wait_with_timing(qt, Some(Duration::from_secs(5)))?;
+        match sender.join() {
+            Ok(result) => crate::ensure_eq!(result?, 4),
+            Err(_) => anyhow::bail!("sender thread panicked"),
+        }
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        crate::ensure_neq!(qr.qr_start_ns, 0);
+        crate::ensure_eq!(qr.qr_end_ns - qr.qr_start_ns >= DELAY.as_nanos() as u64, true);
+        match timing.and_then(|timing| timing.elapsed()) {
+            Some(elapsed) => crate::ensure_eq!(elapsed >= DELAY, true),
+            None => anyhow::bail!("pop should have timestamps"),
+        }
+        libos.sgafree(unsafe { qr.qr_value.sga })?;
+        libos.close(qd)?;
+
+        Ok(())
+    }
+
+    /// Builds the result of a completed operation, as a LibOS hands it out.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    fn cook_qresult(opcode: demi_opcode_t, qd: QDesc, ret: i64, value: demi_qr_value_t, flags: u64) -> demi_qresult_t {
+        demi_qresult_t {
+            qr_opcode: opcode,
+            qr_qd: qd.into(),
+            qr_qt: 0,
+            qr_ret: ret,
+            qr_value: value,
+            qr_flags: flags,
+            qr_start_ns: 0,
+            qr_end_ns: 0,
+            qr_rx_ns: 0,
+        }
+    }
+
+    /// Tests that the results of pushes and failed operations are converted along with their queue descriptors and
+    /// error codes.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn qresult_to_operation_result_push_and_failed() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+        let qd: QDesc = QDesc::from(600);
+
+        let qr: demi_qresult_t = cook_qresult(demi_opcode_t::DEMI_OPC_PUSH, qd, 0, unsafe { mem::zeroed() }, 0);
+        match libos.qresult_to_operation_result(qr)? {
+            (result_qd, OperationResult::Push) => crate::ensure_eq!(result_qd, qd),
+            (_, result) => anyhow::bail!("push should be converted (result={:?})", result),
+        }
+
+        let qr: demi_qresult_t = cook_qresult(
+            demi_opcode_t::DEMI_OPC_FAILED,
+            qd,
+            libc::ECONNRESET as i64,
+            unsafe { mem::zeroed() },
+            0,
+        );
+        match libos.qresult_to_operation_result(qr)? {
+            (result_qd, OperationResult::Failed(e)) => {
+                crate::ensure_eq!(result_qd, qd);
+                crate::ensure_eq!(e.errno, libc::ECONNRESET);
+            },
+            (_, result) => anyhow::bail!("failure should be converted (result={:?})", result),
+        }
+
+        let qr: demi_qresult_t = cook_qresult(demi_opcode_t::DEMI_OPC_INVALID, qd, 0, unsafe { mem::zeroed() }, 0);
+        match libos.qresult_to_operation_result(qr) {
+            Err(e) => crate::ensure_eq!(e.errno, libc::EINVAL),
+            Ok((_, result)) => anyhow::bail!("invalid result should be rejected (result={:?})", result),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that the results of pops are converted along with their data, the address of the remote peer if there is
+    /// one, and whether they reached the end of the stream.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn qresult_to_operation_result_pop() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+        let qd: QDesc = QDesc::from(600);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 8080);
+
+        // A datagram, which carries the address of its sender.
+        let mut sga: demi_sgarray_t = cook_sga(&mut libos, b"datagram")?;
+        sga.sga_addr = socketaddrv4_to_sockaddr(&remote);
+        let qr: demi_qresult_t = cook_qresult(demi_opcode_t::DEMI_OPC_POP, qd, 0, demi_qr_value_t { sga }, 0);
+        match libos.qresult_to_operation_result(qr)? {
+            (result_qd, OperationResult::Pop(addr, buf, eof)) => {
+                crate::ensure_eq!(result_qd, qd);
+                crate::ensure_eq!(addr, Some(remote));
+                crate::ensure_eq!(&buf[..], b"datagram");
+                crate::ensure_eq!(eof, false);
+            },
+            (_, result) => anyhow::bail!("pop should be converted (result={:?})", result),
+        }
+
+        // The last bytes of a stream, which carry no address.
+        let mut sga: demi_sgarray_t = cook_sga(&mut libos, b"stream")?;
+        sga.sga_addr = unsafe { mem::zeroed() };
+        let qr: demi_qresult_t = cook_qresult(demi_opcode_t::DEMI_OPC_POP, qd, 0, demi_qr_value_t { sga }, DEMI_QR_EOF);
+        match libos.qresult_to_operation_result(qr)? {
+            (result_qd, OperationResult::Pop(addr, buf, eof)) => {
+                crate::ensure_eq!(result_qd, qd);
+                crate::ensure_eq!(addr, None);
+                crate::ensure_eq!(&buf[..], b"stream");
+                crate::ensure_eq!(eof, true);
+            },
+            (_, result) => anyhow::bail!("pop should be converted (result={:?})", result),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that the results of accepts are converted along with the new queue descriptor and the address of the
+    /// remote peer.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn qresult_to_operation_result_accept() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+        let qd: QDesc = QDesc::from(600);
+        let new_qd: QDesc = QDesc::from(601);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 49152);
+
+        let ares: demi_accept_result_t = demi_accept_result_t {
+            qd: u32::from(new_qd) as i32,
+            addr: socketaddrv4_to_sockaddr(&remote),
+        };
+        let qr: demi_qresult_t = cook_qresult(demi_opcode_t::DEMI_OPC_ACCEPT, qd, 0, demi_qr_value_t { ares }, 0);
+        match libos.qresult_to_operation_result(qr)? {
+            (result_qd, OperationResult::Accept((accepted_qd, addr))) => {
+                crate::ensure_eq!(result_qd, qd);
+                crate::ensure_eq!(accepted_qd, new_qd);
+                crate::ensure_eq!(addr, remote);
+            },
+            (_, result) => anyhow::bail!("accept should be converted (result={:?})", result),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that [LibOS::wait2_any] hands back the operation that completed as an [OperationResult], along with its
+    /// offset and queue descriptor.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn wait2_any_pop() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+        let idle_addr: SocketAddrV4 = SocketAddrV4::new(
+            Ipv4Addr::LOCALHOST,
+            UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port(),
+        );
+        let idle_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        libos.bind(idle_qd, idle_addr)?;
+        let addr: SocketAddrV4 = SocketAddrV4::new(
+            Ipv4Addr::LOCALHOST,
+            UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port(),
+        );
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        libos.bind(qd, addr)?;
+
+        // Only the second socket gets data.
+        let qts: [QToken; 2] = [libos.pop(idle_qd, None)?, libos.pop(qd, None)?];
+        let sender: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+        sender.send_to(b"ping", addr)?;
+        match libos.wait2_any(&qts, Some(Duration::from_secs(5)))? {
+            (1, result_qd, OperationResult::Pop(remote, buf, eof)) => {
+                crate::ensure_eq!(result_qd, qd);
+                crate::ensure_eq!(remote.map(|remote| remote.port()), Some(sender.local_addr()?.port()));
+                crate::ensure_eq!(&buf[..], b"ping");
+                crate::ensure_eq!(eof, false);
+            },
+            (i, _, result) => anyhow::bail!("pop should have completed (i={:?}, result={:?})", i, result),
+        }
+
+        libos.close(idle_qd)?;
+        libos.close(qd)?;
+
+        Ok(())
+    }
+
+    /// Data that the tests of [CorkGuard](super::cork::CorkGuard) push in pieces.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    const CORKED_PIECES: [&[u8]; 3] = [b"HEADERS ", b"DATA ", b"END_STREAM"];
+
+    /// Allocates a scatter-gather array that holds a copy of `data`.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    fn cook_sga(libos: &mut LibOS, data: &[u8]) -> Result<demi_sgarray_t> {
+        let sga: demi_sgarray_t = libos.sgaalloc(data.len())?;
+        let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
+        // Safety: The scatter-gather array was just allocated with room for all of the data.
+        unsafe { slice::from_raw_parts_mut(ptr, data.len()) }.copy_from_slice(data);
+        Ok(sga)
+    }
+
+    /// Waits for a push to complete.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    fn wait_push(libos: &mut LibOS, qt: QToken) -> Result<()> {
+        let qr: demi_qresult_t = libos.wait(qt, Some(Duration::from_secs(5)))?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        Ok(())
+    }
+
+    /// Tests that pushes to a UDP socket that are held back by a cork guard go out as a single datagram, and only once
+    /// the guard is flushed. Dropping the guard without flushing it discards them.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn cork_udp_sends_single_datagram() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+        let receiver: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+        receiver.set_nonblocking(true)?;
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, receiver.local_addr()?.port());
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        let sgas: Vec<demi_sgarray_t> = CORKED_PIECES
+            .iter()
+            .map(|piece| cook_sga(&mut libos, piece))
+            .collect::<Result<_>>()?;
+
+        // Pushes that are held back by a guard that is dropped without being flushed are discarded.
+        let mut buf: [u8; 64] = [0; 64];
+        let mut cork = libos.cork(qd)?;
+        cork.pushto(&sgas[0], remote)?;
+        drop(cork);
+        crate::ensure_eq!(
+            receiver.recv(&mut buf).map_err(|e| e.kind()).err(),
+            Some(ErrorKind::WouldBlock)
+        );
+
+        let mut cork = libos.cork(qd)?;
+        for sga in &sgas {
+            cork.pushto(sga, remote)?;
+        }
+        // Pushes to other remote addresses cannot join the datagram.
+        let other: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, remote.port().wrapping_add(1));
+        crate::ensure_eq!(cork.pushto(&sgas[0], other).is_err(), true);
+        // Nothing goes out while the guard holds the pushes back.
+        crate::ensure_eq!(
+            receiver.recv(&mut buf).map_err(|e| e.kind()).err(),
+            Some(ErrorKind::WouldBlock)
+        );
+        let qt: QToken = cork.flush()?;
+        wait_push(&mut libos, qt)?;
+
+        receiver.set_nonblocking(false)?;
+        receiver.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let nbytes: usize = receiver.recv(&mut buf)?;
+        crate::ensure_eq!(&buf[..nbytes], CORKED_PIECES.concat().as_slice());
+
+        for sga in sgas {
+            libos.sgafree(sga)?;
+        }
+        libos.close(qd)?;
+
+        Ok(())
+    }
+
+    /// Tests that pushes to a TCP socket that are held back by a cork guard arrive together, in a single receive call.
+    #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+    #[test]
+    fn cork_tcp_sends_single_push() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("demikernel: {}\n")?[0].clone());
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap(CatnapLibOS::new(&config)));
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, listener.local_addr()?.port());
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        let qt: QToken = libos.connect(qd, remote)?;
+        let (mut stream, _): (TcpStream, _) = listener.accept()?;
+        let qr: demi_qresult_t = libos.wait(qt, Some(Duration::from_secs(5)))?;
+        crate::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_CONNECT);
+        let sgas: Vec<demi_sgarray_t> = CORKED_PIECES
+            .iter()
+            .map(|piece| cook_sga(&mut libos, piece))
+            .collect::<Result<_>>()?;
+
+        let mut cork = libos.cork(qd)?;
+        for sga in &sgas {
+            cork.push(sga)?;
+        }
+        let qt: QToken = cork.flush()?;
+        wait_push(&mut libos, qt)?;
+
+        let mut buf: [u8; 64] = [0; 64];
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let nbytes: usize = stream.read(&mut buf)?;
+        crate::ensure_eq!(&buf[..nbytes], CORKED_PIECES.concat().as_slice());
+
+        for sga in sgas {
+            libos.sgafree(sga)?;
+        }
+        libos.close(qd)?;
+
+        Ok(())
+    }
+}
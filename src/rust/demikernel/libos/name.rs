@@ -19,6 +19,7 @@ pub enum LibOSName {
     CatnapW,
     Catcollar,
     Catnip,
+    CatnipTap,
     Catmem,
     Catloop,
 }
@@ -50,6 +51,7 @@ impl From<String> for LibOSName {
             "catnapw" => LibOSName::CatnapW,
             "catcollar" => LibOSName::Catcollar,
             "catnip" => LibOSName::Catnip,
+            "catnip-tap" => LibOSName::CatnipTap,
             "catmem" => LibOSName::Catmem,
             "catloop" => LibOSName::Catloop,
             _ => panic!("unkown libos"),
@@ -8,16 +8,35 @@
 use crate::{
     runtime::{
         fail::Fail,
+        network::types::{
+            ByteStats,
+            ConnEvent,
+            DpdkPortStats,
+            LinkStatus,
+            MacAddress,
+            NicStats,
+            PeerStats,
+            PushCompletion,
+            SocketOption,
+        },
         types::{
             demi_qresult_t,
             demi_sgarray_t,
         },
+        ConnectionInfo,
         QDesc,
         QToken,
+        QueueInfo,
     },
     scheduler::TaskHandle,
 };
-use ::std::net::SocketAddrV4;
+use ::std::{
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    time::Duration,
+};
 
 #[cfg(feature = "catcollar-libos")]
 use crate::catcollar::CatcollarLibOS;
@@ -81,6 +100,25 @@ impl NetworkLibOS {
         }
     }
 
+    /// Creates a pair of connected stream sockets. Only LibOSes that run over shared memory can connect them without
+    /// going through the network.
+    pub fn socketpair(&mut self) -> Result<(QDesc, QDesc), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.socketpair(),
+        }
+    }
+
     /// Binds a socket to a local address.
     pub fn bind(&mut self, sockqd: QDesc, local: SocketAddrV4) -> Result<(), Fail> {
         match self {
@@ -132,6 +170,60 @@ impl NetworkLibOS {
         }
     }
 
+    /// Accepts an incoming connection on a TCP socket, failing with `ETIMEDOUT` if none arrives within `timeout`.
+    pub fn accept_timeout(&mut self, sockqd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.accept_timeout(sockqd, timeout),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.accept_timeout(sockqd, timeout),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => libos.accept_timeout(sockqd, timeout),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.accept_timeout(sockqd, timeout),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.accept_timeout(sockqd, timeout),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.accept_timeout(sockqd, timeout),
+        }
+    }
+
+    /// Stops accepting incoming connections on a listening TCP socket.
+    pub fn stop_listening(&mut self, sockqd: QDesc) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.stop_listening(sockqd),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.stop_listening(sockqd),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => libos.stop_listening(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.stop_listening(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.stop_listening(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.stop_listening(sockqd),
+        }
+    }
+
+    /// Duplicates a socket queue descriptor.
+    pub fn dup(&mut self, sockqd: QDesc) -> Result<QDesc, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.dup(sockqd),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.dup(sockqd),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => libos.dup(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.dup(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.dup(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.dup(sockqd),
+        }
+    }
+
     /// Accepts an incoming connection on a TCP socket.
     pub fn accept(&mut self, sockqd: QDesc) -> Result<QToken, Fail> {
         match self {
@@ -168,6 +260,24 @@ impl NetworkLibOS {
         }
     }
 
+    /// Binds a socket to a local address and initiates a connection with a remote TCP peer.
+    pub fn connect_from(&mut self, sockqd: QDesc, local: SocketAddrV4, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.connect_from(sockqd, local, remote),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.connect_from(sockqd, local, remote),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.connect_from(sockqd, local, remote),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.connect_from(sockqd, local, remote),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.connect_from(sockqd, local, remote),
+        }
+    }
+
     /// Closes a socket.
     pub fn close(&mut self, sockqd: QDesc) -> Result<(), Fail> {
         match self {
@@ -221,6 +331,26 @@ impl NetworkLibOS {
         }
     }
 
+    /// Pushes a scatter-gather array to a TCP socket, completing once the peer acknowledges all of it. Fails with
+    /// `ETIMEDOUT` and aborts the connection if that takes longer than `timeout`.
+    #[allow(unused_variables)]
+    pub fn push_timeout(&mut self, sockqd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.push_timeout(sockqd, sga, timeout),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.push_timeout(sockqd, sga, timeout),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
     /// Pushes a scatter-gather array to a UDP socket.
     pub fn pushto(&mut self, sockqd: QDesc, sga: &demi_sgarray_t, to: SocketAddrV4) -> Result<QToken, Fail> {
         match self {
@@ -235,10 +365,439 @@ impl NetworkLibOS {
             #[cfg(feature = "catnip-libos")]
             NetworkLibOS::Catnip(libos) => libos.pushto(sockqd, sga, to),
             #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.pushto(sockqd, sga, to),
+        }
+    }
+
+    /// Pushes a batch of scatter-gather arrays to a UDP socket, each one to its own remote address. LibOSes without
+    /// a batched send path push datagrams one by one.
+    pub fn pushto_batch(
+        &mut self,
+        sockqd: QDesc,
+        datagrams: &[(demi_sgarray_t, SocketAddrV4)],
+    ) -> Result<Vec<QToken>, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.pushto_batch(sockqd, datagrams),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.pushto_batch(sockqd, datagrams),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => datagrams
+                .iter()
+                .map(|(sga, to)| libos.pushto(sockqd, sga, *to))
+                .collect(),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => datagrams
+                .iter()
+                .map(|(sga, to)| libos.pushto(sockqd, sga, *to))
+                .collect(),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.pushto_batch(sockqd, datagrams),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Returns the number of bytes that may be popped right away from a socket.
+    pub fn bytes_available(&self, sockqd: QDesc) -> Result<usize, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.bytes_available(sockqd),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.bytes_available(sockqd),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => libos.bytes_available(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.bytes_available(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.bytes_available(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.bytes_available(sockqd),
+        }
+    }
+
+    /// Returns the maximum number of bytes that a single push transfers as a unit on a socket.
+    pub fn max_push_size(&self, sockqd: QDesc) -> Result<usize, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.max_push_size(sockqd),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.max_push_size(sockqd),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => libos.max_push_size(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.max_push_size(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.max_push_size(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.max_push_size(sockqd),
+        }
+    }
+
+    /// Returns the statistics of the frames transmitted and received on the NIC. Only LibOSes that drive the NIC
+    /// directly keep track of these.
+    pub fn nic_stats(&self) -> Result<NicStats, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => Ok(libos.nic_stats()),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Returns the statistics that the NIC keeps in hardware, including frames that it dropped before they reached the
+    /// LibOS. Only LibOSes that drive the NIC through DPDK can query them.
+    pub fn network_stats(&self) -> Result<DpdkPortStats, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.port_stats(),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Returns the status of the link of the NIC. Only LibOSes that drive the NIC directly can query it.
+    pub fn link_status(&self) -> Result<LinkStatus, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => Ok(libos.link_status()),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Returns the number of bytes that the connection of a socket sent, received and retransmitted so far. Only LibOSes
+    /// that run their own network stack keep track of these.
+    #[allow(unused_variables)]
+    pub fn byte_stats(&self, sockqd: QDesc) -> Result<ByteStats, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.byte_stats(sockqd),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.byte_stats(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Returns the telemetry on the remote hosts that the LibOS connected to, aggregated across all TCP connections to
+    /// each of them. Only LibOSes that run their own network stack keep it.
+    pub fn peer_telemetry(&self) -> Result<Vec<(Ipv4Addr, PeerStats)>, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => Ok(libos.peer_telemetry()),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => Ok(libos.peer_telemetry()),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Takes the oldest TCP connection event, waiting up to `timeout` for one to come up. Only LibOSes that run their own
+    /// network stack report connection events.
+    #[allow(unused_variables)]
+    pub fn next_event(&mut self, timeout: Option<Duration>) -> Result<Option<ConnEvent>, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => Ok(libos.next_event(timeout)),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => Ok(libos.next_event(timeout)),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Changes the local IPv4 and link addresses. Only LibOSes that run their own network stack can change them.
+    #[allow(unused_variables)]
+    pub fn set_local_identity(&mut self, ipv4_addr: Ipv4Addr, link_addr: MacAddress) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.set_local_identity(ipv4_addr, link_addr),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.set_local_identity(ipv4_addr, link_addr),
+            #[cfg(feature = "catloop-libos")]
             NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
         }
     }
 
+    /// Broadcasts an ARP request. Only LibOSes that run their own network stack speak ARP.
+    #[allow(unused_variables)]
+    pub fn send_arp_request(&mut self, target: Ipv4Addr) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.send_arp_request(target),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.send_arp_request(target),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Broadcasts an ARP request and waits for the reply. Only LibOSes that run their own network stack speak ARP.
+    #[allow(unused_variables)]
+    pub fn wait_arp(&mut self, target: Ipv4Addr, timeout: Duration) -> Result<MacAddress, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.wait_arp(target, timeout),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.wait_arp(target, timeout),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Makes a UDP socket join a multicast group, receiving datagrams from any source.
+    #[allow(unused_variables)]
+    pub fn join_multicast(&mut self, sockqd: QDesc, group: Ipv4Addr) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.join_multicast(sockqd, group),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.join_multicast(sockqd, group),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.join_multicast(sockqd, group),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Makes a UDP socket receive the datagrams that a source sends to a multicast group.
+    #[allow(unused_variables)]
+    pub fn join_source_specific_multicast(
+        &mut self,
+        sockqd: QDesc,
+        group: Ipv4Addr,
+        src: Ipv4Addr,
+    ) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.join_source_specific_multicast(sockqd, group, src),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.join_source_specific_multicast(sockqd, group, src),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.join_source_specific_multicast(sockqd, group, src),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Stops a UDP socket from receiving the datagrams that a source sends to a multicast group.
+    #[allow(unused_variables)]
+    pub fn block_source(&mut self, sockqd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.block_source(sockqd, group, src),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.block_source(sockqd, group, src),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.block_source(sockqd, group, src),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Sets when pushes on a socket complete.
+    #[allow(unused_variables)]
+    pub fn set_push_completion(&mut self, sockqd: QDesc, push_completion: PushCompletion) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.set_push_completion(sockqd, push_completion),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.set_push_completion(sockqd, push_completion),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Sets an option on a socket.
+    #[allow(unused_variables)]
+    pub fn setsockopt(&mut self, sockqd: QDesc, option: SocketOption) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.setsockopt(sockqd, option),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Returns the local address that a socket is bound to.
+    pub fn getsockname(&self, sockqd: QDesc) -> Result<SocketAddrV4, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.getsockname(sockqd),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.getsockname(sockqd),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => libos.getsockname(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.getsockname(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.getsockname(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.getsockname(sockqd),
+        }
+    }
+
+    /// Takes a snapshot of every open socket.
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.queues(),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.queues(),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Vec::new(),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.queues(),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.queues(),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.queues(),
+        }
+    }
+
+    /// Takes a snapshot of every connection that was accepted and is still open. Only LibOSes that keep track of their
+    /// connections can list them.
+    pub fn list_connections(&self) -> Result<Vec<ConnectionInfo>, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => Ok(libos.list_connections()),
+        }
+    }
+
+    /// Takes a snapshot of a socket.
+    #[allow(unused_variables)]
+    pub fn query_queue(&self, sockqd: QDesc) -> Result<QueueInfo, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.query_queue(sockqd),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.query_queue(sockqd),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.query_queue(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.query_queue(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.query_queue(sockqd),
+        }
+    }
+
+    /// Forces transmission of all data that is buffered on a socket.
+    pub fn flush(&self, sockqd: QDesc) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.flush(sockqd),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.flush(sockqd),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => libos.flush(sockqd),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.flush(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.flush(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.flush(sockqd),
+        }
+    }
+
     /// Pops data from a socket.
     pub fn pop(&mut self, sockqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         match self {
@@ -257,6 +816,61 @@ impl NetworkLibOS {
         }
     }
 
+    /// Pops data from a socket without removing it.
+    #[allow(unused_variables)]
+    pub fn pop_peek(&mut self, sockqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.pop_peek(sockqd, size),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.pop_peek(sockqd, size),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Pops up to `max_msgs` datagrams or stream chunks from a socket in a single operation.
+    pub fn pop_multiple(&mut self, sockqd: QDesc, max_msgs: usize) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.pop_multiple(sockqd, max_msgs),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.pop_multiple(sockqd, max_msgs),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.pop_multiple(sockqd, max_msgs),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
+    /// Pops data from a socket, failing with `ETIMEDOUT` if no data arrives within `timeout`.
+    pub fn pop_timeout(&mut self, sockqd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.pop_timeout(sockqd, size, timeout),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.pop_timeout(sockqd, size, timeout),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(libos) => libos.pop_timeout(sockqd, size, timeout),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(libos) => libos.pop_timeout(sockqd, size, timeout),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.pop_timeout(sockqd, size, timeout),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(libos) => libos.pop_timeout(sockqd, size, timeout),
+        }
+    }
+
     /// Waits for any operation in an I/O queue.
     pub fn poll(&mut self) {
         match self {
@@ -310,6 +924,24 @@ impl NetworkLibOS {
         }
     }
 
+    /// Packs the result of a completed [NetworkLibOS::pop_multiple] into one scatter-gather array per message.
+    pub fn pack_batch_result(&mut self, handle: TaskHandle) -> Result<Vec<demi_sgarray_t>, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder(libos) => libos.pack_batch_result(handle),
+            #[cfg(all(feature = "catnap-libos", target_os = "linux"))]
+            NetworkLibOS::Catnap(libos) => libos.pack_batch_result(handle),
+            #[cfg(all(feature = "catnapw-libos", target_os = "windows"))]
+            NetworkLibOS::CatnapW(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip(libos) => libos.pack_batch_result(handle),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop(_) => Err(Fail::new(libc::ENOTSUP, "operation not supported")),
+        }
+    }
+
     /// Allocates a scatter-gather array.
     pub fn sgaalloc(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
         match self {
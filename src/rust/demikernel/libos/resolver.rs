@@ -0,0 +1,204 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    QToken,
+};
+use ::std::net::{
+    Ipv4Addr,
+    SocketAddrV4,
+};
+
+//======================================================================================================================
+// Traits
+//======================================================================================================================
+
+/// Translates host names into addresses, so that sockets can connect by name.
+pub trait Resolver {
+    /// Returns the candidate addresses of `host` on `port`, in the order in which they should be tried.
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddrV4>, Fail>;
+}
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Resolver that only understands dotted-quad addresses. This is the default one, as it keeps us from pulling in a
+/// DNS client.
+#[derive(Default)]
+pub struct NumericResolver;
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Resolver for NumericResolver {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddrV4>, Fail> {
+        match host.parse::<Ipv4Addr>() {
+            Ok(addr) => Ok(vec![SocketAddrV4::new(addr, port)]),
+            Err(_) => {
+                let cause: String = format!("cannot resolve host name without a resolver (host={:?})", host);
+                error!("resolve(): {}", cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Resolves `host`, which has the form `name:port`, with `resolver` and issues `connect` on each candidate address in
+/// order, until one of them is accepted. The error of the last candidate is returned if none is.
+pub fn connect_host<F>(host: &str, resolver: &dyn Resolver, mut connect: F) -> Result<QToken, Fail>
+where
+    F: FnMut(SocketAddrV4) -> Result<QToken, Fail>,
+{
+    trace!("connect_host() host={:?}", host);
+
+    // Split host name and port number.
+    let (name, port): (&str, u16) = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() => match port.parse::<u16>() {
+            Ok(port) => (name, port),
+            Err(_) => {
+                let cause: String = format!("invalid port number (host={:?})", host);
+                error!("connect_host(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        },
+        _ => {
+            let cause: String = format!("host should have the form name:port (host={:?})", host);
+            error!("connect_host(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        },
+    };
+
+    let mut result: Result<QToken, Fail> = Err(Fail::new(libc::EHOSTUNREACH, "host has no addresses"));
+    for remote in resolver.resolve(name, port)? {
+        result = connect(remote);
+        match &result {
+            Ok(_) => break,
+            Err(e) => warn!("connect_host(): cannot connect to {:?} ({:?})", remote, e),
+        }
+    }
+    result
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        connect_host,
+        NumericResolver,
+        Resolver,
+    };
+    use crate::runtime::{
+        fail::Fail,
+        QToken,
+    };
+    use ::anyhow::Result;
+    use ::std::net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    };
+
+    /// Resolver that maps a single name to a fixed list of addresses.
+    struct StubResolver {
+        name: &'static str,
+        addrs: Vec<Ipv4Addr>,
+    }
+
+    impl Resolver for StubResolver {
+        fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddrV4>, Fail> {
+            if host != self.name {
+                return Err(Fail::new(libc::EHOSTUNREACH, "unknown host"));
+            }
+            Ok(self.addrs.iter().map(|addr| SocketAddrV4::new(*addr, port)).collect())
+        }
+    }
+
+    /// Tests if connect is issued on the address that the resolver maps a name to.
+    #[test]
+    fn connect_host_uses_resolver() -> Result<()> {
+        let resolver: StubResolver = StubResolver {
+            name: "bob.example",
+            addrs: vec![Ipv4Addr::new(192, 168, 1, 2)],
+        };
+        let mut remotes: Vec<SocketAddrV4> = Vec::new();
+        let result: Result<QToken, Fail> = connect_host("bob.example:8080", &resolver, |remote| {
+            remotes.push(remote);
+            Ok(QToken::from(1))
+        });
+        crate::ensure_eq!(result.is_ok(), true);
+        crate::ensure_eq!(remotes, vec![SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 8080)]);
+
+        // Unknown names and malformed hosts should fail without issuing connect.
+        for host in ["alice.example:8080", "bob.example", "bob.example:port", ":8080"] {
+            let mut issued: usize = 0;
+            let result: Result<QToken, Fail> = connect_host(host, &resolver, |_| {
+                issued += 1;
+                Ok(QToken::from(1))
+            });
+            crate::ensure_eq!(result.is_err(), true);
+            crate::ensure_eq!(issued, 0);
+        }
+
+        Ok(())
+    }
+
+    /// Tests if candidates are tried in order until connect accepts one.
+    #[test]
+    fn connect_host_tries_candidates_in_order() -> Result<()> {
+        let resolver: StubResolver = StubResolver {
+            name: "bob.example",
+            addrs: vec![
+                Ipv4Addr::new(10, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 2),
+                Ipv4Addr::new(10, 0, 0, 3),
+            ],
+        };
+        let mut remotes: Vec<Ipv4Addr> = Vec::new();
+        let result: Result<QToken, Fail> = connect_host("bob.example:80", &resolver, |remote| {
+            remotes.push(*remote.ip());
+            if *remote.ip() == Ipv4Addr::new(10, 0, 0, 2) {
+                Ok(QToken::from(1))
+            } else {
+                Err(Fail::new(libc::ENETUNREACH, "network is unreachable"))
+            }
+        });
+        crate::ensure_eq!(result.is_ok(), true);
+        crate::ensure_eq!(remotes, vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)]);
+
+        // The error of the last candidate should be returned if all of them fail.
+        let result: Result<QToken, Fail> = connect_host("bob.example:80", &resolver, |_| {
+            Err(Fail::new(libc::ENETUNREACH, "network is unreachable"))
+        });
+        crate::ensure_eq!(result.err().map(|e| e.errno), Some(libc::ENETUNREACH));
+
+        Ok(())
+    }
+
+    /// Tests if the default resolver only accepts dotted-quad addresses.
+    #[test]
+    fn numeric_resolver() -> Result<()> {
+        let resolver: NumericResolver = NumericResolver;
+        let mut remotes: Vec<SocketAddrV4> = Vec::new();
+        let result: Result<QToken, Fail> = connect_host("127.0.0.1:1234", &resolver, |remote| {
+            remotes.push(remote);
+            Ok(QToken::from(1))
+        });
+        crate::ensure_eq!(result.is_ok(), true);
+        crate::ensure_eq!(remotes, vec![SocketAddrV4::new(Ipv4Addr::LOCALHOST, 1234)]);
+        crate::ensure_eq!(resolver.resolve("localhost", 1234).is_err(), true);
+        Ok(())
+    }
+}
@@ -5,6 +5,8 @@
 // Imports
 //==============================================================================
 
+#[cfg(feature = "dhcp")]
+use crate::inetstack::protocols::dhcp::DhcpLease;
 use crate::{
     inetstack::protocols::{
         arp::ArpPeer,
@@ -12,15 +14,29 @@ use crate::{
             EtherType2,
             Ethernet2Header,
         },
+        ipv4::{
+            PacketFilter,
+            RouteEntry,
+        },
         queue::InetQueue,
-        tcp::operations::{
-            AcceptFuture,
-            CloseFuture,
-            ConnectFuture,
-            PopFuture,
-            PushFuture,
+        tcp::{
+            gro::ReceiveCoalescer,
+            operations::{
+                AcceptFuture,
+                CloseFuture,
+                ConnectFuture,
+                PopFuture,
+                PopMultipleFuture,
+                PushFuture,
+            },
+            SegmentAuthenticator,
+            TcpConnectionStats,
+            TcpInfo,
+        },
+        udp::{
+            UdpPopFuture,
+            UdpPopMultipleFuture,
         },
-        udp::UdpPopFuture,
         Peer,
     },
     pal::constants::{
@@ -38,7 +54,16 @@ use crate::{
                 TcpConfig,
                 UdpConfig,
             },
-            types::MacAddress,
+            types::{
+                ByteStats,
+                ConnEvent,
+                HandshakeStats,
+                LinkStatus,
+                MacAddress,
+                NicStats,
+                PeerStats,
+                PushCompletion,
+            },
             NetworkRuntime,
         },
         queue::{
@@ -50,6 +75,7 @@ use crate::{
             QDesc,
             QToken,
             QType,
+            QueueInfo,
         },
         timer::TimerRc,
     },
@@ -58,16 +84,29 @@ use crate::{
         TaskHandle,
     },
 };
+use ::futures::{
+    future::Either,
+    task::noop_waker_ref,
+    FutureExt,
+};
 use ::libc::c_int;
 use ::std::{
     cell::RefCell,
+    future::Future,
     net::{
         Ipv4Addr,
         SocketAddrV4,
     },
     pin::Pin,
     rc::Rc,
-    time::Instant,
+    task::{
+        Context,
+        Poll,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 #[cfg(feature = "profiler")]
@@ -96,20 +135,21 @@ const MAX_RECV_ITERS: usize = 2;
 // Structures
 //======================================================================================================================
 
-pub struct InetStack<const N: usize> {
-    arp: ArpPeer<N>,
-    ipv4: Peer<N>,
-    qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>>,
-    rt: Rc<dyn NetworkRuntime<N>>,
+pub struct InetStack {
+    arp: ArpPeer,
+    ipv4: Peer,
+    qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
+    rt: Rc<dyn NetworkRuntime>,
     local_link_addr: MacAddress,
     scheduler: Scheduler,
     clock: TimerRc,
     ts_iters: usize,
+    gro: ReceiveCoalescer,
 }
 
-impl<const N: usize> InetStack<N> {
+impl InetStack {
     pub fn new(
-        rt: Rc<dyn NetworkRuntime<N>>,
+        rt: Rc<dyn NetworkRuntime>,
         scheduler: Scheduler,
         clock: TimerRc,
         local_link_addr: MacAddress,
@@ -118,10 +158,14 @@ impl<const N: usize> InetStack<N> {
         tcp_config: TcpConfig,
         rng_seed: [u8; 32],
         arp_config: ArpConfig,
+        packet_filter: Option<PacketFilter>,
     ) -> Result<Self, Fail> {
-        let qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>> =
-            Rc::new(RefCell::new(IoQueueTable::<InetQueue<N>>::new()));
-        let arp: ArpPeer<N> = ArpPeer::new(
+        // Time operations against the timer of the runtime.
+        scheduler.set_clock(clock.clone());
+        let qtable: Rc<RefCell<IoQueueTable<InetQueue>>> = Rc::new(RefCell::new(IoQueueTable::<InetQueue>::new()));
+        let gro: ReceiveCoalescer =
+            ReceiveCoalescer::new(tcp_config.get_gro_max_size(), tcp_config.get_rx_checksum_offload());
+        let arp: ArpPeer = ArpPeer::new(
             rt.clone(),
             scheduler.clone(),
             clock.clone(),
@@ -129,7 +173,7 @@ impl<const N: usize> InetStack<N> {
             local_ipv4_addr,
             arp_config,
         )?;
-        let ipv4: Peer<N> = Peer::new(
+        let ipv4: Peer = Peer::new(
             rt.clone(),
             scheduler.clone(),
             qtable.clone(),
@@ -140,6 +184,7 @@ impl<const N: usize> InetStack<N> {
             tcp_config,
             arp.clone(),
             rng_seed,
+            packet_filter,
         )?;
         Ok(Self {
             arp,
@@ -150,6 +195,7 @@ impl<const N: usize> InetStack<N> {
             scheduler,
             clock,
             ts_iters: 0,
+            gro,
         })
     }
 
@@ -230,6 +276,52 @@ impl<const N: usize> InetStack<N> {
         }
     }
 
+    /// Allocates an unused port in the ephemeral port range for the local address `ip`, as [bind](Self::bind) does
+    /// for port zero. Ports are handed out circularly, starting from a random offset into the range, and are reserved
+    /// right away, so that rapid successive allocations never return the same port.
+    pub fn allocate_ephemeral_port(&mut self, ip: Ipv4Addr) -> Result<u16, Fail> {
+        trace!("allocate_ephemeral_port(): ip={:?}", ip);
+        self.ipv4.alloc_ephemeral_port(ip)
+    }
+
+    /// Restricts the ports that are allocated from the ephemeral port range to those from `first` to `last`, both
+    /// included.
+    pub fn set_ephemeral_port_range(&mut self, first: u16, last: u16) -> Result<(), Fail> {
+        trace!("set_ephemeral_port_range(): first={:?}, last={:?}", first, last);
+        self.ipv4.set_ephemeral_port_range(first, last)
+    }
+
+    /// Returns the local endpoint that the socket referred to by `qd` is bound to. After binding to port zero, this is
+    /// the ephemeral port that was selected.
+    pub fn getsockname(&self, qd: QDesc) -> Result<SocketAddrV4, Fail> {
+        trace!("getsockname(): qd={:?}", qd);
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.local_endpoint(qd),
+            Some(QType::UdpSocket) => self.ipv4.udp.local_endpoint(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Limits the number of queue descriptors that may be open at once.
+    pub fn set_max_open_queues(&mut self, max_open_queues: Option<usize>) {
+        self.qtable.borrow_mut().set_max_queues(max_open_queues);
+    }
+
+    /// Takes a snapshot of every open queue descriptor.
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        self.qtable.borrow().queues()
+    }
+
+    /// Takes a snapshot of the queue referred to by `qd`.
+    pub fn query_queue(&self, qd: QDesc) -> Result<QueueInfo, Fail> {
+        trace!("query_queue(): qd={:?}", qd);
+        match self.qtable.borrow().query(&qd) {
+            Some(info) => Ok(info),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
     ///
     /// **Brief**
     ///
@@ -263,6 +355,21 @@ impl<const N: usize> InetStack<N> {
         }
     }
 
+    /// Stops accepting connections on the listening socket referred to by `qd`. Connections that were not yet accepted
+    /// are reset and pending accepts complete with `ECONNABORTED`, whereas established connections are unaffected. The
+    /// socket remains open until it is closed.
+    pub fn stop_listening(&mut self, qd: QDesc) -> Result<(), Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::stop_listening");
+        trace!("stop_listening(): {:?}", qd);
+
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.stop_listening(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
     ///
     /// **Brief**
     ///
@@ -280,11 +387,30 @@ impl<const N: usize> InetStack<N> {
         timer!("inetstack::accept");
         trace!("accept(): {:?}", qd);
 
+        self.do_accept(qd, None)
+    }
+
+    /// Same as [Self::accept], but the operation fails with `ETIMEDOUT` if no connection request arrives within
+    /// `timeout`. The listening socket remains usable after a timeout.
+    pub fn accept_timeout(&mut self, qd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::accept_timeout");
+        trace!("accept_timeout(): {:?}, timeout={:?}", qd, timeout);
+
+        self.do_accept(qd, Some(timeout))
+    }
+
+    /// Schedules a coroutine that accepts an incoming connection, with an optional timeout.
+    fn do_accept(&mut self, qd: QDesc, timeout: Option<Duration>) -> Result<QToken, Fail> {
         // Search for target queue descriptor.
         match self.lookup_qtype(&qd) {
             Some(QType::TcpSocket) => {
-                let (new_qd, future): (QDesc, AcceptFuture<N>) = self.ipv4.tcp.do_accept(qd);
-                let qtable_ptr: Rc<RefCell<IoQueueTable<InetQueue<N>>>> = self.qtable.clone();
+                let (new_qd, future): (QDesc, AcceptFuture) = self.ipv4.tcp.do_accept(qd)?;
+                let future = match timeout {
+                    Some(timeout) => Either::Left(self.clock.timeout(future, timeout)),
+                    None => Either::Right(future),
+                };
+                let qtable_ptr: Rc<RefCell<IoQueueTable<InetQueue>>> = self.qtable.clone();
                 let coroutine: Pin<Box<Operation>> = Box::pin(async move {
                     // Wait for accept to complete.
                     let result: Result<(QDesc, SocketAddrV4), Fail> = future.await;
@@ -331,32 +457,85 @@ impl<const N: usize> InetStack<N> {
         timer!("inetstack::connect");
         trace!("connect(): qd={:?} remote={:?}", qd, remote);
 
-        let task: OperationTask = match self.lookup_qtype(&qd) {
-            Some(QType::TcpSocket) => {
-                let future: ConnectFuture<N> = self.ipv4.tcp.connect(qd, remote)?;
-                let coroutine: Pin<Box<Operation>> = Box::pin(async move {
-                    // Wait for connect to complete.
-                    let result: Result<(), Fail> = future.await;
-                    // Handle result.
-                    match result {
-                        Ok(()) => (qd, OperationResult::Connect),
-                        Err(e) => (qd, OperationResult::Failed(e)),
-                    }
-                });
-                let task_id: String = format!("Inetstack::TCP::connect for qd={:?}", qd);
-                OperationTask::new(task_id, coroutine)
-            },
+        let future: ConnectFuture = match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.connect(qd, remote)?,
+            Some(_) => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        };
+
+        let qt: QToken = self.do_connect(qd, future)?;
+        trace!("connect() qt={:?}", qt);
+        Ok(qt)
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Binds the socket referred to by `qd` to the local endpoint specified by
+    /// `local` and connects it to the remote endpoint specified by `remote`. If
+    /// the port of `local` is zero, an ephemeral port is selected. If the socket
+    /// is already bound, `local` must match the address that it is bound to.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, a queue token is returned. This token can be
+    /// used to push and pop data to/from the queue that connects the local and
+    /// remote endpoints. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn connect_from(&mut self, qd: QDesc, local: SocketAddrV4, remote: SocketAddrV4) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::connect_from");
+        trace!("connect_from(): qd={:?} local={:?} remote={:?}", qd, local, remote);
+
+        let future: ConnectFuture = match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.connect_from(qd, local, remote)?,
             Some(_) => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
             None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
         };
 
+        let qt: QToken = self.do_connect(qd, future)?;
+        trace!("connect_from() qt={:?}", qt);
+        Ok(qt)
+    }
+
+    /// Schedules the coroutine that waits for the connection on the socket referred to by `qd` to complete.
+    fn do_connect(&mut self, qd: QDesc, future: ConnectFuture) -> Result<QToken, Fail> {
+        let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+            // Wait for connect to complete.
+            let result: Result<(), Fail> = future.await;
+            // Handle result.
+            match result {
+                Ok(()) => (qd, OperationResult::Connect),
+                Err(e) => (qd, OperationResult::Failed(e)),
+            }
+        });
+        let task_id: String = format!("Inetstack::TCP::connect for qd={:?}", qd);
+        let task: OperationTask = OperationTask::new(task_id, coroutine);
+
         let handle: TaskHandle = match self.scheduler.insert(task) {
             Some(handle) => handle,
             None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
         };
-        let qt: QToken = handle.get_task_id().into();
-        trace!("connect() qt={:?}", qt);
-        Ok(qt)
+        Ok(handle.get_task_id().into())
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Duplicates the queue descriptor `qd`. The new queue descriptor refers to the same socket, and the socket is only
+    /// closed once all queue descriptors that refer to it are closed.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the new queue descriptor is returned. Upon failure, `Fail` is returned instead.
+    ///
+    pub fn dup(&mut self, qd: QDesc) -> Result<QDesc, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::dup");
+        trace!("dup(): qd={:?}", qd);
+
+        self.qtable.borrow_mut().dup(&qd)
     }
 
     ///
@@ -374,6 +553,14 @@ impl<const N: usize> InetStack<N> {
         timer!("inetstack::close");
         trace!("close(): qd={:?}", qd);
 
+        // Other queue descriptors still refer to this socket, so leave it open.
+        let duplicate: Option<bool> = self.qtable.borrow_mut().close_duplicate(&qd);
+        match duplicate {
+            Some(true) => return Ok(()),
+            Some(false) => (),
+            None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+
         match self.lookup_qtype(&qd) {
             Some(QType::TcpSocket) => self.ipv4.tcp.do_close(qd),
             Some(QType::UdpSocket) => self.ipv4.udp.do_close(qd),
@@ -397,10 +584,33 @@ impl<const N: usize> InetStack<N> {
         timer!("inetstack::async_close");
         trace!("async_close(): qd={:?}", qd);
 
-        let qtable_ptr: Rc<RefCell<IoQueueTable<InetQueue<N>>>> = self.qtable.clone();
+        let duplicate: Option<bool> = self.qtable.borrow_mut().close_duplicate(&qd);
+        let (task_id, coroutine): (String, Pin<Box<Operation>>) = match duplicate {
+            // Other queue descriptors still refer to this socket, so leave it open.
+            Some(true) => {
+                let task_id: String = format!("Inetstack::close for duplicate qd={:?}", qd);
+                let coroutine: Pin<Box<Operation>> = Box::pin(async move { (qd, OperationResult::Close) });
+                (task_id, coroutine)
+            },
+            Some(false) => self.do_async_close(qd)?,
+            None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        };
+
+        let handle: TaskHandle = match self.scheduler.insert(OperationTask::new(task_id, coroutine)) {
+            Some(handle) => handle,
+            None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+        };
+        let qt: QToken = handle.get_task_id().into();
+        trace!("async_close() qt={:?}", qt);
+        Ok(qt)
+    }
+
+    /// Builds the coroutine that closes the socket referred to by `qd`.
+    fn do_async_close(&mut self, qd: QDesc) -> Result<(String, Pin<Box<Operation>>), Fail> {
+        let qtable_ptr: Rc<RefCell<IoQueueTable<InetQueue>>> = self.qtable.clone();
         let (task_id, coroutine): (String, Pin<Box<Operation>>) = match self.lookup_qtype(&qd) {
             Some(QType::TcpSocket) => {
-                let future: CloseFuture<N> = self.ipv4.tcp.do_async_close(qd)?;
+                let future: CloseFuture = self.ipv4.tcp.do_async_close(qd)?;
                 let task_id: String = format!("Inetstack::TCP::close for qd={:?}", qd);
                 let coroutine: Pin<Box<Operation>> = Box::pin(async move {
                     let result: Result<(), Fail> = future.await;
@@ -426,14 +636,7 @@ impl<const N: usize> InetStack<N> {
             Some(_) => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
             None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
         };
-
-        let handle: TaskHandle = match self.scheduler.insert(OperationTask::new(task_id, coroutine)) {
-            Some(handle) => handle,
-            None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
-        };
-        let qt: QToken = handle.get_task_id().into();
-        trace!("async_close() qt={:?}", qt);
-        Ok(qt)
+        Ok((task_id, coroutine))
     }
 
     /// Pushes a buffer to a TCP socket.
@@ -459,6 +662,30 @@ impl<const N: usize> InetStack<N> {
         }
     }
 
+    /// Pushes a buffer to a TCP socket. Unlike [InetStack::do_push], the operation completes only once the peer
+    /// acknowledges all of `buf`. It fails with `ETIMEDOUT` if that does not happen within `timeout`, in which case the
+    /// connection is aborted.
+    pub fn do_push_timeout(&mut self, qd: QDesc, buf: DemiBuffer, timeout: Duration) -> Result<OperationTask, Fail> {
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => {
+                let future = self.ipv4.tcp.push_timeout(qd, buf, timeout)?;
+                let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+                    // Wait for the peer to acknowledge the data.
+                    let result: Result<(), Fail> = future.await;
+                    // Handle result.
+                    match result {
+                        Ok(()) => (qd, OperationResult::Push),
+                        Err(e) => (qd, OperationResult::Failed(e)),
+                    }
+                });
+                let task_id: String = format!("Inetstack::TCP::push_timeout for qd={:?}", qd);
+                Ok(OperationTask::new(task_id, coroutine))
+            },
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
     /// Pushes raw data to a TCP socket.
     /// TODO: Move this function to demikernel repo once we have a common buffer representation across all libOSes.
     pub fn push2(&mut self, qd: QDesc, data: &[u8]) -> Result<QToken, Fail> {
@@ -498,6 +725,36 @@ impl<const N: usize> InetStack<N> {
         }
     }
 
+    /// Pushes a batch of buffers to a UDP socket, each one to its own remote address. Datagrams that can be sent right
+    /// away are transmitted in a single pass. Upon success, one operation is returned for each datagram, in order.
+    pub fn do_pushto_batch(
+        &mut self,
+        qd: QDesc,
+        datagrams: Vec<(DemiBuffer, SocketAddrV4)>,
+    ) -> Result<Vec<OperationTask>, Fail> {
+        match self.lookup_qtype(&qd) {
+            Some(QType::UdpSocket) => {
+                let results: Vec<Result<(), Fail>> = self.ipv4.udp.do_pushto_batch(qd, datagrams)?;
+                let tasks: Vec<OperationTask> = results
+                    .into_iter()
+                    .map(|result| {
+                        let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+                            match result {
+                                Ok(()) => (qd, OperationResult::Push),
+                                Err(e) => (qd, OperationResult::Failed(e)),
+                            }
+                        });
+                        let task_id: String = format!("Inetstack::UDP::pushto for qd={:?}", qd);
+                        OperationTask::new(task_id, coroutine)
+                    })
+                    .collect();
+                Ok(tasks)
+            },
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
     /// Pushes raw data to a UDP socket.
     /// TODO: Move this function to demikernel repo once we have a common buffer representation across all libOSes.
     pub fn pushto2(&mut self, qd: QDesc, data: &[u8], remote: SocketAddrV4) -> Result<QToken, Fail> {
@@ -507,9 +764,6 @@ impl<const N: usize> InetStack<N> {
 
         // Convert raw data to a buffer representation.
         let buf: DemiBuffer = DemiBuffer::from_slice(data)?;
-        if buf.is_empty() {
-            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
-        }
         let task: OperationTask = self.do_pushto(qd, buf, remote)?;
         // Issue operation.
         let handle: TaskHandle = match self.scheduler.insert(task) {
@@ -521,6 +775,140 @@ impl<const N: usize> InetStack<N> {
         Ok(qt)
     }
 
+    /// Returns the maximum number of bytes that a single push transfers as a unit on the socket referred to by `qd`.
+    /// Pushes to a UDP socket that exceed this size fail with `EMSGSIZE`, whereas pushes to a TCP socket that exceed
+    /// it are split into multiple segments.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("max_push_size(): qd={:?}", qd);
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.max_push_size(qd),
+            Some(QType::UdpSocket) => self.ipv4.udp.max_push_size(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the number of bytes that may be popped right away from the socket referred to by `qd`. For a UDP socket,
+    /// this is the size of the next datagram. For a listening TCP socket, this is the number of connections that may be
+    /// accepted right away instead.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("bytes_available(): qd={:?}", qd);
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.bytes_available(qd),
+            Some(QType::UdpSocket) => self.ipv4.udp.bytes_available(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Sets when pushes on the socket referred to by `qd` complete. Datagrams are handed over to the network as soon as
+    /// they are pushed and never acknowledged, thus UDP sockets only support [PushCompletion::Enqueued].
+    pub fn set_push_completion(&mut self, qd: QDesc, push_completion: PushCompletion) -> Result<(), Fail> {
+        trace!(
+            "set_push_completion(): qd={:?}, push_completion={:?}",
+            qd,
+            push_completion
+        );
+        match (self.lookup_qtype(&qd), push_completion) {
+            (Some(QType::TcpSocket), _) => self.ipv4.tcp.set_push_completion(qd, push_completion),
+            (Some(QType::UdpSocket), PushCompletion::Enqueued) => Ok(()),
+            (Some(QType::UdpSocket), PushCompletion::Acked) => Err(Fail::new(
+                libc::ENOTSUP,
+                "UDP sockets do not support completion on acknowledgement",
+            )),
+            (Some(_), _) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            (None, _) => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Sets the authenticator that signs and validates the segments of the TCP socket referred to by `qd`, e.g. a
+    /// [crate::inetstack::protocols::tcp::TcpMd5Signature]. This must be done before the socket connects or listens.
+    pub fn set_tcp_authenticator(
+        &mut self,
+        qd: QDesc,
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+    ) -> Result<(), Fail> {
+        trace!("set_tcp_authenticator(): qd={:?}", qd);
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.set_authenticator(qd, authenticator),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the number of incoming TCP segments that were dropped because they failed authentication.
+    pub fn tcp_unauthenticated_segments(&self) -> u64 {
+        self.ipv4.tcp.unauthenticated_segments()
+    }
+
+    /// Returns the number of incoming TCP segments that were dropped because they carried an illegal combination of
+    /// flags.
+    pub fn tcp_malformed_segments(&self) -> u64 {
+        self.ipv4.tcp.malformed_segments()
+    }
+
+    /// Returns the telemetry on the remote hosts that we connected to, aggregated across all TCP connections to each of
+    /// them.
+    pub fn peer_telemetry(&self) -> Vec<(Ipv4Addr, PeerStats)> {
+        self.ipv4.tcp.peer_telemetry()
+    }
+
+    /// Returns the counters on the TCP handshakes that remote hosts initiated towards our listening sockets.
+    pub fn handshake_stats(&self) -> HandshakeStats {
+        self.ipv4.tcp.handshake_stats()
+    }
+
+    /// Resets the counters on TCP handshakes.
+    pub fn reset_handshake_stats(&mut self) {
+        self.ipv4.tcp.reset_handshake_stats()
+    }
+
+    /// Adds an equal-cost multi-path route to the network `net`/`prefix`, replacing the route to that network if there
+    /// is one already. Each gateway comes with a weight, and TCP connections that we open afterwards spread across the
+    /// gateways in proportion to these weights. All segments of a connection go through the same gateway.
+    pub fn add_ecmp_route(&mut self, net: Ipv4Addr, prefix: u8, gateways: Vec<(Ipv4Addr, u32)>) -> Result<(), Fail> {
+        trace!("add_ecmp_route(): net={:?}/{:?} gateways={:?}", net, prefix, gateways);
+        let gateways: Vec<RouteEntry> = gateways
+            .into_iter()
+            .map(|(gateway, weight)| RouteEntry { gateway, weight })
+            .collect();
+        self.ipv4.tcp.add_ecmp_route(net, prefix, gateways)
+    }
+
+    /// Takes the oldest TCP connection event that the application has yet to drain, waiting up to `timeout` for one to
+    /// come up. Without a timeout, this waits for as long as it takes. Events that are pending already are returned
+    /// right away, so a zero timeout takes one of them without making progress on the network.
+    pub fn next_event(&mut self, timeout: Option<Duration>) -> Option<ConnEvent> {
+        trace!("next_event() timeout={:?}", timeout);
+        let start: Instant = Instant::now();
+        loop {
+            if let Some(event) = self.ipv4.tcp.next_event() {
+                return Some(event);
+            }
+            if timeout.is_some_and(|timeout| Instant::now().duration_since(start) >= timeout) {
+                return None;
+            }
+            self.poll_bg_work();
+        }
+    }
+
+    /// Returns the number of TCP connections in TIME-WAIT that were reused for new outgoing connections.
+    pub fn tcp_recycled_connections(&self) -> u64 {
+        self.ipv4.tcp.recycled_connections()
+    }
+
+    /// Forces transmission of all data that is buffered on the socket referred to by `qd`. Datagrams are transmitted
+    /// as soon as they are pushed, thus this is a no-op on UDP sockets.
+    pub fn flush(&self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush(): qd={:?}", qd);
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.flush(qd),
+            Some(QType::UdpSocket) => Ok(()),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
     /// Create a pop request to write data from IO connection represented by `qd` into a buffer
     /// allocated by the application.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
@@ -529,31 +917,82 @@ impl<const N: usize> InetStack<N> {
 
         trace!("pop() qd={:?}, size={:?}", qd, size);
 
+        self.do_pop(qd, size, None, false)
+    }
+
+    /// Same as [Self::pop], but the data is left in the receive queue of the socket, so that the next pop returns the
+    /// same bytes. This is only supported on TCP sockets.
+    pub fn pop_peek(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::pop_peek");
+
+        trace!("pop_peek() qd={:?}, size={:?}", qd, size);
+
+        self.do_pop(qd, size, None, true)
+    }
+
+    /// Same as [Self::pop], but the operation fails with `ETIMEDOUT` if no data arrives within `timeout`. Data that
+    /// arrives right at expiry is still delivered. The socket remains usable after a timeout.
+    pub fn pop_timeout(&mut self, qd: QDesc, size: Option<usize>, timeout: Duration) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::pop_timeout");
+
+        trace!("pop_timeout() qd={:?}, size={:?}, timeout={:?}", qd, size, timeout);
+
+        self.do_pop(qd, size, Some(timeout), false)
+    }
+
+    /// Schedules a coroutine that pops data from the socket referred to by `qd`, with an optional timeout. If `peek` is
+    /// set, the data is left in the receive queue.
+    fn do_pop(
+        &mut self,
+        qd: QDesc,
+        size: Option<usize>,
+        timeout: Option<Duration>,
+        peek: bool,
+    ) -> Result<QToken, Fail> {
         // We just assert 'size' here, because it was previously checked at PDPIX layer.
         debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
 
         let (task_id, coroutine): (String, Pin<Box<Operation>>) = match self.lookup_qtype(&qd) {
             Some(QType::TcpSocket) => {
                 let task_id: String = format!("Inetstack::TCP::pop for qd={:?}", qd);
-                let future: PopFuture<N> = self.ipv4.tcp.pop(qd, size);
+                let future: PopFuture = if peek {
+                    self.ipv4.tcp.pop_peek(qd, size)
+                } else {
+                    self.ipv4.tcp.pop(qd, size)
+                };
+                let future = match timeout {
+                    Some(timeout) => Either::Left(self.clock.timeout(future, timeout)),
+                    None => Either::Right(future),
+                };
                 let coroutine: Pin<Box<Operation>> = Box::pin(async move {
                     // Wait for pop to complete.
                     let result: Result<DemiBuffer, Fail> = future.await;
                     // Handle result.
                     match result {
-                        Ok(buf) => (qd, OperationResult::Pop(None, buf)),
+                        Ok(buf) => {
+                            // Only the end of the stream yields an empty buffer.
+                            let eof: bool = buf.is_empty();
+                            (qd, OperationResult::Pop(None, buf, eof))
+                        },
                         Err(e) => (qd, OperationResult::Failed(e)),
                     }
                 });
                 (task_id, coroutine)
             },
+            Some(QType::UdpSocket) if peek => return Err(Fail::new(libc::ENOTSUP, "operation not supported")),
             Some(QType::UdpSocket) => {
                 let task_id: String = format!("Inetstack::UDP::pop for qd={:?}", qd);
                 let future: UdpPopFuture = self.ipv4.udp.do_pop(qd, size);
+                let future = match timeout {
+                    Some(timeout) => Either::Left(self.clock.timeout(future, timeout)),
+                    None => Either::Right(future),
+                };
                 let coroutine: Pin<Box<Operation>> = Box::pin(async move {
                     let result: Result<(SocketAddrV4, DemiBuffer), Fail> = future.await;
                     match result {
-                        Ok((addr, buf)) => (qd, OperationResult::Pop(Some(addr), buf)),
+                        Ok((addr, buf)) => (qd, OperationResult::Pop(Some(addr), buf, false)),
                         Err(e) => (qd, OperationResult::Failed(e)),
                     }
                 });
@@ -572,6 +1011,152 @@ impl<const N: usize> InetStack<N> {
         Ok(qt)
     }
 
+    /// Pops the oldest datagram that is pending on the UDP socket referred to by `qd`, or returns `None` if there is
+    /// none. Unlike [InetStack::pop], this does not block and does not go through the scheduler, so it suits
+    /// applications that poll for datagrams in a tight loop. Both drain the same receive queue.
+    pub fn pop_datagram(&mut self, qd: QDesc) -> Result<Option<(SocketAddrV4, DemiBuffer)>, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::pop_datagram");
+        trace!("pop_datagram() qd={:?}", qd);
+
+        match self.lookup_qtype(&qd) {
+            Some(QType::UdpSocket) => self.ipv4.udp.do_try_pop(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the number of incoming datagrams that the UDP socket referred to by `qd` dropped because its receive
+    /// queue was full.
+    pub fn udp_recv_dropped(&self, qd: QDesc) -> Result<u64, Fail> {
+        match self.lookup_qtype(&qd) {
+            Some(QType::UdpSocket) => self.ipv4.udp.get_recv_dropped(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Makes the UDP socket referred to by `qd` join the multicast group `group`, receiving datagrams from any source.
+    pub fn join_multicast(&mut self, qd: QDesc, group: Ipv4Addr) -> Result<(), Fail> {
+        trace!("join_multicast(): qd={:?} group={:?}", qd, group);
+        match self.lookup_qtype(&qd) {
+            Some(QType::UdpSocket) => self.ipv4.udp.join_multicast(qd, group),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Makes the UDP socket referred to by `qd` receive the datagrams that `src` sends to the multicast group `group`
+    /// (RFC 4607). Datagrams from sources that were not joined this way are filtered out.
+    pub fn join_source_specific_multicast(&mut self, qd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        trace!(
+            "join_source_specific_multicast(): qd={:?} group={:?} src={:?}",
+            qd,
+            group,
+            src
+        );
+        match self.lookup_qtype(&qd) {
+            Some(QType::UdpSocket) => self.ipv4.udp.join_source_specific_multicast(qd, group, src),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Stops the UDP socket referred to by `qd` from receiving the datagrams that `src` sends to the multicast group
+    /// `group`, which the socket should have joined with [InetStack::join_multicast].
+    pub fn block_source(&mut self, qd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        trace!("block_source(): qd={:?} group={:?} src={:?}", qd, group, src);
+        match self.lookup_qtype(&qd) {
+            Some(QType::UdpSocket) => self.ipv4.udp.block_source(qd, group, src),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns statistics about the TCP connection referred to by `qd`.
+    pub fn tcp_connection_stats(&self, qd: QDesc) -> Result<TcpConnectionStats, Fail> {
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.stats(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the internals of the TCP connection referred to by `qd`, such as its round-trip time estimates and its
+    /// congestion window, along the lines of `TCP_INFO`.
+    pub fn tcp_info(&self, qd: QDesc) -> Result<TcpInfo, Fail> {
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.info(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the number of bytes that the TCP connection referred to by `qd` sent, received and retransmitted so far.
+    pub fn byte_stats(&self, qd: QDesc) -> Result<ByteStats, Fail> {
+        match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => self.ipv4.tcp.byte_stats(qd),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Pops up to `max_msgs` datagrams, or chunks of data on a TCP socket, from the socket referred to by `qd` in a
+    /// single operation. The operation completes as soon as at least one of them is available, with an
+    /// [OperationResult::PopBatch] that holds all of them that are available at that time.
+    pub fn pop_multiple(&mut self, qd: QDesc, max_msgs: usize) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::pop_multiple");
+        trace!("pop_multiple() qd={:?}, max_msgs={:?}", qd, max_msgs);
+
+        if max_msgs == 0 {
+            return Err(Fail::new(libc::EINVAL, "cannot pop zero messages"));
+        }
+
+        let (task_id, coroutine): (String, Pin<Box<Operation>>) = match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => {
+                let task_id: String = format!("Inetstack::TCP::pop_multiple for qd={:?}", qd);
+                let future: PopMultipleFuture = self.ipv4.tcp.pop_multiple(qd, max_msgs);
+                let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+                    let result: Result<Vec<DemiBuffer>, Fail> = future.await;
+                    match result {
+                        Ok(bufs) => (
+                            qd,
+                            OperationResult::PopBatch(bufs.into_iter().map(|buf| (None, buf)).collect()),
+                        ),
+                        Err(e) => (qd, OperationResult::Failed(e)),
+                    }
+                });
+                (task_id, coroutine)
+            },
+            Some(QType::UdpSocket) => {
+                let task_id: String = format!("Inetstack::UDP::pop_multiple for qd={:?}", qd);
+                let future: UdpPopMultipleFuture = self.ipv4.udp.do_pop_multiple(qd, max_msgs);
+                let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+                    let result: Result<Vec<(SocketAddrV4, DemiBuffer)>, Fail> = future.await;
+                    match result {
+                        Ok(msgs) => (
+                            qd,
+                            OperationResult::PopBatch(msgs.into_iter().map(|(addr, buf)| (Some(addr), buf)).collect()),
+                        ),
+                        Err(e) => (qd, OperationResult::Failed(e)),
+                    }
+                });
+                (task_id, coroutine)
+            },
+            Some(_) => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        };
+
+        let handle: TaskHandle = match self.scheduler.insert(OperationTask::new(task_id, coroutine)) {
+            Some(handle) => handle,
+            None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+        };
+        let qt: QToken = handle.get_task_id().into();
+        trace!("pop_multiple() qt={:?}", qt);
+        Ok(qt)
+    }
+
     /// Waits for an operation to complete.
     #[deprecated]
     pub fn wait2(&mut self, qt: QToken) -> Result<(QDesc, OperationResult), Fail> {
@@ -661,6 +1246,124 @@ impl<const N: usize> InetStack<N> {
         }
     }
 
+    /// Returns the number of incoming packets dropped by the packet filter that was registered at construction.
+    pub fn filtered_packets(&self) -> u64 {
+        self.ipv4.filtered_packets()
+    }
+
+    /// Returns the statistics of the frames that the underlying network runtime has transmitted and received so far.
+    pub fn nic_stats(&self) -> NicStats {
+        self.rt.nic_stats()
+    }
+
+    /// Returns the status of the link of the underlying NIC. While the link is down, pushes fail with `ENETDOWN`.
+    pub fn link_status(&self) -> LinkStatus {
+        self.rt.link_status()
+    }
+
+    /// Acquires an IPv4 address through DHCP. The returned queue token completes with [OperationResult::Dhcp] once a
+    /// server grants a lease, which is then renewed in the background. The leased address is not applied to the stack,
+    /// callers should do so with [Self::set_local_identity], so that renewals reach us. As this operation is not tied
+    /// to any queue, it completes on queue descriptor zero.
+    #[cfg(feature = "dhcp")]
+    pub fn dhcp_request(&mut self) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::dhcp_request");
+        trace!("dhcp_request()");
+
+        let future = self.ipv4.dhcp_request();
+        let qd: QDesc = QDesc::from(0);
+        let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+            match future.await {
+                Ok(lease) => (qd, OperationResult::Dhcp(lease)),
+                Err(e) => (qd, OperationResult::Failed(e)),
+            }
+        });
+        let task: OperationTask = OperationTask::new(String::from("Inetstack::DHCP::request"), coroutine);
+        let handle: TaskHandle = match self.scheduler.insert(task) {
+            Some(handle) => handle,
+            None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+        };
+        Ok(handle.get_task_id().into())
+    }
+
+    /// Returns the current DHCP lease, if any.
+    #[cfg(feature = "dhcp")]
+    pub fn dhcp_lease(&self) -> Option<DhcpLease> {
+        self.ipv4.dhcp_lease()
+    }
+
+    /// Changes the local IPv4 and link addresses of this stack, as needed when the local IPv4 address moves to a
+    /// different NIC port. Neighbors learn about the change through a gratuitous ARP. New sockets and connections use
+    /// the new addresses, and sockets that are bound or listening on the old IPv4 address move over to the new one.
+    /// Established connections keep their original addresses.
+    pub fn set_local_identity(&mut self, local_ipv4_addr: Ipv4Addr, local_link_addr: MacAddress) -> Result<(), Fail> {
+        trace!(
+            "set_local_identity() ipv4={:?} link={:?}",
+            local_ipv4_addr,
+            local_link_addr
+        );
+        if local_ipv4_addr.is_unspecified() || local_ipv4_addr.is_broadcast() || local_ipv4_addr.is_multicast() {
+            return Err(Fail::new(libc::EINVAL, "invalid local IPv4 address"));
+        }
+        if local_link_addr.is_nil() || !local_link_addr.is_unicast() {
+            return Err(Fail::new(libc::EINVAL, "invalid local link address"));
+        }
+
+        self.local_link_addr = local_link_addr;
+        self.arp.set_local_identity(local_ipv4_addr, local_link_addr);
+        self.ipv4.set_local_identity(local_ipv4_addr, local_link_addr);
+        self.arp.announce();
+
+        Ok(())
+    }
+
+    /// Broadcasts an ARP request for `target` right away, so that applications may warm up the ARP cache before they
+    /// open connections. The reply, if any, updates the ARP cache. Success means that the request went out, not that a
+    /// reply came back.
+    pub fn send_arp_request(&mut self, target: Ipv4Addr) -> Result<(), Fail> {
+        trace!("send_arp_request() target={:?}", target);
+        Self::check_arp_target(target)?;
+        self.arp.send_request(target);
+        Ok(())
+    }
+
+    /// Broadcasts an ARP request for `target`, and waits up to `timeout` for the reply. Returns the link address of
+    /// `target`, which is in the ARP cache from then on.
+    pub fn wait_arp(&mut self, target: Ipv4Addr, timeout: Duration) -> Result<MacAddress, Fail> {
+        trace!("wait_arp() target={:?} timeout={:?}", target, timeout);
+        Self::check_arp_target(target)?;
+
+        // Wait for the reply before requesting it, so that it cannot slip by.
+        let mut reply: Pin<Box<dyn Future<Output = Result<MacAddress, Fail>>>> =
+            self.arp.wait_reply(target).boxed_local();
+        self.arp.send_request(target);
+
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+        let start: Instant = Instant::now();
+        loop {
+            self.poll_bg_work();
+            if let Poll::Ready(result) = reply.poll_unpin(&mut ctx) {
+                return result;
+            }
+            if Instant::now().duration_since(start) >= timeout {
+                let cause: String = format!("no ARP reply (target={:?})", target);
+                error!("wait_arp(): {}", cause);
+                return Err(Fail::new(libc::ETIMEDOUT, &cause));
+            }
+        }
+    }
+
+    /// Checks if ARP requests may target `target`.
+    fn check_arp_target(target: Ipv4Addr) -> Result<(), Fail> {
+        if target.is_unspecified() || target.is_broadcast() || target.is_multicast() {
+            let cause: String = format!("invalid ARP target (target={:?})", target);
+            error!("check_arp_target(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        Ok(())
+    }
+
     /// Scheduler will poll all futures that are ready to make progress.
     /// Then ask the runtime to receive new data which we will forward to the engine to parse and
     /// route to the correct protocol.
@@ -682,7 +1385,7 @@ impl<const N: usize> InetStack<N> {
                     #[cfg(feature = "profiler")]
                     timer!("inetstack::poll_bg_work::for::receive");
 
-                    self.rt.receive()
+                    self.gro.coalesce(self.rt.receive())
                 };
 
                 {
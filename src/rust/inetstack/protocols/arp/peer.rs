@@ -47,6 +47,7 @@ use ::libc::{
 };
 use ::std::{
     cell::{
+        Cell,
         RefCell,
         RefMut,
     },
@@ -68,11 +69,13 @@ use ::std::{
 /// Arp Peer
 ///
 #[derive(Clone)]
-pub struct ArpPeer<const N: usize> {
-    rt: Rc<dyn NetworkRuntime<N>>,
+pub struct ArpPeer {
+    rt: Rc<dyn NetworkRuntime>,
     clock: TimerRc,
-    local_link_addr: MacAddress,
-    local_ipv4_addr: Ipv4Addr,
+    /// Local link address. This is shared by all clones of this peer, so that it may change at runtime.
+    local_link_addr: Rc<Cell<MacAddress>>,
+    /// Local IPv4 address. This is shared by all clones of this peer, so that it may change at runtime.
+    local_ipv4_addr: Rc<Cell<Ipv4Addr>>,
     cache: Rc<RefCell<ArpCache>>,
     waiters: Rc<RefCell<HashMap<Ipv4Addr, LinkedList<Sender<MacAddress>>>>>,
     arp_config: ArpConfig,
@@ -87,15 +90,15 @@ pub struct ArpPeer<const N: usize> {
 // Associate Functions
 //==============================================================================
 
-impl<const N: usize> ArpPeer<N> {
+impl ArpPeer {
     pub fn new(
-        rt: Rc<dyn NetworkRuntime<N>>,
+        rt: Rc<dyn NetworkRuntime>,
         scheduler: Scheduler,
         clock: TimerRc,
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         arp_config: ArpConfig,
-    ) -> Result<ArpPeer<N>, Fail> {
+    ) -> Result<ArpPeer, Fail> {
         let cache: Rc<RefCell<ArpCache>> = Rc::new(RefCell::new(ArpCache::new(
             clock.clone(),
             Some(arp_config.get_cache_ttl()),
@@ -117,11 +120,11 @@ impl<const N: usize> ArpPeer<N> {
                 ))
             },
         };
-        let peer: ArpPeer<N> = ArpPeer {
+        let peer: ArpPeer = ArpPeer {
             rt,
             clock,
-            local_link_addr,
-            local_ipv4_addr,
+            local_link_addr: Rc::new(Cell::new(local_link_addr)),
+            local_ipv4_addr: Rc::new(Cell::new(local_ipv4_addr)),
             cache,
             waiters: Rc::new(RefCell::new(HashMap::default())),
             arp_config,
@@ -201,7 +204,7 @@ impl<const N: usize> ArpPeer<N> {
             }
         };
         // from RFC 826: ?Am I the target protocol address?
-        if header.get_destination_protocol_addr() != self.local_ipv4_addr {
+        if header.get_destination_protocol_addr() != self.local_ipv4_addr.get() {
             if merge_flag {
                 // we did do something.
                 return Ok(());
@@ -224,11 +227,15 @@ impl<const N: usize> ArpPeer<N> {
                 // > Swap hardware and protocol fields, putting the local
                 // > hardware and protocol addresses in the sender fields.
                 let reply = ArpMessage::new(
-                    Ethernet2Header::new(header.get_sender_hardware_addr(), self.local_link_addr, EtherType2::Arp),
+                    Ethernet2Header::new(
+                        header.get_sender_hardware_addr(),
+                        self.local_link_addr.get(),
+                        EtherType2::Arp,
+                    ),
                     ArpHeader::new(
                         ArpOperation::Reply,
-                        self.local_link_addr,
-                        self.local_ipv4_addr,
+                        self.local_link_addr.get(),
+                        self.local_ipv4_addr.get(),
                         header.get_sender_hardware_addr(),
                         header.get_sender_protocol_addr(),
                     ),
@@ -261,22 +268,11 @@ impl<const N: usize> ArpPeer<N> {
         let cache = self.cache.clone();
         let arp_options = self.arp_config.clone();
         let clock: TimerRc = self.clock.clone();
-        let local_link_addr: MacAddress = self.local_link_addr.clone();
-        let local_ipv4_addr: Ipv4Addr = self.local_ipv4_addr.clone();
+        let msg: ArpMessage = self.request(ipv4_addr);
         async move {
             if let Some(&link_addr) = cache.borrow().get(ipv4_addr) {
                 return Ok(link_addr);
             }
-            let msg = ArpMessage::new(
-                Ethernet2Header::new(MacAddress::broadcast(), local_link_addr, EtherType2::Arp),
-                ArpHeader::new(
-                    ArpOperation::Request,
-                    local_link_addr,
-                    local_ipv4_addr,
-                    MacAddress::broadcast(),
-                    ipv4_addr,
-                ),
-            );
             let mut arp_response = arp.do_wait_link_addr(ipv4_addr).fuse();
 
             // from TCP/IP illustrated, chapter 4:
@@ -306,6 +302,72 @@ impl<const N: usize> ArpPeer<N> {
         }
     }
 
+    /// Broadcasts an ARP request for `ipv4_addr` right away, regardless of whether it is cached already. The reply, if
+    /// any, updates the cache.
+    pub fn send_request(&self, ipv4_addr: Ipv4Addr) {
+        let msg: ArpMessage = self.request(ipv4_addr);
+        debug!("Requesting {:?}", msg);
+        self.rt.transmit(Box::new(msg));
+    }
+
+    /// Waits for the next ARP message that tells the link address of `ipv4_addr`, ignoring the cache. The wait is
+    /// cancelled if a query for the same address gives up in the meantime.
+    pub fn wait_reply(&self, ipv4_addr: Ipv4Addr) -> impl Future<Output = Result<MacAddress, Fail>> {
+        let (tx, rx): (Sender<MacAddress>, Receiver<MacAddress>) = channel();
+        self.waiters.borrow_mut().entry(ipv4_addr).or_default().push_back(tx);
+        rx.map(|r| r.map_err(|_| Fail::new(libc::ECANCELED, "ARP wait cancelled")))
+    }
+
+    /// Builds an ARP request for `ipv4_addr`.
+    fn request(&self, ipv4_addr: Ipv4Addr) -> ArpMessage {
+        let local_link_addr: MacAddress = self.local_link_addr.get();
+        ArpMessage::new(
+            Ethernet2Header::new(MacAddress::broadcast(), local_link_addr, EtherType2::Arp),
+            ArpHeader::new(
+                ArpOperation::Request,
+                local_link_addr,
+                self.local_ipv4_addr.get(),
+                MacAddress::broadcast(),
+                ipv4_addr,
+            ),
+        )
+    }
+
+    /// Gets the local link address.
+    pub fn get_local_link_addr(&self) -> MacAddress {
+        self.local_link_addr.get()
+    }
+
+    /// Gets the local IPv4 address.
+    pub fn get_local_ipv4_addr(&self) -> Ipv4Addr {
+        self.local_ipv4_addr.get()
+    }
+
+    /// Changes the local IPv4 and link addresses. This takes effect on all clones of this peer.
+    pub fn set_local_identity(&self, local_ipv4_addr: Ipv4Addr, local_link_addr: MacAddress) {
+        self.local_ipv4_addr.set(local_ipv4_addr);
+        self.local_link_addr.set(local_link_addr);
+    }
+
+    /// Broadcasts a gratuitous ARP request for our own IPv4 address (RFC 5227 ARP Announcement), so that neighbors
+    /// that have our IPv4 address in their caches update it to our current link address.
+    pub fn announce(&self) {
+        let local_link_addr: MacAddress = self.local_link_addr.get();
+        let local_ipv4_addr: Ipv4Addr = self.local_ipv4_addr.get();
+        let msg = ArpMessage::new(
+            Ethernet2Header::new(MacAddress::broadcast(), local_link_addr, EtherType2::Arp),
+            ArpHeader::new(
+                ArpOperation::Request,
+                local_link_addr,
+                local_ipv4_addr,
+                MacAddress::nil(),
+                local_ipv4_addr,
+            ),
+        );
+        debug!("Announcing {:?}", msg);
+        self.rt.transmit(Box::new(msg));
+    }
+
     #[cfg(test)]
     pub fn export_cache(&self) -> HashMap<Ipv4Addr, MacAddress> {
         self.cache.borrow().export()
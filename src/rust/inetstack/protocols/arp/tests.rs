@@ -13,10 +13,7 @@ use crate::{
             Engine,
         },
     },
-    runtime::network::{
-        consts::RECEIVE_BATCH_SIZE,
-        types::MacAddress,
-    },
+    runtime::network::types::MacAddress,
 };
 use ::anyhow::Result;
 use ::futures::{
@@ -32,6 +29,7 @@ use ::libc::{
 };
 use ::std::{
     future::Future,
+    net::Ipv4Addr,
     task::Poll,
     time::{
         Duration,
@@ -44,9 +42,9 @@ use ::std::{
 fn immediate_reply() -> Result<()> {
     // tests to ensure that an are request results in a reply.
     let now = Instant::now();
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice(now);
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob(now);
-    let mut carrie: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_carrie(now);
+    let mut alice: Engine = test_helpers::new_alice(now);
+    let mut bob: Engine = test_helpers::new_bob(now);
+    let mut carrie: Engine = test_helpers::new_carrie(now);
 
     crate::ensure_eq!(alice.rt.arp_options.get_request_timeout(), Duration::from_secs(1));
 
@@ -96,9 +94,9 @@ fn immediate_reply() -> Result<()> {
 fn slow_reply() -> Result<()> {
     // tests to ensure that an are request results in a reply.
     let mut now = Instant::now();
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice(now);
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob(now);
-    let mut carrie: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_carrie(now);
+    let mut alice: Engine = test_helpers::new_alice(now);
+    let mut bob: Engine = test_helpers::new_bob(now);
+    let mut carrie: Engine = test_helpers::new_carrie(now);
 
     // this test is written based on certain assumptions.
     crate::ensure_eq!(alice.rt.arp_options.get_retry_count() > 0, true);
@@ -159,7 +157,7 @@ fn slow_reply() -> Result<()> {
 fn no_reply() -> Result<()> {
     // tests to ensure that an are request results in a reply.
     let mut now = Instant::now();
-    let alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice(now);
+    let alice: Engine = test_helpers::new_alice(now);
 
     crate::ensure_eq!(alice.rt.arp_options.get_retry_count(), 2);
     crate::ensure_eq!(alice.rt.arp_options.get_request_timeout(), Duration::from_secs(1));
@@ -204,3 +202,97 @@ fn no_reply() -> Result<()> {
         _ => anyhow::bail!("poll should have succeeded"),
     }
 }
+
+/// Tests that changing the local identity announces it with a gratuitous ARP.
+#[test]
+fn announce_on_identity_change() -> Result<()> {
+    let now = Instant::now();
+    let mut alice: Engine = test_helpers::new_alice(now);
+    let mut bob: Engine = test_helpers::new_bob(now);
+    // Alice keeps her IPv4 address but fails over to a NIC with a different link address.
+    let new_ipv4: Ipv4Addr = test_helpers::ALICE_IPV4;
+    let new_mac: MacAddress = MacAddress::new([0x12, 0x23, 0x45, 0x67, 0x89, 0xcd]);
+
+    // Let bob learn about alice first, so that he has an entry to update.
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut fut = alice.arp_query(test_helpers::BOB_IPV4).boxed_local();
+    crate::ensure_eq!(Future::poll(fut.as_mut(), &mut ctx).is_pending(), true);
+    if let Err(e) = bob.receive(alice.rt.pop_frame()) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    let cache = bob.export_arp_cache();
+    crate::ensure_eq!(cache.get(&test_helpers::ALICE_IPV4), Some(&test_helpers::ALICE_MAC));
+
+    alice.set_local_identity(new_ipv4, new_mac);
+    crate::ensure_eq!(alice.arp.get_local_ipv4_addr(), new_ipv4);
+    crate::ensure_eq!(alice.arp.get_local_link_addr(), new_mac);
+
+    let announcement = alice.rt.pop_frame();
+    let (header, payload) = match Ethernet2Header::parse(announcement.clone()) {
+        Ok(result) => result,
+        Err(e) => anyhow::bail!("Could not parse ethernet header: {:?}", e),
+    };
+    crate::ensure_eq!(header.dst_addr(), MacAddress::broadcast());
+    crate::ensure_eq!(header.src_addr(), new_mac);
+    let arp = match ArpHeader::parse(payload) {
+        Ok(arp) => arp,
+        Err(e) => anyhow::bail!("Could not parse arp header: {:?}", e),
+    };
+    crate::ensure_eq!(arp.get_operation(), ArpOperation::Request);
+    crate::ensure_eq!(arp.get_sender_hardware_addr(), new_mac);
+    crate::ensure_eq!(arp.get_sender_protocol_addr(), new_ipv4);
+    crate::ensure_eq!(arp.get_destination_protocol_addr(), new_ipv4);
+
+    // Bob learns the new binding from the announcement.
+    if let Err(e) = bob.receive(announcement) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    let cache = bob.export_arp_cache();
+    crate::ensure_eq!(cache.get(&new_ipv4), Some(&new_mac));
+
+    Ok(())
+}
+
+/// Tests that a manual request goes out right away, and that its reply both reaches the waiter and updates the cache.
+#[test]
+fn manual_request() -> Result<()> {
+    let now = Instant::now();
+    let mut alice: Engine = test_helpers::new_alice(now);
+    let mut carrie: Engine = test_helpers::new_carrie(now);
+
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut fut = alice.arp_wait_reply(test_helpers::CARRIE_IPV4).boxed_local();
+    crate::ensure_eq!(Future::poll(fut.as_mut(), &mut ctx).is_pending(), true);
+
+    alice.arp_send_request(test_helpers::CARRIE_IPV4);
+    let request = alice.rt.pop_frame();
+    let (header, payload) = match Ethernet2Header::parse(request.clone()) {
+        Ok(result) => result,
+        Err(e) => anyhow::bail!("Could not parse ethernet header: {:?}", e),
+    };
+    crate::ensure_eq!(header.dst_addr(), MacAddress::broadcast());
+    let arp = match ArpHeader::parse(payload) {
+        Ok(arp) => arp,
+        Err(e) => anyhow::bail!("Could not parse arp header: {:?}", e),
+    };
+    crate::ensure_eq!(arp.get_operation(), ArpOperation::Request);
+    crate::ensure_eq!(arp.get_sender_protocol_addr(), test_helpers::ALICE_IPV4);
+    crate::ensure_eq!(arp.get_destination_protocol_addr(), test_helpers::CARRIE_IPV4);
+
+    if let Err(e) = carrie.receive(request) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    carrie.clock.advance_clock(now);
+    if let Err(e) = alice.receive(carrie.rt.pop_frame()) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+
+    match Future::poll(fut.as_mut(), &mut ctx) {
+        Poll::Ready(Ok(link_addr)) => crate::ensure_eq!(link_addr, test_helpers::CARRIE_MAC),
+        _ => anyhow::bail!("wait should have completed"),
+    }
+    let cache = alice.export_arp_cache();
+    crate::ensure_eq!(cache.get(&test_helpers::CARRIE_IPV4), Some(&test_helpers::CARRIE_MAC));
+
+    Ok(())
+}
@@ -0,0 +1,296 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    memory::DemiBuffer,
+    network::types::MacAddress,
+};
+use ::libc::{
+    EBADMSG,
+    ENOTSUP,
+};
+use ::std::{
+    net::Ipv4Addr,
+    slice::ChunksExact,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+const DHCP_HTYPE_ETHER2: u8 = 1;
+const DHCP_HLEN_ETHER2: u8 = 6;
+const DHCP_FLAG_BROADCAST: u16 = 0x8000;
+const DHCP_MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// Size of the fixed part of a message, magic cookie included.
+const DHCP_HEADER_SIZE: usize = 240;
+/// Some servers still drop messages that are shorter than a BOOTP one (RFC 951), so we pad ours up to that size.
+const DHCP_MESSAGE_MIN_SIZE: usize = 300;
+
+const DHCP_OPTION_PAD: u8 = 0;
+const DHCP_OPTION_ROUTER: u8 = 3;
+const DHCP_OPTION_DNS: u8 = 6;
+const DHCP_OPTION_REQUESTED_IP: u8 = 50;
+const DHCP_OPTION_LEASE_TIME: u8 = 51;
+const DHCP_OPTION_MESSAGE_TYPE: u8 = 53;
+const DHCP_OPTION_SERVER_ID: u8 = 54;
+const DHCP_OPTION_PARAMETER_LIST: u8 = 55;
+const DHCP_OPTION_RENEWAL_TIME: u8 = 58;
+const DHCP_OPTION_END: u8 = 255;
+
+//======================================================================================================================
+// Enumerations
+//======================================================================================================================
+
+/// BOOTP operation of a DHCP message.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DhcpOperation {
+    Request = 1,
+    Reply = 2,
+}
+
+/// Type of a DHCP message (RFC 2132, Section 9.6).
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DhcpMessageType {
+    Discover = 1,
+    Offer = 2,
+    Request = 3,
+    Decline = 4,
+    Ack = 5,
+    Nak = 6,
+    Release = 7,
+    Inform = 8,
+}
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+///
+/// # DHCP Message
+///
+/// Only the fields and options that the client needs are kept. Other options are skipped when parsing.
+///
+#[derive(Clone, Debug)]
+pub struct DhcpMessage {
+    pub op: DhcpOperation,
+    pub msg_type: DhcpMessageType,
+    /// Transaction identifier.
+    pub xid: u32,
+    /// Asks the server to broadcast its replies, because we cannot receive unicast ones before we have an address.
+    pub broadcast: bool,
+    /// Address of the client, if it already has one.
+    pub ciaddr: Ipv4Addr,
+    /// Address offered to the client.
+    pub yiaddr: Ipv4Addr,
+    /// Link address of the client.
+    pub chaddr: MacAddress,
+    pub requested_ip: Option<Ipv4Addr>,
+    pub server_id: Option<Ipv4Addr>,
+    /// Lease duration, in seconds.
+    pub lease_time: Option<u32>,
+    /// Renewal (T1) time, in seconds.
+    pub renewal_time: Option<u32>,
+    pub routers: Vec<Ipv4Addr>,
+    pub dns: Vec<Ipv4Addr>,
+    /// Options that the client asks the server for.
+    pub parameter_list: Vec<u8>,
+}
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+impl DhcpMessage {
+    /// Creates a DHCP message that carries no options other than its type.
+    pub fn new(op: DhcpOperation, msg_type: DhcpMessageType, xid: u32, chaddr: MacAddress) -> Self {
+        Self {
+            op,
+            msg_type,
+            xid,
+            broadcast: false,
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            chaddr,
+            requested_ip: None,
+            server_id: None,
+            lease_time: None,
+            renewal_time: None,
+            routers: Vec::new(),
+            dns: Vec::new(),
+            parameter_list: Vec::new(),
+        }
+    }
+
+    /// Creates a message that the client sends to servers. The client asks for the options that make up a lease.
+    pub fn new_client(msg_type: DhcpMessageType, xid: u32, chaddr: MacAddress) -> Self {
+        let mut msg: Self = Self::new(DhcpOperation::Request, msg_type, xid, chaddr);
+        msg.parameter_list = vec![
+            DHCP_OPTION_ROUTER,
+            DHCP_OPTION_DNS,
+            DHCP_OPTION_LEASE_TIME,
+            DHCP_OPTION_RENEWAL_TIME,
+        ];
+        msg
+    }
+
+    /// Parses a DHCP message.
+    pub fn parse(buf: &[u8]) -> Result<Self, Fail> {
+        if buf.len() < DHCP_HEADER_SIZE {
+            return Err(Fail::new(EBADMSG, "DHCP message too short"));
+        }
+        let op: DhcpOperation = match buf[0] {
+            1 => DhcpOperation::Request,
+            2 => DhcpOperation::Reply,
+            _ => return Err(Fail::new(ENOTSUP, "unsupported DHCP operation")),
+        };
+        if buf[1] != DHCP_HTYPE_ETHER2 || buf[2] != DHCP_HLEN_ETHER2 {
+            return Err(Fail::new(ENOTSUP, "unsupported DHCP hardware type"));
+        }
+        if buf[236..240] != DHCP_MAGIC_COOKIE {
+            return Err(Fail::new(EBADMSG, "bad DHCP magic cookie"));
+        }
+        let xid: u32 = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+        let flags: u16 = u16::from_be_bytes([buf[10], buf[11]]);
+        let ciaddr: Ipv4Addr = Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]);
+        let yiaddr: Ipv4Addr = Ipv4Addr::new(buf[16], buf[17], buf[18], buf[19]);
+        let chaddr: MacAddress = MacAddress::from_bytes(&buf[28..34]);
+
+        let mut msg_type: Option<DhcpMessageType> = None;
+        let mut msg: Self = Self::new(op, DhcpMessageType::Discover, xid, chaddr);
+        msg.broadcast = flags & DHCP_FLAG_BROADCAST != 0;
+        msg.ciaddr = ciaddr;
+        msg.yiaddr = yiaddr;
+
+        // Parse options.
+        let mut options: &[u8] = &buf[DHCP_HEADER_SIZE..];
+        while let Some((&code, rest)) = options.split_first() {
+            match code {
+                DHCP_OPTION_PAD => {
+                    options = rest;
+                    continue;
+                },
+                DHCP_OPTION_END => break,
+                _ => (),
+            }
+            let (len, rest): (usize, &[u8]) = match rest.split_first() {
+                Some((&len, rest)) if rest.len() >= len as usize => (len as usize, rest),
+                _ => return Err(Fail::new(EBADMSG, "DHCP option overflows message")),
+            };
+            let value: &[u8] = &rest[..len];
+            options = &rest[len..];
+            match code {
+                DHCP_OPTION_MESSAGE_TYPE => msg_type = Some(Self::parse_message_type(value)?),
+                DHCP_OPTION_REQUESTED_IP => msg.requested_ip = Self::parse_addrs(value)?.first().copied(),
+                DHCP_OPTION_SERVER_ID => msg.server_id = Self::parse_addrs(value)?.first().copied(),
+                DHCP_OPTION_LEASE_TIME => msg.lease_time = Some(Self::parse_u32(value)?),
+                DHCP_OPTION_RENEWAL_TIME => msg.renewal_time = Some(Self::parse_u32(value)?),
+                DHCP_OPTION_ROUTER => msg.routers = Self::parse_addrs(value)?,
+                DHCP_OPTION_DNS => msg.dns = Self::parse_addrs(value)?,
+                DHCP_OPTION_PARAMETER_LIST => msg.parameter_list = value.to_vec(),
+                _ => trace!("skipping DHCP option {:?}", code),
+            }
+        }
+
+        match msg_type {
+            Some(msg_type) => msg.msg_type = msg_type,
+            None => return Err(Fail::new(EBADMSG, "DHCP message has no type")),
+        }
+        Ok(msg)
+    }
+
+    /// Serializes the target DHCP message.
+    pub fn serialize(&self) -> DemiBuffer {
+        let mut buf: Vec<u8> = vec![0; DHCP_HEADER_SIZE];
+        buf[0] = self.op as u8;
+        buf[1] = DHCP_HTYPE_ETHER2;
+        buf[2] = DHCP_HLEN_ETHER2;
+        buf[4..8].copy_from_slice(&self.xid.to_be_bytes());
+        if self.broadcast {
+            buf[10..12].copy_from_slice(&DHCP_FLAG_BROADCAST.to_be_bytes());
+        }
+        buf[12..16].copy_from_slice(&self.ciaddr.octets());
+        buf[16..20].copy_from_slice(&self.yiaddr.octets());
+        buf[28..34].copy_from_slice(&self.chaddr.octets());
+        buf[236..240].copy_from_slice(&DHCP_MAGIC_COOKIE);
+
+        // Serialize options.
+        buf.extend_from_slice(&[DHCP_OPTION_MESSAGE_TYPE, 1, self.msg_type as u8]);
+        if let Some(addr) = self.requested_ip {
+            Self::serialize_option(&mut buf, DHCP_OPTION_REQUESTED_IP, &addr.octets());
+        }
+        if let Some(addr) = self.server_id {
+            Self::serialize_option(&mut buf, DHCP_OPTION_SERVER_ID, &addr.octets());
+        }
+        if let Some(secs) = self.lease_time {
+            Self::serialize_option(&mut buf, DHCP_OPTION_LEASE_TIME, &secs.to_be_bytes());
+        }
+        if let Some(secs) = self.renewal_time {
+            Self::serialize_option(&mut buf, DHCP_OPTION_RENEWAL_TIME, &secs.to_be_bytes());
+        }
+        for (code, addrs) in [(DHCP_OPTION_ROUTER, &self.routers), (DHCP_OPTION_DNS, &self.dns)] {
+            if !addrs.is_empty() {
+                let value: Vec<u8> = addrs.iter().flat_map(|addr| addr.octets()).collect();
+                Self::serialize_option(&mut buf, code, &value);
+            }
+        }
+        if !self.parameter_list.is_empty() {
+            Self::serialize_option(&mut buf, DHCP_OPTION_PARAMETER_LIST, &self.parameter_list);
+        }
+        buf.push(DHCP_OPTION_END);
+        if buf.len() < DHCP_MESSAGE_MIN_SIZE {
+            buf.resize(DHCP_MESSAGE_MIN_SIZE, DHCP_OPTION_PAD);
+        }
+
+        DemiBuffer::from_slice(&buf).expect("DHCP message should be shorter than u16::MAX")
+    }
+
+    /// Appends an option to a message that is being serialized.
+    fn serialize_option(buf: &mut Vec<u8>, code: u8, value: &[u8]) {
+        debug_assert!(value.len() <= u8::MAX as usize);
+        buf.push(code);
+        buf.push(value.len() as u8);
+        buf.extend_from_slice(value);
+    }
+
+    /// Parses the value of the message type option.
+    fn parse_message_type(value: &[u8]) -> Result<DhcpMessageType, Fail> {
+        match value {
+            [1] => Ok(DhcpMessageType::Discover),
+            [2] => Ok(DhcpMessageType::Offer),
+            [3] => Ok(DhcpMessageType::Request),
+            [4] => Ok(DhcpMessageType::Decline),
+            [5] => Ok(DhcpMessageType::Ack),
+            [6] => Ok(DhcpMessageType::Nak),
+            [7] => Ok(DhcpMessageType::Release),
+            [8] => Ok(DhcpMessageType::Inform),
+            _ => Err(Fail::new(ENOTSUP, "unsupported DHCP message type")),
+        }
+    }
+
+    /// Parses the value of an option that holds a list of addresses.
+    fn parse_addrs(value: &[u8]) -> Result<Vec<Ipv4Addr>, Fail> {
+        let chunks: ChunksExact<u8> = value.chunks_exact(4);
+        if value.is_empty() || !chunks.remainder().is_empty() {
+            return Err(Fail::new(EBADMSG, "bad DHCP address option"));
+        }
+        Ok(chunks
+            .map(|octets| Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+            .collect())
+    }
+
+    /// Parses the value of an option that holds a 32-bit number.
+    fn parse_u32(value: &[u8]) -> Result<u32, Fail> {
+        match value {
+            [a, b, c, d] => Ok(u32::from_be_bytes([*a, *b, *c, *d])),
+            _ => Err(Fail::new(EBADMSG, "bad DHCP time option")),
+        }
+    }
+}
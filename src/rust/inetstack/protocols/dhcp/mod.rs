@@ -0,0 +1,25 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! # Dynamic Host Configuration Protocol
+//!
+//! # References
+//!
+//! - https://datatracker.ietf.org/doc/html/rfc2131.
+//! - https://datatracker.ietf.org/doc/html/rfc2132.
+
+mod message;
+mod peer;
+
+#[cfg(test)]
+mod tests;
+
+//==============================================================================
+// Exports
+//==============================================================================
+
+pub use self::peer::{
+    DhcpLease,
+    DhcpPeer,
+    DHCP_CLIENT_PORT,
+};
@@ -0,0 +1,400 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::{
+        futures::UtilityMethods,
+        protocols::{
+            arp::ArpPeer,
+            dhcp::message::{
+                DhcpMessage,
+                DhcpMessageType,
+                DhcpOperation,
+            },
+            ethernet2::{
+                EtherType2,
+                Ethernet2Header,
+            },
+            ip::IpProtocol,
+            ipv4::Ipv4Header,
+            udp::{
+                UdpDatagram,
+                UdpHeader,
+            },
+        },
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            types::MacAddress,
+            NetworkRuntime,
+        },
+        queue::BackgroundTask,
+        timer::TimerRc,
+    },
+    scheduler::Scheduler,
+};
+use ::futures::{
+    channel::mpsc,
+    StreamExt,
+};
+use ::rand::{
+    prelude::SmallRng,
+    Rng,
+    SeedableRng,
+};
+use ::std::{
+    cell::{
+        Cell,
+        RefCell,
+    },
+    collections::HashMap,
+    future::Future,
+    net::Ipv4Addr,
+    rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Port on which DHCP servers listen.
+pub const DHCP_SERVER_PORT: u16 = 67;
+/// Port on which DHCP clients listen.
+pub const DHCP_CLIENT_PORT: u16 = 68;
+/// Time to wait for a reply to the first transmission of a message. It doubles on every retransmission (RFC 2131,
+/// Section 4.1).
+const DHCP_RETRANSMIT_TIMEOUT: Duration = Duration::from_secs(4);
+/// Number of times a message is retransmitted before giving up.
+const DHCP_MAX_RETRANSMITS: usize = 4;
+/// Shortest time to wait before retrying a renewal that went unanswered (RFC 2131, Section 4.4.5).
+const DHCP_RENEWAL_RETRY_MIN: Duration = Duration::from_secs(60);
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Lease of an IPv4 address, as granted by a DHCP server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DhcpLease {
+    /// Leased address.
+    pub ip: Ipv4Addr,
+    /// Default gateway, or the unspecified address if the server did not name one.
+    pub gateway: Ipv4Addr,
+    /// Domain name servers.
+    pub dns: Vec<Ipv4Addr>,
+    /// Time for which the address is leased.
+    pub lease_duration: Duration,
+}
+
+///
+/// Dynamic Host Configuration Protocol (DHCP) Client
+///
+/// Acquires an IPv4 address from a DHCP server and renews the lease at T1, that is half way through it. Messages are
+/// sent as raw UDP datagrams, because the client has no address to bind a socket to until the exchange completes, and
+/// replies are handed over by the IPv4 layer while a transaction is outstanding.
+///
+/// DHCP is defined in RFC 2131, and its options in RFC 2132.
+///
+#[derive(Clone)]
+pub struct DhcpPeer {
+    /// Underlying runtime.
+    rt: Rc<dyn NetworkRuntime>,
+    /// Underlying scheduler, which runs lease renewals.
+    scheduler: Scheduler,
+    clock: TimerRc,
+    /// Underlying ARP peer, which resolves the server on renewals.
+    arp: ArpPeer,
+    /// Local link address. This is shared with renewals, so that it may change at runtime.
+    local_link_addr: Rc<Cell<MacAddress>>,
+    /// Offload checksum to hardware?
+    checksum_offload: bool,
+    rng: Rc<RefCell<SmallRng>>,
+    /// Outstanding transactions, indexed by transaction identifier.
+    transactions: Rc<RefCell<HashMap<u32, mpsc::UnboundedSender<DhcpMessage>>>>,
+    /// Current lease, if any.
+    lease: Rc<RefCell<Option<DhcpLease>>>,
+    /// Bumped whenever a new lease is acquired, so that renewals of former leases stop.
+    generation: Rc<Cell<u64>>,
+}
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+impl DhcpPeer {
+    /// Creates a DHCP client.
+    pub fn new(
+        rt: Rc<dyn NetworkRuntime>,
+        scheduler: Scheduler,
+        clock: TimerRc,
+        local_link_addr: MacAddress,
+        arp: ArpPeer,
+        checksum_offload: bool,
+        rng_seed: [u8; 32],
+    ) -> Self {
+        Self {
+            rt,
+            scheduler,
+            clock,
+            arp,
+            local_link_addr: Rc::new(Cell::new(local_link_addr)),
+            checksum_offload,
+            rng: Rc::new(RefCell::new(SmallRng::from_seed(rng_seed))),
+            transactions: Rc::new(RefCell::new(HashMap::new())),
+            lease: Rc::new(RefCell::new(None)),
+            generation: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// Queries whether or not the client is waiting for replies from a server.
+    pub fn is_waiting(&self) -> bool {
+        !self.transactions.borrow().is_empty()
+    }
+
+    /// Returns the current lease, if any.
+    pub fn get_lease(&self) -> Option<DhcpLease> {
+        self.lease.borrow().clone()
+    }
+
+    /// Changes the local link address that DHCP messages are sent from.
+    pub fn set_local_identity(&self, local_link_addr: MacAddress) {
+        self.local_link_addr.set(local_link_addr);
+    }
+
+    /// Parses and handles a datagram that was sent to the DHCP client port.
+    pub fn receive(&mut self, ipv4_hdr: &Ipv4Header, buf: DemiBuffer) -> Result<(), Fail> {
+        let (udp_hdr, data): (UdpHeader, DemiBuffer) = UdpHeader::parse(ipv4_hdr, buf, self.checksum_offload)?;
+        if udp_hdr.src_port() != DHCP_SERVER_PORT || udp_hdr.dest_port() != DHCP_CLIENT_PORT {
+            return Err(Fail::new(libc::ENOTCONN, "port not bound"));
+        }
+        let msg: DhcpMessage = DhcpMessage::parse(&data[..])?;
+        debug!("DHCP received {:?} (xid={:#x})", msg.msg_type, msg.xid);
+        if msg.op != DhcpOperation::Reply || msg.chaddr != self.local_link_addr.get() {
+            debug!("DHCP message is not meant for us, dropping it");
+            return Ok(());
+        }
+        match self.transactions.borrow().get(&msg.xid) {
+            Some(tx) => {
+                let _ = tx.unbounded_send(msg);
+            },
+            None => debug!("DHCP message does not match any transaction, dropping it"),
+        }
+        Ok(())
+    }
+
+    /// Acquires a lease from any DHCP server that answers, and schedules its renewal.
+    pub fn request(&mut self) -> impl Future<Output = Result<DhcpLease, Fail>> {
+        let peer: DhcpPeer = self.clone();
+        async move {
+            let xid: u32 = peer.rng.borrow_mut().gen();
+            let local_link_addr: MacAddress = peer.local_link_addr.get();
+
+            // Look for servers.
+            let mut discover: DhcpMessage = DhcpMessage::new_client(DhcpMessageType::Discover, xid, local_link_addr);
+            discover.broadcast = true;
+            let offer: DhcpMessage = peer
+                .exchange(
+                    discover,
+                    Ipv4Addr::UNSPECIFIED,
+                    Ipv4Addr::BROADCAST,
+                    MacAddress::broadcast(),
+                    &[DhcpMessageType::Offer],
+                )
+                .await?;
+            let server: Ipv4Addr = match offer.server_id {
+                Some(server) => server,
+                None => return Err(Fail::new(libc::EBADMSG, "DHCP offer does not identify its server")),
+            };
+            debug!("DHCP server {:?} offered {:?}", server, offer.yiaddr);
+
+            // Take the first offer. The request is broadcast too, so that other servers learn that we declined theirs.
+            let mut request: DhcpMessage = DhcpMessage::new_client(DhcpMessageType::Request, xid, local_link_addr);
+            request.broadcast = true;
+            request.requested_ip = Some(offer.yiaddr);
+            request.server_id = Some(server);
+            let ack: DhcpMessage = peer
+                .exchange(
+                    request,
+                    Ipv4Addr::UNSPECIFIED,
+                    Ipv4Addr::BROADCAST,
+                    MacAddress::broadcast(),
+                    &[DhcpMessageType::Ack, DhcpMessageType::Nak],
+                )
+                .await?;
+            let lease: DhcpLease = Self::parse_lease(ack)?;
+            info!(
+                "DHCP server {:?} leased {:?} for {:?}",
+                server, lease.ip, lease.lease_duration
+            );
+
+            peer.schedule_renewal(lease.clone(), server);
+            Ok(lease)
+        }
+    }
+
+    /// Records a new lease and schedules its renewal. Renewals of former leases are cancelled.
+    fn schedule_renewal(&self, lease: DhcpLease, server: Ipv4Addr) {
+        *self.lease.borrow_mut() = Some(lease.clone());
+        let generation: u64 = self.generation.get() + 1;
+        self.generation.set(generation);
+        let task: BackgroundTask = BackgroundTask::new(
+            String::from("Inetstack::DHCP::renewal"),
+            Box::pin(self.clone().renew(lease, server, self.clock.now(), generation)),
+        );
+        if self.scheduler.insert(task).is_none() {
+            warn!("failed to schedule DHCP lease renewal");
+        }
+    }
+
+    /// Renews a lease at T1 with the server that granted it, for as long as the server agrees. The lease is dropped
+    /// once the server declines it or it expires.
+    async fn renew(self, mut lease: DhcpLease, server: Ipv4Addr, mut acquired: Instant, generation: u64) {
+        let mut deadline: Instant = acquired + lease.lease_duration / 2;
+        loop {
+            self.clock.wait_until(self.clock.clone(), deadline).await;
+            if self.generation.get() != generation {
+                return;
+            }
+
+            // Renew the lease with the server that granted it.
+            let xid: u32 = self.rng.borrow_mut().gen();
+            let mut request: DhcpMessage =
+                DhcpMessage::new_client(DhcpMessageType::Request, xid, self.local_link_addr.get());
+            request.ciaddr = lease.ip;
+            let result: Result<DhcpLease, Fail> = match self.arp.query(server).await {
+                Ok(server_link_addr) => self
+                    .exchange(
+                        request,
+                        lease.ip,
+                        server,
+                        server_link_addr,
+                        &[DhcpMessageType::Ack, DhcpMessageType::Nak],
+                    )
+                    .await
+                    .and_then(Self::parse_lease),
+                Err(e) => Err(e),
+            };
+            if self.generation.get() != generation {
+                return;
+            }
+
+            let now: Instant = self.clock.now();
+            let expiry: Instant = acquired + lease.lease_duration;
+            match result {
+                Ok(renewed) => {
+                    info!(
+                        "DHCP server {:?} renewed {:?} for {:?}",
+                        server, renewed.ip, renewed.lease_duration
+                    );
+                    lease = renewed;
+                    acquired = now;
+                    deadline = acquired + lease.lease_duration / 2;
+                    *self.lease.borrow_mut() = Some(lease.clone());
+                },
+                Err(e) if e.errno == libc::ECONNREFUSED || now >= expiry => {
+                    warn!("DHCP lease of {:?} lost ({:?})", lease.ip, e);
+                    *self.lease.borrow_mut() = None;
+                    return;
+                },
+                // Try again half way through the rest of the lease.
+                Err(e) => {
+                    warn!("failed to renew DHCP lease of {:?} ({:?})", lease.ip, e);
+                    deadline = now + ((expiry - now) / 2).max(DHCP_RENEWAL_RETRY_MIN).min(expiry - now);
+                },
+            }
+        }
+    }
+
+    /// Sends a message and waits for a reply of one of the `expected` types, retransmitting the message with an
+    /// exponential backoff until one arrives.
+    async fn exchange(
+        &self,
+        msg: DhcpMessage,
+        src_ipv4_addr: Ipv4Addr,
+        dst_ipv4_addr: Ipv4Addr,
+        dst_link_addr: MacAddress,
+        expected: &[DhcpMessageType],
+    ) -> Result<DhcpMessage, Fail> {
+        let xid: u32 = msg.xid;
+        let (tx, mut rx) = mpsc::unbounded();
+        self.transactions.borrow_mut().insert(xid, tx);
+
+        let data: DemiBuffer = msg.serialize();
+        let mut timeout: Duration = DHCP_RETRANSMIT_TIMEOUT;
+        let mut reply: Option<DhcpMessage> = None;
+        'retransmit: for _ in 0..=DHCP_MAX_RETRANSMITS {
+            debug!("DHCP send {:?} (xid={:#x})", msg.msg_type, xid);
+            self.transmit(data.clone(), src_ipv4_addr, dst_ipv4_addr, dst_link_addr);
+            let deadline: Instant = self.clock.now() + timeout;
+            loop {
+                match rx
+                    .next()
+                    .with_timeout(self.clock.wait_until(self.clock.clone(), deadline))
+                    .await
+                {
+                    Ok(Some(msg)) if expected.contains(&msg.msg_type) => {
+                        reply = Some(msg);
+                        break 'retransmit;
+                    },
+                    Ok(Some(msg)) => debug!("ignoring unexpected DHCP {:?}", msg.msg_type),
+                    Ok(None) => unreachable!("transaction should be outstanding"),
+                    Err(_) => break,
+                }
+            }
+            timeout *= 2;
+        }
+
+        self.transactions.borrow_mut().remove(&xid);
+        match reply {
+            Some(reply) => Ok(reply),
+            None => {
+                let cause: String = format!("no reply from DHCP server (xid={:#x})", xid);
+                error!("exchange(): {}", cause);
+                Err(Fail::new(libc::ETIMEDOUT, &cause))
+            },
+        }
+    }
+
+    /// Sends a DHCP message as a raw UDP datagram.
+    fn transmit(&self, data: DemiBuffer, src_ipv4_addr: Ipv4Addr, dst_ipv4_addr: Ipv4Addr, dst_link_addr: MacAddress) {
+        let mut ipv4_hdr: Ipv4Header = Ipv4Header::new(src_ipv4_addr, dst_ipv4_addr, IpProtocol::UDP);
+        ipv4_hdr.set_identification(self.rt.next_ipv4_id());
+        let datagram: UdpDatagram = UdpDatagram::new(
+            Ethernet2Header::new(dst_link_addr, self.local_link_addr.get(), EtherType2::Ipv4),
+            ipv4_hdr,
+            UdpHeader::new(DHCP_CLIENT_PORT, DHCP_SERVER_PORT),
+            data,
+            self.checksum_offload,
+        );
+        self.rt.transmit(Box::new(datagram));
+    }
+
+    /// Builds a lease out of the reply of a server to a request.
+    fn parse_lease(reply: DhcpMessage) -> Result<DhcpLease, Fail> {
+        if reply.msg_type == DhcpMessageType::Nak {
+            let cause: String = format!("DHCP server declined the request (xid={:#x})", reply.xid);
+            error!("parse_lease(): {}", cause);
+            return Err(Fail::new(libc::ECONNREFUSED, &cause));
+        }
+        let lease_time: u32 = match reply.lease_time {
+            Some(lease_time) => lease_time,
+            None => return Err(Fail::new(libc::EBADMSG, "DHCP acknowledgement has no lease time")),
+        };
+        Ok(DhcpLease {
+            ip: reply.yiaddr,
+            gateway: reply.routers.first().copied().unwrap_or(Ipv4Addr::UNSPECIFIED),
+            dns: reply.dns,
+            lease_duration: Duration::from_secs(lease_time as u64),
+        })
+    }
+}
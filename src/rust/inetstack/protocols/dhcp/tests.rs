@@ -0,0 +1,280 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use crate::{
+    inetstack::{
+        protocols::{
+            dhcp::{
+                message::{
+                    DhcpMessage,
+                    DhcpMessageType,
+                    DhcpOperation,
+                },
+                peer::DHCP_SERVER_PORT,
+                DhcpLease,
+                DHCP_CLIENT_PORT,
+            },
+            ethernet2::{
+                EtherType2,
+                Ethernet2Header,
+            },
+            ip::IpProtocol,
+            ipv4::Ipv4Header,
+            udp::{
+                UdpDatagram,
+                UdpHeader,
+            },
+        },
+        test_helpers::{
+            self,
+            Engine,
+        },
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            types::MacAddress,
+            NetworkRuntime,
+        },
+    },
+};
+use ::anyhow::Result;
+use ::futures::task::{
+    noop_waker_ref,
+    Context,
+};
+use ::std::{
+    future::Future,
+    net::Ipv4Addr,
+    pin::Pin,
+    task::Poll,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+const GATEWAY_IPV4: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 254);
+const DNS_IPV4: [Ipv4Addr; 2] = [Ipv4Addr::new(8, 8, 8, 8), Ipv4Addr::new(8, 8, 4, 4)];
+const LEASE_TIME: u32 = 3600;
+
+//==============================================================================
+// Helper Functions
+//==============================================================================
+
+/// Parses a DHCP message that a client sent, along with the addresses that it was sent from and to.
+fn parse_client_frame(buf: DemiBuffer) -> Result<(MacAddress, Ipv4Addr, Ipv4Addr, DhcpMessage)> {
+    let (eth2_hdr, buf) = Ethernet2Header::parse(buf)?;
+    let (ipv4_hdr, buf) = Ipv4Header::parse(buf)?;
+    crate::ensure_eq!(ipv4_hdr.get_protocol(), IpProtocol::UDP);
+    let (udp_hdr, buf) = UdpHeader::parse(&ipv4_hdr, buf, false)?;
+    crate::ensure_eq!(udp_hdr.src_port(), DHCP_CLIENT_PORT);
+    crate::ensure_eq!(udp_hdr.dest_port(), DHCP_SERVER_PORT);
+    let msg: DhcpMessage = DhcpMessage::parse(&buf[..])?;
+    crate::ensure_eq!(msg.op, DhcpOperation::Request);
+    Ok((
+        eth2_hdr.dst_addr(),
+        ipv4_hdr.get_src_addr(),
+        ipv4_hdr.get_dest_addr(),
+        msg,
+    ))
+}
+
+/// Builds the reply of a mock DHCP server, which runs on Bob, to a client message.
+fn server_reply(client_msg: &DhcpMessage, msg_type: DhcpMessageType, lease_time: u32) -> DhcpMessage {
+    let mut reply: DhcpMessage = DhcpMessage::new(DhcpOperation::Reply, msg_type, client_msg.xid, client_msg.chaddr);
+    reply.server_id = Some(test_helpers::BOB_IPV4);
+    if msg_type != DhcpMessageType::Nak {
+        reply.yiaddr = test_helpers::ALICE_IPV4;
+        reply.lease_time = Some(lease_time);
+        reply.routers = vec![GATEWAY_IPV4];
+        reply.dns = DNS_IPV4.to_vec();
+    }
+    reply
+}
+
+/// Sends the reply of the mock DHCP server to `dst_ipv4_addr` and has Alice receive it.
+fn send_reply(alice: &mut Engine, bob: &Engine, reply: DhcpMessage, dst_ipv4_addr: Ipv4Addr) -> Result<()> {
+    let dst_link_addr: MacAddress = if dst_ipv4_addr.is_broadcast() {
+        MacAddress::broadcast()
+    } else {
+        test_helpers::ALICE_MAC
+    };
+    bob.rt.transmit(Box::new(UdpDatagram::new(
+        Ethernet2Header::new(dst_link_addr, test_helpers::BOB_MAC, EtherType2::Ipv4),
+        Ipv4Header::new(test_helpers::BOB_IPV4, dst_ipv4_addr, IpProtocol::UDP),
+        UdpHeader::new(DHCP_SERVER_PORT, DHCP_CLIENT_PORT),
+        reply.serialize(),
+        false,
+    )));
+    alice.receive(bob.rt.pop_frame())?;
+    Ok(())
+}
+
+/// Has Alice acquire a lease from the mock DHCP server.
+fn acquire_lease(alice: &mut Engine, bob: &Engine, ctx: &mut Context) -> Result<DhcpLease> {
+    let mut dhcp_fut = Box::pin(alice.dhcp_request());
+    match Future::poll(Pin::new(&mut dhcp_fut), ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("DHCP request should not complete"),
+    };
+
+    // Alice looks for servers.
+    let (dst_link_addr, src_ipv4_addr, dst_ipv4_addr, discover) = parse_client_frame(alice.rt.pop_frame())?;
+    crate::ensure_eq!(discover.msg_type, DhcpMessageType::Discover);
+    crate::ensure_eq!(discover.chaddr, test_helpers::ALICE_MAC);
+    crate::ensure_eq!(discover.broadcast, true);
+    crate::ensure_eq!(dst_link_addr, MacAddress::broadcast());
+    crate::ensure_eq!(src_ipv4_addr, Ipv4Addr::UNSPECIFIED);
+    crate::ensure_eq!(dst_ipv4_addr, Ipv4Addr::BROADCAST);
+
+    // Bob offers an address.
+    let offer: DhcpMessage = server_reply(&discover, DhcpMessageType::Offer, LEASE_TIME);
+    send_reply(alice, bob, offer, Ipv4Addr::BROADCAST)?;
+    match Future::poll(Pin::new(&mut dhcp_fut), ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("DHCP request should not complete"),
+    };
+
+    // Alice requests the offered address.
+    let (_, _, dst_ipv4_addr, request) = parse_client_frame(alice.rt.pop_frame())?;
+    crate::ensure_eq!(request.msg_type, DhcpMessageType::Request);
+    crate::ensure_eq!(request.xid, discover.xid);
+    crate::ensure_eq!(request.requested_ip, Some(test_helpers::ALICE_IPV4));
+    crate::ensure_eq!(request.server_id, Some(test_helpers::BOB_IPV4));
+    crate::ensure_eq!(dst_ipv4_addr, Ipv4Addr::BROADCAST);
+
+    // Bob acknowledges the request.
+    let ack: DhcpMessage = server_reply(&request, DhcpMessageType::Ack, LEASE_TIME);
+    send_reply(alice, bob, ack, Ipv4Addr::BROADCAST)?;
+    match Future::poll(Pin::new(&mut dhcp_fut), ctx) {
+        Poll::Ready(Ok(lease)) => Ok(lease),
+        _ => anyhow::bail!("DHCP request should have completed"),
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+/// Tests if a lease is acquired through a full Discover, Offer, Request, and Ack exchange.
+#[test]
+fn dhcp_request() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let bob: Engine = test_helpers::new_bob2(now);
+
+    let lease: DhcpLease = acquire_lease(&mut alice, &bob, &mut ctx)?;
+    crate::ensure_eq!(lease.ip, test_helpers::ALICE_IPV4);
+    crate::ensure_eq!(lease.gateway, GATEWAY_IPV4);
+    crate::ensure_eq!(lease.dns, DNS_IPV4.to_vec());
+    crate::ensure_eq!(lease.lease_duration, Duration::from_secs(LEASE_TIME as u64));
+    crate::ensure_eq!(alice.dhcp_lease(), Some(lease));
+
+    Ok(())
+}
+
+/// Tests if a lease is renewed with the server that granted it at T1.
+#[test]
+fn dhcp_renewal() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let bob: Engine = test_helpers::new_bob2(now);
+    acquire_lease(&mut alice, &bob, &mut ctx)?;
+
+    // Nothing happens before T1.
+    now += Duration::from_secs(LEASE_TIME as u64 / 2 - 1);
+    alice.clock.advance_clock(now);
+    alice.rt.poll_scheduler();
+    crate::ensure_eq!(alice.rt.pop_frame_unchecked().is_none(), true);
+
+    // Alice asks Bob to renew the lease at T1.
+    now += Duration::from_secs(1);
+    alice.clock.advance_clock(now);
+    alice.rt.poll_scheduler();
+    let (dst_link_addr, src_ipv4_addr, dst_ipv4_addr, request) = parse_client_frame(alice.rt.pop_frame())?;
+    crate::ensure_eq!(request.msg_type, DhcpMessageType::Request);
+    crate::ensure_eq!(request.ciaddr, test_helpers::ALICE_IPV4);
+    crate::ensure_eq!(request.requested_ip, None);
+    crate::ensure_eq!(dst_link_addr, test_helpers::BOB_MAC);
+    crate::ensure_eq!(src_ipv4_addr, test_helpers::ALICE_IPV4);
+    crate::ensure_eq!(dst_ipv4_addr, test_helpers::BOB_IPV4);
+
+    // Bob renews the lease for longer.
+    let ack: DhcpMessage = server_reply(&request, DhcpMessageType::Ack, 2 * LEASE_TIME);
+    send_reply(&mut alice, &bob, ack, test_helpers::ALICE_IPV4)?;
+    alice.rt.poll_scheduler();
+    crate::ensure_eq!(
+        alice.dhcp_lease().map(|lease| lease.lease_duration),
+        Some(Duration::from_secs(2 * LEASE_TIME as u64))
+    );
+
+    Ok(())
+}
+
+/// Tests if a request that the server declines fails.
+#[test]
+fn dhcp_request_declined() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let bob: Engine = test_helpers::new_bob2(now);
+
+    let mut dhcp_fut = Box::pin(alice.dhcp_request());
+    let _ = Future::poll(Pin::new(&mut dhcp_fut), &mut ctx);
+    let (_, _, _, discover) = parse_client_frame(alice.rt.pop_frame())?;
+    send_reply(
+        &mut alice,
+        &bob,
+        server_reply(&discover, DhcpMessageType::Offer, LEASE_TIME),
+        Ipv4Addr::BROADCAST,
+    )?;
+    let _ = Future::poll(Pin::new(&mut dhcp_fut), &mut ctx);
+    let (_, _, _, request) = parse_client_frame(alice.rt.pop_frame())?;
+    send_reply(
+        &mut alice,
+        &bob,
+        server_reply(&request, DhcpMessageType::Nak, 0),
+        Ipv4Addr::BROADCAST,
+    )?;
+    match Future::poll(Pin::new(&mut dhcp_fut), &mut ctx) {
+        Poll::Ready(Err(e)) => crate::ensure_eq!(e.errno, libc::ECONNREFUSED),
+        _ => anyhow::bail!("DHCP request should have failed"),
+    };
+    crate::ensure_eq!(alice.dhcp_lease(), None);
+
+    Ok(())
+}
+
+/// Tests if messages are retransmitted with an exponential backoff until the request times out.
+#[test]
+fn dhcp_request_timeout() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+    let mut alice: Engine = test_helpers::new_alice2(now);
+
+    let mut dhcp_fut = Box::pin(alice.dhcp_request());
+    let mut timeout: Duration = Duration::from_secs(4);
+    let result: Result<DhcpLease, Fail> = loop {
+        if let Poll::Ready(result) = Future::poll(Pin::new(&mut dhcp_fut), &mut ctx) {
+            break result;
+        }
+        let (_, _, _, discover) = parse_client_frame(alice.rt.pop_frame())?;
+        crate::ensure_eq!(discover.msg_type, DhcpMessageType::Discover);
+        crate::ensure_eq!(alice.rt.pop_frame_unchecked().is_none(), true);
+        now += timeout;
+        alice.clock.advance_clock(now);
+        timeout *= 2;
+    };
+    crate::ensure_eq!(result.err().map(|e| e.errno), Some(libc::ETIMEDOUT));
+    crate::ensure_eq!(timeout, Duration::from_secs(128));
+
+    Ok(())
+}
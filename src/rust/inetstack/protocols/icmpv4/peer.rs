@@ -17,7 +17,10 @@ use crate::{
                 Icmpv4Type2,
             },
             ip::IpProtocol,
-            ipv4::Ipv4Header,
+            ipv4::{
+                Ipv4Header,
+                Ipv4Options,
+            },
         },
     },
     runtime::{
@@ -56,7 +59,10 @@ use ::rand::{
     SeedableRng,
 };
 use ::std::{
-    cell::RefCell,
+    cell::{
+        Cell,
+        RefCell,
+    },
     collections::HashMap,
     future::Future,
     net::Ipv4Addr,
@@ -108,20 +114,22 @@ impl ReqQueue {
 ///
 /// ICMP for IPv4 is defined in RFC 792.
 ///
-pub struct Icmpv4Peer<const N: usize> {
+pub struct Icmpv4Peer {
     /// Underlying Runtime
-    rt: Rc<dyn NetworkRuntime<N>>,
+    rt: Rc<dyn NetworkRuntime>,
 
     clock: TimerRc,
 
-    local_link_addr: MacAddress,
-    local_ipv4_addr: Ipv4Addr,
+    /// Local link address. This is shared with the background co-routine, so that it may change at runtime.
+    local_link_addr: Rc<Cell<MacAddress>>,
+    /// Local IPv4 address. This is shared with the background co-routine, so that it may change at runtime.
+    local_ipv4_addr: Rc<Cell<Ipv4Addr>>,
 
     /// Underlying ARP Peer
-    arp: ArpPeer<N>,
+    arp: ArpPeer,
 
-    /// Transmitter
-    tx: mpsc::UnboundedSender<(Ipv4Addr, u16, u16, DemiBuffer)>,
+    /// Transmitter of echo requests to reply, along with the IPv4 options for the reply.
+    tx: mpsc::UnboundedSender<(Ipv4Addr, Ipv4Options, u16, u16, DemiBuffer)>,
 
     /// Queue of Requests
     requests: Rc<RefCell<ReqQueue>>,
@@ -137,26 +145,28 @@ pub struct Icmpv4Peer<const N: usize> {
     background: TaskHandle,
 }
 
-impl<const N: usize> Icmpv4Peer<N> {
+impl Icmpv4Peer {
     /// Creates a new peer for handling ICMP.
     pub fn new(
-        rt: Rc<dyn NetworkRuntime<N>>,
+        rt: Rc<dyn NetworkRuntime>,
         scheduler: Scheduler,
         clock: TimerRc,
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
-        arp: ArpPeer<N>,
+        arp: ArpPeer,
         rng_seed: [u8; 32],
     ) -> Result<Self, Fail> {
         let (tx, rx) = mpsc::unbounded();
         let requests = ReqQueue::new();
         let rng: Rc<RefCell<SmallRng>> = Rc::new(RefCell::new(SmallRng::from_seed(rng_seed)));
+        let local_link_addr: Rc<Cell<MacAddress>> = Rc::new(Cell::new(local_link_addr));
+        let local_ipv4_addr: Rc<Cell<Ipv4Addr>> = Rc::new(Cell::new(local_ipv4_addr));
         let task: BackgroundTask = BackgroundTask::new(
             String::from("Inetstack::ICMP::background"),
             Box::pin(Self::background(
                 rt.clone(),
-                local_link_addr,
-                local_ipv4_addr,
+                local_link_addr.clone(),
+                local_ipv4_addr.clone(),
                 arp.clone(),
                 rx,
             )),
@@ -185,28 +195,33 @@ impl<const N: usize> Icmpv4Peer<N> {
 
     /// Background task for replying to ICMP messages.
     async fn background(
-        rt: Rc<dyn NetworkRuntime<N>>,
-        local_link_addr: MacAddress,
-        local_ipv4_addr: Ipv4Addr,
-        arp: ArpPeer<N>,
-        mut rx: mpsc::UnboundedReceiver<(Ipv4Addr, u16, u16, DemiBuffer)>,
+        rt: Rc<dyn NetworkRuntime>,
+        local_link_addr: Rc<Cell<MacAddress>>,
+        local_ipv4_addr: Rc<Cell<Ipv4Addr>>,
+        arp: ArpPeer,
+        mut rx: mpsc::UnboundedReceiver<(Ipv4Addr, Ipv4Options, u16, u16, DemiBuffer)>,
     ) {
         // Reply requests.
-        while let Some((dst_ipv4_addr, id, seq_num, data)) = rx.next().await {
+        while let Some((dst_ipv4_addr, options, id, seq_num, data)) = rx.next().await {
+            let mut ipv4_hdr: Ipv4Header = Ipv4Header::new(local_ipv4_addr.get(), dst_ipv4_addr, IpProtocol::ICMPv4);
+            // A source route may send the reply through another hop first.
+            ipv4_hdr.set_options(options);
+            let next_hop: Ipv4Addr = ipv4_hdr.get_dest_addr();
             debug!("initiating ARP query");
-            let dst_link_addr: MacAddress = match arp.query(dst_ipv4_addr).await {
+            let dst_link_addr: MacAddress = match arp.query(next_hop).await {
                 Ok(dst_link_addr) => dst_link_addr,
                 Err(e) => {
                     warn!("reply_to_ping({}, {}, {}) failed: {:?}", dst_ipv4_addr, id, seq_num, e);
                     continue;
                 },
             };
-            debug!("ARP query complete ({} -> {})", dst_ipv4_addr, dst_link_addr);
+            debug!("ARP query complete ({} -> {})", next_hop, dst_link_addr);
             debug!("reply ping ({}, {}, {})", dst_ipv4_addr, id, seq_num);
             // Send reply message.
+            ipv4_hdr.set_identification(rt.next_ipv4_id());
             rt.transmit(Box::new(Icmpv4Message::new(
-                Ethernet2Header::new(dst_link_addr, local_link_addr, EtherType2::Ipv4),
-                Ipv4Header::new(local_ipv4_addr, dst_ipv4_addr, IpProtocol::ICMPv4),
+                Ethernet2Header::new(dst_link_addr, local_link_addr.get(), EtherType2::Ipv4),
+                ipv4_hdr,
                 Icmpv4Header::new(Icmpv4Type2::EchoReply { id, seq_num }, 0),
                 data,
             )));
@@ -219,8 +234,18 @@ impl<const N: usize> Icmpv4Peer<N> {
         debug!("ICMPv4 received {:?}", icmpv4_hdr);
         match icmpv4_hdr.get_protocol() {
             Icmpv4Type2::EchoRequest { id, seq_num } => {
+                // Echo replies carry the route options of the request (see RFC 1122, Section 3.2.2.6).
+                let options: Ipv4Options = match Ipv4Options::from_options(
+                    &ipv4_header.get_options().reply_options(self.local_ipv4_addr.get()),
+                ) {
+                    Ok(options) => options,
+                    Err(e) => {
+                        warn!("dropping options of echo request: {:?}", e);
+                        Ipv4Options::new()
+                    },
+                };
                 self.tx
-                    .unbounded_send((ipv4_header.get_src_addr(), id, seq_num, data))
+                    .unbounded_send((ipv4_header.get_src_addr(), options, id, seq_num, data))
                     .unwrap();
             },
             Icmpv4Type2::EchoReply { id, seq_num } => {
@@ -235,10 +260,16 @@ impl<const N: usize> Icmpv4Peer<N> {
         Ok(())
     }
 
+    /// Changes the local IPv4 and link addresses that ICMP messages are sent from.
+    pub fn set_local_identity(&self, local_ipv4_addr: Ipv4Addr, local_link_addr: MacAddress) {
+        self.local_ipv4_addr.set(local_ipv4_addr);
+        self.local_link_addr.set(local_link_addr);
+    }
+
     /// Computes the identifier for an ICMP message.
     fn make_id(&self) -> u16 {
         let mut state: u32 = 0xFFFF;
-        let addr_octets: [u8; 4] = self.local_ipv4_addr.octets();
+        let addr_octets: [u8; 4] = self.local_ipv4_addr.get().octets();
         state += u16::from_be_bytes([addr_octets[0], addr_octets[1]]) as u32;
         state += u16::from_be_bytes([addr_octets[2], addr_octets[3]]) as u32;
 
@@ -273,12 +304,12 @@ impl<const N: usize> Icmpv4Peer<N> {
         let id: u16 = self.make_id();
         let seq_num: u16 = self.make_seq_num();
         let echo_request: Icmpv4Type2 = Icmpv4Type2::EchoRequest { id, seq_num };
-        let arp: ArpPeer<N> = self.arp.clone();
-        let rt: Rc<dyn NetworkRuntime<N>> = self.rt.clone();
+        let arp: ArpPeer = self.arp.clone();
+        let rt: Rc<dyn NetworkRuntime> = self.rt.clone();
         let clock: TimerRc = self.clock.clone();
         let requests: Rc<RefCell<ReqQueue>> = self.requests.clone();
-        let local_link_addr: MacAddress = self.local_link_addr.clone();
-        let local_ipv4_addr: Ipv4Addr = self.local_ipv4_addr.clone();
+        let local_link_addr: MacAddress = self.local_link_addr.get();
+        let local_ipv4_addr: Ipv4Addr = self.local_ipv4_addr.get();
         async move {
             let t0: Instant = clock.now();
             debug!("initiating ARP query");
@@ -287,9 +318,11 @@ impl<const N: usize> Icmpv4Peer<N> {
 
             let data: DemiBuffer = DemiBuffer::new(datagram::ICMPV4_ECHO_REQUEST_MESSAGE_SIZE);
 
+            let mut ipv4_hdr: Ipv4Header = Ipv4Header::new(local_ipv4_addr, dst_ipv4_addr, IpProtocol::ICMPv4);
+            ipv4_hdr.set_identification(rt.next_ipv4_id());
             let msg: Icmpv4Message = Icmpv4Message::new(
                 Ethernet2Header::new(dst_link_addr, local_link_addr, EtherType2::Ipv4),
-                Ipv4Header::new(local_ipv4_addr, dst_ipv4_addr, IpProtocol::ICMPv4),
+                ipv4_hdr,
                 Icmpv4Header::new(echo_request, 0),
                 data,
             );
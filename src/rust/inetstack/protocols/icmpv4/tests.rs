@@ -2,11 +2,23 @@
 // Licensed under the MIT license.
 
 use crate::{
-    inetstack::test_helpers::{
-        self,
-        Engine,
+    inetstack::{
+        protocols::{
+            ethernet2::ETHERNET2_HEADER_SIZE,
+            ip::IpProtocol,
+            ipv4::{
+                Ipv4Header,
+                Ipv4Option,
+                Ipv4Options,
+                Ipv4Route,
+            },
+        },
+        test_helpers::{
+            self,
+            Engine,
+        },
     },
-    runtime::network::consts::RECEIVE_BATCH_SIZE,
+    runtime::memory::DemiBuffer,
 };
 use ::anyhow::Result;
 use ::futures::task::{
@@ -32,9 +44,9 @@ fn ipv4_ping() -> Result<()> {
     let mut ctx = Context::from_waker(noop_waker_ref());
     let mut now = Instant::now();
 
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
 
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
 
     // Alice pings Bob.
     let mut ping_fut = Box::pin(alice.ipv4_ping(test_helpers::BOB_IPV4, None));
@@ -74,9 +86,9 @@ fn ipv4_ping_loop() -> Result<()> {
     let mut ctx = Context::from_waker(noop_waker_ref());
     let mut now = Instant::now();
 
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
 
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
 
     for _ in 1..1000 {
         // Alice pings Bob.
@@ -112,3 +124,69 @@ fn ipv4_ping_loop() -> Result<()> {
 
     Ok(())
 }
+
+/// Tests if a reply to a ping that asks to record its route carries on recording the route, starting with the
+/// replying host.
+#[test]
+fn ipv4_ping_record_route() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    let mut alice: Engine = test_helpers::new_alice2(now);
+
+    let mut bob: Engine = test_helpers::new_bob2(now);
+
+    // Alice pings Bob.
+    let mut ping_fut = Box::pin(alice.ipv4_ping(test_helpers::BOB_IPV4, None));
+    match Future::poll(Pin::new(&mut ping_fut), &mut ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("Ping should not complete"),
+    };
+
+    // Rewrite the ping request, so that it asks to record the route through two hops.
+    let request: DemiBuffer = alice.rt.pop_frame();
+    let (_, icmpv4_bytes) = Ipv4Header::parse(DemiBuffer::from_slice(&request[ETHERNET2_HEADER_SIZE..])?)?;
+    let mut ipv4_hdr: Ipv4Header =
+        Ipv4Header::new(test_helpers::ALICE_IPV4, test_helpers::BOB_IPV4, IpProtocol::ICMPv4);
+    let unspecified: std::net::Ipv4Addr = std::net::Ipv4Addr::UNSPECIFIED;
+    let route: Ipv4Route = Ipv4Route::new(vec![unspecified, unspecified]);
+    ipv4_hdr.set_options(Ipv4Options::from_options(&[
+        Ipv4Option::Nop,
+        Ipv4Option::RecordRoute(route),
+    ])?);
+    let ipv4_hdr_size: usize = ipv4_hdr.compute_size();
+    let mut bytes: Vec<u8> = vec![0; ETHERNET2_HEADER_SIZE + ipv4_hdr_size + icmpv4_bytes.len()];
+    bytes[..ETHERNET2_HEADER_SIZE].copy_from_slice(&request[..ETHERNET2_HEADER_SIZE]);
+    ipv4_hdr.serialize(
+        &mut bytes[ETHERNET2_HEADER_SIZE..(ETHERNET2_HEADER_SIZE + ipv4_hdr_size)],
+        icmpv4_bytes.len(),
+    );
+    bytes[(ETHERNET2_HEADER_SIZE + ipv4_hdr_size)..].copy_from_slice(&icmpv4_bytes);
+
+    // Bob receives ping request from Alice, and replies.
+    bob.receive(DemiBuffer::from_slice(&bytes)?)?;
+    bob.rt.poll_scheduler();
+
+    // The reply records Bob as the first hop of its route.
+    let reply: DemiBuffer = bob.rt.pop_frame();
+    let (reply_hdr, _) = Ipv4Header::parse(DemiBuffer::from_slice(&reply[ETHERNET2_HEADER_SIZE..])?)?;
+    crate::ensure_eq!(reply_hdr.get_dest_addr(), test_helpers::ALICE_IPV4);
+    let expected: Ipv4Route = Ipv4Route {
+        addrs: vec![test_helpers::BOB_IPV4, unspecified],
+        next: 1,
+    };
+    crate::ensure_eq!(
+        reply_hdr.get_options().options(),
+        vec![Ipv4Option::RecordRoute(expected)]
+    );
+
+    // Alice receives reply from Bob.
+    alice.receive(reply)?;
+    alice.rt.poll_scheduler();
+    match Future::poll(Pin::new(&mut ping_fut), &mut ctx) {
+        Poll::Ready(Ok(_)) => {},
+        _ => anyhow::bail!("Ping should have completed"),
+    };
+
+    Ok(())
+}
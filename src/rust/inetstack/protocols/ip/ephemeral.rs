@@ -2,9 +2,9 @@
 // Licensed under the MIT license.
 
 use crate::runtime::fail::Fail;
-use ::rand::prelude::{
-    SliceRandom,
-    SmallRng,
+use ::rand::{
+    prelude::SmallRng,
+    Rng,
 };
 
 //==============================================================================
@@ -13,13 +13,24 @@ use ::rand::prelude::{
 
 const FIRST_PRIVATE_PORT: u16 = 49152;
 const LAST_PRIVATE_PORT: u16 = 65535;
+const NUM_PRIVATE_PORTS: usize = (LAST_PRIVATE_PORT - FIRST_PRIVATE_PORT) as usize + 1;
 
 //==============================================================================
 // Structures
 //==============================================================================
 
+/// Allocator for port numbers in the ephemeral port range. The allocator is shared by all protocols of the stack, so
+/// the same port number may be reserved more than once (e.g. by a TCP and a UDP socket). A port is only handed out by
+/// [EphemeralPorts::alloc_any] once nobody holds it.
 pub struct EphemeralPorts {
-    ports: Vec<u16>,
+    /// Number of holders of each port in the ephemeral range.
+    holders: Vec<u16>,
+    /// Offset into the ephemeral range of the first port that is handed out.
+    first: usize,
+    /// Number of ports that are handed out.
+    count: usize,
+    /// Offset into the ports that are handed out from which the next search starts.
+    next: usize,
 }
 
 //==============================================================================
@@ -27,13 +38,15 @@ pub struct EphemeralPorts {
 //==============================================================================
 
 impl EphemeralPorts {
+    /// Creates an allocator whose first search starts at a random offset into the ephemeral range, so that the
+    /// sequence of allocated ports does not fingerprint the stack.
     pub fn new(rng: &mut SmallRng) -> Self {
-        let mut ports: Vec<u16> = Vec::<u16>::new();
-        for port in FIRST_PRIVATE_PORT..LAST_PRIVATE_PORT {
-            ports.push(port);
+        Self {
+            holders: vec![0; NUM_PRIVATE_PORTS],
+            first: 0,
+            count: NUM_PRIVATE_PORTS,
+            next: rng.gen_range(0..NUM_PRIVATE_PORTS),
         }
-        ports.shuffle(rng);
-        Self { ports }
     }
 
     pub fn first_private_port() -> u16 {
@@ -44,27 +57,131 @@ impl EphemeralPorts {
         port >= FIRST_PRIVATE_PORT
     }
 
+    /// Allocates an unused port, searching the ephemeral range circularly from where the last search stopped. The
+    /// returned port is reserved until it is released with [EphemeralPorts::free].
     pub fn alloc_any(&mut self) -> Result<u16, Fail> {
-        self.ports.pop().ok_or(Fail::new(
+        for i in 0..self.count {
+            let index: usize = (self.next + i) % self.count;
+            let offset: usize = self.first + index;
+            if self.holders[offset] == 0 {
+                self.holders[offset] = 1;
+                self.next = (index + 1) % self.count;
+                return Ok(FIRST_PRIVATE_PORT + offset as u16);
+            }
+        }
+        Err(Fail::new(
             libc::EADDRINUSE,
             "all port numbers in the ephemeral port range are currently in use",
         ))
     }
 
-    /// Allocates the specified port from the pool.
+    /// Restricts the ports that [EphemeralPorts::alloc_any] hands out to those from `first` to `last`, both included.
+    /// Ports that are already reserved stay so.
+    pub fn set_range(&mut self, first: u16, last: u16) -> Result<(), Fail> {
+        if !Self::is_private(first) || last < first {
+            let cause: String = format!("invalid ephemeral port range (first={:?}, last={:?})", first, last);
+            error!("set_range(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        self.first = (first - FIRST_PRIVATE_PORT) as usize;
+        self.count = (last - first) as usize + 1;
+        self.next = 0;
+        Ok(())
+    }
+
+    /// Reserves the specified port, so that it is not handed out by [EphemeralPorts::alloc_any].
     pub fn alloc_port(&mut self, port: u16) -> Result<(), Fail> {
-        // Check if port is not in the pool.
-        if !self.ports.contains(&port) {
-            return Err(Fail::new(libc::ENOENT, "port number not found"));
+        if !Self::is_private(port) {
+            return Err(Fail::new(
+                libc::EINVAL,
+                "port number is not in the ephemeral port range",
+            ));
+        }
+        self.holders[(port - FIRST_PRIVATE_PORT) as usize] += 1;
+        Ok(())
+    }
+
+    /// Releases one reservation of the specified port.
+    pub fn free(&mut self, port: u16) {
+        if Self::is_private(port) {
+            let holders: &mut u16 = &mut self.holders[(port - FIRST_PRIVATE_PORT) as usize];
+            *holders = holders.saturating_sub(1);
+        }
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        EphemeralPorts,
+        FIRST_PRIVATE_PORT,
+        LAST_PRIVATE_PORT,
+        NUM_PRIVATE_PORTS,
+    };
+    use ::anyhow::Result;
+    use ::rand::{
+        prelude::SmallRng,
+        SeedableRng,
+    };
+    use ::std::collections::HashSet;
+
+    /// Tests that every port of the ephemeral range is handed out exactly once before the range is exhausted.
+    #[test]
+    fn alloc_any_exhausts_range() -> Result<()> {
+        let mut rng: SmallRng = SmallRng::from_seed([0; 32]);
+        let mut ports: EphemeralPorts = EphemeralPorts::new(&mut rng);
+        let mut allocated: HashSet<u16> = HashSet::new();
+        for _ in 0..NUM_PRIVATE_PORTS {
+            let port: u16 = ports.alloc_any()?;
+            crate::ensure_eq!(port >= FIRST_PRIVATE_PORT && port <= LAST_PRIVATE_PORT, true);
+            crate::ensure_eq!(allocated.insert(port), true);
         }
+        crate::ensure_eq!(ports.alloc_any().is_err(), true);
 
-        // Remove port from the pool.
-        self.ports.retain(|&p| p != port);
+        // A released port becomes available again.
+        ports.free(LAST_PRIVATE_PORT);
+        crate::ensure_eq!(ports.alloc_any()?, LAST_PRIVATE_PORT);
+        Ok(())
+    }
+
+    /// Tests that reserved ports are skipped and that successive allocations follow each other circularly.
+    #[test]
+    fn alloc_any_skips_reserved_ports() -> Result<()> {
+        let mut rng: SmallRng = SmallRng::from_seed([0; 32]);
+        let mut ports: EphemeralPorts = EphemeralPorts::new(&mut rng);
+        let first: u16 = ports.alloc_any()?;
+        ports.free(first);
 
+        // Reserve the port after the first one twice, so a single release does not free it.
+        let next: u16 = if first == LAST_PRIVATE_PORT {
+            FIRST_PRIVATE_PORT
+        } else {
+            first + 1
+        };
+        ports.alloc_port(next)?;
+        ports.alloc_port(next)?;
+        ports.free(next);
+
+        let second: u16 = ports.alloc_any()?;
+        crate::ensure_neq!(second, next);
+        crate::ensure_eq!(ports.alloc_port(80).is_err(), true);
         Ok(())
     }
 
-    pub fn free(&mut self, port: u16) {
-        self.ports.push(port);
+    /// Tests that a restricted range only hands out its own ports.
+    #[test]
+    fn alloc_any_in_range() -> Result<()> {
+        let mut rng: SmallRng = SmallRng::from_seed([0; 32]);
+        let mut ports: EphemeralPorts = EphemeralPorts::new(&mut rng);
+        ports.set_range(FIRST_PRIVATE_PORT + 1, FIRST_PRIVATE_PORT + 2)?;
+        crate::ensure_eq!(ports.alloc_any()?, FIRST_PRIVATE_PORT + 1);
+        crate::ensure_eq!(ports.alloc_any()?, FIRST_PRIVATE_PORT + 2);
+        crate::ensure_eq!(ports.alloc_any().is_err(), true);
+        crate::ensure_eq!(ports.set_range(80, 81).is_err(), true);
+        Ok(())
     }
 }
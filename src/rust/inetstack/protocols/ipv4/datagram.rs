@@ -5,6 +5,7 @@
 // Imports
 //==============================================================================
 
+use super::options::Ipv4Options;
 use crate::{
     inetstack::protocols::ip::IpProtocol,
     runtime::{
@@ -55,6 +56,15 @@ const IPV4_CTRL_FLAG_DF: u8 = 0x2;
 /// IPv4 Control Flag: More Fragments.
 const IPV4_CTRL_FLAG_MF: u8 = 0x1;
 
+/// ECN Codepoint: Not ECN-Capable Transport (see RFC 3168).
+pub const IPV4_ECN_NOT_ECT: u8 = 0x0;
+
+/// ECN Codepoint: ECN-Capable Transport, ECT(0) (see RFC 3168).
+pub const IPV4_ECN_ECT0: u8 = 0x2;
+
+/// ECN Codepoint: Congestion Experienced (see RFC 3168).
+pub const IPV4_ECN_CE: u8 = 0x3;
+
 //==============================================================================
 // Structures
 //==============================================================================
@@ -90,6 +100,8 @@ pub struct Ipv4Header {
     src_addr: Ipv4Addr,
     /// Destination IP address (32 bits).
     dst_addr: Ipv4Addr,
+    /// Options (up to 40 bytes).
+    options: Ipv4Options,
 }
 
 //==============================================================================
@@ -114,6 +126,7 @@ impl Ipv4Header {
             header_checksum: 0,
             src_addr,
             dst_addr,
+            options: Ipv4Options::new(),
         }
     }
 
@@ -154,9 +167,6 @@ impl Ipv4Header {
 
         // Explicit congestion notification.
         let ecn: u8 = hdr_buf[1] & 3;
-        if ecn != 0 {
-            warn!("ignoring ecn field (ecn={:?})", ecn);
-        }
 
         // Total length.
         let total_length: u16 = u16::from_be_bytes([hdr_buf[2], hdr_buf[3]]);
@@ -226,6 +236,9 @@ impl Ipv4Header {
         // Destination address.
         let dst_addr: Ipv4Addr = Ipv4Addr::new(hdr_buf[16], hdr_buf[17], hdr_buf[18], hdr_buf[19]);
 
+        // Options.
+        let options: Ipv4Options = Ipv4Options::parse(&hdr_buf[(IPV4_HEADER_MIN_SIZE as usize)..])?;
+
         // Truncate datagram.
         let padding_bytes: usize = buf.len() - (total_length as usize);
         buf.adjust(hdr_size as usize)?;
@@ -245,6 +258,7 @@ impl Ipv4Header {
             header_checksum,
             src_addr,
             dst_addr,
+            options,
         };
 
         Ok((header, buf))
@@ -252,6 +266,8 @@ impl Ipv4Header {
 
     /// Serializes the target IPv4 header.
     pub fn serialize(&self, buf: &mut [u8], payload_len: usize) {
+        assert_eq!(buf.len(), self.compute_size(), "buffer should fit the IPv4 header");
+        let (buf, options_buf): (&mut [u8], &mut [u8]) = buf.split_at_mut(IPV4_HEADER_MIN_SIZE as usize);
         let buf: &mut [u8; IPV4_HEADER_MIN_SIZE as usize] = buf
             .try_into()
             .expect("buffer should be large enough to hold an IPv4 header");
@@ -263,7 +279,7 @@ impl Ipv4Header {
         buf[1] = (self.dscp << 2) | (self.ecn & 3);
 
         // Total Length.
-        buf[2..4].copy_from_slice(&(IPV4_HEADER_MIN_SIZE + (payload_len as u16)).to_be_bytes());
+        buf[2..4].copy_from_slice(&((self.compute_size() + payload_len) as u16).to_be_bytes());
 
         // Identification.
         buf[4..6].copy_from_slice(&self.identification.to_be_bytes());
@@ -285,8 +301,11 @@ impl Ipv4Header {
         // Destination Address.
         buf[16..20].copy_from_slice(&self.dst_addr.octets());
 
+        // Options.
+        self.options.serialize(options_buf);
+
         // Header Checksum.
-        let checksum: u16 = Self::compute_checksum(buf);
+        let checksum: u16 = Self::compute_checksum_parts(buf, options_buf);
         buf[10..12].copy_from_slice(&checksum.to_be_bytes());
     }
 
@@ -305,10 +324,47 @@ impl Ipv4Header {
         self.protocol
     }
 
-    /// Computes the checksum of the target IPv4 header.
-    pub fn compute_checksum(buf: &[u8]) -> u16 {
-        let mut state: u32 = 0xffff;
+    /// Returns the identification field stored in the target IPv4 header.
+    pub fn get_identification(&self) -> u16 {
+        self.identification
+    }
+
+    /// Sets the identification field in the target IPv4 header.
+    pub fn set_identification(&mut self, identification: u16) {
+        self.identification = identification;
+    }
+
+    /// Returns the explicit congestion notification field stored in the target IPv4 header.
+    pub fn get_ecn(&self) -> u8 {
+        self.ecn
+    }
 
+    /// Sets the explicit congestion notification field in the target IPv4 header.
+    pub fn set_ecn(&mut self, ecn: u8) {
+        debug_assert!(ecn <= IPV4_ECN_CE);
+        self.ecn = ecn;
+    }
+
+    /// Returns the options field stored in the target IPv4 header.
+    pub fn get_options(&self) -> &Ipv4Options {
+        &self.options
+    }
+
+    /// Sets the options field in the target IPv4 header, and resizes the header to fit them.
+    ///
+    /// If the options carry a source route that was not followed yet, the datagram is first sent to the next hop of
+    /// the route, and the destination that this header held so far becomes the last hop of the route (see RFC 791,
+    /// Section 3.1).
+    pub fn set_options(&mut self, mut options: Ipv4Options) {
+        if let Some(first_hop) = options.route_through_first_hop(self.dst_addr) {
+            self.dst_addr = first_hop;
+        }
+        self.options = options;
+        self.ihl = IPV4_IHL_NO_OPTIONS + (options.compute_size() / 4) as u8;
+    }
+
+    /// Computes the checksum of the target IPv4 header. The header may carry options.
+    pub fn compute_checksum(buf: &[u8]) -> u16 {
         // Do not compute checksum if buffer is too small.
         if buf.len() < IPV4_HEADER_MIN_SIZE as usize {
             // This should not happen by construction. If it does, log it.
@@ -316,6 +372,14 @@ impl Ipv4Header {
             return 0;
         }
 
+        let (buf, options_buf): (&[u8], &[u8]) = buf.split_at(IPV4_HEADER_MIN_SIZE as usize);
+        Self::compute_checksum_parts(buf, options_buf)
+    }
+
+    /// Computes the checksum of an IPv4 header, given its fixed part and its options.
+    fn compute_checksum_parts(buf: &[u8], options_buf: &[u8]) -> u16 {
+        let mut state: u32 = 0xffff;
+
         for i in 0..5 {
             state += u16::from_be_bytes([buf[2 * i], buf[2 * i + 1]]) as u32;
         }
@@ -324,6 +388,9 @@ impl Ipv4Header {
         for i in 6..10 {
             state += u16::from_be_bytes([buf[2 * i], buf[2 * i + 1]]) as u32;
         }
+        for word in options_buf.chunks_exact(2) {
+            state += u16::from_be_bytes([word[0], word[1]]) as u32;
+        }
         while state > 0xffff {
             state -= 0xffff;
         }
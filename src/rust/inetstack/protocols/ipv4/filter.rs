@@ -0,0 +1,73 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use ::std::net::Ipv4Addr;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// IP-Level Packet Filter
+///
+/// Decides which incoming packets are handed over to the transport protocols, based on their source address and, for
+/// TCP and UDP, their source port. Packets that are not admitted are silently dropped and counted.
+pub struct PacketFilter {
+    /// Returns `true` if packets from the given source should be admitted.
+    predicate: Box<dyn Fn(Ipv4Addr, Option<u16>) -> bool>,
+    /// Number of packets dropped by this filter.
+    dropped: u64,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+impl PacketFilter {
+    /// Creates a packet filter that admits the packets for which `predicate` returns `true`. The predicate is given the
+    /// source address of a packet and, for TCP and UDP, its source port.
+    pub fn new<F>(predicate: F) -> Self
+    where
+        F: Fn(Ipv4Addr, Option<u16>) -> bool + 'static,
+    {
+        Self {
+            predicate: Box::new(predicate),
+            dropped: 0,
+        }
+    }
+
+    /// Creates a packet filter that only admits packets from the given sources. A source without a port matches any
+    /// port.
+    pub fn allowlist(sources: Vec<(Ipv4Addr, Option<u16>)>) -> Self {
+        Self::new(move |addr, port| Self::matches(&sources, addr, port))
+    }
+
+    /// Creates a packet filter that admits packets from any source but the given ones. A source without a port
+    /// matches any port.
+    pub fn denylist(sources: Vec<(Ipv4Addr, Option<u16>)>) -> Self {
+        Self::new(move |addr, port| !Self::matches(&sources, addr, port))
+    }
+
+    /// Checks if a packet from the given source should be admitted, counting it as dropped otherwise.
+    pub fn admits(&mut self, addr: Ipv4Addr, port: Option<u16>) -> bool {
+        if (self.predicate)(addr, port) {
+            true
+        } else {
+            self.dropped += 1;
+            false
+        }
+    }
+
+    /// Returns the number of packets dropped by this filter.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Checks if a source matches any of the given ones.
+    fn matches(sources: &[(Ipv4Addr, Option<u16>)], addr: Ipv4Addr, port: Option<u16>) -> bool {
+        sources.iter().any(|&(a, p)| a == addr && (p.is_none() || p == port))
+    }
+}
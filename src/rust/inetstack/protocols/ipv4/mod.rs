@@ -2,6 +2,9 @@
 // Licensed under the MIT license.
 
 mod datagram;
+mod filter;
+mod options;
+mod routing;
 
 #[cfg(test)]
 mod tests;
@@ -10,8 +13,24 @@ mod tests;
 // Exports
 //==============================================================================
 
-pub use self::datagram::{
-    Ipv4Header,
-    IPV4_HEADER_MIN_SIZE,
-    IPV4_HEADER_MAX_SIZE,
+pub use self::{
+    datagram::{
+        Ipv4Header,
+        IPV4_ECN_CE,
+        IPV4_ECN_ECT0,
+        IPV4_ECN_NOT_ECT,
+        IPV4_HEADER_MAX_SIZE,
+        IPV4_HEADER_MIN_SIZE,
+    },
+    filter::PacketFilter,
+    options::{
+        Ipv4Option,
+        Ipv4Options,
+        Ipv4Route,
+        IPV4_OPTIONS_MAX_SIZE,
+    },
+    routing::{
+        RouteEntry,
+        RoutingTable,
+    },
 };
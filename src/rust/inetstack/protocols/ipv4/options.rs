@@ -0,0 +1,341 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::fail::Fail;
+use ::libc::{
+    EBADMSG,
+    EMSGSIZE,
+};
+use ::std::net::Ipv4Addr;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Maximum size of the options field of an IPv4 header (in bytes).
+pub const IPV4_OPTIONS_MAX_SIZE: usize = 40;
+
+/// Option Type: End of Option List.
+const IPV4_OPTION_EOL: u8 = 0;
+
+/// Option Type: No Operation.
+const IPV4_OPTION_NOP: u8 = 1;
+
+/// Option Type: Record Route.
+const IPV4_OPTION_RR: u8 = 7;
+
+/// Option Type: Loose Source and Record Route.
+const IPV4_OPTION_LSRR: u8 = 131;
+
+/// Option Type: Strict Source and Record Route.
+const IPV4_OPTION_SSRR: u8 = 137;
+
+/// Size of the type, length and pointer fields of a route option (in bytes).
+const IPV4_ROUTE_OPTION_HEADER_SIZE: usize = 3;
+
+/// Smallest legal value for the pointer field of a route option, which points at its first address.
+const IPV4_ROUTE_OPTION_MIN_POINTER: u8 = 4;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Addresses that a route option carries, along with the position of the next one to visit or record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv4Route {
+    /// Addresses of the route. Entries before [Self::next] were already visited or recorded.
+    pub addrs: Vec<Ipv4Addr>,
+    /// Index of the next address to visit or record. The route is complete once this reaches the end.
+    pub next: usize,
+}
+
+/// An option of an IPv4 header (see RFC 791, Section 3.1).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ipv4Option {
+    /// No Operation (type 1), which aligns the options that follow.
+    Nop,
+    /// Loose Source and Record Route (type 131). The datagram goes through the listed hops, and possibly others.
+    LooseSourceRoute(Ipv4Route),
+    /// Strict Source and Record Route (type 137). The datagram goes through the listed hops, and only them.
+    StrictSourceRoute(Ipv4Route),
+    /// Record Route (type 7). Each hop records its address in the free slots.
+    RecordRoute(Ipv4Route),
+}
+
+/// Options field of an IPv4 header. Options are kept in their wire format, so that headers remain plain values, and
+/// options that are not supported are carried along untouched.
+#[derive(Clone, Copy)]
+pub struct Ipv4Options {
+    /// Options in wire format, without trailing padding.
+    bytes: [u8; IPV4_OPTIONS_MAX_SIZE],
+    /// Number of bytes in use.
+    len: u8,
+}
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+/// Associated Functions for IPv4 Routes
+impl Ipv4Route {
+    /// Creates a route through `addrs` that was not visited yet, or that has free slots to record `addrs.len()` hops.
+    pub fn new(addrs: Vec<Ipv4Addr>) -> Self {
+        Self { addrs, next: 0 }
+    }
+
+    /// Checks if every address in the target route was visited or recorded.
+    pub fn is_complete(&self) -> bool {
+        self.next >= self.addrs.len()
+    }
+
+    /// Returns the next address to visit, if any.
+    pub fn next_hop(&self) -> Option<Ipv4Addr> {
+        self.addrs.get(self.next).copied()
+    }
+
+    /// Records `addr` in the next free slot of the target route, if any is left.
+    pub fn record(&mut self, addr: Ipv4Addr) {
+        if let Some(slot) = self.addrs.get_mut(self.next) {
+            *slot = addr;
+            self.next += 1;
+        }
+    }
+
+    /// Parses the route option that `buf` holds, which starts at its type field.
+    fn parse(buf: &[u8]) -> Result<Self, Fail> {
+        let len: usize = buf.len();
+        if len < IPV4_ROUTE_OPTION_HEADER_SIZE || (len - IPV4_ROUTE_OPTION_HEADER_SIZE) & 3 != 0 {
+            return Err(Fail::new(EBADMSG, "ipv4 route option has invalid length"));
+        }
+        let pointer: u8 = buf[2];
+        if pointer < IPV4_ROUTE_OPTION_MIN_POINTER || pointer & 3 != 0 || pointer as usize > len + 1 {
+            return Err(Fail::new(EBADMSG, "ipv4 route option has invalid pointer"));
+        }
+        let addrs: Vec<Ipv4Addr> = buf[IPV4_ROUTE_OPTION_HEADER_SIZE..]
+            .chunks_exact(4)
+            .map(|octets| Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+            .collect();
+        let next: usize = ((pointer - IPV4_ROUTE_OPTION_MIN_POINTER) / 4) as usize;
+        Ok(Self { addrs, next })
+    }
+
+    /// Serializes the target route as an option of type `option_type` at the end of `buf`.
+    fn serialize(&self, option_type: u8, buf: &mut Vec<u8>) {
+        buf.push(option_type);
+        buf.push((IPV4_ROUTE_OPTION_HEADER_SIZE + 4 * self.addrs.len()) as u8);
+        buf.push(IPV4_ROUTE_OPTION_MIN_POINTER + 4 * self.next as u8);
+        for addr in &self.addrs {
+            buf.extend_from_slice(&addr.octets());
+        }
+    }
+}
+
+/// Associated Functions for IPv4 Options
+impl Ipv4Options {
+    /// Creates an empty options field.
+    pub fn new() -> Self {
+        Self {
+            bytes: [0; IPV4_OPTIONS_MAX_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Encodes `options` into an options field. This fails with `EMSGSIZE` if they do not fit in an IPv4 header.
+    pub fn from_options(options: &[Ipv4Option]) -> Result<Self, Fail> {
+        let mut buf: Vec<u8> = Vec::with_capacity(IPV4_OPTIONS_MAX_SIZE);
+        for option in options {
+            match option {
+                Ipv4Option::Nop => buf.push(IPV4_OPTION_NOP),
+                Ipv4Option::LooseSourceRoute(route) => route.serialize(IPV4_OPTION_LSRR, &mut buf),
+                Ipv4Option::StrictSourceRoute(route) => route.serialize(IPV4_OPTION_SSRR, &mut buf),
+                Ipv4Option::RecordRoute(route) => route.serialize(IPV4_OPTION_RR, &mut buf),
+            }
+        }
+        if buf.len() > IPV4_OPTIONS_MAX_SIZE {
+            return Err(Fail::new(EMSGSIZE, "ipv4 options do not fit in header"));
+        }
+        let mut bytes: [u8; IPV4_OPTIONS_MAX_SIZE] = [0; IPV4_OPTIONS_MAX_SIZE];
+        bytes[..buf.len()].copy_from_slice(&buf);
+        Ok(Self {
+            bytes,
+            len: buf.len() as u8,
+        })
+    }
+
+    /// Parses the options field of an IPv4 header. Options that are not supported are kept, but are still checked to
+    /// be well formed.
+    pub fn parse(buf: &[u8]) -> Result<Self, Fail> {
+        if buf.len() > IPV4_OPTIONS_MAX_SIZE {
+            return Err(Fail::new(EBADMSG, "ipv4 options too large"));
+        }
+        let mut offset: usize = 0;
+        while offset < buf.len() {
+            let option_len: usize = match buf[offset] {
+                IPV4_OPTION_EOL => break,
+                IPV4_OPTION_NOP => 1,
+                option_type => {
+                    if offset + 1 >= buf.len() || (buf[offset + 1] as usize) < 2 {
+                        return Err(Fail::new(EBADMSG, "ipv4 option has invalid length"));
+                    }
+                    let option_len: usize = buf[offset + 1] as usize;
+                    if offset + option_len > buf.len() {
+                        return Err(Fail::new(EBADMSG, "ipv4 option does not fit in header"));
+                    }
+                    if let IPV4_OPTION_RR | IPV4_OPTION_LSRR | IPV4_OPTION_SSRR = option_type {
+                        Ipv4Route::parse(&buf[offset..(offset + option_len)])?;
+                    }
+                    option_len
+                },
+            };
+            offset += option_len;
+        }
+        let mut bytes: [u8; IPV4_OPTIONS_MAX_SIZE] = [0; IPV4_OPTIONS_MAX_SIZE];
+        bytes[..offset].copy_from_slice(&buf[..offset]);
+        Ok(Self {
+            bytes,
+            len: offset as u8,
+        })
+    }
+
+    /// Checks if the target options field holds no options.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Computes the size of the target options field once serialized, including padding (in bytes).
+    pub fn compute_size(&self) -> usize {
+        (self.len as usize + 3) & !3
+    }
+
+    /// Serializes the target options field, padding it with end of option list markers.
+    pub fn serialize(&self, buf: &mut [u8]) {
+        let len: usize = self.len as usize;
+        buf[..len].copy_from_slice(&self.bytes[..len]);
+        buf[len..].fill(IPV4_OPTION_EOL);
+    }
+
+    /// Decodes the supported options in the target options field. Other options are skipped.
+    pub fn options(&self) -> Vec<Ipv4Option> {
+        let buf: &[u8] = &self.bytes[..self.len as usize];
+        let mut options: Vec<Ipv4Option> = Vec::new();
+        let mut offset: usize = 0;
+        while offset < buf.len() {
+            // Options were checked upon parsing or encoding, so they are well formed.
+            let option_len: usize = match buf[offset] {
+                IPV4_OPTION_NOP => {
+                    options.push(Ipv4Option::Nop);
+                    1
+                },
+                option_type => {
+                    let option_len: usize = buf[offset + 1] as usize;
+                    let option_buf: &[u8] = &buf[offset..(offset + option_len)];
+                    match option_type {
+                        IPV4_OPTION_RR => options.push(Ipv4Option::RecordRoute(Ipv4Route::parse(option_buf).unwrap())),
+                        IPV4_OPTION_LSRR => {
+                            options.push(Ipv4Option::LooseSourceRoute(Ipv4Route::parse(option_buf).unwrap()))
+                        },
+                        IPV4_OPTION_SSRR => {
+                            options.push(Ipv4Option::StrictSourceRoute(Ipv4Route::parse(option_buf).unwrap()))
+                        },
+                        _ => {},
+                    }
+                    option_len
+                },
+            };
+            offset += option_len;
+        }
+        options
+    }
+
+    /// Returns the source route in the target options field, if any, and whether it is strict.
+    pub fn source_route(&self) -> Option<(Ipv4Route, bool)> {
+        self.options().into_iter().find_map(|option| match option {
+            Ipv4Option::LooseSourceRoute(route) => Some((route, false)),
+            Ipv4Option::StrictSourceRoute(route) => Some((route, true)),
+            _ => None,
+        })
+    }
+
+    /// Sends a datagram for `dst` along the source route in the target options field, if that route was not followed
+    /// yet. The first hop of the route is taken out and returned, so that it becomes the destination of the datagram,
+    /// and `dst` becomes the last hop. Since the route keeps its length, the options field keeps its size.
+    pub fn route_through_first_hop(&mut self, dst: Ipv4Addr) -> Option<Ipv4Addr> {
+        let len: usize = self.len as usize;
+        let mut offset: usize = 0;
+        while offset < len {
+            let option_type: u8 = self.bytes[offset];
+            if option_type == IPV4_OPTION_NOP {
+                offset += 1;
+                continue;
+            }
+            let option_len: usize = self.bytes[offset + 1] as usize;
+            if let IPV4_OPTION_LSRR | IPV4_OPTION_SSRR = option_type {
+                let option_buf: &mut [u8] = &mut self.bytes[offset..(offset + option_len)];
+                if option_len == IPV4_ROUTE_OPTION_HEADER_SIZE || option_buf[2] != IPV4_ROUTE_OPTION_MIN_POINTER {
+                    return None;
+                }
+                let addrs: &mut [u8] = &mut option_buf[IPV4_ROUTE_OPTION_HEADER_SIZE..];
+                let first_hop: Ipv4Addr = Ipv4Addr::new(addrs[0], addrs[1], addrs[2], addrs[3]);
+                addrs.rotate_left(4);
+                let last: usize = addrs.len() - 4;
+                addrs[last..].copy_from_slice(&dst.octets());
+                return Some(first_hop);
+            }
+            offset += option_len;
+        }
+        None
+    }
+
+    /// Builds the options for a reply to a datagram that carried the target options, as RFC 1122 asks for echo
+    /// replies (Section 3.2.2.6). A record route goes on recording, starting with `local`, so that it covers the whole
+    /// round trip. A source route that was followed all the way is reversed, so that the reply goes back the same way
+    /// once it is set on a header whose destination is the sender. Other options are left out.
+    pub fn reply_options(&self, local: Ipv4Addr) -> Vec<Ipv4Option> {
+        let mut options: Vec<Ipv4Option> = Vec::new();
+        for option in self.options() {
+            match option {
+                Ipv4Option::RecordRoute(mut route) => {
+                    route.record(local);
+                    options.push(Ipv4Option::RecordRoute(route));
+                },
+                Ipv4Option::LooseSourceRoute(route) if route.is_complete() => {
+                    options.push(Ipv4Option::LooseSourceRoute(Self::reverse_route(route)))
+                },
+                Ipv4Option::StrictSourceRoute(route) if route.is_complete() => {
+                    options.push(Ipv4Option::StrictSourceRoute(Self::reverse_route(route)))
+                },
+                _ => {},
+            }
+        }
+        options
+    }
+
+    /// Reverses a source route that was followed all the way.
+    fn reverse_route(mut route: Ipv4Route) -> Ipv4Route {
+        route.addrs.reverse();
+        route.next = 0;
+        route
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Debug Trait Implementation for IPv4 Options
+impl std::fmt::Debug for Ipv4Options {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.options()).finish()
+    }
+}
+
+/// Default Trait Implementation for IPv4 Options
+impl Default for Ipv4Options {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::ip::IpProtocol,
+    runtime::fail::Fail,
+};
+use ::std::{
+    cell::RefCell,
+    collections::hash_map::DefaultHasher,
+    hash::{
+        Hash,
+        Hasher,
+    },
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
+    rc::Rc,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// A next hop of a route, along with its share of the flows that take the route.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RouteEntry {
+    /// Gateway that packets are handed to.
+    pub gateway: Ipv4Addr,
+    /// Share of the flows that go through this gateway, relative to the other next hops of the route.
+    pub weight: u32,
+}
+
+/// Route to a destination network, with one or more next hops of equal cost.
+struct Route {
+    net: Ipv4Addr,
+    prefix: u8,
+    entries: Vec<RouteEntry>,
+}
+
+/// Table of the routes to destinations that are not on the local link. Destinations that no route covers are reached
+/// directly. When a route has several next hops, each flow sticks to one of them, and flows spread across them in
+/// proportion to their weights (ECMP). Clones share the same table.
+#[derive(Clone, Default)]
+pub struct RoutingTable {
+    routes: Rc<RefCell<Vec<Route>>>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl RoutingTable {
+    /// Adds a route to the network `net`/`prefix` through `gateways`, replacing the route to that network if there is
+    /// one already.
+    pub fn add_ecmp_route(&self, net: Ipv4Addr, prefix: u8, gateways: Vec<RouteEntry>) -> Result<(), Fail> {
+        if prefix > 32 {
+            let cause: String = format!("invalid prefix length (prefix={:?})", prefix);
+            error!("add_ecmp_route(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if gateways.is_empty() || gateways.iter().any(|entry| entry.weight == 0) {
+            let cause: String = format!(
+                "route needs next hops with non-zero weights (net={:?}/{:?})",
+                net, prefix
+            );
+            error!("add_ecmp_route(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        let net: Ipv4Addr = Ipv4Addr::from(u32::from(net) & Self::netmask(prefix));
+        let mut routes = self.routes.borrow_mut();
+        routes.retain(|route| route.net != net || route.prefix != prefix);
+        routes.push(Route {
+            net,
+            prefix,
+            entries: gateways,
+        });
+        Ok(())
+    }
+
+    /// Selects the next hop of the flow between `local` and `remote`. Routes with longer prefixes win over shorter ones.
+    /// Among the next hops of a route, the flow goes through the one that scores highest in weighted rendezvous hashing,
+    /// so that all packets of the flow take the same path, and only the flows of a next hop move if it is removed.
+    pub fn next_hop(&self, protocol: IpProtocol, local: SocketAddrV4, remote: SocketAddrV4) -> Ipv4Addr {
+        let routes = self.routes.borrow();
+        let route: Option<&Route> = routes
+            .iter()
+            .filter(|route| u32::from(*remote.ip()) & Self::netmask(route.prefix) == u32::from(route.net))
+            .max_by_key(|route| route.prefix);
+        let route: &Route = match route {
+            Some(route) => route,
+            None => return *remote.ip(),
+        };
+
+        route
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut hasher: DefaultHasher = DefaultHasher::new();
+                (protocol as u8, local, remote, entry.gateway).hash(&mut hasher);
+                // Map the hash to (0, 1], and weigh it so that the chances of each next hop scoring highest follow
+                // their weights.
+                let unit: f64 = ((hasher.finish() >> 11) + 1) as f64 / (1u64 << 53) as f64;
+                (entry.gateway, entry.weight as f64 / -unit.ln())
+            })
+            .fold(None, |best: Option<(Ipv4Addr, f64)>, candidate| match best {
+                Some(best) if best.1 >= candidate.1 => Some(best),
+                _ => Some(candidate),
+            })
+            .map(|(gateway, _)| gateway)
+            .unwrap_or(*remote.ip())
+    }
+
+    /// Returns the network mask of a prefix length.
+    fn netmask(prefix: u8) -> u32 {
+        match prefix {
+            0 => 0,
+            prefix => u32::MAX << (32 - prefix as u32),
+        }
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RouteEntry,
+        RoutingTable,
+    };
+    use crate::inetstack::protocols::ip::IpProtocol;
+    use ::anyhow::Result;
+    use ::std::net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    };
+
+    /// Tests that the most specific route wins, and that destinations that no route covers are reached directly.
+    #[test]
+    fn test_routing_table_longest_prefix_match() -> Result<()> {
+        let routes: RoutingTable = RoutingTable::default();
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 49152);
+        let gateway: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 254);
+        let specific_gateway: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 253);
+        routes.add_ecmp_route(Ipv4Addr::new(10, 1, 2, 3), 8, vec![RouteEntry { gateway, weight: 1 }])?;
+        routes.add_ecmp_route(
+            Ipv4Addr::new(10, 1, 0, 0),
+            16,
+            vec![RouteEntry {
+                gateway: specific_gateway,
+                weight: 1,
+            }],
+        )?;
+
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(10, 2, 0, 1), 80);
+        crate::ensure_eq!(routes.next_hop(IpProtocol::TCP, local, remote), gateway);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(10, 1, 0, 1), 80);
+        crate::ensure_eq!(routes.next_hop(IpProtocol::TCP, local, remote), specific_gateway);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 80);
+        crate::ensure_eq!(routes.next_hop(IpProtocol::TCP, local, remote), *remote.ip());
+
+        // Routes without next hops, or with next hops that take no flows, are rejected.
+        crate::ensure_eq!(routes.add_ecmp_route(Ipv4Addr::UNSPECIFIED, 0, vec![]).is_err(), true);
+        crate::ensure_eq!(
+            routes
+                .add_ecmp_route(Ipv4Addr::UNSPECIFIED, 0, vec![RouteEntry { gateway, weight: 0 }])
+                .is_err(),
+            true
+        );
+
+        Ok(())
+    }
+
+    /// Tests that flows spread across next hops in proportion to their weights, and that each flow sticks to its next
+    /// hop.
+    #[test]
+    fn test_routing_table_weighted_next_hops() -> Result<()> {
+        const NUM_FLOWS: u16 = 4000;
+        let routes: RoutingTable = RoutingTable::default();
+        let local: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 80);
+        let gateways: [Ipv4Addr; 2] = [Ipv4Addr::new(192, 168, 1, 253), Ipv4Addr::new(192, 168, 1, 254)];
+        routes.add_ecmp_route(
+            Ipv4Addr::UNSPECIFIED,
+            0,
+            vec![
+                RouteEntry {
+                    gateway: gateways[0],
+                    weight: 1,
+                },
+                RouteEntry {
+                    gateway: gateways[1],
+                    weight: 3,
+                },
+            ],
+        )?;
+
+        let mut counts: [u16; 2] = [0; 2];
+        for port in 0..NUM_FLOWS {
+            let local: SocketAddrV4 = SocketAddrV4::new(local, 49152 + port);
+            let next_hop: Ipv4Addr = routes.next_hop(IpProtocol::TCP, local, remote);
+            crate::ensure_eq!(routes.next_hop(IpProtocol::TCP, local, remote), next_hop);
+            match gateways.iter().position(|gateway| *gateway == next_hop) {
+                Some(i) => counts[i] += 1,
+                None => anyhow::bail!("flow should go through one of the gateways (next_hop={:?})", next_hop),
+            }
+        }
+        // The second gateway takes three quarters of the flows, give or take a few percent.
+        crate::ensure_eq!(counts[1] > NUM_FLOWS / 10 * 7 && counts[1] < NUM_FLOWS / 10 * 8, true);
+
+        Ok(())
+    }
+}
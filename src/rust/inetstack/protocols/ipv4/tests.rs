@@ -9,7 +9,12 @@ use crate::{
     inetstack::{
         protocols::{
             ip::IpProtocol,
-            ipv4::Ipv4Header,
+            ipv4::{
+                Ipv4Header,
+                Ipv4Option,
+                Ipv4Options,
+                Ipv4Route,
+            },
         },
         test_helpers::{
             ALICE_IPV4,
@@ -135,6 +140,57 @@ fn test_ipv4_header_parse_good() -> Result<()> {
     Ok(())
 }
 
+/// Encodes IPv4 options, and parses them back.
+#[test]
+fn test_ipv4_options_roundtrip() -> Result<()> {
+    let route: Ipv4Route = Ipv4Route {
+        addrs: vec![ALICE_IPV4, BOB_IPV4],
+        next: 1,
+    };
+    let options: Vec<Ipv4Option> = vec![
+        Ipv4Option::Nop,
+        Ipv4Option::LooseSourceRoute(route.clone()),
+        Ipv4Option::RecordRoute(route),
+    ];
+    let encoded: Ipv4Options = Ipv4Options::from_options(&options)?;
+    // One byte for the no operation, and eleven for each route, padded to a multiple of four.
+    crate::ensure_eq!(encoded.compute_size(), 24);
+
+    let mut buf: [u8; 24] = [0xff; 24];
+    encoded.serialize(&mut buf);
+    crate::ensure_eq!(&buf[..4], &[1, 131, 11, 8]);
+    crate::ensure_eq!(buf[23], 0);
+    let decoded: Ipv4Options = Ipv4Options::parse(&buf)?;
+    crate::ensure_eq!(decoded.options(), options);
+
+    Ok(())
+}
+
+/// Serializes an IPv4 header that carries a source route, and parses it back.
+#[test]
+fn test_ipv4_header_source_route() -> Result<()> {
+    const PAYLOAD_SIZE: usize = 8;
+    let hop: std::net::Ipv4Addr = std::net::Ipv4Addr::new(192, 168, 1, 1);
+    let route: Ipv4Route = Ipv4Route::new(vec![hop]);
+    let mut ipv4_hdr: Ipv4Header = Ipv4Header::new(ALICE_IPV4, BOB_IPV4, IpProtocol::UDP);
+    ipv4_hdr.set_options(Ipv4Options::from_options(&[Ipv4Option::StrictSourceRoute(route)])?);
+
+    // The datagram goes to the first hop, and the final destination becomes the last hop of the route.
+    crate::ensure_eq!(ipv4_hdr.get_dest_addr(), hop);
+    crate::ensure_eq!(ipv4_hdr.compute_size(), 28);
+
+    let mut buf: [u8; 28 + PAYLOAD_SIZE] = [0; 28 + PAYLOAD_SIZE];
+    ipv4_hdr.serialize(&mut buf[..28], PAYLOAD_SIZE);
+    let buf_bytes: DemiBuffer = DemiBuffer::from_slice(&buf)?;
+    let (parsed_hdr, payload) = Ipv4Header::parse(buf_bytes)?;
+    crate::ensure_eq!(payload.len(), PAYLOAD_SIZE);
+    crate::ensure_eq!(parsed_hdr.get_dest_addr(), hop);
+    let expected: Ipv4Route = Ipv4Route::new(vec![BOB_IPV4]);
+    crate::ensure_eq!(parsed_hdr.get_options().source_route(), Some((expected, true)));
+
+    Ok(())
+}
+
 //==============================================================================
 // Unit-Tests for Invalid Path
 //==============================================================================
@@ -503,7 +559,7 @@ fn test_ipv4_header_parse_unsupported_ecn() -> Result<()> {
         };
 
         match Ipv4Header::parse(buf_bytes) {
-            Ok(_) => {},
+            Ok((header, _)) => crate::ensure_eq!(header.get_ecn(), ecn),
             Err(_) => anyhow::bail!("ecn field should be ignored (ecn={:?})", ecn),
         };
     }
@@ -636,3 +692,18 @@ fn test_ipv4_header_parse_unsupported_protocol() -> Result<()> {
 
     Ok(())
 }
+
+/// Parses IPv4 options that are not well formed.
+#[test]
+fn test_ipv4_options_parse_invalid() -> Result<()> {
+    // Option that claims to be larger than the options field.
+    crate::ensure_eq!(Ipv4Options::parse(&[7, 8, 4, 0]).is_err(), true);
+    // Option whose length is too small to hold its own type and length.
+    crate::ensure_eq!(Ipv4Options::parse(&[7, 1, 4, 0]).is_err(), true);
+    // Route whose pointer goes before its first address.
+    crate::ensure_eq!(Ipv4Options::parse(&[131, 7, 3, 0, 0, 0, 0, 0]).is_err(), true);
+    // Route whose pointer goes past its end.
+    crate::ensure_eq!(Ipv4Options::parse(&[137, 7, 12, 0, 0, 0, 0, 0]).is_err(), true);
+
+    Ok(())
+}
@@ -1,14 +1,30 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+#[cfg(feature = "dhcp")]
+use crate::inetstack::protocols::dhcp::{
+    DhcpLease,
+    DhcpPeer,
+    DHCP_CLIENT_PORT,
+};
 use crate::{
     inetstack::protocols::{
         arp::ArpPeer,
         icmpv4::Icmpv4Peer,
-        ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ip::{
+            EphemeralPorts,
+            IpProtocol,
+        },
+        ipv4::{
+            Ipv4Header,
+            PacketFilter,
+            IPV4_HEADER_MIN_SIZE,
+        },
         queue::InetQueue,
-        tcp::TcpPeer,
+        tcp::{
+            TcpPeer,
+            MIN_TCP_HEADER_SIZE,
+        },
         udp::UdpPeer,
     },
     runtime::{
@@ -27,7 +43,14 @@ use crate::{
     },
     scheduler::scheduler::Scheduler,
 };
-use ::libc::ENOTCONN;
+use ::libc::{
+    ENOTCONN,
+    ENOTSUP,
+};
+use ::rand::{
+    prelude::SmallRng,
+    SeedableRng,
+};
 use ::std::{
     cell::RefCell,
     future::Future,
@@ -39,38 +62,49 @@ use ::std::{
 #[cfg(test)]
 use crate::runtime::QDesc;
 
-pub struct Peer<const N: usize> {
+pub struct Peer {
     local_ipv4_addr: Ipv4Addr,
-    icmpv4: Icmpv4Peer<N>,
-    pub tcp: TcpPeer<N>,
-    pub udp: UdpPeer<N>,
+    /// Clock that received datagrams are stamped with, unless the network runtime already did so.
+    clock: TimerRc,
+    packet_filter: Option<PacketFilter>,
+    /// Ephemeral ports, shared by TCP and UDP.
+    ephemeral_ports: Rc<RefCell<EphemeralPorts>>,
+    icmpv4: Icmpv4Peer,
+    #[cfg(feature = "dhcp")]
+    dhcp: DhcpPeer,
+    pub tcp: TcpPeer,
+    pub udp: UdpPeer,
 }
 
-impl<const N: usize> Peer<N> {
+impl Peer {
     pub fn new(
-        rt: Rc<dyn NetworkRuntime<N>>,
+        rt: Rc<dyn NetworkRuntime>,
         scheduler: Scheduler,
-        qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>>,
+        qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
         clock: TimerRc,
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         udp_config: UdpConfig,
-        tcp_config: TcpConfig,
-        arp: ArpPeer<N>,
+        mut tcp_config: TcpConfig,
+        arp: ArpPeer,
         rng_seed: [u8; 32],
+        packet_filter: Option<PacketFilter>,
     ) -> Result<Self, Fail> {
+        let mut rng: SmallRng = SmallRng::from_seed(rng_seed);
+        let ephemeral_ports: Rc<RefCell<EphemeralPorts>> = Rc::new(RefCell::new(EphemeralPorts::new(&mut rng)));
         let udp_offload_checksum: bool = udp_config.get_tx_checksum_offload();
-        let udp: UdpPeer<N> = UdpPeer::new(
+        let udp: UdpPeer = UdpPeer::new(
             rt.clone(),
             scheduler.clone(),
             qtable.clone(),
-            rng_seed,
+            ephemeral_ports.clone(),
             local_link_addr,
             local_ipv4_addr,
             udp_offload_checksum,
+            udp_config.get_recv_queue_depth(),
             arp.clone(),
         )?;
-        let icmpv4: Icmpv4Peer<N> = Icmpv4Peer::new(
+        let icmpv4: Icmpv4Peer = Icmpv4Peer::new(
             rt.clone(),
             scheduler.clone(),
             clock.clone(),
@@ -79,7 +113,22 @@ impl<const N: usize> Peer<N> {
             arp.clone(),
             rng_seed,
         )?;
-        let tcp: TcpPeer<N> = TcpPeer::new(
+        #[cfg(feature = "dhcp")]
+        let dhcp: DhcpPeer = DhcpPeer::new(
+            rt.clone(),
+            scheduler.clone(),
+            clock.clone(),
+            local_link_addr,
+            arp.clone(),
+            udp_offload_checksum,
+            rng_seed,
+        );
+        // Full-sized segments must fit in the MTU of the NIC, along with their headers.
+        let max_mss: usize = rt
+            .mtu()
+            .saturating_sub(IPV4_HEADER_MIN_SIZE as usize + MIN_TCP_HEADER_SIZE);
+        tcp_config.clamp_advertised_mss(max_mss);
+        let tcp: TcpPeer = TcpPeer::new(
             rt.clone(),
             scheduler.clone(),
             qtable.clone(),
@@ -88,30 +137,115 @@ impl<const N: usize> Peer<N> {
             local_ipv4_addr,
             tcp_config,
             arp,
-            rng_seed,
+            ephemeral_ports.clone(),
         )?;
 
         Ok(Peer {
             local_ipv4_addr,
+            clock,
+            packet_filter,
+            ephemeral_ports,
             icmpv4,
+            #[cfg(feature = "dhcp")]
+            dhcp,
             tcp,
             udp,
         })
     }
 
-    pub fn receive(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
+    pub fn receive(&mut self, mut buf: DemiBuffer) -> Result<(), Fail> {
+        // Stamp the datagram with the time it was received at, so that it is handed over to the application along with
+        // its payload.
+        if buf.sw_timestamp().is_none() {
+            buf.set_sw_timestamp(Some(self.clock.now()));
+        }
         let (header, payload) = Ipv4Header::parse(buf)?;
         debug!("Ipv4 received {:?}", header);
-        if header.get_dest_addr() != self.local_ipv4_addr && !header.get_dest_addr().is_broadcast() {
+        // Multicast datagrams are only delivered to UDP sockets, which filter them by group membership.
+        let is_multicast: bool = header.get_dest_addr().is_multicast() && header.get_protocol() == IpProtocol::UDP;
+        if header.get_dest_addr() != self.local_ipv4_addr && !header.get_dest_addr().is_broadcast() && !is_multicast {
             return Err(Fail::new(ENOTCONN, "invalid destination address"));
         }
+        // A source route that was not followed all the way names more hops to go through, but we do not forward
+        // datagrams. A strict route whose next hop is ourselves is bogus, so we drop it without further notice.
+        if let Some((route, strict)) = header.get_options().source_route() {
+            if let Some(next_hop) = route.next_hop() {
+                if strict && next_hop == self.local_ipv4_addr {
+                    debug!("Dropped strict source routed datagram to local address {:?}", next_hop);
+                    return Ok(());
+                }
+                return Err(Fail::new(
+                    ENOTSUP,
+                    "forwarding of source routed datagrams is not supported",
+                ));
+            }
+        }
+        if let Some(packet_filter) = self.packet_filter.as_mut() {
+            // Only TCP and UDP have ports, and both place the source port at the start of their header.
+            let port: Option<u16> = match header.get_protocol() {
+                IpProtocol::TCP | IpProtocol::UDP if payload.len() >= 2 => {
+                    Some(u16::from_be_bytes([payload[0], payload[1]]))
+                },
+                _ => None,
+            };
+            if !packet_filter.admits(header.get_src_addr(), port) {
+                debug!(
+                    "Packet filter dropped packet from {:?}:{:?}",
+                    header.get_src_addr(),
+                    port
+                );
+                return Ok(());
+            }
+        }
         match header.get_protocol() {
             IpProtocol::ICMPv4 => self.icmpv4.receive(&header, payload),
             IpProtocol::TCP => self.tcp.receive(&header, payload),
-            IpProtocol::UDP => self.udp.do_receive(&header, payload),
+            IpProtocol::UDP => {
+                // Replies to the DHCP client go around UDP sockets, as they may be sent before we have an address.
+                #[cfg(feature = "dhcp")]
+                if self.dhcp.is_waiting()
+                    && payload.len() >= 4
+                    && u16::from_be_bytes([payload[2], payload[3]]) == DHCP_CLIENT_PORT
+                {
+                    return self.dhcp.receive(&header, payload);
+                }
+                self.udp.do_receive(&header, payload)
+            },
         }
     }
 
+    /// Changes the local IPv4 and link addresses. New sockets and connections use the new addresses, while established
+    /// connections keep their original ones.
+    pub fn set_local_identity(&mut self, local_ipv4_addr: Ipv4Addr, local_link_addr: MacAddress) {
+        self.local_ipv4_addr = local_ipv4_addr;
+        self.icmpv4.set_local_identity(local_ipv4_addr, local_link_addr);
+        #[cfg(feature = "dhcp")]
+        self.dhcp.set_local_identity(local_link_addr);
+        self.tcp.set_local_identity(local_ipv4_addr, local_link_addr);
+        self.udp.set_local_identity(local_ipv4_addr, local_link_addr);
+    }
+
+    /// Allocates an unused port in the ephemeral port range for the local address `ip`, which may also be the
+    /// unspecified address. The port is reserved right away, so that successive calls never return the same port.
+    pub fn alloc_ephemeral_port(&mut self, ip: Ipv4Addr) -> Result<u16, Fail> {
+        if !ip.is_unspecified() && ip != self.local_ipv4_addr {
+            return Err(Fail::new(libc::EADDRNOTAVAIL, "address is not a local address"));
+        }
+        self.ephemeral_ports.borrow_mut().alloc_any()
+    }
+
+    /// Restricts the ports that are allocated from the ephemeral port range to those from `first` to `last`.
+    pub fn set_ephemeral_port_range(&mut self, first: u16, last: u16) -> Result<(), Fail> {
+        self.ephemeral_ports.borrow_mut().set_range(first, last)
+    }
+
+    /// Returns the number of incoming packets dropped by the packet filter.
+    pub fn filtered_packets(&self) -> u64 {
+        self.packet_filter
+            .as_ref()
+            .map_or(0, |packet_filter| packet_filter.dropped())
+    }
+
     pub fn ping(
         &mut self,
         dest_ipv4_addr: Ipv4Addr,
@@ -119,10 +253,22 @@ impl<const N: usize> Peer<N> {
     ) -> impl Future<Output = Result<Duration, Fail>> {
         self.icmpv4.ping(dest_ipv4_addr, timeout)
     }
+
+    /// Acquires an IPv4 address through DHCP.
+    #[cfg(feature = "dhcp")]
+    pub fn dhcp_request(&mut self) -> impl Future<Output = Result<DhcpLease, Fail>> {
+        self.dhcp.request()
+    }
+
+    /// Returns the current DHCP lease, if any.
+    #[cfg(feature = "dhcp")]
+    pub fn dhcp_lease(&self) -> Option<DhcpLease> {
+        self.dhcp.get_lease()
+    }
 }
 
 #[cfg(test)]
-impl<const N: usize> Peer<N> {
+impl Peer {
     pub fn tcp_mss(&self, fd: QDesc) -> Result<usize, Fail> {
         self.tcp.remote_mss(fd)
     }
@@ -130,4 +276,12 @@ impl<const N: usize> Peer<N> {
     pub fn tcp_rto(&self, fd: QDesc) -> Result<Duration, Fail> {
         self.tcp.current_rto(fd)
     }
+
+    pub fn tcp_stats(&self, fd: QDesc) -> Result<super::tcp::TcpConnectionStats, Fail> {
+        self.tcp.stats(fd)
+    }
+
+    pub fn tcp_info(&self, fd: QDesc) -> Result<super::tcp::TcpInfo, Fail> {
+        self.tcp.info(fd)
+    }
 }
@@ -5,19 +5,27 @@ use super::{
 use crate::runtime::queue::{
     IoQueue,
     QType,
+    QueueInfo,
 };
 
 /// Per-queue metadata: Inet stack Control Block
-pub enum InetQueue<const N: usize> {
+pub enum InetQueue {
     Udp(UdpQueue),
-    Tcp(TcpQueue<N>),
+    Tcp(TcpQueue),
 }
 
-impl<const N: usize> IoQueue for InetQueue<N> {
+impl IoQueue for InetQueue {
     fn get_qtype(&self) -> QType {
         match self {
             Self::Udp(_) => QType::UdpSocket,
             Self::Tcp(_) => QType::TcpSocket,
         }
     }
+
+    fn describe(&self, info: &mut QueueInfo) {
+        match self {
+            Self::Udp(queue) => queue.describe(info),
+            Self::Tcp(queue) => queue.describe(info),
+        }
+    }
 }
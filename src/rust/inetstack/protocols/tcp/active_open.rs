@@ -3,7 +3,6 @@
 
 use crate::{
     inetstack::protocols::{
-        arp::ArpPeer,
         ethernet2::{
             EtherType2,
             Ethernet2Header,
@@ -11,36 +10,35 @@ use crate::{
         ip::IpProtocol,
         ipv4::Ipv4Header,
         tcp::{
+            auth::SegmentAuthenticator,
             constants::FALLBACK_MSS,
+            context::TcpContext,
             established::{
-                congestion_control::{
-                    self,
-                    CongestionControl,
-                },
                 ControlBlock,
+                HandshakeParameters,
             },
             segment::{
                 TcpHeader,
                 TcpOptions2,
                 TcpSegment,
             },
+            timestamps::{
+                find_timestamps,
+                timestamp_value,
+            },
+            trace::{
+                self,
+                ConnectionSpan,
+                ConnectionState,
+            },
             SeqNumber,
         },
     },
     runtime::{
         fail::Fail,
-        network::{
-            config::TcpConfig,
-            types::MacAddress,
-            NetworkRuntime,
-        },
         queue::BackgroundTask,
-        timer::TimerRc,
-    },
-    scheduler::{
-        Scheduler,
-        TaskHandle,
     },
+    scheduler::TaskHandle,
 };
 use ::libc::{
     ECONNREFUSED,
@@ -50,7 +48,10 @@ use ::std::{
     cell::RefCell,
     convert::TryInto,
     future::Future,
-    net::SocketAddrV4,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
     rc::Rc,
     task::{
         Context,
@@ -59,62 +60,67 @@ use ::std::{
     },
 };
 
-struct ConnectResult<const N: usize> {
+struct ConnectResult {
     waker: Option<Waker>,
-    result: Option<Result<ControlBlock<N>, Fail>>,
+    result: Option<Result<ControlBlock, Fail>>,
 }
 
-pub struct ActiveOpenSocket<const N: usize> {
+pub struct ActiveOpenSocket {
     local_isn: SeqNumber,
 
     local: SocketAddrV4,
     remote: SocketAddrV4,
+    // Host that segments to the remote are handed to.
+    next_hop: Ipv4Addr,
 
-    rt: Rc<dyn NetworkRuntime<N>>,
-    scheduler: Scheduler,
-    clock: TimerRc,
-    local_link_addr: MacAddress,
-    tcp_config: TcpConfig,
-    arp: ArpPeer<N>,
+    ctx: TcpContext,
+    authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+    span: ConnectionSpan,
 
     #[allow(unused)]
     handle: TaskHandle,
-    result: Rc<RefCell<ConnectResult<N>>>,
+    result: Rc<RefCell<ConnectResult>>,
 }
 
-impl<const N: usize> ActiveOpenSocket<N> {
+impl ActiveOpenSocket {
     pub fn new(
-        scheduler: Scheduler,
+        ctx: TcpContext,
         local_isn: SeqNumber,
         local: SocketAddrV4,
         remote: SocketAddrV4,
-        rt: Rc<dyn NetworkRuntime<N>>,
-        tcp_config: TcpConfig,
-        local_link_addr: MacAddress,
-        clock: TimerRc,
-        arp: ArpPeer<N>,
+        next_hop: Ipv4Addr,
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
     ) -> Self {
         let result = ConnectResult {
             waker: None,
             result: None,
         };
         let result = Rc::new(RefCell::new(result));
+        let span: ConnectionSpan = ConnectionSpan::new(local, remote);
+        {
+            let _span = span.enter();
+            trace::transition(
+                local,
+                remote,
+                ConnectionState::Closed,
+                ConnectionState::SynSent,
+                "connect",
+            );
+        }
 
         let future = Self::background(
             local_isn,
             local,
             remote,
-            rt.clone(),
-            clock.clone(),
-            local_link_addr,
-            tcp_config.clone(),
-            arp.clone(),
+            next_hop,
+            ctx.clone(),
+            authenticator.clone(),
             result.clone(),
         );
         let task: BackgroundTask =
             BackgroundTask::new(String::from("Inetstack::TCP::activeopen::background"), Box::pin(future));
 
-        let handle: TaskHandle = match scheduler.insert(task) {
+        let handle: TaskHandle = match ctx.scheduler.insert(task) {
             Some(handle) => handle,
             None => panic!("failed to insert task in the scheduler"),
         };
@@ -124,18 +130,16 @@ impl<const N: usize> ActiveOpenSocket<N> {
             local_isn,
             local,
             remote,
-            rt,
-            scheduler: scheduler.clone(),
-            clock,
-            local_link_addr,
-            tcp_config,
-            arp,
+            next_hop,
+            ctx,
+            authenticator,
+            span,
             handle,
             result,
         }
     }
 
-    pub fn poll_result(&mut self, context: &mut Context) -> Poll<Result<ControlBlock<N>, Fail>> {
+    pub fn poll_result(&mut self, context: &mut Context) -> Poll<Result<ControlBlock, Fail>> {
         let mut r = self.result.borrow_mut();
         match r.result.take() {
             None => {
@@ -146,7 +150,7 @@ impl<const N: usize> ActiveOpenSocket<N> {
         }
     }
 
-    fn set_result(&mut self, result: Result<ControlBlock<N>, Fail>) {
+    fn set_result(&mut self, result: Result<ControlBlock, Fail>) {
         let mut r = self.result.borrow_mut();
         if let Some(w) = r.waker.take() {
             w.wake()
@@ -155,6 +159,8 @@ impl<const N: usize> ActiveOpenSocket<N> {
     }
 
     pub fn receive(&mut self, header: &TcpHeader) {
+        let span: ConnectionSpan = self.span.clone();
+        let _span = span.enter();
         let expected_seq = self.local_isn + SeqNumber::from(1);
 
         // Bail if we didn't receive a ACK packet with the right sequence number.
@@ -164,6 +170,13 @@ impl<const N: usize> ActiveOpenSocket<N> {
 
         // Check if our peer is refusing our connection request.
         if header.rst {
+            trace::transition(
+                self.local,
+                self.remote,
+                ConnectionState::SynSent,
+                ConnectionState::Closed,
+                "RST_received",
+            );
             self.set_result(Err(Fail::new(ECONNREFUSED, "connection refused")));
             return;
         }
@@ -175,8 +188,14 @@ impl<const N: usize> ActiveOpenSocket<N> {
 
         debug!("Received SYN+ACK: {:?}", header);
 
+        // Our peer agrees to use timestamps by echoing the option (RFC 7323).
+        let remote_timestamp: Option<u32> = match self.ctx.tcp_config.get_timestamps_enabled() {
+            true => find_timestamps(header).map(|(sender_timestamp, _)| sender_timestamp),
+            false => None,
+        };
+
         // Acknowledge the SYN+ACK segment.
-        let remote_link_addr = match self.arp.try_query(self.remote.ip().clone()) {
+        let remote_link_addr = match self.ctx.arp.try_query(self.next_hop) {
             Some(r) => r,
             None => panic!("TODO: Clean up ARP query control flow"),
         };
@@ -185,18 +204,30 @@ impl<const N: usize> ActiveOpenSocket<N> {
         let mut tcp_hdr = TcpHeader::new(self.local.port(), self.remote.port());
         tcp_hdr.ack = true;
         tcp_hdr.ack_num = remote_seq_num;
-        tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
+        tcp_hdr.window_size = self.ctx.tcp_config.get_receive_window_size();
         tcp_hdr.seq_num = self.local_isn + SeqNumber::from(1);
+        if let Some(remote_timestamp) = remote_timestamp {
+            tcp_hdr.push_option(TcpOptions2::Timestamp {
+                sender_timestamp: timestamp_value(&self.ctx.clock),
+                echo_timestamp: remote_timestamp,
+            });
+        }
+        let mut ipv4_hdr: Ipv4Header =
+            Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP);
+        ipv4_hdr.set_identification(self.ctx.rt.next_ipv4_id());
+        if let Some(authenticator) = &self.authenticator {
+            tcp_hdr.sign(authenticator.as_ref(), &ipv4_hdr, &[]);
+        }
         debug!("Sending ACK: {:?}", tcp_hdr);
 
         let segment = TcpSegment {
-            ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
-            ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP),
+            ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.ctx.local_link_addr, EtherType2::Ipv4),
+            ipv4_hdr,
             tcp_hdr,
             data: None,
-            tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
+            tx_checksum_offload: self.ctx.tcp_config.get_rx_checksum_offload(),
         };
-        self.rt.transmit(Box::new(segment));
+        self.ctx.rt.transmit(Box::new(segment));
 
         let mut remote_window_scale = None;
         let mut mss = FALLBACK_MSS;
@@ -215,14 +246,14 @@ impl<const N: usize> ActiveOpenSocket<N> {
         }
 
         let (local_window_scale, remote_window_scale) = match remote_window_scale {
-            Some(w) => (self.tcp_config.get_window_scale() as u32, w),
+            Some(w) => (self.ctx.tcp_config.get_window_scale() as u32, w),
             None => (0, 0),
         };
 
         // TODO(RFC1323): Clamp the scale to 14 instead of panicking.
         assert!(local_window_scale <= 14 && remote_window_scale <= 14);
 
-        let rx_window_size: u32 = (self.tcp_config.get_receive_window_size())
+        let rx_window_size: u32 = (self.ctx.tcp_config.get_receive_window_size())
             .checked_shl(local_window_scale as u32)
             .expect("TODO: Window size overflow")
             .try_into()
@@ -240,25 +271,34 @@ impl<const N: usize> ActiveOpenSocket<N> {
             local_window_scale, remote_window_scale
         );
 
+        // An ECN-setup SYN+ACK has only ECE set (RFC 3168).
+        let ecn: bool = self.ctx.tcp_config.get_ecn_enabled() && header.ece && !header.cwr;
+
+        let handshake = HandshakeParameters {
+            receiver_seq_no: remote_seq_num,
+            receiver_window_size: rx_window_size,
+            receiver_window_scale: local_window_scale,
+            sender_seq_no: expected_seq,
+            sender_window_size: tx_window_size,
+            sender_window_scale: remote_window_scale,
+            sender_mss: mss,
+            ecn,
+            remote_timestamp,
+        };
         let cb = ControlBlock::new(
             self.local,
             self.remote,
-            self.rt.clone(),
-            self.scheduler.clone(),
-            self.clock.clone(),
-            self.local_link_addr,
-            self.tcp_config.clone(),
-            self.arp.clone(),
-            remote_seq_num,
-            self.tcp_config.get_ack_delay_timeout(),
-            rx_window_size,
-            local_window_scale,
-            expected_seq,
-            tx_window_size,
-            remote_window_scale,
-            mss,
-            congestion_control::None::new,
-            None,
+            self.next_hop,
+            self.ctx.clone(),
+            handshake,
+            self.authenticator.clone(),
+        );
+        trace::transition(
+            self.local,
+            self.remote,
+            ConnectionState::SynSent,
+            ConnectionState::Established,
+            "SYN_ACK_received",
         );
         self.set_result(Ok(cb));
     }
@@ -267,19 +307,25 @@ impl<const N: usize> ActiveOpenSocket<N> {
         local_isn: SeqNumber,
         local: SocketAddrV4,
         remote: SocketAddrV4,
-        rt: Rc<dyn NetworkRuntime<N>>,
-        clock: TimerRc,
-        local_link_addr: MacAddress,
-        tcp_config: TcpConfig,
-        arp: ArpPeer<N>,
-        result: Rc<RefCell<ConnectResult<N>>>,
+        next_hop: Ipv4Addr,
+        ctx: TcpContext,
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+        result: Rc<RefCell<ConnectResult>>,
     ) -> impl Future<Output = ()> {
+        let TcpContext {
+            rt,
+            clock,
+            local_link_addr,
+            tcp_config,
+            arp,
+            ..
+        } = ctx;
         let handshake_retries: usize = tcp_config.get_handshake_retries();
         let handshake_timeout = tcp_config.get_handshake_timeout();
 
         async move {
             for _ in 0..handshake_retries {
-                let remote_link_addr = match arp.query(remote.ip().clone()).await {
+                let remote_link_addr = match arp.query(next_hop).await {
                     Ok(r) => r,
                     Err(e) => {
                         warn!("ARP query failed: {:?}", e);
@@ -291,6 +337,11 @@ impl<const N: usize> ActiveOpenSocket<N> {
                 tcp_hdr.syn = true;
                 tcp_hdr.seq_num = local_isn;
                 tcp_hdr.window_size = tcp_config.get_receive_window_size();
+                // An ECN-setup SYN has both ECE and CWR set (RFC 3168).
+                if tcp_config.get_ecn_enabled() {
+                    tcp_hdr.ece = true;
+                    tcp_hdr.cwr = true;
+                }
 
                 let mss = tcp_config.get_advertised_mss() as u16;
                 tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
@@ -299,10 +350,24 @@ impl<const N: usize> ActiveOpenSocket<N> {
                 tcp_hdr.push_option(TcpOptions2::WindowScale(tcp_config.get_window_scale()));
                 info!("Advertising window scale: {}", tcp_config.get_window_scale());
 
+                if tcp_config.get_timestamps_enabled() {
+                    tcp_hdr.push_option(TcpOptions2::Timestamp {
+                        sender_timestamp: timestamp_value(&clock),
+                        echo_timestamp: 0,
+                    });
+                }
+
+                let mut ipv4_hdr: Ipv4Header =
+                    Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP);
+                ipv4_hdr.set_identification(rt.next_ipv4_id());
+                if let Some(authenticator) = &authenticator {
+                    tcp_hdr.sign(authenticator.as_ref(), &ipv4_hdr, &[]);
+                }
+
                 debug!("Sending SYN {:?}", tcp_hdr);
                 let segment = TcpSegment {
                     ethernet2_hdr: Ethernet2Header::new(remote_link_addr, local_link_addr, EtherType2::Ipv4),
-                    ipv4_hdr: Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
+                    ipv4_hdr,
                     tcp_hdr,
                     data: None,
                     tx_checksum_offload: tcp_config.get_rx_checksum_offload(),
@@ -310,6 +375,13 @@ impl<const N: usize> ActiveOpenSocket<N> {
                 rt.transmit(Box::new(segment));
                 clock.wait(clock.clone(), handshake_timeout).await;
             }
+            trace::transition(
+                local,
+                remote,
+                ConnectionState::SynSent,
+                ConnectionState::Closed,
+                "handshake_timeout",
+            );
             let mut r = result.borrow_mut();
             if let Some(w) = r.waker.take() {
                 w.wake()
@@ -328,7 +400,7 @@ impl<const N: usize> ActiveOpenSocket<N> {
 // Trait Implementations
 //======================================================================================================================
 
-impl<const N: usize> Drop for ActiveOpenSocket<N> {
+impl Drop for ActiveOpenSocket {
     fn drop(&mut self) {
         self.handle.deschedule();
     }
@@ -0,0 +1,345 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::inetstack::protocols::{
+    ip::IpProtocol,
+    ipv4::Ipv4Header,
+    tcp::segment::{
+        TcpHeader,
+        TcpOptions2,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Size of the TCP MD5 signature option (RFC 2385).
+pub const MD5_SIGNATURE_OPTION_SIZE: usize = 18;
+
+/// Per-round shift amounts of MD5 (RFC 1321).
+const MD5_SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+/// Per-step additive constants of MD5 (RFC 1321).
+const MD5_CONSTANTS: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501, 0x698098d8,
+    0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821, 0xf61e2562, 0xc040b340,
+    0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8, 0x21e1cde6, 0xc33707d6, 0xf4d50d87,
+    0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a, 0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c,
+    0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, 0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039,
+    0xe6db99e5, 0x1fa27cf8, 0xc4ac5665, 0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92,
+    0xffeff47d, 0x85845dd1, 0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb,
+    0xeb86d391,
+];
+
+//======================================================================================================================
+// Traits
+//======================================================================================================================
+
+/// Signs outgoing segments and validates incoming segments of a TCP connection. An authenticator is configured on a
+/// socket before it connects or listens, and is then used for every segment of the connection, including the
+/// handshake.
+pub trait SegmentAuthenticator {
+    /// Returns the number of bytes taken by the option that carries the signature of a segment.
+    fn option_size(&self) -> usize;
+
+    /// Computes the option that signs a segment. `header_size` is the size of the TCP header once the signature option
+    /// is appended to `tcp_hdr`.
+    fn sign(&self, ipv4_hdr: &Ipv4Header, tcp_hdr: &TcpHeader, header_size: usize, payload: &[u8]) -> TcpOptions2;
+
+    /// Checks whether a received segment carries a valid signature. `header_size` is the size of its TCP header.
+    fn verify(&self, ipv4_hdr: &Ipv4Header, tcp_hdr: &TcpHeader, header_size: usize, payload: &[u8]) -> bool;
+}
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Authenticator for the TCP MD5 signature option (RFC 2385).
+pub struct TcpMd5Signature {
+    key: Vec<u8>,
+}
+
+/// Incremental MD5 hash (RFC 1321).
+struct Md5 {
+    state: [u32; 4],
+    buffer: [u8; 64],
+    buffered: usize,
+    length: u64,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TcpMd5Signature {
+    /// Creates an authenticator that signs segments with the shared secret `key`.
+    pub fn new(key: &[u8]) -> Self {
+        Self { key: key.to_vec() }
+    }
+
+    /// Computes the MD5 digest of a segment, which covers the pseudo-header, the fixed part of the TCP header with a
+    /// zeroed checksum, the payload, and the key.
+    fn digest(&self, ipv4_hdr: &Ipv4Header, tcp_hdr: &TcpHeader, header_size: usize, payload: &[u8]) -> [u8; 16] {
+        let mut md5: Md5 = Md5::new();
+        md5.update(&ipv4_hdr.get_src_addr().octets());
+        md5.update(&ipv4_hdr.get_dest_addr().octets());
+        md5.update(&[0, IpProtocol::TCP as u8]);
+        md5.update(&((header_size + payload.len()) as u16).to_be_bytes());
+        md5.update(&tcp_hdr.serialize_fixed(header_size));
+        md5.update(payload);
+        md5.update(&self.key);
+        md5.finalize()
+    }
+}
+
+impl Md5 {
+    fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: [0; 64],
+            buffered: 0,
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+        while !data.is_empty() {
+            let n: usize = usize::min(64 - self.buffered, data.len());
+            self.buffer[self.buffered..(self.buffered + n)].copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+            if self.buffered == 64 {
+                let block: [u8; 64] = self.buffer;
+                self.compress(&block);
+                self.buffered = 0;
+            }
+        }
+    }
+
+    fn finalize(mut self) -> [u8; 16] {
+        let length: u64 = self.length.wrapping_mul(8);
+        // Pad with a one bit and zeros up to 8 bytes short of a block, then append the length in bits.
+        self.update(&[0x80]);
+        while self.buffered != 56 {
+            self.update(&[0]);
+        }
+        self.update(&length.to_le_bytes());
+        let mut digest: [u8; 16] = [0; 16];
+        for (i, word) in self.state.iter().enumerate() {
+            digest[(4 * i)..(4 * i + 4)].copy_from_slice(&word.to_le_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut words: [u32; 16] = [0; 16];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = u32::from_le_bytes([block[4 * i], block[4 * i + 1], block[4 * i + 2], block[4 * i + 3]]);
+        }
+        let [mut a, mut b, mut c, mut d]: [u32; 4] = self.state;
+        for i in 0..64 {
+            let (f, g): (u32, usize) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let sum: u32 = a.wrapping_add(f).wrapping_add(MD5_CONSTANTS[i]).wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(sum.rotate_left(MD5_SHIFTS[(i / 16) * 4 + i % 4]));
+        }
+        self.state[0] = self.state[0].wrapping_add(a);
+        self.state[1] = self.state[1].wrapping_add(b);
+        self.state[2] = self.state[2].wrapping_add(c);
+        self.state[3] = self.state[3].wrapping_add(d);
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl SegmentAuthenticator for TcpMd5Signature {
+    fn option_size(&self) -> usize {
+        MD5_SIGNATURE_OPTION_SIZE
+    }
+
+    fn sign(&self, ipv4_hdr: &Ipv4Header, tcp_hdr: &TcpHeader, header_size: usize, payload: &[u8]) -> TcpOptions2 {
+        TcpOptions2::Md5Signature(self.digest(ipv4_hdr, tcp_hdr, header_size, payload))
+    }
+
+    fn verify(&self, ipv4_hdr: &Ipv4Header, tcp_hdr: &TcpHeader, header_size: usize, payload: &[u8]) -> bool {
+        let signature: [u8; 16] = match tcp_hdr.iter_options().find_map(|option| match option {
+            TcpOptions2::Md5Signature(signature) => Some(*signature),
+            _ => None,
+        }) {
+            Some(signature) => signature,
+            None => return false,
+        };
+        let digest: [u8; 16] = self.digest(ipv4_hdr, tcp_hdr, header_size, payload);
+        // Compare all bytes, so that the time taken does not reveal how much of the signature matched.
+        signature.iter().zip(digest.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Md5,
+        SegmentAuthenticator,
+        TcpMd5Signature,
+    };
+    use crate::inetstack::protocols::{
+        ip::IpProtocol,
+        ipv4::Ipv4Header,
+        tcp::{
+            segment::{
+                SelectiveAcknowlegement,
+                TcpHeader,
+                TcpOptions2,
+                MAX_TCP_HEADER_SIZE,
+            },
+            SeqNumber,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::net::Ipv4Addr;
+
+    fn md5(data: &[u8]) -> [u8; 16] {
+        let mut md5: Md5 = Md5::new();
+        md5.update(data);
+        md5.finalize()
+    }
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Tests MD5 against the test suite of RFC 1321.
+    #[test]
+    fn md5_test_suite() -> Result<()> {
+        let vectors: [(&str, &str); 7] = [
+            ("", "d41d8cd98f00b204e9800998ecf8427e"),
+            ("a", "0cc175b9c0f1b6a831c399e269772661"),
+            ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+            ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+            ("abcdefghijklmnopqrstuvwxyz", "c3fcd3d76192e4007dfb496cca67e13b"),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "d174ab98d277d9f5a5611c2c9f419d9f",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "57edf4a22be3c955ac49da2e2107b67a",
+            ),
+        ];
+        for (input, expected) in vectors {
+            crate::ensure_eq!(hex(&md5(input.as_bytes())), expected);
+        }
+
+        // Feeding the input in pieces yields the same digest.
+        let mut md5: Md5 = Md5::new();
+        for piece in ["1234567890"; 8] {
+            md5.update(piece.as_bytes());
+        }
+        crate::ensure_eq!(hex(&md5.finalize()), "57edf4a22be3c955ac49da2e2107b67a");
+        Ok(())
+    }
+
+    /// Tests that the MD5 signature covers the pseudo-header, the fixed TCP header, the payload, and the key, in this
+    /// order (RFC 2385).
+    #[test]
+    fn md5_signature_vector() -> Result<()> {
+        let ipv4_hdr: Ipv4Header = Ipv4Header::new(
+            Ipv4Addr::new(192, 168, 1, 1),
+            Ipv4Addr::new(192, 168, 1, 2),
+            IpProtocol::TCP,
+        );
+        let mut tcp_hdr: TcpHeader = TcpHeader::new(179, 49152);
+        tcp_hdr.seq_num = SeqNumber::from(0x01020304);
+        tcp_hdr.ack_num = SeqNumber::from(0x05060708);
+        tcp_hdr.ack = true;
+        tcp_hdr.psh = true;
+        tcp_hdr.window_size = 0x1000;
+        let payload: &[u8] = b"hello";
+        let authenticator: TcpMd5Signature = TcpMd5Signature::new(b"secret");
+        tcp_hdr.sign(&authenticator, &ipv4_hdr, payload);
+
+        #[rustfmt::skip]
+        let expected: Vec<u8> = [
+            // Pseudo-header: source, destination, zero, protocol, and segment length (40 + 5 bytes).
+            &[192, 168, 1, 1, 192, 168, 1, 2, 0, 6, 0, 45][..],
+            // Fixed TCP header: ports, sequence and acknowledgement numbers, data offset (10 words), flags, window,
+            // zero checksum, and urgent pointer.
+            &[0, 179, 192, 0, 1, 2, 3, 4, 5, 6, 7, 8, 0xa0, 0x18, 0x10, 0, 0, 0, 0, 0][..],
+            payload,
+            b"secret",
+        ]
+        .concat();
+        let signature: [u8; 16] = match tcp_hdr.iter_options().last() {
+            Some(TcpOptions2::Md5Signature(signature)) => *signature,
+            _ => anyhow::bail!("header should carry an MD5 signature"),
+        };
+        crate::ensure_eq!(signature, md5(&expected));
+        crate::ensure_eq!(tcp_hdr.compute_size(), 40);
+        crate::ensure_eq!(authenticator.verify(&ipv4_hdr, &tcp_hdr, 40, payload), true);
+
+        // Any change to the segment or the key invalidates the signature.
+        crate::ensure_eq!(authenticator.verify(&ipv4_hdr, &tcp_hdr, 40, b"hellO"), false);
+        let other: TcpMd5Signature = TcpMd5Signature::new(b"Secret");
+        crate::ensure_eq!(other.verify(&ipv4_hdr, &tcp_hdr, 40, payload), false);
+        tcp_hdr.seq_num = SeqNumber::from(0x01020305);
+        crate::ensure_eq!(authenticator.verify(&ipv4_hdr, &tcp_hdr, 40, payload), false);
+        Ok(())
+    }
+
+    /// Tests that the signature option fits next to timestamps and SACK blocks, dropping SACK blocks if needed.
+    #[test]
+    fn md5_signature_option_space() -> Result<()> {
+        let ipv4_hdr: Ipv4Header = Ipv4Header::new(
+            Ipv4Addr::new(192, 168, 1, 1),
+            Ipv4Addr::new(192, 168, 1, 2),
+            IpProtocol::TCP,
+        );
+        let authenticator: TcpMd5Signature = TcpMd5Signature::new(b"secret");
+        let sack: SelectiveAcknowlegement = SelectiveAcknowlegement {
+            begin: SeqNumber::from(100),
+            end: SeqNumber::from(200),
+        };
+        let mut tcp_hdr: TcpHeader = TcpHeader::new(179, 49152);
+        tcp_hdr.ack = true;
+        tcp_hdr.push_option(TcpOptions2::Timestamp {
+            sender_timestamp: 1,
+            echo_timestamp: 2,
+        });
+        tcp_hdr.push_option(TcpOptions2::SelectiveAcknowlegement {
+            num_sacks: 3,
+            sacks: [sack; 4],
+        });
+        tcp_hdr.sign(&authenticator, &ipv4_hdr, &[]);
+
+        crate::ensure_eq!(tcp_hdr.compute_size(), MAX_TCP_HEADER_SIZE);
+        let num_sacks: usize = match tcp_hdr.iter_options().nth(1) {
+            Some(TcpOptions2::SelectiveAcknowlegement { num_sacks, .. }) => *num_sacks,
+            _ => anyhow::bail!("header should carry SACK blocks"),
+        };
+        crate::ensure_eq!(num_sacks, 1);
+        crate::ensure_eq!(
+            authenticator.verify(&ipv4_hdr, &tcp_hdr, MAX_TCP_HEADER_SIZE, &[]),
+            true
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use super::{
+    events::ConnEventQueue,
+    isn_generator::IsnGenerator,
+    pmtu::PmtuTable,
+    telemetry::{
+        HandshakeTelemetry,
+        PeerTelemetry,
+    },
+};
+use crate::{
+    inetstack::protocols::arp::ArpPeer,
+    runtime::{
+        network::{
+            config::TcpConfig,
+            types::MacAddress,
+            NetworkRuntime,
+        },
+        timer::TimerRc,
+    },
+    scheduler::Scheduler,
+};
+use ::std::{
+    cell::{
+        Cell,
+        RefCell,
+    },
+    rc::Rc,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Resources of a TCP peer that its listening sockets, connecting sockets and connections share. Each socket gets its
+/// own clone, so that it can move to a new local link address on its own.
+#[derive(Clone)]
+pub struct TcpContext {
+    pub rt: Rc<dyn NetworkRuntime>,
+    pub scheduler: Scheduler,
+    pub clock: TimerRc,
+    pub local_link_addr: MacAddress,
+    pub tcp_config: TcpConfig,
+    pub arp: ArpPeer,
+    // Number of bytes buffered in the receive queues of all connections.
+    pub total_rx_buffer_bytes: Rc<Cell<usize>>,
+    pub isn_generator: Rc<RefCell<IsnGenerator>>,
+    // Maximum segment sizes that black hole detection settled on for remote hosts.
+    pub pmtu_table: PmtuTable,
+    // Round-trip times, byte counters and failed connection attempts of remote hosts.
+    pub telemetry: PeerTelemetry,
+    // Counters on the handshakes that remote hosts initiate towards our listening sockets.
+    pub handshakes: HandshakeTelemetry,
+    // Connection events that the application has yet to drain.
+    pub events: ConnEventQueue,
+}
@@ -12,7 +12,7 @@ use ::futures::{
 };
 use ::std::rc::Rc;
 
-pub async fn acknowledger<const N: usize>(cb: Rc<ControlBlock<N>>) -> Result<!, Fail> {
+pub async fn acknowledger(cb: Rc<ControlBlock>) -> Result<!, Fail> {
     loop {
         // TODO: Implement TCP delayed ACKs, subject to restrictions from RFC 1122
         // - TCP should implement a delayed ACK
@@ -0,0 +1,39 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use super::ControlBlock;
+use crate::runtime::fail::Fail;
+use ::futures::future;
+use ::std::{
+    rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// Sends keep-alive probes on connections that stay idle for the NAT traversal keep-alive interval, so that NAT boxes
+/// on the path do not time out their mapping of the connection. This does nothing unless NAT traversal keep-alives are
+/// enabled.
+pub async fn keepalive(cb: Rc<ControlBlock>) -> Result<!, Fail> {
+    let interval: Duration = match cb.get_keepalive_interval() {
+        Some(interval) => interval,
+        None => future::pending().await,
+    };
+
+    let mut deadline: Instant = cb.get_last_activity() + interval;
+    loop {
+        cb.clock.wait_until(cb.clock.clone(), deadline).await;
+
+        // Some segment went through since we set the deadline, thus the mapping was refreshed already.
+        let idle_deadline: Instant = cb.get_last_activity() + interval;
+        if idle_deadline > cb.clock.now() {
+            deadline = idle_deadline;
+            continue;
+        }
+
+        let remote_link_addr = cb.arp().query(cb.get_next_hop()).await?;
+        cb.send_keepalive(remote_link_addr);
+        deadline = cb.clock.now() + interval;
+    }
+}
@@ -2,11 +2,13 @@
 // Licensed under the MIT license.
 
 mod acknowledger;
+mod keepalive;
 mod retransmitter;
 mod sender;
 
 use self::{
     acknowledger::acknowledger,
+    keepalive::keepalive,
     retransmitter::retransmitter,
     sender::sender,
 };
@@ -18,24 +20,20 @@ use ::futures::{
 };
 use ::std::rc::Rc;
 
-pub async fn background<const N: usize>(
-    cb: Rc<ControlBlock<N>>,
-    fd: QDesc,
-    _dead_socket_tx: mpsc::UnboundedSender<QDesc>,
-) {
+pub async fn background(cb: Rc<ControlBlock>, fd: QDesc, _dead_socket_tx: mpsc::UnboundedSender<QDesc>) {
     let acknowledger = acknowledger(cb.clone()).fuse();
     futures::pin_mut!(acknowledger);
 
     let retransmitter = retransmitter(cb.clone()).fuse();
     futures::pin_mut!(retransmitter);
 
-    let sender = sender(cb.clone()).fuse();
-    futures::pin_mut!(sender);
+    let keepalive = keepalive(cb.clone()).fuse();
+    futures::pin_mut!(keepalive);
 
     let r = futures::select_biased! {
         r = acknowledger => r,
         r = retransmitter => r,
-        r = sender => r,
+        r = keepalive => r,
     };
     error!("Connection (fd {:?}) terminated: {:?}", fd, r);
 
@@ -44,3 +42,10 @@ pub async fn background<const N: usize>(
     //     .unbounded_send(fd)
     //     .expect("Failed to terminate connection");
 }
+
+/// Pushes out bulk data. This runs as a separate task so that it can be scheduled at a lower priority than the
+/// acknowledger and the retransmitter.
+pub async fn background_sender(cb: Rc<ControlBlock>, fd: QDesc) {
+    let r = sender(cb).await;
+    error!("Connection (fd {:?}) sender terminated: {:?}", fd, r);
+}
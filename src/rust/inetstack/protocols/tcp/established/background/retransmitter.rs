@@ -18,8 +18,13 @@ use ::std::{
     },
 };
 
-pub async fn retransmitter<const N: usize>(cb: Rc<ControlBlock<N>>) -> Result<!, Fail> {
+pub async fn retransmitter(cb: Rc<ControlBlock>) -> Result<!, Fail> {
     loop {
+        // Stop if the connection was aborted because a push was not acknowledged in time.
+        if cb.has_timed_out() {
+            return Err(Fail::new(libc::ETIMEDOUT, "connection timed out"));
+        }
+
         // Pin future for timeout retransmission.
         let (rtx_deadline, rtx_deadline_changed) = cb.watch_retransmit_deadline();
         futures::pin_mut!(rtx_deadline_changed);
@@ -41,9 +46,22 @@ pub async fn retransmitter<const N: usize>(cb: Rc<ControlBlock<N>>) -> Result<!,
         }
         futures::pin_mut!(rtx_fast_retransmit_changed);
 
+        // Pin future for the user timeout (RFC 5482). Acknowledgements move this deadline along with the retransmission
+        // deadline, so we recompute it whenever the latter changes.
+        let uto_future = match cb.get_user_timeout_deadline() {
+            Some(t) => Either::Left(cb.clock.wait_until(cb.clock.clone(), t).fuse()),
+            None => Either::Right(future::pending()),
+        };
+        futures::pin_mut!(uto_future);
+
         futures::select_biased! {
             _ = rtx_deadline_changed => continue,
             _ = rtx_fast_retransmit_changed => continue,
+            _ = uto_future => {
+                trace!("User Timeout Expired");
+                cb.abort_on_timeout("user timeout expired");
+                return Err(Fail::new(libc::ETIMEDOUT, "user timeout expired"));
+            },
             _ = rtx_future => {
                 trace!("Retransmission Timer Expired");
                 // Notify congestion control about RTO.
@@ -52,8 +70,11 @@ pub async fn retransmitter<const N: usize>(cb: Rc<ControlBlock<N>>) -> Result<!,
                 let (send_unacknowledged, _) = cb.get_send_unacked();
                 cb.congestion_control_on_rto(send_unacknowledged);
 
-                // RFC 6298 Section 5.4: Retransmit earliest unacknowledged segment.
-                cb.retransmit();
+                // RFC 6298 Section 5.4: Retransmit earliest unacknowledged segment, unless black hole detection
+                // (RFC 2923) already sends it again in smaller segments.
+                if !cb.probe_black_hole() {
+                    cb.retransmit();
+                }
 
                 // RFC 6298 Section 5.5: Back off the retransmission timer.
                 cb.rto_back_off();
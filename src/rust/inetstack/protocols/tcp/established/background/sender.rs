@@ -1,28 +1,25 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use super::super::{
-    ctrlblk::ControlBlock,
-    sender::UnackedSegment,
-};
+use super::super::ctrlblk::ControlBlock;
 use crate::{
     inetstack::protocols::tcp::{
         segment::TcpHeader,
         SeqNumber,
     },
-    runtime::{
-        fail::Fail,
-        memory::DemiBuffer,
-    },
+    runtime::fail::Fail,
 };
 use ::futures::FutureExt;
 use ::std::{
     cmp,
     rc::Rc,
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
-pub async fn sender<const N: usize>(cb: Rc<ControlBlock<N>>) -> Result<!, Fail> {
+pub async fn sender(cb: Rc<ControlBlock>) -> Result<!, Fail> {
     'top: loop {
         // First, check to see if there's any unsent data.
         // TODO: Change this to just look at the unsent queue to see if it is empty or not.
@@ -47,41 +44,8 @@ pub async fn sender<const N: usize>(cb: Rc<ControlBlock<N>>) -> Result<!, Fail>
         // If we don't have any window size at all, we need to transition to PERSIST mode and
         // repeatedly send window probes until window opens up.
         if win_sz == 0 {
-            // Send a window probe (this is a one-byte packet designed to elicit a window update from our peer).
-            let remote_link_addr = cb.arp().query(cb.get_remote().ip().clone()).await?;
-            let buf: DemiBuffer = cb
-                .pop_one_unsent_byte()
-                .unwrap_or_else(|| panic!("No unsent data? {}, {}", send_next, unsent_seq));
-
-            // Update SND.NXT.
-            cb.modify_send_next(|s| s + SeqNumber::from(1));
-
-            // Add the probe byte (as a new separate buffer) to our unacknowledged queue.
-            let unacked_segment = UnackedSegment {
-                bytes: buf.clone(),
-                initial_tx: Some(cb.clock.now()),
-            };
-            cb.push_unacked_segment(unacked_segment);
-
-            let mut header: TcpHeader = cb.tcp_header();
-            header.seq_num = send_next;
-            cb.emit(header, Some(buf.clone()), remote_link_addr);
-
-            // Note that we loop here *forever*, exponentially backing off.
-            // TODO: Use the correct PERSIST mode timer here.
-            let mut timeout: Duration = Duration::from_secs(1);
-            loop {
-                futures::select_biased! {
-                    _ = win_sz_changed => continue 'top,
-                    _ = cb.clock.wait(cb.clock.clone(), timeout).fuse() => {
-                        timeout *= 2;
-                    }
-                }
-                // Retransmit our window probe.
-                let mut header: TcpHeader = cb.tcp_header();
-                header.seq_num = send_next;
-                cb.emit(header, Some(buf.clone()), remote_link_addr);
-            }
+            persist(&cb).await?;
+            continue 'top;
         }
 
         // The remote window is nonzero, but there still may not be room.
@@ -120,51 +84,46 @@ pub async fn sender<const N: usize>(cb: Rc<ControlBlock<N>>) -> Result<!, Fail>
         // TODO: Silly window syndrome - See RFC 1122's discussion of the SWS avoidance algorithm.
 
         // TODO: Link-level concerns don't belong here, we should call an IP-level send routine below.
-        let remote_link_addr = cb.arp().query(cb.get_remote().ip().clone()).await?;
+        let remote_link_addr = cb.arp().query(cb.get_next_hop()).await?;
 
         // Form an outgoing packet.
         let max_size: usize = cmp::min(
             cmp::min((win_sz - sent_data) as usize, cb.get_mss()),
             (effective_cwnd - sent_data) as usize,
         );
-        let (segment_data, do_push): (DemiBuffer, bool) = cb
-            .pop_unsent_segment(max_size)
-            .expect("No unsent data with sequence number gap?");
-        let mut segment_data_len: u32 = segment_data.len() as u32;
-
-        let rto: Duration = cb.rto();
-        cb.congestion_control_on_send(rto, sent_data);
+        let sent: bool = cb.send_next_segment(remote_link_addr, max_size, sent_data);
+        assert!(sent, "No unsent data with sequence number gap?");
+    }
+}
 
-        // Prepare the segment and send it.
-        let mut header: TcpHeader = cb.tcp_header();
-        header.seq_num = send_next;
-        if segment_data_len == 0 {
-            // This buffer is the end-of-send marker.
-            debug_assert!(cb.user_is_done_sending.get());
-            // Set FIN and adjust sequence number consumption accordingly.
-            header.fin = true;
-            segment_data_len = 1;
-        } else if do_push {
-            header.psh = true;
+/// Runs the PERSIST mode of the sender, until the remote side opens up its window again. Every time that the persist
+/// timer expires, we send a zero-window probe, and back off exponentially. A zero-window probe is a segment without
+/// data that carries an old sequence number, so that our peer drops it and replies with an ACK that reports its
+/// current window. Window updates that keep the window closed do not reset the persist timer.
+async fn persist(cb: &Rc<ControlBlock>) -> Result<(), Fail> {
+    let mut timeout: Duration = cb.clamp_persist_timeout(cb.rto());
+    let mut deadline: Instant = cb.clock.now() + timeout;
+    loop {
+        let (win_sz, win_sz_changed) = cb.get_send_window();
+        if win_sz != 0 {
+            return Ok(());
         }
-        cb.emit(header, Some(segment_data.clone()), remote_link_addr);
-
-        // Update SND.NXT.
-        cb.modify_send_next(|s| s + SeqNumber::from(segment_data_len));
-
-        // Put this segment on the unacknowledged list.
-        let unacked_segment = UnackedSegment {
-            bytes: segment_data,
-            initial_tx: Some(cb.clock.now()),
-        };
-        cb.push_unacked_segment(unacked_segment);
-
-        // Set the retransmit timer.
-        // TODO: Fix how the retransmit timer works.
-        let retransmit_deadline = cb.get_retransmit_deadline();
-        if retransmit_deadline.is_none() {
-            let rto: Duration = cb.rto();
-            cb.set_retransmit_deadline(Some(cb.clock.now() + rto));
+        futures::pin_mut!(win_sz_changed);
+
+        futures::select_biased! {
+            _ = win_sz_changed => continue,
+            _ = cb.clock.wait_until(cb.clock.clone(), deadline).fuse() => {},
         }
+
+        // Send a zero-window probe.
+        let remote_link_addr = cb.arp().query(cb.get_next_hop()).await?;
+        let (send_unacked, _) = cb.get_send_unacked();
+        let mut header: TcpHeader = cb.tcp_header();
+        header.seq_num = send_unacked - SeqNumber::from(1);
+        debug!("Sending zero-window probe: {:?}", header);
+        cb.emit(header, None, remote_link_addr);
+
+        timeout = cb.clamp_persist_timeout(timeout * 2);
+        deadline = cb.clock.now() + timeout;
     }
 }
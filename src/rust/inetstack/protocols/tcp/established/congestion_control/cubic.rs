@@ -283,6 +283,10 @@ impl SlowStartCongestionAvoidance for Cubic {
         self.cwnd.watch()
     }
 
+    fn get_ssthresh(&self) -> u32 {
+        self.ssthresh.get()
+    }
+
     fn on_cwnd_check_before_send(&self) {
         let long_time_since_send: bool =
             Instant::now().duration_since(self.last_send_time.get()) > self.rtt_at_last_send.get();
@@ -328,6 +332,27 @@ impl SlowStartCongestionAvoidance for Cubic {
         self.on_rto_ss_ca();
         self.on_rto_fast_recovery(send_unacked);
     }
+
+    fn on_congestion_signal(&self, send_unacked: SeqNumber, send_next: SeqNumber) {
+        // RFC 3168 asks us to react at most once per window of data, and fast recovery already shrunk cwnd.
+        if self.in_fast_recovery.get() || send_unacked < self.recover.get() {
+            return;
+        }
+        self.recover.set(send_next);
+
+        // Otherwise, halve cwnd as RFC 3168 asks, but without retransmitting anything.
+        let cwnd: u32 = self.cwnd.get();
+        let reduced_cwnd: u32 = max(cwnd / 2, self.mss);
+        if self.fast_convergence {
+            self.fast_convergence();
+        } else {
+            self.w_max.set(cwnd);
+        }
+        self.ssthresh.set(max(reduced_cwnd, 2 * self.mss));
+        self.cwnd.set(reduced_cwnd);
+        self.ca_start.set(Instant::now());
+        self.last_congestion_was_rto.set(false);
+    }
 }
 
 impl FastRetransmitRecovery for Cubic {
@@ -360,3 +385,46 @@ impl LimitedTransmit for Cubic {
         self.limited_transmit_cwnd_increase.watch()
     }
 }
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::Cubic;
+    use crate::inetstack::protocols::tcp::{
+        congestion_control::CongestionControl,
+        SeqNumber,
+    };
+    use ::anyhow::Result;
+
+    /// Tests that a congestion signal halves cwnd, but only once per window of data.
+    #[test]
+    fn test_on_congestion_signal() -> Result<()> {
+        let mss: usize = 1000;
+        let send_unacked: SeqNumber = SeqNumber::from(1);
+        let cc: Box<dyn CongestionControl> = Cubic::new(mss, send_unacked, None);
+        let cwnd: u32 = cc.get_cwnd();
+
+        // The first signal reduces cwnd.
+        let send_next: SeqNumber = send_unacked + SeqNumber::from(cwnd);
+        cc.on_congestion_signal(send_unacked, send_next);
+        let reduced_cwnd: u32 = cwnd / 2;
+        crate::ensure_eq!(cc.get_cwnd(), reduced_cwnd);
+
+        // Further signals for the same window of data do not.
+        cc.on_congestion_signal(send_unacked + SeqNumber::from(1), send_next);
+        crate::ensure_eq!(cc.get_cwnd(), reduced_cwnd);
+
+        // Once that window is acknowledged, the next signal reduces cwnd again.
+        cc.on_congestion_signal(send_next, send_next + SeqNumber::from(cwnd));
+        crate::ensure_eq!(cc.get_cwnd(), reduced_cwnd / 2);
+
+        // The window never shrinks below one segment.
+        cc.on_congestion_signal(send_next + SeqNumber::from(cwnd), send_next + SeqNumber::from(2 * cwnd));
+        crate::ensure_eq!(cc.get_cwnd(), mss as u32);
+
+        Ok(())
+    }
+}
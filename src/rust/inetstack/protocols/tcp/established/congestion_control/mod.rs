@@ -31,6 +31,11 @@ pub trait SlowStartCongestionAvoidance {
         (u32::MAX, WatchFuture::Pending)
     }
 
+    // Size of cwnd at which slow start ends and congestion avoidance begins.
+    fn get_ssthresh(&self) -> u32 {
+        u32::MAX
+    }
+
     // Called immediately before the cwnd check is performed before data is sent.
     fn on_cwnd_check_before_send(&self) {}
 
@@ -42,6 +47,9 @@ pub trait SlowStartCongestionAvoidance {
 
     // Called immediately before a segment is sent for the 1st time.
     fn on_send(&self, _rto: Duration, _num_sent_bytes: u32) {}
+
+    // Called when the network signals congestion without dropping a segment (i.e. on an ECN-Echo).
+    fn on_congestion_signal(&self, _send_unacked: SeqNumber, _send_next: SeqNumber) {}
 }
 
 pub trait FastRetransmitRecovery
@@ -81,3 +89,14 @@ pub trait CongestionControl: SlowStartCongestionAvoidance + FastRetransmitRecove
 }
 
 pub type CongestionControlConstructor = fn(usize, SeqNumber, Option<options::Options>) -> Box<dyn CongestionControl>;
+
+/// Selects the congestion control algorithm of a connection. Connections run without congestion control, unless they
+/// negotiated ECN: those run Cubic, so that they keep a congestion window to reduce when the network signals congestion
+/// (RFC 3168).
+pub fn select(ecn: bool) -> CongestionControlConstructor {
+    if ecn {
+        Cubic::new
+    } else {
+        None::new
+    }
+}
@@ -2,15 +2,15 @@
 // Licensed under the MIT license.
 
 use super::{
-    congestion_control::{
-        self,
-        CongestionControlConstructor,
-    },
+    congestion_control,
+    rcv_window::ReceiveWindowTuner,
     rto::RtoCalculator,
     sender::{
         Sender,
         UnackedSegment,
     },
+    TcpConnectionStats,
+    TcpInfo,
 };
 use crate::{
     inetstack::protocols::{
@@ -20,12 +20,36 @@ use crate::{
             Ethernet2Header,
         },
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            IPV4_ECN_CE,
+            IPV4_ECN_ECT0,
+        },
         tcp::{
+            auth::SegmentAuthenticator,
+            context::TcpContext,
+            events::ConnEventQueue,
+            pmtu::PmtuTable,
+            receive_buffer::{
+                new_receive_buffer,
+                ReceiveBuffer,
+            },
             segment::{
                 TcpHeader,
+                TcpOptions2,
                 TcpSegment,
             },
+            telemetry::PeerTelemetry,
+            timestamps::{
+                find_timestamps,
+                timestamp_after,
+                timestamp_value,
+                TIMESTAMPS_OPTION_SIZE,
+            },
+            trace::{
+                self,
+                ConnectionSpan,
+            },
             SeqNumber,
         },
     },
@@ -34,7 +58,11 @@ use crate::{
         memory::DemiBuffer,
         network::{
             config::TcpConfig,
-            types::MacAddress,
+            types::{
+                ByteStats,
+                ConnEvent,
+                MacAddress,
+            },
             NetworkRuntime,
         },
         timer::TimerRc,
@@ -42,18 +70,24 @@ use crate::{
             WatchFuture,
             WatchedValue,
         },
+        QDesc,
     },
     scheduler::scheduler::Scheduler,
 };
 use ::std::{
     cell::{
         Cell,
+        Ref,
         RefCell,
         RefMut,
     },
+    cmp,
     collections::VecDeque,
     convert::TryInto,
-    net::SocketAddrV4,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
     rc::Rc,
     task::{
         Context,
@@ -72,10 +106,9 @@ use ::std::{
 // mechanism used to manage the receive queue (a VecDeque) than anything else.
 const RECV_QUEUE_SZ: usize = 2048;
 
-// TODO: Review this value (and its purpose).  It (16 segments) seems awfully small (would make fast retransmit less
-// useful), and this mechanism isn't the best way to protect ourselves against deliberate out-of-order segment attacks.
-// Ideally, we'd limit out-of-order data to that which (along with the unread data) will fit in the receive window.
-const MAX_OUT_OF_ORDER: usize = 16;
+// Number of retransmission timeouts in a row on a full-sized segment after which black hole detection lowers the MSS.
+// A single timeout is far more likely a congestion loss than a black hole (RFC 2923).
+const BLACK_HOLE_RTO_COUNT: u32 = 2;
 
 // TCP Connection State.
 // Note: This ControlBlock structure is only used after we've reached the ESTABLISHED state, so states LISTEN,
@@ -116,17 +149,39 @@ struct Receiver {
 
     // Receive queue.  Contains in-order received (and acknowledged) data ready for the application to read.
     recv_queue: RefCell<VecDeque<DemiBuffer>>,
+
+    // Number of bytes currently held in the receive queue.
+    recv_queue_bytes: Cell<usize>,
+
+    // Number of bytes held in the receive queues of all connections.  Shared with the TCP peer.
+    total_rx_buffer_bytes: Rc<Cell<usize>>,
+
+    // Number of bytes that we received in order so far.
+    received_bytes: Cell<u64>,
+
+    // Sequence number of the urgent byte that the remote pointed at, if it is yet to be received.  Urgent data is not
+    // delivered out of band, so this byte is stripped from the stream.
+    urgent: Cell<Option<SeqNumber>>,
 }
 
 impl Receiver {
-    pub fn new(reader_next: SeqNumber, receive_next: SeqNumber) -> Self {
+    pub fn new(reader_next: SeqNumber, receive_next: SeqNumber, total_rx_buffer_bytes: Rc<Cell<usize>>) -> Self {
         Self {
             reader_next: Cell::new(reader_next),
             receive_next: Cell::new(receive_next),
             recv_queue: RefCell::new(VecDeque::with_capacity(RECV_QUEUE_SZ)),
+            recv_queue_bytes: Cell::new(0),
+            total_rx_buffer_bytes,
+            received_bytes: Cell::new(0),
+            urgent: Cell::new(None),
         }
     }
 
+    /// Returns the number of bytes currently held in the receive queue.
+    pub fn get_buffered_bytes(&self) -> usize {
+        self.recv_queue_bytes.get()
+    }
+
     pub fn pop(&self, size: Option<usize>) -> Result<Option<DemiBuffer>, Fail> {
         let mut recv_queue: RefMut<VecDeque<DemiBuffer>> = self.recv_queue.borrow_mut();
 
@@ -149,25 +204,134 @@ impl Receiver {
 
         self.reader_next
             .set(self.reader_next.get() + SeqNumber::from(buf.len() as u32));
+        self.recv_queue_bytes.set(self.recv_queue_bytes.get() - buf.len());
+        self.total_rx_buffer_bytes
+            .set(self.total_rx_buffer_bytes.get() - buf.len());
+
+        Ok(Some(buf))
+    }
+
+    /// Copies up to `size` bytes from the front of the receive queue without removing them, or the whole front buffer
+    /// if no size is given. This copies at most 64 KiB, which is the largest buffer that we can allocate.
+    pub fn peek(&self, size: Option<usize>) -> Result<Option<DemiBuffer>, Fail> {
+        let recv_queue: Ref<VecDeque<DemiBuffer>> = self.recv_queue.borrow();
+
+        // Check if the receive queue is empty.
+        let front: &DemiBuffer = match recv_queue.front() {
+            Some(front) => front,
+            None => return Ok(None),
+        };
+
+        let len: usize = match size {
+            Some(size) => usize::min(size, self.recv_queue_bytes.get()),
+            None => front.len(),
+        };
+        let len: usize = usize::min(len, u16::MAX as usize);
+        let mut buf: DemiBuffer = DemiBuffer::new(len as u16);
+        let mut copied: usize = 0;
+        for chunk in recv_queue.iter() {
+            if copied == len {
+                break;
+            }
+            let n: usize = usize::min(len - copied, chunk.len());
+            buf[copied..(copied + n)].copy_from_slice(&chunk[..n]);
+            copied += n;
+        }
 
         Ok(Some(buf))
     }
 
-    pub fn push(&self, buf: DemiBuffer) {
-        let buf_len: u32 = buf.len() as u32;
-        self.recv_queue.borrow_mut().push_back(buf);
-        self.receive_next
-            .set(self.receive_next.get() + SeqNumber::from(buf_len as u32));
+    /// Returns the number of bytes that we received in order so far.
+    pub fn get_received_bytes(&self) -> u64 {
+        self.received_bytes.get()
+    }
+
+    pub fn push(&self, mut buf: DemiBuffer) {
+        let seg_len: u32 = buf.len() as u32;
+        let receive_next: SeqNumber = self.receive_next.get();
+        self.receive_next.set(receive_next + SeqNumber::from(seg_len));
+
+        // Strip the urgent byte if this buffer holds it.  It still takes up sequence space, so it counts as read.
+        let mut buf_len: u32 = seg_len;
+        if let Some(urgent) = self.urgent.get() {
+            if receive_next <= urgent && urgent < receive_next + SeqNumber::from(seg_len) {
+                let offset: usize = u32::from(urgent - receive_next) as usize;
+                if offset > 0 {
+                    let front: DemiBuffer = buf
+                        .split_front(offset)
+                        .expect("urgent byte should be within the buffer");
+                    self.recv_queue.borrow_mut().push_back(front);
+                }
+                buf.adjust(1).expect("urgent byte should be within the buffer");
+                self.reader_next.set(self.reader_next.get() + SeqNumber::from(1));
+                self.urgent.set(None);
+                buf_len -= 1;
+            }
+        }
+
+        self.received_bytes.set(self.received_bytes.get() + buf_len as u64);
+        if !buf.is_empty() || seg_len == 0 {
+            self.recv_queue.borrow_mut().push_back(buf);
+        }
+        self.recv_queue_bytes
+            .set(self.recv_queue_bytes.get() + buf_len as usize);
+        self.total_rx_buffer_bytes
+            .set(self.total_rx_buffer_bytes.get() + buf_len as usize);
+    }
+
+    /// Marks the byte at `urgent` as urgent, so that it gets stripped from the stream when it arrives.  Only the most
+    /// recent urgent byte is remembered, and those that were received already are ignored.
+    pub fn set_urgent(&self, urgent: SeqNumber) {
+        if urgent >= self.receive_next.get() {
+            self.urgent.set(Some(urgent));
+        }
+    }
+
+    /// Drops all data held in the receive queue.
+    pub fn clear(&self) {
+        self.recv_queue.borrow_mut().clear();
+        self.total_rx_buffer_bytes
+            .set(self.total_rx_buffer_bytes.get() - self.recv_queue_bytes.get());
+        self.recv_queue_bytes.set(0);
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        // Release whatever the application did not read from the shared accounting.
+        self.clear();
     }
 }
 
+/// Connection parameters that the two ends settled on during the handshake.
+pub struct HandshakeParameters {
+    // Next sequence number that we expect from our peer.
+    pub receiver_seq_no: SeqNumber,
+    // Receive window that we start with, and its scale factor.
+    pub receiver_window_size: u32,
+    pub receiver_window_scale: u32,
+    // Next sequence number that we send.
+    pub sender_seq_no: SeqNumber,
+    // Send window that our peer advertised, and its scale factor.
+    pub sender_window_size: u32,
+    pub sender_window_scale: u8,
+    // Maximum segment size that our peer advertised.
+    pub sender_mss: usize,
+    // Whether both ends agreed to use Explicit Congestion Notification (RFC 3168).
+    pub ecn: bool,
+    // Timestamp of the last segment of the handshake, if both ends agreed to use timestamps (RFC 7323).
+    pub remote_timestamp: Option<u32>,
+}
+
 /// Transmission control block for representing our TCP connection.
 // TODO: Make all public fields in this structure private.
-pub struct ControlBlock<const N: usize> {
+pub struct ControlBlock {
     local: SocketAddrV4,
     remote: SocketAddrV4,
+    // Host that segments to the remote are handed to, which is the remote itself unless a route says otherwise.
+    next_hop: Ipv4Addr,
 
-    rt: Rc<dyn NetworkRuntime<N>>,
+    rt: Rc<dyn NetworkRuntime>,
     pub scheduler: Scheduler,
     pub clock: TimerRc,
     local_link_addr: MacAddress,
@@ -175,10 +339,10 @@ pub struct ControlBlock<const N: usize> {
 
     // TODO: We shouldn't be keeping anything datalink-layer specific at this level.  The IP layer should be holding
     // this along with other remote IP information (such as routing, path MTU, etc).
-    arp: Rc<ArpPeer<N>>,
+    arp: Rc<ArpPeer>,
 
     // Send-side state information.  TODO: Consider incorporating this directly into ControlBlock.
-    sender: Sender<N>,
+    sender: Sender,
 
     // TCP Connection State.
     state: Cell<State>,
@@ -189,7 +353,17 @@ pub struct ControlBlock<const N: usize> {
 
     // This is our receive buffer size, which is also the maximum size of our receive window.
     // Note: The maximum possible advertised window is 1 GiB with window scaling and 64 KiB without.
-    receive_buffer_size: u32,
+    receive_buffer_size: Cell<u32>,
+
+    // Receive window that we last advertised to our peer.
+    advertised_window: Cell<u32>,
+
+    // Right edge of the receive window that we last advertised to our peer, which we must not move back (RFC 1122
+    // Section 4.2.2.16).
+    advertised_right_edge: Cell<SeqNumber>,
+
+    // Receive window auto-tuning state, if enabled.  This adjusts the receive buffer size above.
+    rcv_window_tuner: Option<RefCell<ReceiveWindowTuner>>,
 
     // TODO: Review how this is used.  We could have separate window scale factors, so there should be one for the
     // receiver and one for the sender.
@@ -204,7 +378,7 @@ pub struct ControlBlock<const N: usize> {
     // receive window) but can't yet present to the user because we're missing some other data that comes between this
     // and what we've already presented to the user.
     //
-    out_of_order: RefCell<VecDeque<(SeqNumber, DemiBuffer)>>,
+    out_of_order: RefCell<Box<dyn ReceiveBuffer>>,
 
     // The sequence number of the FIN, if we received it out-of-order.
     // Note: This could just be a boolean to remember if we got a FIN; the sequence number is for checking correctness.
@@ -216,10 +390,74 @@ pub struct ControlBlock<const N: usize> {
     // Whether the user has called close.
     pub user_is_done_sending: Cell<bool>,
 
+    // Whether we reset this connection because its receive queue grew past the configured limit.
+    receive_buffer_exhausted: Cell<bool>,
+
+    // Whether we aborted this connection because sent data remained unacknowledged for too long, either past the user
+    // timeout or past the deadline of a push.
+    timed_out: Cell<bool>,
+
+    // Whether our peer reset this connection.
+    reset_by_peer: Cell<bool>,
+
+    // Span within which operations on this connection run.
+    span: ConnectionSpan,
+
     // Congestion control trait implementation we're currently using.
     // TODO: Consider switching this to a static implementation to avoid V-table call overhead.
     cc: Box<dyn congestion_control::CongestionControl>,
 
+    // Whether both ends agreed to use Explicit Congestion Notification (RFC 3168) during the handshake.
+    ecn: bool,
+
+    // Whether we should set ECE on outgoing segments, because we received a CE-marked segment that our peer has not
+    // yet answered with CWR.
+    ecn_echo: Cell<bool>,
+
+    // Whether we should set CWR on the next new data segment, because we reduced our congestion window in response to
+    // an ECE from our peer.
+    ecn_cwr_pending: Cell<bool>,
+
+    // Number of CE-marked segments that we received.
+    ecn_ce_events: Cell<u64>,
+
+    // Number of out-of-order segments that we dropped because too many were buffered already.
+    dropped_ooo_segments: Cell<u64>,
+
+    // Signs the segments that we send, if the connection is authenticated.
+    authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+
+    // Most recent timestamp that we received from our peer (TS.Recent in RFC 7323), if both ends agreed to use
+    // timestamps during the handshake.
+    ts_recent: Option<Cell<u32>>,
+
+    // Timestamp of the last segment that we sent.
+    ts_sent: Cell<u32>,
+
+    // Time at which we leave TIME-WAIT, if we are in it.
+    time_wait_deadline: Cell<Option<Instant>>,
+
+    // Maximum segment sizes that black hole detection settled on for remote hosts.
+    pmtu_table: PmtuTable,
+
+    // Telemetry on remote hosts, which our round-trip time samples feed into.
+    telemetry: PeerTelemetry,
+
+    // Queue that we report resets and closes of this connection to, along with the queue descriptor of the socket that
+    // the application knows this connection by. This is only set while the application holds that queue descriptor.
+    events: RefCell<Option<(ConnEventQueue, QDesc)>>,
+
+    // Whether black hole detection lowered our MSS, and we still wait for an ACK to confirm that the new one makes it
+    // through the path.
+    mss_probe_pending: Cell<bool>,
+
+    // Number of retransmission timeouts in a row on a full-sized segment, since we last lowered our MSS or received an
+    // ACK for new data.
+    black_hole_rtos: Cell<u32>,
+
+    // Time at which we last sent or received a segment.
+    last_activity: Cell<Instant>,
+
     // Current retransmission timer expiration time.
     // TODO: Consider storing this directly in the RtoCalculator.
     retransmit_deadline: WatchedValue<Option<Instant>>,
@@ -230,31 +468,81 @@ pub struct ControlBlock<const N: usize> {
 
 //==============================================================================
 
-impl<const N: usize> ControlBlock<N> {
+impl ControlBlock {
     pub fn new(
         local: SocketAddrV4,
         remote: SocketAddrV4,
-        rt: Rc<dyn NetworkRuntime<N>>,
-        scheduler: Scheduler,
-        clock: TimerRc,
-        local_link_addr: MacAddress,
-        tcp_config: TcpConfig,
-        arp: ArpPeer<N>,
-        receiver_seq_no: SeqNumber,
-        ack_delay_timeout: Duration,
-        receiver_window_size: u32,
-        receiver_window_scale: u32,
-        sender_seq_no: SeqNumber,
-        sender_window_size: u32,
-        sender_window_scale: u8,
-        sender_mss: usize,
-        cc_constructor: CongestionControlConstructor,
-        congestion_control_options: Option<congestion_control::Options>,
+        next_hop: Ipv4Addr,
+        ctx: TcpContext,
+        handshake: HandshakeParameters,
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
     ) -> Self {
-        let sender: Sender<N> = Sender::new(sender_seq_no, sender_window_size, sender_window_scale, sender_mss);
+        let TcpContext {
+            rt,
+            scheduler,
+            clock,
+            local_link_addr,
+            tcp_config,
+            arp,
+            total_rx_buffer_bytes,
+            pmtu_table,
+            telemetry,
+            ..
+        } = ctx;
+        let HandshakeParameters {
+            receiver_seq_no,
+            receiver_window_size,
+            receiver_window_scale,
+            sender_seq_no,
+            sender_window_size,
+            sender_window_scale,
+            sender_mss,
+            ecn,
+            remote_timestamp,
+        } = handshake;
+        // Leave room for the signature option, followed by an end of options list and padded to 32 bits, in every
+        // segment that we send.
+        let sender_mss: usize = match &authenticator {
+            Some(authenticator) => sender_mss.saturating_sub((authenticator.option_size() + 4) & !0x3),
+            None => sender_mss,
+        };
+        // Likewise for the timestamps option.
+        let sender_mss: usize = match remote_timestamp {
+            Some(_) => sender_mss.saturating_sub(TIMESTAMPS_OPTION_SIZE),
+            None => sender_mss,
+        };
+        // Do not send segments that are known to vanish on the path to our peer.
+        let sender_mss: usize = match pmtu_table.get(remote.ip(), clock.now()) {
+            Some(path_mss) => cmp::min(sender_mss, path_mss),
+            None => sender_mss,
+        };
+        let sender: Sender = Sender::new(sender_seq_no, sender_window_size, sender_window_scale, sender_mss);
+        let rcv_window_tuner: Option<RefCell<ReceiveWindowTuner>> = if tcp_config.get_receive_window_auto_tuning() {
+            // We cannot advertise a window larger than what fits in the header with our window scale.
+            let max_window_size: u32 = cmp::min(
+                tcp_config.get_max_receive_window_size(),
+                (u16::MAX as u32) << receiver_window_scale,
+            );
+            Some(RefCell::new(ReceiveWindowTuner::new(
+                receiver_window_size,
+                max_window_size,
+                clock.now(),
+            )))
+        } else {
+            None
+        };
+        let out_of_order: Box<dyn ReceiveBuffer> = new_receive_buffer(
+            tcp_config.get_receive_buffer_mode(),
+            receiver_window_size as usize,
+            tcp_config.get_max_out_of_order_segments(),
+            receiver_seq_no,
+        );
+        let ack_delay_timeout: Duration = tcp_config.get_ack_delay_timeout();
+        let now: Instant = clock.now();
         Self {
             local,
             remote,
+            next_hop,
             rt,
             scheduler,
             clock,
@@ -265,14 +553,36 @@ impl<const N: usize> ControlBlock<N> {
             state: Cell::new(State::Established),
             ack_delay_timeout,
             ack_deadline: WatchedValue::new(None),
-            receive_buffer_size: receiver_window_size,
+            receive_buffer_size: Cell::new(receiver_window_size),
+            advertised_window: Cell::new(receiver_window_size),
+            advertised_right_edge: Cell::new(receiver_seq_no + SeqNumber::from(receiver_window_size)),
+            rcv_window_tuner,
             window_scale: receiver_window_scale,
             waker: RefCell::new(None),
-            out_of_order: RefCell::new(VecDeque::new()),
+            out_of_order: RefCell::new(out_of_order),
             out_of_order_fin: Cell::new(Option::None),
-            receiver: Receiver::new(receiver_seq_no, receiver_seq_no),
+            receiver: Receiver::new(receiver_seq_no, receiver_seq_no, total_rx_buffer_bytes),
             user_is_done_sending: Cell::new(false),
-            cc: cc_constructor(sender_mss, sender_seq_no, congestion_control_options),
+            receive_buffer_exhausted: Cell::new(false),
+            timed_out: Cell::new(false),
+            reset_by_peer: Cell::new(false),
+            span: ConnectionSpan::new(local, remote),
+            cc: congestion_control::select(ecn)(sender_mss, sender_seq_no, None),
+            ecn,
+            ecn_echo: Cell::new(false),
+            ecn_cwr_pending: Cell::new(false),
+            ecn_ce_events: Cell::new(0),
+            dropped_ooo_segments: Cell::new(0),
+            authenticator,
+            ts_recent: remote_timestamp.map(Cell::new),
+            ts_sent: Cell::new(0),
+            time_wait_deadline: Cell::new(None),
+            pmtu_table,
+            telemetry,
+            events: RefCell::new(None),
+            mss_probe_pending: Cell::new(false),
+            black_hole_rtos: Cell::new(0),
+            last_activity: Cell::new(now),
             retransmit_deadline: WatchedValue::new(None),
             rto_calculator: RefCell::new(RtoCalculator::new()),
         }
@@ -286,19 +596,99 @@ impl<const N: usize> ControlBlock<N> {
         self.remote
     }
 
+    pub fn get_next_hop(&self) -> Ipv4Addr {
+        self.next_hop
+    }
+
     // TODO: Remove this.  ARP doesn't belong at this layer.
-    pub fn arp(&self) -> Rc<ArpPeer<N>> {
+    pub fn arp(&self) -> Rc<ArpPeer> {
         self.arp.clone()
     }
 
+    /// Reports the resets and closes of this connection to `events`, as happening to the socket referred to by `qd`.
+    /// Passing `None` stops reporting, as needed once the application let go of the socket.
+    pub fn set_event_queue(&self, events: Option<(ConnEventQueue, QDesc)>) {
+        *self.events.borrow_mut() = events;
+    }
+
+    /// Reports an event of this connection, if we have a queue to report to.
+    fn report(&self, event: impl FnOnce(QDesc) -> ConnEvent) {
+        if let Some((events, qd)) = &*self.events.borrow() {
+            events.push(event(*qd));
+        }
+    }
+
+    /// Moves this connection to `state`, because of `event`.
+    fn set_state(&self, state: State, event: &'static str) {
+        let old: State = self.state.replace(state);
+        if old != state {
+            trace::transition(self.local, self.remote, old.into(), state.into(), event);
+        }
+    }
+
     pub fn send(&self, buf: DemiBuffer) -> Result<(), Fail> {
+        let _span = self.span.enter();
+        if self.receive_buffer_exhausted.get() {
+            return Err(Fail::new(libc::ECONNRESET, "connection reset"));
+        }
+        if self.timed_out.get() {
+            return Err(Fail::new(libc::ETIMEDOUT, "connection timed out"));
+        }
+        if self.reset_by_peer.get() {
+            return Err(Fail::new(libc::ECONNRESET, "connection reset by peer"));
+        }
         self.sender.send(buf, self)
     }
 
+    /// Forces transmission of any buffered send data and of any pending delayed ACK.
+    pub fn flush(&self) {
+        self.sender.flush(self);
+
+        // Any data segment that we sent above carries an ACK, so this only fires if we have nothing to send.
+        if self.ack_deadline.get().is_some() {
+            self.send_ack();
+        }
+    }
+
     pub fn retransmit(&self) {
         self.sender.retransmit(self)
     }
 
+    /// Black hole detection (RFC 2923). If the oldest unacknowledged segment is full-sized and timed out repeatedly, the
+    /// path may be silently dropping segments that large, thus halve the MSS and send all unacknowledged data again in
+    /// smaller segments. Returns whether the MSS was lowered, in which case the caller should not retransmit the oldest
+    /// segment itself.
+    pub fn probe_black_hole(&self) -> bool {
+        if !self.tcp_config.get_pmtud_probe_enabled() {
+            return false;
+        }
+        let mss: usize = self.sender.get_mss();
+        let min_mss: usize = self.tcp_config.get_pmtud_min_mss();
+        match self.sender.get_oldest_unacked_len() {
+            Some(len) if len >= mss && mss > min_mss => {
+                let rtos: u32 = self.black_hole_rtos.get() + 1;
+                if rtos < BLACK_HOLE_RTO_COUNT {
+                    self.black_hole_rtos.set(rtos);
+                    return false;
+                }
+                self.black_hole_rtos.set(0);
+                let new_mss: usize = cmp::max(mss / 2, min_mss);
+                warn!(
+                    "probe_black_hole(): lowering MSS (remote={:?}, mss={:?}, new_mss={:?})",
+                    self.remote, mss, new_mss
+                );
+                self.sender.set_mss(new_mss);
+                self.sender.requeue_unacked();
+                self.mss_probe_pending.set(true);
+                true
+            },
+            _ => {
+                self.black_hole_rtos.set(0);
+                false
+            },
+        }
+    }
+
     pub fn congestion_control_watch_retransmit_now_flag(&self) -> (bool, WatchFuture<bool>) {
         self.cc.watch_retransmit_now_flag()
     }
@@ -376,13 +766,60 @@ impl<const N: usize> ControlBlock<N> {
     }
 
     pub fn rto_add_sample(&self, rtt: Duration) {
+        self.telemetry.record_rtt(*self.remote.ip(), rtt, self.clock.now());
         self.rto_calculator.borrow_mut().add_sample(rtt)
     }
 
+    /// Clamps an interval between zero-window probes to the configured bounds.
+    pub fn clamp_persist_timeout(&self, timeout: Duration) -> Duration {
+        timeout.clamp(
+            self.tcp_config.get_persist_timeout_min(),
+            self.tcp_config.get_persist_timeout_max(),
+        )
+    }
+
+    /// Gets the interval between keep-alive probes on idle connections, if NAT traversal keep-alives are enabled.
+    pub fn get_keepalive_interval(&self) -> Option<Duration> {
+        if self.tcp_config.get_nat_traversal_keepalive() {
+            Some(self.tcp_config.get_keepalive_nat_interval())
+        } else {
+            None
+        }
+    }
+
+    /// Gets the time at which we last sent or received a segment.
+    pub fn get_last_activity(&self) -> Instant {
+        self.last_activity.get()
+    }
+
+    /// Sends a keep-alive probe, unless we are waiting for our peer to acknowledge something, in which case the
+    /// connection is not idle. The probe carries a single null byte at the sequence number of the last byte that our
+    /// peer acknowledged, so that our peer drops it and replies with an ACK, while NAT boxes on the path see a segment
+    /// with data. Returns whether a probe was sent.
+    pub fn send_keepalive(&self, remote_link_addr: MacAddress) -> bool {
+        let (send_unacked, _): (SeqNumber, _) = self.get_send_unacked();
+        let (send_next, _): (SeqNumber, _) = self.get_send_next();
+        if !matches!(self.state.get(), State::Established | State::CloseWait) || send_unacked != send_next {
+            return false;
+        }
+
+        let mut header: TcpHeader = self.tcp_header();
+        header.seq_num = send_unacked - SeqNumber::from(1);
+        let mut body: DemiBuffer = DemiBuffer::new(1);
+        body[0] = 0;
+        debug!("Sending keep-alive probe: {:?}", header);
+        self.emit(header, Some(body), remote_link_addr);
+        true
+    }
+
     pub fn rto(&self) -> Duration {
         self.rto_calculator.borrow().rto()
     }
 
+    pub fn srtt(&self) -> Duration {
+        self.rto_calculator.borrow().srtt()
+    }
+
     pub fn rto_back_off(&self) {
         self.rto_calculator.borrow_mut().back_off()
     }
@@ -391,17 +828,15 @@ impl<const N: usize> ControlBlock<N> {
         self.sender.top_size_unsent()
     }
 
-    pub fn pop_unsent_segment(&self, max_bytes: usize) -> Option<(DemiBuffer, bool)> {
-        self.sender.pop_unsent(max_bytes)
-    }
-
-    pub fn pop_one_unsent_byte(&self) -> Option<DemiBuffer> {
-        self.sender.pop_one_unsent_byte()
+    pub fn send_next_segment(&self, remote_link_addr: MacAddress, max_size: usize, sent_data: u32) -> bool {
+        self.sender
+            .send_next_segment(self, remote_link_addr, max_size, sent_data)
     }
 
     // This is the main TCP receive routine.
     //
-    pub fn receive(&self, header: &mut TcpHeader, mut data: DemiBuffer) {
+    pub fn receive(&self, ip_header: &Ipv4Header, header: &mut TcpHeader, mut data: DemiBuffer) {
+        let _span = self.span.enter();
         debug!(
             "{:?} Connection Receiving {} bytes + {:?}",
             self.state.get(),
@@ -409,11 +844,36 @@ impl<const N: usize> ControlBlock<N> {
             header
         );
 
+        // We already reset this connection, so there is nothing left to do with incoming segments.
+        if self.receive_buffer_exhausted.get() || self.timed_out.get() {
+            debug!("Dropping segment received on reset connection");
+            return;
+        }
+
         let mut should_schedule_ack: bool = false;
 
         // TODO: We're probably getting "now" here in order to get a timestamp as close as possible to when we received
         // the packet.  However, this is wasteful if we don't take a path below that actually uses it.  Review this.
         let now: Instant = self.clock.now();
+        self.last_activity.set(now);
+
+        // Shrink the receive window of idle connections before checking the segment against it.
+        if let Some(ref tuner) = self.rcv_window_tuner {
+            let window_size: u32 = tuner.borrow_mut().on_idle_check(now, self.srtt());
+            self.resize_receive_buffer(window_size);
+        }
+
+        // Protect against wrapped sequence numbers by dropping segments whose timestamp is older than the most recent
+        // one that we received (PAWS, RFC 7323).  These may be old duplicates, e.g. of a previous incarnation of this
+        // connection.
+        let timestamps: Option<(u32, u32)> = self.ts_recent.as_ref().and(find_timestamps(header));
+        if let (Some(ts_recent), Some((sender_timestamp, _))) = (&self.ts_recent, timestamps) {
+            if !header.rst && timestamp_after(ts_recent.get(), sender_timestamp) {
+                debug!("Dropping segment with old timestamp {}", sender_timestamp);
+                self.send_ack();
+                return;
+            }
+        }
 
         // Check to see if the segment is acceptable sequence-wise (i.e. contains some data that fits within the receive
         // window, or is a non-data segment with a sequence number that falls within the window).  Unacceptable segments
@@ -527,7 +987,15 @@ impl<const N: usize> ControlBlock<N> {
         // this point, and only proceed onwards if seg_start == receive_next.  But we process any RSTs, SYNs, or ACKs
         // we receive (as long as they're in the window) as we receive them, even if they're out-of-order.  It's only
         // when we get to processing the data (and FIN) that we store aside any out-of-order segments for later.
-        debug_assert!(receive_next <= seg_start && seg_end < after_receive_window);
+        // Segments that take up no sequence space are acceptable at RCV.NXT even when our receive window is closed.
+        debug_assert!(receive_next <= seg_start && (seg_len == 0 || seg_end < after_receive_window));
+
+        // Remember the timestamp of our peer, which we echo in the segments that we send.
+        if let (Some(ts_recent), Some((sender_timestamp, _))) = (&self.ts_recent, timestamps) {
+            if seg_start == receive_next {
+                ts_recent.set(sender_timestamp);
+            }
+        }
 
         // Check the RST bit.
         if header.rst {
@@ -539,11 +1007,17 @@ impl<const N: usize> ControlBlock<N> {
             match self.state.get() {
                 // Data transfer states.
                 State::Established | State::FinWait1 | State::FinWait2 | State::CloseWait => {
-                    // TODO: Return all outstanding user Receive and Send requests with "reset" responses.
                     // TODO: Flush all segment queues.
 
                     // Enter Closed state.
-                    self.state.set(State::Closed);
+                    self.set_state(State::Closed, "RST_received");
+                    self.reset_by_peer.set(true);
+                    self.report(|qd| ConnEvent::Reset(qd, libc::ECONNRESET));
+
+                    // Wake up any pending pop, so that it learns about the reset.
+                    if let Some(w) = self.waker.borrow_mut().take() {
+                        w.wake()
+                    }
 
                     // TODO: Delete the ControlBlock.
                     return;
@@ -551,8 +1025,13 @@ impl<const N: usize> ControlBlock<N> {
 
                 // Closing states.
                 State::Closing | State::LastAck | State::TimeWait => {
+                    // Our peer gave up before we were done closing, unless we reported the close already.
+                    if self.state.get() != State::TimeWait {
+                        self.report(|qd| ConnEvent::Reset(qd, libc::ECONNRESET));
+                    }
+
                     // Enter Closed state.
-                    self.state.set(State::Closed);
+                    self.set_state(State::Closed, "RST_received");
 
                     // TODO: Delete the ControlBlock.
                     return;
@@ -578,7 +1057,8 @@ impl<const N: usize> ControlBlock<N> {
             // TODO: Flush all segment queues.
 
             // Enter Closed state.
-            self.state.set(State::Closed);
+            self.set_state(State::Closed, "SYN_received");
+            self.report(|qd| ConnEvent::Reset(qd, libc::ECONNRESET));
 
             // TODO: Delete the ControlBlock.
             return;
@@ -614,13 +1094,27 @@ impl<const N: usize> ControlBlock<N> {
             header.ack_num,
         );
 
+        // Check for congestion signals. This comes after the ACK is accounted for, so that the ACK that echoes a
+        // signal does not grow the congestion window back once it is reduced (RFC 3168).
+        if self.ecn {
+            self.receive_ecn(ip_header, header);
+        }
+
         if send_unacknowledged < header.ack_num {
             if header.ack_num <= send_next {
                 // This segment acknowledges new data (possibly and/or FIN).
                 let bytes_acknowledged: u32 = (header.ack_num - send_unacknowledged).into();
 
                 // Remove the now acknowledged data from the unacknowledged queue.
-                self.sender.remove_acknowledged_data(self, bytes_acknowledged, now);
+                if let Some(rtt) = self.sender.remove_acknowledged_data(bytes_acknowledged, now) {
+                    self.rto_add_sample(rtt);
+                }
+
+                // Segments of the MSS that black hole detection settled on make it through, so remember it.
+                self.black_hole_rtos.set(0);
+                if self.mss_probe_pending.replace(false) {
+                    self.pmtu_table.record(*self.remote.ip(), self.sender.get_mss(), now);
+                }
 
                 // Update SND.UNA to SEG.ACK.
                 self.sender.send_unacked.set(header.ack_num);
@@ -639,20 +1133,20 @@ impl<const N: usize> ControlBlock<N> {
                         State::Established => (), // Common case.  Nothing more to do.
                         State::FinWait1 => {
                             // Our FIN is now ACK'd, so enter FIN-WAIT-2.
-                            self.state.set(State::FinWait2);
+                            self.set_state(State::FinWait2, "ACK_received");
                         },
                         State::Closing => {
                             // Our FIN is now ACK'd, so enter TIME-WAIT.
-                            self.state.set(State::TimeWait);
+                            self.enter_time_wait(now, "ACK_received");
                         },
                         State::LastAck => {
                             // Our FIN is now ACK'd, so this connection can be safely closed.  In LAST-ACK state we
                             // were just waiting for all of our sent data (including FIN) to be ACK'd, so now that it
                             // is, we can delete our state (we maintained it in case we needed to retransmit something,
                             // but we had already sent everything we're ever going to send (incl. FIN) at least once).
-                            self.state.set(State::Closed);
+                            self.set_state(State::Closed, "ACK_received");
+                            self.report(ConnEvent::Closed);
                         },
-                        // TODO: Handle TimeWait to Closed transition.
                         _ => (),
                     }
                 } else {
@@ -672,11 +1166,20 @@ impl<const N: usize> ControlBlock<N> {
         } else {
             // Duplicate ACK (doesn't acknowledge anything new).  We can mostly ignore this, except for fast-retransmit.
             // TODO: Implement fast-retransmit.  In which case, we'd increment our dup-ack counter here.
+
+            // It may still carry a window update, though (e.g. in response to a zero-window probe).
+            if header.ack_num == send_unacknowledged {
+                self.sender.update_send_window(header);
+            }
         }
 
-        // TODO: Check the URG bit.  If we decide to support this, how should we do it?
-        if header.urg {
-            warn!("Got packet with URG bit set!");
+        // Urgent data is not supported: there is no out-of-band channel to deliver it through (see RFC 6093).  Following
+        // the BSD interpretation, the urgent pointer points at the byte that follows the urgent byte, which we strip
+        // from the stream so that it never reaches the application.
+        if header.urg && header.urgent_pointer > 0 {
+            let urgent: SeqNumber = header.seq_num + SeqNumber::from(header.urgent_pointer as u32 - 1);
+            debug!("Stripping urgent byte at {}", urgent);
+            self.receiver.set_urgent(urgent);
         }
 
         // We can only process in-order data (or FIN).  Check for out-of-order segment.
@@ -691,11 +1194,12 @@ impl<const N: usize> ControlBlock<N> {
                         if header.fin {
                             seg_len -= 1;
                             self.store_out_of_order_fin(seg_end);
-                            seg_end = seg_end - SeqNumber::from(1);
                         }
                         debug_assert_eq!(seg_len, data.len() as u32);
-                        if seg_len > 0 {
-                            self.store_out_of_order_segment(seg_start, seg_end, data);
+                        if seg_len > 0 && !self.out_of_order.borrow_mut().store(receive_next, seg_start, data) {
+                            // Too many out-of-order segments are buffered already. Our peer will retransmit this one.
+                            debug!("Dropped out-of-order segment");
+                            self.dropped_ooo_segments.set(self.dropped_ooo_segments.get() + 1);
                         }
                         // Sending an ACK here is only a "MAY" according to the RFCs, but helpful for fast retransmit.
                         self.send_ack();
@@ -714,6 +1218,10 @@ impl<const N: usize> ControlBlock<N> {
                 State::Established | State::FinWait1 | State::FinWait2 => {
                     // We can only legitimately receive data in ESTABLISHED, FIN-WAIT-1, and FIN-WAIT-2.
                     header.fin |= self.receive_data(seg_start, data);
+                    if self.receiver.get_buffered_bytes() > self.tcp_config.get_max_receive_buffer_bytes() {
+                        self.reset_on_receive_buffer_exhaustion();
+                        return;
+                    }
                     should_schedule_ack = true;
                 },
                 state => warn!("Ignoring data received after FIN (in state {:?}).", state),
@@ -730,23 +1238,23 @@ impl<const N: usize> ControlBlock<N> {
                 .set(self.receiver.receive_next.get() + SeqNumber::from(1));
 
             match self.state.get() {
-                State::Established => self.state.set(State::CloseWait),
+                State::Established => self.set_state(State::CloseWait, "FIN_received"),
                 State::FinWait1 => {
                     // RFC 793 has a benign logic flaw.  It says "If our FIN has been ACKed (perhaps in this segment),
                     // then enter TIME-WAIT, start the time-wait timer, turn off the other timers;".  But if our FIN
                     // has been ACK'd, we'd be in FIN-WAIT-2 here as a result of processing that ACK (see ACK handling
                     // above) and will enter TIME-WAIT in the FIN-WAIT-2 case below.  So we can skip that clause and go
                     // straight to "otherwise enter the CLOSING state".
-                    self.state.set(State::Closing);
+                    self.set_state(State::Closing, "FIN_received");
                 },
                 State::FinWait2 => {
                     // Enter TIME-WAIT.
-                    self.state.set(State::TimeWait);
-                    // TODO: Start the time-wait timer and turn off the other timers.
+                    self.enter_time_wait(now, "FIN_received");
                 },
                 State::CloseWait | State::Closing | State::LastAck => (), // Remain in current state.
                 State::TimeWait => {
-                    // TODO: Remain in TIME-WAIT.  Restart the 2 MSL time-wait timeout.
+                    // Our peer retransmitted its FIN.  Remain in TIME-WAIT, but restart the 2 MSL time-wait timeout.
+                    self.enter_time_wait(now, "FIN_received");
                 },
                 state => panic!("Bad TCP state {:?}", state), // Should never happen.
             }
@@ -773,9 +1281,20 @@ impl<const N: usize> ControlBlock<N> {
                 // Start the delayed ACK timer to ensure an ACK gets sent soon even if no piggyback opportunity occurs.
                 self.ack_deadline.set(Some(now + self.ack_delay_timeout));
             } else {
-                // We already owe our peer an ACK (the timer was already running), so cancel the timer and ACK now.
-                self.ack_deadline.set(None);
-                self.send_ack();
+                // We already owe our peer an ACK (the timer was already running), so ACK now.  If ACK holding is
+                // enabled, give the application a brief chance to push a response that the ACK can ride on instead.
+                // The hold never pushes the ACK past the delayed ACK deadline, and it is short enough not to inflate
+                // our peer's RTT estimate noticeably.
+                let ack_hold_timeout: Duration = self.tcp_config.get_ack_hold_timeout();
+                if ack_hold_timeout.is_zero() {
+                    self.ack_deadline.set(None);
+                    self.send_ack();
+                } else {
+                    let hold_deadline: Instant = now + ack_hold_timeout;
+                    if let Some(deadline) = self.ack_deadline.get() {
+                        self.ack_deadline.set(Some(cmp::min(deadline, hold_deadline)));
+                    }
+                }
             }
         }
     }
@@ -788,12 +1307,18 @@ impl<const N: usize> ControlBlock<N> {
     /// Note this routine will only be called for connections with a ControlBlock (i.e. in state ESTABLISHED or later).
     ///
     pub fn close(&self) -> Result<(), Fail> {
+        let _span = self.span.enter();
         // Check to see if close has already been called, as we should only do this once.
         if self.user_is_done_sending.get() {
             // Review: Should we return an error here instead?  RFC 793 recommends a "connection closing" error.
             return Ok(());
         }
 
+        // There is nothing left to shut down once our peer has reset the connection.
+        if self.reset_by_peer.get() {
+            return Ok(());
+        }
+
         // In the normal case, we'll be in either ESTABLISHED or CLOSE_WAIT here (depending upon whether we've received
         // a FIN from our peer yet).  Queue up a FIN to be sent, and attempt to send it immediately (if possible).  We
         // only change state to FIN-WAIT-1 or LAST_ACK after we've actually been able to send the FIN.
@@ -833,6 +1358,9 @@ impl<const N: usize> ControlBlock<N> {
         header.ack = true;
         header.ack_num = self.receiver.receive_next.get();
 
+        // Keep echoing congestion to our peer until it tells us that it reduced its congestion window.
+        header.ece = self.ecn_echo.get();
+
         // Return this header.
         header
     }
@@ -847,14 +1375,186 @@ impl<const N: usize> ControlBlock<N> {
 
         // TODO: Remove this if clause once emit() is fixed to not require the remote hardware addr (this should be
         // left to the ARP layer and not exposed to TCP).
-        if let Some(remote_link_addr) = self.arp().try_query(self.remote.ip().clone()) {
+        if let Some(remote_link_addr) = self.arp().try_query(self.next_hop) {
+            self.emit(header, None, remote_link_addr);
+        }
+    }
+
+    /// Processes the Explicit Congestion Notification (RFC 3168) signals carried by an incoming segment.
+    fn receive_ecn(&self, ip_header: &Ipv4Header, header: &TcpHeader) {
+        // Our peer reduced its congestion window in response to our echo.
+        if header.cwr {
+            self.ecn_echo.set(false);
+        }
+
+        // A router on the path from our peer is congested.  Echo this back until our peer answers with CWR.
+        if ip_header.get_ecn() == IPV4_ECN_CE {
+            debug!("Received CE-marked segment");
+            self.ecn_ce_events.set(self.ecn_ce_events.get() + 1);
+            self.ecn_echo.set(true);
+        }
+
+        // A router on the path to our peer is congested.  Reduce our congestion window, and tell our peer about it.
+        if header.ece && !header.syn {
+            let (send_unacknowledged, _): (SeqNumber, _) = self.sender.get_send_unacked();
+            let (send_next, _): (SeqNumber, _) = self.sender.get_send_next();
+            self.cc.on_congestion_signal(send_unacknowledged, send_next);
+            self.ecn_cwr_pending.set(true);
+        }
+    }
+
+    /// Enters (or stays in) TIME-WAIT, where we linger for twice the maximum segment lifetime, so that any segment
+    /// still in flight expires before the connection can be reincarnated.
+    fn enter_time_wait(&self, now: Instant, event: &'static str) {
+        if self.state.get() != State::TimeWait {
+            self.report(ConnEvent::Closed);
+        }
+        self.set_state(State::TimeWait, event);
+        self.retransmit_deadline.set(None);
+        self.time_wait_deadline.set(Some(now + 2 * self.tcp_config.get_msl()));
+    }
+
+    /// Checks whether this connection is in TIME-WAIT.
+    pub fn is_time_wait(&self) -> bool {
+        self.state.get() == State::TimeWait
+    }
+
+    /// Checks whether this connection is gone, either because it was closed or because it stayed in TIME-WAIT for long
+    /// enough.
+    pub fn is_closed(&self) -> bool {
+        match self.state.get() {
+            State::Closed => true,
+            State::TimeWait => self
+                .time_wait_deadline
+                .get()
+                .is_some_and(|deadline| deadline <= self.clock.now()),
+            _ => false,
+        }
+    }
+
+    /// Returns the timestamp of the last segment that we sent, if this connection uses timestamps.
+    pub fn get_last_timestamp(&self) -> Option<u32> {
+        self.ts_recent.as_ref().map(|_| self.ts_sent.get())
+    }
+
+    /// Returns statistics about this connection.
+    pub fn get_stats(&self) -> TcpConnectionStats {
+        TcpConnectionStats {
+            tcp_ecn_ce_events: self.ecn_ce_events.get(),
+            dropped_ooo_segments: self.dropped_ooo_segments.get(),
+        }
+    }
+
+    /// Returns the number of bytes that this connection sent, received and retransmitted so far.
+    pub fn get_byte_stats(&self) -> ByteStats {
+        ByteStats {
+            sent: self.sender.get_sent_bytes(),
+            received: self.receiver.get_received_bytes(),
+            retransmitted: self.sender.get_retransmitted().1,
+        }
+    }
+
+    /// Returns the internals of this connection.
+    pub fn get_info(&self) -> TcpInfo {
+        let rto_calculator: Ref<RtoCalculator> = self.rto_calculator.borrow();
+        let (retransmitted_segments, retransmitted_bytes): (u64, u64) = self.sender.get_retransmitted();
+        TcpInfo {
+            state: self.state.get(),
+            srtt: rto_calculator.has_sample().then(|| rto_calculator.srtt()),
+            rttvar: rto_calculator.has_sample().then(|| rto_calculator.rttvar()),
+            cwnd: self.cc.get_cwnd(),
+            ssthresh: self.cc.get_ssthresh(),
+            retransmitted_segments,
+            retransmitted_bytes,
+        }
+    }
+
+    /// Abort this connection because the application is not draining its receive queue fast enough.  We send a RST
+    /// to our peer and drop any data that we have buffered, so that it no longer counts against our memory limits.
+    fn reset_on_receive_buffer_exhaustion(&self) {
+        warn!(
+            "Resetting connection {:?} -> {:?}: receive buffer exhausted ({} bytes)",
+            self.local,
+            self.remote,
+            self.receiver.get_buffered_bytes()
+        );
+
+        self.abort();
+        self.receive_buffer_exhausted.set(true);
+        self.report(|qd| ConnEvent::Reset(qd, libc::ENOBUFS));
+        self.receiver.clear();
+        self.out_of_order.borrow_mut().clear();
+
+        // Wake up any pending pop, so that it learns about the reset.
+        if let Some(w) = self.waker.borrow_mut().take() {
+            w.wake()
+        }
+    }
+
+    /// Returns the time at which this connection should be aborted because its oldest unacknowledged data has been
+    /// outstanding for longer than the user timeout (RFC 5482). Returns `None` if there is no user timeout or no data
+    /// is outstanding.
+    pub fn get_user_timeout_deadline(&self) -> Option<Instant> {
+        let user_timeout: Duration = self.tcp_config.get_user_timeout()?;
+        let oldest_unacked_tx: Instant = self.sender.get_oldest_unacked_tx()?;
+        Some(oldest_unacked_tx + user_timeout)
+    }
+
+    /// Abort this connection because sent data remained unacknowledged for too long.  We send a RST to our peer, in
+    /// case it is still listening, and fail any further operations with ETIMEDOUT.
+    pub fn abort_on_timeout(&self, cause: &str) {
+        warn!("Aborting connection {:?} -> {:?}: {}", self.local, self.remote, cause);
+
+        self.abort();
+        if !self.timed_out.replace(true) {
+            self.report(|qd| ConnEvent::Reset(qd, libc::ETIMEDOUT));
+        }
+
+        // Wake up the retransmitter, so that it stops.
+        self.set_retransmit_deadline(None);
+
+        // Wake up any pending pop, so that it learns about the timeout.
+        if let Some(w) = self.waker.borrow_mut().take() {
+            w.wake()
+        }
+    }
+
+    /// Returns whether this connection was aborted because sent data remained unacknowledged for too long.
+    pub fn has_timed_out(&self) -> bool {
+        self.timed_out.get()
+    }
+
+    /// Waits until our peer acknowledges all data up to (but not including) `seq_no`. This fails if the connection is
+    /// aborted first.
+    pub async fn wait_for_ack(&self, seq_no: SeqNumber) -> Result<(), Fail> {
+        loop {
+            let (send_unacked, send_unacked_changed): (SeqNumber, WatchFuture<SeqNumber>) = self.get_send_unacked();
+            if send_unacked >= seq_no {
+                return Ok(());
+            }
+            if self.timed_out.get() {
+                return Err(Fail::new(libc::ETIMEDOUT, "connection timed out"));
+            }
+            send_unacked_changed.await;
+        }
+    }
+
+    /// Aborts this connection by sending a RST to our peer and moving straight to the closed state.
+    pub fn abort(&self) {
+        let _span = self.span.enter();
+        let mut header: TcpHeader = self.tcp_header();
+        let (seq_num, _): (SeqNumber, _) = self.get_send_next();
+        header.seq_num = seq_num;
+        header.rst = true;
+        if let Some(remote_link_addr) = self.arp().try_query(self.next_hop) {
             self.emit(header, None, remote_link_addr);
         }
+        self.set_state(State::Closed, "abort");
     }
 
     /// Transmit this message to our connected peer.
     ///
-    pub fn emit(&self, header: TcpHeader, body: Option<DemiBuffer>, remote_link_addr: MacAddress) {
+    pub fn emit(&self, mut header: TcpHeader, body: Option<DemiBuffer>, remote_link_addr: MacAddress) {
         // Only perform this debug print in debug builds.  debug_assertions is compiler set in non-optimized builds.
         #[cfg(debug_assertions)]
         if body.is_some() {
@@ -867,12 +1567,43 @@ impl<const N: usize> ControlBlock<N> {
         debug_assert!(header.ack);
 
         let sent_fin: bool = header.fin;
+        let window_size: u32 = (header.window_size as u32) << self.window_scale;
+        let right_edge: SeqNumber = header.ack_num + SeqNumber::from(window_size);
+
+        // Mark new data segments as ECN-capable (RFC 3168).  Pure ACKs and retransmissions are never marked.
+        let mut ipv4_hdr: Ipv4Header =
+            Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP);
+        ipv4_hdr.set_identification(self.rt.next_ipv4_id());
+        let has_data: bool = body.as_ref().is_some_and(|body| !body.is_empty());
+        if self.ecn && has_data && header.seq_num == self.sender.get_send_next().0 {
+            ipv4_hdr.set_ecn(IPV4_ECN_ECT0);
+            if self.ecn_cwr_pending.replace(false) {
+                header.cwr = true;
+            }
+        }
+
+        if let Some(ts_recent) = &self.ts_recent {
+            let sender_timestamp: u32 = timestamp_value(&self.clock);
+            header.push_option(TcpOptions2::Timestamp {
+                sender_timestamp,
+                echo_timestamp: ts_recent.get(),
+            });
+            self.ts_sent.set(sender_timestamp);
+        }
+
+        if let Some(authenticator) = &self.authenticator {
+            let data: &[u8] = match &body {
+                Some(body) => &body[..],
+                None => &[],
+            };
+            header.sign(authenticator.as_ref(), &ipv4_hdr, data);
+        }
 
         // Prepare description of TCP segment to send.
         // TODO: Change this to call lower levels to fill in their header information, handle routing, ARPing, etc.
         let segment = TcpSegment {
             ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
-            ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP),
+            ipv4_hdr,
             tcp_hdr: header,
             data: body,
             tx_checksum_offload: self.tcp_config.get_tx_checksum_offload(),
@@ -886,14 +1617,17 @@ impl<const N: usize> ControlBlock<N> {
 
         // Since we sent an ACK, cancel any outstanding delayed ACK request.
         self.set_ack_deadline(None);
+        self.advertised_window.set(window_size);
+        self.advertised_right_edge.set(right_edge);
+        self.last_activity.set(self.clock.now());
 
         // If we sent a FIN, update our protocol state.
         if sent_fin {
             match self.state.get() {
                 // Active close.
-                State::Established => self.state.set(State::FinWait1),
+                State::Established => self.set_state(State::FinWait1, "FIN_sent"),
                 // Passive close.
-                State::CloseWait => self.state.set(State::LastAck),
+                State::CloseWait => self.set_state(State::LastAck, "FIN_sent"),
                 // We can legitimately retransmit the FIN in these states.  And we stay there until the FIN is ACK'd.
                 State::FinWait1 | State::LastAck => {},
                 // We shouldn't be sending a FIN from any other state.
@@ -906,6 +1640,11 @@ impl<const N: usize> ControlBlock<N> {
         self.sender.remote_mss()
     }
 
+    /// Returns the number of bytes that were received in order and are waiting to be popped.
+    pub fn bytes_available(&self) -> usize {
+        self.receiver.get_buffered_bytes()
+    }
+
     pub fn get_ack_deadline(&self) -> (Option<Instant>, WatchFuture<Option<Instant>>) {
         self.ack_deadline.watch()
     }
@@ -916,7 +1655,7 @@ impl<const N: usize> ControlBlock<N> {
 
     pub fn get_receive_window_size(&self) -> u32 {
         let bytes_unread: u32 = (self.receiver.receive_next.get() - self.receiver.reader_next.get()).into();
-        self.receive_buffer_size - bytes_unread
+        self.receive_buffer_size.get() - bytes_unread
     }
 
     pub fn hdr_window_size(&self) -> u16 {
@@ -934,6 +1673,7 @@ impl<const N: usize> ControlBlock<N> {
     }
 
     pub fn poll_recv(&self, ctx: &mut Context, size: Option<usize>) -> Poll<Result<DemiBuffer, Fail>> {
+        let _span = self.span.enter();
         // TODO: Need to add a way to indicate that the other side closed (i.e. that we've received a FIN).
         // Should we do this via a zero-sized buffer?  Same as with the unsent and unacked queues on the send side?
         //
@@ -941,13 +1681,33 @@ impl<const N: usize> ControlBlock<N> {
         //  if self.receiver.reader_next.get() == self.receiver.receive_next.get() {
         // But that will think data is available to be read once we've received a FIN, because FINs consume sequence
         // number space.  Now we call is_empty() on the receive queue instead.
+        if self.receive_buffer_exhausted.get() {
+            return Poll::Ready(Err(Fail::new(libc::ENOBUFS, "receive buffer exhausted")));
+        }
+        if self.timed_out.get() {
+            return Poll::Ready(Err(Fail::new(libc::ETIMEDOUT, "connection timed out")));
+        }
         if self.receiver.recv_queue.borrow().is_empty() {
+            // Data that arrived before the reset is still handed out.
+            if self.reset_by_peer.get() {
+                return Poll::Ready(Err(Fail::new(libc::ECONNRESET, "connection reset by peer")));
+            }
             *self.waker.borrow_mut() = Some(ctx.waker().clone());
             return Poll::Pending;
         }
 
         match self.receiver.pop(size) {
-            Ok(Some(segment)) => Poll::Ready(Ok(segment)),
+            Ok(Some(segment)) => {
+                if let Some(ref tuner) = self.rcv_window_tuner {
+                    let window_size: u32 = tuner.borrow_mut().on_read(segment.len(), self.clock.now(), self.srtt());
+                    // Let our peer know right away when the window grows, so that it can take advantage of it.
+                    if self.resize_receive_buffer(window_size) {
+                        self.send_ack();
+                    }
+                }
+                self.send_window_update();
+                Poll::Ready(Ok(segment))
+            },
             Ok(None) => {
                 warn!("poll_recv(): polling empty receive queue (ignoring spurious wake up)");
                 Poll::Pending
@@ -956,110 +1716,62 @@ impl<const N: usize> ControlBlock<N> {
         }
     }
 
-    // This routine remembers that we have received an out-of-order FIN.
-    //
-    pub fn store_out_of_order_fin(&self, fin: SeqNumber) {
-        self.out_of_order_fin.set(Some(fin));
+    /// Resizes the receive buffer to `window_size`, but keeps it large enough for the right edge of the window that we
+    /// last advertised, since moving that back would drop data that our peer may already have sent. A smaller size thus
+    /// only takes effect as the application reads data. Returns whether the receive buffer grew.
+    fn resize_receive_buffer(&self, window_size: u32) -> bool {
+        let advertised: u32 = (self.advertised_right_edge.get() - self.receiver.reader_next.get()).into();
+        let window_size: u32 = cmp::max(window_size, advertised);
+        window_size > self.receive_buffer_size.replace(window_size)
     }
 
-    // This routine takes an incoming TCP segment and adds it to the out-of-order receive queue.
-    // If the new segment had a FIN it has been removed prior to this routine being called.
-    // Note: Since this is not the "fast path", this is written for clarity over efficiency.
-    //
-    pub fn store_out_of_order_segment(&self, mut new_start: SeqNumber, mut new_end: SeqNumber, mut buf: DemiBuffer) {
-        let mut out_of_order = self.out_of_order.borrow_mut();
-        let mut action_index: usize = out_of_order.len();
-        let mut another_pass_neeeded: bool = true;
-
-        while another_pass_neeeded {
-            another_pass_neeeded = false;
-
-            // Find the new segment's place in the out-of-order store.
-            // The out-of-order store is sorted by starting sequence number, and contains no duplicate data.
-            action_index = out_of_order.len();
-            for index in 0..out_of_order.len() {
-                let stored_segment: &(SeqNumber, DemiBuffer) = &out_of_order[index];
-
-                // Properties of the segment stored at this index.
-                let stored_start: SeqNumber = stored_segment.0;
-                let stored_len: u32 = stored_segment.1.len() as u32;
-                debug_assert_ne!(stored_len, 0);
-                let stored_end: SeqNumber = stored_start + SeqNumber::from(stored_len - 1);
-
-                //
-                // The new data segment has six possibilites when compared to an existing out-of-order segment:
-                //
-                //                                |<- out-of-order segment ->|
-                //
-                // |<- new before->|    |<- new front overlap ->|    |<- new end overlap ->|    |<- new after ->|
-                //                                   |<- new duplicate ->|
-                //                            |<- new completely encompassing ->|
-                //
-                if new_start < stored_start {
-                    // The new segment starts before the start of this out-of-order segment.
-                    if new_end < stored_start {
-                        // The new segment comes completely before this out-of-order segment.
-                        // Since the out-of-order store is sorted, we don't need to check for overlap with any more.
-                        action_index = index;
-                        break;
-                    }
-                    // The end of the new segment overlaps with the start of this out-of-order segment.
-                    if stored_end < new_end {
-                        // The new segment ends after the end of this out-of-order segment.  In other words, the new
-                        // segment completely encompasses the out-of-order segment.
-
-                        // Set flags to remove the currently stored segment and re-run the insertion loop, as the
-                        // new segment may completely encompass even more segments.
-                        another_pass_neeeded = true;
-                        action_index = index;
-                        break;
-                    }
-                    // We have some data overlap between the new segment and the front of the out-of-order segment.
-                    // Trim the end of the new segment and stop checking for out-of-order overlap.
-                    let excess: u32 = u32::from(new_end - stored_start) + 1;
-                    new_end = new_end - SeqNumber::from(excess);
-                    buf.trim(excess as usize)
-                        .expect("'buf' should contain at least 'excess' bytes");
-                    break;
-                } else {
-                    // The new segment starts at or after the start of this out-of-order segment.
-                    // This is the stored_start <= new_start case.
-                    if new_end <= stored_end {
-                        // And the new segment ends at or before this out-of-order segment.
-                        // The new segment's data is a complete duplicate of this out-of-order segment's data.
-                        // Just drop the new segment.
-                        return;
-                    }
-                    if stored_end < new_start {
-                        // The new segment comes entirely after this out-of-order segment.
-                        // Continue to check the next out-of-order segment for potential overlap.
-                        continue;
-                    }
-                    // We have some data overlap between the new segment and the end of the out-of-order segment.
-                    // Adjust the beginning of the new segment and continue on to check the next out-of-order segment.
-                    let duplicate: u32 = u32::from(stored_end - new_start);
-                    new_start = new_start + SeqNumber::from(duplicate);
-                    buf.adjust(duplicate as usize)
-                        .expect("'buf' should contain at least 'duplicate' bytes");
-                    continue;
-                }
-            }
-
-            if another_pass_neeeded {
-                // The new segment completely encompassed an existing segment, which we will now remove.
-                out_of_order.remove(action_index);
-            }
+    /// Sends a window update if reading from the receive queue opened the window far enough past what we last
+    /// advertised, so that a peer that holds back data because of a small window does not have to wait for its persist
+    /// timer to find out. Smaller increments are not worth a segment of their own, as they would only make our peer send
+    /// small segments (receiver-side silly window syndrome avoidance, RFC 1122 Section 4.2.3.3).
+    fn send_window_update(&self) {
+        // Only bother if our peer may still send data.
+        if !matches!(self.state.get(), State::Established | State::FinWait1 | State::FinWait2) {
+            return;
         }
+        let window_size: u32 = self.get_receive_window_size();
+        let advertised_window: u32 = self.advertised_window.get();
+        let threshold: u32 = cmp::min(
+            self.tcp_config.get_advertised_mss() as u32,
+            self.receive_buffer_size.get() / 2,
+        );
+        if window_size > advertised_window && window_size - advertised_window >= threshold {
+            debug!(
+                "send_window_update(): window opened (advertised={:?}, window={:?})",
+                advertised_window, window_size
+            );
+            self.send_ack();
+        }
+    }
 
-        // Insert the new segment into the correct position.
-        out_of_order.insert(action_index, (new_start, buf));
-
-        // If the out-of-order store now contains too many entries, delete the later entries.
-        // TODO: The out-of-order store is already limited (in size) by our receive window, while the below check
-        // imposes a limit on the number of entries.  Do we need this?  Presumably for attack mitigation?
-        while out_of_order.len() > MAX_OUT_OF_ORDER {
-            out_of_order.pop_back();
+    /// Same as [ControlBlock::poll_recv], but the data is copied out of the receive queue without being removed, so
+    /// that the next receive returns it again.
+    pub fn poll_peek(&self, ctx: &mut Context, size: Option<usize>) -> Poll<Result<DemiBuffer, Fail>> {
+        if self.receive_buffer_exhausted.get() {
+            return Poll::Ready(Err(Fail::new(libc::ENOBUFS, "receive buffer exhausted")));
         }
+        if self.timed_out.get() {
+            return Poll::Ready(Err(Fail::new(libc::ETIMEDOUT, "connection timed out")));
+        }
+        match self.receiver.peek(size) {
+            Ok(Some(buf)) => Poll::Ready(Ok(buf)),
+            Ok(None) => {
+                *self.waker.borrow_mut() = Some(ctx.waker().clone());
+                Poll::Pending
+            },
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    // This routine remembers that we have received an out-of-order FIN.
+    //
+    pub fn store_out_of_order_fin(&self, fin: SeqNumber) {
+        self.out_of_order_fin.set(Some(fin));
     }
 
     // This routine takes an incoming in-order TCP segment and adds the data to the user's receive queue.  If the new
@@ -1083,23 +1795,14 @@ impl<const N: usize> ControlBlock<N> {
         // Okay, we've successfully received some new data.  Check if any of the formerly out-of-order data waiting in
         // the out-of-order queue is now in-order.  If so, we can move it to the receive queue.
         let mut added_out_of_order: bool = false;
-        let mut out_of_order = self.out_of_order.borrow_mut();
-        while !out_of_order.is_empty() {
-            if let Some(stored_entry) = out_of_order.front() {
-                if stored_entry.0 == recv_next {
-                    // Move this entry's buffer from the out-of-order store to the receive queue.
-                    // This data is now considered to be "received" by TCP, and included in our RCV.NXT calculation.
-                    debug!("Recovering out-of-order packet at {}", recv_next);
-                    if let Some(temp) = out_of_order.pop_front() {
-                        recv_next = recv_next + SeqNumber::from(temp.1.len() as u32);
-                        self.receiver.push(temp.1);
-                        added_out_of_order = true;
-                    }
-                } else {
-                    // Since our out-of-order list is sorted, we can stop when the next segment is not in sequence.
-                    break;
-                }
-            }
+        let mut out_of_order: RefMut<Box<dyn ReceiveBuffer>> = self.out_of_order.borrow_mut();
+        while let Some(buf) = out_of_order.take(recv_next) {
+            // Move this data from the out-of-order store to the receive queue.
+            // This data is now considered to be "received" by TCP, and included in our RCV.NXT calculation.
+            debug!("Recovering out-of-order data at {}", recv_next);
+            recv_next = recv_next + SeqNumber::from(buf.len() as u32);
+            self.receiver.push(buf);
+            added_out_of_order = true;
         }
 
         // TODO: Review recent change to update control block copy of recv_next upon each push to the receiver.
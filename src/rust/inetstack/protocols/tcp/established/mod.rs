@@ -4,19 +4,32 @@
 mod background;
 pub mod congestion_control;
 mod ctrlblk;
+mod rcv_window;
 mod rto;
 mod sender;
 
 pub use self::ctrlblk::{
     ControlBlock,
+    HandshakeParameters,
     State,
 };
 
 use crate::{
-    inetstack::protocols::tcp::segment::TcpHeader,
+    inetstack::protocols::{
+        ipv4::Ipv4Header,
+        tcp::{
+            events::ConnEventQueue,
+            segment::TcpHeader,
+            SeqNumber,
+        },
+    },
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
+        network::types::{
+            ByteStats,
+            ConnEvent,
+        },
         queue::BackgroundTask,
         QDesc,
     },
@@ -24,6 +37,7 @@ use crate::{
 };
 use ::futures::channel::mpsc;
 use ::std::{
+    future::Future,
     net::SocketAddrV4,
     rc::Rc,
     task::{
@@ -33,45 +47,145 @@ use ::std::{
     time::Duration,
 };
 
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Scheduling priority of the background co-routine that acknowledges and retransmits data.
+const BACKGROUND_TASK_PRIORITY: u8 = 0;
+
+/// Scheduling priority of the background co-routine that sends bulk data. This is lower than that of acknowledgements,
+/// so that these are not starved by large transfers.
+const SENDER_TASK_PRIORITY: u8 = 1;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Statistics About a TCP Connection
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpConnectionStats {
+    /// Number of segments received with the Congestion Experienced codepoint (RFC 3168).
+    pub tcp_ecn_ce_events: u64,
+    /// Number of out-of-order segments dropped because too many were buffered already.
+    pub dropped_ooo_segments: u64,
+}
+
+/// Internals of a TCP Connection, along the lines of `TCP_INFO`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TcpInfo {
+    /// State of the connection.
+    pub state: State,
+    /// Smoothed round-trip time, or `None` until we took the first RTT sample.
+    pub srtt: Option<Duration>,
+    /// Round-trip time variation, or `None` until we took the first RTT sample.
+    pub rttvar: Option<Duration>,
+    /// Congestion window, in bytes.
+    pub cwnd: u32,
+    /// Slow start threshold, in bytes.
+    pub ssthresh: u32,
+    /// Number of segments that we retransmitted.
+    pub retransmitted_segments: u64,
+    /// Number of bytes that we retransmitted.
+    pub retransmitted_bytes: u64,
+}
+
 #[derive(Clone)]
-pub struct EstablishedSocket<const N: usize> {
-    pub cb: Rc<ControlBlock<N>>,
+pub struct EstablishedSocket {
+    pub cb: Rc<ControlBlock>,
     /// The background co-routines handles various tasks, such as retransmission and acknowledging.
     /// We annotate it as unused because the compiler believes that it is never called which is not the case.
     #[allow(unused)]
     background: TaskHandle,
+    /// The background co-routine that sends data.
+    sender: TaskHandle,
 }
 
-impl<const N: usize> EstablishedSocket<N> {
-    pub fn new(cb: ControlBlock<N>, qd: QDesc, dead_socket_tx: mpsc::UnboundedSender<QDesc>) -> Self {
+impl EstablishedSocket {
+    /// Sets up the connection of `cb` as the socket referred to by `qd`, and reports it as established to `events`,
+    /// which it keeps reporting its resets and closes to.
+    pub fn new(
+        cb: ControlBlock,
+        qd: QDesc,
+        dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+        events: ConnEventQueue,
+    ) -> Self {
+        events.push(ConnEvent::Established(qd));
+        cb.set_event_queue(Some((events, qd)));
         let cb = Rc::new(cb);
         // TODO: Maybe add the queue descriptor here.
         let task: BackgroundTask = BackgroundTask::new(
             String::from("Inetstack::TCP::established::background"),
             Box::pin(background::background(cb.clone(), qd, dead_socket_tx)),
         );
-        let handle: TaskHandle = match cb.scheduler.insert(task) {
+        let handle: TaskHandle = match cb.scheduler.spawn_with_priority(BACKGROUND_TASK_PRIORITY, task) {
+            Some(handle) => handle,
+            None => panic!("failed to insert task in the scheduler"),
+        };
+        let task: BackgroundTask = BackgroundTask::new(
+            String::from("Inetstack::TCP::established::sender"),
+            Box::pin(background::background_sender(cb.clone(), qd)),
+        );
+        let sender: TaskHandle = match cb.scheduler.spawn_with_priority(SENDER_TASK_PRIORITY, task) {
             Some(handle) => handle,
             None => panic!("failed to insert task in the scheduler"),
         };
         Self {
             cb: cb.clone(),
             background: handle.clone(),
+            sender,
         }
     }
 
-    pub fn receive(&self, header: &mut TcpHeader, data: DemiBuffer) {
-        self.cb.receive(header, data)
+    pub fn receive(&self, ip_header: &Ipv4Header, header: &mut TcpHeader, data: DemiBuffer) {
+        self.cb.receive(ip_header, header, data)
     }
 
     pub fn send(&self, buf: DemiBuffer) -> Result<(), Fail> {
         self.cb.send(buf)
     }
 
+    /// Sends `buf` and returns a future that completes once our peer acknowledges all of it.
+    pub fn send_acked(&self, buf: DemiBuffer) -> Result<impl Future<Output = Result<(), Fail>>, Fail> {
+        self.cb.send(buf)?;
+        let (end, _): (SeqNumber, _) = self.cb.get_unsent_seq_no();
+        let cb: Rc<ControlBlock> = self.cb.clone();
+        Ok(async move { cb.wait_for_ack(end).await })
+    }
+
+    /// Sends `buf` and returns a future that completes once our peer acknowledges all of it. If that takes longer than
+    /// `timeout`, the future fails with ETIMEDOUT and the connection is aborted, as the data may have been partially
+    /// delivered and there is no way to take it back.
+    pub fn send_with_deadline(
+        &self,
+        buf: DemiBuffer,
+        timeout: Duration,
+    ) -> Result<impl Future<Output = Result<(), Fail>>, Fail> {
+        let acked = self.cb.clock.timeout(self.send_acked(buf)?, timeout);
+        let cb: Rc<ControlBlock> = self.cb.clone();
+        Ok(async move {
+            if let Err(e) = acked.await {
+                if !cb.has_timed_out() {
+                    cb.abort_on_timeout("push deadline expired");
+                }
+                return Err(e);
+            }
+            Ok(())
+        })
+    }
+
+    pub fn flush(&self) {
+        self.cb.flush()
+    }
+
     pub fn poll_recv(&self, ctx: &mut Context, size: Option<usize>) -> Poll<Result<DemiBuffer, Fail>> {
         self.cb.poll_recv(ctx, size)
     }
 
+    pub fn poll_peek(&self, ctx: &mut Context, size: Option<usize>) -> Poll<Result<DemiBuffer, Fail>> {
+        self.cb.poll_peek(ctx, size)
+    }
+
     pub fn close(&self) -> Result<(), Fail> {
         self.cb.close()
     }
@@ -84,10 +198,26 @@ impl<const N: usize> EstablishedSocket<N> {
         self.cb.remote_mss()
     }
 
+    pub fn bytes_available(&self) -> usize {
+        self.cb.bytes_available()
+    }
+
     pub fn current_rto(&self) -> Duration {
         self.cb.rto()
     }
 
+    pub fn stats(&self) -> TcpConnectionStats {
+        self.cb.get_stats()
+    }
+
+    pub fn info(&self) -> TcpInfo {
+        self.cb.get_info()
+    }
+
+    pub fn byte_stats(&self) -> ByteStats {
+        self.cb.get_byte_stats()
+    }
+
     pub fn endpoints(&self) -> (SocketAddrV4, SocketAddrV4) {
         (self.cb.get_local(), self.cb.get_remote())
     }
@@ -97,8 +227,9 @@ impl<const N: usize> EstablishedSocket<N> {
 // Trait Implementations
 //======================================================================================================================
 
-impl<const N: usize> Drop for EstablishedSocket<N> {
+impl Drop for EstablishedSocket {
     fn drop(&mut self) {
         self.background.deschedule();
+        self.sender.deschedule();
     }
 }
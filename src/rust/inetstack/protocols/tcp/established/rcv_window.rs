@@ -0,0 +1,89 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use ::std::{
+    cmp,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+// TCP Receive Window Auto-Tuning.
+// This follows the spirit of Linux's dynamic right-sizing: about once per round-trip time, we measure how many bytes the
+// application has read, and grow the receive window to twice that amount, so that a fast reader does not keep a fast
+// sender window-limited.  Once the application stops reading for a while, the window shrinks back to its initial size.
+// The size that this picks is only a target: the control block never moves the right edge of a window that it already
+// advertised back, so a smaller window only takes effect as the application reads data.
+
+// Number of round-trip times without the application reading anything after which we consider a connection idle.
+const IDLE_RTT_COUNT: u32 = 8;
+
+#[derive(Debug)]
+pub struct ReceiveWindowTuner {
+    // Initial receive window size.  We never shrink below this.
+    min_window_size: u32,
+
+    // Upper bound for the receive window size.
+    max_window_size: u32,
+
+    // Current receive window size.
+    window_size: u32,
+
+    // Number of bytes read by the application in the current measurement period.
+    bytes_read: usize,
+
+    // Start of the current measurement period.
+    measurement_start: Instant,
+
+    // Last time the application read some data.
+    last_read: Instant,
+}
+
+impl ReceiveWindowTuner {
+    /// Initializes a receive window tuner.
+    pub fn new(window_size: u32, max_window_size: u32, now: Instant) -> Self {
+        Self {
+            min_window_size: window_size,
+            max_window_size: cmp::max(window_size, max_window_size),
+            window_size,
+            bytes_read: 0,
+            measurement_start: now,
+            last_read: now,
+        }
+    }
+
+    /// Shrinks the receive window back to its initial size if the application has not read anything for a while.
+    pub fn on_idle_check(&mut self, now: Instant, rtt: Duration) -> u32 {
+        if self.window_size > self.min_window_size && now.duration_since(self.last_read) > rtt * IDLE_RTT_COUNT {
+            self.window_size = self.min_window_size;
+            self.bytes_read = 0;
+            self.measurement_start = now;
+        }
+        self.window_size
+    }
+
+    /// Accounts for `nbytes` read by the application, and grows the receive window if the application drained more
+    /// than half of it during the last round-trip time.
+    pub fn on_read(&mut self, nbytes: usize, now: Instant, rtt: Duration) -> u32 {
+        self.on_idle_check(now, rtt);
+
+        self.bytes_read += nbytes;
+        self.last_read = now;
+
+        let elapsed: Duration = now.duration_since(self.measurement_start);
+        if elapsed >= rtt && !elapsed.is_zero() {
+            // Normalize the measurement to a single round-trip time.
+            let bytes_per_rtt: f64 = self.bytes_read as f64 * rtt.as_secs_f64() / elapsed.as_secs_f64();
+            let target: u32 = (2.0 * bytes_per_rtt).min(self.max_window_size as f64) as u32;
+            if target > self.window_size {
+                debug!("Growing receive window from {} to {}", self.window_size, target);
+                self.window_size = target;
+            }
+            self.bytes_read = 0;
+            self.measurement_start = now;
+        }
+
+        self.window_size
+    }
+}
@@ -83,6 +83,21 @@ impl RtoCalculator {
         self.update_rto(self.rto * 2.0);
     }
 
+    /// Gets the current smoothed round-trip time.  This is 1 second until the first sample has been received.
+    pub fn srtt(&self) -> Duration {
+        Duration::from_secs_f64(self.srtt)
+    }
+
+    /// Gets the current round-trip time variation.  This is 0 seconds until the first sample has been received.
+    pub fn rttvar(&self) -> Duration {
+        Duration::from_secs_f64(self.rttvar)
+    }
+
+    /// Checks whether an RTT sample has been received yet.
+    pub fn has_sample(&self) -> bool {
+        self.received_sample
+    }
+
     /// Gets the current RTO value.
     pub fn rto(&self) -> Duration {
         Duration::from_secs_f64(self.rto)
@@ -10,6 +10,7 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
+        network::types::MacAddress,
         watched::{
             WatchFuture,
             WatchedValue,
@@ -25,6 +26,7 @@ use ::std::{
         Cell,
         RefCell,
     },
+    cmp,
     collections::VecDeque,
     convert::TryInto,
     fmt,
@@ -42,6 +44,8 @@ pub struct UnackedSegment {
     pub bytes: DemiBuffer,
     // Set to `None` on retransmission to implement Karn's algorithm.
     pub initial_tx: Option<Instant>,
+    // Time at which this segment was first sent.  Unlike `initial_tx`, this is kept across retransmissions.
+    pub first_tx: Instant,
 }
 
 /// Hard limit for unsent queue.
@@ -51,7 +55,7 @@ const UNSENT_QUEUE_CUTOFF: usize = 1024;
 
 // TODO: Consider moving retransmit timer and congestion control fields out of this structure.
 // TODO: Make all public fields in this structure private.
-pub struct Sender<const N: usize> {
+pub struct Sender {
     //
     // Send Sequence Space:
     //
@@ -88,12 +92,19 @@ pub struct Sender<const N: usize> {
     // RFC 1323: Number of bits to shift advertised window, defaults to zero.
     window_scale: u8,
 
-    // Maximum Segment Size currently in use for this connection.
-    // TODO: Revisit this once we support path MTU discovery.
-    mss: usize,
+    // Maximum Segment Size currently in use for this connection.  Black hole detection (RFC 2923) may lower it.
+    mss: Cell<usize>,
+
+    // Number of segments, and of bytes in them, that we retransmitted.
+    retransmitted_segments: Cell<u64>,
+    retransmitted_bytes: Cell<u64>,
+
+    // End of the data that we sent so far, and number of bytes that we sent, retransmissions excluded.
+    send_high: Cell<SeqNumber>,
+    sent_bytes: Cell<u64>,
 }
 
-impl<const N: usize> fmt::Debug for Sender<N> {
+impl fmt::Debug for Sender {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Sender")
             .field("send_unacked", &self.send_unacked)
@@ -101,12 +112,12 @@ impl<const N: usize> fmt::Debug for Sender<N> {
             .field("unsent_seq_no", &self.unsent_seq_no)
             .field("send_window", &self.send_window)
             .field("window_scale", &self.window_scale)
-            .field("mss", &self.mss)
+            .field("mss", &self.mss.get())
             .finish()
     }
 }
 
-impl<const N: usize> Sender<N> {
+impl Sender {
     pub fn new(seq_no: SeqNumber, send_window: u32, window_scale: u8, mss: usize) -> Self {
         Self {
             send_unacked: WatchedValue::new(seq_no),
@@ -120,12 +131,51 @@ impl<const N: usize> Sender<N> {
             send_window_last_update_ack: Cell::new(seq_no),
 
             window_scale,
-            mss,
+            mss: Cell::new(mss),
+
+            retransmitted_segments: Cell::new(0),
+            retransmitted_bytes: Cell::new(0),
+
+            send_high: Cell::new(seq_no),
+            sent_bytes: Cell::new(0),
         }
     }
 
     pub fn get_mss(&self) -> usize {
-        self.mss
+        self.mss.get()
+    }
+
+    pub fn set_mss(&self, mss: usize) {
+        self.mss.set(mss)
+    }
+
+    /// Returns the number of segments, and of bytes in them, that we retransmitted.
+    pub fn get_retransmitted(&self) -> (u64, u64) {
+        (self.retransmitted_segments.get(), self.retransmitted_bytes.get())
+    }
+
+    /// Returns the number of bytes that we sent, retransmissions excluded.
+    pub fn get_sent_bytes(&self) -> u64 {
+        self.sent_bytes.get()
+    }
+
+    /// Records that we sent a segment that carries `len` bytes of data, starting at `seq_num`. Data that we sent before
+    /// (e.g. when data is requeued after the MSS shrinks) counts as retransmitted instead.
+    pub fn record_sent(&self, seq_num: SeqNumber, len: usize) {
+        let end: SeqNumber = seq_num + SeqNumber::from(len as u32);
+        let send_high: SeqNumber = self.send_high.get();
+        let new_bytes: usize = if end > send_high {
+            self.send_high.set(end);
+            cmp::min(u32::from(end - send_high) as usize, len)
+        } else {
+            0
+        };
+        self.sent_bytes.set(self.sent_bytes.get() + new_bytes as u64);
+        if new_bytes < len {
+            self.retransmitted_segments.set(self.retransmitted_segments.get() + 1);
+            self.retransmitted_bytes
+                .set(self.retransmitted_bytes.get() + (len - new_bytes) as u64);
+        }
     }
 
     pub fn get_send_window(&self) -> (u32, WatchFuture<u32>) {
@@ -152,9 +202,30 @@ impl<const N: usize> Sender<N> {
         self.unacked_queue.borrow_mut().push_back(segment)
     }
 
+    /// Returns the size of the oldest unacknowledged segment, if there is any.
+    pub fn get_oldest_unacked_len(&self) -> Option<usize> {
+        self.unacked_queue.borrow().front().map(|segment| segment.bytes.len())
+    }
+
+    /// Moves all unacknowledged data back to the front of the unsent queue and rewinds SND.NXT to SND.UNA, so that it
+    /// is sent again in segments that fit the current MSS.
+    pub fn requeue_unacked(&self) {
+        let mut unacked_queue = self.unacked_queue.borrow_mut();
+        let mut unsent_queue = self.unsent_queue.borrow_mut();
+        while let Some(segment) = unacked_queue.pop_back() {
+            unsent_queue.push_front(segment.bytes);
+        }
+        self.send_next.set(self.send_unacked.get());
+    }
+
+    /// Returns the time at which the oldest unacknowledged data was first sent, if there is any.
+    pub fn get_oldest_unacked_tx(&self) -> Option<Instant> {
+        self.unacked_queue.borrow().front().map(|segment| segment.first_tx)
+    }
+
     // This is the main TCP send routine.
     //
-    pub fn send(&self, buf: DemiBuffer, cb: &ControlBlock<N>) -> Result<(), Fail> {
+    pub fn send(&self, buf: DemiBuffer, cb: &ControlBlock) -> Result<(), Fail> {
         // If the user is done sending (i.e. has called close on this connection), then they shouldn't be sending.
         //
         if cb.user_is_done_sending.get() {
@@ -189,8 +260,9 @@ impl<const N: usize> Sender<N> {
         // it on the unsent queue and that's it.
         //
 
-        // Check for unsent data.
-        if self.unsent_queue.borrow().is_empty() {
+        // Check for unsent data. Buffers that do not fit in a single segment are always delegated to the background
+        // sender, which splits them into MSS-sized segments.
+        if self.unsent_queue.borrow().is_empty() && (buf_len as usize) <= self.mss.get() {
             // No unsent data queued up, so we can try to send this new buffer immediately.
 
             // Calculate amount of data in flight (SND.NXT - SND.UNA).
@@ -198,7 +270,6 @@ impl<const N: usize> Sender<N> {
             let send_next: SeqNumber = self.send_next.get();
             let sent_data: u32 = (send_next - send_unacknowledged).into();
 
-            // TODO: What limits buffer len to MSS?
             let in_flight_after_send: u32 = sent_data + buf_len;
 
             // Before we get cwnd for the check, we prompt it to shrink it if the connection has been idle.
@@ -211,7 +282,7 @@ impl<const N: usize> Sender<N> {
             let win_sz: u32 = self.send_window.get();
 
             if win_sz > 0 && win_sz >= in_flight_after_send && effective_cwnd >= in_flight_after_send {
-                if let Some(remote_link_addr) = cb.arp().try_query(cb.get_remote().ip().clone()) {
+                if let Some(remote_link_addr) = cb.arp().try_query(cb.get_next_hop()) {
                     // This hook is primarily intended to record the last time we sent data, so we can later tell if
                     // the connection has been idle.
                     let rto: Duration = cb.rto();
@@ -229,6 +300,7 @@ impl<const N: usize> Sender<N> {
                         header.psh = true;
                     }
                     trace!("Send immediate");
+                    self.record_sent(send_next, buf.len());
                     cb.emit(header, Some(buf.clone()), remote_link_addr);
 
                     // Update SND.NXT.
@@ -238,9 +310,11 @@ impl<const N: usize> Sender<N> {
                     self.unsent_seq_no.modify(|s| s + SeqNumber::from(buf_len));
 
                     // Put the segment we just sent on the retransmission queue.
+                    let now: Instant = cb.clock.now();
                     let unacked_segment = UnackedSegment {
                         bytes: buf,
-                        initial_tx: Some(cb.clock.now()),
+                        initial_tx: Some(now),
+                        first_tx: now,
                     };
                     self.unacked_queue.borrow_mut().push_back(unacked_segment);
 
@@ -271,8 +345,104 @@ impl<const N: usize> Sender<N> {
         Ok(())
     }
 
+    /// Forces transmission of data on the unsent queue, instead of waiting for the background sender to pick it up.
+    /// Data is split into MSS-sized segments and sent until either the unsent queue is drained or we run out of
+    /// window space. Whatever cannot be sent right away is left to the background sender.
+    pub fn flush(&self, cb: &ControlBlock) {
+        // TODO: Link-level concerns don't belong here, we should call an IP-level send routine below.
+        let remote_link_addr: MacAddress = match cb.arp().try_query(cb.get_next_hop()) {
+            Some(remote_link_addr) => remote_link_addr,
+            None => {
+                warn!("no ARP cache entry for flush");
+                return;
+            },
+        };
+
+        while !self.unsent_queue.borrow().is_empty() {
+            // Calculate amount of data in flight (SND.NXT - SND.UNA).
+            let send_next: SeqNumber = self.send_next.get();
+            let sent_data: u32 = (send_next - self.send_unacked.get()).into();
+
+            // Before we get cwnd for the check, we prompt it to shrink it if the connection has been idle.
+            cb.congestion_control_on_cwnd_check_before_send();
+            let effective_cwnd: u32 =
+                cb.congestion_control_get_cwnd() + cb.congestion_control_get_limited_transmit_cwnd_increase();
+            let win_sz: u32 = self.send_window.get();
+
+            // Zero window probes are left to the background sender.
+            if win_sz <= sent_data || effective_cwnd <= sent_data {
+                break;
+            }
+
+            // Form an outgoing segment and send it.
+            let max_size: usize = cmp::min(
+                cmp::min((win_sz - sent_data) as usize, self.mss.get()),
+                (effective_cwnd - sent_data) as usize,
+            );
+            trace!("Send flushed");
+            if !self.send_next_segment(cb, remote_link_addr, max_size, sent_data) {
+                break;
+            }
+        }
+    }
+
+    /// Sends the next segment off the unsent queue, carrying up to `max_size` bytes, and puts it on the retransmission
+    /// queue. The caller has checked that the segment fits in the send and congestion windows, given the `sent_data`
+    /// bytes that are already in flight. Returns whether there was anything to send.
+    pub fn send_next_segment(
+        &self,
+        cb: &ControlBlock,
+        remote_link_addr: MacAddress,
+        max_size: usize,
+        sent_data: u32,
+    ) -> bool {
+        let (segment_data, do_push): (DemiBuffer, bool) = match self.pop_unsent(max_size) {
+            Some(unsent) => unsent,
+            None => return false,
+        };
+        let mut segment_data_len: u32 = segment_data.len() as u32;
+
+        let rto: Duration = cb.rto();
+        cb.congestion_control_on_send(rto, sent_data);
+
+        // Prepare the segment and send it.
+        let send_next: SeqNumber = self.send_next.get();
+        let mut header: TcpHeader = cb.tcp_header();
+        header.seq_num = send_next;
+        if segment_data_len == 0 {
+            // This buffer is the end-of-send marker.
+            debug_assert!(cb.user_is_done_sending.get());
+            // Set FIN and adjust sequence number consumption accordingly.
+            header.fin = true;
+            segment_data_len = 1;
+        } else if do_push {
+            header.psh = true;
+        }
+        self.record_sent(send_next, segment_data.len());
+        cb.emit(header, Some(segment_data.clone()), remote_link_addr);
+
+        // Update SND.NXT.
+        self.send_next.modify(|s| s + SeqNumber::from(segment_data_len));
+
+        // Put the segment we just sent on the retransmission queue.
+        let now: Instant = cb.clock.now();
+        let unacked_segment = UnackedSegment {
+            bytes: segment_data,
+            initial_tx: Some(now),
+            first_tx: now,
+        };
+        self.unacked_queue.borrow_mut().push_back(unacked_segment);
+
+        // Start the retransmission timer if it isn't already running.
+        if cb.get_retransmit_deadline().is_none() {
+            let rto: Duration = cb.rto();
+            cb.set_retransmit_deadline(Some(cb.clock.now() + rto));
+        }
+        true
+    }
+
     /// Retransmits the earliest segment that has not (yet) been acknowledged by our peer.
-    pub fn retransmit(&self, cb: &ControlBlock<N>) {
+    pub fn retransmit(&self, cb: &ControlBlock) {
         // Check that we have an unacknowledged segment.
         if let Some(segment) = self.unacked_queue.borrow_mut().front_mut() {
             // We're retransmitting this, so we can no longer use an ACK for it as an RTT measurement (as we can't tell
@@ -285,13 +455,16 @@ impl<const N: usize> Sender<N> {
             // TODO: Issue #198 Repacketization - we should send a full MSS (and set the FIN flag if applicable).
 
             // Prepare and send the segment.
-            if let Some(first_hop_link_addr) = cb.arp().try_query(cb.get_remote().ip().clone()) {
+            if let Some(first_hop_link_addr) = cb.arp().try_query(cb.get_next_hop()) {
                 let mut header: TcpHeader = cb.tcp_header();
                 header.seq_num = self.send_unacked.get();
                 if data.len() == 0 {
                     // This buffer is the end-of-send marker.  Retransmit the FIN.
                     header.fin = true;
                 }
+                self.retransmitted_segments.set(self.retransmitted_segments.get() + 1);
+                self.retransmitted_bytes
+                    .set(self.retransmitted_bytes.get() + data.len() as u64);
                 cb.emit(header, Some(data), first_hop_link_addr);
             }
         } else {
@@ -302,68 +475,68 @@ impl<const N: usize> Sender<N> {
         }
     }
 
-    // Remove acknowledged data from the unacknowledged (a.k.a. retransmission) queue.
-    //
-    pub fn remove_acknowledged_data(&self, cb: &ControlBlock<N>, bytes_acknowledged: u32, now: Instant) {
+    /// Removes acknowledged data from the unacknowledged (a.k.a. retransmission) queue. Only the segments that the ACK
+    /// covers are visited, and those that it fully acknowledges are released in one go. Returns a round-trip time sample
+    /// if the ACK covers data that was not retransmitted.
+    pub fn remove_acknowledged_data(&self, bytes_acknowledged: u32, now: Instant) -> Option<Duration> {
+        let mut unacked_queue = self.unacked_queue.borrow_mut();
         let mut bytes_remaining: usize = bytes_acknowledged as usize;
+        let mut rtt: Option<Duration> = None;
+        let mut num_acked: usize = 0;
 
-        while bytes_remaining != 0 {
-            if let Some(segment) = self.unacked_queue.borrow_mut().front_mut() {
-                // Add sample for RTO if we have an initial transmit time.
-                // Note that in the case of repacketization, an ack for the first byte is enough for the time sample.
-                // TODO: TCP timestamp support.
-                if let Some(initial_tx) = segment.initial_tx {
-                    cb.rto_add_sample(now - initial_tx);
-                }
-
-                if segment.bytes.len() > bytes_remaining {
-                    // Only some of the data in this segment has been acked.  Remove just the acked amount.
-                    segment
-                        .bytes
-                        .adjust(bytes_remaining)
-                        .expect("'segment' should contain at least 'bytes_remaining'");
-                    segment.initial_tx = None;
+        for segment in unacked_queue.iter() {
+            if bytes_remaining == 0 {
+                break;
+            }
 
-                    // Leave this segment on the unacknowledged queue.
-                    break;
-                }
+            // Take a single RTT sample per ACK, from the oldest segment that it covers and that was not retransmitted.
+            // Note that in the case of repacketization, an ack for the first byte is enough for the time sample.
+            if rtt.is_none() {
+                rtt = segment.initial_tx.map(|initial_tx| now - initial_tx);
+            }
 
-                if segment.bytes.len() == 0 {
-                    // This buffer is the end-of-send marker.  So we should only have one byte of acknowledged sequence
-                    // space remaining (corresponding to our FIN).
-                    debug_assert_eq!(bytes_remaining, 1);
-                    bytes_remaining = 0;
-                }
+            if segment.bytes.len() > bytes_remaining {
+                // Only some of the data in this segment has been acked.
+                break;
+            }
 
-                bytes_remaining -= segment.bytes.len();
+            if segment.bytes.len() == 0 {
+                // This buffer is the end-of-send marker.  So we should only have one byte of acknowledged sequence
+                // space remaining (corresponding to our FIN).
+                debug_assert_eq!(bytes_remaining, 1);
+                bytes_remaining = 0;
             } else {
-                debug_assert!(false); // Shouldn't have bytes_remaining with no segments remaining in unacked_queue.
+                bytes_remaining -= segment.bytes.len();
             }
-
-            // Remove this segment from the unacknowledged queue.
-            // TODO: Mark the send operation associated with this buffer as complete, so the user can reuse the buffer.
-            self.unacked_queue.borrow_mut().pop_front();
+            num_acked += 1;
         }
-    }
 
-    pub fn pop_one_unsent_byte(&self) -> Option<DemiBuffer> {
-        let mut queue = self.unsent_queue.borrow_mut();
+        // Release the fully acknowledged segments.
+        // TODO: Mark the send operation associated with these buffers as complete, so the user can reuse the buffers.
+        unacked_queue.drain(..num_acked);
 
-        let buf = queue.front_mut()?;
-        let mut cloned_buf = buf.clone();
-        let buf_len: usize = buf.len();
-
-        // Pop one byte off the buf still in the queue and all but one of the bytes on our clone.
-        buf.adjust(1).expect("'buf' should contain at least one byte");
-        cloned_buf
-            .trim(buf_len - 1)
-            .expect("'cloned_buf' should contain at least one less than its professed length");
+        if bytes_remaining != 0 {
+            match unacked_queue.front_mut() {
+                Some(segment) => {
+                    // Remove just the acked amount, and leave this segment on the unacknowledged queue.
+                    segment
+                        .bytes
+                        .adjust(bytes_remaining)
+                        .expect("'segment' should contain at least 'bytes_remaining'");
+                    segment.initial_tx = None;
+                },
+                // Shouldn't have bytes_remaining with no segments remaining in unacked_queue.
+                None => debug_assert!(false),
+            }
+        }
 
-        Some(cloned_buf)
+        rtt
     }
 
+    /// Pops the data for the next segment, of up to `max_bytes` bytes, off the unsent queue. Small buffers are coalesced
+    /// into a single segment, as push boundaries are not preserved in a byte stream. Returns the data along with whether
+    /// the segment ends at the end of a pushed buffer, in which case the PSH flag should be set.
     pub fn pop_unsent(&self, max_bytes: usize) -> Option<(DemiBuffer, bool)> {
-        // TODO: Use a scatter/gather array to coalesce multiple buffers into a single segment.
         let mut unsent_queue = self.unsent_queue.borrow_mut();
         let mut buf: DemiBuffer = unsent_queue.pop_front()?;
         let mut do_push: bool = true;
@@ -383,6 +556,36 @@ impl<const N: usize> Sender<N> {
 
             // Suppress PSH flag for partial buffers.
             do_push = false;
+        } else if buf_len > 0 && buf_len < max_bytes && unsent_queue.front().is_some_and(|next| !next.is_empty()) {
+            // Figure out how much data follows, up to the end-of-send marker (if any).
+            let mut segment_len: usize = buf_len;
+            for next in unsent_queue.iter() {
+                if next.is_empty() || segment_len >= max_bytes {
+                    break;
+                }
+                segment_len = cmp::min(segment_len + next.len(), max_bytes);
+            }
+
+            // Copy the data into a single buffer.
+            // TODO: Use a scatter/gather array to coalesce multiple buffers into a single segment without copying.
+            let mut segment: DemiBuffer = DemiBuffer::new(segment_len as u16);
+            segment[..buf_len].copy_from_slice(&buf[..]);
+            let mut offset: usize = buf_len;
+            while offset < segment_len {
+                let mut next: DemiBuffer = unsent_queue
+                    .pop_front()
+                    .expect("unsent queue should hold the data that we counted above");
+                let nbytes: usize = cmp::min(next.len(), segment_len - offset);
+                segment[offset..(offset + nbytes)].copy_from_slice(&next[..nbytes]);
+                offset += nbytes;
+                if nbytes < next.len() {
+                    // Leave the rest of this buffer for the next segment.
+                    next.adjust(nbytes).expect("'next' should contain at least 'nbytes'");
+                    unsent_queue.push_front(next);
+                    do_push = false;
+                }
+            }
+            buf = segment;
         }
         Some((buf, do_push))
     }
@@ -415,6 +618,93 @@ impl<const N: usize> Sender<N> {
     }
 
     pub fn remote_mss(&self) -> usize {
-        self.mss
+        self.mss.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Sender,
+        UnackedSegment,
+    };
+    use crate::{
+        inetstack::protocols::tcp::SeqNumber,
+        runtime::memory::DemiBuffer,
+    };
+    use ::anyhow::Result;
+    use ::std::time::{
+        Duration,
+        Instant,
+    };
+    use ::test::{
+        black_box,
+        Bencher,
+    };
+
+    /// Size of the segments that the benchmarks acknowledge.
+    const BENCH_SEGMENT_SIZE: usize = 1460;
+
+    /// Instantiates a sender with `num_segments` segments of `segment_size` bytes in flight, sent at `now`.
+    fn sender_in_flight(num_segments: usize, segment_size: usize, now: Instant) -> Sender {
+        let sender: Sender = Sender::new(SeqNumber::from(0), u32::MAX, 0, segment_size);
+        let bytes: DemiBuffer = DemiBuffer::new(segment_size as u16);
+        for _ in 0..num_segments {
+            sender.push_unacked_segment(UnackedSegment {
+                bytes: bytes.clone(),
+                initial_tx: Some(now),
+                first_tx: now,
+            });
+        }
+        sender
+    }
+
+    /// Tests that acknowledged data is removed from the unacknowledged queue, also when an ACK covers a segment only
+    /// partially.
+    #[test]
+    fn test_remove_acknowledged_data() -> Result<()> {
+        let now: Instant = Instant::now();
+        let sender: Sender = sender_in_flight(4, 100, now);
+
+        // Acknowledge the first segment and half of the second one.
+        let rtt: Option<Duration> = sender.remove_acknowledged_data(150, now + Duration::from_millis(10));
+        crate::ensure_eq!(rtt, Some(Duration::from_millis(10)));
+        crate::ensure_eq!(sender.unacked_queue.borrow().len(), 3);
+        crate::ensure_eq!(sender.get_oldest_unacked_len(), Some(50));
+
+        // The rest of the second segment no longer gives an RTT sample, but the third one does.
+        let rtt: Option<Duration> = sender.remove_acknowledged_data(50, now + Duration::from_millis(20));
+        crate::ensure_eq!(rtt, None);
+        let rtt: Option<Duration> = sender.remove_acknowledged_data(200, now + Duration::from_millis(30));
+        crate::ensure_eq!(rtt, Some(Duration::from_millis(30)));
+        crate::ensure_eq!(sender.unacked_queue.borrow().is_empty(), true);
+
+        Ok(())
+    }
+
+    /// Acknowledges a window of `window_size` bytes one segment at a time.
+    fn bench_ack_per_segment(b: &mut Bencher, window_size: usize) {
+        let num_segments: usize = window_size / BENCH_SEGMENT_SIZE;
+        let now: Instant = Instant::now();
+        b.bytes = (num_segments * BENCH_SEGMENT_SIZE) as u64;
+        b.iter(|| {
+            let sender: Sender = sender_in_flight(num_segments, BENCH_SEGMENT_SIZE, now);
+            for _ in 0..num_segments {
+                black_box(sender.remove_acknowledged_data(BENCH_SEGMENT_SIZE as u32, now));
+            }
+        });
+    }
+
+    // The throughput of both benchmarks should match, as the cost of an ACK depends on the data that it acknowledges
+    // and not on the amount of data in flight.
+
+    #[bench]
+    fn bench_ack_per_segment_64kb_window(b: &mut Bencher) {
+        bench_ack_per_segment(b, 64 * 1024);
+    }
+
+    #[bench]
+    fn bench_ack_per_segment_1mb_window(b: &mut Bencher) {
+        bench_ack_per_segment(b, 1024 * 1024);
     }
 }
@@ -0,0 +1,144 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::network::types::ConnEvent;
+use ::std::{
+    cell::RefCell,
+    collections::VecDeque,
+    rc::Rc,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Pending connection events, along with the bound on them.
+struct Inner {
+    events: VecDeque<ConnEvent>,
+    capacity: usize,
+    // Whether an overflow marker is pending, in which case further events are dropped until it is drained.
+    overflowed: bool,
+}
+
+/// Bounded queue of connection events, which the TCP state machine reports to and the application drains. Once only a
+/// single slot is left, an overflow marker takes it and further events are dropped until the marker is drained, so an
+/// application that does not drain the queue does not make it grow. A queue without capacity drops all events. Clones
+/// share the same queue.
+#[derive(Clone)]
+pub struct ConnEventQueue {
+    inner: Rc<RefCell<Inner>>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl ConnEventQueue {
+    /// Creates a queue that holds up to `capacity` events, the overflow marker included.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(Inner {
+                events: VecDeque::new(),
+                capacity,
+                overflowed: false,
+            })),
+        }
+    }
+
+    /// Reports an event. A listening socket that keeps turning away connections reports it only once until the
+    /// application drains the report.
+    pub fn push(&self, event: ConnEvent) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.capacity == 0 || inner.overflowed {
+            return;
+        }
+        if let ConnEvent::AcceptQueueFull(_) = event {
+            if inner.events.contains(&event) {
+                return;
+            }
+        }
+        if inner.events.len() + 1 >= inner.capacity {
+            warn!("push(): connection event queue is full, dropping events");
+            inner.overflowed = true;
+            inner.events.push_back(ConnEvent::Overflow);
+            return;
+        }
+        inner.events.push_back(event);
+    }
+
+    /// Takes the oldest event, if any.
+    pub fn pop(&self) -> Option<ConnEvent> {
+        let mut inner = self.inner.borrow_mut();
+        let event: Option<ConnEvent> = inner.events.pop_front();
+        if event == Some(ConnEvent::Overflow) {
+            inner.overflowed = false;
+        }
+        event
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::ConnEventQueue;
+    use crate::runtime::{
+        network::types::ConnEvent,
+        QDesc,
+    };
+    use ::anyhow::Result;
+
+    /// Tests that a full queue drops events behind an overflow marker, and takes new ones once the marker is drained.
+    #[test]
+    fn test_conn_event_queue_overflow() -> Result<()> {
+        let queue: ConnEventQueue = ConnEventQueue::new(3);
+        for i in 0..5 {
+            queue.push(ConnEvent::Established(QDesc::from(i)));
+        }
+        crate::ensure_eq!(queue.pop(), Some(ConnEvent::Established(QDesc::from(0))));
+        crate::ensure_eq!(queue.pop(), Some(ConnEvent::Established(QDesc::from(1))));
+
+        // Events are still dropped until the marker is drained, even though there is room for them.
+        queue.push(ConnEvent::Closed(QDesc::from(0)));
+        crate::ensure_eq!(queue.pop(), Some(ConnEvent::Overflow));
+        crate::ensure_eq!(queue.pop(), None);
+
+        queue.push(ConnEvent::Closed(QDesc::from(1)));
+        crate::ensure_eq!(queue.pop(), Some(ConnEvent::Closed(QDesc::from(1))));
+        crate::ensure_eq!(queue.pop(), None);
+
+        Ok(())
+    }
+
+    /// Tests that a listening socket that keeps turning away connections is reported once until the report is drained.
+    #[test]
+    fn test_conn_event_queue_coalesces_accept_queue_full() -> Result<()> {
+        let queue: ConnEventQueue = ConnEventQueue::new(16);
+        let listener: QDesc = QDesc::from(7);
+        queue.push(ConnEvent::AcceptQueueFull(listener));
+        queue.push(ConnEvent::AcceptQueueFull(listener));
+        crate::ensure_eq!(queue.pop(), Some(ConnEvent::AcceptQueueFull(listener)));
+        crate::ensure_eq!(queue.pop(), None);
+
+        queue.push(ConnEvent::AcceptQueueFull(listener));
+        crate::ensure_eq!(queue.pop(), Some(ConnEvent::AcceptQueueFull(listener)));
+
+        Ok(())
+    }
+
+    /// Tests that a queue without capacity drops all events.
+    #[test]
+    fn test_conn_event_queue_disabled() -> Result<()> {
+        let queue: ConnEventQueue = ConnEventQueue::new(0);
+        queue.push(ConnEvent::Established(QDesc::from(0)));
+        crate::ensure_eq!(queue.pop(), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,434 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::{
+        ethernet2::ETHERNET2_HEADER_SIZE,
+        ip::IpProtocol,
+        ipv4::{
+            Ipv4Header,
+            IPV4_HEADER_MIN_SIZE,
+        },
+        tcp::segment::MIN_TCP_HEADER_SIZE,
+    },
+    runtime::{
+        memory::DemiBuffer,
+        network::{
+            consts::MAX_RECEIVE_BATCH_SIZE,
+            ReceiveBatch,
+        },
+    },
+};
+use ::arrayvec::ArrayVec;
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// EtherType of IPv4 frames.
+const ETHERTYPE_IPV4: u16 = 0x800;
+
+/// Offset of the IPv4 header in a frame.
+const IPV4_OFFSET: usize = ETHERNET2_HEADER_SIZE;
+
+/// Offset of the TCP header in a frame. Datagrams that carry IPv4 options are never coalesced.
+const TCP_OFFSET: usize = IPV4_OFFSET + IPV4_HEADER_MIN_SIZE as usize;
+
+/// Bits of the IPv4 flags and fragment offset that must be clear for a datagram to be coalesced: anything but the
+/// don't fragment flag.
+const IPV4_FRAGMENT_MASK: u16 = 0xbfff;
+
+/// TCP flags that prevent a segment from being coalesced: CWR, URG, RST, SYN and FIN.
+const TCP_FLAGS_NO_COALESCE: u8 = (1 << 7) | (1 << 5) | (1 << 2) | (1 << 1) | (1 << 0);
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Location of a TCP segment in a frame that may be coalesced.
+#[derive(Clone, Copy, Debug)]
+struct SegmentInfo {
+    /// Offset of the payload in the frame.
+    payload_offset: usize,
+    /// End of the payload in the frame. Any Ethernet padding comes after.
+    payload_end: usize,
+    /// Sequence number of the segment.
+    seq_num: u32,
+}
+
+/// Software receive coalescing (GRO). Within a batch of received frames, consecutive TCP segments of a connection that
+/// carry contiguous data are merged into a single segment before the stack processes them, so that the per-segment
+/// costs of the stack are paid once per merged segment. Segments that cannot be merged are passed through untouched.
+pub struct ReceiveCoalescer {
+    /// Maximum number of payload bytes in a merged segment. Zero disables coalescing.
+    max_size: usize,
+    /// Are checksums checked by the hardware?
+    rx_checksum_offload: bool,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl ReceiveCoalescer {
+    pub fn new(max_size: usize, rx_checksum_offload: bool) -> Self {
+        Self {
+            max_size,
+            rx_checksum_offload,
+        }
+    }
+
+    /// Merges consecutive segments of the same connection in `batch`. Segments are merged if they carry contiguous
+    /// data, and they have the same flags, options and explicit congestion notification codepoint. The merged segment
+    /// carries the acknowledgement number and window of the last segment in it.
+    pub fn coalesce(&self, batch: ReceiveBatch) -> ReceiveBatch {
+        if self.max_size == 0 || batch.len() < 2 {
+            return batch;
+        }
+
+        let mut out: ReceiveBatch = ReceiveBatch::new();
+        let mut run: ArrayVec<(DemiBuffer, SegmentInfo), MAX_RECEIVE_BATCH_SIZE> = ArrayVec::new();
+        let mut run_size: usize = 0;
+        for buf in batch {
+            let info: SegmentInfo = match self.inspect(&buf) {
+                Some(info) => info,
+                None => {
+                    self.flush(&mut run, &mut out);
+                    out.push(buf);
+                    continue;
+                },
+            };
+            let size: usize = info.payload_end - info.payload_offset;
+            if let Some((last_buf, last_info)) = run.last() {
+                if run_size + size > self.max_size || !can_merge(last_buf, last_info, &buf, &info) {
+                    self.flush(&mut run, &mut out);
+                }
+            }
+            if run.is_empty() {
+                run_size = 0;
+            }
+            run_size += size;
+            run.push((buf, info));
+        }
+        self.flush(&mut run, &mut out);
+
+        out
+    }
+
+    /// Checks whether a frame carries a TCP segment that may be merged with others, and locates it.
+    fn inspect(&self, buf: &DemiBuffer) -> Option<SegmentInfo> {
+        if buf.len() < TCP_OFFSET + MIN_TCP_HEADER_SIZE {
+            return None;
+        }
+        if u16::from_be_bytes([buf[12], buf[13]]) != ETHERTYPE_IPV4 {
+            return None;
+        }
+
+        // Only plain IPv4 datagrams, without options nor fragmentation, that carry TCP.
+        let ipv4_hdr: &[u8] = &buf[IPV4_OFFSET..TCP_OFFSET];
+        if ipv4_hdr[0] != 0x45
+            || u16::from_be_bytes([ipv4_hdr[6], ipv4_hdr[7]]) & IPV4_FRAGMENT_MASK != 0
+            || ipv4_hdr[9] != IpProtocol::TCP as u8
+        {
+            return None;
+        }
+        let total_length: usize = u16::from_be_bytes([ipv4_hdr[2], ipv4_hdr[3]]) as usize;
+        let payload_end: usize = IPV4_OFFSET + total_length;
+        if total_length < IPV4_HEADER_MIN_SIZE as usize + MIN_TCP_HEADER_SIZE || payload_end > buf.len() {
+            return None;
+        }
+
+        // Only segments that carry data and no flags that must be seen on their own.
+        let tcp_hdr: &[u8] = &buf[TCP_OFFSET..payload_end];
+        let data_offset: usize = (tcp_hdr[12] >> 4) as usize * 4;
+        if data_offset < MIN_TCP_HEADER_SIZE || data_offset >= tcp_hdr.len() {
+            return None;
+        }
+        if tcp_hdr[13] & TCP_FLAGS_NO_COALESCE != 0 {
+            return None;
+        }
+
+        // Merged segments get new checksums, so corrupted segments must not make it into one.
+        if u16::from_be_bytes([ipv4_hdr[10], ipv4_hdr[11]]) != Ipv4Header::compute_checksum(ipv4_hdr) {
+            return None;
+        }
+        if !self.rx_checksum_offload
+            && u16::from_be_bytes([tcp_hdr[16], tcp_hdr[17]]) != tcp_checksum(ipv4_hdr, tcp_hdr)
+        {
+            return None;
+        }
+
+        Some(SegmentInfo {
+            payload_offset: TCP_OFFSET + data_offset,
+            payload_end,
+            seq_num: u32::from_be_bytes([tcp_hdr[4], tcp_hdr[5], tcp_hdr[6], tcp_hdr[7]]),
+        })
+    }
+
+    /// Passes the segments of a run on, merged into a single one if there are many.
+    fn flush(&self, run: &mut ArrayVec<(DemiBuffer, SegmentInfo), MAX_RECEIVE_BATCH_SIZE>, out: &mut ReceiveBatch) {
+        if run.len() < 2 {
+            out.extend(run.drain(..).map(|(buf, _)| buf));
+            return;
+        }
+
+        let (first_buf, first_info): &(DemiBuffer, SegmentInfo) = &run[0];
+        let (last_buf, _): &(DemiBuffer, SegmentInfo) = &run[run.len() - 1];
+        let header_size: usize = first_info.payload_offset;
+        let payload_size: usize = run.iter().map(|(_, info)| info.payload_end - info.payload_offset).sum();
+        let mut merged: DemiBuffer = DemiBuffer::new((header_size + payload_size) as u16);
+        merged.set_sw_timestamp(first_buf.sw_timestamp());
+
+        // Headers come from the first segment, but the acknowledgement number and window from the last one.
+        merged[..header_size].copy_from_slice(&first_buf[..header_size]);
+        merged[TCP_OFFSET + 8..TCP_OFFSET + 12].copy_from_slice(&last_buf[TCP_OFFSET + 8..TCP_OFFSET + 12]);
+        merged[TCP_OFFSET + 14..TCP_OFFSET + 16].copy_from_slice(&last_buf[TCP_OFFSET + 14..TCP_OFFSET + 16]);
+        let mut offset: usize = header_size;
+        for (buf, info) in run.iter() {
+            let size: usize = info.payload_end - info.payload_offset;
+            merged[offset..offset + size].copy_from_slice(&buf[info.payload_offset..info.payload_end]);
+            offset += size;
+        }
+
+        // Fix up the length and checksums.
+        let total_length: u16 = (header_size + payload_size - IPV4_OFFSET) as u16;
+        merged[IPV4_OFFSET + 2..IPV4_OFFSET + 4].copy_from_slice(&total_length.to_be_bytes());
+        let ipv4_checksum: u16 = Ipv4Header::compute_checksum(&merged[IPV4_OFFSET..TCP_OFFSET]);
+        merged[IPV4_OFFSET + 10..IPV4_OFFSET + 12].copy_from_slice(&ipv4_checksum.to_be_bytes());
+        if !self.rx_checksum_offload {
+            let tcp_checksum: u16 = tcp_checksum(&merged[IPV4_OFFSET..TCP_OFFSET], &merged[TCP_OFFSET..]);
+            merged[TCP_OFFSET + 16..TCP_OFFSET + 18].copy_from_slice(&tcp_checksum.to_be_bytes());
+        }
+
+        trace!("flush(): merged {:?} segments ({:?} bytes)", run.len(), payload_size);
+        run.clear();
+        out.push(merged);
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Checks whether a segment continues the data of another one, in the same connection and with the same headers but for
+/// the acknowledgement number and window.
+fn can_merge(prev_buf: &DemiBuffer, prev: &SegmentInfo, next_buf: &DemiBuffer, next: &SegmentInfo) -> bool {
+    let prev_size: u32 = (prev.payload_end - prev.payload_offset) as u32;
+    prev.seq_num.wrapping_add(prev_size) == next.seq_num
+        // Link addresses.
+        && prev_buf[..ETHERNET2_HEADER_SIZE] == next_buf[..ETHERNET2_HEADER_SIZE]
+        // Explicit congestion notification codepoint.
+        && prev_buf[IPV4_OFFSET + 1] == next_buf[IPV4_OFFSET + 1]
+        // IPv4 addresses.
+        && prev_buf[IPV4_OFFSET + 12..TCP_OFFSET] == next_buf[IPV4_OFFSET + 12..TCP_OFFSET]
+        // Ports.
+        && prev_buf[TCP_OFFSET..TCP_OFFSET + 4] == next_buf[TCP_OFFSET..TCP_OFFSET + 4]
+        // Data offset and flags.
+        && prev_buf[TCP_OFFSET + 12..TCP_OFFSET + 14] == next_buf[TCP_OFFSET + 12..TCP_OFFSET + 14]
+        // Options.
+        && prev_buf[TCP_OFFSET + MIN_TCP_HEADER_SIZE..prev.payload_offset]
+            == next_buf[TCP_OFFSET + MIN_TCP_HEADER_SIZE..next.payload_offset]
+}
+
+/// Computes the checksum of a TCP segment, skipping the checksum field of its header.
+fn tcp_checksum(ipv4_hdr: &[u8], segment: &[u8]) -> u16 {
+    let mut state: u32 = 0xffff;
+
+    // Pseudo-header: addresses, protocol and length.
+    state = fold(state, &ipv4_hdr[12..20]);
+    state += IpProtocol::TCP as u32;
+    state += segment.len() as u32;
+
+    // Segment, but for the checksum field.
+    state = fold(state, &segment[..16]);
+    state = fold(state, &segment[18..]);
+
+    while state > 0xffff {
+        state -= 0xffff;
+    }
+    !state as u16
+}
+
+/// Adds up the 16-bit words of `data` into `state`, padding the last one with zero.
+fn fold(mut state: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for chunk in &mut chunks {
+        state += u16::from_be_bytes([chunk[0], chunk[1]]) as u32;
+    }
+    if let Some(&b) = chunks.remainder().first() {
+        state += u16::from_be_bytes([b, 0]) as u32;
+    }
+    state
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::ReceiveCoalescer;
+    use crate::{
+        inetstack::{
+            protocols::{
+                ethernet2::{
+                    EtherType2,
+                    Ethernet2Header,
+                },
+                ip::IpProtocol,
+                ipv4::Ipv4Header,
+                tcp::{
+                    segment::TcpHeader,
+                    SeqNumber,
+                },
+            },
+            test_helpers,
+        },
+        runtime::{
+            memory::DemiBuffer,
+            network::ReceiveBatch,
+        },
+    };
+    use ::anyhow::Result;
+    use ::test::{
+        black_box,
+        Bencher,
+    };
+
+    /// Size of the payload of the segments in the tests.
+    const SEGMENT_SIZE: usize = 1000;
+
+    /// Builds a frame that carries a segment from Alice to Bob.
+    fn build_frame(seq_num: u32, ack_num: u32, src_port: u16, fin: bool, payload: &[u8]) -> DemiBuffer {
+        let eth2_header: Ethernet2Header =
+            Ethernet2Header::new(test_helpers::BOB_MAC, test_helpers::ALICE_MAC, EtherType2::Ipv4);
+        let ipv4_header: Ipv4Header =
+            Ipv4Header::new(test_helpers::ALICE_IPV4, test_helpers::BOB_IPV4, IpProtocol::TCP);
+        let mut tcp_header: TcpHeader = TcpHeader::new(src_port, 80);
+        tcp_header.seq_num = SeqNumber::from(seq_num);
+        tcp_header.ack_num = SeqNumber::from(ack_num);
+        tcp_header.ack = true;
+        tcp_header.psh = true;
+        tcp_header.fin = fin;
+        tcp_header.window_size = ack_num as u16;
+
+        let eth2_size: usize = eth2_header.compute_size();
+        let ipv4_size: usize = ipv4_header.compute_size();
+        let tcp_size: usize = tcp_header.compute_size();
+        let mut buf: DemiBuffer = DemiBuffer::new((eth2_size + ipv4_size + tcp_size + payload.len()) as u16);
+        eth2_header.serialize(&mut buf[..eth2_size]);
+        ipv4_header.serialize(&mut buf[eth2_size..eth2_size + ipv4_size], tcp_size + payload.len());
+        let tcp_end: usize = eth2_size + ipv4_size + tcp_size;
+        tcp_header.serialize(&mut buf[eth2_size + ipv4_size..tcp_end], &ipv4_header, payload, false);
+        buf[tcp_end..].copy_from_slice(payload);
+        buf
+    }
+
+    /// Builds a batch of segments that carry contiguous data, each one acknowledging a bit more than the previous one.
+    fn build_batch(num_segments: usize) -> (ReceiveBatch, Vec<u8>) {
+        let mut batch: ReceiveBatch = ReceiveBatch::new();
+        let mut data: Vec<u8> = Vec::with_capacity(num_segments * SEGMENT_SIZE);
+        for i in 0..num_segments {
+            let payload: Vec<u8> = (0..SEGMENT_SIZE).map(|j| (i + j) as u8).collect();
+            batch.push(build_frame((i * SEGMENT_SIZE) as u32, i as u32, 49152, false, &payload));
+            data.extend_from_slice(&payload);
+        }
+        (batch, data)
+    }
+
+    /// Parses a frame with the stack parsers, checksums included.
+    fn parse_frame(buf: DemiBuffer) -> Result<(TcpHeader, DemiBuffer)> {
+        let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(buf)?;
+        let (ipv4_header, ipv4_payload): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+        Ok(TcpHeader::parse(&ipv4_header, ipv4_payload, false)?)
+    }
+
+    /// Tests that contiguous segments are merged into a single one that carries all data, with the acknowledgement
+    /// number and window of the last one.
+    #[test]
+    fn test_coalesce_contiguous() -> Result<()> {
+        let (batch, data): (ReceiveBatch, Vec<u8>) = build_batch(8);
+        let mut out: ReceiveBatch = ReceiveCoalescer::new(64 * 1024 - 256, false).coalesce(batch);
+        crate::ensure_eq!(out.len(), 1);
+
+        let (tcp_header, payload): (TcpHeader, DemiBuffer) = parse_frame(out.remove(0))?;
+        crate::ensure_eq!(tcp_header.seq_num, SeqNumber::from(0));
+        crate::ensure_eq!(tcp_header.ack_num, SeqNumber::from(7));
+        crate::ensure_eq!(tcp_header.window_size, 7);
+        crate::ensure_eq!(tcp_header.psh, true);
+        crate::ensure_eq!(&payload[..], &data[..]);
+        Ok(())
+    }
+
+    /// Tests that merged segments do not grow beyond the maximum size.
+    #[test]
+    fn test_coalesce_max_size() -> Result<()> {
+        let (batch, data): (ReceiveBatch, Vec<u8>) = build_batch(8);
+        let out: ReceiveBatch = ReceiveCoalescer::new(3 * SEGMENT_SIZE, false).coalesce(batch);
+        crate::ensure_eq!(out.len(), 3);
+
+        let mut received: Vec<u8> = Vec::new();
+        for (i, buf) in out.into_iter().enumerate() {
+            let (tcp_header, payload): (TcpHeader, DemiBuffer) = parse_frame(buf)?;
+            crate::ensure_eq!(tcp_header.seq_num, SeqNumber::from((3 * i * SEGMENT_SIZE) as u32));
+            received.extend_from_slice(&payload[..]);
+        }
+        crate::ensure_eq!(received, data);
+        Ok(())
+    }
+
+    /// Tests that segments that cannot be merged are passed through untouched, in order.
+    #[test]
+    fn test_coalesce_passthrough() -> Result<()> {
+        let payload: [u8; SEGMENT_SIZE] = [0x5a; SEGMENT_SIZE];
+        let size: u32 = SEGMENT_SIZE as u32;
+        let mut corrupted: DemiBuffer = build_frame(5 * size, 0, 49152, false, &payload);
+        let len: usize = corrupted.len();
+        corrupted[len - 1] ^= 0xff;
+        let frames: Vec<DemiBuffer> = vec![
+            build_frame(0, 0, 49152, false, &payload),
+            // Gap in the sequence space.
+            build_frame(2 * size, 0, 49152, false, &payload),
+            // Another connection.
+            build_frame(3 * size, 0, 49153, false, &payload),
+            // Flag that must be seen on its own.
+            build_frame(3 * size, 0, 49152, true, &payload),
+            // Pure acknowledgement.
+            build_frame(4 * size, 0, 49152, false, &[]),
+            // Bad checksum.
+            corrupted,
+            // Not TCP.
+            DemiBuffer::from_slice(&[0; 64])?,
+        ];
+
+        let mut batch: ReceiveBatch = ReceiveBatch::new();
+        batch.extend(frames.iter().cloned());
+        let out: ReceiveBatch = ReceiveCoalescer::new(64 * 1024 - 256, false).coalesce(batch);
+        crate::ensure_eq!(out.len(), frames.len());
+        for (buf, expected) in out.iter().zip(frames.iter()) {
+            crate::ensure_eq!(&buf[..], &expected[..]);
+        }
+        Ok(())
+    }
+
+    /// Tests that coalescing is disabled when the maximum size is zero.
+    #[test]
+    fn test_coalesce_disabled() -> Result<()> {
+        let (batch, _): (ReceiveBatch, Vec<u8>) = build_batch(8);
+        let out: ReceiveBatch = ReceiveCoalescer::new(0, false).coalesce(batch);
+        crate::ensure_eq!(out.len(), 8);
+        Ok(())
+    }
+
+    #[bench]
+    fn bench_coalesce(b: &mut Bencher) {
+        let (batch, _): (ReceiveBatch, Vec<u8>) = build_batch(32);
+        let coalescer: ReceiveCoalescer = ReceiveCoalescer::new(64 * 1024 - 256, false);
+        b.bytes = (32 * SEGMENT_SIZE) as u64;
+        b.iter(|| black_box(coalescer.coalesce(batch.clone())));
+    }
+}
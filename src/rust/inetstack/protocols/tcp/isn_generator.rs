@@ -1,47 +1,294 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use crate::inetstack::protocols::tcp::SeqNumber;
-#[allow(unused_imports)]
-use std::{
-    hash::Hasher,
+use crate::{
+    inetstack::protocols::tcp::SeqNumber,
+    runtime::timer::TimerRc,
+};
+use ::rand::{
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
+use ::std::{
+    collections::HashMap,
+    mem,
     net::SocketAddrV4,
-    num::Wrapping,
+    time::Duration,
 };
 
-#[allow(dead_code)]
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Lifetime of the secret that initial sequence numbers are hashed with. It outlasts twice the maximum segment lifetime,
+/// so that ISNs only need to be remembered for two lifetimes to keep them increasing across a rotation of the secret.
+const ISN_SECRET_LIFETIME: Duration = Duration::from_secs(120);
+
+/// Period of the clock-driven component of initial sequence numbers (RFC 6528).
+const ISN_CLOCK_TICK: Duration = Duration::from_micros(4);
+
+/// Maximum number of ISNs that are remembered for each secret. Passive opens generate an ISN for every SYN, so this
+/// keeps a flood of SYNs from spoofed addresses from growing the generator without bound.
+const MAX_REMEMBERED_ISNS: usize = 16 * 1024;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Generator of initial sequence numbers, as per RFC 6528. An ISN is the sum of a clock that ticks every 4 µs and of a
+/// keyed hash of the connection identifier, so that ISNs of a connection keep increasing over time, while ISNs of other
+/// connections cannot be guessed by off-path attackers. Secrets are drawn from the randomness of the operating system,
+/// so that they differ across hosts and runs.
 pub struct IsnGenerator {
-    nonce: u32,
-    counter: Wrapping<u16>,
+    /// Source of secrets.
+    rng: StdRng,
+    /// Secret key of the hash.
+    secret: [u64; 2],
+    /// Time of the last rotation of the secret.
+    rotated_at: Duration,
+    /// ISNs generated with the current secret, up to [MAX_REMEMBERED_ISNS] of them.
+    current: HashMap<(SocketAddrV4, SocketAddrV4), SeqNumber>,
+    /// ISNs generated with the previous secret.
+    previous: HashMap<(SocketAddrV4, SocketAddrV4), SeqNumber>,
 }
 
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
 impl IsnGenerator {
-    pub fn new(nonce: u32) -> Self {
+    pub fn new() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+
+    /// Creates a generator that draws its secrets from `rng`.
+    fn with_rng(mut rng: StdRng) -> Self {
+        let secret: [u64; 2] = rng.gen();
         Self {
-            nonce,
-            counter: Wrapping(0),
+            rng,
+            secret,
+            rotated_at: Duration::ZERO,
+            current: HashMap::new(),
+            previous: HashMap::new(),
         }
     }
 
     #[cfg(test)]
-    pub fn generate(&mut self, _local: &SocketAddrV4, _remote: &SocketAddrV4) -> SeqNumber {
+    pub fn generate(&mut self, _local: &SocketAddrV4, _remote: &SocketAddrV4, _clock: &TimerRc) -> SeqNumber {
         SeqNumber::from(0)
     }
 
     #[cfg(not(test))]
-    pub fn generate(&mut self, local: &SocketAddrV4, remote: &SocketAddrV4) -> SeqNumber {
-        let crc: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_CKSUM);
-        let mut digest = crc.digest();
-        digest.update(&remote.ip().octets());
-        let remote_port: u16 = remote.port().into();
-        digest.update(&remote_port.to_be_bytes());
-        digest.update(&local.ip().octets());
-        let local_port: u16 = local.port().into();
-        digest.update(&local_port.to_be_bytes());
-        digest.update(&self.nonce.to_be_bytes());
-        let digest = digest.finalize();
-        let isn = SeqNumber::from(digest + self.counter.0 as u32);
-        self.counter += Wrapping(1);
+    pub fn generate(&mut self, local: &SocketAddrV4, remote: &SocketAddrV4, clock: &TimerRc) -> SeqNumber {
+        self.generate_at(local, remote, clock.elapsed())
+    }
+
+    /// Generates the ISN of a connection, given the time that elapsed since the clock started.
+    fn generate_at(&mut self, local: &SocketAddrV4, remote: &SocketAddrV4, elapsed: Duration) -> SeqNumber {
+        if elapsed.saturating_sub(self.rotated_at) >= ISN_SECRET_LIFETIME {
+            self.secret = self.rng.gen();
+            self.rotated_at = elapsed;
+            self.previous = mem::take(&mut self.current);
+        }
+
+        let mut id: [u8; 12] = [0; 12];
+        id[0..4].copy_from_slice(&local.ip().octets());
+        id[4..6].copy_from_slice(&local.port().to_be_bytes());
+        id[6..10].copy_from_slice(&remote.ip().octets());
+        id[10..12].copy_from_slice(&remote.port().to_be_bytes());
+        let ticks: u32 = (elapsed.as_nanos() / ISN_CLOCK_TICK.as_nanos()) as u32;
+        let mut isn: SeqNumber = SeqNumber::from(ticks.wrapping_add(siphash24(&self.secret, &id) as u32));
+
+        // The hash changes along with the secret, thus make sure that the ISN still comes after the previous one. Once
+        // too many ISNs are remembered, new connections only get the guarantee of the clock until the next rotation.
+        let key: (SocketAddrV4, SocketAddrV4) = (*local, *remote);
+        if let Some(last) = self.current.get(&key).or_else(|| self.previous.get(&key)) {
+            if isn <= *last {
+                isn = *last + SeqNumber::from(1);
+            }
+        }
+        if self.current.len() < MAX_REMEMBERED_ISNS || self.current.contains_key(&key) {
+            self.current.insert(key, isn);
+        }
+
         isn
     }
 }
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Default for IsnGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Computes the SipHash-2-4 of `data` under `key`.
+fn siphash24(key: &[u64; 2], data: &[u8]) -> u64 {
+    let mut v: [u64; 4] = [
+        0x736f6d6570736575 ^ key[0],
+        0x646f72616e646f6d ^ key[1],
+        0x6c7967656e657261 ^ key[0],
+        0x7465646279746573 ^ key[1],
+    ];
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let m: u64 = u64::from_le_bytes([
+            chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7],
+        ]);
+        v[3] ^= m;
+        sipround(&mut v);
+        sipround(&mut v);
+        v[0] ^= m;
+    }
+
+    // The last block carries the remaining bytes and the length of the data.
+    let mut last: u64 = (data.len() as u64) << 56;
+    for (i, byte) in chunks.remainder().iter().enumerate() {
+        last |= (*byte as u64) << (8 * i);
+    }
+    v[3] ^= last;
+    sipround(&mut v);
+    sipround(&mut v);
+    v[0] ^= last;
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        sipround(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+/// Runs a round of SipHash.
+fn sipround(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13);
+    v[1] ^= v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16);
+    v[3] ^= v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21);
+    v[3] ^= v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17);
+    v[1] ^= v[2];
+    v[2] = v[2].rotate_left(32);
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod test {
+    use super::{
+        siphash24,
+        IsnGenerator,
+        ISN_SECRET_LIFETIME,
+        MAX_REMEMBERED_ISNS,
+    };
+    use crate::inetstack::protocols::tcp::SeqNumber;
+    use ::anyhow::Result;
+    use ::rand::{
+        rngs::StdRng,
+        SeedableRng,
+    };
+    use ::std::{
+        net::{
+            Ipv4Addr,
+            SocketAddrV4,
+        },
+        time::Duration,
+    };
+
+    /// Tests the SipHash-2-4 implementation against the reference test vector.
+    #[test]
+    fn test_siphash24() -> Result<()> {
+        let key: [u64; 2] = [0x0706050403020100, 0x0f0e0d0c0b0a0908];
+        let data: Vec<u8> = (0..15).collect();
+        crate::ensure_eq!(siphash24(&key, &data), 0xa129ca6149be45e5);
+        Ok(())
+    }
+
+    /// Tests that the ISNs of a connection keep increasing over time, also across rotations of the secret.
+    #[test]
+    fn test_isn_monotonic() -> Result<()> {
+        let mut generator: IsnGenerator = IsnGenerator::with_rng(StdRng::from_seed([7; 32]));
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 49152);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 80);
+
+        let mut elapsed: Duration = Duration::ZERO;
+        let mut last: SeqNumber = generator.generate_at(&local, &remote, elapsed);
+        for _ in 0..64 {
+            elapsed += ISN_SECRET_LIFETIME / 8;
+            let isn: SeqNumber = generator.generate_at(&local, &remote, elapsed);
+            crate::ensure_eq!(isn > last, true);
+            last = isn;
+        }
+        Ok(())
+    }
+
+    /// Tests that connections between different endpoints get different ISNs at the same time.
+    #[test]
+    fn test_isn_differs_across_connections() -> Result<()> {
+        let mut generator: IsnGenerator = IsnGenerator::with_rng(StdRng::from_seed([7; 32]));
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 49152);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 80);
+        let elapsed: Duration = Duration::from_secs(1);
+
+        let isn: SeqNumber = generator.generate_at(&local, &remote, elapsed);
+        for other in [
+            SocketAddrV4::new(*remote.ip(), 81),
+            SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 3), 80),
+        ] {
+            crate::ensure_neq!(generator.generate_at(&local, &other, elapsed), isn);
+        }
+        let other_local: SocketAddrV4 = SocketAddrV4::new(*local.ip(), 49153);
+        crate::ensure_neq!(generator.generate_at(&other_local, &remote, elapsed), isn);
+        Ok(())
+    }
+
+    /// Tests that generators pick their secrets on their own, so that they hand out different ISNs for the same
+    /// connection.
+    #[test]
+    fn test_isn_secret_from_entropy() -> Result<()> {
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 49152);
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 80);
+        let elapsed: Duration = Duration::from_secs(1);
+
+        let isn: SeqNumber = IsnGenerator::new().generate_at(&local, &remote, elapsed);
+        crate::ensure_neq!(IsnGenerator::new().generate_at(&local, &remote, elapsed), isn);
+        Ok(())
+    }
+
+    /// Tests that a flood of connections from different endpoints does not grow the generator without bound.
+    #[test]
+    fn test_isn_remembered_bounded() -> Result<()> {
+        let mut generator: IsnGenerator = IsnGenerator::with_rng(StdRng::from_seed([7; 32]));
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 80);
+        let elapsed: Duration = Duration::from_secs(1);
+
+        for i in 0..(MAX_REMEMBERED_ISNS + 1024) as u32 {
+            let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::from(0x0a000000 + i), 49152);
+            generator.generate_at(&local, &remote, elapsed);
+        }
+        crate::ensure_eq!(generator.current.len(), MAX_REMEMBERED_ISNS);
+
+        // Remembered ISNs are dropped once they are two lifetimes of the secret old.
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 49152);
+        generator.generate_at(&local, &remote, elapsed + ISN_SECRET_LIFETIME);
+        generator.generate_at(&local, &remote, elapsed + ISN_SECRET_LIFETIME * 2);
+        crate::ensure_eq!(generator.current.len() + generator.previous.len(), 2);
+        Ok(())
+    }
+}
@@ -2,21 +2,39 @@
 // Licensed under the MIT license.
 
 mod active_open;
+pub mod auth;
 pub mod constants;
+mod context;
 mod established;
+mod events;
+pub mod gro;
 mod isn_generator;
 pub mod operations;
 mod passive_open;
 pub mod peer;
+mod pmtu;
 pub mod queue;
+mod receive_buffer;
 pub mod segment;
 mod sequence_number;
+mod telemetry;
+mod timestamps;
+mod trace;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::{
-    established::congestion_control,
+    auth::{
+        SegmentAuthenticator,
+        TcpMd5Signature,
+    },
+    established::{
+        congestion_control,
+        State as TcpState,
+        TcpConnectionStats,
+        TcpInfo,
+    },
     peer::TcpPeer,
     segment::{
         MAX_TCP_HEADER_SIZE,
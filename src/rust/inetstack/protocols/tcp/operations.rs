@@ -23,69 +23,81 @@ use ::std::{
     },
 };
 
-pub struct ConnectFuture<const N: usize> {
+pub struct ConnectFuture {
     pub qd: QDesc,
-    pub inner: Rc<RefCell<Inner<N>>>,
+    pub inner: Rc<RefCell<Inner>>,
 }
 
-impl<const N: usize> fmt::Debug for ConnectFuture<N> {
+impl fmt::Debug for ConnectFuture {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "ConnectFuture({:?})", self.qd)
     }
 }
 
-impl<const N: usize> Future for ConnectFuture<N> {
+impl Future for ConnectFuture {
     type Output = Result<(), Fail>;
 
     fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
         let self_ = self.get_mut();
-        self_.inner.borrow_mut().poll_connect_finished(self_.qd, context)
+        let qd: QDesc = self_.qd;
+        self_
+            .inner
+            .borrow_mut()
+            .poll_connect_finished(qd, context)
+            .map_err(|e| Fail::new(e.errno, &format!("failed to connect (qd={:?})", qd)).chain(e))
     }
 }
 
 /// Accept Operation Descriptor
-pub struct AcceptFuture<const N: usize> {
+pub struct AcceptFuture {
     /// Queue descriptor of listening socket.
     qd: QDesc,
     // Pre-booked queue descriptor for incoming connection.
     new_qd: QDesc,
     // Reference to associated inner TCP peer.
-    inner: Rc<RefCell<Inner<N>>>,
+    inner: Rc<RefCell<Inner>>,
 }
 
 /// Associated Functions for Accept Operation Descriptors
-impl<const N: usize> AcceptFuture<N> {
+impl AcceptFuture {
     /// Creates a descriptor for an accept operation.
-    pub fn new(qd: QDesc, new_qd: QDesc, inner: Rc<RefCell<Inner<N>>>) -> Self {
+    pub fn new(qd: QDesc, new_qd: QDesc, inner: Rc<RefCell<Inner>>) -> Self {
         Self { qd, new_qd, inner }
     }
 }
 
 /// Debug Trait Implementation for Accept Operation Descriptors
-impl<const N: usize> fmt::Debug for AcceptFuture<N> {
+impl fmt::Debug for AcceptFuture {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "AcceptFuture({:?})", self.qd)
     }
 }
 
 /// Future Trait Implementation for Accept Operation Descriptors
-impl<const N: usize> Future for AcceptFuture<N> {
+impl Future for AcceptFuture {
     type Output = Result<(QDesc, SocketAddrV4), Fail>;
 
     /// Polls the underlying accept operation.
     fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
-        let self_: &mut AcceptFuture<N> = self.get_mut();
+        let self_: &mut AcceptFuture = self.get_mut();
         // TODO: The following design pattern looks ugly. We should move poll_accept to the inner structure.
-        let peer: TcpPeer<N> = TcpPeer {
+        let peer: TcpPeer = TcpPeer {
             inner: self_.inner.clone(),
         };
-        peer.poll_accept(self_.qd, self_.new_qd, context)
+        let qd: QDesc = self_.qd;
+        peer.poll_accept(qd, self_.new_qd, context)
+            .map_err(|e| Fail::new(e.errno, &format!("failed to accept connection (qd={:?})", qd)).chain(e))
     }
 }
 
+/// Completes once the peer acknowledges some pushed data.
+pub type AckFuture = Pin<Box<dyn Future<Output = Result<(), Fail>>>>;
+
 pub struct PushFuture {
     pub qd: QDesc,
     pub err: Option<Fail>,
+    /// Completes once the peer acknowledges the pushed data, if the socket completes pushes on acknowledgement.
+    pub acked: Option<AckFuture>,
 }
 
 impl fmt::Debug for PushFuture {
@@ -97,27 +109,33 @@ impl fmt::Debug for PushFuture {
 impl Future for PushFuture {
     type Output = Result<(), Fail>;
 
-    fn poll(self: Pin<&mut Self>, _context: &mut Context) -> Poll<Self::Output> {
-        match self.get_mut().err.take() {
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let self_: &mut Self = self.get_mut();
+        if let Some(e) = self_.err.take() {
+            return Poll::Ready(Err(e));
+        }
+        match self_.acked {
+            Some(ref mut acked) => acked.as_mut().poll(context),
             None => Poll::Ready(Ok(())),
-            Some(e) => Poll::Ready(Err(e)),
         }
     }
 }
 
-pub struct PopFuture<const N: usize> {
+pub struct PopFuture {
     pub qd: QDesc,
     pub size: Option<usize>,
-    pub inner: Rc<RefCell<Inner<N>>>,
+    /// Leave the data in the receive queue?
+    pub peek: bool,
+    pub inner: Rc<RefCell<Inner>>,
 }
 
-impl<const N: usize> fmt::Debug for PopFuture<N> {
+impl fmt::Debug for PopFuture {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "PopFuture({:?})", self.qd)
     }
 }
 
-impl<const N: usize> Future for PopFuture<N> {
+impl Future for PopFuture {
     type Output = Result<DemiBuffer, Fail>;
 
     fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
@@ -126,22 +144,53 @@ impl<const N: usize> Future for PopFuture<N> {
         let peer = TcpPeer {
             inner: self_.inner.clone(),
         };
-        peer.poll_recv(self_.qd, ctx, size)
+        let qd: QDesc = self_.qd;
+        let result: Poll<Result<DemiBuffer, Fail>> = if self_.peek {
+            peer.poll_peek(qd, ctx, size)
+        } else {
+            peer.poll_recv(qd, ctx, size)
+        };
+        result.map_err(|e| Fail::new(e.errno, &format!("failed to pop data (qd={:?})", qd)).chain(e))
+    }
+}
+
+pub struct PopMultipleFuture {
+    pub qd: QDesc,
+    pub max_msgs: usize,
+    pub inner: Rc<RefCell<Inner>>,
+}
+
+impl fmt::Debug for PopMultipleFuture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PopMultipleFuture({:?})", self.qd)
+    }
+}
+
+impl Future for PopMultipleFuture {
+    type Output = Result<Vec<DemiBuffer>, Fail>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let self_ = self.get_mut();
+        let max_msgs: usize = self_.max_msgs;
+        let peer = TcpPeer {
+            inner: self_.inner.clone(),
+        };
+        peer.poll_recv_multiple(self_.qd, ctx, max_msgs)
     }
 }
 
-pub struct CloseFuture<const N: usize> {
+pub struct CloseFuture {
     pub qd: QDesc,
-    pub inner: Rc<RefCell<Inner<N>>>,
+    pub inner: Rc<RefCell<Inner>>,
 }
 
-impl<const N: usize> fmt::Debug for CloseFuture<N> {
+impl fmt::Debug for CloseFuture {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "CloseFuture({:?})", self.qd)
     }
 }
 
-impl<const N: usize> Future for CloseFuture<N> {
+impl Future for CloseFuture {
     type Output = Result<(), Fail>;
 
     fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
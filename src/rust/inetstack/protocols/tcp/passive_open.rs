@@ -2,13 +2,21 @@
 // Licensed under the MIT license.
 
 use super::{
+    auth::SegmentAuthenticator,
     constants::FALLBACK_MSS,
-    established::ControlBlock,
-    isn_generator::IsnGenerator,
+    context::TcpContext,
+    established::{
+        ControlBlock,
+        HandshakeParameters,
+    },
+    trace::{
+        self,
+        ConnectionSpan,
+        ConnectionState,
+    },
 };
 use crate::{
     inetstack::protocols::{
-        arp::ArpPeer,
         ethernet2::{
             EtherType2,
             Ethernet2Header,
@@ -16,35 +24,35 @@ use crate::{
         ip::IpProtocol,
         ipv4::Ipv4Header,
         tcp::{
-            established::{
-                congestion_control,
-                congestion_control::CongestionControl,
-            },
             segment::{
                 TcpHeader,
                 TcpOptions2,
                 TcpSegment,
             },
+            timestamps::{
+                find_timestamps,
+                timestamp_value,
+            },
             SeqNumber,
         },
     },
     runtime::{
         fail::Fail,
         network::{
-            config::TcpConfig,
-            types::MacAddress,
-            NetworkRuntime,
+            config::BacklogPolicy,
+            types::{
+                ConnEvent,
+                MacAddress,
+            },
         },
         queue::BackgroundTask,
-        timer::TimerRc,
-    },
-    scheduler::{
-        Scheduler,
-        TaskHandle,
+        QDesc,
     },
+    scheduler::TaskHandle,
 };
 use ::libc::{
     EBADMSG,
+    ECONNABORTED,
     ECONNREFUSED,
     ETIMEDOUT,
 };
@@ -57,14 +65,20 @@ use ::std::{
     },
     convert::TryInto,
     future::Future,
-    net::SocketAddrV4,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
     rc::Rc,
     task::{
         Context,
         Poll,
         Waker,
     },
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 struct InflightAccept {
@@ -73,38 +87,52 @@ struct InflightAccept {
     header_window_size: u16,
     remote_window_scale: Option<u8>,
     mss: usize,
+    ecn: bool,
+    remote_timestamp: Option<u32>,
+    // When the connection request came in.
+    syn_received_at: Instant,
 
     #[allow(unused)]
     handle: TaskHandle,
 }
 
-struct ReadySockets<const N: usize> {
-    ready: VecDeque<Result<ControlBlock<N>, Fail>>,
+struct ReadySockets {
+    ready: VecDeque<Result<ControlBlock, Fail>>,
     endpoints: HashSet<SocketAddrV4>,
-    waker: Option<Waker>,
+    // Wakers of pending accepts. There may be several of them on the same listening socket.
+    wakers: Vec<Waker>,
+    // Was the listening socket shut down?
+    stopped: bool,
 }
 
-impl<const N: usize> ReadySockets<N> {
-    fn push_ok(&mut self, cb: ControlBlock<N>) {
+impl ReadySockets {
+    fn push_ok(&mut self, cb: ControlBlock) {
         assert!(self.endpoints.insert(cb.get_remote()));
         self.ready.push_back(Ok(cb));
-        if let Some(w) = self.waker.take() {
-            w.wake()
-        }
+        self.wake();
     }
 
     fn push_err(&mut self, err: Fail) {
         self.ready.push_back(Err(err));
-        if let Some(w) = self.waker.take() {
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        for w in self.wakers.drain(..) {
             w.wake()
         }
     }
 
-    fn poll(&mut self, ctx: &mut Context) -> Poll<Result<ControlBlock<N>, Fail>> {
+    fn poll(&mut self, ctx: &mut Context) -> Poll<Result<ControlBlock, Fail>> {
+        if self.stopped {
+            return Poll::Ready(Err(Fail::new(ECONNABORTED, "listening socket was shut down")));
+        }
         let r = match self.ready.pop_front() {
             Some(r) => r,
             None => {
-                self.waker.replace(ctx.waker().clone());
+                if !self.wakers.iter().any(|w| w.will_wake(ctx.waker())) {
+                    self.wakers.push(ctx.waker().clone());
+                }
                 return Poll::Pending;
             },
         };
@@ -119,52 +147,49 @@ impl<const N: usize> ReadySockets<N> {
     }
 }
 
-pub struct PassiveSocket<const N: usize> {
+pub struct PassiveSocket {
     inflight: HashMap<SocketAddrV4, InflightAccept>,
-    ready: Rc<RefCell<ReadySockets<N>>>,
+    ready: Rc<RefCell<ReadySockets>>,
 
     max_backlog: usize,
-    isn_generator: IsnGenerator,
+    ctx: TcpContext,
+    // Queue descriptor of this socket, which we report turned away connections with.
+    qd: QDesc,
 
     local: SocketAddrV4,
-    rt: Rc<dyn NetworkRuntime<N>>,
-    scheduler: Scheduler,
-    clock: TimerRc,
-    tcp_config: TcpConfig,
-    local_link_addr: MacAddress,
-    arp: ArpPeer<N>,
+    authenticator: Option<Rc<dyn SegmentAuthenticator>>,
 }
 
-impl<const N: usize> PassiveSocket<N> {
+impl PassiveSocket {
     pub fn new(
         local: SocketAddrV4,
         max_backlog: usize,
-        rt: Rc<dyn NetworkRuntime<N>>,
-        scheduler: Scheduler,
-        clock: TimerRc,
-        tcp_config: TcpConfig,
-        local_link_addr: MacAddress,
-        arp: ArpPeer<N>,
-        nonce: u32,
+        ctx: TcpContext,
+        qd: QDesc,
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
     ) -> Self {
         let ready = ReadySockets {
             ready: VecDeque::new(),
             endpoints: HashSet::new(),
-            waker: None,
+            wakers: Vec::new(),
+            stopped: false,
         };
         let ready = Rc::new(RefCell::new(ready));
+        trace::transition(
+            local,
+            SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+            ConnectionState::Closed,
+            ConnectionState::Listen,
+            "listen",
+        );
         Self {
             inflight: HashMap::new(),
             ready,
             max_backlog,
-            isn_generator: IsnGenerator::new(nonce),
+            ctx,
+            qd,
             local,
-            local_link_addr,
-            rt,
-            scheduler,
-            clock,
-            tcp_config,
-            arp,
+            authenticator,
         }
     }
 
@@ -173,7 +198,59 @@ impl<const N: usize> PassiveSocket<N> {
         self.local
     }
 
-    pub fn poll_accept(&mut self, ctx: &mut Context) -> Poll<Result<ControlBlock<N>, Fail>> {
+    /// Moves this socket over to a new local address. Connections that are still going through the handshake keep the
+    /// old one.
+    pub fn set_local_identity(&mut self, local: SocketAddrV4, local_link_addr: MacAddress) {
+        self.local = local;
+        self.ctx.local_link_addr = local_link_addr;
+    }
+
+    /// Returns the number of connections that completed the handshake and are waiting to be accepted.
+    pub fn accept_queue_len(&self) -> usize {
+        self.ready.borrow().len()
+    }
+
+    /// Checks if this socket was shut down with [Self::stop_listening].
+    pub fn is_stopped(&self) -> bool {
+        self.ready.borrow().stopped
+    }
+
+    /// Stops accepting new connections. Connections that are still in the backlog are reset, and pending accepts
+    /// complete with `ECONNABORTED`.
+    pub fn stop_listening(&mut self) {
+        // Reset connections that are still going through the handshake.
+        let inflight: Vec<(SocketAddrV4, InflightAccept)> = self.inflight.drain().collect();
+        for (remote, mut inflight) in inflight {
+            inflight.handle.deschedule();
+            self.ctx.handshakes.record_failed(false);
+            self.send_rst(remote, inflight.local_isn + SeqNumber::from(1), None);
+            trace::transition(
+                self.local,
+                remote,
+                ConnectionState::SynReceived,
+                ConnectionState::Closed,
+                "close",
+            );
+        }
+        trace::transition(
+            self.local,
+            SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+            ConnectionState::Listen,
+            ConnectionState::Closed,
+            "close",
+        );
+
+        // Reset connections that were established but not yet accepted.
+        let mut ready = self.ready.borrow_mut();
+        for cb in ready.ready.drain(..).flatten() {
+            cb.abort();
+        }
+        ready.endpoints.clear();
+        ready.stopped = true;
+        ready.wake();
+    }
+
+    pub fn poll_accept(&mut self, ctx: &mut Context) -> Poll<Result<ControlBlock, Fail>> {
         self.ready.borrow_mut().poll(ctx)
     }
 
@@ -187,6 +264,8 @@ impl<const N: usize> PassiveSocket<N> {
 
         // If the packet is for an inflight connection, route it there.
         if self.inflight.contains_key(&remote) {
+            let span: ConnectionSpan = ConnectionSpan::new(self.local, remote);
+            let _span = span.enter();
             if !header.ack {
                 return Err(Fail::new(EBADMSG, "expeting ACK"));
             }
@@ -197,14 +276,20 @@ impl<const N: usize> PassiveSocket<N> {
                 header_window_size,
                 remote_window_scale,
                 mss,
+                ecn,
+                remote_timestamp,
+                syn_received_at,
                 ..
             } = self.inflight.get(&remote).unwrap();
             if header.ack_num != local_isn + SeqNumber::from(1) {
                 return Err(Fail::new(EBADMSG, "invalid SYN+ACK seq num"));
             }
+            let remote_timestamp: Option<u32> = remote_timestamp.map(|remote_timestamp| {
+                find_timestamps(header).map_or(remote_timestamp, |(sender_timestamp, _)| sender_timestamp)
+            });
 
             let (local_window_scale, remote_window_scale) = match remote_window_scale {
-                Some(w) => (self.tcp_config.get_window_scale() as u32, w),
+                Some(w) => (self.ctx.tcp_config.get_window_scale() as u32, w),
                 None => (0, 0),
             };
             let remote_window_size = (header_window_size)
@@ -212,7 +297,7 @@ impl<const N: usize> PassiveSocket<N> {
                 .expect("TODO: Window size overflow")
                 .try_into()
                 .expect("TODO: Window size overflow");
-            let local_window_size = (self.tcp_config.get_receive_window_size() as u32)
+            let local_window_size = (self.ctx.tcp_config.get_receive_window_size() as u32)
                 .checked_shl(local_window_scale as u32)
                 .expect("TODO: Window size overflow");
             info!(
@@ -227,26 +312,35 @@ impl<const N: usize> PassiveSocket<N> {
             if let Some(mut inflight) = self.inflight.remove(&remote) {
                 inflight.handle.deschedule();
             }
+            self.ctx
+                .handshakes
+                .record_completed(self.ctx.clock.now().duration_since(syn_received_at));
 
+            let handshake = HandshakeParameters {
+                receiver_seq_no: remote_isn + SeqNumber::from(1),
+                receiver_window_size: local_window_size,
+                receiver_window_scale: local_window_scale,
+                sender_seq_no: local_isn + SeqNumber::from(1),
+                sender_window_size: remote_window_size,
+                sender_window_scale: remote_window_scale,
+                sender_mss: mss,
+                ecn,
+                remote_timestamp,
+            };
             let cb = ControlBlock::new(
                 self.local,
                 remote,
-                self.rt.clone(),
-                self.scheduler.clone(),
-                self.clock.clone(),
-                self.local_link_addr,
-                self.tcp_config.clone(),
-                self.arp.clone(),
-                remote_isn + SeqNumber::from(1),
-                self.tcp_config.get_ack_delay_timeout(),
-                local_window_size,
-                local_window_scale,
-                local_isn + SeqNumber::from(1),
-                remote_window_size,
-                remote_window_scale,
-                mss,
-                congestion_control::None::new,
-                None,
+                *remote.ip(),
+                self.ctx.clone(),
+                handshake,
+                self.authenticator.clone(),
+            );
+            trace::transition(
+                self.local,
+                remote,
+                ConnectionState::SynReceived,
+                ConnectionState::Established,
+                "ACK_received",
             );
             self.ready.borrow_mut().push_ok(cb);
             return Ok(());
@@ -257,29 +351,48 @@ impl<const N: usize> PassiveSocket<N> {
             return Err(Fail::new(EBADMSG, "invalid flags"));
         }
         debug!("Received SYN: {:?}", header);
+        self.ctx.handshakes.record_syn_received();
         if inflight_len + self.ready.borrow().len() >= self.max_backlog {
-            // TODO: Should we send a RST here?
+            self.ctx.handshakes.record_failed(false);
+            self.ctx.events.push(ConnEvent::AcceptQueueFull(self.qd));
+            match self.ctx.tcp_config.get_backlog_full_policy() {
+                BacklogPolicy::Drop => debug!("Backlog is full, dropping SYN from {:?}", remote),
+                BacklogPolicy::Rst => {
+                    debug!("Backlog is full, refusing connection from {:?}", remote);
+                    self.send_rst(remote, SeqNumber::from(0), Some(header.seq_num + SeqNumber::from(1)));
+                },
+            }
             return Err(Fail::new(ECONNREFUSED, "connection refused"));
         }
-        let local_isn = self.isn_generator.generate(&self.local, &remote);
+        let local_isn = self
+            .ctx
+            .isn_generator
+            .borrow_mut()
+            .generate(&self.local, &remote, &self.ctx.clock);
         let remote_isn = header.seq_num;
+        // An ECN-setup SYN has both ECE and CWR set (RFC 3168).
+        let ecn: bool = self.ctx.tcp_config.get_ecn_enabled() && header.ece && header.cwr;
+        // We only use timestamps if our peer offers them (RFC 7323).
+        let remote_timestamp: Option<u32> = match self.ctx.tcp_config.get_timestamps_enabled() {
+            true => find_timestamps(header).map(|(sender_timestamp, _)| sender_timestamp),
+            false => None,
+        };
         let future = Self::background(
             local_isn,
             remote_isn,
+            ecn,
+            remote_timestamp,
             self.local,
             remote,
-            self.rt.clone(),
-            self.clock.clone(),
-            self.tcp_config.clone(),
-            self.local_link_addr,
-            self.arp.clone(),
+            self.ctx.clone(),
+            self.authenticator.clone(),
             self.ready.clone(),
         );
         let task: BackgroundTask = BackgroundTask::new(
             String::from("Inetstack::TCP::passiveopen::background"),
             Box::pin(future),
         );
-        let handle: TaskHandle = match self.scheduler.insert(task) {
+        let handle: TaskHandle = match self.ctx.scheduler.insert(task) {
             Some(handle) => handle,
             None => panic!("failed to insert task in the scheduler"),
         };
@@ -299,30 +412,84 @@ impl<const N: usize> PassiveSocket<N> {
                 _ => continue,
             }
         }
+        {
+            let span: ConnectionSpan = ConnectionSpan::new(self.local, remote);
+            let _span = span.enter();
+            trace::transition(
+                self.local,
+                remote,
+                ConnectionState::Listen,
+                ConnectionState::SynReceived,
+                "SYN_received",
+            );
+        }
         let accept = InflightAccept {
             local_isn,
             remote_isn,
             header_window_size: header.window_size,
             remote_window_scale,
             mss,
+            ecn,
+            remote_timestamp,
+            syn_received_at: self.ctx.clock.now(),
             handle,
         };
         self.inflight.insert(remote, accept);
         Ok(())
     }
 
+    /// Sends a RST to `remote`. If `ack_num` is given, the RST also acknowledges it.
+    fn send_rst(&self, remote: SocketAddrV4, seq_num: SeqNumber, ack_num: Option<SeqNumber>) {
+        let remote_link_addr: MacAddress = match self.ctx.arp.try_query(*remote.ip()) {
+            Some(remote_link_addr) => remote_link_addr,
+            None => {
+                warn!("Cannot reset connection from {:?}: not in ARP cache", remote);
+                return;
+            },
+        };
+        let mut tcp_hdr = TcpHeader::new(self.local.port(), remote.port());
+        tcp_hdr.rst = true;
+        tcp_hdr.seq_num = seq_num;
+        if let Some(ack_num) = ack_num {
+            tcp_hdr.ack = true;
+            tcp_hdr.ack_num = ack_num;
+        }
+        let mut ipv4_hdr: Ipv4Header = Ipv4Header::new(*self.local.ip(), *remote.ip(), IpProtocol::TCP);
+        ipv4_hdr.set_identification(self.ctx.rt.next_ipv4_id());
+        if let Some(authenticator) = &self.authenticator {
+            tcp_hdr.sign(authenticator.as_ref(), &ipv4_hdr, &[]);
+        }
+        debug!("Sending RST: {:?}", tcp_hdr);
+        let segment = TcpSegment {
+            ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.ctx.local_link_addr, EtherType2::Ipv4),
+            ipv4_hdr,
+            tcp_hdr,
+            data: None,
+            tx_checksum_offload: self.ctx.tcp_config.get_rx_checksum_offload(),
+        };
+        self.ctx.rt.transmit(Box::new(segment));
+    }
+
     fn background(
         local_isn: SeqNumber,
         remote_isn: SeqNumber,
+        ecn: bool,
+        remote_timestamp: Option<u32>,
         local: SocketAddrV4,
         remote: SocketAddrV4,
-        rt: Rc<dyn NetworkRuntime<N>>,
-        clock: TimerRc,
-        tcp_config: TcpConfig,
-        local_link_addr: MacAddress,
-        arp: ArpPeer<N>,
-        ready: Rc<RefCell<ReadySockets<N>>>,
+        ctx: TcpContext,
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+        ready: Rc<RefCell<ReadySockets>>,
     ) -> impl Future<Output = ()> {
+        let TcpContext {
+            rt,
+            clock,
+            local_link_addr,
+            tcp_config,
+            arp,
+            handshakes,
+            ..
+        } = ctx;
         let handshake_retries: usize = tcp_config.get_handshake_retries();
         let handshake_timeout: Duration = tcp_config.get_handshake_timeout();
 
@@ -341,6 +508,8 @@ impl<const N: usize> PassiveSocket<N> {
                 tcp_hdr.ack = true;
                 tcp_hdr.ack_num = remote_isn + SeqNumber::from(1);
                 tcp_hdr.window_size = tcp_config.get_receive_window_size();
+                // An ECN-setup SYN+ACK has only ECE set (RFC 3168).
+                tcp_hdr.ece = ecn;
 
                 let mss = tcp_config.get_advertised_mss() as u16;
                 tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
@@ -349,17 +518,40 @@ impl<const N: usize> PassiveSocket<N> {
                 tcp_hdr.push_option(TcpOptions2::WindowScale(tcp_config.get_window_scale()));
                 info!("Advertising window scale: {}", tcp_config.get_window_scale());
 
+                if let Some(remote_timestamp) = remote_timestamp {
+                    tcp_hdr.push_option(TcpOptions2::Timestamp {
+                        sender_timestamp: timestamp_value(&clock),
+                        echo_timestamp: remote_timestamp,
+                    });
+                }
+
+                let mut ipv4_hdr: Ipv4Header =
+                    Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP);
+                ipv4_hdr.set_identification(rt.next_ipv4_id());
+                if let Some(authenticator) = &authenticator {
+                    tcp_hdr.sign(authenticator.as_ref(), &ipv4_hdr, &[]);
+                }
+
                 debug!("Sending SYN+ACK: {:?}", tcp_hdr);
                 let segment = TcpSegment {
                     ethernet2_hdr: Ethernet2Header::new(remote_link_addr, local_link_addr, EtherType2::Ipv4),
-                    ipv4_hdr: Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
+                    ipv4_hdr,
                     tcp_hdr,
                     data: None,
                     tx_checksum_offload: tcp_config.get_rx_checksum_offload(),
                 };
                 rt.transmit(Box::new(segment));
+                handshakes.record_syn_ack_sent();
                 clock.wait(clock.clone(), handshake_timeout).await;
             }
+            trace::transition(
+                local,
+                remote,
+                ConnectionState::SynReceived,
+                ConnectionState::Closed,
+                "handshake_timeout",
+            );
+            handshakes.record_failed(true);
             ready.borrow_mut().push_err(Fail::new(ETIMEDOUT, "handshake timeout"));
         }
     }
@@ -7,10 +7,22 @@
 
 use super::{
     active_open::ActiveOpenSocket,
-    established::EstablishedSocket,
+    auth::SegmentAuthenticator,
+    context::TcpContext,
+    established::{
+        EstablishedSocket,
+        TcpConnectionStats,
+        TcpInfo,
+    },
+    events::ConnEventQueue,
     isn_generator::IsnGenerator,
     passive_open::PassiveSocket,
+    pmtu::PmtuTable,
     queue::TcpQueue,
+    telemetry::{
+        HandshakeTelemetry,
+        PeerTelemetry,
+    },
 };
 use crate::{
     inetstack::protocols::{
@@ -23,21 +35,32 @@ use crate::{
             EphemeralPorts,
             IpProtocol,
         },
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            RouteEntry,
+            RoutingTable,
+        },
         queue::InetQueue,
         tcp::{
             established::ControlBlock,
             operations::{
                 AcceptFuture,
+                AckFuture,
                 CloseFuture,
                 ConnectFuture,
                 PopFuture,
+                PopMultipleFuture,
                 PushFuture,
             },
             segment::{
                 TcpHeader,
+                TcpOptions2,
                 TcpSegment,
             },
+            timestamps::{
+                timestamp_after,
+                timestamp_value,
+            },
             SeqNumber,
         },
     },
@@ -45,8 +68,16 @@ use crate::{
         fail::Fail,
         memory::DemiBuffer,
         network::{
+            check_link_up,
             config::TcpConfig,
-            types::MacAddress,
+            types::{
+                ByteStats,
+                ConnEvent,
+                HandshakeStats,
+                MacAddress,
+                PeerStats,
+                PushCompletion,
+            },
             NetworkRuntime,
         },
         queue::IoQueueTable,
@@ -56,19 +87,15 @@ use crate::{
     scheduler::scheduler::Scheduler,
 };
 use ::futures::channel::mpsc;
-use ::rand::{
-    prelude::SmallRng,
-    Rng,
-    SeedableRng,
-};
-
 use ::std::{
     cell::{
+        Cell,
         Ref,
         RefCell,
         RefMut,
     },
     collections::HashMap,
+    future::Future,
     net::{
         Ipv4Addr,
         SocketAddrV4,
@@ -88,12 +115,12 @@ use crate::timer;
 // Enumerations
 //==============================================================================
 
-pub enum Socket<const N: usize> {
+pub enum Socket {
     Inactive(Option<SocketAddrV4>),
-    Listening(PassiveSocket<N>),
-    Connecting(ActiveOpenSocket<N>),
-    Established(EstablishedSocket<N>),
-    Closing(EstablishedSocket<N>),
+    Listening(PassiveSocket),
+    Connecting(ActiveOpenSocket),
+    Established(EstablishedSocket),
+    Closing(EstablishedSocket),
 }
 
 #[derive(PartialEq, Eq, Hash)]
@@ -106,43 +133,60 @@ enum SocketId {
 // Structures
 //==============================================================================
 
-pub struct Inner<const N: usize> {
-    isn_generator: IsnGenerator,
-    ephemeral_ports: EphemeralPorts,
+pub struct Inner {
+    isn_generator: Rc<RefCell<IsnGenerator>>,
+    // Maximum segment sizes that black hole detection settled on for remote hosts.
+    pmtu_table: PmtuTable,
+    // Routes that outbound connections take to remote hosts off the local link.
+    routes: RoutingTable,
+    // Round-trip times, byte counters and failed connection attempts of remote hosts.
+    telemetry: PeerTelemetry,
+    // Counters on the handshakes that remote hosts initiate towards our listening sockets.
+    handshakes: HandshakeTelemetry,
+    // Connection events that the application has yet to drain.
+    events: ConnEventQueue,
+    ephemeral_ports: Rc<RefCell<EphemeralPorts>>,
     // queue descriptor -> per queue metadata
-    qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>>,
+    qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
     // Connection or socket identifier for mapping incoming packets to the Demikernel queue
     addresses: HashMap<SocketId, QDesc>,
-    rt: Rc<dyn NetworkRuntime<N>>,
+    rt: Rc<dyn NetworkRuntime>,
     scheduler: Scheduler,
     clock: TimerRc,
     local_link_addr: MacAddress,
     local_ipv4_addr: Ipv4Addr,
     tcp_config: TcpConfig,
-    arp: ArpPeer<N>,
-    rng: Rc<RefCell<SmallRng>>,
+    arp: ArpPeer,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+    // Number of bytes buffered in the receive queues of all connections.
+    total_rx_buffer_bytes: Rc<Cell<usize>>,
+    // Number of incoming segments dropped because they failed authentication.
+    unauthenticated_segments: Cell<u64>,
+    // Number of incoming segments dropped because they carried an illegal combination of flags.
+    malformed_segments: Cell<u64>,
+    // Number of connections in TIME-WAIT that were reused for new outgoing connections.
+    recycled_connections: Cell<u64>,
 }
 
-pub struct TcpPeer<const N: usize> {
-    pub(super) inner: Rc<RefCell<Inner<N>>>,
+pub struct TcpPeer {
+    pub(super) inner: Rc<RefCell<Inner>>,
 }
 
 //==============================================================================
 // Associated Functions
 //==============================================================================
 
-impl<const N: usize> TcpPeer<N> {
+impl TcpPeer {
     pub fn new(
-        rt: Rc<dyn NetworkRuntime<N>>,
+        rt: Rc<dyn NetworkRuntime>,
         scheduler: Scheduler,
-        qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>>,
+        qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
         clock: TimerRc,
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         tcp_config: TcpConfig,
-        arp: ArpPeer<N>,
-        rng_seed: [u8; 32],
+        arp: ArpPeer,
+        ephemeral_ports: Rc<RefCell<EphemeralPorts>>,
     ) -> Result<Self, Fail> {
         let (tx, rx) = mpsc::unbounded();
         let inner = Rc::new(RefCell::new(Inner::new(
@@ -154,7 +198,7 @@ impl<const N: usize> TcpPeer<N> {
             local_ipv4_addr,
             tcp_config,
             arp,
-            rng_seed,
+            ephemeral_ports,
             tx,
             rx,
         )));
@@ -165,14 +209,17 @@ impl<const N: usize> TcpPeer<N> {
     pub fn do_socket(&self) -> Result<QDesc, Fail> {
         #[cfg(feature = "profiler")]
         timer!("tcp::socket");
-        let inner: Ref<Inner<N>> = self.inner.borrow();
-        let mut qtable: RefMut<IoQueueTable<InetQueue<N>>> = inner.qtable.borrow_mut();
-        let new_qd: QDesc = qtable.alloc(InetQueue::Tcp(TcpQueue::new()));
+        let inner: Ref<Inner> = self.inner.borrow();
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = inner.qtable.borrow_mut();
+        let new_qd: QDesc = qtable.alloc(InetQueue::Tcp(TcpQueue::new()))?;
         Ok(new_qd)
     }
 
     pub fn bind(&self, qd: QDesc, mut addr: SocketAddrV4) -> Result<(), Fail> {
-        let mut inner: RefMut<Inner<N>> = self.inner.borrow_mut();
+        let mut inner: RefMut<Inner> = self.inner.borrow_mut();
+
+        // Release the addresses of connections that are gone.
+        inner.reap_closed_connections();
 
         // Check if address is already bound.
         for (socket_id, _) in &inner.addresses {
@@ -186,15 +233,15 @@ impl<const N: usize> TcpPeer<N> {
 
         // Check if this is an ephemeral port.
         if EphemeralPorts::is_private(addr.port()) {
-            // Allocate ephemeral port from the pool, to leave  ephemeral port allocator in a consistent state.
-            inner.ephemeral_ports.alloc_port(addr.port())?
+            // Reserve the port, so that the ephemeral port allocator does not hand it out.
+            inner.ephemeral_ports.borrow_mut().alloc_port(addr.port())?
         }
 
         // Check if we have to handle wildcard port binding.
         if addr.port() == 0 {
             // Allocate ephemeral port.
             // TODO: we should free this when closing.
-            let new_port: u16 = inner.ephemeral_ports.alloc_any()?;
+            let new_port: u16 = inner.ephemeral_ports.borrow_mut().alloc_any()?;
             addr.set_port(new_port);
         }
 
@@ -203,6 +250,7 @@ impl<const N: usize> TcpPeer<N> {
             Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
                 Socket::Inactive(None) => {
                     queue.set_socket(Socket::Inactive(Some(addr)));
+                    queue.set_ephemeral_port(Some(addr.port()).filter(|port| EphemeralPorts::is_private(*port)));
                     Ok(())
                 },
                 Socket::Inactive(_) => Err(Fail::new(libc::EINVAL, "socket is already bound to an address")),
@@ -223,7 +271,7 @@ impl<const N: usize> TcpPeer<N> {
             Err(e) => {
                 // Rollback ephemeral port allocation.
                 if EphemeralPorts::is_private(addr.port()) {
-                    inner.ephemeral_ports.free(addr.port());
+                    inner.ephemeral_ports.borrow_mut().free(addr.port());
                 }
                 Err(e)
             },
@@ -231,23 +279,67 @@ impl<const N: usize> TcpPeer<N> {
     }
 
     pub fn receive(&self, ip_header: &Ipv4Header, buf: DemiBuffer) -> Result<(), Fail> {
-        self.inner.borrow().receive(ip_header, buf)
+        self.inner.borrow_mut().receive(ip_header, buf)
+    }
+
+    /// Changes the local IPv4 and link addresses that new connections use. Sockets that are bound or listening on the
+    /// old IPv4 address are moved over to the new one. Established connections keep their original addresses.
+    pub fn set_local_identity(&self, local_ipv4_addr: Ipv4Addr, local_link_addr: MacAddress) {
+        let mut inner_: RefMut<Inner> = self.inner.borrow_mut();
+        let inner: &mut Inner = &mut inner_;
+        let old_ipv4_addr: Ipv4Addr = inner.local_ipv4_addr;
+        inner.local_ipv4_addr = local_ipv4_addr;
+        inner.local_link_addr = local_link_addr;
+        if old_ipv4_addr == local_ipv4_addr {
+            return;
+        }
+
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = inner.qtable.borrow_mut();
+        let rebound: Vec<SocketAddrV4> = inner
+            .addresses
+            .keys()
+            .filter_map(|socket_id| match socket_id {
+                SocketId::Passive(addr) if *addr.ip() == old_ipv4_addr => Some(*addr),
+                _ => None,
+            })
+            .collect();
+        for addr in rebound {
+            let new_addr: SocketAddrV4 = SocketAddrV4::new(local_ipv4_addr, addr.port());
+            if inner.addresses.contains_key(&SocketId::Passive(new_addr)) {
+                warn!(
+                    "Cannot move TCP socket from {:?}: {:?} is already bound",
+                    addr, new_addr
+                );
+                continue;
+            }
+            if let Some(qd) = inner.addresses.remove(&SocketId::Passive(addr)) {
+                if let Some(InetQueue::Tcp(queue)) = qtable.get_resolved_mut(&qd) {
+                    match queue.get_mut_socket() {
+                        Socket::Inactive(Some(_)) => queue.set_socket(Socket::Inactive(Some(new_addr))),
+                        Socket::Listening(socket) => socket.set_local_identity(new_addr, local_link_addr),
+                        _ => (),
+                    }
+                }
+                inner.addresses.insert(SocketId::Passive(new_addr), qd);
+            }
+        }
     }
 
     // Marks the target socket as passive.
     pub fn listen(&self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
         // This code borrows a reference to inner, instead of the entire self structure,
         // so we can still borrow self later.
-        let mut inner_: RefMut<Inner<N>> = self.inner.borrow_mut();
-        let inner: &mut Inner<N> = &mut *inner_;
-        let mut qtable: RefMut<IoQueueTable<InetQueue<N>>> = inner.qtable.borrow_mut();
+        let mut inner_: RefMut<Inner> = self.inner.borrow_mut();
+        let inner: &mut Inner = &mut *inner_;
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = inner.qtable.borrow_mut();
         // Get bound address while checking for several issues.
         match qtable.get_mut(&qd) {
             Some(InetQueue::Tcp(queue)) => match queue.get_mut_socket() {
                 Socket::Inactive(Some(local)) => {
+                    let local: SocketAddrV4 = *local;
                     // Check if there isn't a socket listening on this address/port pair.
-                    if inner.addresses.contains_key(&SocketId::Passive(*local)) {
-                        if *inner.addresses.get(&SocketId::Passive(*local)).unwrap() != qd {
+                    if inner.addresses.contains_key(&SocketId::Passive(local)) {
+                        if *inner.addresses.get(&SocketId::Passive(local)).unwrap() != qd {
                             return Err(Fail::new(
                                 libc::EADDRINUSE,
                                 "another socket is already listening on the same address/port pair",
@@ -255,19 +347,8 @@ impl<const N: usize> TcpPeer<N> {
                         }
                     }
 
-                    let nonce: u32 = inner.rng.borrow_mut().gen();
-                    let socket = PassiveSocket::new(
-                        *local,
-                        backlog,
-                        inner.rt.clone(),
-                        inner.scheduler.clone(),
-                        inner.clock.clone(),
-                        inner.tcp_config.clone(),
-                        inner.local_link_addr,
-                        inner.arp.clone(),
-                        nonce,
-                    );
-                    inner.addresses.insert(SocketId::Passive(local.clone()), qd);
+                    let socket = PassiveSocket::new(local, backlog, inner.context(), qd, queue.get_authenticator());
+                    inner.addresses.insert(SocketId::Passive(local), qd);
                     queue.set_socket(Socket::Listening(socket));
                     Ok(())
                 },
@@ -283,13 +364,47 @@ impl<const N: usize> TcpPeer<N> {
         }
     }
 
+    /// Stops accepting connections on a listening socket. The socket remains open until it is closed.
+    pub fn stop_listening(&self, qd: QDesc) -> Result<(), Fail> {
+        let inner: Ref<Inner> = self.inner.borrow();
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = inner.qtable.borrow_mut();
+        match qtable.get_mut(&qd) {
+            Some(InetQueue::Tcp(queue)) => match queue.get_mut_socket() {
+                Socket::Listening(socket) if !socket.is_stopped() => {
+                    socket.stop_listening();
+                    Ok(())
+                },
+                _ => {
+                    let cause: String = format!("socket is not listening (qd={:?})", qd);
+                    error!("stop_listening(): {}", cause);
+                    Err(Fail::new(libc::EINVAL, &cause))
+                },
+            },
+            _ => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
     /// Accepts an incoming connection.
-    pub fn do_accept(&self, qd: QDesc) -> (QDesc, AcceptFuture<N>) {
-        let mut inner_: RefMut<Inner<N>> = self.inner.borrow_mut();
-        let inner: &mut Inner<N> = &mut *inner_;
+    pub fn do_accept(&self, qd: QDesc) -> Result<(QDesc, AcceptFuture), Fail> {
+        let mut inner_: RefMut<Inner> = self.inner.borrow_mut();
+        let inner: &mut Inner = &mut *inner_;
 
-        let new_qd: QDesc = inner.qtable.borrow_mut().alloc(InetQueue::Tcp(TcpQueue::new()));
-        (new_qd, AcceptFuture::new(qd, new_qd, self.inner.clone()))
+        // Accepted connections are authenticated like the listening socket.
+        let mut new_queue: TcpQueue = TcpQueue::new();
+        if let Some(InetQueue::Tcp(queue)) = inner.qtable.borrow().get(&qd) {
+            // Do not accept connections on a listening socket that was shut down.
+            if let Socket::Listening(socket) = queue.get_socket() {
+                if socket.is_stopped() {
+                    let cause: String = format!("listening socket was shut down (qd={:?})", qd);
+                    error!("do_accept(): {}", cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                }
+            }
+            new_queue.set_authenticator(queue.get_authenticator());
+        }
+
+        let new_qd: QDesc = inner.qtable.borrow_mut().alloc(InetQueue::Tcp(new_queue))?;
+        Ok((new_qd, AcceptFuture::new(qd, new_qd, self.inner.clone())))
     }
 
     /// Handles an incoming connection.
@@ -299,26 +414,28 @@ impl<const N: usize> TcpPeer<N> {
         new_qd: QDesc,
         ctx: &mut Context,
     ) -> Poll<Result<(QDesc, SocketAddrV4), Fail>> {
-        let mut inner: RefMut<Inner<N>> = self.inner.borrow_mut();
+        let mut inner: RefMut<Inner> = self.inner.borrow_mut();
 
-        let cb: ControlBlock<N> = match inner.qtable.borrow_mut().get_mut(&qd) {
+        let result: Result<ControlBlock, Fail> = match inner.qtable.borrow_mut().get_resolved_mut(&qd) {
             Some(InetQueue::Tcp(queue)) => match queue.get_mut_socket() {
                 Socket::Listening(socket) => match socket.poll_accept(ctx) {
                     Poll::Pending => return Poll::Pending,
-                    Poll::Ready(result) => match result {
-                        Ok(cb) => cb,
-                        Err(err) => {
-                            inner.qtable.borrow_mut().free(&new_qd);
-                            return Poll::Ready(Err(err));
-                        },
-                    },
+                    Poll::Ready(result) => result,
                 },
                 _ => return Poll::Ready(Err(Fail::new(libc::EOPNOTSUPP, "socket not listening"))),
             },
             _ => return Poll::Ready(Err(Fail::new(libc::EBADF, "invalid queue descriptor"))),
         };
+        let cb: ControlBlock = match result {
+            Ok(cb) => cb,
+            Err(err) => {
+                inner.qtable.borrow_mut().free(&new_qd);
+                return Poll::Ready(Err(err));
+            },
+        };
 
-        let established: EstablishedSocket<N> = EstablishedSocket::new(cb, new_qd, inner.dead_socket_tx.clone());
+        let established: EstablishedSocket =
+            EstablishedSocket::new(cb, new_qd, inner.dead_socket_tx.clone(), inner.events.clone());
         let local: SocketAddrV4 = established.cb.get_local();
         let remote: SocketAddrV4 = established.cb.get_remote();
         match inner.qtable.borrow_mut().get_mut(&new_qd) {
@@ -336,36 +453,62 @@ impl<const N: usize> TcpPeer<N> {
         Poll::Ready(Ok((new_qd, remote)))
     }
 
-    pub fn connect(&self, qd: QDesc, remote: SocketAddrV4) -> Result<ConnectFuture<N>, Fail> {
-        let mut inner_: RefMut<Inner<N>> = self.inner.borrow_mut();
-        let inner: &mut Inner<N> = &mut *inner_;
-        let mut qtable: RefMut<IoQueueTable<InetQueue<N>>> = inner.qtable.borrow_mut();
+    pub fn connect(&self, qd: QDesc, remote: SocketAddrV4) -> Result<ConnectFuture, Fail> {
+        let mut inner_: RefMut<Inner> = self.inner.borrow_mut();
+        let inner: &mut Inner = &mut *inner_;
+
+        // Release the addresses of connections that are gone.
+        inner.reap_closed_connections();
+
+        // Unbound sockets get a port in the ephemeral port range. Once these run out, we may take over the port of a
+        // connection to the same remote that is in TIME-WAIT, along with a floor for our initial sequence number.
+        let unbound: bool = match inner.qtable.borrow().get(&qd) {
+            Some(InetQueue::Tcp(queue)) => matches!(queue.get_socket(), Socket::Inactive(None)),
+            _ => false,
+        };
+        let ephemeral: Option<(u16, Option<SeqNumber>)> = if unbound {
+            let result: Result<u16, Fail> = inner.ephemeral_ports.borrow_mut().alloc_any();
+            match result {
+                Ok(local_port) => Some((local_port, None)),
+                Err(e) => match inner.recycle_time_wait(&remote) {
+                    Some((local_port, min_isn)) => Some((local_port, Some(min_isn))),
+                    None => return Err(e),
+                },
+            }
+        } else {
+            None
+        };
+
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = inner.qtable.borrow_mut();
 
         // Get local address bound to socket.
         match qtable.get_mut(&qd) {
             Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
                 Socket::Inactive(local_socket) => {
-                    let local: SocketAddrV4 = match local_socket {
-                        Some(local) => local.clone(),
-                        None => {
-                            // TODO: we should free this when closing.
-                            let local_port: u16 = inner.ephemeral_ports.alloc_any()?;
-                            SocketAddrV4::new(inner.local_ipv4_addr, local_port)
+                    let (local, min_isn): (SocketAddrV4, Option<SeqNumber>) = match (local_socket, ephemeral) {
+                        (Some(local), _) => (local.clone(), None),
+                        (None, Some((local_port, min_isn))) => {
+                            queue.set_ephemeral_port(Some(local_port));
+                            (SocketAddrV4::new(inner.local_ipv4_addr, local_port), min_isn)
                         },
+                        (None, None) => unreachable!("unbound socket should have an ephemeral port"),
                     };
 
-                    // Create active socket.
-                    let local_isn: SeqNumber = inner.isn_generator.generate(&local, &remote);
-                    let socket: ActiveOpenSocket<N> = ActiveOpenSocket::new(
-                        inner.scheduler.clone(),
+                    // Create active socket. A reincarnation of a connection in TIME-WAIT starts its sequence numbers
+                    // past the ones of the previous incarnation.
+                    let local_isn: SeqNumber = match min_isn {
+                        Some(min_isn) => min_isn,
+                        None => inner.isn_generator.borrow_mut().generate(&local, &remote, &inner.clock),
+                    };
+                    // Pick the next hop once, so that all segments of the connection take the same path.
+                    let next_hop: Ipv4Addr = inner.routes.next_hop(IpProtocol::TCP, local, remote);
+                    let socket: ActiveOpenSocket = ActiveOpenSocket::new(
+                        inner.context(),
                         local_isn,
                         local,
                         remote,
-                        inner.rt.clone(),
-                        inner.tcp_config.clone(),
-                        inner.local_link_addr,
-                        inner.clock.clone(),
-                        inner.arp.clone(),
+                        next_hop,
+                        queue.get_authenticator(),
                     );
 
                     // Update socket state.
@@ -385,11 +528,66 @@ impl<const N: usize> TcpPeer<N> {
         })
     }
 
+    /// Binds the socket referred to by `qd` to `local`, unless it is already bound, and connects it to `remote`. A
+    /// wildcard port in `local` selects an ephemeral port. If the socket is already bound, `local` must match the
+    /// address that it is bound to.
+    pub fn connect_from(&self, qd: QDesc, local: SocketAddrV4, remote: SocketAddrV4) -> Result<ConnectFuture, Fail> {
+        let (bound, local_ipv4_addr): (Option<SocketAddrV4>, Ipv4Addr) = {
+            let inner: Ref<Inner> = self.inner.borrow();
+            let bound: Option<SocketAddrV4> = match inner.qtable.borrow().get(&qd) {
+                Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
+                    Socket::Inactive(addr) => *addr,
+                    Socket::Listening(_) => return Err(Fail::new(libc::EOPNOTSUPP, "socket is listening")),
+                    Socket::Connecting(_) => return Err(Fail::new(libc::EALREADY, "socket is connecting")),
+                    Socket::Established(_) => return Err(Fail::new(libc::EISCONN, "socket is connected")),
+                    Socket::Closing(_) => return Err(Fail::new(libc::EINVAL, "socket is closed")),
+                },
+                _ => return Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+            };
+            (bound, inner.local_ipv4_addr)
+        };
+
+        // Check if the local address is one of ours.
+        if !local.ip().is_unspecified() && *local.ip() != local_ipv4_addr {
+            return Err(Fail::new(libc::EADDRNOTAVAIL, "cannot assign requested address"));
+        }
+
+        match bound {
+            // Check if the socket is bound to the requested address.
+            Some(addr) => {
+                if local.port() != 0 && local.port() != addr.port() {
+                    return Err(Fail::new(libc::EINVAL, "socket is bound to a different address"));
+                }
+            },
+            None => self.bind(qd, SocketAddrV4::new(local_ipv4_addr, local.port()))?,
+        }
+
+        self.connect(qd, remote)
+    }
+
     pub fn poll_recv(&self, qd: QDesc, ctx: &mut Context, size: Option<usize>) -> Poll<Result<DemiBuffer, Fail>> {
-        let inner: Ref<Inner<N>> = self.inner.borrow();
-        let mut qtable: RefMut<IoQueueTable<InetQueue<N>>> = inner.qtable.borrow_mut();
-        match qtable.get_mut(&qd) {
+        self.do_poll_recv(qd, ctx, size, false)
+    }
+
+    /// Polls for received data like [TcpPeer::poll_recv], but leaves the data in the receive queue, so that the next
+    /// receive returns the same bytes.
+    pub fn poll_peek(&self, qd: QDesc, ctx: &mut Context, size: Option<usize>) -> Poll<Result<DemiBuffer, Fail>> {
+        self.do_poll_recv(qd, ctx, size, true)
+    }
+
+    fn do_poll_recv(
+        &self,
+        qd: QDesc,
+        ctx: &mut Context,
+        size: Option<usize>,
+        peek: bool,
+    ) -> Poll<Result<DemiBuffer, Fail>> {
+        let mut inner: RefMut<Inner> = self.inner.borrow_mut();
+        let result: Poll<Result<DemiBuffer, Fail>> = match inner.qtable.borrow_mut().get_resolved_mut(&qd) {
             Some(InetQueue::Tcp(ref mut queue)) => match queue.get_mut_socket() {
+                Socket::Established(ref mut socket) | Socket::Closing(ref mut socket) if peek => {
+                    socket.poll_peek(ctx, size)
+                },
                 Socket::Established(ref mut socket) => socket.poll_recv(ctx, size),
                 Socket::Closing(ref mut socket) => socket.poll_recv(ctx, size),
                 Socket::Connecting(_) => Poll::Ready(Err(Fail::new(libc::EINPROGRESS, "socket connecting"))),
@@ -397,33 +595,240 @@ impl<const N: usize> TcpPeer<N> {
                 Socket::Listening(_) => Poll::Ready(Err(Fail::new(libc::ENOTCONN, "socket listening"))),
             },
             _ => Poll::Ready(Err(Fail::new(libc::EBADF, "bad queue descriptor"))),
+        };
+
+        // The connection was reset because its receive buffer was exhausted or its user timeout expired, so release
+        // the queue descriptor.
+        if let Poll::Ready(Err(ref e)) = result {
+            if e.errno == libc::ENOBUFS || e.errno == libc::ETIMEDOUT {
+                let queue: Option<InetQueue> = inner.qtable.borrow_mut().free(&qd);
+                if let Some(InetQueue::Tcp(queue)) = queue {
+                    if let Socket::Established(socket) | Socket::Closing(socket) = queue.get_socket() {
+                        let (local, remote): (SocketAddrV4, SocketAddrV4) = socket.endpoints();
+                        inner.addresses.remove(&SocketId::Active(local, remote));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Polls for up to `max_msgs` chunks of received data at once. This completes as soon as at least one chunk is
+    /// available, with all chunks that are available at that time. Chunks are not coalesced.
+    pub fn poll_recv_multiple(
+        &self,
+        qd: QDesc,
+        ctx: &mut Context,
+        max_msgs: usize,
+    ) -> Poll<Result<Vec<DemiBuffer>, Fail>> {
+        let mut bufs: Vec<DemiBuffer> = Vec::new();
+        while bufs.len() < max_msgs {
+            match self.poll_recv(qd, ctx, None) {
+                Poll::Ready(Ok(buf)) => bufs.push(buf),
+                // Report failures only if there is no data to hand out, so that they surface on the next pop.
+                Poll::Ready(Err(e)) if bufs.is_empty() => return Poll::Ready(Err(e)),
+                Poll::Ready(Err(_)) | Poll::Pending => break,
+            }
+        }
+
+        if bufs.is_empty() {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(bufs))
         }
     }
 
     /// TODO: Should probably check for valid queue descriptor before we schedule the future
     pub fn push(&self, qd: QDesc, buf: DemiBuffer) -> PushFuture {
-        let err: Option<Fail> = match self.send(qd, buf) {
-            Ok(()) => None,
-            Err(e) => Some(e),
-        };
-        PushFuture { qd, err }
+        match self.send(qd, buf) {
+            Ok(acked) => PushFuture { qd, err: None, acked },
+            Err(e) => PushFuture {
+                qd,
+                err: Some(e),
+                acked: None,
+            },
+        }
+    }
+
+    /// Pushes `buf` to the socket referred to by `qd`. Unlike [TcpPeer::push], the returned future completes only once
+    /// our peer acknowledges all of `buf`, and fails with ETIMEDOUT if that does not happen within `timeout`. In the
+    /// latter case, the connection is aborted.
+    pub fn push_timeout(
+        &self,
+        qd: QDesc,
+        buf: DemiBuffer,
+        timeout: Duration,
+    ) -> Result<impl Future<Output = Result<(), Fail>>, Fail> {
+        let inner = self.inner.borrow();
+        check_link_up(inner.rt.as_ref())?;
+        let qtable = inner.qtable.borrow();
+        match qtable.get(&qd) {
+            Some(InetQueue::Tcp(ref queue)) => match queue.get_socket() {
+                Socket::Established(ref socket) => socket.send_with_deadline(buf, timeout),
+                _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
     }
 
     /// TODO: Should probably check for valid queue descriptor before we schedule the future
-    pub fn pop(&self, qd: QDesc, size: Option<usize>) -> PopFuture<N> {
+    pub fn pop(&self, qd: QDesc, size: Option<usize>) -> PopFuture {
+        PopFuture {
+            qd,
+            size,
+            peek: false,
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Same as [TcpPeer::pop], but the returned future leaves the data in the receive queue, as `MSG_PEEK` does.
+    pub fn pop_peek(&self, qd: QDesc, size: Option<usize>) -> PopFuture {
         PopFuture {
             qd,
             size,
+            peek: true,
             inner: self.inner.clone(),
         }
     }
 
-    fn send(&self, qd: QDesc, buf: DemiBuffer) -> Result<(), Fail> {
+    /// TODO: Should probably check for valid queue descriptor before we schedule the future
+    pub fn pop_multiple(&self, qd: QDesc, max_msgs: usize) -> PopMultipleFuture {
+        PopMultipleFuture {
+            qd,
+            max_msgs,
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Sends `buf` on the socket referred to by `qd`. If the socket completes pushes on acknowledgement, this returns a
+    /// future that completes once our peer acknowledges all of `buf`.
+    fn send(&self, qd: QDesc, buf: DemiBuffer) -> Result<Option<AckFuture>, Fail> {
+        let inner = self.inner.borrow();
+        check_link_up(inner.rt.as_ref())?;
+        let qtable = inner.qtable.borrow();
+        match qtable.get(&qd) {
+            Some(InetQueue::Tcp(ref queue)) => match (queue.get_socket(), queue.get_push_completion()) {
+                (Socket::Established(ref socket), PushCompletion::Enqueued) => {
+                    socket.send(buf)?;
+                    Ok(None)
+                },
+                (Socket::Established(ref socket), PushCompletion::Acked) => Ok(Some(Box::pin(socket.send_acked(buf)?))),
+                _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Sets when pushes on the socket referred to by `qd` complete.
+    pub fn set_push_completion(&self, qd: QDesc, push_completion: PushCompletion) -> Result<(), Fail> {
+        let inner = self.inner.borrow();
+        let mut qtable = inner.qtable.borrow_mut();
+        match qtable.get_mut(&qd) {
+            Some(InetQueue::Tcp(ref mut queue)) => {
+                queue.set_push_completion(push_completion);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Sets the authenticator that signs and validates the segments of the socket referred to by `qd`. This must be
+    /// done before the socket connects or listens. Connections accepted on a listening socket inherit its
+    /// authenticator.
+    pub fn set_authenticator(
+        &self,
+        qd: QDesc,
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+    ) -> Result<(), Fail> {
+        let inner = self.inner.borrow();
+        let mut qtable = inner.qtable.borrow_mut();
+        match qtable.get_mut(&qd) {
+            Some(InetQueue::Tcp(ref mut queue)) => match queue.get_socket() {
+                Socket::Inactive(_) => {
+                    queue.set_authenticator(authenticator);
+                    Ok(())
+                },
+                _ => {
+                    let cause: String = format!("socket is already in use (qd={:?})", qd);
+                    error!("set_authenticator(): {}", cause);
+                    Err(Fail::new(libc::EISCONN, &cause))
+                },
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the number of incoming segments that were dropped because they failed authentication.
+    pub fn unauthenticated_segments(&self) -> u64 {
+        self.inner.borrow().unauthenticated_segments.get()
+    }
+
+    /// Returns the number of incoming segments that were dropped because they carried an illegal combination of flags.
+    pub fn malformed_segments(&self) -> u64 {
+        self.inner.borrow().malformed_segments.get()
+    }
+
+    /// Returns the number of connections in TIME-WAIT that were reused for new outgoing connections.
+    pub fn recycled_connections(&self) -> u64 {
+        self.inner.borrow().recycled_connections.get()
+    }
+
+    /// Returns the telemetry on the remote hosts that we connected to, aggregated across all connections to each of them.
+    /// Remote hosts that we have no connection to are forgotten once their telemetry was not updated for the retention
+    /// time in the TCP configuration.
+    pub fn peer_telemetry(&self) -> Vec<(Ipv4Addr, PeerStats)> {
+        let inner = self.inner.borrow();
+        let live: Vec<(Ipv4Addr, ByteStats)> = inner
+            .qtable
+            .borrow()
+            .get_values()
+            .filter_map(|(_, queue)| match queue {
+                InetQueue::Tcp(queue) => match queue.get_socket() {
+                    Socket::Established(socket) | Socket::Closing(socket) => {
+                        Some((*socket.endpoints().1.ip(), socket.byte_stats()))
+                    },
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        inner
+            .telemetry
+            .snapshot(live, inner.clock.now(), inner.tcp_config.get_peer_telemetry_retention())
+    }
+
+    /// Returns the counters on the handshakes that remote hosts initiated towards our listening sockets.
+    pub fn handshake_stats(&self) -> HandshakeStats {
+        self.inner.borrow().handshakes.snapshot()
+    }
+
+    /// Resets the counters on handshakes.
+    pub fn reset_handshake_stats(&self) {
+        self.inner.borrow().handshakes.reset();
+    }
+
+    /// Adds a route to the network `net`/`prefix` through `gateways`, replacing the route to that network if there is
+    /// one already. Connections that we open afterwards spread across the gateways in proportion to their weights.
+    pub fn add_ecmp_route(&self, net: Ipv4Addr, prefix: u8, gateways: Vec<RouteEntry>) -> Result<(), Fail> {
+        self.inner.borrow().routes.add_ecmp_route(net, prefix, gateways)
+    }
+
+    /// Takes the oldest connection event that the application has yet to drain, if any.
+    pub fn next_event(&self) -> Option<ConnEvent> {
+        self.inner.borrow().events.pop()
+    }
+
+    /// Forces transmission of any data that is buffered on a TCP socket.
+    pub fn flush(&self, qd: QDesc) -> Result<(), Fail> {
         let inner = self.inner.borrow();
         let qtable = inner.qtable.borrow();
         match qtable.get(&qd) {
             Some(InetQueue::Tcp(ref queue)) => match queue.get_socket() {
-                Socket::Established(ref socket) => socket.send(buf),
+                Socket::Established(ref socket) => {
+                    socket.flush();
+                    Ok(())
+                },
                 _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
             },
             _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
@@ -432,7 +837,7 @@ impl<const N: usize> TcpPeer<N> {
 
     /// Closes a TCP socket.
     pub fn do_close(&self, qd: QDesc) -> Result<(), Fail> {
-        let mut inner: RefMut<Inner<N>> = self.inner.borrow_mut();
+        let mut inner: RefMut<Inner> = self.inner.borrow_mut();
         // TODO: Currently we do not handle close correctly because we continue to receive packets at this point to finish the TCP close protocol.
         // 1. We do not remove the endpoint from the addresses table
         // 2. We do not remove the queue from the queue table.
@@ -453,6 +858,8 @@ impl<const N: usize> TcpPeer<N> {
                     },
                     // Closing a bound socket.
                     Socket::Inactive(Some(addr)) => (addr.clone(), Ok(())),
+                    // Closing a listening socket that was shut down.
+                    Socket::Listening(socket) if socket.is_stopped() => (socket.endpoint(), Ok(())),
                     // Closing a listening socket.
                     Socket::Listening(socket) => {
                         let cause: String = format!("cannot close a listening socket (qd={:?})", qd);
@@ -481,7 +888,7 @@ impl<const N: usize> TcpPeer<N> {
     }
 
     /// Closes a TCP socket.
-    pub fn do_async_close(&self, qd: QDesc) -> Result<CloseFuture<N>, Fail> {
+    pub fn do_async_close(&self, qd: QDesc) -> Result<CloseFuture, Fail> {
         match self.inner.borrow().qtable.borrow_mut().get_mut(&qd) {
             Some(InetQueue::Tcp(queue)) => {
                 match queue.get_socket() {
@@ -526,7 +933,7 @@ impl<const N: usize> TcpPeer<N> {
 
     pub fn remote_mss(&self, qd: QDesc) -> Result<usize, Fail> {
         let inner = self.inner.borrow();
-        let qtable: Ref<IoQueueTable<InetQueue<N>>> = inner.qtable.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
         match qtable.get(&qd) {
             Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
                 Socket::Established(socket) => Ok(socket.remote_mss()),
@@ -536,9 +943,38 @@ impl<const N: usize> TcpPeer<N> {
         }
     }
 
+    /// Returns the maximum number of bytes that are sent in a single segment on a TCP socket. Pushes larger than this
+    /// are accepted and split into multiple segments.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        let inner = self.inner.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
+        match qtable.get(&qd) {
+            Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
+                Socket::Established(socket) => Ok(socket.remote_mss()),
+                _ => Ok(inner.tcp_config.get_advertised_mss()),
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the number of bytes that may be popped right away from the TCP socket referred to by `qd`. For a
+    /// listening socket, this is the number of connections that may be accepted right away instead.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        let inner = self.inner.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
+        match qtable.get(&qd) {
+            Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
+                Socket::Established(socket) | Socket::Closing(socket) => Ok(socket.bytes_available()),
+                Socket::Listening(socket) => Ok(socket.accept_queue_len()),
+                Socket::Inactive(_) | Socket::Connecting(_) => Ok(0),
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
     pub fn current_rto(&self, qd: QDesc) -> Result<Duration, Fail> {
         let inner = self.inner.borrow();
-        let qtable: Ref<IoQueueTable<InetQueue<N>>> = inner.qtable.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
         match qtable.get(&qd) {
             Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
                 Socket::Established(socket) => Ok(socket.current_rto()),
@@ -548,9 +984,65 @@ impl<const N: usize> TcpPeer<N> {
         }
     }
 
+    /// Returns statistics about the TCP connection referred to by `qd`.
+    pub fn stats(&self, qd: QDesc) -> Result<TcpConnectionStats, Fail> {
+        let inner = self.inner.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
+        match qtable.get(&qd) {
+            Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
+                Socket::Established(socket) => Ok(socket.stats()),
+                _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the internals of the TCP connection referred to by `qd`.
+    pub fn info(&self, qd: QDesc) -> Result<TcpInfo, Fail> {
+        let inner = self.inner.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
+        match qtable.get(&qd) {
+            Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
+                Socket::Established(socket) => Ok(socket.info()),
+                _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the number of bytes that the TCP connection referred to by `qd` sent, received and retransmitted so far.
+    /// The counters remain available while the connection is closing.
+    pub fn byte_stats(&self, qd: QDesc) -> Result<ByteStats, Fail> {
+        let inner = self.inner.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
+        match qtable.get(&qd) {
+            Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
+                Socket::Established(socket) | Socket::Closing(socket) => Ok(socket.byte_stats()),
+                _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Returns the local endpoint of the socket referred to by `qd`. Sockets that are not bound yet report the
+    /// unspecified address and port zero.
+    pub fn local_endpoint(&self, qd: QDesc) -> Result<SocketAddrV4, Fail> {
+        let inner = self.inner.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
+        match qtable.get(&qd) {
+            Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
+                Socket::Inactive(addr) => Ok(addr.unwrap_or(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))),
+                Socket::Listening(socket) => Ok(socket.endpoint()),
+                Socket::Connecting(socket) => Ok(socket.endpoints().0),
+                Socket::Established(socket) | Socket::Closing(socket) => Ok(socket.endpoints().0),
+            },
+            _ => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
     pub fn endpoints(&self, qd: QDesc) -> Result<(SocketAddrV4, SocketAddrV4), Fail> {
         let inner = self.inner.borrow();
-        let qtable: Ref<IoQueueTable<InetQueue<N>>> = inner.qtable.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = inner.qtable.borrow();
         match qtable.get(&qd) {
             Some(InetQueue::Tcp(queue)) => match queue.get_socket() {
                 Socket::Established(socket) => Ok(socket.endpoints()),
@@ -561,25 +1053,45 @@ impl<const N: usize> TcpPeer<N> {
     }
 }
 
-impl<const N: usize> Inner<N> {
+impl Inner {
+    /// Returns the resources that sockets and connections of this peer share.
+    fn context(&self) -> TcpContext {
+        TcpContext {
+            rt: self.rt.clone(),
+            scheduler: self.scheduler.clone(),
+            clock: self.clock.clone(),
+            local_link_addr: self.local_link_addr,
+            tcp_config: self.tcp_config.clone(),
+            arp: self.arp.clone(),
+            total_rx_buffer_bytes: self.total_rx_buffer_bytes.clone(),
+            isn_generator: self.isn_generator.clone(),
+            pmtu_table: self.pmtu_table.clone(),
+            telemetry: self.telemetry.clone(),
+            handshakes: self.handshakes.clone(),
+            events: self.events.clone(),
+        }
+    }
+
     fn new(
-        rt: Rc<dyn NetworkRuntime<N>>,
+        rt: Rc<dyn NetworkRuntime>,
         scheduler: Scheduler,
-        qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>>,
+        qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
         clock: TimerRc,
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         tcp_config: TcpConfig,
-        arp: ArpPeer<N>,
-        rng_seed: [u8; 32],
+        arp: ArpPeer,
+        ephemeral_ports: Rc<RefCell<EphemeralPorts>>,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
         _dead_socket_rx: mpsc::UnboundedReceiver<QDesc>,
     ) -> Self {
-        let mut rng: SmallRng = SmallRng::from_seed(rng_seed);
-        let ephemeral_ports: EphemeralPorts = EphemeralPorts::new(&mut rng);
-        let nonce: u32 = rng.gen();
         Self {
-            isn_generator: IsnGenerator::new(nonce),
+            isn_generator: Rc::new(RefCell::new(IsnGenerator::new())),
+            pmtu_table: PmtuTable::default(),
+            routes: RoutingTable::default(),
+            telemetry: PeerTelemetry::default(),
+            handshakes: HandshakeTelemetry::default(),
+            events: ConnEventQueue::new(tcp_config.get_conn_event_queue_capacity()),
             ephemeral_ports,
             rt: rt,
             scheduler,
@@ -590,12 +1102,16 @@ impl<const N: usize> Inner<N> {
             local_ipv4_addr: local_ipv4_addr,
             tcp_config: tcp_config,
             arp: arp,
-            rng: Rc::new(RefCell::new(rng)),
             dead_socket_tx: dead_socket_tx,
+            total_rx_buffer_bytes: Rc::new(Cell::new(0)),
+            unauthenticated_segments: Cell::new(0),
+            malformed_segments: Cell::new(0),
+            recycled_connections: Cell::new(0),
         }
     }
 
-    fn receive(&self, ip_hdr: &Ipv4Header, buf: DemiBuffer) -> Result<(), Fail> {
+    fn receive(&mut self, ip_hdr: &Ipv4Header, buf: DemiBuffer) -> Result<(), Fail> {
+        let segment_size: usize = buf.len();
         let (mut tcp_hdr, data) = TcpHeader::parse(ip_hdr, buf, self.tcp_config.get_rx_checksum_offload())?;
         debug!("TCP received {:?}", tcp_hdr);
         let local = SocketAddrV4::new(ip_hdr.get_dest_addr(), tcp_hdr.dst_port);
@@ -605,6 +1121,20 @@ impl<const N: usize> Inner<N> {
             return Err(Fail::new(libc::EINVAL, "invalid address type"));
         }
 
+        // Drop segments that carry no control flags at all, or flags that contradict each other.
+        if let Some(reason) = Self::check_flags(&tcp_hdr) {
+            return Err(self.drop_malformed(&local, &remote, reason));
+        }
+
+        // Segments for a connection that is gone go to the listening socket, if any.
+        if let Some(&qd) = self.addresses.get(&SocketId::Active(local, remote)) {
+            if self.is_closed(qd) {
+                if let Some(port) = self.release_connection(qd) {
+                    self.ephemeral_ports.borrow_mut().free(port);
+                }
+            }
+        }
+
         // grab the queue descriptor based on the incoming.
         let &qd: &QDesc = match self.addresses.get(&SocketId::Active(local, remote)) {
             Some(qdesc) => qdesc,
@@ -615,28 +1145,67 @@ impl<const N: usize> Inner<N> {
         };
         // look up the queue metadata based on queue descriptor.
         let mut qtable = self.qtable.borrow_mut();
-        match qtable.get_mut(&qd) {
-            Some(InetQueue::Tcp(queue)) => match queue.get_mut_socket() {
-                Socket::Established(socket) => {
-                    debug!("Routing to established connection: {:?}", socket.endpoints());
-                    socket.receive(&mut tcp_hdr, data);
-                    return Ok(());
-                },
-                Socket::Connecting(socket) => {
-                    debug!("Routing to connecting connection: {:?}", socket.endpoints());
-                    socket.receive(&tcp_hdr);
-                    return Ok(());
-                },
-                Socket::Listening(socket) => {
-                    debug!("Routing to passive connection: {:?}", local);
-                    return socket.receive(ip_hdr, &tcp_hdr);
-                },
-                Socket::Inactive(_) => (),
-                Socket::Closing(socket) => {
-                    debug!("Routing to closing connection: {:?}", socket.endpoints());
-                    socket.receive(&mut tcp_hdr, data);
-                    return Ok(());
-                },
+        match qtable.get_resolved_mut(&qd) {
+            Some(InetQueue::Tcp(queue)) => {
+                // Drop segments that are not signed as expected by the socket.
+                let header_size: usize = segment_size - data.len();
+                if !Self::authenticate(queue.get_authenticator(), ip_hdr, &tcp_hdr, header_size, &data) {
+                    self.unauthenticated_segments
+                        .set(self.unauthenticated_segments.get() + 1);
+                    let cause: String = format!(
+                        "TCP segment failed authentication (local={:?}, remote={:?})",
+                        local, remote
+                    );
+                    warn!("receive(): {}", cause);
+                    return Err(Fail::new(libc::EBADMSG, &cause));
+                }
+                match queue.get_mut_socket() {
+                    Socket::Established(socket) => {
+                        debug!("Routing to established connection: {:?}", socket.endpoints());
+                        socket.receive(ip_hdr, &mut tcp_hdr, data);
+                        return Ok(());
+                    },
+                    Socket::Connecting(socket) => {
+                        debug!("Routing to connecting connection: {:?}", socket.endpoints());
+                        socket.receive(&tcp_hdr);
+                        return Ok(());
+                    },
+                    Socket::Listening(socket) => {
+                        // Refuse new connections if the listening socket was shut down.
+                        if socket.is_stopped() {
+                            if !tcp_hdr.rst {
+                                debug!("Refusing connection from {:?}: listening socket was shut down", remote);
+                                self.send_rst(&local, &remote)?;
+                            }
+                            return Ok(());
+                        }
+                        // There is no connection to close, unless this acknowledges a pending one.
+                        if tcp_hdr.fin && !tcp_hdr.ack {
+                            return Err(self.drop_malformed(&local, &remote, "FIN on a listening socket"));
+                        }
+                        // Refuse new connections if receive queues are already holding too much data.
+                        let total_rx_buffer_bytes: usize = self.total_rx_buffer_bytes.get();
+                        if tcp_hdr.syn
+                            && !tcp_hdr.ack
+                            && total_rx_buffer_bytes > self.tcp_config.get_total_rx_buffer_cap()
+                        {
+                            warn!(
+                                "Refusing connection from {:?}: receive buffers exhausted ({:?} bytes)",
+                                remote, total_rx_buffer_bytes
+                            );
+                            self.send_rst(&local, &remote)?;
+                            return Ok(());
+                        }
+                        debug!("Routing to passive connection: {:?}", local);
+                        return socket.receive(ip_hdr, &tcp_hdr);
+                    },
+                    Socket::Inactive(_) => (),
+                    Socket::Closing(socket) => {
+                        debug!("Routing to closing connection: {:?}", socket.endpoints());
+                        socket.receive(ip_hdr, &mut tcp_hdr, data);
+                        return Ok(());
+                    },
+                }
             },
             _ => panic!("No queue descriptor"),
         };
@@ -647,6 +1216,134 @@ impl<const N: usize> Inner<N> {
         Ok(())
     }
 
+    /// Checks the flags of a received segment. Returns why the segment is malformed, if it is.
+    fn check_flags(tcp_hdr: &TcpHeader) -> Option<&'static str> {
+        if !(tcp_hdr.urg || tcp_hdr.ack || tcp_hdr.psh || tcp_hdr.rst || tcp_hdr.syn || tcp_hdr.fin) {
+            return Some("no flags set");
+        }
+        if tcp_hdr.syn && tcp_hdr.fin {
+            return Some("SYN and FIN set");
+        }
+        if tcp_hdr.syn && tcp_hdr.rst {
+            return Some("SYN and RST set");
+        }
+        None
+    }
+
+    /// Accounts for a segment that is dropped because it is malformed.
+    fn drop_malformed(&self, local: &SocketAddrV4, remote: &SocketAddrV4, reason: &str) -> Fail {
+        self.malformed_segments.set(self.malformed_segments.get() + 1);
+        let cause: String = format!(
+            "malformed TCP segment: {} (local={:?}, remote={:?})",
+            reason, local, remote
+        );
+        warn!("receive(): {}", cause);
+        Fail::new(libc::EBADMSG, &cause)
+    }
+
+    /// Checks a received segment against the authenticator of the socket that it is for. Segments for sockets without
+    /// an authenticator must not be signed.
+    fn authenticate(
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+        ip_hdr: &Ipv4Header,
+        tcp_hdr: &TcpHeader,
+        header_size: usize,
+        data: &[u8],
+    ) -> bool {
+        match authenticator {
+            Some(authenticator) => authenticator.verify(ip_hdr, tcp_hdr, header_size, data),
+            None => !tcp_hdr
+                .iter_options()
+                .any(|option| matches!(option, TcpOptions2::Md5Signature(_))),
+        }
+    }
+
+    /// Releases the connections that the application closed and that are gone, either because both ends shut them
+    /// down or because they stayed in TIME-WAIT for long enough.
+    fn reap_closed_connections(&mut self) {
+        let closed: Vec<QDesc> = self
+            .addresses
+            .iter()
+            .filter_map(|(socket_id, &qd)| match socket_id {
+                SocketId::Active(..) if self.is_closed(qd) => Some(qd),
+                _ => None,
+            })
+            .collect();
+        for qd in closed {
+            if let Some(port) = self.release_connection(qd) {
+                self.ephemeral_ports.borrow_mut().free(port);
+            }
+        }
+    }
+
+    /// Looks for a connection to `remote` in TIME-WAIT whose 4-tuple can be reused for a new outgoing connection, and
+    /// releases it. This is safe if the connection used timestamps and our timestamp clock has moved on since, since
+    /// our peer then drops any old duplicate (RFC 6191). Returns the port of the connection, which remains reserved
+    /// for the new one, along with the sequence number past which the new connection should start.
+    fn recycle_time_wait(&mut self, remote: &SocketAddrV4) -> Option<(u16, SeqNumber)> {
+        if !self.tcp_config.get_time_wait_recycling() || !self.tcp_config.get_timestamps_enabled() {
+            return None;
+        }
+        let timestamp: u32 = timestamp_value(&self.clock);
+        let (qd, local, min_isn): (QDesc, SocketAddrV4, SeqNumber) =
+            self.addresses
+                .iter()
+                .find_map(|(socket_id, &qd)| -> Option<(QDesc, SocketAddrV4, SeqNumber)> {
+                    let local: SocketAddrV4 = match socket_id {
+                        SocketId::Active(local, other) if other == remote => *local,
+                        _ => return None,
+                    };
+                    let qtable: Ref<IoQueueTable<InetQueue>> = self.qtable.borrow();
+                    let queue: &TcpQueue = match qtable.get_resolved(&qd) {
+                        Some(InetQueue::Tcp(queue)) if queue.get_ephemeral_port() == Some(local.port()) => queue,
+                        _ => return None,
+                    };
+                    match queue.get_socket() {
+                        Socket::Closing(socket) if socket.cb.is_time_wait() => {
+                            let last_timestamp: u32 = socket.cb.get_last_timestamp()?;
+                            if !timestamp_after(timestamp, last_timestamp) {
+                                return None;
+                            }
+                            // Leave a full window between the sequence numbers of both incarnations.
+                            let (send_next, _): (SeqNumber, _) = socket.cb.get_send_next();
+                            Some((qd, local, send_next + SeqNumber::from(u16::MAX as u32 + 2)))
+                        },
+                        _ => None,
+                    }
+                })?;
+        debug!("Recycling connection in TIME-WAIT: {:?} -> {:?}", local, remote);
+        self.release_connection(qd);
+        self.recycled_connections.set(self.recycled_connections.get() + 1);
+        Some((local.port(), min_isn))
+    }
+
+    /// Checks whether the socket referred to by `qd` was closed by the application and its connection is gone.
+    fn is_closed(&self, qd: QDesc) -> bool {
+        match self.qtable.borrow().get_resolved(&qd) {
+            Some(InetQueue::Tcp(queue)) => {
+                matches!(queue.get_socket(), Socket::Closing(socket) if socket.cb.is_closed())
+            },
+            _ => false,
+        }
+    }
+
+    /// Frees the queue descriptor of a closed socket and removes its addresses. Returns the port that the socket held
+    /// in the ephemeral port range, which the caller should either release or hand over.
+    fn release_connection(&mut self, qd: QDesc) -> Option<u16> {
+        self.addresses.retain(|_, other| *other != qd);
+        match self.qtable.borrow_mut().free(&qd) {
+            Some(InetQueue::Tcp(queue)) => {
+                if let Socket::Established(socket) | Socket::Closing(socket) = queue.get_socket() {
+                    let (_, remote): (SocketAddrV4, SocketAddrV4) = socket.endpoints();
+                    self.telemetry
+                        .record_closed(*remote.ip(), socket.byte_stats(), self.clock.now());
+                }
+                queue.get_ephemeral_port()
+            },
+            _ => None,
+        }
+    }
+
     fn send_rst(&self, local: &SocketAddrV4, remote: &SocketAddrV4) -> Result<(), Fail> {
         // TODO: Make this work pending on ARP resolution if needed.
         let remote_link_addr = self
@@ -657,9 +1354,12 @@ impl<const N: usize> Inner<N> {
         let mut tcp_hdr = TcpHeader::new(local.port(), remote.port());
         tcp_hdr.rst = true;
 
+        let mut ipv4_hdr: Ipv4Header = Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP);
+        ipv4_hdr.set_identification(self.rt.next_ipv4_id());
+
         let segment = TcpSegment {
             ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
-            ipv4_hdr: Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
+            ipv4_hdr,
             tcp_hdr,
             data: None,
             tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
@@ -670,22 +1370,31 @@ impl<const N: usize> Inner<N> {
     }
 
     pub(super) fn poll_connect_finished(&mut self, qd: QDesc, context: &mut Context) -> Poll<Result<(), Fail>> {
-        let mut qtable: RefMut<IoQueueTable<InetQueue<N>>> = self.qtable.borrow_mut();
-        match qtable.get_mut(&qd) {
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = self.qtable.borrow_mut();
+        match qtable.get_resolved_mut(&qd) {
             Some(InetQueue::Tcp(queue)) => match queue.get_mut_socket() {
                 Socket::Connecting(socket) => {
-                    let result: Result<ControlBlock<N>, Fail> = match socket.poll_result(context) {
+                    let result: Result<ControlBlock, Fail> = match socket.poll_result(context) {
                         Poll::Pending => return Poll::Pending,
                         Poll::Ready(r) => r,
                     };
                     match result {
                         Ok(cb) => {
-                            let new_socket =
-                                Socket::Established(EstablishedSocket::new(cb, qd, self.dead_socket_tx.clone()));
+                            let new_socket = Socket::Established(EstablishedSocket::new(
+                                cb,
+                                qd,
+                                self.dead_socket_tx.clone(),
+                                self.events.clone(),
+                            ));
                             queue.set_socket(new_socket);
                             Poll::Ready(Ok(()))
                         },
-                        Err(fail) => Poll::Ready(Err(fail)),
+                        Err(fail) => {
+                            let (_, remote): (SocketAddrV4, SocketAddrV4) = socket.endpoints();
+                            self.telemetry.record_failure(*remote.ip(), self.clock.now());
+                            self.events.push(ConnEvent::Reset(qd, fail.errno));
+                            Poll::Ready(Err(fail))
+                        },
                     }
                 },
                 _ => Poll::Ready(Err(Fail::new(libc::EAGAIN, "socket not connecting"))),
@@ -702,7 +1411,11 @@ impl<const N: usize> Inner<N> {
                     // Closing an active socket.
                     Socket::Closing(socket) => match socket.poll_close() {
                         Poll::Pending => return Poll::Pending,
-                        Poll::Ready(_) => Some(SocketId::Active(socket.endpoints().0, socket.endpoints().1)),
+                        Poll::Ready(_) => {
+                            // The queue descriptor is about to be reused, so stop reporting events on it.
+                            socket.cb.set_event_queue(None);
+                            Some(SocketId::Active(socket.endpoints().0, socket.endpoints().1))
+                        },
                     },
                     // Closing an unbound socket.
                     Socket::Inactive(None) => None,
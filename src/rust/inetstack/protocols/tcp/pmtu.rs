@@ -0,0 +1,103 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::std::{
+    cell::RefCell,
+    collections::HashMap,
+    net::Ipv4Addr,
+    rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Time after which we forget a recorded maximum segment size, so that connections try full-sized segments again in case
+/// the path changed. This is the interval after which RFC 1191 Section 6.3 suggests to raise the path MTU estimate.
+const ENTRY_LIFETIME: Duration = Duration::from_secs(600);
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Table of the maximum segment sizes that made it through the path to remote hosts, after black hole detection
+/// (RFC 2923) had to lower them. New connections to these hosts start with the recorded size right away, instead of
+/// detecting the black hole again, until the entry expires. Clones share the same table.
+#[derive(Clone, Default)]
+pub struct PmtuTable {
+    // Maximum segment size for each remote host, along with when it was recorded.
+    entries: Rc<RefCell<HashMap<Ipv4Addr, (usize, Instant)>>>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl PmtuTable {
+    /// Returns the maximum segment size that is known to make it through the path to a remote host, if any.
+    pub fn get(&self, remote: &Ipv4Addr, now: Instant) -> Option<usize> {
+        let mut entries = self.entries.borrow_mut();
+        match entries.get(remote) {
+            Some(&(mss, recorded_at)) if now.duration_since(recorded_at) < ENTRY_LIFETIME => Some(mss),
+            Some(_) => {
+                debug!("get(): forgetting segment size for {:?}", remote);
+                entries.remove(remote);
+                None
+            },
+            None => None,
+        }
+    }
+
+    /// Records a maximum segment size that made it through the path to a remote host.
+    pub fn record(&self, remote: Ipv4Addr, mss: usize, now: Instant) {
+        debug!("record(): path to {:?} takes segments of {:?} bytes", remote, mss);
+        self.entries.borrow_mut().insert(remote, (mss, now));
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        PmtuTable,
+        ENTRY_LIFETIME,
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        net::Ipv4Addr,
+        time::{
+            Duration,
+            Instant,
+        },
+    };
+
+    /// Tests that recorded segment sizes are forgotten once they expire.
+    #[test]
+    fn test_pmtu_table_expiry() -> Result<()> {
+        let table: PmtuTable = PmtuTable::default();
+        let remote: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 2);
+        let now: Instant = Instant::now();
+        table.record(remote, 576, now);
+
+        crate::ensure_eq!(table.get(&remote, now), Some(576));
+        crate::ensure_eq!(
+            table.get(&remote, now + ENTRY_LIFETIME - Duration::from_secs(1)),
+            Some(576)
+        );
+        crate::ensure_eq!(table.get(&remote, now + ENTRY_LIFETIME), None);
+        crate::ensure_eq!(table.get(&remote, now), None);
+
+        Ok(())
+    }
+}
@@ -5,54 +5,131 @@
 // Imports
 //======================================================================================================================
 
-use super::peer::Socket;
+use super::{
+    auth::SegmentAuthenticator,
+    peer::Socket,
+};
 use crate::runtime::{
-    queue::IoQueue,
+    network::types::PushCompletion,
+    queue::{
+        IoQueue,
+        QueueInfo,
+        QueueState,
+    },
     QType,
 };
+use ::std::rc::Rc;
 
 //======================================================================================================================
 // Structures
 //======================================================================================================================
 
 /// Per-queue metadata for the TCP socket.
-pub struct TcpQueue<const N: usize> {
-    socket: Socket<N>,
+pub struct TcpQueue {
+    socket: Socket,
+    /// When pushes on this socket complete.
+    push_completion: PushCompletion,
+    /// Signs and validates the segments of this socket, if any.
+    authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+    /// Port that this socket holds in the ephemeral port range, if any.
+    ephemeral_port: Option<u16>,
 }
 
 //======================================================================================================================
 // Associated Functions
 //======================================================================================================================
 
-impl<const N: usize> TcpQueue<N> {
+impl TcpQueue {
     pub fn new() -> Self {
         Self {
             socket: Socket::Inactive(None),
+            push_completion: PushCompletion::default(),
+            authenticator: None,
+            ephemeral_port: None,
         }
     }
 
     /// Get/borrow reference to underlying TCP socket data structure.
-    pub fn get_socket(&self) -> &Socket<N> {
+    pub fn get_socket(&self) -> &Socket {
         &self.socket
     }
 
     /// Get/borrow mutable reference to underlying TCP socket data structure.
-    pub fn get_mut_socket(&mut self) -> &mut Socket<N> {
+    pub fn get_mut_socket(&mut self) -> &mut Socket {
         &mut self.socket
     }
 
     /// Set underlying TCP socket data structure.
-    pub fn set_socket(&mut self, s: Socket<N>) {
+    pub fn set_socket(&mut self, s: Socket) {
         self.socket = s;
     }
+
+    /// Gets when pushes on this socket complete.
+    pub fn get_push_completion(&self) -> PushCompletion {
+        self.push_completion
+    }
+
+    /// Sets when pushes on this socket complete.
+    pub fn set_push_completion(&mut self, push_completion: PushCompletion) {
+        self.push_completion = push_completion;
+    }
+
+    /// Gets the authenticator of the segments of this socket.
+    pub fn get_authenticator(&self) -> Option<Rc<dyn SegmentAuthenticator>> {
+        self.authenticator.clone()
+    }
+
+    /// Sets the authenticator of the segments of this socket.
+    pub fn set_authenticator(&mut self, authenticator: Option<Rc<dyn SegmentAuthenticator>>) {
+        self.authenticator = authenticator;
+    }
+
+    /// Gets the port that this socket holds in the ephemeral port range.
+    pub fn get_ephemeral_port(&self) -> Option<u16> {
+        self.ephemeral_port
+    }
+
+    /// Sets the port that this socket holds in the ephemeral port range.
+    pub fn set_ephemeral_port(&mut self, port: Option<u16>) {
+        self.ephemeral_port = port;
+    }
 }
 
 //======================================================================================================================
 // Trait implementation
 //======================================================================================================================
 
-impl<const N: usize> IoQueue for TcpQueue<N> {
+impl Default for TcpQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IoQueue for TcpQueue {
     fn get_qtype(&self) -> QType {
         QType::TcpSocket
     }
+
+    fn describe(&self, info: &mut QueueInfo) {
+        let (state, local, remote) = match &self.socket {
+            Socket::Inactive(None) => (QueueState::Unbound, None, None),
+            Socket::Inactive(Some(local)) => (QueueState::Bound, Some(*local), None),
+            Socket::Listening(socket) => (QueueState::Listening, Some(socket.endpoint()), None),
+            Socket::Connecting(socket) => {
+                let (local, remote) = socket.endpoints();
+                (QueueState::Connecting, Some(local), Some(remote))
+            },
+            Socket::Established(socket) => {
+                let (local, remote) = socket.endpoints();
+                (QueueState::Established, Some(local), Some(remote))
+            },
+            Socket::Closing(socket) => {
+                let (local, remote) = socket.endpoints();
+                (QueueState::Closing, Some(local), Some(remote))
+            },
+        };
+        info.state = Some(state);
+        info.local = local;
+        info.remote = remote;
+    }
 }
@@ -0,0 +1,627 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::{
+    inetstack::protocols::tcp::SeqNumber,
+    runtime::{
+        memory::DemiBuffer,
+        network::config::ReceiveBufferMode,
+    },
+};
+use ::std::{
+    cmp,
+    collections::VecDeque,
+};
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Number of bytes tracked by each word of the bitmap of a [RingReceiveBuffer].
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+//==============================================================================
+// Traits
+//==============================================================================
+
+/// Reassembly Buffer for Out-of-Order Data
+///
+/// Holds data that was received ahead of a "hole" in the receive sequence number space, until the hole is filled.
+/// In-order data never goes through this buffer, so it is delivered to the application without any copies.
+pub trait ReceiveBuffer {
+    /// Stores an out-of-order segment that starts at `seg_start`, where `receive_next` is the sequence number of the
+    /// next byte that we expect to receive in order. Data that is already stored is not stored again. Returns `false`
+    /// if the segment was dropped because the buffer already holds as many segments as it may.
+    fn store(&mut self, receive_next: SeqNumber, seg_start: SeqNumber, buf: DemiBuffer) -> bool;
+
+    /// Takes stored data that starts at `receive_next`, if any. Call this repeatedly until it returns `None` to
+    /// collect all data that became in order.
+    fn take(&mut self, receive_next: SeqNumber) -> Option<DemiBuffer>;
+
+    /// Drops all stored data.
+    fn clear(&mut self);
+
+    /// Returns the number of separate segments that are stored.
+    fn num_segments(&self) -> usize;
+}
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Linear Reassembly Buffer
+///
+/// Keeps out-of-order segments as they arrived, in a list that is sorted by sequence number and holds no duplicate
+/// data. Segments are trimmed where they overlap, which is cheap, but the list is scanned on every insertion.
+pub struct LinearReceiveBuffer {
+    segments: VecDeque<(SeqNumber, DemiBuffer)>,
+    /// Maximum number of segments in `segments`.
+    max_segments: usize,
+}
+
+/// Ring Reassembly Buffer
+///
+/// Copies out-of-order data into a circular buffer that covers the receive window, and tracks which bytes were
+/// received in a bitmap. Insertion costs are independent of how fragmented the received data is, and data that
+/// becomes in order is handed over as a single contiguous buffer. Storage is only allocated once some data arrives out
+/// of order.
+pub struct RingReceiveBuffer {
+    /// Size of the ring, which is a multiple of [BITS_PER_WORD].
+    capacity: usize,
+    /// Maximum number of separate runs of received bytes in the ring.
+    max_segments: usize,
+    /// Backing storage. Empty until some data arrives out of order.
+    data: Vec<u8>,
+    /// One bit for each byte of `data`, set if that byte was received.
+    received: Vec<u64>,
+    /// Index in `data` of the byte with sequence number `base`.
+    head: usize,
+    /// Sequence number of the byte at `head`.
+    base: SeqNumber,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+impl LinearReceiveBuffer {
+    /// Creates a list that holds up to `max_segments` segments.
+    pub fn new(max_segments: usize) -> Self {
+        Self {
+            segments: VecDeque::new(),
+            max_segments,
+        }
+    }
+}
+
+impl RingReceiveBuffer {
+    /// Creates a ring buffer that holds up to `capacity` bytes past `receive_next`, in up to `max_segments` separate
+    /// runs.
+    pub fn new(capacity: usize, max_segments: usize, receive_next: SeqNumber) -> Self {
+        // Round the capacity up to a whole number of bitmap words.
+        let capacity: usize = cmp::max(capacity, 1);
+        Self {
+            capacity: capacity + (BITS_PER_WORD - capacity % BITS_PER_WORD) % BITS_PER_WORD,
+            max_segments,
+            data: Vec::new(),
+            received: Vec::new(),
+            head: 0,
+            base: receive_next,
+        }
+    }
+
+    /// Moves the start of the ring up to `receive_next`, dropping whatever was stored before it. That data was
+    /// received in order in the meantime.
+    fn advance(&mut self, receive_next: SeqNumber) {
+        let delta: usize = u32::from(receive_next - self.base) as usize;
+        if delta == 0 {
+            return;
+        }
+        if !self.data.is_empty() {
+            self.clear_range(self.head, cmp::min(delta, self.capacity));
+        }
+        self.head = (self.head + delta) % self.capacity;
+        self.base = receive_next;
+    }
+
+    /// Returns the (up to two) contiguous runs of indexes in the ring that `len` bytes starting at `start` occupy.
+    fn runs(&self, start: usize, len: usize) -> [(usize, usize); 2] {
+        let first: usize = cmp::min(len, self.capacity - start);
+        [(start, first), (0, len - first)]
+    }
+
+    /// Marks `len` bytes starting at index `start` of the ring as received.
+    fn set_range(&mut self, start: usize, len: usize) {
+        for (start, len) in self.runs(start, len) {
+            Self::update_bits(&mut self.received, start, len, true);
+        }
+    }
+
+    /// Marks `len` bytes starting at index `start` of the ring as not received.
+    fn clear_range(&mut self, start: usize, len: usize) {
+        for (start, len) in self.runs(start, len) {
+            Self::update_bits(&mut self.received, start, len, false);
+        }
+    }
+
+    /// Sets or clears `len` bits of a bitmap, starting at bit `start`. The range must not wrap around.
+    fn update_bits(bitmap: &mut [u64], start: usize, len: usize, value: bool) {
+        let mut index: usize = start;
+        let end: usize = start + len;
+        while index < end {
+            let offset: usize = index % BITS_PER_WORD;
+            let nbits: usize = cmp::min(BITS_PER_WORD - offset, end - index);
+            let mask: u64 = if nbits == BITS_PER_WORD {
+                u64::MAX
+            } else {
+                ((1u64 << nbits) - 1) << offset
+            };
+            let word: &mut u64 = &mut bitmap[index / BITS_PER_WORD];
+            if value {
+                *word |= mask;
+            } else {
+                *word &= !mask;
+            }
+            index += nbits;
+        }
+    }
+
+    /// Counts the received bytes that immediately follow index `start` of the ring, up to `max` of them.
+    fn count_received(&self, start: usize, max: usize) -> usize {
+        let mut count: usize = 0;
+        let mut index: usize = start;
+        while count < max {
+            let offset: usize = index % BITS_PER_WORD;
+            let ones: usize = (self.received[index / BITS_PER_WORD] >> offset).trailing_ones() as usize;
+            let available: usize = cmp::min(ones, BITS_PER_WORD - offset);
+            count += available;
+            if available < BITS_PER_WORD - offset {
+                break;
+            }
+            index = (index + available) % self.capacity;
+        }
+        cmp::min(count, max)
+    }
+
+    /// Checks whether any of the `len` bytes starting at index `start` of the ring were received.
+    fn any_received(&self, start: usize, len: usize) -> bool {
+        self.runs(start, len).iter().any(|&(start, len)| {
+            let mut index: usize = start;
+            let end: usize = start + len;
+            while index < end {
+                let offset: usize = index % BITS_PER_WORD;
+                let nbits: usize = cmp::min(BITS_PER_WORD - offset, end - index);
+                let mask: u64 = if nbits == BITS_PER_WORD {
+                    u64::MAX
+                } else {
+                    ((1u64 << nbits) - 1) << offset
+                };
+                if self.received[index / BITS_PER_WORD] & mask != 0 {
+                    return true;
+                }
+                index += nbits;
+            }
+            false
+        })
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+impl ReceiveBuffer for LinearReceiveBuffer {
+    // Note: Since this is not the "fast path", this is written for clarity over efficiency.
+    fn store(&mut self, _receive_next: SeqNumber, seg_start: SeqNumber, mut buf: DemiBuffer) -> bool {
+        debug_assert!(!buf.is_empty());
+        let mut new_start: SeqNumber = seg_start;
+        let mut new_end: SeqNumber = seg_start + SeqNumber::from(buf.len() as u32 - 1);
+        let out_of_order: &mut VecDeque<(SeqNumber, DemiBuffer)> = &mut self.segments;
+        let mut action_index: usize = out_of_order.len();
+        let mut another_pass_neeeded: bool = true;
+
+        while another_pass_neeeded {
+            another_pass_neeeded = false;
+
+            // Find the new segment's place in the out-of-order store.
+            // The out-of-order store is sorted by starting sequence number, and contains no duplicate data.
+            action_index = out_of_order.len();
+            for (index, stored_segment) in out_of_order.iter().enumerate() {
+                // Properties of the segment stored at this index.
+                let stored_start: SeqNumber = stored_segment.0;
+                let stored_len: u32 = stored_segment.1.len() as u32;
+                debug_assert_ne!(stored_len, 0);
+                let stored_end: SeqNumber = stored_start + SeqNumber::from(stored_len - 1);
+
+                //
+                // The new data segment has six possibilites when compared to an existing out-of-order segment:
+                //
+                //                                |<- out-of-order segment ->|
+                //
+                // |<- new before->|    |<- new front overlap ->|    |<- new end overlap ->|    |<- new after ->|
+                //                                   |<- new duplicate ->|
+                //                            |<- new completely encompassing ->|
+                //
+                if new_start < stored_start {
+                    // The new segment starts before the start of this out-of-order segment.
+                    if new_end < stored_start {
+                        // The new segment comes completely before this out-of-order segment.
+                        // Since the out-of-order store is sorted, we don't need to check for overlap with any more.
+                        action_index = index;
+                        break;
+                    }
+                    // The end of the new segment overlaps with the start of this out-of-order segment.
+                    if stored_end < new_end {
+                        // The new segment ends after the end of this out-of-order segment.  In other words, the new
+                        // segment completely encompasses the out-of-order segment.
+
+                        // Set flags to remove the currently stored segment and re-run the insertion loop, as the
+                        // new segment may completely encompass even more segments.
+                        another_pass_neeeded = true;
+                        action_index = index;
+                        break;
+                    }
+                    // We have some data overlap between the new segment and the front of the out-of-order segment.
+                    // Trim the end of the new segment and stop checking for out-of-order overlap.
+                    let excess: u32 = u32::from(new_end - stored_start) + 1;
+                    new_end = new_end - SeqNumber::from(excess);
+                    buf.trim(excess as usize)
+                        .expect("'buf' should contain at least 'excess' bytes");
+                    break;
+                } else {
+                    // The new segment starts at or after the start of this out-of-order segment.
+                    // This is the stored_start <= new_start case.
+                    if new_end <= stored_end {
+                        // And the new segment ends at or before this out-of-order segment.
+                        // The new segment's data is a complete duplicate of this out-of-order segment's data.
+                        // Just drop the new segment.
+                        return true;
+                    }
+                    if stored_end < new_start {
+                        // The new segment comes entirely after this out-of-order segment.
+                        // Continue to check the next out-of-order segment for potential overlap.
+                        continue;
+                    }
+                    // We have some data overlap between the new segment and the end of the out-of-order segment.
+                    // Adjust the beginning of the new segment and continue on to check the next out-of-order segment.
+                    let duplicate: u32 = u32::from(stored_end - new_start) + 1;
+                    new_start = new_start + SeqNumber::from(duplicate);
+                    buf.adjust(duplicate as usize)
+                        .expect("'buf' should contain at least 'duplicate' bytes");
+                    continue;
+                }
+            }
+
+            if another_pass_neeeded {
+                // The new segment completely encompassed an existing segment, which we will now remove.
+                out_of_order.remove(action_index);
+            }
+        }
+
+        // If the out-of-order store is full, drop the new segment rather than one that we already have. This bounds
+        // the work done on each insertion, and thereby mitigates deliberate out-of-order segment attacks.
+        if out_of_order.len() >= self.max_segments {
+            return false;
+        }
+
+        // Insert the new segment into the correct position.
+        out_of_order.insert(action_index, (new_start, buf));
+        true
+    }
+
+    fn take(&mut self, receive_next: SeqNumber) -> Option<DemiBuffer> {
+        // Since our out-of-order list is sorted, only the first segment may be in sequence.
+        match self.segments.front() {
+            Some((seg_start, _)) if *seg_start == receive_next => self.segments.pop_front().map(|(_, buf)| buf),
+            _ => None,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.segments.clear();
+    }
+
+    fn num_segments(&self) -> usize {
+        self.segments.len()
+    }
+}
+
+impl ReceiveBuffer for RingReceiveBuffer {
+    fn store(&mut self, receive_next: SeqNumber, seg_start: SeqNumber, buf: DemiBuffer) -> bool {
+        self.advance(receive_next);
+
+        // Skip any data that we already received in order.
+        let mut bytes: &[u8] = &buf[..];
+        let mut start: SeqNumber = seg_start;
+        if start < self.base {
+            let duplicate: usize = cmp::min(u32::from(self.base - start) as usize, bytes.len());
+            bytes = &bytes[duplicate..];
+            start = self.base;
+        }
+
+        // Drop whatever does not fit in the ring. Our peer will retransmit it.
+        let offset: usize = u32::from(start - self.base) as usize;
+        if offset >= self.capacity || bytes.is_empty() {
+            return true;
+        }
+        let len: usize = cmp::min(bytes.len(), self.capacity - offset);
+
+        if self.data.is_empty() {
+            self.data = vec![0; self.capacity];
+            self.received = vec![0; self.capacity / BITS_PER_WORD];
+        }
+
+        // Drop the data if the ring is full, unless it touches data that we have, and so does not add another run.
+        // The byte at the head of the ring is never received, so the runs do not wrap around it.
+        let index: usize = (self.head + offset) % self.capacity;
+        if self.num_segments() >= self.max_segments
+            && !self.any_received(
+                (index + self.capacity - 1) % self.capacity,
+                cmp::min(len + 2, self.capacity),
+            )
+        {
+            return false;
+        }
+
+        // Copy the data into the ring, and remember that we have it.
+        let mut copied: usize = 0;
+        for (run_start, run_len) in self.runs(index, len) {
+            self.data[run_start..(run_start + run_len)].copy_from_slice(&bytes[copied..(copied + run_len)]);
+            copied += run_len;
+        }
+        self.set_range(index, len);
+        true
+    }
+
+    fn take(&mut self, receive_next: SeqNumber) -> Option<DemiBuffer> {
+        if self.data.is_empty() {
+            self.base = receive_next;
+            return None;
+        }
+        self.advance(receive_next);
+
+        // Hand over all contiguous data at the start of the ring, as far as it fits in a single buffer.
+        let len: usize = self.count_received(self.head, u16::MAX as usize);
+        if len == 0 {
+            return None;
+        }
+        let mut buf: DemiBuffer = DemiBuffer::new(len as u16);
+        let mut copied: usize = 0;
+        for (run_start, run_len) in self.runs(self.head, len) {
+            buf[copied..(copied + run_len)].copy_from_slice(&self.data[run_start..(run_start + run_len)]);
+            copied += run_len;
+        }
+        self.clear_range(self.head, len);
+        self.head = (self.head + len) % self.capacity;
+        self.base = self.base + SeqNumber::from(len as u32);
+        Some(buf)
+    }
+
+    fn clear(&mut self) {
+        self.data = Vec::new();
+        self.received = Vec::new();
+    }
+
+    fn num_segments(&self) -> usize {
+        // Count the bytes that were received, but whose predecessor was not.
+        let nwords: usize = self.received.len();
+        (0..nwords)
+            .map(|i| {
+                let word: u64 = self.received[i];
+                let carry: u64 = self.received[(i + nwords - 1) % nwords] >> (BITS_PER_WORD - 1);
+                (word & !((word << 1) | carry)).count_ones() as usize
+            })
+            .sum()
+    }
+}
+
+//==============================================================================
+// Standalone Functions
+//==============================================================================
+
+/// Creates the reassembly buffer for a connection, where `capacity` is the size of its receive window and
+/// `receive_next` is the sequence number of the first byte that we expect to receive. The buffer holds up to
+/// `max_segments` separate segments.
+pub fn new_receive_buffer(
+    mode: ReceiveBufferMode,
+    capacity: usize,
+    max_segments: usize,
+    receive_next: SeqNumber,
+) -> Box<dyn ReceiveBuffer> {
+    match mode {
+        ReceiveBufferMode::Linear => Box::new(LinearReceiveBuffer::new(max_segments)),
+        ReceiveBufferMode::Ring => Box::new(RingReceiveBuffer::new(capacity, max_segments, receive_next)),
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        new_receive_buffer,
+        ReceiveBuffer,
+    };
+    use crate::{
+        inetstack::protocols::tcp::SeqNumber,
+        runtime::{
+            memory::DemiBuffer,
+            network::config::ReceiveBufferMode,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::collections::VecDeque;
+    use ::test::{
+        black_box,
+        Bencher,
+    };
+
+    /// Maximum number of segments that the reassembly buffers hold, unless a test says otherwise.
+    const MAX_SEGMENTS: usize = 64;
+    /// Size of the segments that the benchmarks receive.
+    const BENCH_SEGMENT_SIZE: usize = 1024;
+    /// Number of segments that the benchmarks receive per iteration.
+    const BENCH_NUM_SEGMENTS: usize = 64;
+
+    /// Creates a segment of `len` bytes whose contents are derived from the sequence numbers that it covers.
+    fn segment(start: u32, len: usize) -> DemiBuffer {
+        let bytes: Vec<u8> = (0..len).map(|i| (start as usize + i) as u8).collect();
+        DemiBuffer::from_slice(&bytes).expect("slice should fit in DemiBuffer")
+    }
+
+    /// Receives segments in the given order, returning the data that got delivered in order. In-order segments bypass
+    /// the reassembly buffer, like in the control block.
+    fn receive(mode: ReceiveBufferMode, isn: u32, segments: &[(u32, usize)]) -> (Vec<u8>, SeqNumber) {
+        let mut receive_buffer: Box<dyn ReceiveBuffer> =
+            new_receive_buffer(mode, 0xffff, MAX_SEGMENTS, SeqNumber::from(isn));
+        let mut receive_next: SeqNumber = SeqNumber::from(isn);
+        let mut delivered: Vec<u8> = Vec::new();
+        for &(start, len) in segments {
+            let seg_start: SeqNumber = SeqNumber::from(start);
+            let buf: DemiBuffer = segment(start, len);
+            if seg_start == receive_next {
+                receive_next = receive_next + SeqNumber::from(len as u32);
+                delivered.extend_from_slice(&buf[..]);
+                while let Some(buf) = receive_buffer.take(receive_next) {
+                    receive_next = receive_next + SeqNumber::from(buf.len() as u32);
+                    delivered.extend_from_slice(&buf[..]);
+                }
+            } else if receive_next < seg_start {
+                receive_buffer.store(receive_next, seg_start, buf);
+            }
+        }
+        (delivered, receive_next)
+    }
+
+    /// Tests if both modes reassemble reordered, duplicate, and overlapping segments.
+    #[test]
+    fn test_reassembly() -> Result<()> {
+        // Sequence numbers wrap around in the middle of the data.
+        let isn: u32 = u32::MAX - 100;
+        let segments: Vec<(u32, usize)> = vec![
+            (isn.wrapping_add(300), 100),
+            (isn.wrapping_add(100), 100),
+            (isn.wrapping_add(150), 100),
+            (isn.wrapping_add(100), 100),
+            (isn, 100),
+            (isn.wrapping_add(250), 50),
+        ];
+        let expected: Vec<u8> = (0..400).map(|i| (isn as usize + i) as u8).collect();
+        for mode in [ReceiveBufferMode::Linear, ReceiveBufferMode::Ring] {
+            let (delivered, receive_next): (Vec<u8>, SeqNumber) = receive(mode, isn, &segments);
+            crate::ensure_eq!(delivered, expected);
+            crate::ensure_eq!(receive_next, SeqNumber::from(isn.wrapping_add(400)));
+        }
+
+        Ok(())
+    }
+
+    /// Tests if the ring drops data past its end, and keeps working once it wraps around.
+    #[test]
+    fn test_ring_wraparound() -> Result<()> {
+        let mut receive_buffer: Box<dyn ReceiveBuffer> =
+            new_receive_buffer(ReceiveBufferMode::Ring, 128, MAX_SEGMENTS, SeqNumber::from(0));
+
+        // Data past the end of the ring is dropped.
+        receive_buffer.store(SeqNumber::from(0), SeqNumber::from(64), segment(64, 128));
+        crate::ensure_eq!(receive_buffer.take(SeqNumber::from(0)).is_none(), true);
+        let buf: DemiBuffer = receive_buffer
+            .take(SeqNumber::from(64))
+            .expect("data should be in order");
+        crate::ensure_eq!(buf[..], segment(64, 64)[..]);
+
+        // Data that straddles the end of the ring comes back in one piece.
+        receive_buffer.store(SeqNumber::from(160), SeqNumber::from(200), segment(200, 80));
+        let buf: DemiBuffer = receive_buffer
+            .take(SeqNumber::from(200))
+            .expect("data should be in order");
+        crate::ensure_eq!(buf[..], segment(200, 80)[..]);
+        crate::ensure_eq!(receive_buffer.take(SeqNumber::from(280)).is_none(), true);
+
+        Ok(())
+    }
+
+    /// Tests if both modes drop new segments once they hold as many as they may, and keep the ones that they have.
+    #[test]
+    fn test_segment_limit() -> Result<()> {
+        for mode in [ReceiveBufferMode::Linear, ReceiveBufferMode::Ring] {
+            let mut receive_buffer: Box<dyn ReceiveBuffer> = new_receive_buffer(mode, 0xffff, 10, SeqNumber::from(0));
+
+            // Every other block of 10 bytes arrives, so that no two segments touch.
+            let mut dropped: usize = 0;
+            for i in 0..100 {
+                let start: u32 = 20 * i + 10;
+                if !receive_buffer.store(SeqNumber::from(0), SeqNumber::from(start), segment(start, 10)) {
+                    dropped += 1;
+                }
+            }
+            crate::ensure_eq!(dropped, 90);
+            crate::ensure_eq!(receive_buffer.num_segments(), 10);
+
+            // The first hole gets filled, and the first stored segment comes through.
+            let buf: DemiBuffer = receive_buffer
+                .take(SeqNumber::from(10))
+                .expect("data should be in order");
+            crate::ensure_eq!(buf[..], segment(10, 10)[..]);
+            crate::ensure_eq!(receive_buffer.num_segments(), 9);
+        }
+
+        Ok(())
+    }
+
+    /// Builds the order in which the segments of a benchmark arrive. If `lossy`, every tenth segment is lost at first
+    /// and retransmitted after the following segments.
+    fn bench_arrivals(lossy: bool) -> Vec<(u32, usize)> {
+        let mut arrivals: Vec<(u32, usize)> = Vec::with_capacity(BENCH_NUM_SEGMENTS);
+        let mut lost: VecDeque<(u32, usize)> = VecDeque::new();
+        for i in 0..BENCH_NUM_SEGMENTS {
+            let seg: (u32, usize) = ((i * BENCH_SEGMENT_SIZE) as u32, BENCH_SEGMENT_SIZE);
+            if lossy && i % 10 == 3 {
+                lost.push_back(seg);
+            } else {
+                arrivals.push(seg);
+            }
+            if lossy && i % 10 == 9 {
+                arrivals.extend(lost.drain(..));
+            }
+        }
+        arrivals.extend(lost.drain(..));
+        arrivals
+    }
+
+    fn bench_receive(b: &mut Bencher, mode: ReceiveBufferMode, lossy: bool) {
+        let arrivals: Vec<(u32, usize)> = bench_arrivals(lossy);
+        b.bytes = (BENCH_NUM_SEGMENTS * BENCH_SEGMENT_SIZE) as u64;
+        b.iter(|| {
+            let (delivered, _): (Vec<u8>, SeqNumber) = receive(mode, 0, &arrivals);
+            assert_eq!(delivered.len(), BENCH_NUM_SEGMENTS * BENCH_SEGMENT_SIZE);
+            black_box(delivered);
+        });
+    }
+
+    #[bench]
+    fn bench_linear_sequential(b: &mut Bencher) {
+        bench_receive(b, ReceiveBufferMode::Linear, false);
+    }
+
+    #[bench]
+    fn bench_linear_lossy(b: &mut Bencher) {
+        bench_receive(b, ReceiveBufferMode::Linear, true);
+    }
+
+    #[bench]
+    fn bench_ring_sequential(b: &mut Bencher) {
+        bench_receive(b, ReceiveBufferMode::Ring, false);
+    }
+
+    #[bench]
+    fn bench_ring_lossy(b: &mut Bencher) {
+        bench_receive(b, ReceiveBufferMode::Ring, true);
+    }
+}
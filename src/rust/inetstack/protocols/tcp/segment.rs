@@ -6,7 +6,10 @@ use crate::{
         ethernet2::Ethernet2Header,
         ip::IpProtocol,
         ipv4::Ipv4Header,
-        tcp::SeqNumber,
+        tcp::{
+            auth::SegmentAuthenticator,
+            SeqNumber,
+        },
     },
     runtime::{
         fail::Fail,
@@ -103,6 +106,7 @@ pub enum TcpOptions2 {
         sender_timestamp: u32,
         echo_timestamp: u32,
     },
+    Md5Signature([u8; 16]),
 }
 
 impl TcpOptions2 {
@@ -115,6 +119,7 @@ impl TcpOptions2 {
             SelectiveAcknowlegementPermitted => 2,
             SelectiveAcknowlegement { num_sacks, .. } => 2 + 8 * num_sacks,
             Timestamp { .. } => 10,
+            Md5Signature(..) => 18,
         }
     }
 
@@ -161,6 +166,12 @@ impl TcpOptions2 {
                 buf[6..10].copy_from_slice(&echo_timestamp.to_be_bytes());
                 10
             },
+            Md5Signature(signature) => {
+                buf[0] = 19;
+                buf[1] = 18;
+                buf[2..18].copy_from_slice(signature);
+                18
+            },
         }
     }
 }
@@ -353,6 +364,17 @@ impl TcpHeader {
                             echo_timestamp,
                         }
                     },
+                    19 => {
+                        let mut temp: [u8; 1] = [0; 1];
+                        option_rdr.read_exact(&mut temp)?;
+                        let option_length: u8 = temp[0];
+                        if option_length != 18 {
+                            return Err(Fail::new(EBADMSG, "TCP MD5 signature size was not 18"));
+                        }
+                        let mut signature: [u8; 16] = [0; 16];
+                        option_rdr.read_exact(&mut signature)?;
+                        TcpOptions2::Md5Signature(signature)
+                    },
                     _ => return Err(Fail::new(EBADMSG, "invalid TCP option")),
                 };
                 if num_options >= option_list.len() {
@@ -389,12 +411,42 @@ impl TcpHeader {
     }
 
     pub fn serialize(&self, buf: &mut [u8], ipv4_hdr: &Ipv4Header, data: &[u8], tx_checksum_offload: bool) {
-        let fixed_buf: &mut [u8; MIN_TCP_HEADER_SIZE] = (&mut buf[..MIN_TCP_HEADER_SIZE]).try_into().unwrap();
+        buf[..MIN_TCP_HEADER_SIZE].copy_from_slice(&self.serialize_fixed(self.compute_size()));
+
+        let mut cur_pos: usize = MIN_TCP_HEADER_SIZE;
+        for i in 0..self.num_options {
+            let bytes_written = self.option_list[i].serialize(&mut buf[cur_pos..]);
+            cur_pos += bytes_written;
+        }
+        // Write out an "End of options list" if we had options.
+        if self.num_options > 0 {
+            buf[cur_pos] = 0;
+            cur_pos += 1;
+        }
+        // Zero out the remainder of padding in the header.
+        for byte in &mut buf[cur_pos..] {
+            *byte = 0;
+        }
+
+        // Alright, we've fully filled out the header, time to compute the checksum.
+        if !tx_checksum_offload {
+            let checksum: u16 = tcp_checksum(ipv4_hdr, &buf[..], data);
+            buf[16..18].copy_from_slice(&checksum.to_be_bytes());
+        } else {
+            buf[16] = 0;
+            buf[17] = 0;
+        }
+    }
+
+    /// Serializes the fixed part of the header, for a header whose size including options is `header_size`. The
+    /// checksum is left zeroed.
+    pub fn serialize_fixed(&self, header_size: usize) -> [u8; MIN_TCP_HEADER_SIZE] {
+        let mut fixed_buf: [u8; MIN_TCP_HEADER_SIZE] = [0; MIN_TCP_HEADER_SIZE];
         fixed_buf[0..2].copy_from_slice(&self.src_port.to_be_bytes());
         fixed_buf[2..4].copy_from_slice(&self.dst_port.to_be_bytes());
         fixed_buf[4..8].copy_from_slice(&u32::from(self.seq_num).to_be_bytes());
         fixed_buf[8..12].copy_from_slice(&u32::from(self.ack_num).to_be_bytes());
-        fixed_buf[12] = ((self.compute_size() / 4) as u8) << 4;
+        fixed_buf[12] = ((header_size / 4) as u8) << 4;
         if self.ns {
             fixed_buf[12] |= 1;
         }
@@ -426,42 +478,24 @@ impl TcpHeader {
 
         fixed_buf[14..16].copy_from_slice(&self.window_size.to_be_bytes());
 
-        // Write the checksum (bytes 16..18) later.
+        // Leave the checksum (bytes 16..18) zeroed.
 
         fixed_buf[18..20].copy_from_slice(&self.urgent_pointer.to_be_bytes());
-
-        let mut cur_pos: usize = MIN_TCP_HEADER_SIZE;
-        for i in 0..self.num_options {
-            let bytes_written = self.option_list[i].serialize(&mut buf[cur_pos..]);
-            cur_pos += bytes_written;
-        }
-        // Write out an "End of options list" if we had options.
-        if self.num_options > 0 {
-            buf[cur_pos] = 0;
-            cur_pos += 1;
-        }
-        // Zero out the remainder of padding in the header.
-        for byte in &mut buf[cur_pos..] {
-            *byte = 0;
-        }
-
-        // Alright, we've fully filled out the header, time to compute the checksum.
-        if !tx_checksum_offload {
-            let checksum: u16 = tcp_checksum(ipv4_hdr, &buf[..], data);
-            buf[16..18].copy_from_slice(&checksum.to_be_bytes());
-        } else {
-            buf[16] = 0;
-            buf[17] = 0;
-        }
+        fixed_buf
     }
 
     // TODO: Review the use of usize here (and everywhere in inetstack, really).
     pub fn compute_size(&self) -> usize {
-        let mut size = MIN_TCP_HEADER_SIZE;
+        self.compute_size_with(0)
+    }
+
+    /// Computes the size of the header once an option of `extra_option_size` bytes is appended to it.
+    fn compute_size_with(&self, extra_option_size: usize) -> usize {
+        let mut size = MIN_TCP_HEADER_SIZE + extra_option_size;
         for i in 0..self.num_options {
             size += self.option_list[i].compute_size();
         }
-        if self.num_options > 0 {
+        if self.num_options > 0 || extra_option_size > 0 {
             // Add a byte for the "End of options list" if needed.
             size += 1;
         }
@@ -479,6 +513,30 @@ impl TcpHeader {
         self.option_list[self.num_options] = option;
         self.num_options += 1;
     }
+
+    /// Signs this header and the `data` that follows it, by appending the option computed by `authenticator`. SACK
+    /// blocks are dropped if the options would not fit otherwise, since the peer can do without them.
+    pub fn sign(&mut self, authenticator: &dyn SegmentAuthenticator, ipv4_hdr: &Ipv4Header, data: &[u8]) {
+        let option_size: usize = authenticator.option_size();
+        while self.compute_size_with(option_size) > MAX_TCP_HEADER_SIZE && self.drop_sack_block() {}
+        let header_size: usize = self.compute_size_with(option_size);
+        let signature: TcpOptions2 = authenticator.sign(ipv4_hdr, self, header_size, data);
+        self.push_option(signature);
+        debug_assert_eq!(self.compute_size(), header_size);
+    }
+
+    /// Drops the last block of the SACK option, if it has more than one. Returns whether a block was dropped.
+    fn drop_sack_block(&mut self) -> bool {
+        for option in self.option_list[..self.num_options].iter_mut() {
+            if let TcpOptions2::SelectiveAcknowlegement { num_sacks, .. } = option {
+                if *num_sacks > 1 {
+                    *num_sacks -= 1;
+                    return true;
+                }
+            }
+        }
+        false
+    }
 }
 
 fn tcp_checksum(ipv4_header: &Ipv4Header, header: &[u8], data: &[u8]) -> u16 {
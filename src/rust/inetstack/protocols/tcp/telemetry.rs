@@ -0,0 +1,152 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::network::types::{
+    ByteStats,
+    HandshakeStats,
+    PeerStats,
+};
+use ::std::{
+    cell::RefCell,
+    collections::HashMap,
+    net::Ipv4Addr,
+    rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Telemetry on a single remote host.
+struct Entry {
+    stats: PeerStats,
+    last_update: Instant,
+}
+
+/// Table of telemetry on remote hosts, aggregated across the connections to each of them. Connections record round-trip
+/// time samples and failed connection attempts as they happen, and fold their byte counters in when they are released.
+/// Clones share the same table.
+#[derive(Clone, Default)]
+pub struct PeerTelemetry {
+    entries: Rc<RefCell<HashMap<Ipv4Addr, Entry>>>,
+}
+
+/// Counters on the handshakes that remote hosts initiate towards our listening sockets. Clones share the same counters.
+#[derive(Clone, Default)]
+pub struct HandshakeTelemetry {
+    stats: Rc<RefCell<HandshakeStats>>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl PeerTelemetry {
+    /// Folds a round-trip time sample into the smoothed round-trip time to a remote host, with the gain that RFC 6298
+    /// uses for a single connection.
+    pub fn record_rtt(&self, remote: Ipv4Addr, rtt: Duration, now: Instant) {
+        let mut entries = self.entries.borrow_mut();
+        let entry: &mut Entry = Self::entry(&mut entries, remote, now);
+        entry.stats.srtt = Some(match entry.stats.srtt {
+            Some(srtt) => (srtt * 7 + rtt) / 8,
+            None => rtt,
+        });
+    }
+
+    /// Records a connection attempt to a remote host that was refused or timed out.
+    pub fn record_failure(&self, remote: Ipv4Addr, now: Instant) {
+        let mut entries = self.entries.borrow_mut();
+        Self::entry(&mut entries, remote, now).stats.connection_failures += 1;
+    }
+
+    /// Folds the byte counters of a connection to a remote host that is being released.
+    pub fn record_closed(&self, remote: Ipv4Addr, byte_stats: ByteStats, now: Instant) {
+        let mut entries = self.entries.borrow_mut();
+        let entry: &mut Entry = Self::entry(&mut entries, remote, now);
+        entry.stats.bytes_sent += byte_stats.sent;
+        entry.stats.bytes_retransmitted += byte_stats.retransmitted;
+    }
+
+    /// Returns the telemetry on all remote hosts, with the byte counters of the connections in `live` added in. Remote
+    /// hosts that have no connection in `live` and were last updated longer than `retention` ago are forgotten.
+    pub fn snapshot(
+        &self,
+        live: Vec<(Ipv4Addr, ByteStats)>,
+        now: Instant,
+        retention: Duration,
+    ) -> Vec<(Ipv4Addr, PeerStats)> {
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|remote, entry| {
+            now.duration_since(entry.last_update) <= retention || live.iter().any(|(other, _)| other == remote)
+        });
+        let mut stats: HashMap<Ipv4Addr, PeerStats> =
+            entries.iter().map(|(remote, entry)| (*remote, entry.stats)).collect();
+        for (remote, byte_stats) in live {
+            let peer_stats: &mut PeerStats = stats.entry(remote).or_default();
+            peer_stats.bytes_sent += byte_stats.sent;
+            peer_stats.bytes_retransmitted += byte_stats.retransmitted;
+        }
+        stats.into_iter().collect()
+    }
+
+    /// Returns the entry of a remote host, creating it if needed, and marks it as updated.
+    fn entry(entries: &mut HashMap<Ipv4Addr, Entry>, remote: Ipv4Addr, now: Instant) -> &mut Entry {
+        let entry: &mut Entry = entries.entry(remote).or_insert_with(|| Entry {
+            stats: PeerStats::default(),
+            last_update: now,
+        });
+        entry.last_update = now;
+        entry
+    }
+}
+
+impl HandshakeTelemetry {
+    /// Records a connection request.
+    pub fn record_syn_received(&self) {
+        self.stats.borrow_mut().syn_received += 1;
+    }
+
+    /// Records a SYN+ACK that was sent, be it a retransmission or not.
+    pub fn record_syn_ack_sent(&self) {
+        self.stats.borrow_mut().syn_ack_sent += 1;
+    }
+
+    /// Records a handshake that completed `latency` after the connection request came in, and folds that latency into
+    /// the moving average with the same gain as the smoothed round-trip time.
+    pub fn record_completed(&self, latency: Duration) {
+        let mut stats = self.stats.borrow_mut();
+        let latency: u64 = latency.as_micros() as u64;
+        stats.avg_handshake_latency_us = match stats.handshakes_completed {
+            0 => latency,
+            _ => (stats.avg_handshake_latency_us * 7 + latency) / 8,
+        };
+        stats.handshakes_completed += 1;
+    }
+
+    /// Records a handshake that did not complete. Handshakes that timed out are counted apart as well.
+    pub fn record_failed(&self, timed_out: bool) {
+        let mut stats = self.stats.borrow_mut();
+        stats.handshakes_failed += 1;
+        if timed_out {
+            stats.handshake_timeout_count += 1;
+        }
+    }
+
+    /// Returns the counters.
+    pub fn snapshot(&self) -> HandshakeStats {
+        *self.stats.borrow()
+    }
+
+    /// Resets the counters.
+    pub fn reset(&self) {
+        *self.stats.borrow_mut() = HandshakeStats::default();
+    }
+}
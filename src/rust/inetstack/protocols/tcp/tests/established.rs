@@ -3,17 +3,44 @@
 
 use crate::{
     inetstack::{
-        protocols::tcp::{
-            operations::PushFuture,
-            tests::{
-                check_packet_data,
-                check_packet_pure_ack,
-                setup::{
-                    advance_clock,
-                    connection_setup,
+        protocols::{
+            ethernet2::{
+                Ethernet2Header,
+                ETHERNET2_HEADER_SIZE,
+            },
+            ipv4::{
+                Ipv4Header,
+                IPV4_ECN_CE,
+                IPV4_ECN_ECT0,
+                IPV4_ECN_NOT_ECT,
+                IPV4_HEADER_MIN_SIZE,
+            },
+            tcp::{
+                gro::ReceiveCoalescer,
+                operations::{
+                    AcceptFuture,
+                    ConnectFuture,
+                    PopFuture,
+                    PushFuture,
+                },
+                segment::{
+                    TcpHeader,
+                    TcpSegment,
+                    MIN_TCP_HEADER_SIZE,
+                },
+                tests::{
+                    check_packet_data,
+                    check_packet_pure_ack,
+                    setup::{
+                        advance_clock,
+                        connection_setup,
+                        serialize_segment,
+                    },
                 },
+                SeqNumber,
+                TcpInfo,
+                TcpState,
             },
-            SeqNumber,
         },
         test_helpers::{
             self,
@@ -22,7 +49,16 @@ use crate::{
     },
     runtime::{
         memory::DemiBuffer,
-        network::consts::RECEIVE_BATCH_SIZE,
+        network::{
+            config::TcpConfig,
+            consts::MAX_GRO_SIZE,
+            types::{
+                ConnEvent,
+                PeerStats,
+                PushCompletion,
+            },
+            ReceiveBatch,
+        },
         QDesc,
     },
 };
@@ -30,15 +66,26 @@ use ::anyhow::Result;
 use ::futures::task::noop_waker_ref;
 use ::rand;
 use ::std::{
+    cmp,
     collections::VecDeque,
     future::Future,
-    net::SocketAddrV4,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
     pin::Pin,
     task::{
         Context,
         Poll,
     },
-    time::Instant,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use ::test::{
+    black_box,
+    Bencher,
 };
 
 //=============================================================================
@@ -55,11 +102,11 @@ fn cook_buffer(size: usize, stamp: Option<u8>) -> DemiBuffer {
 
 //=============================================================================
 
-fn send_data<const N: usize>(
+fn send_data(
     ctx: &mut Context,
     now: &mut Instant,
-    receiver: &mut Engine<N>,
-    sender: &mut Engine<N>,
+    receiver: &mut Engine,
+    sender: &mut Engine,
     sender_fd: QDesc,
     window_size: u16,
     seq_no: SeqNumber,
@@ -102,10 +149,10 @@ fn send_data<const N: usize>(
 
 //=============================================================================
 
-fn recv_data<const N: usize>(
+fn recv_data(
     ctx: &mut Context,
-    receiver: &mut Engine<N>,
-    sender: &mut Engine<N>,
+    receiver: &mut Engine,
+    sender: &mut Engine,
     receiver_fd: QDesc,
     bytes: DemiBuffer,
 ) -> Result<()> {
@@ -133,12 +180,7 @@ fn recv_data<const N: usize>(
 
 //=============================================================================
 
-fn recv_pure_ack<const N: usize>(
-    now: &mut Instant,
-    sender: &mut Engine<N>,
-    receiver: &mut Engine<N>,
-    ack_num: SeqNumber,
-) -> Result<()> {
+fn recv_pure_ack(now: &mut Instant, sender: &mut Engine, receiver: &mut Engine, ack_num: SeqNumber) -> Result<()> {
     trace!(
         "recv_pure_ack ====> ack: {:?} -> {:?}",
         sender.rt.ipv4_addr,
@@ -169,11 +211,11 @@ fn recv_pure_ack<const N: usize>(
 
 //=============================================================================
 
-fn send_recv<const N: usize>(
+fn send_recv(
     ctx: &mut Context,
     now: &mut Instant,
-    server: &mut Engine<N>,
-    client: &mut Engine<N>,
+    server: &mut Engine,
+    client: &mut Engine,
     server_fd: QDesc,
     client_fd: QDesc,
     window_size: u16,
@@ -206,11 +248,11 @@ fn send_recv<const N: usize>(
 
 //=============================================================================
 
-fn send_recv_round<const N: usize>(
+fn send_recv_round(
     ctx: &mut Context,
     now: &mut Instant,
-    server: &mut Engine<N>,
-    client: &mut Engine<N>,
+    server: &mut Engine,
+    client: &mut Engine,
     server_fd: QDesc,
     client_fd: QDesc,
     window_size: u16,
@@ -246,11 +288,11 @@ fn send_recv_round<const N: usize>(
 
 //=============================================================================
 
-fn connection_hangup<const N: usize>(
+fn connection_hangup(
     _ctx: &mut Context,
     now: &mut Instant,
-    server: &mut Engine<N>,
-    client: &mut Engine<N>,
+    server: &mut Engine,
+    client: &mut Engine,
     server_fd: QDesc,
     client_fd: QDesc,
 ) -> Result<()> {
@@ -319,8 +361,8 @@ pub fn test_send_recv_loop() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup peers.
-    let mut server: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
     let window_scale: u8 = client.rt.tcp_config.get_window_scale();
     let max_window_size: u32 =
         match (client.rt.tcp_config.get_receive_window_size() as u32).checked_shl(window_scale as u32) {
@@ -364,8 +406,8 @@ pub fn test_send_recv_round_loop() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup peers.
-    let mut server: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
     let window_scale: u8 = client.rt.tcp_config.get_window_scale();
     let max_window_size: u32 =
         match (client.rt.tcp_config.get_receive_window_size() as u32).checked_shl(window_scale as u32) {
@@ -411,8 +453,8 @@ pub fn test_send_recv_with_delay() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup peers.
-    let mut server: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
     let window_scale: u8 = client.rt.tcp_config.get_window_scale();
     let max_window_size: u32 =
         match (client.rt.tcp_config.get_receive_window_size() as u32).checked_shl(window_scale as u32) {
@@ -476,8 +518,10 @@ pub fn test_send_recv_with_delay() -> Result<()> {
 
 //=============================================================================
 
+/// Tests pushes around the maximum segment size. Pushes that exceed it should be
+/// accepted and split into multiple segments.
 #[test]
-fn test_connect_disconnect() -> Result<()> {
+pub fn test_push_size_limits() -> Result<()> {
     let mut ctx = Context::from_waker(noop_waker_ref());
     let mut now = Instant::now();
 
@@ -486,14 +530,2241 @@ fn test_connect_disconnect() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup peers.
-    let mut server: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let window_scale: u8 = client.rt.tcp_config.get_window_scale();
+    let max_window_size: u32 =
+        match (client.rt.tcp_config.get_receive_window_size() as u32).checked_shl(window_scale as u32) {
+            Some(shift) => shift,
+            None => anyhow::bail!("incorrect receive window"),
+        };
 
     let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
         connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
     crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
 
-    connection_hangup(&mut ctx, &mut now, &mut server, &mut client, server_fd, client_fd)?;
+    let mss: usize = client.tcp_max_push_size(client_fd)?;
+    let mut seq_no: SeqNumber = SeqNumber::from(1);
+
+    for bufsize in [mss - 1, mss, mss + 1] {
+        let buf: DemiBuffer = cook_buffer(bufsize, None);
+        let mut push_future: PushFuture = client.tcp_push(client_fd, buf);
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+
+        // Receive all segments.
+        let mut nsegments: usize = 0;
+        let mut nbytes: usize = 0;
+        while nbytes < bufsize {
+            client.rt.poll_scheduler();
+            let bytes: DemiBuffer = client.rt.pop_frame();
+            let len: usize = check_packet_data(
+                bytes.clone(),
+                client.rt.link_addr,
+                server.rt.link_addr,
+                client.rt.ipv4_addr,
+                server.rt.ipv4_addr,
+                max_window_size as u16,
+                seq_no,
+                None,
+            )?;
+            crate::ensure_eq!(len <= mss, true);
+            recv_data(&mut ctx, &mut server, &mut client, server_fd, bytes)?;
+
+            nsegments += 1;
+            nbytes += len;
+            seq_no = seq_no + SeqNumber::from(len as u32);
+            recv_pure_ack(&mut now, &mut server, &mut client, seq_no)?;
+        }
+        crate::ensure_eq!(nbytes, bufsize);
+        crate::ensure_eq!(nsegments, if bufsize > mss { 2 } else { 1 });
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests if flushing a socket emits buffered data right away, without waiting for the background sender.
+#[test]
+pub fn test_flush() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let window_scale: u8 = client.rt.tcp_config.get_window_scale();
+    let max_window_size: u32 =
+        match (client.rt.tcp_config.get_receive_window_size() as u32).checked_shl(window_scale as u32) {
+            Some(shift) => shift,
+            None => anyhow::bail!("incorrect receive window"),
+        };
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Push a buffer that does not fit in a single segment, so that it is held on the unsent queue.
+    let mss: usize = client.tcp_max_push_size(client_fd)?;
+    let bufsize: usize = mss + 1;
+    let buf: DemiBuffer = cook_buffer(bufsize, None);
+    let mut push_future: PushFuture = client.tcp_push(client_fd, buf);
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+
+    // Flush the socket and check that all segments are emitted without running the background sender.
+    client.tcp_flush(client_fd)?;
+    let mut seq_no: SeqNumber = SeqNumber::from(1);
+    let mut nbytes: usize = 0;
+    while nbytes < bufsize {
+        let bytes: DemiBuffer = match client.rt.pop_frame_unchecked() {
+            Some(bytes) => bytes,
+            None => anyhow::bail!("flush should have emitted all buffered data"),
+        };
+        let len: usize = check_packet_data(
+            bytes.clone(),
+            client.rt.link_addr,
+            server.rt.link_addr,
+            client.rt.ipv4_addr,
+            server.rt.ipv4_addr,
+            max_window_size as u16,
+            seq_no,
+            None,
+        )?;
+        recv_data(&mut ctx, &mut server, &mut client, server_fd, bytes)?;
+        nbytes += len;
+        seq_no = seq_no + SeqNumber::from(len as u32);
+    }
+    crate::ensure_eq!(nbytes, bufsize);
+
+    // The background sender should have nothing left to send.
+    client.rt.poll_scheduler();
+    crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests if flushing a socket emits all buffered data right away while a small segment is still unacknowledged, which
+/// Nagle's algorithm would otherwise hold the trailing partial segment back for.
+#[test]
+pub fn test_flush_with_unacked_data() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let window_scale: u8 = client.rt.tcp_config.get_window_scale();
+    let max_window_size: u32 =
+        match (client.rt.tcp_config.get_receive_window_size() as u32).checked_shl(window_scale as u32) {
+            Some(shift) => shift,
+            None => anyhow::bail!("incorrect receive window"),
+        };
+
+    let ((_, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Push a small buffer and leave it unacknowledged.
+    let small_bufsize: usize = 32;
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(small_bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let mut seq_no: SeqNumber = SeqNumber::from(1);
+    let bytes: DemiBuffer = match client.rt.pop_frame_unchecked() {
+        Some(bytes) => bytes,
+        None => anyhow::bail!("small push should have been sent right away"),
+    };
+    let len: usize = check_packet_data(
+        bytes,
+        client.rt.link_addr,
+        server.rt.link_addr,
+        client.rt.ipv4_addr,
+        server.rt.ipv4_addr,
+        max_window_size as u16,
+        seq_no,
+        None,
+    )?;
+    crate::ensure_eq!(len, small_bufsize);
+    seq_no = seq_no + SeqNumber::from(len as u32);
+
+    // Push a buffer that ends in a partial segment, so that it is held on the unsent queue.
+    let mss: usize = client.tcp_max_push_size(client_fd)?;
+    let bufsize: usize = mss + 1;
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+
+    // Flush the socket and check that all segments, including the trailing partial one, are emitted right away.
+    client.tcp_flush(client_fd)?;
+    let mut lens: Vec<usize> = Vec::new();
+    while let Some(bytes) = client.rt.pop_frame_unchecked() {
+        let len: usize = check_packet_data(
+            bytes,
+            client.rt.link_addr,
+            server.rt.link_addr,
+            client.rt.ipv4_addr,
+            server.rt.ipv4_addr,
+            max_window_size as u16,
+            seq_no,
+            None,
+        )?;
+        lens.push(len);
+        seq_no = seq_no + SeqNumber::from(len as u32);
+    }
+    crate::ensure_eq!(lens, vec![mss, 1]);
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests if a connection is reset once the application stops draining its receive queue.
+#[test]
+pub fn test_reset_on_receive_buffer_exhaustion() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let max_receive_buffer_bytes: usize = 2 * bufsize;
+
+    // Setup peers.
+    let tcp_config: TcpConfig = TcpConfig::builder()
+        .max_receive_buffer_bytes(max_receive_buffer_bytes)
+        .build();
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Send data up to the limit, without ever popping it on the server.
+    for _ in 0..(max_receive_buffer_bytes / bufsize + 1) {
+        let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+        let bytes: DemiBuffer = client.rt.pop_frame();
+        if let Err(e) = server.receive(bytes) {
+            anyhow::bail!("receive returned error: {:?}", e);
+        }
+    }
+
+    // The last segment exceeded the limit, so the server should have reset the connection.
+    let mut bytes: DemiBuffer = server.rt.pop_frame();
+    while let Some(next) = server.rt.pop_frame_unchecked() {
+        bytes = next;
+    }
+    let (_, eth2_payload) = Ethernet2Header::parse(bytes).unwrap();
+    let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload).unwrap();
+    let (tcp_header, _) = TcpHeader::parse(&ipv4_header, ipv4_payload, false).unwrap();
+    crate::ensure_eq!(tcp_header.rst, true);
+
+    // Popping on the server should report the reset and release the queue descriptor.
+    let mut pop_future = server.tcp_pop(server_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ENOBUFS => {},
+        _ => anyhow::bail!("pop should have failed with ENOBUFS"),
+    }
+    let mut pop_future = server.tcp_pop(server_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::EBADF => {},
+        _ => anyhow::bail!("queue descriptor should have been released"),
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests pops with a timeout. A pop that sees no data fails with ETIMEDOUT once the timeout expires, leaving the socket
+/// usable, and data that arrives exactly at expiry wins over the timeout.
+#[test]
+pub fn test_pop_timeout() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let timeout: Duration = Duration::from_secs(30);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // No data arrives, so the pop should time out.
+    let mut pop_future = Box::pin(server.tcp_pop_timeout(server_fd, timeout));
+    crate::ensure_eq!(Future::poll(pop_future.as_mut(), &mut ctx).is_pending(), true);
+    now += timeout / 2;
+    server.clock.advance_clock(now);
+    crate::ensure_eq!(Future::poll(pop_future.as_mut(), &mut ctx).is_pending(), true);
+    now += timeout / 2;
+    server.clock.advance_clock(now);
+    match Future::poll(pop_future.as_mut(), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ETIMEDOUT => {},
+        _ => anyhow::bail!("pop should have timed out"),
+    }
+
+    // The socket should remain usable, and data that arrives right at expiry should win.
+    let mut pop_future = Box::pin(server.tcp_pop_timeout(server_fd, timeout));
+    crate::ensure_eq!(Future::poll(pop_future.as_mut(), &mut ctx).is_pending(), true);
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    now += timeout;
+    server.clock.advance_clock(now);
+    if let Err(e) = server.receive(client.rt.pop_frame()) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    match Future::poll(pop_future.as_mut(), &mut ctx) {
+        Poll::Ready(Ok(buf)) => crate::ensure_eq!(buf.len(), bufsize),
+        _ => anyhow::bail!("pop should have returned data"),
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests that peeking at received data leaves it in place, so that the next pop returns the same bytes.
+#[test]
+pub fn test_pop_peek() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Peeking should wait for data, like popping does.
+    let mut peek_future = server.tcp_pop_peek(server_fd, Some(4));
+    crate::ensure_eq!(Future::poll(Pin::new(&mut peek_future), &mut ctx).is_pending(), true);
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    if let Err(e) = server.receive(client.rt.pop_frame()) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+
+    // Peek at the first bytes twice.
+    match Future::poll(Pin::new(&mut peek_future), &mut ctx) {
+        Poll::Ready(Ok(buf)) => crate::ensure_eq!(&buf[..], &[0, 1, 2, 3]),
+        _ => anyhow::bail!("peek should have returned data"),
+    }
+    let mut peek_future = server.tcp_pop_peek(server_fd, Some(4));
+    match Future::poll(Pin::new(&mut peek_future), &mut ctx) {
+        Poll::Ready(Ok(buf)) => crate::ensure_eq!(&buf[..], &[0, 1, 2, 3]),
+        _ => anyhow::bail!("peek should have returned data"),
+    }
+
+    // Popping should return the same bytes, followed by the rest of the data.
+    let mut pop_future = server.tcp_pop(server_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Ok(buf)) => crate::ensure_eq!(buf[..], cook_buffer(bufsize, None)[..]),
+        _ => anyhow::bail!("pop should have returned data"),
+    }
+    let mut peek_future = server.tcp_pop_peek(server_fd, Some(4));
+    crate::ensure_eq!(Future::poll(Pin::new(&mut peek_future), &mut ctx).is_pending(), true);
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests that peeking at more data than fits in a single buffer returns the first 64 KiB of it.
+#[test]
+pub fn test_pop_peek_large() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 32768;
+    let nbufs: usize = 3;
+
+    // Setup peers with a receive window that fits all the data.
+    let tcp_config: TcpConfig = TcpConfig::builder().window_scale(2).build();
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, tcp_config.clone());
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Queue the data at the server, without popping it.
+    for i in 0..nbufs {
+        let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, Some(i as u8)));
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+    }
+    for _ in 0..8 {
+        advance_clock(Some(&mut server), Some(&mut client), &mut now);
+        loop {
+            let mut progress: bool = false;
+            client.rt.poll_scheduler();
+            server.rt.poll_scheduler();
+            while let Some(bytes) = client.rt.pop_frame_unchecked() {
+                progress = true;
+                if let Err(e) = server.receive(bytes) {
+                    anyhow::bail!("receive returned error: {:?}", e);
+                }
+            }
+            while let Some(bytes) = server.rt.pop_frame_unchecked() {
+                progress = true;
+                if let Err(e) = client.receive(bytes) {
+                    anyhow::bail!("receive returned error: {:?}", e);
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+    }
+
+    // Peek at all of it.
+    let mut peek_future = server.tcp_pop_peek(server_fd, Some(nbufs * bufsize));
+    match Future::poll(Pin::new(&mut peek_future), &mut ctx) {
+        Poll::Ready(Ok(buf)) => {
+            crate::ensure_eq!(buf.len(), u16::MAX as usize);
+            for (i, byte) in buf.iter().enumerate() {
+                crate::ensure_eq!(*byte, (i / bufsize) as u8);
+            }
+        },
+        _ => anyhow::bail!("peek should have returned data"),
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Sets the URG flag and the urgent pointer of the TCP segment in an outgoing frame.
+fn mark_urgent(bytes: DemiBuffer, urgent_pointer: u16) -> Result<DemiBuffer> {
+    let (ethernet2_hdr, eth2_payload) = Ethernet2Header::parse(bytes)?;
+    let (ipv4_hdr, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (mut tcp_hdr, data) = TcpHeader::parse(&ipv4_hdr, ipv4_payload, false)?;
+    tcp_hdr.urg = true;
+    tcp_hdr.urgent_pointer = urgent_pointer;
+    serialize_segment(TcpSegment {
+        ethernet2_hdr,
+        ipv4_hdr,
+        tcp_hdr,
+        data: Some(data),
+        tx_checksum_offload: false,
+    })
+}
+
+/// Tests that urgent bytes are stripped from the stream, both when the urgent pointer points into the segment that
+/// carries it and when it points into a later one, and that they still count as received.
+#[test]
+pub fn test_urgent_data_stripped() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 8;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // The first segment points at its own fourth byte, the second one points at the fourth byte of the third segment.
+    for urgent_pointer in [Some(4), Some(12), None] {
+        let _: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+        client.rt.poll_scheduler();
+        let mut bytes: DemiBuffer = client.rt.pop_frame();
+        let seq_num: SeqNumber = parse_tcp_segment(bytes.clone())?.0.seq_num;
+        if let Some(urgent_pointer) = urgent_pointer {
+            bytes = mark_urgent(bytes, urgent_pointer)?;
+        }
+        if let Err(e) = server.receive(bytes) {
+            anyhow::bail!("receive returned error: {:?}", e);
+        }
+        advance_clock(Some(&mut server), Some(&mut client), &mut now);
+        server.rt.poll_scheduler();
+
+        // The whole segment is acknowledged, including the urgent byte.
+        let ack: DemiBuffer = server.rt.pop_frame();
+        crate::ensure_eq!(
+            parse_tcp_segment(ack.clone())?.0.ack_num,
+            seq_num + SeqNumber::from(bufsize as u32)
+        );
+        if let Err(e) = client.receive(ack) {
+            anyhow::bail!("receive returned error: {:?}", e);
+        }
+    }
+
+    // The urgent bytes never reach the application.
+    let mut received: Vec<u8> = Vec::new();
+    loop {
+        let mut pop_future = server.tcp_pop(server_fd);
+        match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+            Poll::Ready(Ok(buf)) => received.extend_from_slice(&buf[..]),
+            Poll::Pending => break,
+            Poll::Ready(Err(e)) => anyhow::bail!("pop returned error: {:?}", e),
+        }
+    }
+    let stripped: Vec<u8> = vec![0, 1, 2, 4, 5, 6, 7];
+    crate::ensure_eq!(
+        received,
+        [&stripped[..], &cook_buffer(bufsize, None)[..], &stripped[..]].concat()
+    );
+    crate::ensure_eq!(server.tcp_bytes_available(server_fd)?, 0);
 
     Ok(())
 }
+
+//=============================================================================
+
+/// Tests if the number of bytes available on a connection follows the data that arrives and the data that is popped.
+#[test]
+pub fn test_bytes_available() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(server.tcp_bytes_available(server_fd)?, 0);
+
+    // Data that arrives adds to the bytes available.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    if let Err(e) = server.receive(client.rt.pop_frame()) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    crate::ensure_eq!(server.tcp_bytes_available(server_fd)?, bufsize);
+
+    // Peeking leaves the bytes available as they are, whereas popping takes them away.
+    let mut peek_future = server.tcp_pop_peek(server_fd, Some(4));
+    crate::ensure_eq!(Future::poll(Pin::new(&mut peek_future), &mut ctx).is_ready(), true);
+    crate::ensure_eq!(server.tcp_bytes_available(server_fd)?, bufsize);
+    let mut pop_future = server.tcp_pop(server_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Ok(buf)) => crate::ensure_eq!(buf.len(), bufsize),
+        _ => anyhow::bail!("pop should have returned data"),
+    }
+    crate::ensure_eq!(server.tcp_bytes_available(server_fd)?, 0);
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests pushes with a deadline. A push completes once all of its data is acknowledged. If the peer acknowledges only
+/// part of it before the deadline, the push fails with ETIMEDOUT and the connection is aborted.
+#[test]
+pub fn test_push_timeout() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let timeout: Duration = Duration::from_secs(30);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // The push completes when the data is acknowledged, not when it is sent.
+    let mut push_future = Box::pin(client.tcp_push_timeout(client_fd, cook_buffer(bufsize, None), timeout)?);
+    crate::ensure_eq!(Future::poll(push_future.as_mut(), &mut ctx).is_pending(), true);
+    server.receive(client.rt.pop_frame())?;
+    crate::ensure_eq!(Future::poll(push_future.as_mut(), &mut ctx).is_pending(), true);
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    client.receive(server.rt.pop_frame())?;
+    match Future::poll(push_future.as_mut(), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let mut pop_future: PopFuture = server.tcp_pop(server_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Ok(buf)) => crate::ensure_eq!(buf.len(), bufsize),
+        _ => anyhow::bail!("pop should have returned data"),
+    }
+
+    // Push two segments worth of data, but only deliver the first one, so that the server acknowledges half of it.
+    let mss: usize = client.tcp_max_push_size(client_fd)?;
+    let mut push_future = Box::pin(client.tcp_push_timeout(client_fd, cook_buffer(2 * mss, None), timeout)?);
+    crate::ensure_eq!(Future::poll(push_future.as_mut(), &mut ctx).is_pending(), true);
+    client.rt.poll_scheduler();
+    server.receive(client.rt.pop_frame())?;
+    while client.rt.pop_frame_unchecked().is_some() {}
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    client.receive(server.rt.pop_frame())?;
+    crate::ensure_eq!(Future::poll(push_future.as_mut(), &mut ctx).is_pending(), true);
+
+    // The push fails once the deadline expires.
+    now += timeout;
+    client.clock.advance_clock(now);
+    match Future::poll(push_future.as_mut(), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ETIMEDOUT => {},
+        _ => anyhow::bail!("push should have timed out"),
+    }
+
+    // The connection is aborted: the client resets it, and further pushes fail.
+    let mut last_frame: Option<DemiBuffer> = None;
+    while let Some(frame) = client.rt.pop_frame_unchecked() {
+        last_frame = Some(frame);
+    }
+    let (tcp_header, _): (TcpHeader, usize) = match last_frame {
+        Some(frame) => parse_tcp_segment(frame)?,
+        None => anyhow::bail!("client should have sent a RST"),
+    };
+    crate::ensure_eq!(tcp_header.rst, true);
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ETIMEDOUT => {},
+        _ => anyhow::bail!("push should have failed"),
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests if a pending pop learns that the peer reset the connection, and if further pushes fail.
+#[test]
+pub fn test_pop_reset_by_peer() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let timeout: Duration = Duration::from_secs(30);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // The server waits for data.
+    let mut pop_future: PopFuture = server.tcp_pop(server_fd);
+    crate::ensure_eq!(Future::poll(Pin::new(&mut pop_future), &mut ctx).is_pending(), true);
+
+    // The client gives up on a push that never gets through, and resets the connection.
+    let mut push_future = Box::pin(client.tcp_push_timeout(client_fd, cook_buffer(bufsize, None), timeout)?);
+    crate::ensure_eq!(Future::poll(push_future.as_mut(), &mut ctx).is_pending(), true);
+    now += timeout;
+    client.clock.advance_clock(now);
+    crate::ensure_eq!(Future::poll(push_future.as_mut(), &mut ctx).is_ready(), true);
+    let mut last_frame: Option<DemiBuffer> = None;
+    while let Some(frame) = client.rt.pop_frame_unchecked() {
+        last_frame = Some(frame);
+    }
+    let rst: DemiBuffer = match last_frame {
+        Some(frame) => frame,
+        None => anyhow::bail!("client should have sent a RST"),
+    };
+    crate::ensure_eq!(parse_tcp_segment(rst.clone())?.0.rst, true);
+    server.receive(rst)?;
+
+    // The pending pop fails, and so do further pushes.
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ECONNRESET => {},
+        _ => anyhow::bail!("pop should have failed with ECONNRESET"),
+    }
+    let mut push_future: PushFuture = server.tcp_push(server_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ECONNRESET => {},
+        _ => anyhow::bail!("push should have failed with ECONNRESET"),
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests that connections report that they were established, closed in an orderly fashion, or reset, on both ends.
+#[test]
+pub fn test_connection_events() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    // Both ends report the connection as established, and as closed once they hung up.
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(server.tcp_next_event(), Some(ConnEvent::Established(server_fd)));
+    crate::ensure_eq!(client.tcp_next_event(), Some(ConnEvent::Established(client_fd)));
+    connection_hangup(&mut ctx, &mut now, &mut server, &mut client, server_fd, client_fd)?;
+    crate::ensure_eq!(server.tcp_next_event(), Some(ConnEvent::Closed(server_fd)));
+    crate::ensure_eq!(client.tcp_next_event(), Some(ConnEvent::Closed(client_fd)));
+    crate::ensure_eq!(server.tcp_next_event(), None);
+    crate::ensure_eq!(client.tcp_next_event(), None);
+
+    // The client gives up on a push that never gets through, and resets the connection.
+    let listen_port: u16 = 81;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let timeout: Duration = Duration::from_secs(30);
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(server.tcp_next_event(), Some(ConnEvent::Established(server_fd)));
+    crate::ensure_eq!(client.tcp_next_event(), Some(ConnEvent::Established(client_fd)));
+    let mut push_future = Box::pin(client.tcp_push_timeout(client_fd, cook_buffer(64, None), timeout)?);
+    crate::ensure_eq!(Future::poll(push_future.as_mut(), &mut ctx).is_pending(), true);
+    now += timeout;
+    client.clock.advance_clock(now);
+    crate::ensure_eq!(Future::poll(push_future.as_mut(), &mut ctx).is_ready(), true);
+    crate::ensure_eq!(
+        client.tcp_next_event(),
+        Some(ConnEvent::Reset(client_fd, libc::ETIMEDOUT))
+    );
+    let mut last_frame: Option<DemiBuffer> = None;
+    while let Some(frame) = client.rt.pop_frame_unchecked() {
+        last_frame = Some(frame);
+    }
+    match last_frame {
+        Some(rst) => server.receive(rst)?,
+        None => anyhow::bail!("client should have sent a RST"),
+    }
+    crate::ensure_eq!(
+        server.tcp_next_event(),
+        Some(ConnEvent::Reset(server_fd, libc::ECONNRESET))
+    );
+    crate::ensure_eq!(server.tcp_next_event(), None);
+    crate::ensure_eq!(client.tcp_next_event(), None);
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests pushes that complete on acknowledgement. Each push completes once the peer acknowledges its last byte, in
+/// order, regardless of when its data was sent.
+#[test]
+pub fn test_push_completion_acked() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((_, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+    client.tcp_set_push_completion(client_fd, PushCompletion::Acked)?;
+
+    // Send two buffers. Neither push completes when its data goes out.
+    let mut first_push: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    crate::ensure_eq!(Future::poll(Pin::new(&mut first_push), &mut ctx).is_pending(), true);
+    let first_segment: DemiBuffer = client.rt.pop_frame();
+    let mut second_push: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    crate::ensure_eq!(Future::poll(Pin::new(&mut second_push), &mut ctx).is_pending(), true);
+    let second_segment: DemiBuffer = client.rt.pop_frame();
+
+    // Acknowledge the first buffer only. Only the first push completes.
+    server.receive(first_segment)?;
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    client.receive(server.rt.pop_frame())?;
+    match Future::poll(Pin::new(&mut first_push), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("first push should have completed successfully"),
+    }
+    crate::ensure_eq!(Future::poll(Pin::new(&mut second_push), &mut ctx).is_pending(), true);
+
+    // Acknowledge the second buffer.
+    server.receive(second_segment)?;
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    client.receive(server.rt.pop_frame())?;
+    match Future::poll(Pin::new(&mut second_push), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("second push should have completed successfully"),
+    }
+
+    // Pushes complete right away again once the socket is switched back.
+    client.tcp_set_push_completion(client_fd, PushCompletion::Enqueued)?;
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Exchanges frames between both peers until there is nothing left to send. The server pops any data right away.
+/// Returns the number of bytes popped by the server and the largest receive window that it advertised.
+fn exchange_and_drain(
+    ctx: &mut Context,
+    server: &mut Engine,
+    client: &mut Engine,
+    server_fd: QDesc,
+) -> Result<(usize, Option<u16>)> {
+    let mut nbytes: usize = 0;
+    let mut window_size: Option<u16> = None;
+    loop {
+        let mut progress: bool = false;
+        client.rt.poll_scheduler();
+        server.rt.poll_scheduler();
+        while let Some(bytes) = client.rt.pop_frame_unchecked() {
+            progress = true;
+            if let Err(e) = server.receive(bytes) {
+                anyhow::bail!("receive returned error: {:?}", e);
+            }
+            loop {
+                let mut pop_future = server.tcp_pop(server_fd);
+                match Future::poll(Pin::new(&mut pop_future), ctx) {
+                    Poll::Ready(Ok(buf)) => nbytes += buf.len(),
+                    Poll::Ready(Err(e)) => anyhow::bail!("pop returned error: {:?}", e),
+                    Poll::Pending => break,
+                }
+            }
+        }
+        while let Some(bytes) = server.rt.pop_frame_unchecked() {
+            progress = true;
+            let (_, eth2_payload) = Ethernet2Header::parse(bytes.clone()).unwrap();
+            let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload).unwrap();
+            let (tcp_header, _) = TcpHeader::parse(&ipv4_header, ipv4_payload, false).unwrap();
+            window_size = cmp::max(window_size, Some(tcp_header.window_size));
+            if let Err(e) = client.receive(bytes) {
+                anyhow::bail!("receive returned error: {:?}", e);
+            }
+        }
+        if !progress {
+            return Ok((nbytes, window_size));
+        }
+    }
+}
+
+/// Tests if the receive window grows toward its configured maximum when the application drains it quickly, and
+/// shrinks back once the connection goes idle without moving its right edge back.
+#[test]
+pub fn test_receive_window_auto_tuning() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let initial_window_size: u16 = 2048;
+    let max_window_size: u16 = 16384;
+    let chunk_size: usize = 1024;
+
+    // Setup peers.
+    let tcp_config: TcpConfig = TcpConfig::builder()
+        .receive_window_size(initial_window_size)
+        .receive_window_auto_tuning(true)
+        .max_receive_window_size(max_window_size as u32)
+        .build();
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Fill the advertised window once per round trip, and have the server drain it right away.
+    let mut window_size: u16 = initial_window_size;
+    for _ in 0..8 {
+        advance_clock(Some(&mut server), Some(&mut client), &mut now);
+
+        let mut nbytes: usize = 0;
+        while nbytes < window_size as usize {
+            let len: usize = cmp::min(chunk_size, window_size as usize - nbytes);
+            let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(len, None));
+            match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+                Poll::Ready(Ok(())) => {},
+                _ => anyhow::bail!("push should have completed successfully"),
+            }
+            nbytes += len;
+        }
+
+        let (drained, advertised): (usize, Option<u16>) =
+            exchange_and_drain(&mut ctx, &mut server, &mut client, server_fd)?;
+        crate::ensure_eq!(drained, nbytes);
+        // Acknowledgements sent before the server drained its queue advertise a smaller window, so keep the largest.
+        if let Some(advertised) = advertised {
+            window_size = cmp::max(window_size, advertised);
+        }
+    }
+    crate::ensure_eq!(window_size, max_window_size);
+
+    // Let the connection go idle. The window shrinks back to its initial size, but only as the application reads data,
+    // so that the right edge of the window that the server already advertised never moves back.
+    for _ in 0..10 {
+        advance_clock(Some(&mut server), Some(&mut client), &mut now);
+        exchange_and_drain(&mut ctx, &mut server, &mut client, server_fd)?;
+    }
+    let mut nbytes: usize = 0;
+    while window_size > initial_window_size {
+        let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(chunk_size, None));
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+        nbytes += chunk_size;
+        exchange_and_drain(&mut ctx, &mut server, &mut client, server_fd)?;
+        server.tcp_flush(server_fd)?;
+        let (_, advertised): (usize, Option<u16>) = exchange_and_drain(&mut ctx, &mut server, &mut client, server_fd)?;
+        window_size = match advertised {
+            Some(advertised) => advertised,
+            None => anyhow::bail!("server should have advertised its window"),
+        };
+        crate::ensure_eq!(
+            window_size as usize,
+            cmp::max(initial_window_size as usize, max_window_size as usize - nbytes)
+        );
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+#[test]
+fn test_connect_disconnect() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    connection_hangup(&mut ctx, &mut now, &mut server, &mut client, server_fd, client_fd)?;
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests if duplicated queue descriptors share an established connection, which is only closed with the last of them.
+#[test]
+fn test_dup() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let window_scale: u8 = client.rt.tcp_config.get_window_scale();
+    let max_window_size: u32 =
+        match (client.rt.tcp_config.get_receive_window_size() as u32).checked_shl(window_scale as u32) {
+            Some(shift) => shift,
+            None => anyhow::bail!("incorrect receive window"),
+        };
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+    let server_dup_fd: QDesc = server.tcp_dup(server_fd)?;
+    let client_dup_fd: QDesc = client.tcp_dup(client_fd)?;
+    crate::ensure_neq!(server_dup_fd, server_fd);
+    crate::ensure_neq!(client_dup_fd, client_fd);
+
+    // Push on the duplicate descriptors and pop on the original ones.
+    let bufsize: usize = 64;
+    let seq_no: SeqNumber = SeqNumber::from(1);
+    let bytes: DemiBuffer = cook_buffer(bufsize, None);
+    let (bytes, _): (DemiBuffer, usize) = send_data(
+        &mut ctx,
+        &mut now,
+        &mut server,
+        &mut client,
+        client_dup_fd,
+        max_window_size as u16,
+        seq_no,
+        None,
+        bytes,
+    )?;
+    recv_data(&mut ctx, &mut server, &mut client, server_fd, bytes)?;
+    let bytes: DemiBuffer = cook_buffer(bufsize, None);
+    let (bytes, _): (DemiBuffer, usize) = send_data(
+        &mut ctx,
+        &mut now,
+        &mut client,
+        &mut server,
+        server_dup_fd,
+        max_window_size as u16,
+        seq_no,
+        Some(seq_no + SeqNumber::from(bufsize as u32)),
+        bytes,
+    )?;
+    recv_data(&mut ctx, &mut client, &mut server, client_fd, bytes)?;
+    recv_pure_ack(
+        &mut now,
+        &mut client,
+        &mut server,
+        seq_no + SeqNumber::from(bufsize as u32),
+    )?;
+
+    // Closing one of the descriptors leaves the connection open: close the original descriptor on the client and the
+    // duplicate one on the server.
+    client.tcp_close(client_fd)?;
+    server.tcp_close(server_dup_fd)?;
+    client.rt.poll_scheduler();
+    server.rt.poll_scheduler();
+    crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+    crate::ensure_eq!(server.rt.pop_frame_unchecked().is_none(), true);
+    crate::ensure_eq!(client.tcp_close(client_fd).is_err(), true);
+    crate::ensure_eq!(server.tcp_close(server_dup_fd).is_err(), true);
+    crate::ensure_eq!(server.tcp_max_push_size(server_fd).is_ok(), true);
+    // The closed descriptors no longer refer to the connection, even though it is still open.
+    crate::ensure_eq!(client.tcp_max_push_size(client_fd).is_err(), true);
+    crate::ensure_eq!(client.tcp_dup(client_fd).is_err(), true);
+
+    // The remaining descriptors still refer to the connection.
+    send_recv(
+        &mut ctx,
+        &mut now,
+        &mut server,
+        &mut client,
+        server_fd,
+        client_dup_fd,
+        max_window_size as u16,
+        seq_no + SeqNumber::from(bufsize as u32),
+        cook_buffer(bufsize, None),
+    )?;
+
+    // Closing the last descriptors closes the connection.
+    connection_hangup(&mut ctx, &mut now, &mut server, &mut client, server_fd, client_dup_fd)?;
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Parses a TCP segment out of an outgoing frame, returning its header and payload length.
+fn parse_tcp_segment(bytes: DemiBuffer) -> Result<(TcpHeader, usize)> {
+    let (_, eth2_payload) = Ethernet2Header::parse(bytes)?;
+    let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (tcp_header, tcp_payload) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+    Ok((tcp_header, tcp_payload.len()))
+}
+
+/// Tests zero-window probes. Once the receiver closes its window, the sender should send probes that elicit window
+/// updates, backing off exponentially within the configured bounds, and resume sending once the window reopens.
+#[test]
+pub fn test_zero_window_probes() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let persist_timeout_min: Duration = Duration::from_secs(10);
+    let persist_timeout_max: Duration = Duration::from_secs(40);
+
+    // Setup peers. The receive window of the server fits a single buffer.
+    let server_tcp_config: TcpConfig = TcpConfig::builder()
+        .receive_window_size(bufsize as u16)
+        .window_scale(0)
+        .build();
+    let client_tcp_config: TcpConfig = TcpConfig::builder()
+        .persist_timeout_min(persist_timeout_min)
+        .persist_timeout_max(persist_timeout_max)
+        .build();
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, server_tcp_config);
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, client_tcp_config);
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Fill up the receive window of the server.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    let (tcp_header, _) = parse_tcp_segment(bytes.clone())?;
+    let send_unacked: SeqNumber = tcp_header.seq_num + SeqNumber::from(bufsize as u32);
+    if let Err(e) = server.receive(bytes) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+
+    // The server acknowledges the data and closes its window.
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    let bytes: DemiBuffer = server.rt.pop_frame();
+    let (tcp_header, _) = parse_tcp_segment(bytes.clone())?;
+    crate::ensure_eq!(tcp_header.ack_num, send_unacked);
+    crate::ensure_eq!(tcp_header.window_size, 0);
+    if let Err(e) = client.receive(bytes) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+
+    // Push more data, which should be held back by the closed window.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize / 2, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    client.rt.poll_scheduler();
+    crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+
+    // The sender should probe the window at exponentially increasing intervals, up to the upper bound.
+    let mut probe: Option<DemiBuffer> = None;
+    for timeout in [10, 20, 40, 40].map(Duration::from_secs) {
+        now += timeout - Duration::from_millis(1);
+        client.clock.advance_clock(now);
+        client.rt.poll_scheduler();
+        crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+
+        now += Duration::from_millis(1);
+        client.clock.advance_clock(now);
+        client.rt.poll_scheduler();
+        let bytes: DemiBuffer = client.rt.pop_frame();
+        let (tcp_header, payload_len) = parse_tcp_segment(bytes.clone())?;
+        crate::ensure_eq!(tcp_header.seq_num, send_unacked - SeqNumber::from(1));
+        crate::ensure_eq!(payload_len, 0);
+        probe = Some(bytes);
+    }
+    server.clock.advance_clock(now);
+
+    // A probe makes the server report its window, which is still closed.
+    if let Err(e) = server.receive(probe.take().unwrap()) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    let bytes: DemiBuffer = server.rt.pop_frame();
+    let (tcp_header, _) = parse_tcp_segment(bytes.clone())?;
+    crate::ensure_eq!(tcp_header.ack_num, send_unacked);
+    crate::ensure_eq!(tcp_header.window_size, 0);
+    if let Err(e) = client.receive(bytes) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    client.rt.poll_scheduler();
+    crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+
+    // Drain the receive queue of the server, so that it opens its window again.
+    let mut pop_future = server.tcp_pop(server_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Ok(_)) => {},
+        _ => anyhow::bail!("pop should have completed"),
+    }
+    while server.rt.pop_frame_unchecked().is_some() {}
+
+    // The next probe should find the window open, and the sender should resume.
+    now += persist_timeout_max;
+    client.clock.advance_clock(now);
+    client.rt.poll_scheduler();
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    if let Err(e) = server.receive(bytes) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    let bytes: DemiBuffer = server.rt.pop_frame();
+    let (tcp_header, _) = parse_tcp_segment(bytes.clone())?;
+    crate::ensure_eq!(tcp_header.window_size, bufsize as u16);
+    if let Err(e) = client.receive(bytes) {
+        anyhow::bail!("receive returned error: {:?}", e);
+    }
+    client.rt.poll_scheduler();
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    let (tcp_header, payload_len) = parse_tcp_segment(bytes)?;
+    crate::ensure_eq!(tcp_header.seq_num, send_unacked);
+    crate::ensure_eq!(payload_len, bufsize / 2);
+
+    Ok(())
+}
+
+/// Tests window updates. Once the application drains a receive queue that had closed the window, the receiver should
+/// announce the reopened window right away, so that the sender resumes without waiting for its persist timer.
+#[test]
+pub fn test_window_update_on_pop() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers. The receive window of the server fits a single buffer.
+    let server_tcp_config: TcpConfig = TcpConfig::builder()
+        .receive_window_size(bufsize as u16)
+        .window_scale(0)
+        .build();
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, server_tcp_config);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Fill up the receive window of the server, which acknowledges the data and closes its window.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    let (tcp_header, _) = parse_tcp_segment(bytes.clone())?;
+    let send_unacked: SeqNumber = tcp_header.seq_num + SeqNumber::from(bufsize as u32);
+    server.receive(bytes)?;
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    let bytes: DemiBuffer = server.rt.pop_frame();
+    let (tcp_header, _) = parse_tcp_segment(bytes.clone())?;
+    crate::ensure_eq!(tcp_header.window_size, 0);
+    client.receive(bytes)?;
+
+    // Push more data, which should be held back by the closed window.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize / 2, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    client.rt.poll_scheduler();
+    crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+
+    // Drain the receive queue of the server, which should announce its reopened window right away.
+    let mut pop_future = server.tcp_pop(server_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Ok(_)) => {},
+        _ => anyhow::bail!("pop should have completed"),
+    }
+    let bytes: DemiBuffer = server.rt.pop_frame();
+    let (tcp_header, payload_len) = parse_tcp_segment(bytes.clone())?;
+    crate::ensure_eq!(payload_len, 0);
+    crate::ensure_eq!(tcp_header.ack_num, send_unacked);
+    crate::ensure_eq!(tcp_header.window_size, bufsize as u16);
+    crate::ensure_eq!(server.rt.pop_frame_unchecked().is_none(), true);
+
+    // The client resumes without waiting for the persist timer.
+    client.receive(bytes)?;
+    client.rt.poll_scheduler();
+    let (tcp_header, payload_len) = parse_tcp_segment(client.rt.pop_frame())?;
+    crate::ensure_eq!(tcp_header.seq_num, send_unacked);
+    crate::ensure_eq!(payload_len, bufsize / 2);
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Runs one round of an echo workload, in which the client sends a request of two segments and the server pushes a
+/// response right away, and returns the segments that the server sent in the meantime.
+fn echo_round(ack_hold_timeout: Duration) -> Result<Vec<(TcpHeader, usize)>> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers.
+    let tcp_config: TcpConfig = TcpConfig::builder().ack_hold_timeout(ack_hold_timeout).build();
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+    while server.rt.pop_frame_unchecked().is_some() {}
+
+    // Send the request.
+    for _ in 0..2 {
+        let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+        let bytes: DemiBuffer = client.rt.pop_frame();
+        if let Err(e) = server.receive(bytes) {
+            anyhow::bail!("receive returned error: {:?}", e);
+        }
+    }
+
+    // Push the response.
+    server.rt.poll_scheduler();
+    let mut push_future: PushFuture = server.tcp_push(server_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+
+    // Let any ACK hold expire, well before the delayed ACK and retransmission timers do.
+    now += Duration::from_millis(1);
+    server.clock.advance_clock(now);
+    server.rt.poll_scheduler();
+
+    let mut segments: Vec<(TcpHeader, usize)> = Vec::new();
+    while let Some(bytes) = server.rt.pop_frame_unchecked() {
+        segments.push(parse_tcp_segment(bytes)?);
+    }
+    Ok(segments)
+}
+
+/// Tests if holding a due ACK briefly lets it ride on the response of an echo workload, instead of going out as a
+/// separate pure ACK.
+#[test]
+pub fn test_ack_hold() -> Result<()> {
+    // Without ACK holding, the server ACKs the request right away and then sends the response.
+    let segments: Vec<(TcpHeader, usize)> = echo_round(Duration::ZERO)?;
+    crate::ensure_eq!(segments.len(), 2);
+    crate::ensure_eq!(segments[0].1, 0);
+    crate::ensure_eq!(segments[1].1, 64);
+
+    // With ACK holding, the ACK rides on the response.
+    let segments: Vec<(TcpHeader, usize)> = echo_round(Duration::from_micros(10))?;
+    crate::ensure_eq!(segments.len(), 1);
+    crate::ensure_eq!(segments[0].1, 64);
+    crate::ensure_eq!(segments[0].0.ack, true);
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests if a connection is aborted with ETIMEDOUT once sent data remains unacknowledged for longer than the user
+/// timeout, regardless of how many retransmissions happened in the meantime.
+#[test]
+pub fn test_user_timeout() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let user_timeout: Duration = Duration::from_secs(10);
+
+    // Setup peers.
+    let tcp_config: TcpConfig = TcpConfig::builder().user_timeout(user_timeout).build();
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((_, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Send data into a black hole.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+
+    // The client keeps retransmitting until right before the user timeout expires.
+    let deadline: Instant = now + user_timeout;
+    let mut retransmissions: usize = 0;
+    while now + Duration::from_secs(1) < deadline {
+        now += Duration::from_secs(1);
+        client.clock.advance_clock(now);
+        client.rt.poll_scheduler();
+        while let Some(bytes) = client.rt.pop_frame_unchecked() {
+            let (tcp_header, _) = parse_tcp_segment(bytes)?;
+            crate::ensure_eq!(tcp_header.rst, false);
+            retransmissions += 1;
+        }
+    }
+    crate::ensure_eq!(retransmissions > 0, true);
+    let mut pop_future = client.tcp_pop(client_fd);
+    crate::ensure_eq!(Future::poll(Pin::new(&mut pop_future), &mut ctx).is_pending(), true);
+
+    // Once the user timeout expires, the client resets the connection.
+    now = deadline;
+    client.clock.advance_clock(now);
+    client.rt.poll_scheduler();
+    let mut bytes: DemiBuffer = client.rt.pop_frame();
+    while let Some(next) = client.rt.pop_frame_unchecked() {
+        bytes = next;
+    }
+    let (tcp_header, _) = parse_tcp_segment(bytes)?;
+    crate::ensure_eq!(tcp_header.rst, true);
+
+    // Further operations on the client should fail with ETIMEDOUT, and the queue descriptor should then be released.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ETIMEDOUT => {},
+        _ => anyhow::bail!("push should have failed with ETIMEDOUT"),
+    }
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ETIMEDOUT => {},
+        _ => anyhow::bail!("pop should have failed with ETIMEDOUT"),
+    }
+    let mut pop_future = client.tcp_pop(client_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::EBADF => {},
+        _ => anyhow::bail!("queue descriptor should have been released"),
+    }
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Parses a TCP segment out of an outgoing frame, returning the ECN field of its IPv4 header and its TCP header.
+fn parse_ecn_segment(bytes: DemiBuffer) -> Result<(u8, TcpHeader)> {
+    let (_, eth2_payload) = Ethernet2Header::parse(bytes)?;
+    let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+    let (tcp_header, _) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+    Ok((ipv4_header.get_ecn(), tcp_header))
+}
+
+/// Marks a frame as Congestion Experienced, as a congested router on the path would.
+fn mark_ce(mut bytes: DemiBuffer) -> DemiBuffer {
+    const IPV4_HEADER_OFFSET: usize = 14;
+    bytes[IPV4_HEADER_OFFSET + 1] |= IPV4_ECN_CE;
+    let checksum: u16 = Ipv4Header::compute_checksum(&bytes[IPV4_HEADER_OFFSET..IPV4_HEADER_OFFSET + 20]);
+    bytes[IPV4_HEADER_OFFSET + 10..IPV4_HEADER_OFFSET + 12].copy_from_slice(&checksum.to_be_bytes());
+    bytes
+}
+
+/// Builds a TCP configuration with Explicit Congestion Notification turned on or off.
+fn ecn_tcp_config(ecn_enabled: bool) -> TcpConfig {
+    TcpConfig::builder().ecn_enabled(ecn_enabled).build()
+}
+
+/// Tests Explicit Congestion Notification. Data segments of a connection that negotiated ECN are marked as
+/// ECN-capable, a CE-marked segment is echoed back with ECE until the sender reduces its congestion window and answers
+/// with CWR.
+#[test]
+pub fn test_ecn() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, ecn_tcp_config(true));
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, ecn_tcp_config(true));
+
+    let ((server_fd, addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Data segments are ECN-capable. Mark one as Congestion Experienced on its way to the server.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    let (ecn, tcp_header): (u8, TcpHeader) = parse_ecn_segment(bytes.clone())?;
+    crate::ensure_eq!(ecn, IPV4_ECN_ECT0);
+    crate::ensure_eq!(tcp_header.cwr, false);
+    server.receive(mark_ce(bytes))?;
+    crate::ensure_eq!(server.tcp_stats(server_fd)?.tcp_ecn_ce_events, 1);
+
+    // The server echoes the congestion signal on its ACK, which is not ECN-capable itself.
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    let bytes: DemiBuffer = server.rt.pop_frame();
+    let (ecn, tcp_header): (u8, TcpHeader) = parse_ecn_segment(bytes.clone())?;
+    crate::ensure_eq!(ecn, IPV4_ECN_NOT_ECT);
+    crate::ensure_eq!(tcp_header.ece, true);
+    let cwnd: u32 = client.tcp_info(client_fd)?.cwnd;
+    client.receive(bytes)?;
+
+    // The client halves its congestion window, and says so with CWR on its next data segment. The echo acknowledges
+    // the data that the client sent in slow start, so the window grows by that much before it is halved.
+    crate::ensure_eq!(client.tcp_info(client_fd)?.cwnd, (cwnd + bufsize as u32) / 2);
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    let (ecn, tcp_header): (u8, TcpHeader) = parse_ecn_segment(bytes.clone())?;
+    crate::ensure_eq!(ecn, IPV4_ECN_ECT0);
+    crate::ensure_eq!(tcp_header.cwr, true);
+    server.receive(bytes)?;
+
+    // The server stops echoing once it sees CWR.
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    let (_, tcp_header): (u8, TcpHeader) = parse_ecn_segment(server.rt.pop_frame())?;
+    crate::ensure_eq!(tcp_header.ece, false);
+    crate::ensure_eq!(server.tcp_stats(server_fd)?.tcp_ecn_ce_events, 1);
+
+    Ok(())
+}
+
+/// Tests that Explicit Congestion Notification is only used if both ends ask for it during the handshake.
+#[test]
+pub fn test_ecn_not_negotiated() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, ecn_tcp_config(false));
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, ecn_tcp_config(true));
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Data segments are not ECN-capable, and CE marks are ignored.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    let (ecn, _): (u8, TcpHeader) = parse_ecn_segment(bytes.clone())?;
+    crate::ensure_eq!(ecn, IPV4_ECN_NOT_ECT);
+    server.receive(mark_ce(bytes))?;
+    crate::ensure_eq!(server.tcp_stats(server_fd)?.tcp_ecn_ce_events, 0);
+
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    let (_, tcp_header): (u8, TcpHeader) = parse_ecn_segment(server.rt.pop_frame())?;
+    crate::ensure_eq!(tcp_header.ece, false);
+
+    Ok(())
+}
+
+//=============================================================================
+
+/// Tests black hole detection (RFC 2923). The path silently drops IPv4 datagrams that carry segments larger than the
+/// default floor of 576 bytes, thus the sender keeps halving its MSS on repeated retransmission timeouts until its
+/// segments make it through, and then sticks to that MSS.
+#[test]
+pub fn test_black_hole_detection() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    let mss: usize = client.rt.tcp_config.get_advertised_mss();
+    let min_mss: usize = client.rt.tcp_config.get_pmtud_min_mss();
+    crate::ensure_eq!(min_mss, 576);
+    let path_mtu: usize = min_mss + IPV4_HEADER_MIN_SIZE as usize + MIN_TCP_HEADER_SIZE;
+
+    for round in 0..2 {
+        // Send a full-sized segment.
+        let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(mss, None));
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+
+        // Shuttle segments through the black hole until the server got all data.
+        let mut received: usize = 0;
+        let mut dropped: Vec<usize> = Vec::new();
+        let mut pop_future: PopFuture = server.tcp_pop(server_fd);
+        for _ in 0..64 {
+            client.rt.poll_scheduler();
+            while let Some(bytes) = client.rt.pop_frame_unchecked() {
+                if bytes.len() - ETHERNET2_HEADER_SIZE > path_mtu {
+                    dropped.push(bytes.len());
+                    continue;
+                }
+                server.receive(bytes)?;
+            }
+            while let Poll::Ready(result) = Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+                received += result?.len();
+                pop_future = server.tcp_pop(server_fd);
+            }
+            if received == mss {
+                break;
+            }
+            advance_clock(Some(&mut server), Some(&mut client), &mut now);
+            server.rt.poll_scheduler();
+            while let Some(bytes) = server.rt.pop_frame_unchecked() {
+                client.receive(bytes)?;
+            }
+        }
+        crate::ensure_eq!(received, mss);
+
+        // Only the first round runs into the black hole, afterwards the client sends smaller segments right away.
+        crate::ensure_eq!(dropped.is_empty(), round != 0);
+        // A single retransmission timeout does not lower the MSS, since it is more likely a congestion loss.
+        if round == 0 {
+            crate::ensure_eq!(dropped.len() > 2, true);
+            crate::ensure_eq!(dropped[1], dropped[0]);
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that the internals of a connection report its round-trip time estimates and the data that it retransmitted.
+#[test]
+pub fn test_tcp_info() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Nothing was acknowledged yet, thus there is no RTT estimate.
+    let info: TcpInfo = client.tcp_info(client_fd)?;
+    crate::ensure_eq!(info.state, TcpState::Established);
+    crate::ensure_eq!(info.srtt, None);
+    crate::ensure_eq!(info.rttvar, None);
+    crate::ensure_eq!(info.retransmitted_segments, 0);
+
+    // Send data, which the server acknowledges.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    recv_data(&mut ctx, &mut server, &mut client, server_fd, bytes)?;
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    client.receive(server.rt.pop_frame())?;
+
+    let info: TcpInfo = client.tcp_info(client_fd)?;
+    crate::ensure_eq!(info.srtt.is_some(), true);
+    crate::ensure_eq!(info.rttvar.is_some(), true);
+    crate::ensure_eq!(info.cwnd > 0, true);
+    crate::ensure_eq!(info.retransmitted_segments, 0);
+
+    // Send more data, which gets lost, so that the client has to retransmit it.
+    let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(bufsize, None));
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    client.rt.pop_frame();
+    let mut retransmission: Option<DemiBuffer> = None;
+    for _ in 0..8 {
+        advance_clock(Some(&mut server), Some(&mut client), &mut now);
+        client.rt.poll_scheduler();
+        if let Some(bytes) = client.rt.pop_frame_unchecked() {
+            retransmission = Some(bytes);
+            break;
+        }
+    }
+    match retransmission {
+        Some(bytes) => recv_data(&mut ctx, &mut server, &mut client, server_fd, bytes)?,
+        None => anyhow::bail!("client should have retransmitted lost data"),
+    }
+
+    let info: TcpInfo = client.tcp_info(client_fd)?;
+    crate::ensure_eq!(info.retransmitted_segments, 1);
+    crate::ensure_eq!(info.retransmitted_bytes, bufsize as u64);
+    crate::ensure_eq!(info.srtt.is_some(), true);
+
+    Ok(())
+}
+
+/// Connects `client` to a socket that `server` listens on at `listen_addr`, shuttling frames between both until the
+/// connection is established. Returns the queue descriptors of the server and client ends of the connection.
+fn connect_peer(
+    ctx: &mut Context,
+    now: &mut Instant,
+    server: &mut Engine,
+    client: &mut Engine,
+    listen_addr: SocketAddrV4,
+) -> Result<(QDesc, QDesc)> {
+    let listen_fd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_fd, listen_addr)?;
+    server.tcp_listen(listen_fd, 1)?;
+    let mut accept_future: AcceptFuture = server.tcp_accept(listen_fd)?;
+    let client_fd: QDesc = client.tcp_socket()?;
+    let mut connect_future: ConnectFuture = client.tcp_connect(client_fd, listen_addr);
+
+    for _ in 0..4 {
+        client.rt.poll_scheduler();
+        while let Some(bytes) = client.rt.pop_frame_unchecked() {
+            server.receive(bytes)?;
+        }
+        server.rt.poll_scheduler();
+        while let Some(bytes) = server.rt.pop_frame_unchecked() {
+            client.receive(bytes)?;
+        }
+        advance_clock(Some(server), Some(client), now);
+    }
+
+    let server_fd: QDesc = match Future::poll(Pin::new(&mut accept_future), ctx) {
+        Poll::Ready(Ok((server_fd, _))) => server_fd,
+        _ => anyhow::bail!("accept should have completed"),
+    };
+    match Future::poll(Pin::new(&mut connect_future), ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    }
+
+    Ok((server_fd, client_fd))
+}
+
+/// Sends `rounds` segments from `client` to `server` one at a time, losing the first transmission of every `loss_every`
+/// segment, if set, so that the client has to retransmit it.
+fn send_with_loss(
+    ctx: &mut Context,
+    now: &mut Instant,
+    server: &mut Engine,
+    client: &mut Engine,
+    server_fd: QDesc,
+    client_fd: QDesc,
+    rounds: usize,
+    loss_every: Option<usize>,
+) -> Result<()> {
+    for round in 0..rounds {
+        let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(64, None));
+        match Future::poll(Pin::new(&mut push_future), ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+        let mut bytes: Option<DemiBuffer> = Some(client.rt.pop_frame());
+        if loss_every.is_some_and(|n| round % n == n - 1) {
+            bytes = None;
+            for _ in 0..8 {
+                advance_clock(Some(server), Some(client), now);
+                client.rt.poll_scheduler();
+                if let Some(retransmission) = client.rt.pop_frame_unchecked() {
+                    bytes = Some(retransmission);
+                    break;
+                }
+            }
+        }
+        match bytes {
+            Some(bytes) => recv_data(ctx, server, client, server_fd, bytes)?,
+            None => anyhow::bail!("client should have retransmitted lost data"),
+        }
+        advance_clock(Some(server), Some(client), now);
+        server.rt.poll_scheduler();
+        client.receive(server.rt.pop_frame())?;
+    }
+
+    Ok(())
+}
+
+/// Tests that the telemetry on remote hosts tells a host on a lossy path apart from a healthy one.
+#[test]
+pub fn test_peer_telemetry() -> Result<()> {
+    const ROUNDS: usize = 8;
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Setup peers.
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let mut healthy: Engine = test_helpers::new_bob2(now);
+    let mut lossy: Engine = test_helpers::new_carrie2(now);
+    let healthy_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let lossy_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::CARRIE_IPV4, 80);
+
+    let (healthy_server_fd, healthy_client_fd): (QDesc, QDesc) =
+        connect_peer(&mut ctx, &mut now, &mut healthy, &mut client, healthy_addr)?;
+    let (lossy_server_fd, lossy_client_fd): (QDesc, QDesc) =
+        connect_peer(&mut ctx, &mut now, &mut lossy, &mut client, lossy_addr)?;
+
+    send_with_loss(
+        &mut ctx,
+        &mut now,
+        &mut healthy,
+        &mut client,
+        healthy_server_fd,
+        healthy_client_fd,
+        ROUNDS,
+        None,
+    )?;
+    send_with_loss(
+        &mut ctx,
+        &mut now,
+        &mut lossy,
+        &mut client,
+        lossy_server_fd,
+        lossy_client_fd,
+        ROUNDS,
+        Some(2),
+    )?;
+
+    // A connection attempt to the lossy host that never gets through.
+    let fd: QDesc = client.tcp_socket()?;
+    let mut connect_future: ConnectFuture = client.tcp_connect(fd, SocketAddrV4::new(test_helpers::CARRIE_IPV4, 81));
+    let mut timed_out: bool = false;
+    for _ in 0..32 {
+        client.rt.poll_scheduler();
+        while client.rt.pop_frame_unchecked().is_some() {}
+        match Future::poll(Pin::new(&mut connect_future), &mut ctx) {
+            Poll::Ready(Err(e)) if e.errno == libc::ETIMEDOUT => {
+                timed_out = true;
+                break;
+            },
+            Poll::Pending => advance_clock(None, Some(&mut client), &mut now),
+            _ => anyhow::bail!("connect should have timed out"),
+        }
+    }
+    crate::ensure_eq!(timed_out, true);
+
+    let telemetry: Vec<(Ipv4Addr, PeerStats)> = client.tcp_peer_telemetry();
+    crate::ensure_eq!(telemetry.len(), 2);
+    let stats_of = |remote: Ipv4Addr| -> Result<PeerStats> {
+        match telemetry.iter().find(|(other, _)| *other == remote) {
+            Some((_, stats)) => Ok(*stats),
+            None => anyhow::bail!("there should be telemetry on {:?}", remote),
+        }
+    };
+    let healthy_stats: PeerStats = stats_of(test_helpers::BOB_IPV4)?;
+    let lossy_stats: PeerStats = stats_of(test_helpers::CARRIE_IPV4)?;
+
+    crate::ensure_eq!(healthy_stats.bytes_sent, (ROUNDS * 64) as u64);
+    crate::ensure_eq!(healthy_stats.bytes_retransmitted, 0);
+    crate::ensure_eq!(healthy_stats.connection_failures, 0);
+    crate::ensure_eq!(healthy_stats.srtt.is_some(), true);
+
+    crate::ensure_eq!(lossy_stats.bytes_sent, (ROUNDS * 64) as u64);
+    crate::ensure_eq!(lossy_stats.bytes_retransmitted, (ROUNDS / 2 * 64) as u64);
+    crate::ensure_eq!(lossy_stats.connection_failures, 1);
+    crate::ensure_eq!(lossy_stats.srtt.is_some(), true);
+    crate::ensure_eq!(lossy_stats.retransmit_ratio() > healthy_stats.retransmit_ratio(), true);
+
+    Ok(())
+}
+
+/// Tests that small pushes that queue up while the receive window of our peer is full are coalesced into full-sized
+/// segments.
+#[test]
+pub fn test_coalesce_small_pushes() -> Result<()> {
+    const NUM_PUSHES: usize = 100;
+    const PUSH_SIZE: usize = 100;
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers. The receive window of the server fits all pushes.
+    let window_size: usize = 16 * 1024;
+    let server_tcp_config: TcpConfig = TcpConfig::builder()
+        .receive_window_size(window_size as u16)
+        .window_scale(0)
+        .build();
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, server_tcp_config);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    let mss: usize = client.tcp_mss(client_fd)?;
+
+    // Fill up the receive window of the server.
+    let mut in_flight: Vec<DemiBuffer> = Vec::new();
+    let mut unused_window: usize = window_size;
+    while unused_window > 0 {
+        let size: usize = cmp::min(unused_window, mss);
+        let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(size, Some(0)));
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+        in_flight.push(client.rt.pop_frame());
+        unused_window -= size;
+    }
+
+    // Push small buffers, which queue up.
+    let mut expected: Vec<u8> = Vec::with_capacity(NUM_PUSHES * PUSH_SIZE);
+    for i in 0..NUM_PUSHES {
+        let buf: DemiBuffer = cook_buffer(PUSH_SIZE, Some(i as u8));
+        expected.extend_from_slice(&buf[..]);
+        let mut push_future: PushFuture = client.tcp_push(client_fd, buf);
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+    }
+    client.rt.poll_scheduler();
+    crate::ensure_eq!(client.rt.pop_frame_unchecked().is_none(), true);
+
+    // Deliver the data that filled up the window, and drain it, which opens up the window again.
+    let mut received: Vec<u8> = Vec::with_capacity(NUM_PUSHES * PUSH_SIZE);
+    let mut pop_future: PopFuture = server.tcp_pop(server_fd);
+    for bytes in in_flight.drain(..) {
+        server.receive(bytes)?;
+    }
+    while let Poll::Ready(result) = Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        crate::ensure_eq!(result?.iter().all(|x| *x == 0), true);
+        pop_future = server.tcp_pop(server_fd);
+    }
+
+    // Shuttle segments until the server got all data.
+    let mut num_segments: usize = 0;
+    for _ in 0..16 {
+        advance_clock(Some(&mut server), Some(&mut client), &mut now);
+        server.rt.poll_scheduler();
+        while let Some(bytes) = server.rt.pop_frame_unchecked() {
+            client.receive(bytes)?;
+        }
+        client.rt.poll_scheduler();
+        while let Some(bytes) = client.rt.pop_frame_unchecked() {
+            num_segments += 1;
+            server.receive(bytes)?;
+        }
+        while let Poll::Ready(result) = Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+            received.extend_from_slice(&result?[..]);
+            pop_future = server.tcp_pop(server_fd);
+        }
+        if received.len() == expected.len() {
+            break;
+        }
+    }
+
+    // The data should arrive in order, in as few segments as it takes.
+    crate::ensure_eq!(received, expected);
+    crate::ensure_eq!(num_segments, (NUM_PUSHES * PUSH_SIZE + mss - 1) / mss);
+
+    Ok(())
+}
+
+/// Tests NAT traversal keep-alives. An idle connection should send a probe that carries a single null byte at an old
+/// sequence number once every keep-alive interval, and the peer should acknowledge the probe without delivering its
+/// byte.
+#[test]
+pub fn test_nat_traversal_keepalive() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let interval: Duration = Duration::from_secs(60);
+
+    // Setup peers.
+    let client_tcp_config: TcpConfig = TcpConfig::builder().nat_traversal_keepalive(true).build();
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, client_tcp_config);
+
+    let ((server_fd, _), _): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    let mut pop_future: PopFuture = server.tcp_pop(server_fd);
+
+    // Nothing should be sent until the connection has been idle for a whole interval. The client sent its last
+    // segment of the handshake a bit before the connection was set up, and it hears back from the server right away
+    // after each probe.
+    let mut idle_since: Instant = now;
+    for i in 0..3 {
+        let bytes: DemiBuffer = loop {
+            advance_clock(Some(&mut server), Some(&mut client), &mut now);
+            client.rt.poll_scheduler();
+            if let Some(bytes) = client.rt.pop_frame_unchecked() {
+                break bytes;
+            }
+            crate::ensure_eq!(now - idle_since < interval, true);
+        };
+        if i > 0 {
+            crate::ensure_eq!(now - idle_since, interval);
+        }
+
+        // The probe should carry a single null byte that our peer already acknowledged.
+        let (_, eth2_payload) = Ethernet2Header::parse(bytes.clone())?;
+        let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
+        let (tcp_header, tcp_payload) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        crate::ensure_eq!(&tcp_payload[..], &[0u8][..]);
+        let probe_seq_num: SeqNumber = tcp_header.seq_num;
+
+        // The server should acknowledge the probe right away, without delivering its byte.
+        server.receive(bytes)?;
+        server.rt.poll_scheduler();
+        let bytes: DemiBuffer = server.rt.pop_frame();
+        let (tcp_header, len) = parse_tcp_segment(bytes.clone())?;
+        crate::ensure_eq!(tcp_header.ack_num, probe_seq_num + SeqNumber::from(1));
+        crate::ensure_eq!(len, 0);
+        crate::ensure_eq!(Future::poll(Pin::new(&mut pop_future), &mut ctx).is_pending(), true);
+        client.receive(bytes)?;
+        idle_since = now;
+    }
+
+    Ok(())
+}
+
+/// Builds the configuration of a server whose receive window fits a batch of segments, and that acknowledges them
+/// right away.
+fn gro_tcp_config(window_size: u16) -> TcpConfig {
+    TcpConfig::builder()
+        .receive_window_size(window_size)
+        .window_scale(0)
+        .ack_delay_timeout(Duration::ZERO)
+        .build()
+}
+
+/// Pushes a window worth of full-sized segments from a client to a server, and collects them in a batch.
+fn push_batch(ctx: &mut Context, client: &mut Engine, client_fd: QDesc, window_size: usize, stamp: u8) -> ReceiveBatch {
+    let mss: usize = client.tcp_mss(client_fd).unwrap();
+    let mut batch: ReceiveBatch = ReceiveBatch::new();
+    let mut unused_window: usize = window_size;
+    while unused_window > 0 {
+        let size: usize = cmp::min(unused_window, mss);
+        let buf: DemiBuffer = cook_buffer(size, Some(stamp.wrapping_add(batch.len() as u8)));
+        let mut push_future: PushFuture = client.tcp_push(client_fd, buf);
+        assert!(Future::poll(Pin::new(&mut push_future), ctx).is_ready());
+        batch.push(client.rt.pop_frame());
+        unused_window -= size;
+    }
+    batch
+}
+
+/// Tests receive coalescing. Segments that arrive in the same batch should be merged, and all data should be delivered
+/// in order.
+#[test]
+pub fn test_receive_coalescing() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let window_size: usize = 16 * 1024;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, gro_tcp_config(window_size as u16));
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    let mss: usize = client.tcp_mss(client_fd)?;
+
+    // Merge segments, four at a time.
+    let batch: ReceiveBatch = push_batch(&mut ctx, &mut client, client_fd, window_size, 0);
+    let num_segments: usize = batch.len();
+    let first_seq_num: SeqNumber = parse_tcp_segment(batch[0].clone())?.0.seq_num;
+    let mut expected: Vec<u8> = Vec::with_capacity(window_size);
+    for bytes in batch.iter() {
+        let (_, len): (TcpHeader, usize) = parse_tcp_segment(bytes.clone())?;
+        expected.extend_from_slice(&bytes[bytes.len() - len..]);
+    }
+    let batch: ReceiveBatch = ReceiveCoalescer::new(4 * mss, false).coalesce(batch);
+    crate::ensure_eq!(batch.len(), (num_segments + 3) / 4);
+
+    // The server should get all data in order, and acknowledge it.
+    let mut received: Vec<u8> = Vec::with_capacity(window_size);
+    let mut pop_future: PopFuture = server.tcp_pop(server_fd);
+    for bytes in batch {
+        server.receive(bytes)?;
+        while let Poll::Ready(result) = Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+            received.extend_from_slice(&result?[..]);
+            pop_future = server.tcp_pop(server_fd);
+        }
+    }
+    crate::ensure_eq!(received, expected);
+    server.rt.poll_scheduler();
+    let mut ack_num: Option<SeqNumber> = None;
+    while let Some(bytes) = server.rt.pop_frame_unchecked() {
+        ack_num = Some(parse_tcp_segment(bytes)?.0.ack_num);
+    }
+    crate::ensure_eq!(ack_num, Some(first_seq_num + SeqNumber::from(window_size as u32)));
+
+    Ok(())
+}
+
+/// Tests that a connection buffers at most as many out-of-order segments as configured, and counts the ones that it
+/// drops.
+#[test]
+pub fn test_max_out_of_order_segments() -> Result<()> {
+    const NUM_SEGMENTS: usize = 100;
+    const MAX_OUT_OF_ORDER_SEGMENTS: usize = 10;
+    const SEGMENT_SIZE: usize = 10;
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let server_tcp_config: TcpConfig = TcpConfig::builder()
+        .max_out_of_order_segments(MAX_OUT_OF_ORDER_SEGMENTS)
+        .build();
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, server_tcp_config);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send one segment more than the out-of-order ones, which gets lost at first.
+    let mut frames: Vec<DemiBuffer> = Vec::with_capacity(NUM_SEGMENTS + 1);
+    for i in 0..=NUM_SEGMENTS {
+        let mut push_future: PushFuture = client.tcp_push(client_fd, cook_buffer(SEGMENT_SIZE, Some(i as u8)));
+        match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("push should have completed successfully"),
+        }
+        frames.push(client.rt.pop_frame());
+    }
+    let first_seq_num: SeqNumber = parse_tcp_segment(frames[0].clone())?.0.seq_num;
+
+    // The server buffers the first out-of-order segments, and drops the rest.
+    for bytes in frames.drain(1..) {
+        server.receive(bytes)?;
+    }
+    let dropped: u64 = (NUM_SEGMENTS - MAX_OUT_OF_ORDER_SEGMENTS) as u64;
+    crate::ensure_eq!(server.tcp_stats(server_fd)?.dropped_ooo_segments, dropped);
+    while server.rt.pop_frame_unchecked().is_some() {}
+
+    // Once the lost segment arrives, the buffered segments become in order, and no more.
+    server.receive(frames.remove(0))?;
+    let mut received: usize = 0;
+    let mut pop_future: PopFuture = server.tcp_pop(server_fd);
+    while let Poll::Ready(result) = Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        received += result?.len();
+        pop_future = server.tcp_pop(server_fd);
+    }
+    crate::ensure_eq!(received, (MAX_OUT_OF_ORDER_SEGMENTS + 1) * SEGMENT_SIZE);
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    server.rt.poll_scheduler();
+    let mut ack_num: Option<SeqNumber> = None;
+    while let Some(bytes) = server.rt.pop_frame_unchecked() {
+        ack_num = Some(parse_tcp_segment(bytes)?.0.ack_num);
+    }
+    let expected: u32 = ((MAX_OUT_OF_ORDER_SEGMENTS + 1) * SEGMENT_SIZE) as u32;
+    crate::ensure_eq!(ack_num, Some(first_seq_num + SeqNumber::from(expected)));
+
+    Ok(())
+}
+
+/// Sets up a connection for the receive coalescing benchmarks.
+fn setup_gro_bench() -> (Context<'static>, Engine, Engine, QDesc, QDesc) {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, gro_tcp_config(u16::MAX));
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let ((server_fd, _), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, 80, listen_addr).unwrap();
+    (ctx, server, client, server_fd, client_fd)
+}
+
+/// Delivers batches of segments to a server, merging them first if `max_size` is non-zero.
+fn bench_receive_batch(b: &mut Bencher, max_size: usize) {
+    let (mut ctx, mut server, mut client, server_fd, client_fd): (Context, Engine, Engine, QDesc, QDesc) =
+        setup_gro_bench();
+    let coalescer: ReceiveCoalescer = ReceiveCoalescer::new(max_size, false);
+    let window_size: usize = 32 * 1024;
+    b.bytes = window_size as u64;
+    b.iter(|| {
+        let batch: ReceiveBatch = coalescer.coalesce(push_batch(&mut ctx, &mut client, client_fd, window_size, 0));
+        let mut pop_future: PopFuture = server.tcp_pop(server_fd);
+        for bytes in batch {
+            server.receive(bytes).unwrap();
+            while let Poll::Ready(result) = Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+                black_box(result.unwrap());
+                pop_future = server.tcp_pop(server_fd);
+            }
+        }
+        server.rt.poll_scheduler();
+        while let Some(bytes) = server.rt.pop_frame_unchecked() {
+            client.receive(bytes).unwrap();
+        }
+    });
+}
+
+#[bench]
+fn bench_receive_batch_without_gro(b: &mut Bencher) {
+    bench_receive_batch(b, 0);
+}
+
+#[bench]
+fn bench_receive_batch_with_gro(b: &mut Bencher) {
+    bench_receive_batch(b, MAX_GRO_SIZE);
+}
@@ -8,17 +8,25 @@ use crate::{
                 EtherType2,
                 Ethernet2Header,
             },
-            ipv4::Ipv4Header,
+            ipv4::{
+                Ipv4Header,
+                RouteEntry,
+            },
             tcp::{
                 operations::{
                     AcceptFuture,
                     ConnectFuture,
+                    PopFuture,
+                    PushFuture,
                 },
                 segment::{
                     TcpHeader,
+                    TcpOptions2,
                     TcpSegment,
                 },
+                SegmentAuthenticator,
                 SeqNumber,
+                TcpMd5Signature,
             },
         },
         test_helpers::{
@@ -27,13 +35,24 @@ use crate::{
         },
     },
     runtime::{
+        fail::Fail,
         memory::DemiBuffer,
         network::{
-            consts::RECEIVE_BATCH_SIZE,
-            types::MacAddress,
+            config::{
+                BacklogPolicy,
+                TcpConfig,
+            },
+            types::{
+                ConnEvent,
+                HandshakeStats,
+                MacAddress,
+            },
             PacketBuf,
         },
         QDesc,
+        QType,
+        QueueInfo,
+        QueueState,
     },
 };
 use ::anyhow::Result;
@@ -43,12 +62,14 @@ use ::libc::{
     ETIMEDOUT,
 };
 use ::std::{
+    collections::HashMap,
     future::Future,
     net::{
         Ipv4Addr,
         SocketAddrV4,
     },
     pin::Pin,
+    rc::Rc,
     task::{
         Context,
         Poll,
@@ -72,7 +93,7 @@ fn test_connection_timeout() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup client.
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
     let nretries: usize = client.rt.tcp_config.get_handshake_retries();
     let timeout: Duration = client.rt.tcp_config.get_handshake_timeout();
 
@@ -80,7 +101,7 @@ fn test_connection_timeout() -> Result<()> {
     advance_clock(None, Some(&mut client), &mut now);
 
     // Client: SYN_SENT state at T(1).
-    let (_, mut connect_future, bytes): (QDesc, ConnectFuture<RECEIVE_BATCH_SIZE>, DemiBuffer) =
+    let (_, mut connect_future, bytes): (QDesc, ConnectFuture, DemiBuffer) =
         connection_setup_listen_syn_sent(&mut client, listen_addr)?;
 
     // Sanity check packet.
@@ -119,18 +140,17 @@ fn test_refuse_connection_early_rst() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup peers.
-    let mut server: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
 
     // Server: LISTEN state at T(0).
-    let _: AcceptFuture<RECEIVE_BATCH_SIZE> = connection_setup_closed_listen(&mut server, listen_addr)?;
+    let _: AcceptFuture = connection_setup_closed_listen(&mut server, listen_addr)?;
 
     // T(0) -> T(1)
     advance_clock(Some(&mut server), Some(&mut client), &mut now);
 
     // Client: SYN_SENT state at T(1).
-    let (_, _, bytes): (QDesc, ConnectFuture<RECEIVE_BATCH_SIZE>, DemiBuffer) =
-        connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let (_, _, bytes): (QDesc, ConnectFuture, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
 
     // Temper packet.
     let (eth2_header, ipv4_header, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
@@ -187,18 +207,17 @@ fn test_refuse_connection_early_ack() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup peers.
-    let mut server: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
 
     // Server: LISTEN state at T(0).
-    let _: AcceptFuture<RECEIVE_BATCH_SIZE> = connection_setup_closed_listen(&mut server, listen_addr)?;
+    let _: AcceptFuture = connection_setup_closed_listen(&mut server, listen_addr)?;
 
     // T(0) -> T(1)
     advance_clock(Some(&mut server), Some(&mut client), &mut now);
 
     // Client: SYN_SENT state at T(1).
-    let (_, _, bytes): (QDesc, ConnectFuture<RECEIVE_BATCH_SIZE>, DemiBuffer) =
-        connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let (_, _, bytes): (QDesc, ConnectFuture, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
 
     // Temper packet.
     let (eth2_header, ipv4_header, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
@@ -255,18 +274,17 @@ fn test_refuse_connection_missing_syn() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup peers.
-    let mut server: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
 
     // Server: LISTEN state at T(0).
-    let _: AcceptFuture<RECEIVE_BATCH_SIZE> = connection_setup_closed_listen(&mut server, listen_addr)?;
+    let _: AcceptFuture = connection_setup_closed_listen(&mut server, listen_addr)?;
 
     // T(0) -> T(1)
     advance_clock(Some(&mut server), Some(&mut client), &mut now);
 
     // Client: SYN_SENT state at T(1).
-    let (_, _, bytes): (QDesc, ConnectFuture<RECEIVE_BATCH_SIZE>, DemiBuffer) =
-        connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let (_, _, bytes): (QDesc, ConnectFuture, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
 
     // Sanity check packet.
     check_packet_pure_syn(
@@ -322,8 +340,70 @@ fn test_refuse_connection_missing_syn() -> Result<()> {
 
 //=============================================================================
 
+/// Tests that segments with illegal combinations of flags are dropped and counted, without a RST in response.
+#[test]
+fn test_drop_malformed_segments() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let _: AcceptFuture = connection_setup_closed_listen(&mut server, listen_addr)?;
+    let (_, _, bytes): (QDesc, ConnectFuture, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+
+    // Tamper the SYN into SYN+FIN, SYN+RST, a segment without flags, and a FIN for a connection that does not exist.
+    let tampers: [fn(&mut TcpHeader); 4] = [
+        |tcp_hdr| tcp_hdr.fin = true,
+        |tcp_hdr| tcp_hdr.rst = true,
+        |tcp_hdr| tcp_hdr.syn = false,
+        |tcp_hdr| {
+            tcp_hdr.syn = false;
+            tcp_hdr.fin = true;
+        },
+    ];
+    for tamper in tampers {
+        let (ethernet2_hdr, ipv4_hdr, mut tcp_hdr): (Ethernet2Header, Ipv4Header, TcpHeader) =
+            extract_headers(bytes.clone())?;
+        tamper(&mut tcp_hdr);
+        let buf: DemiBuffer = serialize_segment(TcpSegment {
+            ethernet2_hdr,
+            ipv4_hdr,
+            tcp_hdr,
+            data: None,
+            tx_checksum_offload: false,
+        })?;
+        match server.receive(buf) {
+            Err(error) if error.errno == EBADMSG => {},
+            _ => anyhow::bail!("malformed segment should have been dropped"),
+        };
+        server.rt.poll_scheduler();
+        crate::ensure_eq!(server.rt.pop_frame_unchecked().is_none(), true);
+    }
+    crate::ensure_eq!(server.tcp_malformed_segments(), 4);
+
+    // The original SYN still gets through.
+    let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+    check_packet_syn_ack(
+        bytes,
+        test_helpers::BOB_MAC,
+        test_helpers::ALICE_MAC,
+        test_helpers::BOB_IPV4,
+        test_helpers::ALICE_IPV4,
+        listen_port,
+    )?;
+    crate::ensure_eq!(server.tcp_malformed_segments(), 4);
+
+    Ok(())
+}
+
+//=============================================================================
+
 /// Extracts headers of a TCP packet.
-fn extract_headers(bytes: DemiBuffer) -> Result<(Ethernet2Header, Ipv4Header, TcpHeader)> {
+pub fn extract_headers(bytes: DemiBuffer) -> Result<(Ethernet2Header, Ipv4Header, TcpHeader)> {
     let (eth2_header, eth2_payload) = Ethernet2Header::parse(bytes)?;
     let (ipv4_header, ipv4_payload) = Ipv4Header::parse(eth2_payload)?;
     let (tcp_header, _) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
@@ -334,7 +414,7 @@ fn extract_headers(bytes: DemiBuffer) -> Result<(Ethernet2Header, Ipv4Header, Tc
 //=============================================================================
 
 /// Serializes a TCP segment.
-fn serialize_segment(pkt: TcpSegment) -> Result<DemiBuffer> {
+pub fn serialize_segment(pkt: TcpSegment) -> Result<DemiBuffer> {
     let header_size: usize = pkt.header_size();
     let body_size: usize = pkt.body_size();
     let mut buf = DemiBuffer::new((header_size + body_size) as u16);
@@ -348,16 +428,16 @@ fn serialize_segment(pkt: TcpSegment) -> Result<DemiBuffer> {
 //=============================================================================
 
 /// Triggers LISTEN -> SYN_SENT state transition.
-fn connection_setup_listen_syn_sent<const N: usize>(
-    client: &mut Engine<N>,
+fn connection_setup_listen_syn_sent(
+    client: &mut Engine,
     listen_addr: SocketAddrV4,
-) -> Result<(QDesc, ConnectFuture<N>, DemiBuffer)> {
+) -> Result<(QDesc, ConnectFuture, DemiBuffer)> {
     // Issue CONNECT operation.
     let client_fd: QDesc = match client.tcp_socket() {
         Ok(fd) => fd,
         Err(e) => anyhow::bail!("client tcp socket returned error: {:?}", e),
     };
-    let connect_future: ConnectFuture<N> = client.tcp_connect(client_fd, listen_addr);
+    let connect_future: ConnectFuture = client.tcp_connect(client_fd, listen_addr);
 
     // SYN_SENT state.
     client.rt.poll_scheduler();
@@ -367,10 +447,7 @@ fn connection_setup_listen_syn_sent<const N: usize>(
 }
 
 /// Triggers CLOSED -> LISTEN state transition.
-fn connection_setup_closed_listen<const N: usize>(
-    server: &mut Engine<N>,
-    listen_addr: SocketAddrV4,
-) -> Result<AcceptFuture<N>> {
+fn connection_setup_closed_listen(server: &mut Engine, listen_addr: SocketAddrV4) -> Result<AcceptFuture> {
     // Issue ACCEPT operation.
     let socket_fd: QDesc = match server.tcp_socket() {
         Ok(fd) => fd,
@@ -382,7 +459,10 @@ fn connection_setup_closed_listen<const N: usize>(
     if let Err(e) = server.tcp_listen(socket_fd, 1) {
         anyhow::bail!("server listen returned an error: {:?}", e);
     }
-    let accept_future: AcceptFuture<N> = server.tcp_accept(socket_fd);
+    let accept_future: AcceptFuture = match server.tcp_accept(socket_fd) {
+        Ok(future) => future,
+        Err(e) => anyhow::bail!("server accept returned an error: {:?}", e),
+    };
 
     // LISTEN state.
     server.rt.poll_scheduler();
@@ -391,7 +471,7 @@ fn connection_setup_closed_listen<const N: usize>(
 }
 
 /// Triggers LISTEN -> SYN_RCVD state transition.
-fn connection_setup_listen_syn_rcvd<const N: usize>(server: &mut Engine<N>, bytes: DemiBuffer) -> Result<DemiBuffer> {
+fn connection_setup_listen_syn_rcvd(server: &mut Engine, bytes: DemiBuffer) -> Result<DemiBuffer> {
     // SYN_RCVD state.
     server.receive(bytes).unwrap();
     server.rt.poll_scheduler();
@@ -399,17 +479,14 @@ fn connection_setup_listen_syn_rcvd<const N: usize>(server: &mut Engine<N>, byte
 }
 
 /// Triggers SYN_SENT -> ESTABLISHED state transition.
-fn connection_setup_syn_sent_established<const N: usize>(
-    client: &mut Engine<N>,
-    bytes: DemiBuffer,
-) -> Result<DemiBuffer> {
+fn connection_setup_syn_sent_established(client: &mut Engine, bytes: DemiBuffer) -> Result<DemiBuffer> {
     client.receive(bytes).unwrap();
     client.rt.poll_scheduler();
     Ok(client.rt.pop_frame())
 }
 
 /// Triggers SYN_RCVD -> ESTABLISHED state transition.
-fn connection_setup_sync_rcvd_established<const N: usize>(server: &mut Engine<N>, bytes: DemiBuffer) -> Result<()> {
+fn connection_setup_sync_rcvd_established(server: &mut Engine, bytes: DemiBuffer) -> Result<()> {
     server.receive(bytes).unwrap();
     server.rt.poll_scheduler();
     Ok(())
@@ -495,11 +572,7 @@ fn check_packet_pure_ack_on_syn_ack(
 }
 
 /// Advances clock by one second.
-pub fn advance_clock<const N: usize>(
-    server: Option<&mut Engine<N>>,
-    client: Option<&mut Engine<N>>,
-    now: &mut Instant,
-) {
+pub fn advance_clock(server: Option<&mut Engine>, client: Option<&mut Engine>, now: &mut Instant) {
     *now += Duration::from_secs(1);
     if let Some(server) = server {
         server.clock.advance_clock(*now);
@@ -510,22 +583,22 @@ pub fn advance_clock<const N: usize>(
 }
 
 /// Runs 3-way connection setup.
-pub fn connection_setup<const N: usize>(
+pub fn connection_setup(
     ctx: &mut Context,
     now: &mut Instant,
-    server: &mut Engine<N>,
-    client: &mut Engine<N>,
+    server: &mut Engine,
+    client: &mut Engine,
     listen_port: u16,
     listen_addr: SocketAddrV4,
 ) -> Result<((QDesc, SocketAddrV4), QDesc)> {
     // Server: LISTEN state at T(0).
-    let mut accept_future: AcceptFuture<N> = connection_setup_closed_listen(server, listen_addr)?;
+    let mut accept_future: AcceptFuture = connection_setup_closed_listen(server, listen_addr)?;
 
     // T(0) -> T(1)
     advance_clock(Some(server), Some(client), now);
 
     // Client: SYN_SENT state at T(1).
-    let (client_fd, mut connect_future, mut bytes): (QDesc, ConnectFuture<N>, DemiBuffer) =
+    let (client_fd, mut connect_future, mut bytes): (QDesc, ConnectFuture, DemiBuffer) =
         connection_setup_listen_syn_sent(client, listen_addr)?;
 
     // Sanity check packet.
@@ -598,11 +671,1015 @@ fn test_good_connect() -> Result<()> {
     let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
 
     // Setup peers.
-    let mut server: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
-    let mut client: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((_, _), _): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    Ok(())
+}
+
+/// Tests if the handshakes that complete on a server are counted, along with their latency, and if the counters reset.
+#[test]
+fn test_handshake_stats() -> Result<()> {
+    const NUM_CONNECTIONS: u16 = 10;
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    for i in 0..NUM_CONNECTIONS {
+        let listen_port: u16 = 80 + i;
+        let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    }
+
+    // The server receives each ACK two seconds after the SYN.
+    let stats: HandshakeStats = server.tcp_handshake_stats();
+    crate::ensure_eq!(stats.syn_received, NUM_CONNECTIONS as u64);
+    crate::ensure_eq!(stats.syn_ack_sent, NUM_CONNECTIONS as u64);
+    crate::ensure_eq!(stats.handshakes_completed, NUM_CONNECTIONS as u64);
+    crate::ensure_eq!(stats.handshakes_failed, 0);
+    crate::ensure_eq!(stats.handshake_timeout_count, 0);
+    crate::ensure_eq!(stats.avg_handshake_latency_us > 0, true);
+    crate::ensure_eq!(
+        stats.avg_handshake_latency_us,
+        Duration::from_secs(2).as_micros() as u64
+    );
+
+    // Handshakes that the client initiated are not counted on its side.
+    crate::ensure_eq!(client.tcp_handshake_stats(), HandshakeStats::default());
+
+    server.reset_tcp_handshake_stats();
+    crate::ensure_eq!(server.tcp_handshake_stats(), HandshakeStats::default());
+
+    Ok(())
+}
+
+/// Tests if establishing a connection traces the state transitions of both ends, in the order in which they happen.
+#[cfg(feature = "tracing")]
+#[test]
+fn test_connect_traces_state_transitions() -> Result<()> {
+    use ::std::{
+        io,
+        sync::{
+            Arc,
+            Mutex,
+        },
+    };
+
+    /// Writer that captures the output of the subscriber.
+    struct CaptureWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CaptureWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let output: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+    let writer: Arc<Mutex<Vec<u8>>> = output.clone();
+    let subscriber = ::tracing_subscriber::fmt()
+        .with_max_level(::tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer(move || CaptureWriter(writer.clone()))
+        .finish();
+    let _guard: ::tracing::subscriber::DefaultGuard = ::tracing::subscriber::set_default(subscriber);
+
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
 
     let ((_, _), _): ((QDesc, SocketAddrV4), QDesc) =
         connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
 
+    let output: String = String::from_utf8(output.lock().unwrap().clone())?;
+    let transitions: Vec<&str> = output
+        .lines()
+        .filter(|line| line.contains("tcp state transition"))
+        .collect();
+
+    // Server listens, client connects, server gets the SYN, client gets the SYN+ACK, and server gets the ACK.
+    let expected: [(&str, &str, &str, Ipv4Addr); 5] = [
+        ("Closed", "Listen", "listen", test_helpers::BOB_IPV4),
+        ("Closed", "SynSent", "connect", test_helpers::ALICE_IPV4),
+        ("Listen", "SynReceived", "SYN_received", test_helpers::BOB_IPV4),
+        ("SynSent", "Established", "SYN_ACK_received", test_helpers::ALICE_IPV4),
+        ("SynReceived", "Established", "ACK_received", test_helpers::BOB_IPV4),
+    ];
+    crate::ensure_eq!(transitions.len(), expected.len());
+    for (line, (old, new, event, src_ip)) in transitions.iter().zip(expected.iter()) {
+        crate::ensure_eq!(line.contains(&format!("old_state={}", old)), true);
+        crate::ensure_eq!(line.contains(&format!("new_state={}", new)), true);
+        crate::ensure_eq!(line.contains(&format!("event=\"{}\"", event)), true);
+        crate::ensure_eq!(line.contains(&format!("src_ip={}", src_ip)), true);
+    }
+
+    Ok(())
+}
+
+/// Tests that queue introspection enumerates listening, established and UDP sockets, along with their addresses.
+#[test]
+fn test_queues() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let udp_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 53);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+
+    let ((server_fd, client_addr), client_fd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut ctx, &mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    let udp_fd: QDesc = server.udp_socket()?;
+    server.udp_bind(udp_fd, udp_addr)?;
+    let unbound_fd: QDesc = server.tcp_socket()?;
+    let dup_fd: QDesc = server.tcp_dup(server_fd)?;
+
+    // Server: listening, established and duplicate TCP sockets, a bound UDP socket and an unbound TCP socket.
+    let queues: Vec<QueueInfo> = server.queues();
+    crate::ensure_eq!(queues.len(), 5);
+    let listening: Vec<&QueueInfo> = queues
+        .iter()
+        .filter(|info| info.state == Some(QueueState::Listening))
+        .collect();
+    crate::ensure_eq!(listening.len(), 1);
+    crate::ensure_eq!(listening[0].qtype, QType::TcpSocket);
+    crate::ensure_eq!(listening[0].local, Some(listen_addr));
+    crate::ensure_eq!(listening[0].remote, None);
+    for fd in [server_fd, dup_fd] {
+        let info: &QueueInfo = match queues.iter().find(|info| info.qd == fd) {
+            Some(info) => info,
+            None => anyhow::bail!("established socket should be enumerated"),
+        };
+        crate::ensure_eq!(info.qtype, QType::TcpSocket);
+        crate::ensure_eq!(info.state, Some(QueueState::Established));
+        crate::ensure_eq!(info.local, Some(listen_addr));
+        crate::ensure_eq!(info.remote, Some(client_addr));
+    }
+    let udp: QueueInfo = match server.query_queue(udp_fd) {
+        Some(info) => info,
+        None => anyhow::bail!("udp socket should be enumerated"),
+    };
+    crate::ensure_eq!(udp.qtype, QType::UdpSocket);
+    crate::ensure_eq!(udp.state, Some(QueueState::Bound));
+    crate::ensure_eq!(udp.local, Some(udp_addr));
+    let unbound: QueueInfo = match server.query_queue(unbound_fd) {
+        Some(info) => info,
+        None => anyhow::bail!("unbound socket should be enumerated"),
+    };
+    crate::ensure_eq!(unbound.state, Some(QueueState::Unbound));
+    crate::ensure_eq!(unbound.local, None);
+
+    // Client: a single established TCP socket.
+    let queues: Vec<QueueInfo> = client.queues();
+    crate::ensure_eq!(queues.len(), 1);
+    crate::ensure_eq!(queues[0].qd, client_fd);
+    crate::ensure_eq!(queues[0].state, Some(QueueState::Established));
+    crate::ensure_eq!(queues[0].local, Some(client_addr));
+    crate::ensure_eq!(queues[0].remote, Some(listen_addr));
+
+    // Closed descriptors should no longer be reported.
+    server.tcp_close(dup_fd)?;
+    crate::ensure_eq!(server.query_queue(dup_fd).is_none(), true);
+    crate::ensure_eq!(server.queues().len(), 4);
+
+    Ok(())
+}
+
+/// Tests that an accept with a timeout fails once the timeout expires, leaving the listening socket usable.
+#[test]
+fn test_accept_timeout() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let timeout: Duration = Duration::from_secs(30);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+
+    // No connection request arrives, so the accept should time out.
+    let mut accept_future = match server.tcp_accept_timeout(listen_fd, timeout) {
+        Ok(future) => Box::pin(future),
+        Err(e) => anyhow::bail!("server accept returned an error: {:?}", e),
+    };
+    crate::ensure_eq!(Future::poll(accept_future.as_mut(), &mut ctx).is_pending(), true);
+    now += timeout;
+    server.clock.advance_clock(now);
+    match Future::poll(accept_future.as_mut(), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == ETIMEDOUT => {},
+        _ => anyhow::bail!("accept should have timed out"),
+    }
+
+    // The listening socket should remain usable.
+    let mut accept_future = match server.tcp_accept_timeout(listen_fd, timeout) {
+        Ok(future) => Box::pin(future),
+        Err(e) => anyhow::bail!("server accept returned an error: {:?}", e),
+    };
+    let (_, _): (QDesc, SocketAddrV4) =
+        connection_setup_handshake(&mut ctx, &mut server, &mut client, listen_addr, accept_future.as_mut())?;
+
+    Ok(())
+}
+
+/// Tests that shutting down a listening socket aborts pending accepts, resets connections in the backlog, refuses new
+/// connections, and leaves established connections untouched.
+#[test]
+fn test_stop_listening() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+
+    // Establish a first connection.
+    let mut accept_future: AcceptFuture = match server.tcp_accept(listen_fd) {
+        Ok(future) => future,
+        Err(e) => anyhow::bail!("server accept returned an error: {:?}", e),
+    };
+    let (server_fd, _): (QDesc, SocketAddrV4) = connection_setup_handshake(
+        &mut ctx,
+        &mut server,
+        &mut client,
+        listen_addr,
+        Pin::new(&mut accept_future),
+    )?;
+
+    // Start a second connection, but leave it in the backlog, with a pending accept.
+    let mut accept_future: AcceptFuture = match server.tcp_accept(listen_fd) {
+        Ok(future) => future,
+        Err(e) => anyhow::bail!("server accept returned an error: {:?}", e),
+    };
+    crate::ensure_eq!(Future::poll(Pin::new(&mut accept_future), &mut ctx).is_pending(), true);
+    let (_, _, bytes): (QDesc, ConnectFuture, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+
+    // Shut down the listening socket.
+    if let Err(e) = server.tcp_stop_listening(listen_fd) {
+        anyhow::bail!("stop listening returned an error: {:?}", e);
+    }
+
+    // The connection in the backlog should have been reset.
+    let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(server.rt.pop_frame())?;
+    crate::ensure_eq!(tcp_header.rst, true);
+
+    // The pending accept should have been aborted, and new accepts should fail.
+    match Future::poll(Pin::new(&mut accept_future), &mut ctx) {
+        Poll::Ready(Err(e)) if e.errno == libc::ECONNABORTED => {},
+        _ => anyhow::bail!("accept should have been aborted"),
+    }
+    match server.tcp_accept(listen_fd) {
+        Err(e) if e.errno == libc::EINVAL => {},
+        _ => anyhow::bail!("accept should have failed"),
+    }
+
+    // New connection requests should be refused.
+    let (_, _, bytes): (QDesc, ConnectFuture, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    server.receive(bytes).unwrap();
+    let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(server.rt.pop_frame())?;
+    crate::ensure_eq!(tcp_header.rst, true);
+
+    // The established connection should be unaffected, and still carry data.
+    let mut pop_future = server.tcp_pop(server_fd);
+    crate::ensure_eq!(Future::poll(Pin::new(&mut pop_future), &mut ctx).is_pending(), true);
+    let mut push_future: PushFuture = server.tcp_push(server_fd, DemiBuffer::from_slice(&[1, 2, 3, 4])?);
+    match Future::poll(Pin::new(&mut push_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("push should have completed successfully"),
+    }
+    let bytes: DemiBuffer = server.rt.pop_frame();
+    let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes.clone())?;
+    crate::ensure_eq!(tcp_header.rst, false);
+    crate::ensure_eq!(tcp_header.psh, true);
+    client.receive(bytes)?;
+
+    // The listening socket can now be closed.
+    if let Err(e) = server.tcp_close(listen_fd) {
+        anyhow::bail!("close returned an error: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// Tests if a listening socket reports the number of connections that may be accepted as the bytes available on it.
+#[test]
+fn test_bytes_available_on_listening_socket() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+    crate::ensure_eq!(server.tcp_bytes_available(listen_fd)?, 0);
+
+    // A connection that is going through the handshake may not be accepted yet.
+    let (_, mut connect_future, bytes): (QDesc, ConnectFuture, DemiBuffer) =
+        connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+    crate::ensure_eq!(server.tcp_bytes_available(listen_fd)?, 0);
+
+    // Once the handshake completes, the connection may be accepted.
+    let bytes: DemiBuffer = connection_setup_syn_sent_established(&mut client, bytes)?;
+    crate::ensure_eq!(Future::poll(Pin::new(&mut connect_future), &mut ctx).is_ready(), true);
+    connection_setup_sync_rcvd_established(&mut server, bytes)?;
+    crate::ensure_eq!(server.tcp_bytes_available(listen_fd)?, 1);
+
+    // Accepting the connection takes it away.
+    let mut accept_future: AcceptFuture = match server.tcp_accept(listen_fd) {
+        Ok(future) => future,
+        Err(e) => anyhow::bail!("server accept returned an error: {:?}", e),
+    };
+    crate::ensure_eq!(Future::poll(Pin::new(&mut accept_future), &mut ctx).is_ready(), true);
+    crate::ensure_eq!(server.tcp_bytes_available(listen_fd)?, 0);
+
+    Ok(())
+}
+
+/// Triggers CLOSED -> LISTEN state transition, and returns the listening socket.
+fn connection_setup_listen(server: &mut Engine, listen_addr: SocketAddrV4) -> Result<QDesc> {
+    let socket_fd: QDesc = match server.tcp_socket() {
+        Ok(fd) => fd,
+        Err(e) => anyhow::bail!("server tcp socket returned error: {:?}", e),
+    };
+    if let Err(e) = server.tcp_bind(socket_fd, listen_addr) {
+        anyhow::bail!("server bind returned an error: {:?}", e);
+    }
+    if let Err(e) = server.tcp_listen(socket_fd, 2) {
+        anyhow::bail!("server listen returned an error: {:?}", e);
+    }
+    Ok(socket_fd)
+}
+
+/// Runs the 3-way handshake of a new connection to a listening socket, and completes the pending `accept_future`.
+fn connection_setup_handshake<F: Future<Output = Result<(QDesc, SocketAddrV4), Fail>>>(
+    ctx: &mut Context,
+    server: &mut Engine,
+    client: &mut Engine,
+    listen_addr: SocketAddrV4,
+    mut accept_future: Pin<&mut F>,
+) -> Result<(QDesc, SocketAddrV4)> {
+    let (_, mut connect_future, bytes): (QDesc, ConnectFuture, DemiBuffer) =
+        connection_setup_listen_syn_sent(client, listen_addr)?;
+    let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(server, bytes)?;
+    let bytes: DemiBuffer = connection_setup_syn_sent_established(client, bytes)?;
+    connection_setup_sync_rcvd_established(server, bytes)?;
+
+    let accepted: (QDesc, SocketAddrV4) = match Future::poll(accept_future.as_mut(), ctx) {
+        Poll::Ready(Ok(accepted)) => accepted,
+        _ => anyhow::bail!("accept should have completed"),
+    };
+    match Future::poll(Pin::new(&mut connect_future), ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    };
+
+    Ok(accepted)
+}
+
+/// Tests that connection requests that overflow the backlog of a listening socket are refused with a RST, or silently
+/// dropped, according to the configured policy.
+#[test]
+fn test_listen_backlog_overflow() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    for policy in [BacklogPolicy::Rst, BacklogPolicy::Drop] {
+        // Setup peers.
+        let tcp_config: TcpConfig = TcpConfig::builder().backlog_full_policy(policy).build();
+        let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+        let mut client: Engine = test_helpers::new_alice2(now);
+        let _listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+
+        // Fill up the backlog.
+        for _ in 0..2 {
+            let (_, _, bytes): (QDesc, ConnectFuture, DemiBuffer) =
+                connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+            connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+        }
+
+        // Further connection requests should not make it to the backlog.
+        let (_, mut connect_future, bytes): (QDesc, ConnectFuture, DemiBuffer) =
+            connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+        let (_, _, syn_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes.clone())?;
+        match server.receive(bytes) {
+            Err(e) if e.errno == libc::ECONNREFUSED => {},
+            _ => anyhow::bail!("connection request should have been refused"),
+        }
+        server.rt.poll_scheduler();
+
+        match policy {
+            // The client should be told that its connection request was refused.
+            BacklogPolicy::Rst => {
+                let bytes: DemiBuffer = server.rt.pop_frame();
+                let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes.clone())?;
+                crate::ensure_eq!(tcp_header.rst, true);
+                crate::ensure_eq!(tcp_header.ack_num, syn_header.seq_num + SeqNumber::from(1));
+                client.receive(bytes).unwrap();
+                match Future::poll(Pin::new(&mut connect_future), &mut ctx) {
+                    Poll::Ready(Err(e)) if e.errno == libc::ECONNREFUSED => {},
+                    _ => anyhow::bail!("connect should have been refused"),
+                }
+            },
+            // The client should not hear back, and retry later.
+            BacklogPolicy::Drop => {
+                crate::ensure_eq!(server.rt.pop_frame_unchecked().is_none(), true);
+                crate::ensure_eq!(Future::poll(Pin::new(&mut connect_future), &mut ctx).is_pending(), true);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that a listening socket with a full backlog reports that it turns away connections, once until the report is
+/// drained, and that the connections that it refuses are reported as reset on the other end.
+#[test]
+fn test_accept_queue_full_event() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+
+    // Fill up the backlog.
+    for _ in 0..2 {
+        let (_, _, bytes): (QDesc, ConnectFuture, DemiBuffer) =
+            connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+        connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+    }
+    crate::ensure_eq!(server.tcp_next_event(), None);
+
+    // Further connection requests are refused, but reported only once.
+    let mut refused: Vec<(QDesc, ConnectFuture)> = Vec::new();
+    for _ in 0..2 {
+        let (client_fd, connect_future, bytes): (QDesc, ConnectFuture, DemiBuffer) =
+            connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+        match server.receive(bytes) {
+            Err(e) if e.errno == libc::ECONNREFUSED => {},
+            _ => anyhow::bail!("connection request should have been refused"),
+        }
+        server.rt.poll_scheduler();
+        client.receive(server.rt.pop_frame())?;
+        refused.push((client_fd, connect_future));
+    }
+    crate::ensure_eq!(server.tcp_next_event(), Some(ConnEvent::AcceptQueueFull(listen_fd)));
+    crate::ensure_eq!(server.tcp_next_event(), None);
+
+    // The client learns about the refusals as it completes its connection attempts.
+    for (client_fd, mut connect_future) in refused {
+        match Future::poll(Pin::new(&mut connect_future), &mut ctx) {
+            Poll::Ready(Err(e)) if e.errno == libc::ECONNREFUSED => {},
+            _ => anyhow::bail!("connect should have been refused"),
+        }
+        crate::ensure_eq!(
+            client.tcp_next_event(),
+            Some(ConnEvent::Reset(client_fd, libc::ECONNREFUSED))
+        );
+    }
+    crate::ensure_eq!(client.tcp_next_event(), None);
+
+    Ok(())
+}
+
+/// Tests that connecting from a local address binds the socket to it, and that the connection originates from it.
+#[test]
+fn test_connect_from() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let local_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 5555);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+    let mut accept_future: AcceptFuture = server.tcp_accept(listen_fd)?;
+
+    // Connect from the local address and check that the SYN is sent from it.
+    let client_fd: QDesc = client.tcp_socket()?;
+    let mut connect_future: ConnectFuture = client.tcp_connect_from(client_fd, local_addr, listen_addr)?;
+    client.rt.poll_scheduler();
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    let (_, ipv4_header, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes.clone())?;
+    crate::ensure_eq!(ipv4_header.get_src_addr(), *local_addr.ip());
+    crate::ensure_eq!(tcp_header.src_port, local_addr.port());
+
+    // Complete the handshake and check that the server sees the local address as the remote end.
+    let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+    let bytes: DemiBuffer = connection_setup_syn_sent_established(&mut client, bytes)?;
+    connection_setup_sync_rcvd_established(&mut server, bytes)?;
+    match Future::poll(Pin::new(&mut accept_future), &mut ctx) {
+        Poll::Ready(Ok((_, addr))) => crate::ensure_eq!(addr, local_addr),
+        _ => anyhow::bail!("accept should have completed"),
+    };
+    match Future::poll(Pin::new(&mut connect_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    };
+
+    // Connect from a wildcard address and check that an ephemeral port is selected.
+    let client_fd: QDesc = client.tcp_socket()?;
+    let wildcard_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+    let _connect_future: ConnectFuture = client.tcp_connect_from(client_fd, wildcard_addr, listen_addr)?;
+    client.rt.poll_scheduler();
+    let (_, ipv4_header, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(client.rt.pop_frame())?;
+    crate::ensure_eq!(ipv4_header.get_src_addr(), test_helpers::ALICE_IPV4);
+    crate::ensure_neq!(tcp_header.src_port, 0);
+
+    // Connect a bound socket from a different port and check that it fails.
+    let client_fd: QDesc = client.tcp_socket()?;
+    client.tcp_bind(client_fd, SocketAddrV4::new(test_helpers::ALICE_IPV4, 6666))?;
+    match client.tcp_connect_from(client_fd, local_addr, listen_addr) {
+        Err(e) if e.errno == libc::EINVAL => {},
+        _ => anyhow::bail!("connect from a different address should have failed"),
+    };
+
+    Ok(())
+}
+
+/// Relays a frame that a gateway received on to Bob, the way that a router would.
+fn relay_to_bob(mut bytes: DemiBuffer) -> Result<(MacAddress, DemiBuffer)> {
+    let (eth2_header, _, _): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes.clone())?;
+    bytes[..6].copy_from_slice(&test_helpers::BOB_MAC.octets());
+    Ok((eth2_header.dst_addr(), bytes))
+}
+
+/// Tests that connections to a destination with an equal-cost multi-path route spread evenly across its gateways, and
+/// that all segments of a connection go through the same gateway.
+#[test]
+fn test_ecmp_route() -> Result<()> {
+    const NUM_CONNECTIONS: usize = 100;
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers. The client reaches the server either directly or through Carrie.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let gateways: [MacAddress; 2] = [test_helpers::BOB_MAC, test_helpers::CARRIE_MAC];
+    client.tcp_add_ecmp_route(
+        test_helpers::BOB_IPV4,
+        32,
+        vec![
+            RouteEntry {
+                gateway: test_helpers::BOB_IPV4,
+                weight: 1,
+            },
+            RouteEntry {
+                gateway: test_helpers::CARRIE_IPV4,
+                weight: 1,
+            },
+        ],
+    )?;
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+
+    let mut counts: [usize; 2] = [0; 2];
+    for _ in 0..NUM_CONNECTIONS {
+        let mut accept_future: AcceptFuture = server.tcp_accept(listen_fd)?;
+        let (_, mut connect_future, bytes): (QDesc, ConnectFuture, DemiBuffer) =
+            connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+        let (syn_gateway, bytes): (MacAddress, DemiBuffer) = relay_to_bob(bytes)?;
+        let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+        let bytes: DemiBuffer = connection_setup_syn_sent_established(&mut client, bytes)?;
+        let (ack_gateway, bytes): (MacAddress, DemiBuffer) = relay_to_bob(bytes)?;
+        connection_setup_sync_rcvd_established(&mut server, bytes)?;
+        crate::ensure_eq!(ack_gateway, syn_gateway);
+        match gateways.iter().position(|gateway| *gateway == syn_gateway) {
+            Some(i) => counts[i] += 1,
+            None => anyhow::bail!("SYN should have gone through a gateway (dst={:?})", syn_gateway),
+        }
+
+        match Future::poll(Pin::new(&mut accept_future), &mut ctx) {
+            Poll::Ready(Ok(_)) => {},
+            _ => anyhow::bail!("accept should have completed"),
+        };
+        match Future::poll(Pin::new(&mut connect_future), &mut ctx) {
+            Poll::Ready(Ok(())) => {},
+            _ => anyhow::bail!("connect should have completed"),
+        };
+    }
+
+    // Each gateway takes about half of the connections.
+    for count in counts {
+        crate::ensure_eq!(
+            count >= NUM_CONNECTIONS * 3 / 10 && count <= NUM_CONNECTIONS * 7 / 10,
+            true
+        );
+    }
+
+    Ok(())
+}
+
+/// Tests that a listening socket moves over to a new local address, and that connections to it are set up from the
+/// new local identity.
+#[test]
+fn test_set_local_identity() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let new_ipv4: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+    let new_mac: MacAddress = MacAddress::new([0x12, 0x23, 0x45, 0x67, 0x89, 0xcd]);
+    let new_listen_addr: SocketAddrV4 = SocketAddrV4::new(new_ipv4, listen_port);
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+    let mut accept_future: AcceptFuture = server.tcp_accept(listen_fd)?;
+
+    // Fail over to the new identity, which is announced with a gratuitous ARP.
+    server.set_local_identity(new_ipv4, new_mac);
+    let (eth2_header, _): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(server.rt.pop_frame())?;
+    crate::ensure_eq!(eth2_header.ether_type(), EtherType2::Arp);
+    crate::ensure_eq!(eth2_header.src_addr(), new_mac);
+    crate::ensure_eq!(eth2_header.dst_addr(), MacAddress::broadcast());
+
+    // Connect to the new address, which the client has to resolve first.
+    let client_fd: QDesc = client.tcp_socket()?;
+    let mut connect_future: ConnectFuture = client.tcp_connect(client_fd, new_listen_addr);
+    client.rt.poll_scheduler();
+    server.receive(client.rt.pop_frame())?;
+    client.receive(server.rt.pop_frame())?;
+    client.rt.poll_scheduler();
+
+    // Complete the handshake and check that the server answers from its new identity.
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    check_packet_pure_syn(
+        bytes.clone(),
+        test_helpers::ALICE_MAC,
+        new_mac,
+        test_helpers::ALICE_IPV4,
+        new_ipv4,
+        listen_port,
+    )?;
+    let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+    check_packet_syn_ack(
+        bytes.clone(),
+        new_mac,
+        test_helpers::ALICE_MAC,
+        new_ipv4,
+        test_helpers::ALICE_IPV4,
+        listen_port,
+    )?;
+    let bytes: DemiBuffer = connection_setup_syn_sent_established(&mut client, bytes)?;
+    connection_setup_sync_rcvd_established(&mut server, bytes)?;
+    match Future::poll(Pin::new(&mut accept_future), &mut ctx) {
+        Poll::Ready(Ok(_)) => {},
+        _ => anyhow::bail!("accept should have completed"),
+    };
+    match Future::poll(Pin::new(&mut connect_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    };
+
+    Ok(())
+}
+
+/// Checks whether a segment carries an MD5 signature.
+fn is_signed(bytes: DemiBuffer) -> Result<bool> {
+    let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes)?;
+    let signed: bool = tcp_header
+        .iter_options()
+        .any(|option| matches!(option, TcpOptions2::Md5Signature(_)));
+    Ok(signed)
+}
+
+/// Pushes data over an authenticated connection and checks that it is received in a signed segment.
+fn push_signed(
+    ctx: &mut Context,
+    sender: &mut Engine,
+    sender_fd: QDesc,
+    receiver: &mut Engine,
+    receiver_fd: QDesc,
+) -> Result<()> {
+    let mut buf: DemiBuffer = DemiBuffer::new(32);
+    buf[..].copy_from_slice(&[7; 32]);
+    let _: PushFuture = sender.tcp_push(sender_fd, buf.clone());
+    sender.rt.poll_scheduler();
+    let bytes: DemiBuffer = sender.rt.pop_frame();
+    crate::ensure_eq!(is_signed(bytes.clone())?, true);
+    receiver.receive(bytes)?;
+    let mut pop_future: PopFuture = receiver.tcp_pop(receiver_fd);
+    match Future::poll(Pin::new(&mut pop_future), ctx) {
+        Poll::Ready(Ok(received)) => crate::ensure_eq!(&received[..], &buf[..]),
+        _ => anyhow::bail!("pop should have completed"),
+    };
+    Ok(())
+}
+
+/// Tests that two stacks set up and use a connection whose segments are signed with the TCP MD5 signature option, and
+/// that segments which are not signed with the right key are dropped.
+#[test]
+fn test_authenticated_connection() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 179;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let authenticator: Rc<dyn SegmentAuthenticator> = Rc::new(TcpMd5Signature::new(b"secret"));
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2(now);
+    let mut client: Engine = test_helpers::new_alice2(now);
+    let listen_fd: QDesc = server.tcp_socket()?;
+    server.tcp_set_authenticator(listen_fd, Some(authenticator.clone()))?;
+    server.tcp_bind(listen_fd, listen_addr)?;
+    server.tcp_listen(listen_fd, 2)?;
+    let mut accept_future: AcceptFuture = server.tcp_accept(listen_fd)?;
+
+    // Sockets cannot become authenticated once they are in use.
+    match server.tcp_set_authenticator(listen_fd, None) {
+        Err(e) if e.errno == libc::EISCONN => {},
+        _ => anyhow::bail!("setting the authenticator of a listening socket should have failed"),
+    };
+
+    // Run the handshake and check that every segment of it is signed.
+    let client_fd: QDesc = client.tcp_socket()?;
+    client.tcp_set_authenticator(client_fd, Some(authenticator.clone()))?;
+    let mut connect_future: ConnectFuture = client.tcp_connect(client_fd, listen_addr);
+    client.rt.poll_scheduler();
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    crate::ensure_eq!(is_signed(bytes.clone())?, true);
+    let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+    crate::ensure_eq!(is_signed(bytes.clone())?, true);
+    let bytes: DemiBuffer = connection_setup_syn_sent_established(&mut client, bytes)?;
+    crate::ensure_eq!(is_signed(bytes.clone())?, true);
+    connection_setup_sync_rcvd_established(&mut server, bytes)?;
+    let server_fd: QDesc = match Future::poll(Pin::new(&mut accept_future), &mut ctx) {
+        Poll::Ready(Ok((server_fd, _))) => server_fd,
+        _ => anyhow::bail!("accept should have completed"),
+    };
+    match Future::poll(Pin::new(&mut connect_future), &mut ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    };
+
+    // Exchange data in both directions. The accepted connection inherits the authenticator of the listening socket.
+    push_signed(&mut ctx, &mut client, client_fd, &mut server, server_fd)?;
+    push_signed(&mut ctx, &mut server, server_fd, &mut client, client_fd)?;
+    crate::ensure_eq!(server.tcp_unauthenticated_segments(), 0);
+
+    // A client without the key cannot connect, and its segments are dropped and counted.
+    for key in [None, Some(b"Secret")] {
+        let mut intruder: Engine = test_helpers::new_alice2(now);
+        let intruder_fd: QDesc = intruder.tcp_socket()?;
+        if let Some(key) = key {
+            intruder.tcp_set_authenticator(intruder_fd, Some(Rc::new(TcpMd5Signature::new(key))))?;
+        }
+        let _: ConnectFuture = intruder.tcp_connect(intruder_fd, listen_addr);
+        intruder.rt.poll_scheduler();
+        match server.receive(intruder.rt.pop_frame()) {
+            Err(e) if e.errno == EBADMSG => {},
+            _ => anyhow::bail!("unauthenticated segment should have been dropped"),
+        };
+        server.rt.poll_scheduler();
+        crate::ensure_eq!(server.rt.pop_frame_unchecked().is_none(), true);
+    }
+    crate::ensure_eq!(server.tcp_unauthenticated_segments(), 2);
+
+    Ok(())
+}
+
+/// Builds a TCP configuration that negotiates timestamps, with TIME-WAIT recycling turned on or off.
+fn time_wait_tcp_config(time_wait_recycling: bool) -> TcpConfig {
+    TcpConfig::builder()
+        .msl(Duration::from_secs(30))
+        .timestamps_enabled(true)
+        .time_wait_recycling(time_wait_recycling)
+        .build()
+}
+
+/// Opens a connection from an unbound socket to a listening socket and closes it again, with the client closing first
+/// so that its end of the connection is left in TIME-WAIT. Returns the SYN that opened the connection.
+fn connection_cycle(
+    ctx: &mut Context,
+    now: &mut Instant,
+    server: &mut Engine,
+    client: &mut Engine,
+    listen_fd: QDesc,
+    listen_addr: SocketAddrV4,
+) -> Result<TcpHeader> {
+    let mut accept_future: AcceptFuture = server.tcp_accept(listen_fd)?;
+    let client_fd: QDesc = client.tcp_socket()?;
+    let mut connect_future: ConnectFuture = client.ipv4.tcp.connect(client_fd, listen_addr)?;
+    client.rt.poll_scheduler();
+    let bytes: DemiBuffer = client.rt.pop_frame();
+    let (_, _, syn_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes.clone())?;
+    advance_clock(Some(server), Some(client), now);
+
+    // Set up the connection.
+    let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(server, bytes)?;
+    let bytes: DemiBuffer = connection_setup_syn_sent_established(client, bytes)?;
+    connection_setup_sync_rcvd_established(server, bytes)?;
+    let server_fd: QDesc = match Future::poll(Pin::new(&mut accept_future), ctx) {
+        Poll::Ready(Ok((server_fd, _))) => server_fd,
+        _ => anyhow::bail!("accept should have completed"),
+    };
+    match Future::poll(Pin::new(&mut connect_future), ctx) {
+        Poll::Ready(Ok(())) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    };
+    advance_clock(Some(server), Some(client), now);
+
+    // Close the connection.
+    connection_close_half(client, server, client_fd)?;
+    connection_close_half(server, client, server_fd)?;
+    advance_clock(Some(server), Some(client), now);
+
+    Ok(syn_header)
+}
+
+/// Closes the sending half of a connection, and gets the FIN acknowledged.
+fn connection_close_half(closer: &mut Engine, peer: &mut Engine, fd: QDesc) -> Result<()> {
+    closer.tcp_close(fd)?;
+    closer.rt.poll_scheduler();
+    peer.receive(closer.rt.pop_frame())?;
+    peer.rt.poll_scheduler();
+    closer.receive(peer.rt.pop_frame())?;
+    Ok(())
+}
+
+/// Returns the sender timestamp of a segment.
+fn sender_timestamp(tcp_header: &TcpHeader) -> Result<u32> {
+    match tcp_header.iter_options().find_map(|option| match option {
+        TcpOptions2::Timestamp { sender_timestamp, .. } => Some(*sender_timestamp),
+        _ => None,
+    }) {
+        Some(sender_timestamp) => Ok(sender_timestamp),
+        None => anyhow::bail!("segment should carry timestamps"),
+    }
+}
+
+/// Tests that connections linger in TIME-WAIT and hold their ephemeral port for twice the maximum segment lifetime.
+#[test]
+fn test_time_wait_holds_port() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let first_port: u16 = 50000;
+    let num_ports: u16 = 4;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, time_wait_tcp_config(false));
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, time_wait_tcp_config(false));
+    client.set_ephemeral_port_range(first_port, first_port + num_ports - 1)?;
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+
+    // Use up all ephemeral ports.
+    for _ in 0..num_ports {
+        connection_cycle(&mut ctx, &mut now, &mut server, &mut client, listen_fd, listen_addr)?;
+    }
+
+    // All ports are held by connections in TIME-WAIT.
+    let client_fd: QDesc = client.tcp_socket()?;
+    match client.ipv4.tcp.connect(client_fd, listen_addr) {
+        Err(e) if e.errno == libc::EADDRINUSE => {},
+        _ => anyhow::bail!("connect should have run out of ephemeral ports"),
+    };
+    crate::ensure_eq!(client.tcp_recycled_connections(), 0);
+
+    // Once TIME-WAIT is over, the ports become available again.
+    now += Duration::from_secs(60);
+    server.clock.advance_clock(now);
+    client.clock.advance_clock(now);
+    for _ in 0..num_ports {
+        let syn_header: TcpHeader =
+            connection_cycle(&mut ctx, &mut now, &mut server, &mut client, listen_fd, listen_addr)?;
+        crate::ensure_eq!(
+            (first_port..first_port + num_ports).contains(&syn_header.src_port),
+            true
+        );
+    }
+    crate::ensure_eq!(client.tcp_recycled_connections(), 0);
+
+    Ok(())
+}
+
+/// Tests that connections in TIME-WAIT are recycled for new outgoing connections once ephemeral ports run out, and that
+/// sequence numbers and timestamps keep increasing across incarnations of the same connection.
+#[test]
+fn test_time_wait_recycling() -> Result<()> {
+    let mut ctx = Context::from_waker(noop_waker_ref());
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let first_port: u16 = 50000;
+    let num_ports: u16 = 4;
+    let num_connections: u16 = 5 * num_ports;
+
+    // Setup peers.
+    let mut server: Engine = test_helpers::new_bob2_with_tcp_config(now, time_wait_tcp_config(false));
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, time_wait_tcp_config(true));
+    client.set_ephemeral_port_range(first_port, first_port + num_ports - 1)?;
+    let listen_fd: QDesc = connection_setup_listen(&mut server, listen_addr)?;
+
+    // Cycle connections, way past the number of ephemeral ports.
+    let mut last_syn: HashMap<u16, TcpHeader> = HashMap::new();
+    for _ in 0..num_connections {
+        let syn_header: TcpHeader =
+            connection_cycle(&mut ctx, &mut now, &mut server, &mut client, listen_fd, listen_addr)?;
+        crate::ensure_eq!(
+            (first_port..first_port + num_ports).contains(&syn_header.src_port),
+            true
+        );
+        if let Some(last_syn_header) = last_syn.get(&syn_header.src_port) {
+            crate::ensure_eq!(syn_header.seq_num > last_syn_header.seq_num, true);
+            crate::ensure_eq!(
+                sender_timestamp(&syn_header)? > sender_timestamp(last_syn_header)?,
+                true
+            );
+        }
+        last_syn.insert(syn_header.src_port, syn_header);
+    }
+    crate::ensure_eq!(client.tcp_recycled_connections(), (num_connections - num_ports) as u64);
+
+    Ok(())
+}
+
+/// Tests that an advertised MSS that does not fit in the MTU of the NIC is clamped when the stack comes up.
+#[test]
+fn test_advertised_mss_clamped_to_mtu() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let tcp_config: TcpConfig = TcpConfig::builder().advertised_mss(9000).build();
+
+    // The test runtime drives a NIC with a standard Ethernet MTU.
+    let mut client: Engine = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+    let client_fd: QDesc = client.tcp_socket()?;
+    crate::ensure_eq!(client.tcp_max_push_size(client_fd)?, 1460);
+
+    // The SYN advertises the clamped MSS.
+    let _connect_future: ConnectFuture = client.tcp_connect(client_fd, listen_addr);
+    client.rt.poll_scheduler();
+    let (_, _, syn_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(client.rt.pop_frame())?;
+    let mss: Option<u16> = syn_header.iter_options().find_map(|option| match option {
+        TcpOptions2::MaximumSegmentSize(mss) => Some(*mss),
+        _ => None,
+    });
+    crate::ensure_eq!(mss, Some(1460));
+
     Ok(())
 }
@@ -0,0 +1,47 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::tcp::segment::{
+        TcpHeader,
+        TcpOptions2,
+    },
+    runtime::timer::TimerRc,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of bytes that the timestamps option takes in every segment, padded to 32 bits (RFC 7323).
+pub const TIMESTAMPS_OPTION_SIZE: usize = 12;
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Returns the value of our timestamp clock, which ticks once per millisecond. The clock is shared by all connections
+/// of the stack, so that the timestamps of successive connections between the same endpoints keep increasing.
+pub fn timestamp_value(clock: &TimerRc) -> u32 {
+    clock.elapsed().as_millis() as u32
+}
+
+/// Returns the sender timestamp (TSval) and the echoed timestamp (TSecr) of a segment, if it carries the option.
+pub fn find_timestamps(header: &TcpHeader) -> Option<(u32, u32)> {
+    header.iter_options().find_map(|option| match option {
+        TcpOptions2::Timestamp {
+            sender_timestamp,
+            echo_timestamp,
+        } => Some((*sender_timestamp, *echo_timestamp)),
+        _ => None,
+    })
+}
+
+/// Checks whether timestamp `a` is more recent than timestamp `b`, accounting for wrap-around (RFC 7323).
+pub fn timestamp_after(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
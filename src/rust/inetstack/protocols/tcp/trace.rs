@@ -0,0 +1,128 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Tracing of the TCP connection state machine.
+//!
+//! With the `tracing` feature, every state transition of a connection is reported as a `tracing` event that carries
+//! the old state, the new state, the event that triggered the transition, and the addresses of both ends of the
+//! connection. Operations on a connection run within a span that is identified by those addresses. Without the
+//! feature, all of this compiles down to nothing.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use super::established::State;
+use ::std::net::SocketAddrV4;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// States of a TCP connection, as named by RFC 793, including those before the connection is established.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConnectionState {
+    Closed,
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    Closing,
+    TimeWait,
+    CloseWait,
+    LastAck,
+}
+
+/// Span within which the operations on a single TCP connection run.
+#[derive(Clone)]
+pub struct ConnectionSpan {
+    #[cfg(feature = "tracing")]
+    span: ::tracing::Span,
+}
+
+/// Guard that keeps a [ConnectionSpan] entered until dropped.
+pub struct EnteredConnectionSpan<'a> {
+    #[cfg(feature = "tracing")]
+    _entered: ::tracing::span::Entered<'a>,
+    #[cfg(not(feature = "tracing"))]
+    _span: ::std::marker::PhantomData<&'a ConnectionSpan>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+/// Associated functions for connection spans.
+impl ConnectionSpan {
+    /// Creates the span of the connection between `local` and `remote`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub fn new(local: SocketAddrV4, remote: SocketAddrV4) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: ::tracing::trace_span!(
+                "tcp_connection",
+                src_ip = %local.ip(),
+                src_port = local.port(),
+                dst_ip = %remote.ip(),
+                dst_port = remote.port(),
+            ),
+        }
+    }
+
+    /// Enters this span. Events that are reported before the returned guard is dropped belong to this connection.
+    pub fn enter(&self) -> EnteredConnectionSpan<'_> {
+        EnteredConnectionSpan {
+            #[cfg(feature = "tracing")]
+            _entered: self.span.enter(),
+            #[cfg(not(feature = "tracing"))]
+            _span: ::std::marker::PhantomData,
+        }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl From<State> for ConnectionState {
+    fn from(state: State) -> Self {
+        match state {
+            State::Established => ConnectionState::Established,
+            State::FinWait1 => ConnectionState::FinWait1,
+            State::FinWait2 => ConnectionState::FinWait2,
+            State::Closing => ConnectionState::Closing,
+            State::TimeWait => ConnectionState::TimeWait,
+            State::CloseWait => ConnectionState::CloseWait,
+            State::LastAck => ConnectionState::LastAck,
+            State::Closed => ConnectionState::Closed,
+        }
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Reports that the connection between `local` and `remote` moved from state `old` to state `new` because of `event`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub fn transition(
+    local: SocketAddrV4,
+    remote: SocketAddrV4,
+    old: ConnectionState,
+    new: ConnectionState,
+    event: &'static str,
+) {
+    #[cfg(feature = "tracing")]
+    ::tracing::trace!(
+        src_ip = %local.ip(),
+        src_port = local.port(),
+        dst_ip = %remote.ip(),
+        dst_port = remote.port(),
+        old_state = ?old,
+        new_state = ?new,
+        event,
+        "tcp state transition"
+    );
+}
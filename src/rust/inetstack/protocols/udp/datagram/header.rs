@@ -105,7 +105,12 @@ impl UdpHeader {
     /// Parses a buffer into a UDP header.
     pub fn parse(ipv4_hdr: &Ipv4Header, buf: DemiBuffer, checksum_offload: bool) -> Result<(Self, DemiBuffer), Fail> {
         match Self::parse_from_slice(ipv4_hdr, &buf[..], checksum_offload) {
-            Ok((udp_hdr, bytes)) => Ok((udp_hdr, DemiBuffer::from_slice(bytes)?)),
+            Ok((udp_hdr, bytes)) => {
+                // The payload is copied out, so carry the receive timestamp over.
+                let mut payload: DemiBuffer = DemiBuffer::from_slice(bytes)?;
+                payload.set_sw_timestamp(buf.sw_timestamp());
+                Ok((udp_hdr, payload))
+            },
             Err(e) => Err(e),
         }
     }
@@ -38,10 +38,12 @@ pub struct UdpDatagram {
     ipv4_hdr: Ipv4Header,
     /// UDP header.
     udp_hdr: UdpHeader,
-    /// Payload
+    /// Payload, preceded by the headers if they were prepended to it.
     data: DemiBuffer,
     /// Offload checksum to hardware?
     checksum_offload: bool,
+    /// Were the headers serialized into the headroom of the payload?
+    prepended: bool,
 }
 
 //==============================================================================
@@ -51,21 +53,65 @@ pub struct UdpDatagram {
 // Associate Functions for UDP Datagrams
 impl UdpDatagram {
     /// Creates a UDP packet.
+    ///
+    /// If the payload has enough headroom (e.g. because it was allocated with `alloc_sgarray()`), the headers are
+    /// serialized right away into it, so that the whole datagram is handed over to the runtime as a single buffer. Any
+    /// bytes that were in the headroom of the payload, including headers that were prepended to a clone of it, are
+    /// overwritten.
     pub fn new(
         ethernet2_hdr: Ethernet2Header,
         ipv4_hdr: Ipv4Header,
         udp_hdr: UdpHeader,
-        data: DemiBuffer,
+        mut data: DemiBuffer,
         checksum_offload: bool,
     ) -> Self {
+        let header_size: usize = ethernet2_hdr.compute_size() + ipv4_hdr.compute_size() + udp_hdr.size();
+        let prepended: bool = data.prepend(header_size).is_ok();
+        if prepended {
+            let (header, payload): (&mut [u8], &mut [u8]) = data.as_bytes_mut().split_at_mut(header_size);
+            Self::serialize_header(&ethernet2_hdr, &ipv4_hdr, &udp_hdr, header, payload, checksum_offload);
+        }
         Self {
             ethernet2_hdr,
             ipv4_hdr,
             udp_hdr,
             data,
             checksum_offload,
+            prepended,
         }
     }
+
+    /// Serializes the headers of a UDP datagram that carries `payload` into a slice.
+    fn serialize_header(
+        ethernet2_hdr: &Ethernet2Header,
+        ipv4_hdr: &Ipv4Header,
+        udp_hdr: &UdpHeader,
+        buf: &mut [u8],
+        payload: &[u8],
+        checksum_offload: bool,
+    ) {
+        let mut cur_pos: usize = 0;
+        let eth_hdr_size: usize = ethernet2_hdr.compute_size();
+        let udp_hdr_size: usize = udp_hdr.size();
+        let ipv4_payload_len: usize = udp_hdr_size + payload.len();
+
+        // Ethernet header.
+        ethernet2_hdr.serialize(&mut buf[cur_pos..(cur_pos + eth_hdr_size)]);
+        cur_pos += eth_hdr_size;
+
+        // IPV4 header.
+        let ipv4_hdr_size = ipv4_hdr.compute_size();
+        ipv4_hdr.serialize(&mut buf[cur_pos..(cur_pos + ipv4_hdr_size)], ipv4_payload_len);
+        cur_pos += ipv4_hdr_size;
+
+        // UDP header.
+        udp_hdr.serialize(
+            &mut buf[cur_pos..(cur_pos + udp_hdr_size)],
+            ipv4_hdr,
+            payload,
+            checksum_offload,
+        );
+    }
 }
 
 //==============================================================================
@@ -74,8 +120,11 @@ impl UdpDatagram {
 
 /// Packet Buffer Trait Implementation for UDP Datagrams
 impl PacketBuf for UdpDatagram {
-    /// Computes the header size of the target UDP datagram.
+    /// Computes the header size of the target UDP datagram. This is zero if the headers were prepended to the payload.
     fn header_size(&self) -> usize {
+        if self.prepended {
+            return 0;
+        }
         self.ethernet2_hdr.compute_size() + self.ipv4_hdr.compute_size() + self.udp_hdr.size()
     }
 
@@ -86,29 +135,16 @@ impl PacketBuf for UdpDatagram {
 
     /// Serializes the header of the target UDP datagram.
     fn write_header(&self, buf: &mut [u8]) {
-        let mut cur_pos: usize = 0;
-        let eth_hdr_size: usize = self.ethernet2_hdr.compute_size();
-        let udp_hdr_size: usize = self.udp_hdr.size();
-        let ipv4_payload_len: usize = udp_hdr_size + self.data.len();
-
-        // Ethernet header.
-        self.ethernet2_hdr
-            .serialize(&mut buf[cur_pos..(cur_pos + eth_hdr_size)]);
-        cur_pos += eth_hdr_size;
-
-        // IPV4 header.
-        let ipv4_hdr_size = self.ipv4_hdr.compute_size();
-        self.ipv4_hdr
-            .serialize(&mut buf[cur_pos..(cur_pos + ipv4_hdr_size)], ipv4_payload_len);
-        cur_pos += ipv4_hdr_size;
-
-        // UDP header.
-        self.udp_hdr.serialize(
-            &mut buf[cur_pos..(cur_pos + udp_hdr_size)],
-            &self.ipv4_hdr,
-            &self.data[..],
-            self.checksum_offload,
-        );
+        if !self.prepended {
+            Self::serialize_header(
+                &self.ethernet2_hdr,
+                &self.ipv4_hdr,
+                &self.udp_hdr,
+                buf,
+                &self.data[..],
+                self.checksum_offload,
+            );
+        }
     }
 
     /// Returns the payload of the target UDP datagram.
@@ -193,4 +229,50 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_udp_datagram_prepended_header() -> Result<()> {
+        // Total header size.
+        const HEADER_SIZE: usize = ETHERNET2_HEADER_SIZE + (IPV4_HEADER_MIN_SIZE as usize) + UDP_HEADER_SIZE;
+
+        // Build fake headers.
+        let ethernet2_hdr: Ethernet2Header = Ethernet2Header::new(
+            MacAddress::new([0xd, 0xe, 0xa, 0xd, 0x0, 0x0]),
+            MacAddress::new([0xb, 0xe, 0xe, 0xf, 0x0, 0x0]),
+            EtherType2::Ipv4,
+        );
+        let ipv4_hdr: Ipv4Header = Ipv4Header::new(
+            Ipv4Addr::new(198, 0, 0, 1),
+            Ipv4Addr::new(198, 0, 0, 2),
+            IpProtocol::UDP,
+        );
+
+        // Build expected header, from a payload without headroom.
+        let bytes: [u8; 8] = [0x0, 0x1, 0x0, 0x1, 0x0, 0x1, 0x0, 0x1];
+        let data: DemiBuffer = DemiBuffer::from_slice(&bytes).expect("bytes should be shorter than u16::MAX");
+        let udp_hdr: UdpHeader = UdpHeader::new(0x32, 0x45);
+        let datagram: UdpDatagram = UdpDatagram::new(ethernet2_hdr.clone(), ipv4_hdr, udp_hdr, data, false);
+        crate::ensure_eq!(datagram.header_size(), HEADER_SIZE);
+        let mut hdr: [u8; HEADER_SIZE] = [0; HEADER_SIZE];
+        datagram.write_header(&mut hdr);
+
+        // Payload with just enough headroom for the headers.
+        let mut data: DemiBuffer = DemiBuffer::new_with_headroom(bytes.len() as u16, HEADER_SIZE as u16)?;
+        data.copy_from_slice(&bytes);
+        let udp_hdr: UdpHeader = UdpHeader::new(0x32, 0x45);
+        let datagram: UdpDatagram = UdpDatagram::new(ethernet2_hdr, ipv4_hdr, udp_hdr, data.clone(), false);
+
+        // Do it.
+        crate::ensure_eq!(datagram.header_size(), 0);
+        crate::ensure_eq!(datagram.body_size(), HEADER_SIZE + bytes.len());
+        let frame: DemiBuffer = match datagram.take_body() {
+            Some(frame) => frame,
+            None => anyhow::bail!("datagram should have a body"),
+        };
+        crate::ensure_eq!(&frame[..HEADER_SIZE], &hdr[..]);
+        crate::ensure_eq!(&frame[HEADER_SIZE..], &bytes[..]);
+        crate::ensure_eq!(unsafe { frame.as_ptr().add(HEADER_SIZE) }, data.as_ptr());
+
+        Ok(())
+    }
 }
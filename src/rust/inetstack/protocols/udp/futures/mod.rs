@@ -2,9 +2,13 @@
 // Licensed under the MIT license.
 
 mod pop;
+mod pop_multiple;
 
 //==============================================================================
 // Exports
 //==============================================================================
 
-pub use self::pop::UdpPopFuture;
+pub use self::{
+    pop::UdpPopFuture,
+    pop_multiple::UdpPopMultipleFuture,
+};
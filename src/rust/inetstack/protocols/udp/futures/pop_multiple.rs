@@ -0,0 +1,83 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::{
+    inetstack::protocols::udp::queue::{
+        SharedQueue,
+        SharedQueueSlot,
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+    },
+};
+use ::std::{
+    future::Future,
+    net::SocketAddrV4,
+    pin::Pin,
+    task::{
+        Context,
+        Poll,
+        Waker,
+    },
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Batched Pop Operation Descriptor
+pub struct UdpPopMultipleFuture {
+    /// Shared receiving queue.
+    recv_queue: SharedQueue<SharedQueueSlot<DemiBuffer>>,
+    /// Maximum number of datagrams to pop.
+    max_msgs: usize,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate Functions for Batched Pop Operation Descriptor
+impl UdpPopMultipleFuture {
+    /// Creates a batched pop operation descriptor.
+    pub fn new(recv_queue: SharedQueue<SharedQueueSlot<DemiBuffer>>, max_msgs: usize) -> Self {
+        Self { recv_queue, max_msgs }
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Future Trait implementation for Batched Pop Operation Descriptor
+impl Future for UdpPopMultipleFuture {
+    type Output = Result<Vec<(SocketAddrV4, DemiBuffer)>, Fail>;
+
+    /// Polls the target batched pop operation descriptor. It completes as soon as at least one datagram is available,
+    /// with all datagrams that are available at that time, up to the maximum.
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let self_: &mut UdpPopMultipleFuture = self.get_mut();
+        let mut msgs: Vec<(SocketAddrV4, DemiBuffer)> = Vec::new();
+        while msgs.len() < self_.max_msgs {
+            match self_.recv_queue.try_pop() {
+                Ok(Some(msg)) => msgs.push((msg.remote, msg.data)),
+                Ok(None) => break,
+                Err(e) if msgs.is_empty() => return Poll::Ready(Err(e)),
+                Err(_) => break,
+            }
+        }
+
+        if msgs.is_empty() {
+            let waker: &Waker = ctx.waker();
+            waker.wake_by_ref();
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(msgs))
+        }
+    }
+}
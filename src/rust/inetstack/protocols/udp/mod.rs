@@ -20,7 +20,13 @@ mod tests;
 //==============================================================================
 
 pub use self::{
-    datagram::UdpHeader,
-    futures::UdpPopFuture,
+    datagram::{
+        UdpDatagram,
+        UdpHeader,
+    },
+    futures::{
+        UdpPopFuture,
+        UdpPopMultipleFuture,
+    },
     peer::UdpPeer,
 };
@@ -9,11 +9,16 @@ use super::{
     datagram::{
         UdpDatagram,
         UdpHeader,
+        UDP_HEADER_SIZE,
+    },
+    futures::{
+        UdpPopFuture,
+        UdpPopMultipleFuture,
     },
-    futures::UdpPopFuture,
     queue::{
         SharedQueue,
         SharedQueueSlot,
+        SourceFilter,
         UdpQueue,
     },
 };
@@ -28,15 +33,20 @@ use crate::{
             EphemeralPorts,
             IpProtocol,
         },
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            IPV4_HEADER_MIN_SIZE,
+        },
         queue::InetQueue,
     },
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
         network::{
+            check_link_up,
             types::MacAddress,
             NetworkRuntime,
+            PacketBuf,
         },
         queue::{
             BackgroundTask,
@@ -49,17 +59,17 @@ use crate::{
         TaskHandle,
     },
 };
-use ::rand::{
-    prelude::SmallRng,
-    SeedableRng,
-};
 use ::std::{
     cell::{
+        Cell,
         Ref,
         RefCell,
         RefMut,
     },
-    collections::HashMap,
+    collections::{
+        HashMap,
+        HashSet,
+    },
     net::{
         Ipv4Addr,
         SocketAddrV4,
@@ -74,9 +84,6 @@ use crate::timer;
 // Constants
 //======================================================================================================================
 
-// Maximum size for receive queues (in messages).
-const RECV_QUEUE_MAX_SIZE: usize = 1024;
-
 // Maximum size for send queues (in messages).
 const SEND_QUEUE_MAX_SIZE: usize = 1024;
 
@@ -86,25 +93,27 @@ const SEND_QUEUE_MAX_SIZE: usize = 1024;
 /// Per-queue metadata: UDP Control Block
 
 /// UDP Peer
-pub struct UdpPeer<const N: usize> {
+pub struct UdpPeer {
     /// Underlying runtime.
-    rt: Rc<dyn NetworkRuntime<N>>,
+    rt: Rc<dyn NetworkRuntime>,
     /// Underlying ARP peer.
-    arp: ArpPeer<N>,
+    arp: ArpPeer,
     /// Ephemeral ports.
-    ephemeral_ports: EphemeralPorts,
+    ephemeral_ports: Rc<RefCell<EphemeralPorts>>,
     /// Opened sockets.
-    qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>>,
+    qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
     /// Bound sockets to look up incoming packets.
     bound: HashMap<SocketAddrV4, QDesc>,
     /// Queue of unset datagrams. This is shared across fast/slow paths.
     send_queue: SharedQueue<SharedQueueSlot<DemiBuffer>>,
-    /// Local link address.
-    local_link_addr: MacAddress,
-    /// Local IPv4 address.
-    local_ipv4_addr: Ipv4Addr,
+    /// Local link address. This is shared with the background co-routine, so that it may change at runtime.
+    local_link_addr: Rc<Cell<MacAddress>>,
+    /// Local IPv4 address. This is shared with the background co-routine, so that it may change at runtime.
+    local_ipv4_addr: Rc<Cell<Ipv4Addr>>,
     /// Offload checksum to hardware?
     checksum_offload: bool,
+    /// Maximum number of datagrams buffered in the receive queue of a socket.
+    recv_queue_depth: usize,
 
     /// The background co-routine sends unset UDP packets.
     /// We annotate it as unused because the compiler believes that it is never called which is not the case.
@@ -117,24 +126,27 @@ pub struct UdpPeer<const N: usize> {
 //======================================================================================================================
 
 /// Associate functions for [UdpPeer].
-impl<const N: usize> UdpPeer<N> {
+impl UdpPeer {
     /// Creates a Udp peer.
     pub fn new(
-        rt: Rc<dyn NetworkRuntime<N>>,
+        rt: Rc<dyn NetworkRuntime>,
         scheduler: Scheduler,
-        qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>>,
-        rng_seed: [u8; 32],
+        qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
+        ephemeral_ports: Rc<RefCell<EphemeralPorts>>,
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         offload_checksum: bool,
-        arp: ArpPeer<N>,
+        recv_queue_depth: usize,
+        arp: ArpPeer,
     ) -> Result<Self, Fail> {
         let send_queue: SharedQueue<SharedQueueSlot<DemiBuffer>> =
             SharedQueue::<SharedQueueSlot<DemiBuffer>>::new(SEND_QUEUE_MAX_SIZE);
+        let local_link_addr: Rc<Cell<MacAddress>> = Rc::new(Cell::new(local_link_addr));
+        let local_ipv4_addr: Rc<Cell<Ipv4Addr>> = Rc::new(Cell::new(local_ipv4_addr));
         let future = Self::background_sender(
             rt.clone(),
-            local_ipv4_addr,
-            local_link_addr,
+            local_ipv4_addr.clone(),
+            local_link_addr.clone(),
             offload_checksum,
             arp.clone(),
             send_queue.clone(),
@@ -149,8 +161,6 @@ impl<const N: usize> UdpPeer<N> {
                 ))
             },
         };
-        let mut rng: SmallRng = SmallRng::from_seed(rng_seed);
-        let ephemeral_ports: EphemeralPorts = EphemeralPorts::new(&mut rng);
         Ok(Self {
             rt: rt.clone(),
             arp,
@@ -161,39 +171,47 @@ impl<const N: usize> UdpPeer<N> {
             local_link_addr,
             local_ipv4_addr,
             checksum_offload: offload_checksum,
+            recv_queue_depth,
             background: handle,
         })
     }
 
     /// Asynchronously send unsent datagrams to remote peer.
     async fn background_sender(
-        rt: Rc<dyn NetworkRuntime<N>>,
-        local_ipv4_addr: Ipv4Addr,
-        local_link_addr: MacAddress,
+        rt: Rc<dyn NetworkRuntime>,
+        local_ipv4_addr: Rc<Cell<Ipv4Addr>>,
+        local_link_addr: Rc<Cell<MacAddress>>,
         offload_checksum: bool,
-        arp: ArpPeer<N>,
+        arp: ArpPeer,
         mut rx: SharedQueue<SharedQueueSlot<DemiBuffer>>,
     ) {
         loop {
             // Grab next unsent datagram.
             match rx.pop().await {
-                // Resolve remote address.
-                Ok(SharedQueueSlot { local, remote, data }) => match arp.query(remote.ip().clone()).await {
-                    // Send datagram.
-                    Ok(link_addr) => {
-                        Self::do_send(
-                            rt.clone(),
-                            local_ipv4_addr,
-                            local_link_addr,
-                            link_addr,
-                            data,
-                            &local,
-                            &remote,
-                            offload_checksum,
-                        );
-                    },
-                    // ARP query failed.
-                    Err(e) => warn!("Failed to send UDP datagram: {:?}", e),
+                Ok(SharedQueueSlot { local, remote, data }) => {
+                    // Resolve remote address. Multicast groups map directly to link addresses (RFC 1112, Section 6.4).
+                    let link_addr: Result<MacAddress, Fail> = if remote.ip().is_multicast() {
+                        Ok(MacAddress::from_ipv4_multicast(*remote.ip()))
+                    } else {
+                        arp.query(remote.ip().clone()).await
+                    };
+                    match link_addr {
+                        // Send datagram.
+                        Ok(link_addr) => {
+                            Self::do_send(
+                                rt.clone(),
+                                local_ipv4_addr.get(),
+                                local_link_addr.get(),
+                                link_addr,
+                                data,
+                                &local,
+                                &remote,
+                                offload_checksum,
+                            );
+                        },
+                        // ARP query failed.
+                        Err(e) => warn!("Failed to send UDP datagram: {:?}", e),
+                    }
                 },
                 // Pop from shared queue failed.
                 Err(e) => warn!("Failed to send UDP datagram: {:?}", e),
@@ -205,8 +223,8 @@ impl<const N: usize> UdpPeer<N> {
     pub fn do_socket(&mut self) -> Result<QDesc, Fail> {
         #[cfg(feature = "profiler")]
         timer!("udp::socket");
-        let mut qtable: RefMut<IoQueueTable<InetQueue<N>>> = self.qtable.borrow_mut();
-        let new_qd: QDesc = qtable.alloc(InetQueue::Udp(UdpQueue::new()));
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = self.qtable.borrow_mut();
+        let new_qd: QDesc = qtable.alloc(InetQueue::Udp(UdpQueue::new()))?;
         Ok(new_qd)
     }
 
@@ -214,7 +232,7 @@ impl<const N: usize> UdpPeer<N> {
     pub fn do_bind(&mut self, qd: QDesc, mut addr: SocketAddrV4) -> Result<(), Fail> {
         #[cfg(feature = "profiler")]
         timer!("udp::bind");
-        let mut qtable: RefMut<IoQueueTable<InetQueue<N>>> = self.qtable.borrow_mut();
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = self.qtable.borrow_mut();
         if self.bound.contains_key(&addr) {
             return Err(Fail::new(libc::EADDRINUSE, "address in use"));
         }
@@ -227,18 +245,18 @@ impl<const N: usize> UdpPeer<N> {
                 }
                 // Check if this is an ephemeral port or a wildcard one.
                 if EphemeralPorts::is_private(addr.port()) {
-                    // Allocate ephemeral port from the pool, to leave  ephemeral port allocator in a consistent state.
-                    self.ephemeral_ports.alloc_port(addr.port())?
+                    // Reserve the port, so that the ephemeral port allocator does not hand it out.
+                    self.ephemeral_ports.borrow_mut().alloc_port(addr.port())?
                 } else if addr.port() == 0 {
                     // Allocate ephemeral port.
                     // TODO: we should free this when closing.
-                    let new_port: u16 = self.ephemeral_ports.alloc_any()?;
+                    let new_port: u16 = self.ephemeral_ports.borrow_mut().alloc_any()?;
                     addr.set_port(new_port);
                 }
 
                 // Bind endpoint and create a receiver-side shared queue.
                 queue.set_addr(addr);
-                queue.set_recv_queue(SharedQueue::<SharedQueueSlot<DemiBuffer>>::new(RECV_QUEUE_MAX_SIZE));
+                queue.set_recv_queue(SharedQueue::<SharedQueueSlot<DemiBuffer>>::new(self.recv_queue_depth));
                 self.bound.insert(addr, qd);
                 Ok(())
             },
@@ -251,7 +269,7 @@ impl<const N: usize> UdpPeer<N> {
             Err(e) => {
                 // Rollback ephemeral port allocation.
                 if EphemeralPorts::is_private(addr.port()) {
-                    self.ephemeral_ports.free(addr.port());
+                    self.ephemeral_ports.borrow_mut().free(addr.port());
                 }
                 Err(e)
             },
@@ -262,36 +280,107 @@ impl<const N: usize> UdpPeer<N> {
     pub fn do_close(&mut self, qd: QDesc) -> Result<(), Fail> {
         #[cfg(feature = "profiler")]
         timer!("udp::close");
-        let mut qtable: RefMut<IoQueueTable<InetQueue<N>>> = self.qtable.borrow_mut();
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = self.qtable.borrow_mut();
         // Lookup associated endpoint.
         match qtable.free(&qd) {
-            Some(InetQueue::Udp(queue)) => match queue.get_addr() {
-                Ok(addr) => {
-                    self.bound.remove(&addr);
-                    Ok(())
-                },
-                Err(e) => Err(e),
+            Some(InetQueue::Udp(queue)) => {
+                // Stop listening to the groups that no other socket is a member of.
+                if queue.get_memberships().next().is_some() {
+                    Self::update_multicast_filter(&self.rt, &qtable);
+                }
+                match queue.get_addr() {
+                    Ok(addr) => {
+                        self.bound.remove(&addr);
+                        Ok(())
+                    },
+                    Err(e) => Err(e),
+                }
             },
             _ => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
         }
     }
 
+    /// Returns the local endpoint of the socket referred to by `qd`. Sockets that are not bound yet report the
+    /// unspecified address and port zero.
+    pub fn local_endpoint(&self, qd: QDesc) -> Result<SocketAddrV4, Fail> {
+        match self.qtable.borrow().get(&qd) {
+            Some(InetQueue::Udp(queue)) if queue.is_bound() => queue.get_addr(),
+            Some(InetQueue::Udp(_)) => Ok(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)),
+            _ => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Changes the local IPv4 and link addresses that datagrams are sent from. Sockets that are bound to the old IPv4
+    /// address are moved over to the new one, so that they keep receiving datagrams.
+    pub fn set_local_identity(&mut self, local_ipv4_addr: Ipv4Addr, local_link_addr: MacAddress) {
+        let old_ipv4_addr: Ipv4Addr = self.local_ipv4_addr.replace(local_ipv4_addr);
+        self.local_link_addr.set(local_link_addr);
+        if old_ipv4_addr == local_ipv4_addr {
+            return;
+        }
+
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = self.qtable.borrow_mut();
+        let rebound: Vec<SocketAddrV4> = self
+            .bound
+            .keys()
+            .filter(|addr| *addr.ip() == old_ipv4_addr)
+            .cloned()
+            .collect();
+        for addr in rebound {
+            let new_addr: SocketAddrV4 = SocketAddrV4::new(local_ipv4_addr, addr.port());
+            if self.bound.contains_key(&new_addr) {
+                warn!(
+                    "Cannot move UDP socket from {:?}: {:?} is already bound",
+                    addr, new_addr
+                );
+                continue;
+            }
+            if let Some(qd) = self.bound.remove(&addr) {
+                if let Some(InetQueue::Udp(queue)) = qtable.get_resolved_mut(&qd) {
+                    queue.set_addr(new_addr);
+                }
+                self.bound.insert(new_addr, qd);
+            }
+        }
+    }
+
+    /// Returns the maximum number of bytes that may be pushed in a single datagram to a UDP socket.
+    pub fn max_push_size(&self, qd: QDesc) -> Result<usize, Fail> {
+        match self.qtable.borrow().get(&qd) {
+            Some(InetQueue::Udp(_)) => Ok(self.max_payload_size()),
+            _ => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Returns the number of bytes in the next datagram that would be popped from the UDP socket referred to by `qd`,
+    /// or zero if no datagram was received yet.
+    pub fn bytes_available(&self, qd: QDesc) -> Result<usize, Fail> {
+        match self.qtable.borrow().get(&qd) {
+            Some(InetQueue::Udp(queue)) => Ok(queue.get_next_datagram_size()),
+            _ => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
     /// Pushes data to a remote UDP peer.
     pub fn do_pushto(&self, qd: QDesc, data: DemiBuffer, remote: SocketAddrV4) -> Result<(), Fail> {
         #[cfg(feature = "profiler")]
         timer!("udp::pushto");
-        let qtable: Ref<IoQueueTable<InetQueue<N>>> = self.qtable.borrow();
+        check_link_up(self.rt.as_ref())?;
+        let qtable: Ref<IoQueueTable<InetQueue>> = self.qtable.borrow();
         // Lookup associated endpoint.
         match qtable.get(&qd) {
             Some(InetQueue::Udp(queue)) => {
                 let local: SocketAddrV4 = queue.get_addr()?;
 
+                // Check if the datagram fits in a single packet.
+                self.check_payload_size(qd, &data)?;
+
                 // Fast path: try to send the datagram immediately.
                 if let Some(link_addr) = self.arp.try_query(remote.ip().clone()) {
                     Ok(Self::do_send(
                         self.rt.clone(),
-                        self.local_ipv4_addr,
-                        self.local_link_addr,
+                        self.local_ipv4_addr.get(),
+                        self.local_link_addr.get(),
                         link_addr,
                         data,
                         &local,
@@ -308,11 +397,107 @@ impl<const N: usize> UdpPeer<N> {
         }
     }
 
+    /// Pushes a batch of datagrams to remote UDP peers in a single pass. Datagrams that can be sent right away are
+    /// handed over to the network runtime as a single batch, and consecutive datagrams to the same destination share
+    /// their Ethernet and IPv4 headers. Upon success, the outcome of each datagram is returned, in order.
+    pub fn do_pushto_batch(
+        &self,
+        qd: QDesc,
+        datagrams: Vec<(DemiBuffer, SocketAddrV4)>,
+    ) -> Result<Vec<Result<(), Fail>>, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("udp::pushto_batch");
+        check_link_up(self.rt.as_ref())?;
+        let qtable: Ref<IoQueueTable<InetQueue>> = self.qtable.borrow();
+        // Lookup associated endpoint.
+        let local: SocketAddrV4 = match qtable.get(&qd) {
+            Some(InetQueue::Udp(queue)) => queue.get_addr()?,
+            _ => return Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        };
+
+        let mut results: Vec<Result<(), Fail>> = Vec::with_capacity(datagrams.len());
+        let mut pkts: Vec<Box<dyn PacketBuf>> = Vec::with_capacity(datagrams.len());
+        // Headers for the destination of the previous datagram, if it was resolved.
+        let mut template: Option<(Ipv4Addr, Ethernet2Header, Ipv4Header)> = None;
+        for (data, remote) in datagrams {
+            if let Err(e) = self.check_payload_size(qd, &data) {
+                results.push(Err(e));
+                continue;
+            }
+
+            let headers: Option<(Ethernet2Header, Ipv4Header)> = match template {
+                Some((ipv4_addr, ref ethernet2_hdr, ipv4_hdr)) if ipv4_addr == *remote.ip() => {
+                    Some((ethernet2_hdr.clone(), ipv4_hdr))
+                },
+                _ => match self.arp.try_query(*remote.ip()) {
+                    Some(link_addr) => {
+                        let ethernet2_hdr: Ethernet2Header =
+                            Ethernet2Header::new(link_addr, self.local_link_addr.get(), EtherType2::Ipv4);
+                        let ipv4_hdr: Ipv4Header =
+                            Ipv4Header::new(self.local_ipv4_addr.get(), *remote.ip(), IpProtocol::UDP);
+                        template = Some((*remote.ip(), ethernet2_hdr.clone(), ipv4_hdr));
+                        Some((ethernet2_hdr, ipv4_hdr))
+                    },
+                    None => {
+                        template = None;
+                        None
+                    },
+                },
+            };
+
+            match headers {
+                // Fast path: send the datagram along with the rest of the batch.
+                Some((ethernet2_hdr, mut ipv4_hdr)) => {
+                    ipv4_hdr.set_identification(self.rt.next_ipv4_id());
+                    let udp_header: UdpHeader = UdpHeader::new(local.port(), remote.port());
+                    debug!("UDP send {:?}", udp_header);
+                    pkts.push(Box::new(UdpDatagram::new(
+                        ethernet2_hdr,
+                        ipv4_hdr,
+                        udp_header,
+                        data,
+                        self.checksum_offload,
+                    )));
+                    results.push(Ok(()));
+                },
+                // Slow path: Defer send operation to the async path.
+                None => results.push(self.send_queue.push(SharedQueueSlot { local, remote, data })),
+            }
+        }
+
+        if !pkts.is_empty() {
+            self.rt.transmit_batch(pkts);
+        }
+        Ok(results)
+    }
+
+    /// Returns the maximum payload size for a datagram (in bytes). We do not support IP fragmentation, thus a datagram
+    /// along with its IPv4 and UDP headers must fit in the MTU of the underlying NIC.
+    fn max_payload_size(&self) -> usize {
+        self.rt.mtu() - (IPV4_HEADER_MIN_SIZE as usize) - UDP_HEADER_SIZE
+    }
+
+    /// Checks if a datagram fits in a single packet.
+    fn check_payload_size(&self, qd: QDesc, data: &DemiBuffer) -> Result<(), Fail> {
+        let max_payload_size: usize = self.max_payload_size();
+        if data.len() > max_payload_size {
+            let cause: String = format!(
+                "datagram is too large (qd={:?}, len={:?}, max={:?})",
+                qd,
+                data.len(),
+                max_payload_size
+            );
+            error!("do_pushto(): {}", cause);
+            return Err(Fail::new(libc::EMSGSIZE, &cause));
+        }
+        Ok(())
+    }
+
     /// Pops data from a socket.
     pub fn do_pop(&self, qd: QDesc, size: Option<usize>) -> UdpPopFuture {
         #[cfg(feature = "profiler")]
         timer!("udp::pop");
-        let qtable: Ref<IoQueueTable<InetQueue<N>>> = self.qtable.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = self.qtable.borrow();
         // Lookup associated receiver-side shared queue.
         match qtable.get(&qd) {
             // Issue pop operation.
@@ -321,11 +506,159 @@ impl<const N: usize> UdpPeer<N> {
         }
     }
 
+    /// Pops up to `max_msgs` datagrams at once.
+    pub fn do_pop_multiple(&self, qd: QDesc, max_msgs: usize) -> UdpPopMultipleFuture {
+        #[cfg(feature = "profiler")]
+        timer!("udp::pop_multiple");
+        let qtable: Ref<IoQueueTable<InetQueue>> = self.qtable.borrow();
+        // Lookup associated receiver-side shared queue.
+        match qtable.get(&qd) {
+            // Issue pop operation.
+            Some(InetQueue::Udp(queue)) => UdpPopMultipleFuture::new(queue.get_recv_queue(), max_msgs),
+            _ => panic!("invalid queue descriptor"),
+        }
+    }
+
+    /// Pops the oldest datagram that is pending in the receive queue of a socket, without blocking. If no datagram is
+    /// pending, this returns `None`.
+    pub fn do_try_pop(&self, qd: QDesc) -> Result<Option<(SocketAddrV4, DemiBuffer)>, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("udp::try_pop");
+        let qtable: Ref<IoQueueTable<InetQueue>> = self.qtable.borrow();
+        // Lookup associated receiver-side shared queue.
+        match qtable.get(&qd) {
+            Some(InetQueue::Udp(queue)) => {
+                // Sockets only get a receive queue once they are bound.
+                queue.get_addr()?;
+                let recv_queue: SharedQueue<SharedQueueSlot<DemiBuffer>> = queue.get_recv_queue();
+                Ok(recv_queue.try_pop()?.map(|msg| (msg.remote, msg.data)))
+            },
+            _ => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Returns the number of incoming datagrams that were dropped because the receive queue of a socket was full.
+    pub fn get_recv_dropped(&self, qd: QDesc) -> Result<u64, Fail> {
+        match self.qtable.borrow().get(&qd) {
+            Some(InetQueue::Udp(queue)) => Ok(queue.get_recv_dropped()),
+            _ => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Makes a UDP socket join a multicast group, receiving datagrams from any source (RFC 1112).
+    pub fn join_multicast(&mut self, qd: QDesc, group: Ipv4Addr) -> Result<(), Fail> {
+        Self::check_multicast_group("join_multicast", &group)?;
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = self.qtable.borrow_mut();
+        match qtable.get_mut(&qd) {
+            Some(InetQueue::Udp(queue)) => {
+                if queue.get_membership(&group).is_some() {
+                    let cause: String = format!("socket is already a member of group (qd={:?}, group={:?})", qd, group);
+                    error!("join_multicast(): {}", cause);
+                    return Err(Fail::new(libc::EADDRINUSE, &cause));
+                }
+                queue.get_or_join_membership(group, SourceFilter::Exclude(HashSet::new()));
+            },
+            _ => return Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+        Self::update_multicast_filter(&self.rt, &qtable);
+        Ok(())
+    }
+
+    /// Makes a UDP socket receive datagrams that `src` sends to a multicast group, as in source-specific multicast
+    /// (RFC 4607). The socket joins the group if it is not a member of it yet, and only receives datagrams from the
+    /// sources that it joined this way.
+    pub fn join_source_specific_multicast(&mut self, qd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        Self::check_multicast_group("join_source_specific_multicast", &group)?;
+        if src.is_unspecified() || src.is_multicast() || src.is_broadcast() {
+            let cause: String = format!("source should be a unicast address (src={:?})", src);
+            error!("join_source_specific_multicast(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = self.qtable.borrow_mut();
+        match qtable.get_mut(&qd) {
+            Some(InetQueue::Udp(queue)) => {
+                match queue.get_or_join_membership(group, SourceFilter::Include(HashSet::new())) {
+                    SourceFilter::Include(sources) => {
+                        sources.insert(src);
+                    },
+                    SourceFilter::Exclude(_) => {
+                        let cause: String = format!(
+                            "socket receives from any source of group (qd={:?}, group={:?})",
+                            qd, group
+                        );
+                        error!("join_source_specific_multicast(): {}", cause);
+                        return Err(Fail::new(libc::EINVAL, &cause));
+                    },
+                }
+            },
+            _ => return Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+        Self::update_multicast_filter(&self.rt, &qtable);
+        Ok(())
+    }
+
+    /// Stops a UDP socket from receiving datagrams that `src` sends to a multicast group, which the socket should have
+    /// joined with [UdpPeer::join_multicast].
+    pub fn block_source(&mut self, qd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        Self::check_multicast_group("block_source", &group)?;
+        let mut qtable: RefMut<IoQueueTable<InetQueue>> = self.qtable.borrow_mut();
+        match qtable.get_mut(&qd) {
+            Some(InetQueue::Udp(queue)) => {
+                if queue.get_membership(&group).is_none() {
+                    let cause: String = format!("socket is not a member of group (qd={:?}, group={:?})", qd, group);
+                    error!("block_source(): {}", cause);
+                    return Err(Fail::new(libc::EADDRNOTAVAIL, &cause));
+                }
+                match queue.get_or_join_membership(group, SourceFilter::Exclude(HashSet::new())) {
+                    SourceFilter::Exclude(sources) => {
+                        sources.insert(src);
+                        Ok(())
+                    },
+                    SourceFilter::Include(_) => {
+                        let cause: String = format!(
+                            "socket receives from specific sources of group (qd={:?}, group={:?})",
+                            qd, group
+                        );
+                        error!("block_source(): {}", cause);
+                        Err(Fail::new(libc::EINVAL, &cause))
+                    },
+                }
+            },
+            _ => Err(Fail::new(libc::EBADF, "invalid queue descriptor")),
+        }
+    }
+
+    /// Checks if `group` is a multicast address.
+    fn check_multicast_group(caller: &str, group: &Ipv4Addr) -> Result<(), Fail> {
+        if !group.is_multicast() {
+            let cause: String = format!("not a multicast group (group={:?})", group);
+            error!("{}(): {}", caller, cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        Ok(())
+    }
+
+    /// Programs the runtime to receive the multicast groups that any UDP socket is a member of.
+    fn update_multicast_filter(rt: &Rc<dyn NetworkRuntime>, qtable: &IoQueueTable<InetQueue>) {
+        let mut addrs: Vec<MacAddress> = Vec::new();
+        for (_, queue) in qtable.get_values() {
+            if let InetQueue::Udp(queue) = queue {
+                for group in queue.get_memberships() {
+                    let addr: MacAddress = MacAddress::from_ipv4_multicast(*group);
+                    if !addrs.contains(&addr) {
+                        addrs.push(addr);
+                    }
+                }
+            }
+        }
+        rt.set_multicast_filter(&addrs);
+    }
+
     /// Consumes the payload from a buffer.
     pub fn do_receive(&mut self, ipv4_hdr: &Ipv4Header, buf: DemiBuffer) -> Result<(), Fail> {
         #[cfg(feature = "profiler")]
         timer!("udp::receive");
-        let qtable: Ref<IoQueueTable<InetQueue<N>>> = self.qtable.borrow();
+        let qtable: Ref<IoQueueTable<InetQueue>> = self.qtable.borrow();
         // Parse datagram.
         let (hdr, data): (UdpHeader, DemiBuffer) = UdpHeader::parse(ipv4_hdr, buf, self.checksum_offload)?;
         debug!("UDP received {:?}", hdr);
@@ -333,17 +666,17 @@ impl<const N: usize> UdpPeer<N> {
         let local: SocketAddrV4 = SocketAddrV4::new(ipv4_hdr.get_dest_addr(), hdr.dest_port());
         let remote: SocketAddrV4 = SocketAddrV4::new(ipv4_hdr.get_src_addr(), hdr.src_port());
 
-        let recv_queue: SharedQueue<SharedQueueSlot<DemiBuffer>> = match self.bound.get(&local) {
-            Some(qd) => match qtable.get(&qd) {
-                Some(InetQueue::Udp(queue)) => queue.get_recv_queue(),
+        let queue: &UdpQueue = match self.bound.get(&local) {
+            Some(qd) => match qtable.get_resolved(&qd) {
+                Some(InetQueue::Udp(queue)) => queue,
                 _ => return Err(Fail::new(libc::ENOTCONN, "port not bound")),
             },
             None => {
                 // Handle wildcard address.
                 let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, hdr.dest_port());
                 match self.bound.get(&local) {
-                    Some(qd) => match qtable.get(&qd) {
-                        Some(InetQueue::Udp(queue)) => queue.get_recv_queue(),
+                    Some(qd) => match qtable.get_resolved(&qd) {
+                        Some(InetQueue::Udp(queue)) => queue,
                         _ => return Err(Fail::new(libc::ENOTCONN, "port not bound")),
                     },
                     // TODO: Send ICMPv4 error in this condition.
@@ -353,6 +686,32 @@ impl<const N: usize> UdpPeer<N> {
         };
         // TODO: Drop this packet if local address/port pair is not bound.
 
+        // Drop multicast datagrams unless the socket is a member of the group and admits their source.
+        if local.ip().is_multicast() {
+            match queue.get_membership(local.ip()) {
+                Some(filter) if filter.admits(remote.ip()) => (),
+                Some(_) => {
+                    debug!("UDP source {:?} filtered out of group {:?}", remote.ip(), local.ip());
+                    return Ok(());
+                },
+                None => {
+                    debug!(
+                        "UDP socket is not a member of group {:?}, dropping datagram",
+                        local.ip()
+                    );
+                    return Ok(());
+                },
+            }
+        }
+
+        // Drop the datagram if the receive queue is full, as the kernel does when SO_RCVBUF overflows.
+        let recv_queue: SharedQueue<SharedQueueSlot<DemiBuffer>> = queue.get_recv_queue();
+        if recv_queue.is_full() {
+            debug!("UDP receive queue full, dropping datagram for {:?}", local);
+            queue.increment_recv_dropped();
+            return Ok(());
+        }
+
         // Push data to the receiver-side shared queue. This will cause the
         // associated pool operation to be ready.
         recv_queue.push(SharedQueueSlot { local, remote, data }).unwrap();
@@ -362,7 +721,7 @@ impl<const N: usize> UdpPeer<N> {
 
     /// Sends a UDP datagram.
     fn do_send(
-        rt: Rc<dyn NetworkRuntime<N>>,
+        rt: Rc<dyn NetworkRuntime>,
         local_ipv4_addr: Ipv4Addr,
         local_link_addr: MacAddress,
         remote_link_addr: MacAddress,
@@ -373,9 +732,11 @@ impl<const N: usize> UdpPeer<N> {
     ) {
         let udp_header: UdpHeader = UdpHeader::new(local.port(), remote.port());
         debug!("UDP send {:?}", udp_header);
+        let mut ipv4_hdr: Ipv4Header = Ipv4Header::new(local_ipv4_addr, remote.ip().clone(), IpProtocol::UDP);
+        ipv4_hdr.set_identification(rt.next_ipv4_id());
         let datagram = UdpDatagram::new(
             Ethernet2Header::new(remote_link_addr, local_link_addr, EtherType2::Ipv4),
-            Ipv4Header::new(local_ipv4_addr, remote.ip().clone(), IpProtocol::UDP),
+            ipv4_hdr,
             udp_header,
             buf,
             offload_checksum,
@@ -8,7 +8,11 @@
 use crate::runtime::{
     fail::Fail,
     memory::DemiBuffer,
-    queue::IoQueue,
+    queue::{
+        IoQueue,
+        QueueInfo,
+        QueueState,
+    },
 };
 use ::futures::{
     channel::mpsc::{
@@ -20,8 +24,18 @@ use ::futures::{
 };
 use ::libc::EIO;
 use ::std::{
-    cell::RefCell,
-    net::SocketAddrV4,
+    cell::{
+        Cell,
+        RefCell,
+    },
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
     rc::Rc,
 };
 
@@ -48,16 +62,31 @@ pub struct SharedQueue<T> {
     tx: Rc<RefCell<Sender<T>>>,
     /// Receive-side endpoint.
     rx: Rc<RefCell<Receiver<T>>>,
+    /// Message at the head of the queue, if it was already taken out of the receive-side endpoint to be peeked at.
+    head: Rc<RefCell<Option<T>>>,
     /// Length of shared queue.
     length: Rc<RefCell<usize>>,
     /// Capacity of shared queue.
     capacity: usize,
 }
 
+/// Filter on the sources of the datagrams that a socket receives from a multicast group (RFC 3376, Section 2).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceFilter {
+    /// Only datagrams from the listed sources are received, as in source-specific multicast (RFC 4607).
+    Include(HashSet<Ipv4Addr>),
+    /// Datagrams from any source but the listed ones are received.
+    Exclude(HashSet<Ipv4Addr>),
+}
+
 /// Per-queue metadata for a UDP socket.
 pub struct UdpQueue {
     addr: Option<SocketAddrV4>,
     recv_queue: Option<SharedQueue<SharedQueueSlot<DemiBuffer>>>,
+    /// Number of incoming datagrams dropped because the receive queue was full.
+    recv_dropped: Cell<u64>,
+    /// Multicast groups that this socket is a member of, along with their source filters.
+    memberships: HashMap<Ipv4Addr, SourceFilter>,
 }
 
 //======================================================================================================================
@@ -72,6 +101,7 @@ impl<T> SharedQueue<T> {
         Self {
             tx: Rc::new(RefCell::new(tx)),
             rx: Rc::new(RefCell::new(rx)),
+            head: Rc::new(RefCell::new(None)),
             length: Rc::new(RefCell::new(0)),
             capacity: size,
         }
@@ -95,8 +125,26 @@ impl<T> SharedQueue<T> {
         }
     }
 
+    /// Checks if the target shared queue holds as many messages as its capacity.
+    pub fn is_full(&self) -> bool {
+        *self.length.borrow() >= self.capacity
+    }
+
+    /// Applies `f` to the message at the head of the target shared queue, without popping it.
+    pub fn peek<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let mut head = self.head.borrow_mut();
+        if head.is_none() {
+            *head = self.rx.borrow_mut().try_next().ok().flatten();
+        }
+        head.as_ref().map(f)
+    }
+
     /// Synchronously attempts to pop a message from the target shared queue.
     pub fn try_pop(&self) -> Result<Option<T>, Fail> {
+        if let Some(msg) = self.head.borrow_mut().take() {
+            *self.length.borrow_mut() -= 1;
+            return Ok(Some(msg));
+        }
         match self.rx.borrow_mut().try_next() {
             Ok(Some(msg)) => {
                 *self.length.borrow_mut() -= 1;
@@ -109,6 +157,10 @@ impl<T> SharedQueue<T> {
 
     /// Asynchronously pops a message from the target shared queue.
     pub async fn pop(&mut self) -> Result<T, Fail> {
+        if let Some(msg) = self.head.borrow_mut().take() {
+            *self.length.borrow_mut() -= 1;
+            return Ok(msg);
+        }
         match self.rx.borrow_mut().next().await {
             Some(msg) => {
                 *self.length.borrow_mut() -= 1;
@@ -119,12 +171,25 @@ impl<T> SharedQueue<T> {
     }
 }
 
+/// Associated Functions for Source Filters.
+impl SourceFilter {
+    /// Checks whether datagrams from `src` pass the target filter.
+    pub fn admits(&self, src: &Ipv4Addr) -> bool {
+        match self {
+            SourceFilter::Include(sources) => sources.contains(src),
+            SourceFilter::Exclude(sources) => !sources.contains(src),
+        }
+    }
+}
+
 /// Getters and setters for per UDP queue metadata.
 impl UdpQueue {
     pub fn new() -> Self {
         Self {
             addr: None,
             recv_queue: None,
+            recv_dropped: Cell::new(0),
+            memberships: HashMap::new(),
         }
     }
 
@@ -149,6 +214,41 @@ impl UdpQueue {
         }
     }
 
+    /// Returns the number of bytes in the datagram at the head of the receive queue of this socket, or zero if there is
+    /// none.
+    pub fn get_next_datagram_size(&self) -> usize {
+        match &self.recv_queue {
+            Some(recv) => recv.peek(|slot| slot.data.len()).unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// Get the number of incoming datagrams dropped because the receive queue of this socket was full.
+    pub fn get_recv_dropped(&self) -> u64 {
+        self.recv_dropped.get()
+    }
+
+    /// Count an incoming datagram that was dropped because the receive queue of this socket was full.
+    pub fn increment_recv_dropped(&self) {
+        self.recv_dropped.set(self.recv_dropped.get() + 1);
+    }
+
+    /// Get the source filter of a multicast group, if this socket is a member of it.
+    pub fn get_membership(&self, group: &Ipv4Addr) -> Option<&SourceFilter> {
+        self.memberships.get(group)
+    }
+
+    /// Get a mutable reference to the source filter of a multicast group, adding the group with `filter` if this socket
+    /// is not a member of it yet.
+    pub fn get_or_join_membership(&mut self, group: Ipv4Addr, filter: SourceFilter) -> &mut SourceFilter {
+        self.memberships.entry(group).or_insert(filter)
+    }
+
+    /// Get the multicast groups that this socket is a member of.
+    pub fn get_memberships(&self) -> impl Iterator<Item = &Ipv4Addr> {
+        self.memberships.keys()
+    }
+
     /// Set the address assigned to this socket/Demikernel queue.
     pub fn set_addr(&mut self, addr: SocketAddrV4) {
         self.addr = Some(addr);
@@ -171,6 +271,7 @@ impl<T> Clone for SharedQueue<T> {
         Self {
             tx: self.tx.clone(),
             rx: self.rx.clone(),
+            head: self.head.clone(),
             length: self.length.clone(),
             capacity: self.capacity,
         }
@@ -182,4 +283,13 @@ impl IoQueue for UdpQueue {
     fn get_qtype(&self) -> crate::QType {
         crate::QType::UdpSocket
     }
+
+    fn describe(&self, info: &mut QueueInfo) {
+        info.state = Some(if self.is_bound() {
+            QueueState::Bound
+        } else {
+            QueueState::Unbound
+        });
+        info.local = self.addr;
+    }
 }
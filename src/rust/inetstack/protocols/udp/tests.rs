@@ -2,13 +2,33 @@
 // // Licensed under the MIT license.
 
 use crate::{
-    inetstack::test_helpers::{
-        self,
-        Engine,
+    inetstack::{
+        protocols::{
+            ethernet2::ETHERNET2_HEADER_SIZE,
+            ipv4::{
+                PacketFilter,
+                IPV4_HEADER_MIN_SIZE,
+            },
+            udp::datagram::UDP_HEADER_SIZE,
+        },
+        test_helpers::{
+            self,
+            Engine,
+        },
     },
     runtime::{
+        fail::Fail,
+        limits,
         memory::DemiBuffer,
-        network::consts::RECEIVE_BATCH_SIZE,
+        network::{
+            config::UdpConfig,
+            types::{
+                LinkStatus,
+                MacAddress,
+                NicStats,
+            },
+            NetworkRuntime,
+        },
         QDesc,
     },
 };
@@ -19,7 +39,11 @@ use ::futures::task::{
 };
 use ::libc::{
     EADDRINUSE,
+    EADDRNOTAVAIL,
     EBADF,
+    EINVAL,
+    EMSGSIZE,
+    ENETDOWN,
     ENOTCONN,
 };
 use ::std::{
@@ -36,6 +60,10 @@ use ::std::{
         Instant,
     },
 };
+use ::test::{
+    black_box,
+    Bencher,
+};
 
 //==============================================================================
 // Bind & Close
@@ -46,7 +74,7 @@ fn udp_bind_udp_close() -> Result<()> {
     let mut now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port = 80;
     let alice_addr = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = match alice.udp_socket() {
@@ -56,7 +84,7 @@ fn udp_bind_udp_close() -> Result<()> {
     alice.udp_bind(alice_fd, alice_addr)?;
 
     // Setup Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port = 80;
     let bob_addr = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
     let bob_fd: QDesc = match bob.udp_socket() {
@@ -84,14 +112,14 @@ fn udp_push_pop() -> Result<()> {
     let mut now: Instant = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port: u16 = 80;
     let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = alice.udp_socket()?;
     alice.udp_bind(alice_fd, alice_addr)?;
 
     // Setup Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port: u16 = 80;
     let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
     let bob_fd: QDesc = bob.udp_socket()?;
@@ -121,6 +149,422 @@ fn udp_push_pop() -> Result<()> {
     Ok(())
 }
 
+/// Tests that datagrams can be popped one by one without blocking, in the order in which they arrived.
+#[test]
+fn udp_pop_datagram() -> Result<()> {
+    let now: Instant = Instant::now();
+    let num_datagrams: usize = 5;
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Nothing has arrived yet.
+    crate::ensure_eq!(bob.udp_pop_datagram(bob_fd)?.is_none(), true);
+
+    // Send datagrams to Bob, each one carrying its sequence number, without posting any pop.
+    for i in 0..num_datagrams {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&[i as u8; 8][..]).expect("slice should fit in DemiBuffer");
+        alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    }
+    alice.rt.poll_scheduler();
+    while let Some(frame) = alice.rt.pop_frame_unchecked() {
+        bob.receive(frame)?;
+    }
+
+    // Pop datagrams one by one.
+    for i in 0..num_datagrams {
+        match bob.udp_pop_datagram(bob_fd)? {
+            Some((remote_addr, buf)) => {
+                crate::ensure_eq!(remote_addr, alice_addr);
+                crate::ensure_eq!(buf[..], [i as u8; 8][..]);
+            },
+            None => anyhow::bail!("pop_datagram should have returned a datagram"),
+        }
+    }
+    crate::ensure_eq!(bob.udp_pop_datagram(bob_fd)?.is_none(), true);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that the bytes available on a socket are the size of the next datagram, which is popped next.
+#[test]
+fn udp_bytes_available() -> Result<()> {
+    let now: Instant = Instant::now();
+    let sizes: [usize; 3] = [8, 16, 24];
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Nothing has arrived yet.
+    crate::ensure_eq!(bob.udp_bytes_available(bob_fd)?, 0);
+
+    // Send datagrams of different sizes to Bob.
+    for (i, size) in sizes.iter().enumerate() {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&vec![i as u8; *size]).expect("slice should fit in DemiBuffer");
+        alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    }
+    alice.rt.poll_scheduler();
+    while let Some(frame) = alice.rt.pop_frame_unchecked() {
+        bob.receive(frame)?;
+    }
+
+    // Asking twice reports the same datagram, and popping moves on to the next one.
+    for (i, size) in sizes.iter().enumerate() {
+        crate::ensure_eq!(bob.udp_bytes_available(bob_fd)?, *size);
+        crate::ensure_eq!(bob.udp_bytes_available(bob_fd)?, *size);
+        match bob.udp_pop_datagram(bob_fd)? {
+            Some((_, buf)) => crate::ensure_eq!(buf[..], vec![i as u8; *size][..]),
+            None => anyhow::bail!("pop_datagram should have returned a datagram"),
+        }
+    }
+    crate::ensure_eq!(bob.udp_bytes_available(bob_fd)?, 0);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that popped datagrams carry the time they were received at, which does not go backwards from one datagram to
+/// the next.
+#[test]
+fn udp_pop_sw_timestamp() -> Result<()> {
+    let mut now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Timestamps are kept relative to a process-wide origin that is set once the first task is created, so only start
+    // counting after the peers are up.
+    now = Instant::now();
+    alice.clock.advance_clock(now);
+    bob.clock.advance_clock(now);
+
+    // Send datagrams to Bob, which receives them one millisecond apart, except for the last two.
+    let mut received: Vec<Instant> = Vec::new();
+    for i in 0..4 {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&[i as u8; 8]).expect("slice should fit in DemiBuffer");
+        alice.udp_pushto(alice_fd, buf, bob_addr)?;
+        alice.rt.poll_scheduler();
+        if i < 3 {
+            now += Duration::from_millis(1);
+            bob.clock.advance_clock(now);
+        }
+        bob.receive(alice.rt.pop_frame())?;
+        received.push(now);
+    }
+
+    // Each datagram carries the time it was received at.
+    let mut last: Option<Instant> = None;
+    for expected in received {
+        let timestamp: Instant = match bob.udp_pop_datagram(bob_fd)? {
+            Some((_, buf)) => match buf.sw_timestamp() {
+                Some(timestamp) => timestamp,
+                None => anyhow::bail!("popped datagram should carry a timestamp"),
+            },
+            None => anyhow::bail!("pop_datagram should have returned a datagram"),
+        };
+        crate::ensure_eq!(timestamp, expected);
+        if let Some(last) = last {
+            crate::ensure_eq!(timestamp >= last, true);
+        }
+        last = Some(timestamp);
+    }
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that a datagram whose payload has enough headroom goes out in a single buffer, with its headers written in
+/// place right in front of the payload.
+#[test]
+fn udp_pushto_prepends_headers() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+    let header_size: usize = ETHERNET2_HEADER_SIZE + (IPV4_HEADER_MIN_SIZE as usize) + UDP_HEADER_SIZE;
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Send a small datagram to Bob, with as much headroom as alloc_sgarray() reserves.
+    let mut buf: DemiBuffer = DemiBuffer::new_with_headroom(32, limits::SGA_HEADROOM_SIZE as u16)?;
+    buf.copy_from_slice(&[0x5a; 32]);
+    alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+    alice.rt.poll_scheduler();
+
+    // The frame should be the payload buffer itself, with the headers prepended to it.
+    let frame: DemiBuffer = alice.rt.pop_frame();
+    crate::ensure_eq!(alice.rt.pop_frame_unchecked().is_none(), true);
+    crate::ensure_eq!(frame.len(), header_size + buf.len());
+    crate::ensure_eq!(unsafe { frame.as_ptr().add(header_size) }, buf.as_ptr());
+
+    // Bob should not be able to tell the difference.
+    bob.receive(frame)?;
+    let mut pop_future = bob.udp_pop(bob_fd);
+    let (remote_addr, received_buf) = match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Ok((remote_addr, received_buf))) => (remote_addr, received_buf),
+        _ => anyhow::bail!("pop should have completed"),
+    };
+    crate::ensure_eq!(remote_addr, alice_addr);
+    crate::ensure_eq!(received_buf[..], buf[..]);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that a batched pop drains several datagrams at once, in order, and never more than requested.
+#[test]
+fn udp_pop_multiple() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+    let num_datagrams: usize = 64;
+    let max_msgs: usize = 32;
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Nothing has arrived yet, so the pop should not complete.
+    let mut pop_future = bob.udp_pop_multiple(bob_fd, max_msgs);
+    if let Poll::Ready(_) = Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        anyhow::bail!("pop should not have completed");
+    }
+
+    // Send datagrams to Bob, each one carrying its sequence number, and hand all of them over to Bob.
+    for i in 0..num_datagrams {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&[i as u8; 8][..]).expect("slice should fit in DemiBuffer");
+        alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    }
+    alice.rt.poll_scheduler();
+    while let Some(frame) = alice.rt.pop_frame_unchecked() {
+        bob.rt.push_frame(frame);
+        for frame in bob.rt.receive() {
+            bob.receive(frame)?;
+        }
+    }
+
+    // Pop all datagrams in as few batches as possible.
+    let mut received: usize = 0;
+    for _ in 0..(num_datagrams / max_msgs) {
+        let msgs: Vec<(SocketAddrV4, DemiBuffer)> = match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+            Poll::Ready(Ok(msgs)) => msgs,
+            _ => anyhow::bail!("pop should have completed"),
+        };
+        crate::ensure_eq!(msgs.len(), max_msgs);
+        for (remote_addr, buf) in msgs {
+            crate::ensure_eq!(remote_addr, alice_addr);
+            crate::ensure_eq!(buf[..], [received as u8; 8][..]);
+            received += 1;
+        }
+        pop_future = bob.udp_pop_multiple(bob_fd, max_msgs);
+    }
+    crate::ensure_eq!(received, num_datagrams);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that datagrams arriving while the receive queue of a socket is full are dropped and counted, and that the
+/// datagrams already queued are kept.
+#[test]
+fn udp_recv_queue_depth() -> Result<()> {
+    let now: Instant = Instant::now();
+    let recv_queue_depth: usize = 4;
+    let num_datagrams: usize = 3 * recv_queue_depth;
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let udp_config: UdpConfig = UdpConfig::new(None, None, Some(recv_queue_depth));
+    let mut bob: Engine = test_helpers::new_bob2_with_udp_config(now, udp_config);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Burst datagrams to Bob, each one carrying its sequence number, without posting any pop.
+    for i in 0..num_datagrams {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&[i as u8; 8][..]).expect("slice should fit in DemiBuffer");
+        alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    }
+    alice.rt.poll_scheduler();
+    while let Some(frame) = alice.rt.pop_frame_unchecked() {
+        bob.receive(frame)?;
+    }
+
+    // The excess should have been dropped and counted.
+    crate::ensure_eq!(bob.udp_recv_dropped(bob_fd)?, (num_datagrams - recv_queue_depth) as u64);
+    for i in 0..recv_queue_depth {
+        match bob.udp_pop_datagram(bob_fd)? {
+            Some((_, buf)) => crate::ensure_eq!(buf[..], [i as u8; 8][..]),
+            None => anyhow::bail!("pop_datagram should have returned a datagram"),
+        }
+    }
+    crate::ensure_eq!(bob.udp_pop_datagram(bob_fd)?.is_none(), true);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+//==============================================================================
+// NIC Statistics
+//==============================================================================
+
+/// Tests that the network runtimes account for the frames of a UDP exchange.
+#[test]
+fn udp_nic_stats() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+    let num_datagrams: usize = 4;
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Send datagrams to Bob, and hand them over to Bob through his runtime.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    for _ in 0..num_datagrams {
+        alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+    }
+    alice.rt.poll_scheduler();
+    while let Some(frame) = alice.rt.pop_frame_unchecked() {
+        bob.rt.push_frame(frame);
+    }
+    for _ in 0..num_datagrams {
+        for frame in bob.rt.receive() {
+            bob.receive(frame)?;
+        }
+        let mut pop_future = bob.udp_pop(bob_fd);
+        match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+            Poll::Ready(Ok((remote_addr, _))) => crate::ensure_eq!(remote_addr, alice_addr),
+            _ => anyhow::bail!("pop should have completed"),
+        };
+    }
+
+    // Check statistics.
+    let alice_stats: NicStats = alice.rt.nic_stats();
+    let bob_stats: NicStats = bob.rt.nic_stats();
+    crate::ensure_eq!(alice_stats.tx_packets, num_datagrams as u64);
+    crate::ensure_eq!(bob_stats.rx_packets, num_datagrams as u64);
+    crate::ensure_eq!(bob_stats.rx_bytes, alice_stats.tx_bytes);
+    crate::ensure_eq!(alice_stats.rx_packets, 0);
+    crate::ensure_eq!(bob_stats.tx_packets, 0);
+    crate::ensure_eq!(alice_stats.tx_dropped, 0);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that pushes fail with `ENETDOWN` while the link is down, and succeed again once it comes back up.
+#[test]
+fn udp_pushto_link_down() -> Result<()> {
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+
+    // Pull the cable.
+    alice.rt.set_link_status(LinkStatus::down());
+    crate::ensure_eq!(alice.rt.link_status().up, false);
+    match alice.udp_pushto(alice_fd, buf.clone(), bob_addr) {
+        Err(e) if e.errno == ENETDOWN => {},
+        _ => anyhow::bail!("pushto should have failed with ENETDOWN"),
+    }
+    crate::ensure_eq!(alice.rt.pop_frame_unchecked().is_none(), true);
+
+    // Plug it back in.
+    alice.rt.set_link_status(LinkStatus::up(10000));
+    crate::ensure_eq!(alice.rt.link_status(), LinkStatus::up(10000));
+    alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    alice.rt.poll_scheduler();
+    crate::ensure_eq!(alice.rt.pop_frame_unchecked().is_some(), true);
+
+    // Close peer.
+    alice.udp_close(alice_fd)?;
+
+    Ok(())
+}
+
 //==============================================================================
 // Push & Pop
 //==============================================================================
@@ -131,14 +575,14 @@ fn udp_push_pop_wildcard_address() -> Result<()> {
     let mut now: Instant = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port: u16 = 80;
     let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = alice.udp_socket()?;
     alice.udp_bind(alice_fd, alice_addr)?;
 
     // Setup Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port: u16 = 80;
     let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
     let bob_fd: QDesc = bob.udp_socket()?;
@@ -178,14 +622,14 @@ fn udp_ping_pong() -> Result<()> {
     let mut now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port = 80;
     let alice_addr = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = alice.udp_socket()?;
     alice.udp_bind(alice_fd, alice_addr)?;
 
     // Setup Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port = 80;
     let bob_addr = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
     let bob_fd: QDesc = bob.udp_socket()?;
@@ -253,12 +697,12 @@ fn udp_loop2_bind_udp_close() -> Result<()> {
     let mut now = Instant::now();
 
     // Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port = 80;
     let alice_addr = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
 
     // Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port = 80;
     let bob_addr = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
 
@@ -302,14 +746,14 @@ fn udp_loop2_push_pop() -> Result<()> {
     let mut now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port = 80;
     let alice_addr = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = alice.udp_socket()?;
     alice.udp_bind(alice_fd, alice_addr)?;
 
     // Setup Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port = 80;
     let bob_addr = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
     let bob_fd: QDesc = bob.udp_socket()?;
@@ -361,14 +805,14 @@ fn udp_loop2_ping_pong() -> Result<()> {
     let mut now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port = 80;
     let alice_addr = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = alice.udp_socket()?;
     alice.udp_bind(alice_fd, alice_addr)?;
 
     // Setup Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port = 80;
     let bob_addr = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
     let bob_fd: QDesc = bob.udp_socket()?;
@@ -429,7 +873,7 @@ fn udp_bind_address_in_use() -> Result<()> {
     let now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port = 80;
     let alice_addr = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = alice.udp_socket()?;
@@ -452,7 +896,7 @@ fn udp_bind_bad_file_descriptor() -> Result<()> {
     let now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port: u16 = 80;
     let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = QDesc::try_from(u32::MAX)?;
@@ -475,7 +919,7 @@ fn udp_udp_close_bad_file_descriptor() -> Result<()> {
     let now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_fd: QDesc = alice.udp_socket()?;
     let alice_port: u16 = 80;
     let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
@@ -506,14 +950,14 @@ fn udp_pop_not_bound() -> Result<()> {
     let mut now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port = 80;
     let alice_addr = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = alice.udp_socket()?;
     alice.udp_bind(alice_fd, alice_addr)?;
 
     // Setup Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port = 80;
     let bob_addr = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
     // Bob does not create a socket.
@@ -547,14 +991,14 @@ fn udp_push_bad_file_descriptor() -> Result<()> {
     let mut now = Instant::now();
 
     // Setup Alice.
-    let mut alice: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut alice: Engine = test_helpers::new_alice2(now);
     let alice_port: u16 = 80;
     let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
     let alice_fd: QDesc = alice.udp_socket()?;
     alice.udp_bind(alice_fd, alice_addr)?;
 
     // Setup Bob.
-    let mut bob: Engine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
     let bob_port: u16 = 80;
     let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
     let bob_fd: QDesc = bob.udp_socket()?;
@@ -576,3 +1020,366 @@ fn udp_push_bad_file_descriptor() -> Result<()> {
 
     Ok(())
 }
+
+//==============================================================================
+// Push Size Limits
+//==============================================================================
+
+#[test]
+fn udp_push_size_limits() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_port: u16 = 80;
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_port: u16 = 80;
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    let max_push_size: usize = alice.udp_max_push_size(alice_fd)?;
+
+    // Datagrams that fit in a single packet should go through.
+    for size in [max_push_size - 1, max_push_size] {
+        let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; size][..]).expect("slice should fit in DemiBuffer");
+        alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+        alice.rt.poll_scheduler();
+
+        bob.receive(alice.rt.pop_frame())?;
+        let mut pop_future = bob.udp_pop(bob_fd);
+        match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+            Poll::Ready(Ok((_, received_buf))) => crate::ensure_eq!(received_buf[..], buf[..]),
+            _ => anyhow::bail!("pop should have completed"),
+        };
+    }
+
+    // Datagrams that do not fit in a single packet should be rejected.
+    let buf: DemiBuffer =
+        DemiBuffer::from_slice(&vec![0x5a; max_push_size + 1][..]).expect("slice should fit in DemiBuffer");
+    match alice.udp_pushto(alice_fd, buf, bob_addr) {
+        Err(e) if e.errno == EMSGSIZE => {},
+        _ => anyhow::bail!("pushto should have failed"),
+    };
+    alice.rt.poll_scheduler();
+    crate::ensure_eq!(alice.rt.pop_frame_unchecked().is_none(), true);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+//==============================================================================
+// Packet Filtering
+//==============================================================================
+
+#[test]
+fn udp_packet_filter() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice, with an allowed and a blocked socket.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let allowed_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let allowed_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(allowed_fd, allowed_addr)?;
+    let blocked_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 81);
+    let blocked_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(blocked_fd, blocked_addr)?;
+
+    // Setup Bob, only admitting packets from the allowed socket.
+    let packet_filter: PacketFilter = PacketFilter::allowlist(vec![(*allowed_addr.ip(), Some(allowed_addr.port()))]);
+    let mut bob: Engine = test_helpers::new_bob2_with_packet_filter(now, packet_filter);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Send data to Bob from both sockets.
+    let blocked_buf: DemiBuffer = DemiBuffer::from_slice(&vec![0xa5; 32][..]).expect("slice should fit in DemiBuffer");
+    alice.udp_pushto(blocked_fd, blocked_buf, bob_addr)?;
+    alice.rt.poll_scheduler();
+    bob.receive(alice.rt.pop_frame())?;
+    let allowed_buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    alice.udp_pushto(allowed_fd, allowed_buf.clone(), bob_addr)?;
+    alice.rt.poll_scheduler();
+    bob.receive(alice.rt.pop_frame())?;
+
+    // Only data from the allowed socket should have been received.
+    crate::ensure_eq!(bob.ipv4.filtered_packets(), 1);
+    let mut pop_future = bob.udp_pop(bob_fd);
+    match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+        Poll::Ready(Ok((remote_addr, received_buf))) => {
+            crate::ensure_eq!(remote_addr, allowed_addr);
+            crate::ensure_eq!(received_buf[..], allowed_buf[..]);
+        },
+        _ => anyhow::bail!("pop should have completed"),
+    };
+    let mut pop_future = bob.udp_pop(bob_fd);
+    crate::ensure_eq!(Future::poll(Pin::new(&mut pop_future), &mut ctx).is_pending(), true);
+
+    // Close peers.
+    alice.udp_close(allowed_fd)?;
+    alice.udp_close(blocked_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+//==============================================================================
+// Multicast
+//==============================================================================
+
+/// Multicast group used in tests.
+const GROUP_IPV4: Ipv4Addr = Ipv4Addr::new(239, 1, 2, 3);
+
+/// Sends a datagram from `sender` to the test multicast group, and hands it over to `receiver`.
+fn udp_send_to_group(sender: &mut Engine, receiver: &mut Engine, port: u16, buf: DemiBuffer) -> Result<()> {
+    let fd: QDesc = sender.udp_socket()?;
+    sender.udp_bind(fd, SocketAddrV4::new(sender.rt.ipv4_addr, port))?;
+    sender.udp_pushto(fd, buf, SocketAddrV4::new(GROUP_IPV4, 5000))?;
+    sender.rt.poll_scheduler();
+    let frame: DemiBuffer = sender.rt.pop_frame();
+    // Multicast groups map directly to link addresses, so no ARP request should precede the datagram.
+    crate::ensure_eq!(frame[..6], MacAddress::from_ipv4_multicast(GROUP_IPV4).octets()[..]);
+    receiver.receive(frame)?;
+    sender.udp_close(fd)?;
+    Ok(())
+}
+
+/// Tests that a socket only receives the datagrams of the sources that it joined a multicast group for.
+#[test]
+fn udp_source_specific_multicast() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let mut carrie: Engine = test_helpers::new_carrie(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 5000))?;
+
+    // Datagrams to groups that the socket is not a member of should be dropped.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    udp_send_to_group(&mut alice, &mut bob, 80, buf.clone())?;
+    crate::ensure_eq!(bob.udp_pop_datagram(bob_fd)?.is_none(), true);
+
+    // Join the group for Alice only, which should make the runtime listen to the group.
+    bob.udp_join_source_specific_multicast(bob_fd, GROUP_IPV4, test_helpers::ALICE_IPV4)?;
+    crate::ensure_eq!(
+        bob.rt.get_multicast_filter(),
+        vec![MacAddress::from_ipv4_multicast(GROUP_IPV4)]
+    );
+
+    // Datagrams from Carrie should be filtered out, while those from Alice should be received.
+    udp_send_to_group(&mut carrie, &mut bob, 81, buf.clone())?;
+    crate::ensure_eq!(bob.udp_pop_datagram(bob_fd)?.is_none(), true);
+    udp_send_to_group(&mut alice, &mut bob, 80, buf.clone())?;
+    match bob.udp_pop_datagram(bob_fd)? {
+        Some((remote, received_buf)) => {
+            crate::ensure_eq!(remote, SocketAddrV4::new(test_helpers::ALICE_IPV4, 80));
+            crate::ensure_eq!(received_buf[..], buf[..]);
+        },
+        None => anyhow::bail!("datagram from Alice should have been received"),
+    }
+
+    // Closing the socket should make the runtime stop listening to the group.
+    bob.udp_close(bob_fd)?;
+    crate::ensure_eq!(bob.rt.get_multicast_filter().is_empty(), true);
+
+    Ok(())
+}
+
+/// Tests that blocked sources are filtered out of groups that a socket joined for any source.
+#[test]
+fn udp_multicast_block_source() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let mut carrie: Engine = test_helpers::new_carrie(now);
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 5000))?;
+    bob.udp_join_multicast(bob_fd, GROUP_IPV4)?;
+    bob.udp_block_source(bob_fd, GROUP_IPV4, test_helpers::CARRIE_IPV4)?;
+
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    udp_send_to_group(&mut carrie, &mut bob, 81, buf.clone())?;
+    crate::ensure_eq!(bob.udp_pop_datagram(bob_fd)?.is_none(), true);
+    udp_send_to_group(&mut alice, &mut bob, 80, buf.clone())?;
+    crate::ensure_eq!(bob.udp_pop_datagram(bob_fd)?.is_some(), true);
+
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that invalid changes to the multicast memberships of a socket fail.
+#[test]
+fn udp_multicast_membership_errors() -> Result<()> {
+    let now: Instant = Instant::now();
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 5000))?;
+    let other_group: Ipv4Addr = Ipv4Addr::new(239, 1, 2, 4);
+
+    // Groups should be multicast addresses, and sources should be unicast ones.
+    match bob.udp_join_multicast(bob_fd, test_helpers::ALICE_IPV4) {
+        Err(e) if e.errno == EINVAL => (),
+        _ => anyhow::bail!("joining a unicast address should fail with EINVAL"),
+    }
+    match bob.udp_join_source_specific_multicast(bob_fd, GROUP_IPV4, other_group) {
+        Err(e) if e.errno == EINVAL => (),
+        _ => anyhow::bail!("joining a multicast source should fail with EINVAL"),
+    }
+
+    // Any-source and source-specific memberships should not be mixed on a group.
+    bob.udp_join_multicast(bob_fd, GROUP_IPV4)?;
+    match bob.udp_join_multicast(bob_fd, GROUP_IPV4) {
+        Err(e) if e.errno == EADDRINUSE => (),
+        _ => anyhow::bail!("joining a group twice should fail with EADDRINUSE"),
+    }
+    match bob.udp_join_source_specific_multicast(bob_fd, GROUP_IPV4, test_helpers::ALICE_IPV4) {
+        Err(e) if e.errno == EINVAL => (),
+        _ => anyhow::bail!("joining a source of an any-source group should fail with EINVAL"),
+    }
+    bob.udp_join_source_specific_multicast(bob_fd, other_group, test_helpers::ALICE_IPV4)?;
+    match bob.udp_block_source(bob_fd, other_group, test_helpers::CARRIE_IPV4) {
+        Err(e) if e.errno == EINVAL => (),
+        _ => anyhow::bail!("blocking a source of a source-specific group should fail with EINVAL"),
+    }
+
+    // Sources may only be blocked on groups that the socket is a member of.
+    match bob.udp_block_source(bob_fd, Ipv4Addr::new(239, 1, 2, 5), test_helpers::CARRIE_IPV4) {
+        Err(e) if e.errno == EADDRNOTAVAIL => (),
+        _ => anyhow::bail!("blocking a source of a foreign group should fail with EADDRNOTAVAIL"),
+    }
+    match bob.udp_join_multicast(QDesc::try_from(u32::MAX)?, GROUP_IPV4) {
+        Err(e) if e.errno == EBADF => (),
+        _ => anyhow::bail!("joining a group on a bad queue descriptor should fail with EBADF"),
+    }
+
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+//==============================================================================
+// Batched Push
+//==============================================================================
+
+#[test]
+fn udp_pushto_batch() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: Engine = test_helpers::new_alice2(now);
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 80);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob, with two sockets.
+    let mut bob: Engine = test_helpers::new_bob2(now);
+    let bob_addrs: [SocketAddrV4; 2] = [
+        SocketAddrV4::new(test_helpers::BOB_IPV4, 80),
+        SocketAddrV4::new(test_helpers::BOB_IPV4, 81),
+    ];
+    let mut bob_fds: Vec<QDesc> = Vec::new();
+    for bob_addr in bob_addrs {
+        let bob_fd: QDesc = bob.udp_socket()?;
+        bob.udp_bind(bob_fd, bob_addr)?;
+        bob_fds.push(bob_fd);
+    }
+
+    // Send a batch of datagrams to Bob, with consecutive datagrams to the same destination, and an oversized one.
+    let destinations: [usize; 6] = [0, 0, 1, 0, 1, 1];
+    let mut datagrams: Vec<(DemiBuffer, SocketAddrV4)> = destinations
+        .iter()
+        .enumerate()
+        .map(|(i, &j)| (DemiBuffer::from_slice(&vec![i as u8; 32 + i]).unwrap(), bob_addrs[j]))
+        .collect();
+    let max_push_size: usize = alice.udp_max_push_size(alice_fd)?;
+    datagrams.insert(3, (DemiBuffer::new(max_push_size as u16 + 1), bob_addrs[0]));
+    let results: Vec<Result<(), Fail>> = alice.udp_pushto_batch(alice_fd, datagrams)?;
+    crate::ensure_eq!(results.len(), destinations.len() + 1);
+    for (i, result) in results.iter().enumerate() {
+        match result {
+            Err(e) if i == 3 => crate::ensure_eq!(e.errno, EMSGSIZE),
+            Ok(()) if i != 3 => {},
+            _ => anyhow::bail!("unexpected outcome for datagram {}: {:?}", i, result),
+        }
+    }
+
+    // All datagrams should arrive, in order, with the correct payloads and destinations.
+    for _ in 0..destinations.len() {
+        bob.receive(alice.rt.pop_frame())?;
+    }
+    crate::ensure_eq!(alice.rt.pop_frame_unchecked().is_none(), true);
+    for (i, &j) in destinations.iter().enumerate() {
+        let mut pop_future = bob.udp_pop(bob_fds[j]);
+        match Future::poll(Pin::new(&mut pop_future), &mut ctx) {
+            Poll::Ready(Ok((remote_addr, received_buf))) => {
+                crate::ensure_eq!(remote_addr, alice_addr);
+                crate::ensure_eq!(received_buf[..], vec![i as u8; 32 + i][..]);
+            },
+            _ => anyhow::bail!("pop should have completed"),
+        };
+    }
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    for bob_fd in bob_fds {
+        bob.udp_close(bob_fd)?;
+    }
+
+    Ok(())
+}
+
+/// Number of datagrams sent in each iteration of the batched push benchmarks.
+const BENCH_BATCH_SIZE: usize = 64;
+
+/// Sets up Alice for the batched push benchmarks, and cooks datagrams to a couple of Bob's ports.
+fn setup_pushto_bench() -> (Engine, QDesc, Vec<(DemiBuffer, SocketAddrV4)>) {
+    let mut alice: Engine = test_helpers::new_alice2(Instant::now());
+    let alice_fd: QDesc = alice.udp_socket().unwrap();
+    alice
+        .udp_bind(alice_fd, SocketAddrV4::new(test_helpers::ALICE_IPV4, 80))
+        .unwrap();
+    let datagrams: Vec<(DemiBuffer, SocketAddrV4)> = (0..BENCH_BATCH_SIZE)
+        .map(|i| {
+            let remote: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80 + (i / 16) as u16);
+            (DemiBuffer::from_slice(&[0x5a; 64]).unwrap(), remote)
+        })
+        .collect();
+    (alice, alice_fd, datagrams)
+}
+
+#[bench]
+fn bench_udp_pushto_loop(b: &mut Bencher) {
+    let (alice, alice_fd, datagrams): (Engine, QDesc, Vec<(DemiBuffer, SocketAddrV4)>) = setup_pushto_bench();
+    b.iter(|| {
+        for (buf, remote) in datagrams.iter() {
+            alice.udp_pushto(alice_fd, buf.clone(), *remote).unwrap();
+        }
+        while let Some(frame) = alice.rt.pop_frame_unchecked() {
+            black_box(frame);
+        }
+    });
+}
+
+#[bench]
+fn bench_udp_pushto_batch(b: &mut Bencher) {
+    let (alice, alice_fd, datagrams): (Engine, QDesc, Vec<(DemiBuffer, SocketAddrV4)>) = setup_pushto_bench();
+    b.iter(|| {
+        let results: Vec<Result<(), Fail>> = alice.udp_pushto_batch(alice_fd, datagrams.clone()).unwrap();
+        black_box(results);
+        while let Some(frame) = alice.rt.pop_frame_unchecked() {
+            black_box(frame);
+        }
+    });
+}
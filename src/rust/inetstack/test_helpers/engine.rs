@@ -1,6 +1,8 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+#[cfg(feature = "dhcp")]
+use crate::inetstack::protocols::dhcp::DhcpLease;
 use crate::{
     inetstack::protocols::{
         arp::ArpPeer,
@@ -8,23 +10,44 @@ use crate::{
             EtherType2,
             Ethernet2Header,
         },
+        ipv4::{
+            PacketFilter,
+            RouteEntry,
+        },
         queue::InetQueue,
-        tcp::operations::{
-            AcceptFuture,
-            ConnectFuture,
-            PopFuture,
-            PushFuture,
+        tcp::{
+            operations::{
+                AcceptFuture,
+                ConnectFuture,
+                PopFuture,
+                PopMultipleFuture,
+                PushFuture,
+            },
+            SegmentAuthenticator,
+            TcpConnectionStats,
+            TcpInfo,
+        },
+        udp::{
+            UdpPopFuture,
+            UdpPopMultipleFuture,
         },
-        udp::UdpPopFuture,
         Peer,
     },
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
-        network::types::MacAddress,
+        network::types::{
+            ByteStats,
+            ConnEvent,
+            HandshakeStats,
+            MacAddress,
+            PeerStats,
+            PushCompletion,
+        },
         queue::IoQueueTable,
         timer::TimerRc,
         QDesc,
+        QueueInfo,
     },
     scheduler::scheduler::Scheduler,
 };
@@ -43,23 +66,28 @@ use ::std::{
 
 use super::TestRuntime;
 
-pub struct Engine<const N: usize> {
+pub struct Engine {
     pub rt: Rc<TestRuntime>,
     pub clock: TimerRc,
-    pub arp: ArpPeer<N>,
-    pub ipv4: Peer<N>,
-    pub qtable: Rc<RefCell<IoQueueTable<InetQueue<N>>>>,
+    pub arp: ArpPeer,
+    pub ipv4: Peer,
+    pub qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
 }
 
-impl<const N: usize> Engine<N> {
-    pub fn new(rt: TestRuntime, scheduler: Scheduler, clock: TimerRc) -> Result<Self, Fail> {
+impl Engine {
+    pub fn new(
+        rt: TestRuntime,
+        scheduler: Scheduler,
+        clock: TimerRc,
+        packet_filter: Option<PacketFilter>,
+    ) -> Result<Self, Fail> {
         let rt = Rc::new(rt);
         let link_addr = rt.link_addr;
         let ipv4_addr = rt.ipv4_addr;
         let arp_options = rt.arp_options.clone();
         let udp_config = rt.udp_config.clone();
         let tcp_config = rt.tcp_config.clone();
-        let qtable = Rc::new(RefCell::new(IoQueueTable::<InetQueue<N>>::new()));
+        let qtable = Rc::new(RefCell::new(IoQueueTable::<InetQueue>::new()));
         let arp = ArpPeer::new(
             rt.clone(),
             scheduler.clone(),
@@ -80,6 +108,7 @@ impl<const N: usize> Engine<N> {
             tcp_config,
             arp.clone(),
             rng_seed,
+            packet_filter,
         )?;
         Ok(Engine {
             rt,
@@ -93,7 +122,7 @@ impl<const N: usize> Engine<N> {
     pub fn receive(&mut self, bytes: DemiBuffer) -> Result<(), Fail> {
         let (header, payload) = Ethernet2Header::parse(bytes)?;
         debug!("Engine received {:?}", header);
-        if self.rt.link_addr != header.dst_addr() && !header.dst_addr().is_broadcast() {
+        if self.arp.get_local_link_addr() != header.dst_addr() && !header.dst_addr().is_multicast() {
             return Err(Fail::new(EBADMSG, "physical destination address mismatch"));
         }
         match header.ether_type() {
@@ -111,14 +140,69 @@ impl<const N: usize> Engine<N> {
         self.ipv4.ping(dest_ipv4_addr, timeout)
     }
 
+    #[cfg(feature = "dhcp")]
+    pub fn dhcp_request(&mut self) -> impl Future<Output = Result<DhcpLease, Fail>> {
+        self.ipv4.dhcp_request()
+    }
+
+    #[cfg(feature = "dhcp")]
+    pub fn dhcp_lease(&self) -> Option<DhcpLease> {
+        self.ipv4.dhcp_lease()
+    }
+
     pub fn udp_pushto(&self, fd: QDesc, buf: DemiBuffer, to: SocketAddrV4) -> Result<(), Fail> {
         self.ipv4.udp.do_pushto(fd, buf, to)
     }
 
+    pub fn udp_pushto_batch(
+        &self,
+        fd: QDesc,
+        datagrams: Vec<(DemiBuffer, SocketAddrV4)>,
+    ) -> Result<Vec<Result<(), Fail>>, Fail> {
+        self.ipv4.udp.do_pushto_batch(fd, datagrams)
+    }
+
+    pub fn udp_max_push_size(&self, fd: QDesc) -> Result<usize, Fail> {
+        self.ipv4.udp.max_push_size(fd)
+    }
+
     pub fn udp_pop(&mut self, fd: QDesc) -> UdpPopFuture {
         self.ipv4.udp.do_pop(fd, None)
     }
 
+    pub fn udp_pop_datagram(&mut self, fd: QDesc) -> Result<Option<(SocketAddrV4, DemiBuffer)>, Fail> {
+        self.ipv4.udp.do_try_pop(fd)
+    }
+
+    pub fn udp_bytes_available(&self, fd: QDesc) -> Result<usize, Fail> {
+        self.ipv4.udp.bytes_available(fd)
+    }
+
+    pub fn udp_recv_dropped(&self, fd: QDesc) -> Result<u64, Fail> {
+        self.ipv4.udp.get_recv_dropped(fd)
+    }
+
+    pub fn udp_join_multicast(&mut self, fd: QDesc, group: Ipv4Addr) -> Result<(), Fail> {
+        self.ipv4.udp.join_multicast(fd, group)
+    }
+
+    pub fn udp_join_source_specific_multicast(
+        &mut self,
+        fd: QDesc,
+        group: Ipv4Addr,
+        src: Ipv4Addr,
+    ) -> Result<(), Fail> {
+        self.ipv4.udp.join_source_specific_multicast(fd, group, src)
+    }
+
+    pub fn udp_block_source(&mut self, fd: QDesc, group: Ipv4Addr, src: Ipv4Addr) -> Result<(), Fail> {
+        self.ipv4.udp.block_source(fd, group, src)
+    }
+
+    pub fn udp_pop_multiple(&mut self, fd: QDesc, max_msgs: usize) -> UdpPopMultipleFuture {
+        self.ipv4.udp.do_pop_multiple(fd, max_msgs)
+    }
+
     pub fn udp_socket(&mut self) -> Result<QDesc, Fail> {
         self.ipv4.udp.do_socket()
     }
@@ -135,29 +219,145 @@ impl<const N: usize> Engine<N> {
         self.ipv4.tcp.do_socket()
     }
 
-    pub fn tcp_connect(&mut self, socket_fd: QDesc, remote_endpoint: SocketAddrV4) -> ConnectFuture<N> {
+    pub fn tcp_connect(&mut self, socket_fd: QDesc, remote_endpoint: SocketAddrV4) -> ConnectFuture {
         self.ipv4.tcp.connect(socket_fd, remote_endpoint).unwrap()
     }
 
+    pub fn tcp_connect_from(
+        &mut self,
+        socket_fd: QDesc,
+        local_endpoint: SocketAddrV4,
+        remote_endpoint: SocketAddrV4,
+    ) -> Result<ConnectFuture, Fail> {
+        self.ipv4.tcp.connect_from(socket_fd, local_endpoint, remote_endpoint)
+    }
+
     pub fn tcp_bind(&mut self, socket_fd: QDesc, endpoint: SocketAddrV4) -> Result<(), Fail> {
         self.ipv4.tcp.bind(socket_fd, endpoint)
     }
 
-    pub fn tcp_accept(&mut self, fd: QDesc) -> AcceptFuture<N> {
-        let (_, future) = self.ipv4.tcp.do_accept(fd);
-        future
+    pub fn tcp_accept(&mut self, fd: QDesc) -> Result<AcceptFuture, Fail> {
+        let (_, future) = self.ipv4.tcp.do_accept(fd)?;
+        Ok(future)
+    }
+
+    pub fn tcp_accept_timeout(
+        &mut self,
+        fd: QDesc,
+        timeout: Duration,
+    ) -> Result<impl Future<Output = Result<(QDesc, SocketAddrV4), Fail>>, Fail> {
+        let (_, future) = self.ipv4.tcp.do_accept(fd)?;
+        Ok(self.clock.timeout(future, timeout))
+    }
+
+    pub fn tcp_stop_listening(&mut self, fd: QDesc) -> Result<(), Fail> {
+        self.ipv4.tcp.stop_listening(fd)
     }
 
     pub fn tcp_push(&mut self, socket_fd: QDesc, buf: DemiBuffer) -> PushFuture {
         self.ipv4.tcp.push(socket_fd, buf)
     }
 
-    pub fn tcp_pop(&mut self, socket_fd: QDesc) -> PopFuture<N> {
+    pub fn tcp_push_timeout(
+        &mut self,
+        socket_fd: QDesc,
+        buf: DemiBuffer,
+        timeout: Duration,
+    ) -> Result<impl Future<Output = Result<(), Fail>>, Fail> {
+        self.ipv4.tcp.push_timeout(socket_fd, buf, timeout)
+    }
+
+    pub fn tcp_set_push_completion(&mut self, socket_fd: QDesc, push_completion: PushCompletion) -> Result<(), Fail> {
+        self.ipv4.tcp.set_push_completion(socket_fd, push_completion)
+    }
+
+    pub fn tcp_set_authenticator(
+        &mut self,
+        socket_fd: QDesc,
+        authenticator: Option<Rc<dyn SegmentAuthenticator>>,
+    ) -> Result<(), Fail> {
+        self.ipv4.tcp.set_authenticator(socket_fd, authenticator)
+    }
+
+    pub fn tcp_unauthenticated_segments(&self) -> u64 {
+        self.ipv4.tcp.unauthenticated_segments()
+    }
+
+    pub fn tcp_malformed_segments(&self) -> u64 {
+        self.ipv4.tcp.malformed_segments()
+    }
+
+    pub fn tcp_peer_telemetry(&self) -> Vec<(Ipv4Addr, PeerStats)> {
+        self.ipv4.tcp.peer_telemetry()
+    }
+
+    pub fn tcp_handshake_stats(&self) -> HandshakeStats {
+        self.ipv4.tcp.handshake_stats()
+    }
+
+    pub fn reset_tcp_handshake_stats(&self) {
+        self.ipv4.tcp.reset_handshake_stats()
+    }
+
+    pub fn tcp_add_ecmp_route(&self, net: Ipv4Addr, prefix: u8, gateways: Vec<RouteEntry>) -> Result<(), Fail> {
+        self.ipv4.tcp.add_ecmp_route(net, prefix, gateways)
+    }
+
+    pub fn tcp_next_event(&self) -> Option<ConnEvent> {
+        self.ipv4.tcp.next_event()
+    }
+
+    pub fn tcp_recycled_connections(&self) -> u64 {
+        self.ipv4.tcp.recycled_connections()
+    }
+
+    pub fn set_ephemeral_port_range(&mut self, first: u16, last: u16) -> Result<(), Fail> {
+        self.ipv4.set_ephemeral_port_range(first, last)
+    }
+
+    pub fn tcp_max_push_size(&self, socket_fd: QDesc) -> Result<usize, Fail> {
+        self.ipv4.tcp.max_push_size(socket_fd)
+    }
+
+    pub fn tcp_bytes_available(&self, socket_fd: QDesc) -> Result<usize, Fail> {
+        self.ipv4.tcp.bytes_available(socket_fd)
+    }
+
+    pub fn tcp_flush(&self, socket_fd: QDesc) -> Result<(), Fail> {
+        self.ipv4.tcp.flush(socket_fd)
+    }
+
+    pub fn tcp_pop_timeout(
+        &mut self,
+        socket_fd: QDesc,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<DemiBuffer, Fail>> {
+        self.clock.timeout(self.ipv4.tcp.pop(socket_fd, None), timeout)
+    }
+
+    pub fn tcp_pop(&mut self, socket_fd: QDesc) -> PopFuture {
         self.ipv4.tcp.pop(socket_fd, None)
     }
 
+    pub fn tcp_pop_peek(&mut self, socket_fd: QDesc, size: Option<usize>) -> PopFuture {
+        self.ipv4.tcp.pop_peek(socket_fd, size)
+    }
+
+    pub fn tcp_pop_multiple(&mut self, socket_fd: QDesc, max_msgs: usize) -> PopMultipleFuture {
+        self.ipv4.tcp.pop_multiple(socket_fd, max_msgs)
+    }
+
+    pub fn tcp_dup(&mut self, socket_fd: QDesc) -> Result<QDesc, Fail> {
+        self.qtable.borrow_mut().dup(&socket_fd)
+    }
+
     pub fn tcp_close(&mut self, socket_fd: QDesc) -> Result<(), Fail> {
-        self.ipv4.tcp.do_close(socket_fd)
+        let duplicate: Option<bool> = self.qtable.borrow_mut().close_duplicate(&socket_fd);
+        match duplicate {
+            Some(true) => Ok(()),
+            Some(false) => self.ipv4.tcp.do_close(socket_fd),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
     }
 
     pub fn tcp_listen(&mut self, socket_fd: QDesc, backlog: usize) -> Result<(), Fail> {
@@ -168,6 +368,14 @@ impl<const N: usize> Engine<N> {
         self.arp.query(ipv4_addr)
     }
 
+    pub fn arp_send_request(&self, ipv4_addr: Ipv4Addr) {
+        self.arp.send_request(ipv4_addr)
+    }
+
+    pub fn arp_wait_reply(&self, ipv4_addr: Ipv4Addr) -> impl Future<Output = Result<MacAddress, Fail>> {
+        self.arp.wait_reply(ipv4_addr)
+    }
+
     pub fn tcp_mss(&self, handle: QDesc) -> Result<usize, Fail> {
         self.ipv4.tcp_mss(handle)
     }
@@ -176,7 +384,33 @@ impl<const N: usize> Engine<N> {
         self.ipv4.tcp_rto(handle)
     }
 
+    pub fn tcp_stats(&self, handle: QDesc) -> Result<TcpConnectionStats, Fail> {
+        self.ipv4.tcp_stats(handle)
+    }
+
+    pub fn tcp_info(&self, handle: QDesc) -> Result<TcpInfo, Fail> {
+        self.ipv4.tcp_info(handle)
+    }
+
+    pub fn tcp_byte_stats(&self, handle: QDesc) -> Result<ByteStats, Fail> {
+        self.ipv4.tcp.byte_stats(handle)
+    }
+
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        self.qtable.borrow().queues()
+    }
+
+    pub fn query_queue(&self, fd: QDesc) -> Option<QueueInfo> {
+        self.qtable.borrow().query(&fd)
+    }
+
     pub fn export_arp_cache(&self) -> HashMap<Ipv4Addr, MacAddress> {
         self.arp.export_cache()
     }
+
+    pub fn set_local_identity(&mut self, ipv4_addr: Ipv4Addr, link_addr: MacAddress) {
+        self.arp.set_local_identity(ipv4_addr, link_addr);
+        self.ipv4.set_local_identity(ipv4_addr, link_addr);
+        self.arp.announce();
+    }
 }
@@ -8,6 +8,7 @@ pub use self::runtime::TestRuntime;
 pub use engine::Engine;
 
 use crate::{
+    inetstack::protocols::ipv4::PacketFilter,
     runtime::{
         network::{
             config::{
@@ -46,7 +47,7 @@ pub const CARRIE_IPV4: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 3);
 // Standalone Functions
 //==============================================================================
 
-pub fn new_alice<const N: usize>(now: Instant) -> Engine<N> {
+pub fn new_alice(now: Instant) -> Engine {
     let arp_options = ArpConfig::new(
         Some(Duration::from_secs(600)),
         Some(Duration::from_secs(1)),
@@ -59,10 +60,10 @@ pub fn new_alice<const N: usize>(now: Instant) -> Engine<N> {
     let rt = TestRuntime::new(now, arp_options, udp_config, tcp_config, ALICE_MAC, ALICE_IPV4);
     let scheduler: Scheduler = rt.scheduler.clone();
     let clock: TimerRc = rt.clock.clone();
-    Engine::new(rt, scheduler, clock).unwrap()
+    Engine::new(rt, scheduler, clock, None).unwrap()
 }
 
-pub fn new_bob<const N: usize>(now: Instant) -> Engine<N> {
+pub fn new_bob(now: Instant) -> Engine {
     let arp_options = ArpConfig::new(
         Some(Duration::from_secs(600)),
         Some(Duration::from_secs(1)),
@@ -75,13 +76,18 @@ pub fn new_bob<const N: usize>(now: Instant) -> Engine<N> {
     let rt = TestRuntime::new(now, arp_options, udp_config, tcp_config, BOB_MAC, BOB_IPV4);
     let scheduler: Scheduler = rt.scheduler.clone();
     let clock: TimerRc = rt.clock.clone();
-    Engine::new(rt, scheduler, clock).unwrap()
+    Engine::new(rt, scheduler, clock, None).unwrap()
 }
 
-pub fn new_alice2<const N: usize>(now: Instant) -> Engine<N> {
+pub fn new_alice2(now: Instant) -> Engine {
+    new_alice2_with_tcp_config(now, TcpConfig::default())
+}
+
+pub fn new_alice2_with_tcp_config(now: Instant, tcp_config: TcpConfig) -> Engine {
     let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::<Ipv4Addr, MacAddress>::new();
     arp.insert(ALICE_IPV4, ALICE_MAC);
     arp.insert(BOB_IPV4, BOB_MAC);
+    arp.insert(CARRIE_IPV4, CARRIE_MAC);
     let arp_options = ArpConfig::new(
         Some(Duration::from_secs(600)),
         Some(Duration::from_secs(1)),
@@ -90,14 +96,34 @@ pub fn new_alice2<const N: usize>(now: Instant) -> Engine<N> {
         Some(false),
     );
     let udp_config = UdpConfig::default();
-    let tcp_config = TcpConfig::default();
     let rt = TestRuntime::new(now, arp_options, udp_config, tcp_config, ALICE_MAC, ALICE_IPV4);
     let scheduler: Scheduler = rt.scheduler.clone();
     let clock: TimerRc = rt.clock.clone();
-    Engine::new(rt, scheduler, clock).unwrap()
+    Engine::new(rt, scheduler, clock, None).unwrap()
+}
+
+pub fn new_bob2(now: Instant) -> Engine {
+    new_bob2_with_tcp_config(now, TcpConfig::default())
+}
+
+pub fn new_bob2_with_tcp_config(now: Instant, tcp_config: TcpConfig) -> Engine {
+    build_bob2(now, UdpConfig::default(), tcp_config, None)
 }
 
-pub fn new_bob2<const N: usize>(now: Instant) -> Engine<N> {
+pub fn new_bob2_with_udp_config(now: Instant, udp_config: UdpConfig) -> Engine {
+    build_bob2(now, udp_config, TcpConfig::default(), None)
+}
+
+pub fn new_bob2_with_packet_filter(now: Instant, packet_filter: PacketFilter) -> Engine {
+    build_bob2(now, UdpConfig::default(), TcpConfig::default(), Some(packet_filter))
+}
+
+fn build_bob2(
+    now: Instant,
+    udp_config: UdpConfig,
+    tcp_config: TcpConfig,
+    packet_filter: Option<PacketFilter>,
+) -> Engine {
     let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::<Ipv4Addr, MacAddress>::new();
     arp.insert(BOB_IPV4, BOB_MAC);
     arp.insert(ALICE_IPV4, ALICE_MAC);
@@ -108,15 +134,13 @@ pub fn new_bob2<const N: usize>(now: Instant) -> Engine<N> {
         Some(arp),
         Some(false),
     );
-    let udp_config = UdpConfig::default();
-    let tcp_config = TcpConfig::default();
     let rt = TestRuntime::new(now, arp_options, udp_config, tcp_config, BOB_MAC, BOB_IPV4);
     let scheduler: Scheduler = rt.scheduler.clone();
     let clock: TimerRc = rt.clock.clone();
-    Engine::new(rt, scheduler, clock).unwrap()
+    Engine::new(rt, scheduler, clock, packet_filter).unwrap()
 }
 
-pub fn new_carrie<const N: usize>(now: Instant) -> Engine<N> {
+pub fn new_carrie(now: Instant) -> Engine {
     let arp_options = ArpConfig::new(
         Some(Duration::from_secs(600)),
         Some(Duration::from_secs(1)),
@@ -130,5 +154,24 @@ pub fn new_carrie<const N: usize>(now: Instant) -> Engine<N> {
     let rt = TestRuntime::new(now, arp_options, udp_config, tcp_config, CARRIE_MAC, CARRIE_IPV4);
     let scheduler: Scheduler = rt.scheduler.clone();
     let clock: TimerRc = rt.clock.clone();
-    Engine::new(rt, scheduler, clock).unwrap()
+    Engine::new(rt, scheduler, clock, None).unwrap()
+}
+
+pub fn new_carrie2(now: Instant) -> Engine {
+    let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::<Ipv4Addr, MacAddress>::new();
+    arp.insert(CARRIE_IPV4, CARRIE_MAC);
+    arp.insert(ALICE_IPV4, ALICE_MAC);
+    let arp_options = ArpConfig::new(
+        Some(Duration::from_secs(600)),
+        Some(Duration::from_secs(1)),
+        Some(2),
+        Some(arp),
+        Some(false),
+    );
+    let udp_config = UdpConfig::default();
+    let tcp_config = TcpConfig::default();
+    let rt = TestRuntime::new(now, arp_options, udp_config, tcp_config, CARRIE_MAC, CARRIE_IPV4);
+    let scheduler: Scheduler = rt.scheduler.clone();
+    let clock: TimerRc = rt.clock.clone();
+    Engine::new(rt, scheduler, clock, None).unwrap()
 }
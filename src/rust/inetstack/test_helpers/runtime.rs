@@ -11,9 +11,15 @@ use crate::{
                 TcpConfig,
                 UdpConfig,
             },
-            types::MacAddress,
+            types::{
+                Ipv4IdGenerator,
+                LinkStatus,
+                MacAddress,
+                NicStats,
+            },
             NetworkRuntime,
             PacketBuf,
+            ReceiveBatch,
         },
         timer::{
             Timer,
@@ -22,7 +28,10 @@ use crate::{
     },
     scheduler::scheduler::Scheduler,
 };
-use ::arrayvec::ArrayVec;
+use ::rand::{
+    prelude::SmallRng,
+    SeedableRng,
+};
 use ::std::{
     cell::RefCell,
     collections::VecDeque,
@@ -41,6 +50,9 @@ pub struct Inner {
     timer: TimerRc,
     incoming: VecDeque<DemiBuffer>,
     outgoing: VecDeque<DemiBuffer>,
+    stats: NicStats,
+    link: LinkStatus,
+    multicast_filter: Vec<MacAddress>,
 }
 
 #[derive(Clone)]
@@ -51,6 +63,7 @@ pub struct TestRuntime {
     pub udp_config: UdpConfig,
     pub tcp_config: TcpConfig,
     inner: Rc<RefCell<Inner>>,
+    ipv4_id: Ipv4IdGenerator,
     pub scheduler: Scheduler,
     pub clock: TimerRc,
 }
@@ -74,11 +87,15 @@ impl TestRuntime {
             timer: TimerRc(Rc::new(Timer::new(now))),
             incoming: VecDeque::new(),
             outgoing: VecDeque::new(),
+            stats: NicStats::default(),
+            link: LinkStatus::up(0),
+            multicast_filter: Vec::new(),
         };
         Self {
             link_addr,
             ipv4_addr,
             inner: Rc::new(RefCell::new(inner)),
+            ipv4_id: Ipv4IdGenerator::new(&mut SmallRng::from_entropy()),
             scheduler: Scheduler::default(),
             clock: TimerRc(Rc::new(Timer::new(now))),
             arp_options,
@@ -103,6 +120,16 @@ impl TestRuntime {
         self.inner.borrow_mut().incoming.push_back(buf);
     }
 
+    /// Simulates a change in the state of the link.
+    pub fn set_link_status(&self, link: LinkStatus) {
+        self.inner.borrow_mut().link = link;
+    }
+
+    /// Returns the multicast link addresses that the stack last asked to receive.
+    pub fn get_multicast_filter(&self) -> Vec<MacAddress> {
+        self.inner.borrow().multicast_filter.clone()
+    }
+
     pub fn poll_scheduler(&self) {
         // let mut ctx = Context::from_waker(noop_waker_ref());
         self.scheduler.poll();
@@ -113,7 +140,7 @@ impl TestRuntime {
 // Trait Implementations
 //==============================================================================
 
-impl<const N: usize> NetworkRuntime<N> for TestRuntime {
+impl NetworkRuntime for TestRuntime {
     fn transmit(&self, pkt: Box<dyn PacketBuf>) {
         let header_size: usize = pkt.header_size();
         let body_size: usize = pkt.body_size();
@@ -122,19 +149,50 @@ impl<const N: usize> NetworkRuntime<N> for TestRuntime {
         // For this test harness, we 2^16 bytes (u16::MAX) as our limit.
         assert!(header_size + body_size < u16::MAX as usize);
 
+        // Packets whose headers were prepended to their body go out as they are, without being copied, just like a NIC
+        // would send a single buffer.
+        if header_size == 0 {
+            if let Some(buf) = pkt.take_body() {
+                let mut inner = self.inner.borrow_mut();
+                inner.stats.record_tx(buf.len());
+                inner.outgoing.push_back(buf);
+                return;
+            }
+        }
+
         let mut buf: DemiBuffer = DemiBuffer::new((header_size + body_size) as u16);
         pkt.write_header(&mut buf[..header_size]);
         if let Some(body) = pkt.take_body() {
             buf[header_size..].copy_from_slice(&body[..]);
         }
-        self.inner.borrow_mut().outgoing.push_back(buf);
+        let mut inner = self.inner.borrow_mut();
+        inner.stats.record_tx(buf.len());
+        inner.outgoing.push_back(buf);
     }
 
-    fn receive(&self) -> ArrayVec<DemiBuffer, N> {
-        let mut out = ArrayVec::new();
-        if let Some(buf) = self.inner.borrow_mut().incoming.pop_front() {
+    fn next_ipv4_id(&self) -> u16 {
+        self.ipv4_id.next_id()
+    }
+
+    fn receive(&self) -> ReceiveBatch {
+        let mut out = ReceiveBatch::new();
+        let mut inner = self.inner.borrow_mut();
+        if let Some(buf) = inner.incoming.pop_front() {
+            inner.stats.record_rx(buf.len());
             out.push(buf);
         }
         out
     }
+
+    fn nic_stats(&self) -> NicStats {
+        self.inner.borrow().stats
+    }
+
+    fn link_status(&self) -> LinkStatus {
+        self.inner.borrow().link
+    }
+
+    fn set_multicast_filter(&self, addrs: &[MacAddress]) {
+        self.inner.borrow_mut().multicast_filter = addrs.to_vec();
+    }
 }
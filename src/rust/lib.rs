@@ -66,10 +66,14 @@ pub use crate::runtime::{
         demi_sgarray_t,
         demi_sgaseg_t,
     },
+    ConnectionInfo,
+    ConnectionState,
     OperationResult,
     QDesc,
     QToken,
     QType,
+    QueueInfo,
+    QueueState,
 };
 
 pub mod demikernel;
@@ -67,6 +67,64 @@ pub unsafe fn set_nonblock(fd: RawFd) -> i32 {
     libc::fcntl(fd, libc::F_SETFL, flags, 1)
 }
 
+/// Sets IP_ADD_MEMBERSHIP option in a socket, joining a multicast group on the default interface.
+#[cfg(feature = "catcollar-libos")]
+pub unsafe fn set_ip_add_membership(fd: RawFd, group: &Ipv4Addr) -> i32 {
+    let value: libc::ip_mreq = libc::ip_mreq {
+        imr_multiaddr: ipv4addr_to_in_addr(group),
+        imr_interface: ipv4addr_to_in_addr(&Ipv4Addr::UNSPECIFIED),
+    };
+    let value_ptr: *const libc::ip_mreq = &value as *const libc::ip_mreq;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::IPPROTO_IP,
+        libc::IP_ADD_MEMBERSHIP,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+/// Sets IP_ADD_SOURCE_MEMBERSHIP option in a socket, receiving datagrams that `src` sends to a multicast group.
+#[cfg(feature = "catcollar-libos")]
+pub unsafe fn set_ip_add_source_membership(fd: RawFd, group: &Ipv4Addr, src: &Ipv4Addr) -> i32 {
+    set_ip_source_option(fd, libc::IP_ADD_SOURCE_MEMBERSHIP, group, src)
+}
+
+/// Sets IP_BLOCK_SOURCE option in a socket, filtering out datagrams that `src` sends to a multicast group.
+#[cfg(feature = "catcollar-libos")]
+pub unsafe fn set_ip_block_source(fd: RawFd, group: &Ipv4Addr, src: &Ipv4Addr) -> i32 {
+    set_ip_source_option(fd, libc::IP_BLOCK_SOURCE, group, src)
+}
+
+/// Sets a source-specific multicast option in a socket (RFC 3678).
+#[cfg(feature = "catcollar-libos")]
+unsafe fn set_ip_source_option(fd: RawFd, option: libc::c_int, group: &Ipv4Addr, src: &Ipv4Addr) -> i32 {
+    let value: libc::ip_mreq_source = libc::ip_mreq_source {
+        imr_multiaddr: ipv4addr_to_in_addr(group),
+        imr_interface: ipv4addr_to_in_addr(&Ipv4Addr::UNSPECIFIED),
+        imr_sourceaddr: ipv4addr_to_in_addr(src),
+    };
+    let value_ptr: *const libc::ip_mreq_source = &value as *const libc::ip_mreq_source;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::IPPROTO_IP,
+        option,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+/// Converts a [std::net::Ipv4Addr] to a [libc::in_addr].
+#[cfg(feature = "catcollar-libos")]
+fn ipv4addr_to_in_addr(addr: &Ipv4Addr) -> libc::in_addr {
+    // Octets are already in network byte order.
+    libc::in_addr {
+        s_addr: u32::from_ne_bytes(addr.octets()),
+    }
+}
+
 /// Converts a [std::net::SocketAddrV4] to a [libc::sockaddr_in].
 fn socketaddrv4_to_sockaddr_in(addr: &SocketAddrV4) -> libc::sockaddr_in {
     libc::sockaddr_in {
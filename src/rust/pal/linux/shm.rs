@@ -45,6 +45,50 @@ pub struct SharedMemory {
 impl SharedMemory {
     /// Opens an existing named shared memory region.
     pub fn open(name: &str, len: usize) -> Result<SharedMemory, Fail> {
+        let mut shm: SharedMemory = SharedMemory::open_unmapped(name)?;
+        shm.map(len)?;
+        Ok(shm)
+    }
+
+    /// Opens an existing named shared memory region, mapping all of it. Unlike [SharedMemory::open], this does not
+    /// require the caller to know the size of the region.
+    #[allow(unused)]
+    pub fn attach(name: &str) -> Result<SharedMemory, Fail> {
+        let mut shm: SharedMemory = SharedMemory::open_unmapped(name)?;
+        let len: usize = shm.stat_size()?;
+        shm.map(len)?;
+        Ok(shm)
+    }
+
+    /// Removes the name of a shared memory region, so that it can no longer be opened. The region itself is released
+    /// once all processes unmap it.
+    #[allow(unused)]
+    pub fn unlink_name(name: &str) -> Result<(), Fail> {
+        let name: ffi::CString = match ffi::CString::new(name.to_string()) {
+            Ok(name) => name,
+            Err(_) => return Err(Fail::new(libc::EINVAL, "could not parse name of shared memory region")),
+        };
+        // Forward request to underlying POSIX OS.
+        unsafe {
+            let ret: libc::c_int = libc::shm_unlink(name.as_ptr());
+
+            // Check for failure return value.
+            if ret == -1 {
+                let errno: libc::c_int = *libc::__errno_location();
+                let cause: String = format!(
+                    "failed to unlink shared memory region (name={:?}, errno={})",
+                    name, errno
+                );
+                error!("unlink_name(): {}", cause);
+                return Err(Fail::new(errno, &cause));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Opens an existing named shared memory region, without mapping it.
+    fn open_unmapped(name: &str) -> Result<SharedMemory, Fail> {
         let name: ffi::CString = match ffi::CString::new(name.to_string()) {
             Ok(name) => name,
             Err(_) => return Err(Fail::new(libc::EINVAL, "could not parse name of shared memory region")),
@@ -56,10 +100,7 @@ impl SharedMemory {
             // Check for failure return value.
             if ret == -1 {
                 let errno: libc::c_int = *libc::__errno_location();
-                let cause: String = format!(
-                    "failed to open shared memory region (name={:?}, len={}, errno={})",
-                    name, len, errno
-                );
+                let cause: String = format!("failed to open shared memory region (name={:?}, errno={})", name, errno);
                 error!("open(): {}", cause);
                 return Err(Fail::new(errno, &cause));
             }
@@ -67,17 +108,13 @@ impl SharedMemory {
             ret
         };
 
-        let mut shm: SharedMemory = SharedMemory {
+        Ok(SharedMemory {
             was_created: false,
             fd,
             name,
             size: 0,
             addr: ptr::null_mut(),
-        };
-
-        shm.map(len)?;
-
-        Ok(shm)
+        })
     }
 
     /// Creates a named shared memory region.
@@ -136,14 +173,15 @@ impl SharedMemory {
         Ok(())
     }
 
-    /// Unlinks the target shared memory region.
+    /// Unlinks the target shared memory region. A region that was already unlinked (see [SharedMemory::unlink_name])
+    /// is left alone.
     fn unlink(&mut self) -> Result<(), Fail> {
         // Forward request to underlying POSIX OS.
         unsafe {
             let ret: libc::c_int = libc::shm_unlink(self.name.as_ptr());
 
             // Check for failure return value.
-            if ret == -1 {
+            if ret == -1 && *libc::__errno_location() != libc::ENOENT {
                 return Err(Fail::new(libc::EAGAIN, "failed to unlink shared memory region"));
             }
         }
@@ -151,6 +189,20 @@ impl SharedMemory {
         Ok(())
     }
 
+    /// Queries the size of the target shared memory region, as set by its creator.
+    fn stat_size(&self) -> Result<usize, Fail> {
+        // Forward request to underlying POSIX OS.
+        let mut stat: libc::stat = unsafe { mem::zeroed() };
+        let ret: libc::c_int = unsafe { libc::fstat(self.fd, &mut stat) };
+
+        // Check for failure return value.
+        if ret == -1 {
+            return Err(Fail::new(libc::EAGAIN, "failed to query size of shared memory region"));
+        }
+
+        Ok(stat.st_size as usize)
+    }
+
     /// Truncates the target shared memory region.
     fn truncate(&mut self, size: usize) -> Result<(), Fail> {
         // Forward request to underlying POSIX OS.
@@ -351,6 +403,25 @@ mod tests {
         Ok(())
     }
 
+    /// Tests if we succeed to attach to a shared memory region without knowing its size, and if a region can no longer
+    /// be opened once it is unlinked.
+    #[test]
+    fn attach_unlink() -> Result<()> {
+        let shm_name: String = "shm-test-attach-unlink".to_string();
+        let _shm_created: SharedMemory = do_create(&shm_name)?;
+        let shm_attached: SharedMemory = match SharedMemory::attach(&shm_name) {
+            Ok(shm) => shm,
+            Err(_) => anyhow::bail!("attaching to an existing shared memory region should be possible"),
+        };
+        crate::ensure_eq!(shm_attached.size(), SHM_SIZE);
+
+        crate::ensure_eq!(SharedMemory::unlink_name(&shm_name).is_ok(), true);
+        crate::ensure_eq!(SharedMemory::attach(&shm_name).is_err(), true);
+        crate::ensure_eq!(SharedMemory::unlink_name(&shm_name).is_err(), true);
+
+        Ok(())
+    }
+
     /// Tets if we succeed to read/write to/from a shared memory region using read/write functions.
     #[test]
     fn read_write() -> Result<()> {
@@ -13,6 +13,7 @@ use ::std::{
     error,
     fmt,
     io,
+    iter,
 };
 
 //==============================================================================
@@ -20,12 +21,17 @@ use ::std::{
 //==============================================================================
 
 /// Failure
+///
+/// A failure may wrap the failure that led to it, so that each layer that an error goes through can add some context
+/// to it. The failures that are wrapped this way form a chain, whose last link is the root cause.
 #[derive(Clone)]
 pub struct Fail {
     /// Error code.
     pub errno: c_int,
     /// Cause.
     pub cause: String,
+    /// Failure that led to this one, if any.
+    source: Option<Box<Fail>>,
 }
 
 //==============================================================================
@@ -39,8 +45,31 @@ impl Fail {
         Self {
             errno,
             cause: cause.to_string(),
+            source: None,
         }
     }
+
+    /// Wraps `cause`, which led to the target failure, so that the target failure adds context to it. If the target
+    /// failure wraps another one already, `cause` is wrapped at the end of its chain.
+    pub fn chain(mut self, cause: Fail) -> Fail {
+        let mut link: &mut Fail = &mut self;
+        while link.source.is_some() {
+            link = link.source.as_mut().unwrap();
+        }
+        link.source = Some(Box::new(cause));
+        self
+    }
+
+    /// Returns the failure at the end of the chain of the target failure, which is the target failure itself if it
+    /// wraps no other one.
+    pub fn root_cause(&self) -> &Fail {
+        self.iter_chain().last().unwrap_or(self)
+    }
+
+    /// Walks the chain of the target failure, starting with the target failure itself and ending with the root cause.
+    pub fn iter_chain(&self) -> impl Iterator<Item = &Fail> {
+        iter::successors(Some(self), |fail| fail.source.as_deref())
+    }
 }
 
 //==============================================================================
@@ -49,27 +78,85 @@ impl Fail {
 
 /// Display Trait Implementation for Failures
 impl fmt::Display for Fail {
+    /// Prints the causes along the chain of the target failure, from the outermost one to the root cause.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Error {:?}: {:?}", self.errno, self.cause)
+        write!(f, "Error {:?}: ", self.errno)?;
+        for (i, fail) in self.iter_chain().enumerate() {
+            if i > 0 {
+                write!(f, ": ")?;
+            }
+            write!(f, "{}", fail.cause)?;
+        }
+        Ok(())
     }
 }
 
 /// Debug trait Implementation for Failures
 impl fmt::Debug for Fail {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Error {:?}: {:?}", self.errno, self.cause)
+        write!(f, "Error {:?}: {:?}", self.errno, self.cause)?;
+        if let Some(source) = &self.source {
+            write!(f, ", caused by {:?}", source)?;
+        }
+        Ok(())
     }
 }
 
 /// Error Trait Implementation for Failures
-impl error::Error for Fail {}
+impl error::Error for Fail {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn error::Error + 'static))
+    }
+}
 
 /// Conversion Trait Implementation for Fail
 impl From<io::Error> for Fail {
     fn from(_: io::Error) -> Self {
-        Self {
-            errno: EIO,
-            cause: "I/O error".to_string(),
-        }
+        Self::new(EIO, "I/O error")
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::Fail;
+    use ::anyhow::Result;
+
+    /// Tests if a chain of failures is walked from the outermost failure to the root cause, and printed that way.
+    #[test]
+    fn test_fail_chain() -> Result<()> {
+        let root: Fail = Fail::new(libc::ENOBUFS, "no buffer space");
+        let fail: Fail = Fail::new(libc::EIO, "operation failed")
+            .chain(Fail::new(libc::ENOBUFS, "failed to send data"))
+            .chain(root);
+
+        let errnos: Vec<i32> = fail.iter_chain().map(|fail| fail.errno).collect();
+        crate::ensure_eq!(errnos, vec![libc::EIO, libc::ENOBUFS, libc::ENOBUFS]);
+        crate::ensure_eq!(fail.root_cause().cause.as_str(), "no buffer space");
+        crate::ensure_eq!(
+            fail.to_string(),
+            format!(
+                "Error {}: operation failed: failed to send data: no buffer space",
+                libc::EIO
+            )
+        );
+
+        Ok(())
+    }
+
+    /// Tests if a failure that wraps no other one is its own root cause.
+    #[test]
+    fn test_fail_without_chain() -> Result<()> {
+        let fail: Fail = Fail::new(libc::EINVAL, "invalid argument");
+        crate::ensure_eq!(fail.iter_chain().count(), 1);
+        crate::ensure_eq!(fail.root_cause().errno, libc::EINVAL);
+        crate::ensure_eq!(fail.to_string(), format!("Error {}: invalid argument", libc::EINVAL));
+
+        Ok(())
     }
 }
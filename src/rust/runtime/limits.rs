@@ -8,3 +8,8 @@ pub const RECVBUF_SIZE_MAX: usize = 8192;
 /// Maximum size for a fixed-size pop operation.
 /// This is set to be at most `RECVBUF_SIZE_MAX`.
 pub const POP_SIZE_MAX: usize = RECVBUF_SIZE_MAX;
+
+/// Headroom reserved in front of the data of scatter-gather arrays that are allocated off the heap.
+/// This is set to fit the Ethernet, IPv4 and UDP headers of a datagram (with room to spare for IPv4 options), and
+/// matches the default headroom of DPDK MBufs.
+pub const SGA_HEADROOM_SIZE: usize = 128;
@@ -8,6 +8,17 @@
 use ::flexi_logger::Logger;
 use ::std::sync::Once;
 
+#[cfg(test)]
+use ::log::{
+    Level,
+    LevelFilter,
+    Log,
+    Metadata,
+    Record,
+};
+#[cfg(test)]
+use ::std::cell::RefCell;
+
 //==============================================================================
 // Static Variables
 //==============================================================================
@@ -15,13 +26,75 @@ use ::std::sync::Once;
 /// Guardian to the logging initialize function.
 static INIT_LOG: Once = Once::new();
 
+#[cfg(test)]
+thread_local! {
+    /// Warnings logged by the calling thread, if they are being captured.
+    static CAPTURED_WARNINGS: RefCell<Option<Vec<String>>> = RefCell::new(None);
+}
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Logger that captures warnings (and errors) for tests to check, before handing all records over to the actual logger.
+#[cfg(test)]
+struct CapturingLogger {
+    inner: Box<dyn Log>,
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+#[cfg(test)]
+impl Log for CapturingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() <= Level::Warn {
+            let _ = CAPTURED_WARNINGS.try_with(|captured| {
+                if let Some(warnings) = captured.borrow_mut().as_mut() {
+                    warnings.push(record.args().to_string());
+                }
+            });
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
 //==============================================================================
 // Standalone Functions
 //==============================================================================
 
 /// Initializes logging features.
+#[cfg(not(test))]
 pub fn initialize() {
     INIT_LOG.call_once(|| {
         Logger::try_with_env().unwrap().start().unwrap();
     });
 }
+
+/// Initializes logging features, so that warnings may be captured as well.
+#[cfg(test)]
+pub fn initialize() {
+    INIT_LOG.call_once(|| {
+        let (inner, _) = Logger::try_with_env().unwrap().build().unwrap();
+        ::log::set_boxed_logger(Box::new(CapturingLogger { inner })).unwrap();
+        ::log::set_max_level(::log::max_level().max(LevelFilter::Warn));
+    });
+}
+
+/// Runs `f` and returns the warnings (and errors) that it logged on the calling thread.
+#[cfg(test)]
+pub fn capture_warnings<F: FnOnce()>(f: F) -> Vec<String> {
+    initialize();
+    CAPTURED_WARNINGS.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+    f();
+    CAPTURED_WARNINGS.with(|captured| captured.borrow_mut().take().unwrap_or_default())
+}
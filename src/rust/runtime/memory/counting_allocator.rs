@@ -0,0 +1,68 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::std::{
+    alloc::{
+        GlobalAlloc,
+        Layout,
+        System,
+    },
+    cell::Cell,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Allocator that counts the allocations made by each thread, along with the bytes they request, and the deallocations,
+/// so that tests can check for allocation-free paths and for leaks. Counters are per thread, so tests that run concurrently do not disturb each other.
+struct CountingAllocator;
+
+thread_local! {
+    static NUM_ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static NUM_ALLOCATED_BYTES: Cell<usize> = const { Cell::new(0) };
+    static NUM_DEALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = NUM_ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        let _ = NUM_ALLOCATED_BYTES.try_with(|count| count.set(count.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = NUM_DEALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Returns the number of allocations that the calling thread has made so far.
+pub fn num_allocations() -> usize {
+    NUM_ALLOCATIONS.with(|count| count.get())
+}
+
+/// Returns the number of bytes that the calling thread has allocated so far.
+pub fn num_allocated_bytes() -> usize {
+    NUM_ALLOCATED_BYTES.with(|count| count.get())
+}
+
+/// Returns the number of deallocations that the calling thread has made so far.
+pub fn num_deallocations() -> usize {
+    NUM_DEALLOCATIONS.with(|count| count.get())
+}
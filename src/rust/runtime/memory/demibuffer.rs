@@ -31,6 +31,7 @@
 use crate::{
     pal::arch,
     runtime::fail::Fail,
+    scheduler::TaskTiming,
 };
 #[cfg(feature = "libdpdk")]
 use ::dpdk_rs::{
@@ -65,6 +66,7 @@ use ::std::{
         NonNull,
     },
     slice,
+    time::Instant,
 };
 
 // Buffer Metadata.
@@ -134,7 +136,15 @@ struct MetaData {
     // Timesync flags for use with IEEE 1588 "Precision Time Protocol" (PTP).
     _timesync: u16,
     // Reserved for dynamic fields.
-    _dynfield: [u32; 9],
+    _dynfield1: u32,
+    // IEEE 1588 hardware timestamp (in nanoseconds) of a received buffer.  Only valid if METADATA_F_RX_IEEE1588_TMST is
+    // set.  In DPDK MBufs, this lives in a dynamic field that we register at HW_TIMESTAMP_DYNFIELD_OFFSET.
+    hw_timestamp: u64,
+    // Software timestamp (in nanoseconds, see `TaskTiming::to_nanos()`) taken when this buffer was received.  Zero if
+    // there is none.  In DPDK MBufs, this lives in a dynamic field that we register at SW_TIMESTAMP_DYNFIELD_OFFSET.
+    sw_timestamp: u64,
+    // Reserved for dynamic fields.
+    _dynfield2: [u32; 4],
 }
 
 // Check MetaData structure alignment and size at compile time.
@@ -149,6 +159,21 @@ const _: () = assert!(std::mem::size_of::<MetaData>() == 2 * arch::CPU_DATA_CACH
 // points to another MetaData's directly attached data.
 const METADATA_F_INDIRECT: u64 = 1 << 62;
 
+// Indicates the IEEE 1588 hardware timestamp (hw_timestamp) of a received buffer is valid.
+const METADATA_F_RX_IEEE1588_TMST: u64 = 1 << 10;
+
+// Requests an IEEE 1588 hardware timestamp for this buffer when it is transmitted.
+const METADATA_F_TX_IEEE1588_TMST: u64 = 1 << 51;
+
+/// Offset of the hardware timestamp within the MetaData structure (and thus, within a DPDK MBuf).
+// This is the second word of the dynamic field area, as the first one isn't aligned for a 64-bit value.
+#[cfg(feature = "libdpdk")]
+pub const HW_TIMESTAMP_DYNFIELD_OFFSET: usize = 96;
+
+/// Offset of the software receive timestamp within the MetaData structure (and thus, within a DPDK MBuf).
+#[cfg(feature = "libdpdk")]
+pub const SW_TIMESTAMP_DYNFIELD_OFFSET: usize = 104;
+
 impl MetaData {
     // Note on Reference Counts:
     // Since we are currently single-threaded, there is no need to use atomic operations for refcnt manipulations.
@@ -285,6 +310,7 @@ impl DemiBuffer {
             metadata.refcnt = 1;
             metadata.nb_segs = 1;
             metadata.ol_flags = 0;
+            metadata.sw_timestamp = 0;
             metadata.pkt_len = capacity as u32;
             metadata.data_len = capacity;
             metadata.buf_len = capacity;
@@ -302,12 +328,36 @@ impl DemiBuffer {
     }
 
     /// Create a new Heap-allocated `DemiBuffer` from a byte slice.
+    ///
+    /// The bytes are copied into the new buffer, so the slice may be modified or freed right away without affecting
+    /// the buffer.  This fails if the slice is longer than the largest `DemiBuffer` (`u16::MAX` bytes).  To hand over
+    /// existing memory without copying, use [DemiBuffer::from_raw] (or [DemiBuffer::from_mbuf] for DPDK buffers).
     pub fn from_slice(slice: &[u8]) -> Result<Self, Fail> {
         // Note: The implementation of the TryFrom trait (see below, under "Trait Implementations") automatically
         // provides us with a TryInto trait implementation (which is where try_into comes from).
         slice.try_into()
     }
 
+    /// Create a new Heap-allocated `DemiBuffer` with a `capacity` byte data area that is preceded by `headroom` bytes of
+    /// unused space.
+    ///
+    /// The headroom lets protocol headers be prepended to the data later on (see [DemiBuffer::prepend]) without copying
+    /// the data into a larger buffer.  This fails if the data area and the headroom do not fit together in the largest
+    /// `DemiBuffer` (`u16::MAX` bytes).
+    pub fn new_with_headroom(capacity: u16, headroom: u16) -> Result<Self, Fail> {
+        let total: u16 = match capacity.checked_add(headroom) {
+            Some(total) => total,
+            None => {
+                let cause: String = format!("buffer is too large (capacity={:?}, headroom={:?})", capacity, headroom);
+                error!("new_with_headroom(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        };
+        let mut buf: DemiBuffer = DemiBuffer::new(total);
+        buf.adjust(headroom as usize)?;
+        Ok(buf)
+    }
+
     /// Creates a `DemiBuffer` from a raw pointer.
     pub unsafe fn from_raw(token: NonNull<u8>) -> Self {
         DemiBuffer {
@@ -353,6 +403,119 @@ impl DemiBuffer {
         self.as_metadata().data_len as usize
     }
 
+    /// Returns the number of unused bytes in front of the data stored in the `DemiBuffer`.
+    pub fn headroom(&self) -> usize {
+        // TODO: Review having this "match", since MetaData and MBuf are laid out the same, these are equivalent cases.
+        match self.get_tag() {
+            Tag::Heap => self.as_metadata().data_off as usize,
+            #[cfg(feature = "libdpdk")]
+            Tag::Dpdk => {
+                let mbuf: *mut rte_mbuf = self.as_mbuf();
+                // Safety: The `mbuf` dereference below is safe, as it is aligned and dereferenceable.
+                unsafe { (*mbuf).data_off as usize }
+            },
+        }
+    }
+
+    /// Returns the number of unused bytes behind the data stored in the last segment of the `DemiBuffer` chain.
+    pub fn tailroom(&self) -> usize {
+        // TODO: Review having this "match", since MetaData and MBuf are laid out the same, these are equivalent cases.
+        match self.get_tag() {
+            Tag::Heap => {
+                let md_last: &mut MetaData = self.as_metadata().get_last_segment();
+                (md_last.buf_len - md_last.data_off - md_last.data_len) as usize
+            },
+            #[cfg(feature = "libdpdk")]
+            Tag::Dpdk => {
+                let mut mbuf: *mut rte_mbuf = self.as_mbuf();
+                // Safety: The `mbuf` dereferences below are safe, as they are aligned and dereferenceable.
+                unsafe {
+                    while !(*mbuf).next.is_null() {
+                        mbuf = (*mbuf).next;
+                    }
+                    ((*mbuf).buf_len - (*mbuf).data_off - (*mbuf).data_len) as usize
+                }
+            },
+        }
+    }
+
+    /// Adds `nbytes` bytes to the beginning of the `DemiBuffer` chain, taking them from the headroom, and returns them
+    /// so that they may be filled in (e.g. with protocol headers).  This is the reverse of [DemiBuffer::adjust].
+    // Note: Clones of a `DemiBuffer` share its headroom, as they share its data area.  So bytes that are prepended to
+    // one of the clones overwrite those that were prepended to any other.  This matches the behavior of DPDK's
+    // rte_pktmbuf_prepend() routine.
+    pub fn prepend(&mut self, nbytes: usize) -> Result<&mut [u8], Fail> {
+        if nbytes > self.headroom() {
+            return Err(Fail::new(
+                libc::EINVAL,
+                "tried to prepend more bytes than there is headroom for",
+            ));
+        }
+
+        // TODO: Review having this "match", since MetaData and MBuf are laid out the same, these are equivalent cases.
+        match self.get_tag() {
+            Tag::Heap => {
+                let metadata: &mut MetaData = self.as_metadata();
+                // The above check against data_off also means that nbytes is <= u16::MAX.  So these casts are safe.
+                metadata.data_off -= nbytes as u16;
+                metadata.pkt_len += nbytes as u32;
+                metadata.data_len += nbytes as u16;
+            },
+            #[cfg(feature = "libdpdk")]
+            Tag::Dpdk => {
+                let mbuf: *mut rte_mbuf = self.as_mbuf();
+                // Safety: The `mbuf` dereferences below are safe, as it is aligned and dereferenceable.
+                // The above check against data_off also means that nbytes is <= u16::MAX.  So these casts are safe.
+                unsafe {
+                    (*mbuf).data_off -= nbytes as u16;
+                    (*mbuf).pkt_len += nbytes as u32;
+                    (*mbuf).data_len += nbytes as u16;
+                }
+            },
+        }
+
+        Ok(&mut self.as_bytes_mut()[..nbytes])
+    }
+
+    /// Adds `nbytes` bytes to the end of the `DemiBuffer`, taking them from the tailroom, and returns them so that they
+    /// may be filled in.  This is the reverse of [DemiBuffer::trim].
+    // Note: This fails on a `DemiBuffer` chain, as the bytes would not be contiguous with the data in the first segment
+    // (which is all that the views of the data cover).
+    pub fn append(&mut self, nbytes: usize) -> Result<&mut [u8], Fail> {
+        if self.is_multi_segment() {
+            return Err(Fail::new(libc::EINVAL, "tried to append to a multi-segment buffer"));
+        }
+        if nbytes > self.tailroom() {
+            return Err(Fail::new(
+                libc::EINVAL,
+                "tried to append more bytes than there is tailroom for",
+            ));
+        }
+
+        // TODO: Review having this "match", since MetaData and MBuf are laid out the same, these are equivalent cases.
+        match self.get_tag() {
+            Tag::Heap => {
+                let metadata: &mut MetaData = self.as_metadata();
+                // The above check against the tailroom also means that nbytes is <= u16::MAX.  So these casts are safe.
+                metadata.pkt_len += nbytes as u32;
+                metadata.data_len += nbytes as u16;
+            },
+            #[cfg(feature = "libdpdk")]
+            Tag::Dpdk => {
+                let mbuf: *mut rte_mbuf = self.as_mbuf();
+                // Safety: The `mbuf` dereferences below are safe, as it is aligned and dereferenceable.
+                // The above check against the tailroom also means that nbytes is <= u16::MAX.  So these casts are safe.
+                unsafe {
+                    (*mbuf).pkt_len += nbytes as u32;
+                    (*mbuf).data_len += nbytes as u16;
+                }
+            },
+        }
+
+        let len: usize = self.len();
+        Ok(&mut self.as_bytes_mut()[(len - nbytes)..])
+    }
+
     /// Removes `nbytes` bytes from the beginning of the `DemiBuffer` chain.
     // Note: If `nbytes` is greater than the length of the first segment in the chain, then this function will fail and
     // return an error, rather than remove the remaining bytes from subsequent segments in the chain.  This is to match
@@ -428,6 +591,22 @@ impl DemiBuffer {
         Ok(())
     }
 
+    /// Shortens the `DemiBuffer` chain to `new_len` bytes, by removing bytes from its end.
+    // Note: This is built on top of trim(), and thus has the same restriction: the bytes to remove must all be in the
+    // last segment of the chain.
+    pub fn truncate(&mut self, new_len: usize) -> Result<(), Fail> {
+        let len: usize = self.len();
+        if new_len > len {
+            let cause: String = format!(
+                "cannot truncate buffer beyond its length (len={:?}, new_len={:?})",
+                len, new_len
+            );
+            error!("truncate(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        self.trim(len - new_len)
+    }
+
     ///
     /// **Description**
     ///
@@ -450,6 +629,12 @@ impl DemiBuffer {
         self.split(false, offset)
     }
 
+    /// Same as [DemiBuffer::split_back], named after [Vec::split_off]: the target [DemiBuffer] keeps the bytes in
+    /// `[0, at)` and the returned one holds the bytes in `[at, len)`.
+    pub fn split_off(&mut self, at: usize) -> Result<Self, Fail> {
+        self.split(false, at)
+    }
+
     ///
     /// **Description**
     ///
@@ -541,6 +726,102 @@ impl DemiBuffer {
         }
     }
 
+    /// Provides a view of the data in the target `DemiBuffer`. This is the same view that is provided by [Deref].
+    pub fn as_bytes(&self) -> &[u8] {
+        // Empty buffers may have no data space at all.
+        let len: usize = self.len();
+        if len == 0 {
+            return &[];
+        }
+        // TODO: Review having this "match", since MetaData and MBuf are laid out the same, these are equivalent cases.
+        match self.get_tag() {
+            Tag::Heap => {
+                // Safety: the call to from_raw_parts is safe, as its arguments refer to a valid readable memory region
+                // of the size specified (which is guaranteed to be smaller than isize::MAX) and is contained within
+                // a single allocated object.  Also, since the data type is u8, proper alignment is not an issue.
+                unsafe { slice::from_raw_parts(self.data_ptr(), len) }
+            },
+            #[cfg(feature = "libdpdk")]
+            Tag::Dpdk => {
+                // Safety: the call to from_raw_parts is safe, as its arguments refer to a valid readable memory region
+                // of the size specified (which is guaranteed to be smaller than isize::MAX) and is contained within
+                // a single allocated object.  Also, since the data type is u8, proper alignment is not an issue.
+                unsafe { slice::from_raw_parts(self.dpdk_data_ptr(), len) }
+            },
+        }
+    }
+
+    /// Provides a mutable view of the data in the target `DemiBuffer`. This is the same view that is provided by
+    /// [DerefMut], and should be used instead of building slices out of raw pointers to the data.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        // Empty buffers may have no data space at all.
+        let len: usize = self.len();
+        if len == 0 {
+            return &mut [];
+        }
+        // TODO: Review having this "match", since MetaData and MBuf are laid out the same, these are equivalent cases.
+        match self.get_tag() {
+            Tag::Heap => {
+                // Safety: the call to from_raw_parts_mut is safe, as its args refer to a valid readable memory region
+                // of the size specified (which is guaranteed to be smaller than isize::MAX) and is contained within
+                // a single allocated object.  Also, since the data type is u8, proper alignment is not an issue.
+                unsafe { slice::from_raw_parts_mut(self.data_ptr(), len) }
+            },
+            #[cfg(feature = "libdpdk")]
+            Tag::Dpdk => {
+                // Safety: the call to from_raw_parts_mut is safe, as its args refer to a valid readable memory region
+                // of the size specified (which is guaranteed to be smaller than isize::MAX) and is contained within
+                // a single allocated object.  Also, since the data type is u8, proper alignment is not an issue.
+                unsafe { slice::from_raw_parts_mut(self.dpdk_data_ptr(), len) }
+            },
+        }
+    }
+
+    /// Returns the IEEE 1588 hardware timestamp (in nanoseconds) taken when this `DemiBuffer` was received, if any.
+    pub fn hw_timestamp(&self) -> Option<u64> {
+        let metadata: &MetaData = self.as_metadata();
+        if metadata.ol_flags & METADATA_F_RX_IEEE1588_TMST != 0 {
+            Some(metadata.hw_timestamp)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the IEEE 1588 hardware timestamp (in nanoseconds) taken when this `DemiBuffer` was received.
+    pub fn set_hw_timestamp(&mut self, timestamp: Option<u64>) {
+        let metadata: &mut MetaData = self.as_metadata();
+        match timestamp {
+            Some(timestamp) => {
+                metadata.hw_timestamp = timestamp;
+                metadata.ol_flags |= METADATA_F_RX_IEEE1588_TMST;
+            },
+            None => metadata.ol_flags &= !METADATA_F_RX_IEEE1588_TMST,
+        }
+    }
+
+    /// Returns the software timestamp taken when this `DemiBuffer` was received, if any.
+    pub fn sw_timestamp(&self) -> Option<Instant> {
+        match self.as_metadata().sw_timestamp {
+            0 => None,
+            nanos => Some(TaskTiming::from_nanos(nanos)),
+        }
+    }
+
+    /// Sets the software timestamp taken when this `DemiBuffer` was received.
+    pub fn set_sw_timestamp(&mut self, timestamp: Option<Instant>) {
+        self.as_metadata().sw_timestamp = timestamp.map_or(0, TaskTiming::to_nanos);
+    }
+
+    /// Requests an IEEE 1588 hardware timestamp to be taken when this `DemiBuffer` is transmitted.
+    pub fn request_tx_hw_timestamp(&mut self) {
+        self.as_metadata().ol_flags |= METADATA_F_TX_IEEE1588_TMST;
+    }
+
+    /// Returns `true` if an IEEE 1588 hardware timestamp was requested for the transmission of this `DemiBuffer`.
+    pub fn tx_hw_timestamp_requested(&self) -> bool {
+        self.as_metadata().ol_flags & METADATA_F_TX_IEEE1588_TMST != 0
+    }
+
     /// Consumes the `DemiBuffer`, returning a raw token (useful for FFI) that can be used with `from_raw()`.
     // Note the type of the token is arbitrary, it should be treated as an opaque value.
     pub fn into_raw(self) -> NonNull<u8> {
@@ -757,6 +1038,8 @@ impl Clone for DemiBuffer {
                         clone.nb_segs = original.nb_segs;
                         clone.pkt_len = original.pkt_len;
                         clone.data_len = original.data_len;
+                        clone.hw_timestamp = original.hw_timestamp;
+                        clone.sw_timestamp = original.sw_timestamp;
 
                         // Special case for zero-length buffers.
                         if original.buf_len == 0 {
@@ -827,44 +1110,14 @@ impl Deref for DemiBuffer {
     type Target = [u8];
 
     fn deref(&self) -> &[u8] {
-        // TODO: Review having this "match", since MetaData and MBuf are laid out the same, these are equivalent cases.
-        match self.get_tag() {
-            Tag::Heap => {
-                // Safety: the call to from_raw_parts is safe, as its arguments refer to a valid readable memory region
-                // of the size specified (which is guaranteed to be smaller than isize::MAX) and is contained within
-                // a single allocated object.  Also, since the data type is u8, proper alignment is not an issue.
-                unsafe { slice::from_raw_parts(self.data_ptr(), self.len()) }
-            },
-            #[cfg(feature = "libdpdk")]
-            Tag::Dpdk => {
-                // Safety: the call to from_raw_parts is safe, as its arguments refer to a valid readable memory region
-                // of the size specified (which is guaranteed to be smaller than isize::MAX) and is contained within
-                // a single allocated object.  Also, since the data type is u8, proper alignment is not an issue.
-                unsafe { slice::from_raw_parts(self.dpdk_data_ptr(), self.len()) }
-            },
-        }
+        self.as_bytes()
     }
 }
 
 /// Mutable De-Reference Trait Implementation for `DemiBuffer`.
 impl DerefMut for DemiBuffer {
     fn deref_mut(&mut self) -> &mut [u8] {
-        // TODO: Review having this "match", since MetaData and MBuf are laid out the same, these are equivalent cases.
-        match self.get_tag() {
-            Tag::Heap => {
-                // Safety: the call to from_raw_parts_mut is safe, as its args refer to a valid readable memory region
-                // of the size specified (which is guaranteed to be smaller than isize::MAX) and is contained within
-                // a single allocated object.  Also, since the data type is u8, proper alignment is not an issue.
-                unsafe { slice::from_raw_parts_mut(self.data_ptr(), self.len()) }
-            },
-            #[cfg(feature = "libdpdk")]
-            Tag::Dpdk => {
-                // Safety: the call to from_raw_parts_mut is safe, as its args refer to a valid readable memory region
-                // of the size specified (which is guaranteed to be smaller than isize::MAX) and is contained within
-                // a single allocated object.  Also, since the data type is u8, proper alignment is not an issue.
-                unsafe { slice::from_raw_parts_mut(self.dpdk_data_ptr(), self.len()) }
-            },
-        }
+        self.as_bytes_mut()
     }
 }
 
@@ -941,10 +1194,9 @@ impl TryFrom<&[u8]> for DemiBuffer {
 
     fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
         // Check size of the slice to ensure a single DemiBuffer can hold it.
-        let size: u16 = if slice.len() < u16::MAX as usize {
-            slice.len() as u16
-        } else {
-            return Err(Fail::new(libc::EINVAL, "slice is larger than a DemiBuffer can hold"));
+        let size: u16 = match u16::try_from(slice.len()) {
+            Ok(size) => size,
+            Err(_) => return Err(Fail::new(libc::EINVAL, "slice is larger than a DemiBuffer can hold")),
         };
 
         // Allocate some memory off the heap.
@@ -964,11 +1216,6 @@ impl TryFrom<&[u8]> for DemiBuffer {
                 let address: *mut u8 = temp.cast::<u8>().as_ptr();
                 // Safety: The call to offset is safe, as the provided offset is known to be within the allocation.
                 metadata.buf_addr = unsafe { address.offset(size_of::<MetaData>() as isize) };
-
-                // Copy the data from the slice into the DemiBuffer.
-                // Safety: This is safe, as the src/dst argument pointers are valid for reads/writes of `size` bytes,
-                // are aligned (trivial for u8 pointers), and the regions they specify do not overlap one another.
-                unsafe { ptr::copy_nonoverlapping(slice.as_ptr(), metadata.buf_addr, size as usize) };
             }
 
             // Set field values as appropriate.
@@ -976,6 +1223,7 @@ impl TryFrom<&[u8]> for DemiBuffer {
             metadata.refcnt = 1;
             metadata.nb_segs = 1;
             metadata.ol_flags = 0;
+            metadata.sw_timestamp = 0;
             metadata.pkt_len = size as u32;
             metadata.data_len = size;
             metadata.buf_len = size;
@@ -984,12 +1232,16 @@ impl TryFrom<&[u8]> for DemiBuffer {
 
         // Embed the buffer type into the lower bits of the pointer.
         let tagged: NonNull<MetaData> = temp.with_addr(temp.addr() | Tag::Heap);
-
-        // Return the new DemiBuffer.
-        Ok(DemiBuffer {
+        let mut buf: DemiBuffer = DemiBuffer {
             tagged_ptr: tagged,
             _phantom: PhantomData,
-        })
+        };
+
+        // Copy the data from the slice into the DemiBuffer.
+        buf.as_bytes_mut().copy_from_slice(slice);
+
+        // Return the new DemiBuffer.
+        Ok(buf)
     }
 }
 
@@ -999,7 +1251,10 @@ impl TryFrom<&[u8]> for DemiBuffer {
 mod tests {
     use super::DemiBuffer;
     use ::anyhow::Result;
-    use std::ptr::NonNull;
+    use std::{
+        ptr::NonNull,
+        time::Instant,
+    };
 
     // Test basic allocation, len, adjust, and trim.
     #[test]
@@ -1063,6 +1318,29 @@ mod tests {
         Ok(())
     }
 
+    // Tests that allocation from a slice copies the data.
+    #[test]
+    fn from_slice() -> Result<()> {
+        let mut data: Vec<u8> = (0..64).collect();
+        let buf: DemiBuffer = DemiBuffer::from_slice(&data)?;
+        crate::ensure_eq!(buf.len(), data.len());
+        crate::ensure_eq!(&buf[..], &data[..]);
+
+        // Modifying the source should not affect the buffer.
+        data.iter_mut().for_each(|byte| *byte = 0xff);
+        crate::ensure_eq!(buf.iter().enumerate().all(|(i, byte)| *byte == i as u8), true);
+
+        // Slices up to the largest buffer size are accepted, longer ones are not.
+        let data: Vec<u8> = vec![0; u16::MAX as usize + 1];
+        crate::ensure_eq!(
+            DemiBuffer::from_slice(&data[..u16::MAX as usize])?.len(),
+            u16::MAX as usize
+        );
+        crate::ensure_eq!(DemiBuffer::from_slice(&data).is_err(), true);
+
+        Ok(())
+    }
+
     // Tests split_back (and also allocation from a slice).
     #[test]
     fn split_back() -> Result<()> {
@@ -1167,4 +1445,186 @@ mod tests {
 
         Ok(())
     }
+
+    // Tests truncate.
+    #[test]
+    fn truncate() -> Result<()> {
+        let data: Vec<u8> = (0..100).collect();
+        let mut buf: DemiBuffer = DemiBuffer::from_slice(&data)?;
+
+        // Truncating beyond the current length should fail, and leave the buffer untouched.
+        crate::ensure_eq!(buf.truncate(101).is_err(), true);
+        crate::ensure_eq!(buf.len(), 100);
+
+        buf.truncate(100)?;
+        crate::ensure_eq!(&buf[..], &data[..]);
+        buf.truncate(40)?;
+        crate::ensure_eq!(&buf[..], &data[..40]);
+        buf.truncate(0)?;
+        crate::ensure_eq!(buf.len(), 0);
+
+        Ok(())
+    }
+
+    // Tests split_off.
+    #[test]
+    fn split_off() -> Result<()> {
+        let data: Vec<u8> = (0..100).collect();
+        let mut buf: DemiBuffer = DemiBuffer::from_slice(&data)?;
+
+        let back: DemiBuffer = buf.split_off(40)?;
+        crate::ensure_eq!(&buf[..], &data[..40]);
+        crate::ensure_eq!(&back[..], &data[40..]);
+        crate::ensure_eq!(buf.len() + back.len(), data.len());
+
+        // Splitting beyond the current length should fail.
+        crate::ensure_eq!(buf.split_off(41).is_err(), true);
+
+        Ok(())
+    }
+
+    // Test hardware timestamp annotations.
+    #[test]
+    fn hw_timestamps() -> Result<()> {
+        // A new `DemiBuffer` carries no timestamps.
+        let mut buf: DemiBuffer = DemiBuffer::new(42);
+        crate::ensure_eq!(buf.hw_timestamp(), None);
+        crate::ensure_eq!(buf.tx_hw_timestamp_requested(), false);
+
+        // Annotate the `DemiBuffer` with a receive timestamp.  Clones should see it too.
+        buf.set_hw_timestamp(Some(1_000_000_007));
+        crate::ensure_eq!(buf.hw_timestamp(), Some(1_000_000_007));
+        let clone: DemiBuffer = buf.clone();
+        crate::ensure_eq!(clone.hw_timestamp(), Some(1_000_000_007));
+
+        // Clear the receive timestamp.
+        buf.set_hw_timestamp(None);
+        crate::ensure_eq!(buf.hw_timestamp(), None);
+        crate::ensure_eq!(clone.hw_timestamp(), Some(1_000_000_007));
+
+        // Request a transmit timestamp.
+        buf.request_tx_hw_timestamp();
+        crate::ensure_eq!(buf.tx_hw_timestamp_requested(), true);
+        crate::ensure_eq!(buf.hw_timestamp(), None);
+
+        Ok(())
+    }
+
+    // Test software timestamp annotations.
+    #[test]
+    fn sw_timestamps() -> Result<()> {
+        // A new `DemiBuffer` carries no timestamp.
+        let mut buf: DemiBuffer = DemiBuffer::new(42);
+        crate::ensure_eq!(buf.sw_timestamp(), None);
+
+        // Annotate the `DemiBuffer` with a receive timestamp.  Clones and splits should see it too.  Timestamps are kept
+        // relative to an origin that is set upon first use, so make sure that the one we check comes after it.
+        buf.set_sw_timestamp(Some(Instant::now()));
+        let now: Instant = Instant::now();
+        buf.set_sw_timestamp(Some(now));
+        crate::ensure_eq!(buf.sw_timestamp(), Some(now));
+        let clone: DemiBuffer = buf.clone();
+        crate::ensure_eq!(clone.sw_timestamp(), Some(now));
+        let front: DemiBuffer = buf.split_front(21)?;
+        crate::ensure_eq!(front.sw_timestamp(), Some(now));
+
+        // Clear the receive timestamp.
+        buf.set_sw_timestamp(None);
+        crate::ensure_eq!(buf.sw_timestamp(), None);
+        crate::ensure_eq!(clone.sw_timestamp(), Some(now));
+
+        Ok(())
+    }
+
+    // Test headroom reservation, prepend, and append.
+    #[test]
+    fn headroom() -> Result<()> {
+        // Create a `DemiBuffer` with a 16 byte data area, preceded by 8 bytes of headroom.
+        let mut buf: DemiBuffer = DemiBuffer::new_with_headroom(16, 8)?;
+        crate::ensure_eq!(buf.len(), 16);
+        crate::ensure_eq!(buf.headroom(), 8);
+        crate::ensure_eq!(buf.tailroom(), 0);
+        buf.as_bytes_mut().copy_from_slice(&[0xaa; 16]);
+
+        // Prepend a header in two steps.  The data should be left in place.
+        let data: *const u8 = buf.as_ptr();
+        buf.prepend(4)?.copy_from_slice(&[3, 4, 5, 6]);
+        buf.prepend(2)?.copy_from_slice(&[1, 2]);
+        crate::ensure_eq!(buf.len(), 22);
+        crate::ensure_eq!(buf.headroom(), 2);
+        crate::ensure_eq!(&buf[..6], &[1, 2, 3, 4, 5, 6]);
+        crate::ensure_eq!(&buf[6..], &[0xaa; 16]);
+        crate::ensure_eq!(unsafe { buf.as_ptr().add(6) }, data);
+
+        // Give back some bytes at the end and append them again.
+        buf.trim(4)?;
+        crate::ensure_eq!(buf.tailroom(), 4);
+        buf.append(4)?.copy_from_slice(&[7, 8, 9, 10]);
+        crate::ensure_eq!(buf.len(), 22);
+        crate::ensure_eq!(&buf[18..], &[7, 8, 9, 10]);
+
+        // Verify bad requests actually fail, and leave the buffer alone.
+        crate::ensure_eq!(buf.prepend(3).is_err(), true);
+        crate::ensure_eq!(buf.append(1).is_err(), true);
+        crate::ensure_eq!(buf.len(), 22);
+        crate::ensure_eq!(DemiBuffer::new_with_headroom(u16::MAX, 1).is_err(), true);
+
+        // A buffer without headroom can't have anything prepended.
+        let mut buf: DemiBuffer = DemiBuffer::new(42);
+        crate::ensure_eq!(buf.headroom(), 0);
+        crate::ensure_eq!(buf.prepend(1).is_err(), true);
+
+        // Neither can a zero-size buffer have anything appended.
+        let mut buf: DemiBuffer = DemiBuffer::new(0);
+        crate::ensure_eq!(buf.append(1).is_err(), true);
+        crate::ensure_eq!(buf.append(0)?.is_empty(), true);
+
+        Ok(())
+    }
+
+    // Test headroom of cloned (indirect) buffers.
+    #[test]
+    fn headroom_clone() -> Result<()> {
+        let mut buf: DemiBuffer = DemiBuffer::new_with_headroom(16, 8)?;
+        buf.as_bytes_mut().copy_from_slice(&[0xaa; 16]);
+
+        // Clones inherit the headroom, and prepending to them does not change the view of the original.
+        let mut clone: DemiBuffer = buf.clone();
+        crate::ensure_eq!(clone.headroom(), 8);
+        clone.prepend(8)?.copy_from_slice(&[0x55; 8]);
+        crate::ensure_eq!(clone.len(), 24);
+        crate::ensure_eq!(&clone[8..], &buf[..]);
+        crate::ensure_eq!(buf.len(), 16);
+        crate::ensure_eq!(buf.headroom(), 8);
+
+        // Split buffers gain headroom from the bytes in front of them.
+        let back: DemiBuffer = buf.split_back(10)?;
+        crate::ensure_eq!(back.headroom(), 18);
+        crate::ensure_eq!(buf.tailroom(), 6);
+
+        Ok(())
+    }
+
+    // Test access to the data through explicitly named views.
+    #[test]
+    fn as_bytes() -> Result<()> {
+        let mut buf: DemiBuffer = DemiBuffer::from_slice(&[1, 2, 3, 4])?;
+        crate::ensure_eq!(buf.as_bytes(), &[1, 2, 3, 4]);
+
+        // Writes through the mutable view are visible through the other views.
+        buf.as_bytes_mut()[1..3].copy_from_slice(&[5, 6]);
+        crate::ensure_eq!(buf.as_bytes(), &[1, 5, 6, 4]);
+        crate::ensure_eq!(&buf[..], buf.as_bytes());
+
+        // Views follow adjustments of the data.
+        buf.adjust(1)?;
+        buf.trim(1)?;
+        crate::ensure_eq!(buf.as_bytes(), &[5, 6]);
+
+        // Empty buffers have empty views.
+        let buf: DemiBuffer = DemiBuffer::from_slice(&[])?;
+        crate::ensure_eq!(buf.as_bytes().is_empty(), true);
+
+        Ok(())
+    }
 }
@@ -2,6 +2,11 @@
 // Licensed under the MIT license.
 
 mod demibuffer;
+mod sga_guard;
+mod sga_tracker;
+
+#[cfg(test)]
+pub mod counting_allocator;
 
 //==============================================================================
 // Imports
@@ -9,6 +14,7 @@ mod demibuffer;
 
 use crate::runtime::{
     fail::Fail,
+    limits,
     types::{
         demi_sgarray_t,
         demi_sgaseg_t,
@@ -26,7 +32,15 @@ use ::std::{
 // Exports
 //==============================================================================
 
-pub use self::demibuffer::*;
+pub(crate) use self::sga_tracker::{
+    check_sgarray,
+    track_sgarray,
+    untrack_sgarray,
+};
+pub use self::{
+    demibuffer::*,
+    sga_guard::SgaGuard,
+};
 
 //==============================================================================
 // Traits
@@ -47,12 +61,14 @@ pub trait MemoryRuntime {
         };
 
         // Create and return a new scatter-gather array (which inherits the DemiBuffer's reference).
-        Ok(demi_sgarray_t {
+        let sga: demi_sgarray_t = demi_sgarray_t {
             sga_buf: buf.into_raw().as_ptr() as *mut c_void,
             sga_numsegs: 1,
             sga_segs: [sga_seg],
             sga_addr: unsafe { mem::zeroed() },
-        })
+        };
+        track_sgarray(&sga);
+        Ok(sga)
     }
 
     /// Allocates a scatter-gather array.
@@ -64,8 +80,10 @@ pub trait MemoryRuntime {
             return Err(Fail::new(libc::EINVAL, "size too large for a single demi_sgaseg_t"));
         }
 
-        // First allocate the underlying DemiBuffer.
-        let buf: DemiBuffer = DemiBuffer::new(size as u16);
+        // First allocate the underlying DemiBuffer. Reserve some headroom in front of the data, as far as it fits, so
+        // that network stacks may prepend protocol headers to it without copying it.
+        let headroom: usize = limits::SGA_HEADROOM_SIZE.min(u16::MAX as usize - size);
+        let buf: DemiBuffer = DemiBuffer::new_with_headroom(size as u16, headroom as u16)?;
 
         // Create a scatter-gather segment to expose the DemiBuffer to the user.
         let data: *const u8 = buf.as_ptr();
@@ -75,12 +93,14 @@ pub trait MemoryRuntime {
         };
 
         // Create and return a new scatter-gather array (which inherits the DemiBuffer's reference).
-        Ok(demi_sgarray_t {
+        let sga: demi_sgarray_t = demi_sgarray_t {
             sga_buf: buf.into_raw().as_ptr() as *mut c_void,
             sga_numsegs: 1,
             sga_segs: [sga_seg],
             sga_addr: unsafe { mem::zeroed() },
-        })
+        };
+        track_sgarray(&sga);
+        Ok(sga)
     }
 
     /// Releases a scatter-gather array.
@@ -94,6 +114,7 @@ pub trait MemoryRuntime {
         if sga.sga_buf == ptr::null_mut() {
             return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid DemiBuffer token"));
         }
+        untrack_sgarray(&sga);
 
         // Convert back to a DemiBuffer and drop it.
         // Safety: The `NonNull::new_unchecked()` call is safe, as we verified `sga.sga_buf` is not null above.
@@ -117,6 +138,7 @@ pub trait MemoryRuntime {
         if sga.sga_buf == ptr::null_mut() {
             return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid DemiBuffer token"));
         }
+        check_sgarray(sga);
 
         // Convert back to a DemiBuffer.
         // Safety: The `NonNull::new_unchecked()` call is safe, as we verified `sga.sga_buf` is not null above.
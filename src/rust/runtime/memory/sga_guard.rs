@@ -0,0 +1,93 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    memory::{
+        untrack_sgarray,
+        DemiBuffer,
+    },
+    types::demi_sgarray_t,
+};
+use ::std::{
+    mem,
+    ops::Deref,
+    ptr::NonNull,
+    slice,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Scoped owner of a scatter-gather array, which releases the array once dropped. This spares the holder from calling
+/// `free_sgarray()` on every path out of the scope that uses the array, including early returns on errors.
+pub struct SgaGuard {
+    sga: demi_sgarray_t,
+}
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+impl SgaGuard {
+    /// Takes ownership of a scatter-gather array that was handed out by a LibOS (e.g. in the result of a pop).
+    pub fn new(sga: demi_sgarray_t) -> Self {
+        Self { sga }
+    }
+
+    /// Returns the data in the first segment of the scatter-gather array.
+    pub fn as_bytes(&self) -> &[u8] {
+        let ptr: *const u8 = self.sga.sga_segs[0].sgaseg_buf as *const u8;
+        let len: usize = self.sga.sga_segs[0].sgaseg_len as usize;
+        if ptr.is_null() || len == 0 {
+            return &[];
+        }
+        // Safety: The segment describes memory of the underlying DemiBuffer, which stays alive while we hold the
+        // scatter-gather array.
+        unsafe { slice::from_raw_parts(ptr, len) }
+    }
+
+    /// Gives up ownership of the scatter-gather array, so that it is not released when the guard goes away. The caller
+    /// becomes responsible for releasing it.
+    pub fn into_inner(self) -> demi_sgarray_t {
+        let sga: demi_sgarray_t = self.sga;
+        mem::forget(self);
+        sga
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Deref for SgaGuard {
+    type Target = demi_sgarray_t;
+
+    fn deref(&self) -> &Self::Target {
+        &self.sga
+    }
+}
+
+impl Drop for SgaGuard {
+    fn drop(&mut self) {
+        // TODO: Release all segments once we support scatter-gather arrays with multiple segments.
+        if self.sga.sga_numsegs != 1 {
+            warn!("drop(): leaking demi_sgarray_t with invalid segment count");
+            return;
+        }
+
+        match NonNull::new(self.sga.sga_buf as *mut u8) {
+            // Safety: The `sga_buf` field contains the DemiBuffer token that the LibOS handed out along with the
+            // scatter-gather array, and we own the only reference to it.
+            Some(token) => {
+                untrack_sgarray(&self.sga);
+                drop(unsafe { DemiBuffer::from_raw(token) })
+            },
+            None => warn!("drop(): demi_sgarray_t has invalid DemiBuffer token"),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::types::demi_sgarray_t;
+#[cfg(debug_assertions)]
+use ::std::{
+    cell::RefCell,
+    collections::HashSet,
+};
+
+//======================================================================================================================
+// Thread Locals
+//======================================================================================================================
+
+// Scatter-gather arrays are tracked by the token of their underlying buffer. Tracking is per thread, as a LibOS and the
+// scatter-gather arrays that it hands out are confined to the thread that runs it.
+#[cfg(debug_assertions)]
+thread_local! {
+    /// Scatter-gather arrays that were handed out and not released yet.
+    static LIVE_SGARRAYS: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+    /// Scatter-gather arrays that were released, so that releasing one twice is told apart from releasing a bogus one.
+    static RELEASED_SGARRAYS: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Records that a scatter-gather array was handed out to the application. This is a no-op in release builds.
+#[cfg(debug_assertions)]
+pub fn track_sgarray(sga: &demi_sgarray_t) {
+    let token: usize = sga.sga_buf as usize;
+    let _ = RELEASED_SGARRAYS.try_with(|released| released.borrow_mut().remove(&token));
+    let _ = LIVE_SGARRAYS.try_with(|live| live.borrow_mut().insert(token));
+}
+
+/// Records that a scatter-gather array is about to be released. Panics if it was released already, or if it was never
+/// handed out, before its underlying buffer is touched. This is a no-op in release builds.
+#[cfg(debug_assertions)]
+pub fn untrack_sgarray(sga: &demi_sgarray_t) {
+    let token: usize = sga.sga_buf as usize;
+    let _ = LIVE_SGARRAYS.try_with(|live| {
+        let was_live: bool = live.borrow_mut().remove(&token);
+        if !was_live {
+            if RELEASED_SGARRAYS.with(|released| released.borrow().contains(&token)) {
+                panic!("double free of demi_sgarray_t (sga_buf={:#x})", token);
+            }
+            panic!(
+                "free of demi_sgarray_t that was not allocated by Demikernel (sga_buf={:#x})",
+                token
+            );
+        }
+        RELEASED_SGARRAYS.with(|released| released.borrow_mut().insert(token));
+    });
+}
+
+/// Panics if a scatter-gather array was released already, before its underlying buffer is touched. This is a no-op in
+/// release builds.
+#[cfg(debug_assertions)]
+pub fn check_sgarray(sga: &demi_sgarray_t) {
+    let token: usize = sga.sga_buf as usize;
+    let _ = LIVE_SGARRAYS.try_with(|live| {
+        if !live.borrow().contains(&token) && RELEASED_SGARRAYS.with(|released| released.borrow().contains(&token)) {
+            panic!("use of demi_sgarray_t after free (sga_buf={:#x})", token);
+        }
+    });
+}
+
+#[cfg(not(debug_assertions))]
+#[inline(always)]
+pub fn track_sgarray(_sga: &demi_sgarray_t) {}
+
+#[cfg(not(debug_assertions))]
+#[inline(always)]
+pub fn untrack_sgarray(_sga: &demi_sgarray_t) {}
+
+#[cfg(not(debug_assertions))]
+#[inline(always)]
+pub fn check_sgarray(_sga: &demi_sgarray_t) {}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use crate::runtime::{
+        memory::MemoryRuntime,
+        types::demi_sgarray_t,
+    };
+    use ::anyhow::Result;
+
+    /// Memory runtime with the default scatter-gather array management.
+    struct TestRuntime;
+
+    impl MemoryRuntime for TestRuntime {}
+
+    /// Tests that scatter-gather arrays may be released once, and may be used up to that point.
+    #[test]
+    fn alloc_clone_free() -> Result<()> {
+        let rt: TestRuntime = TestRuntime;
+        let sga: demi_sgarray_t = rt.alloc_sgarray(64)?;
+        crate::ensure_eq!(rt.clone_sgarray(&sga)?.len(), 64);
+        rt.free_sgarray(sga)?;
+        Ok(())
+    }
+
+    /// Tests that releasing a scatter-gather array twice panics.
+    #[test]
+    #[should_panic(expected = "double free of demi_sgarray_t")]
+    fn double_free() {
+        let rt: TestRuntime = TestRuntime;
+        let sga: demi_sgarray_t = rt.alloc_sgarray(64).unwrap();
+        rt.free_sgarray(sga).unwrap();
+        let _ = rt.free_sgarray(sga);
+    }
+
+    /// Tests that using a scatter-gather array after releasing it panics.
+    #[test]
+    #[should_panic(expected = "use of demi_sgarray_t after free")]
+    fn use_after_free() {
+        let rt: TestRuntime = TestRuntime;
+        let sga: demi_sgarray_t = rt.alloc_sgarray(64).unwrap();
+        rt.free_sgarray(sga).unwrap();
+        let _ = rt.clone_sgarray(&sga);
+    }
+
+    /// Tests that releasing a scatter-gather array that was never handed out panics.
+    #[test]
+    #[should_panic(expected = "free of demi_sgarray_t that was not allocated by Demikernel")]
+    fn free_unknown() {
+        let rt: TestRuntime = TestRuntime;
+        let mut sga: demi_sgarray_t = rt.alloc_sgarray(64).unwrap();
+        let token: *mut libc::c_void = sga.sga_buf;
+        sga.sga_buf = (token as usize + 1) as *mut libc::c_void;
+        let _ = rt.free_sgarray(sga);
+    }
+}
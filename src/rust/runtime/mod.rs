@@ -16,12 +16,16 @@ pub mod types;
 pub mod watched;
 pub use queue::{
     BackgroundTask,
+    ConnectionInfo,
+    ConnectionState,
     Operation,
     OperationResult,
     OperationTask,
     QDesc,
     QToken,
     QType,
+    QueueInfo,
+    QueueState,
 };
 
 #[cfg(feature = "liburing")]
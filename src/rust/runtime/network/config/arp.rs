@@ -31,6 +31,14 @@ pub struct ArpConfig {
     disable_arp: bool,
 }
 
+/// ARP Configuration Builder
+///
+/// Builds an [ArpConfig] through named setters. Settings that are not given keep their default values.
+#[derive(Clone, Debug, Default)]
+pub struct ArpConfigBuilder {
+    config: ArpConfig,
+}
+
 //==============================================================================
 // Associate Functions
 //==============================================================================
@@ -66,6 +74,11 @@ impl ArpConfig {
         config
     }
 
+    /// Creates a builder for an ARP Configuration Descriptor.
+    pub fn builder() -> ArpConfigBuilder {
+        ArpConfigBuilder::default()
+    }
+
     /// Gets the time to live for entries of the ARP Cache in the target [ArpConfig].
     pub fn get_cache_ttl(&self) -> Duration {
         self.cache_ttl
@@ -117,6 +130,44 @@ impl ArpConfig {
     }
 }
 
+/// Associate Functions for ARP Configuration Builder
+impl ArpConfigBuilder {
+    /// Sets the time to live for entries of the ARP Cache.
+    pub fn cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.config.set_cache_ttl(cache_ttl);
+        self
+    }
+
+    /// Sets the timeout for ARP requests.
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.config.set_request_timeout(request_timeout);
+        self
+    }
+
+    /// Sets how many times an ARP request is retried.
+    pub fn max_retries(mut self, retry_count: usize) -> Self {
+        self.config.set_retry_count(retry_count);
+        self
+    }
+
+    /// Sets the static entries that the ARP Cache starts with.
+    pub fn static_table(mut self, initial_values: HashMap<Ipv4Addr, MacAddress>) -> Self {
+        self.config.set_initial_values(initial_values);
+        self
+    }
+
+    /// Sets whether ARP is disabled, in which case only static entries resolve.
+    pub fn disable(mut self, disable_arp: bool) -> Self {
+        self.config.set_disable_arp(disable_arp);
+        self
+    }
+
+    /// Builds the ARP Configuration Descriptor.
+    pub fn build(self) -> ArpConfig {
+        self.config
+    }
+}
+
 //==============================================================================
 // Trait Implementations
 //==============================================================================
@@ -141,10 +192,14 @@ impl Default for ArpConfig {
 
 #[cfg(test)]
 mod tests {
-    use crate::runtime::network::config::ArpConfig;
+    use crate::runtime::network::{
+        config::ArpConfig,
+        types::MacAddress,
+    };
     use ::anyhow::Result;
     use ::std::{
         collections::HashMap,
+        net::Ipv4Addr,
         time::Duration,
     };
 
@@ -160,4 +215,34 @@ mod tests {
 
         Ok(())
     }
+
+    /// Tests instantiation for [ArpConfig] through its builder.
+    #[test]
+    fn test_arp_config_builder() -> Result<()> {
+        let mut static_table: HashMap<Ipv4Addr, MacAddress> = HashMap::new();
+        static_table.insert(
+            Ipv4Addr::new(192, 168, 1, 1),
+            MacAddress::new([0x12, 0x23, 0x45, 0x67, 0x89, 0xab]),
+        );
+
+        let config: ArpConfig = ArpConfig::builder()
+            .cache_ttl(Duration::from_secs(600))
+            .request_timeout(Duration::from_secs(1))
+            .max_retries(2)
+            .static_table(static_table.clone())
+            .disable(true)
+            .build();
+        crate::ensure_eq!(config.get_cache_ttl(), Duration::from_secs(600));
+        crate::ensure_eq!(config.get_request_timeout(), Duration::from_secs(1));
+        crate::ensure_eq!(config.get_retry_count(), 2);
+        crate::ensure_eq!(config.get_initial_values(), &static_table);
+        crate::ensure_eq!(config.get_disable_arp(), true);
+
+        // Settings that are not given keep their defaults.
+        let config: ArpConfig = ArpConfig::builder().max_retries(7).build();
+        crate::ensure_eq!(config.get_cache_ttl(), ArpConfig::default().get_cache_ttl());
+        crate::ensure_eq!(config.get_retry_count(), 7);
+
+        Ok(())
+    }
 }
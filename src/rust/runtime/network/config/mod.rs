@@ -10,7 +10,15 @@ mod udp;
 //==============================================================================
 
 pub use self::{
-    arp::ArpConfig,
-    tcp::TcpConfig,
+    arp::{
+        ArpConfig,
+        ArpConfigBuilder,
+    },
+    tcp::{
+        BacklogPolicy,
+        ReceiveBufferMode,
+        TcpConfig,
+        TcpConfigBuilder,
+    },
     udp::UdpConfig,
 };
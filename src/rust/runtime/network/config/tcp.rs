@@ -7,7 +7,9 @@
 
 use crate::runtime::network::consts::{
     DEFAULT_MSS,
+    MAX_GRO_SIZE,
     MAX_MSS,
+    MAX_WINDOW_SIZE,
     MIN_MSS,
 };
 use ::std::time::Duration;
@@ -16,6 +18,24 @@ use ::std::time::Duration;
 // Structures
 //==============================================================================
 
+/// What to Do With Incoming Connections When the Backlog of a Listening Socket is Full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BacklogPolicy {
+    /// Silently drop the SYN, so that the client retries later.
+    Drop,
+    /// Reply with a RST, so that the client fails with `ECONNREFUSED`.
+    Rst,
+}
+
+/// How to Reassemble Data That Arrives Out of Order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiveBufferMode {
+    /// Keep out-of-order segments in a sorted list.
+    Linear,
+    /// Copy out-of-order data into a circular buffer that covers the receive window.
+    Ring,
+}
+
 /// TCP Configuration Descriptor
 #[derive(Clone, Debug)]
 pub struct TcpConfig {
@@ -31,10 +51,62 @@ pub struct TcpConfig {
     window_scale: u8,
     /// Timeout for Delayed ACKs
     ack_delay_timeout: Duration,
+    /// How Long an ACK That is Due Waits for Outgoing Data to Ride On
+    ack_hold_timeout: Duration,
+    /// How to Reassemble Data That Arrives Out of Order
+    receive_buffer_mode: ReceiveBufferMode,
+    /// How Long Sent Data May Remain Unacknowledged Before the Connection is Aborted (RFC 5482)
+    user_timeout: Option<Duration>,
+    /// Negotiate Explicit Congestion Notification (RFC 3168)? Connections that do run Cubic congestion control.
+    ecn_enabled: bool,
     /// Offload Checksum to Hardware When Receiving?
     rx_checksum_offload: bool,
     /// Offload Checksum to Hardware When Sending?
     tx_checksum_offload: bool,
+    /// Maximum Number of Bytes Buffered in the Receive Queue of a Connection
+    max_receive_buffer_bytes: usize,
+    /// Maximum Number of Bytes Buffered in the Receive Queues of All Connections
+    total_rx_buffer_cap: usize,
+    /// Auto-Tune the Receive Window?
+    receive_window_auto_tuning: bool,
+    /// Upper Bound for the Auto-Tuned Receive Window Size
+    max_receive_window_size: u32,
+    /// Lower Bound for the Interval Between Zero-Window Probes
+    persist_timeout_min: Duration,
+    /// Upper Bound for the Interval Between Zero-Window Probes
+    persist_timeout_max: Duration,
+    /// What to Do With Incoming Connections When the Backlog is Full
+    backlog_full_policy: BacklogPolicy,
+    /// Maximum Segment Lifetime (Connections Linger in TIME-WAIT for Twice This Long)
+    msl: Duration,
+    /// Negotiate Timestamps (RFC 7323)?
+    timestamps_enabled: bool,
+    /// Reuse Connections in TIME-WAIT for New Outgoing Connections When Ephemeral Ports Run Out?
+    time_wait_recycling: bool,
+    /// Probe for Smaller Segments When Full-Sized Ones Go Unacknowledged (RFC 2923)?
+    pmtud_probe_enabled: bool,
+    /// Lower Bound for the Maximum Segment Size When Probing for Black Holes
+    pmtud_min_mss: usize,
+    /// Send Keep-Alive Probes That Refresh the Mappings of NAT Boxes on Idle Connections?
+    nat_traversal_keepalive: bool,
+    /// Interval Between Keep-Alive Probes When Traversing NAT Boxes
+    keepalive_nat_interval: Duration,
+    /// Maximum Number of Payload Bytes in a Segment Merged by Receive Coalescing (Zero Disables Coalescing)
+    gro_max_size: usize,
+    /// Maximum Number of Out-of-Order Segments Buffered per Connection
+    max_out_of_order_segments: usize,
+    /// Time for Which Telemetry on a Remote Host Is Kept After Its Last Update
+    peer_telemetry_retention: Duration,
+    /// Maximum Number of Pending Connection Events (Zero Disables the Event Queue)
+    conn_event_queue_capacity: usize,
+}
+
+/// TCP Configuration Builder
+///
+/// Builds a [TcpConfig] through named setters. Settings that are not given keep their default values.
+#[derive(Clone, Debug, Default)]
+pub struct TcpConfigBuilder {
+    config: TcpConfig,
 }
 
 //==============================================================================
@@ -43,45 +115,9 @@ pub struct TcpConfig {
 
 /// Associate Functions for TCP Configuration Descriptor
 impl TcpConfig {
-    /// Creates a TCP Configuration Descriptor.
-    pub fn new(
-        advertised_mss: Option<usize>,
-        handshake_retries: Option<usize>,
-        handshake_timeout: Option<Duration>,
-        receive_window_size: Option<u16>,
-        window_scale: Option<u8>,
-        ack_delay_timeout: Option<Duration>,
-        rx_checksum_offload: Option<bool>,
-        tx_checksum_offload: Option<bool>,
-    ) -> Self {
-        let mut options = Self::default();
-
-        if let Some(value) = advertised_mss {
-            options = options.set_advertised_mss(value);
-        }
-        if let Some(value) = handshake_retries {
-            options = options.set_handshake_retries(value);
-        }
-        if let Some(value) = handshake_timeout {
-            options = options.set_handshake_timeout(value);
-        }
-        if let Some(value) = receive_window_size {
-            options = options.set_receive_window_size(value);
-        }
-        if let Some(value) = window_scale {
-            options = options.set_window_scale(value);
-        }
-        if let Some(value) = ack_delay_timeout {
-            options = options.set_ack_delay_timeout(value);
-        }
-        if let Some(value) = rx_checksum_offload {
-            options.rx_checksum_offload = value;
-        }
-        if let Some(value) = tx_checksum_offload {
-            options.tx_checksum_offload = value;
-        }
-
-        options
+    /// Creates a builder for a TCP Configuration Descriptor.
+    pub fn builder() -> TcpConfigBuilder {
+        TcpConfigBuilder::default()
     }
 
     /// Gets the advertised maximum segment size in the target [TcpConfig].
@@ -114,6 +150,11 @@ impl TcpConfig {
         self.ack_delay_timeout
     }
 
+    /// Gets the acknowledgement hold timeout in the target [TcpConfig]. A zero timeout disables ACK holding.
+    pub fn get_ack_hold_timeout(&self) -> Duration {
+        self.ack_hold_timeout
+    }
+
     /// Gets the TX hardware checksum offload option in the target [TcpConfig].
     pub fn get_tx_checksum_offload(&self) -> bool {
         self.tx_checksum_offload
@@ -124,6 +165,138 @@ impl TcpConfig {
         self.rx_checksum_offload
     }
 
+    /// Gets the maximum number of bytes buffered in the receive queue of a connection in the target [TcpConfig].
+    pub fn get_max_receive_buffer_bytes(&self) -> usize {
+        self.max_receive_buffer_bytes
+    }
+
+    /// Gets the maximum number of bytes buffered in the receive queues of all connections in the target [TcpConfig].
+    pub fn get_total_rx_buffer_cap(&self) -> usize {
+        self.total_rx_buffer_cap
+    }
+
+    /// Gets the receive window auto-tuning option in the target [TcpConfig].
+    pub fn get_receive_window_auto_tuning(&self) -> bool {
+        self.receive_window_auto_tuning
+    }
+
+    /// Gets the upper bound for the auto-tuned receive window size in the target [TcpConfig].
+    pub fn get_max_receive_window_size(&self) -> u32 {
+        self.max_receive_window_size
+    }
+
+    /// Gets the lower bound for the interval between zero-window probes in the target [TcpConfig].
+    pub fn get_persist_timeout_min(&self) -> Duration {
+        self.persist_timeout_min
+    }
+
+    /// Gets the upper bound for the interval between zero-window probes in the target [TcpConfig].
+    pub fn get_persist_timeout_max(&self) -> Duration {
+        self.persist_timeout_max
+    }
+
+    /// Gets what to do with incoming connections when the backlog is full in the target [TcpConfig].
+    pub fn get_backlog_full_policy(&self) -> BacklogPolicy {
+        self.backlog_full_policy
+    }
+
+    /// Gets how data that arrives out of order is reassembled in the target [TcpConfig].
+    pub fn get_receive_buffer_mode(&self) -> ReceiveBufferMode {
+        self.receive_buffer_mode
+    }
+
+    /// Gets the user timeout in the target [TcpConfig]. Connections without a user timeout are never aborted because
+    /// of unacknowledged data.
+    pub fn get_user_timeout(&self) -> Option<Duration> {
+        self.user_timeout
+    }
+
+    /// Gets the explicit congestion notification option in the target [TcpConfig]. Connections only use ECN if both
+    /// ends agree on it during the handshake. Those that do run Cubic congestion control instead of none at all, so
+    /// that they have a congestion window to halve when their peer echoes a congestion signal.
+    pub fn get_ecn_enabled(&self) -> bool {
+        self.ecn_enabled
+    }
+
+    /// Gets the maximum segment lifetime in the target [TcpConfig]. Connections stay in TIME-WAIT for twice as long.
+    pub fn get_msl(&self) -> Duration {
+        self.msl
+    }
+
+    /// Gets the timestamps option in the target [TcpConfig]. Connections only use timestamps if both ends agree on
+    /// them during the handshake.
+    pub fn get_timestamps_enabled(&self) -> bool {
+        self.timestamps_enabled
+    }
+
+    /// Gets the TIME-WAIT recycling option in the target [TcpConfig]. Only connections that use timestamps are ever
+    /// recycled.
+    pub fn get_time_wait_recycling(&self) -> bool {
+        self.time_wait_recycling
+    }
+
+    /// Gets the black hole detection option in the target [TcpConfig]. When enabled, connections halve their maximum
+    /// segment size whenever a full-sized segment goes unacknowledged until the retransmission timer expires.
+    pub fn get_pmtud_probe_enabled(&self) -> bool {
+        self.pmtud_probe_enabled
+    }
+
+    /// Gets the lower bound for the maximum segment size when probing for black holes in the target [TcpConfig].
+    pub fn get_pmtud_min_mss(&self) -> usize {
+        self.pmtud_min_mss
+    }
+
+    /// Gets the NAT traversal keep-alive option in the target [TcpConfig]. When enabled, connections that sit idle for
+    /// [Self::get_keepalive_nat_interval] send a probe that carries a single null byte. Unlike RFC 1122 keep-alives,
+    /// which are meant to detect dead peers after hours of idleness and carry no data, these probes are meant to
+    /// refresh the mappings of NAT boxes on the path, some of which only do so for segments that carry data.
+    pub fn get_nat_traversal_keepalive(&self) -> bool {
+        self.nat_traversal_keepalive
+    }
+
+    /// Gets the interval between keep-alive probes when traversing NAT boxes in the target [TcpConfig].
+    pub fn get_keepalive_nat_interval(&self) -> Duration {
+        self.keepalive_nat_interval
+    }
+
+    /// Gets the maximum number of payload bytes in a segment merged by receive coalescing in the target [TcpConfig].
+    /// When non-zero, consecutive segments of a connection that arrive in the same batch are merged into a single one
+    /// before the stack processes them, as long as the merged payload stays within this size.
+    pub fn get_gro_max_size(&self) -> usize {
+        self.gro_max_size
+    }
+
+    /// Gets the maximum number of out-of-order segments buffered per connection in the target [TcpConfig]. Once a
+    /// connection buffers this many, further out-of-order segments are dropped until the hole before them is filled.
+    pub fn get_max_out_of_order_segments(&self) -> usize {
+        self.max_out_of_order_segments
+    }
+
+    /// Gets the time for which telemetry on a remote host is kept after its last update in the target [TcpConfig].
+    /// Remote hosts that we have connections to are kept regardless.
+    pub fn get_peer_telemetry_retention(&self) -> Duration {
+        self.peer_telemetry_retention
+    }
+
+    /// Gets the maximum number of pending connection events in the target [TcpConfig]. Events that arrive while the
+    /// queue is full are dropped, and the application learns about it when it drains the queue.
+    pub fn get_conn_event_queue_capacity(&self) -> usize {
+        self.conn_event_queue_capacity
+    }
+
+    /// Clamps the advertised maximum segment size in the target [TcpConfig] to `max_mss`, so that full-sized segments
+    /// fit in the MTU of the NIC. The advertised maximum segment size never goes below the minimum one.
+    pub fn clamp_advertised_mss(&mut self, max_mss: usize) {
+        let max_mss: usize = max_mss.clamp(MIN_MSS, MAX_MSS);
+        if self.advertised_mss > max_mss {
+            warn!(
+                "clamp_advertised_mss(): advertised MSS does not fit in the MTU, clamping it (mss={:?}, max_mss={:?})",
+                self.advertised_mss, max_mss
+            );
+            self.advertised_mss = max_mss;
+        }
+    }
+
     /// Sets the advertised maximum segment size in the target [TcpConfig].
     fn set_advertised_mss(mut self, value: usize) -> Self {
         assert!(value >= MIN_MSS);
@@ -165,6 +338,302 @@ impl TcpConfig {
         self.ack_delay_timeout = value;
         self
     }
+
+    /// Sets the acknowledgement hold timeout in the target [TcpConfig].
+    fn set_ack_hold_timeout(mut self, value: Duration) -> Self {
+        assert!(value <= Duration::from_millis(1));
+        self.ack_hold_timeout = value;
+        self
+    }
+
+    /// Sets the maximum number of bytes buffered in the receive queue of a connection in the target [TcpConfig].
+    fn set_max_receive_buffer_bytes(mut self, value: usize) -> Self {
+        assert!(value > 0);
+        self.max_receive_buffer_bytes = value;
+        self
+    }
+
+    /// Sets the maximum number of bytes buffered in the receive queues of all connections in the target [TcpConfig].
+    fn set_total_rx_buffer_cap(mut self, value: usize) -> Self {
+        assert!(value > 0);
+        self.total_rx_buffer_cap = value;
+        self
+    }
+
+    /// Sets the upper bound for the auto-tuned receive window size in the target [TcpConfig].
+    fn set_max_receive_window_size(mut self, value: u32) -> Self {
+        assert!(value > 0);
+        assert!(value <= MAX_WINDOW_SIZE);
+        self.max_receive_window_size = value;
+        self
+    }
+
+    /// Sets the lower bound for the interval between zero-window probes in the target [TcpConfig].
+    fn set_persist_timeout_min(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.persist_timeout_min = value;
+        self
+    }
+
+    /// Sets the upper bound for the interval between zero-window probes in the target [TcpConfig].
+    fn set_persist_timeout_max(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.persist_timeout_max = value;
+        self
+    }
+
+    /// Sets what to do with incoming connections when the backlog is full in the target [TcpConfig].
+    fn set_backlog_full_policy(mut self, value: BacklogPolicy) -> Self {
+        self.backlog_full_policy = value;
+        self
+    }
+
+    /// Sets the user timeout in the target [TcpConfig].
+    fn set_user_timeout(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.user_timeout = Some(value);
+        self
+    }
+
+    /// Sets the maximum segment lifetime in the target [TcpConfig].
+    fn set_msl(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.msl = value;
+        self
+    }
+
+    /// Sets the lower bound for the maximum segment size when probing for black holes in the target [TcpConfig].
+    fn set_pmtud_min_mss(mut self, value: usize) -> Self {
+        assert!(value >= MIN_MSS);
+        assert!(value <= MAX_MSS);
+        self.pmtud_min_mss = value;
+        self
+    }
+
+    /// Sets the interval between keep-alive probes when traversing NAT boxes in the target [TcpConfig].
+    fn set_keepalive_nat_interval(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.keepalive_nat_interval = value;
+        self
+    }
+
+    /// Sets the maximum number of payload bytes in a segment merged by receive coalescing in the target [TcpConfig].
+    fn set_gro_max_size(mut self, value: usize) -> Self {
+        assert!(value <= MAX_GRO_SIZE);
+        self.gro_max_size = value;
+        self
+    }
+
+    /// Sets the maximum number of out-of-order segments buffered per connection in the target [TcpConfig].
+    fn set_max_out_of_order_segments(mut self, value: usize) -> Self {
+        assert!(value > 0);
+        self.max_out_of_order_segments = value;
+        self
+    }
+
+    /// Sets the time for which telemetry on a remote host is kept after its last update in the target [TcpConfig].
+    fn set_peer_telemetry_retention(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.peer_telemetry_retention = value;
+        self
+    }
+
+    /// Sets the maximum number of pending connection events in the target [TcpConfig]. A non-zero capacity leaves room
+    /// for at least one event besides the overflow marker.
+    fn set_conn_event_queue_capacity(mut self, value: usize) -> Self {
+        assert!(value == 0 || value >= 2);
+        self.conn_event_queue_capacity = value;
+        self
+    }
+}
+
+/// Associate Functions for TCP Configuration Builder
+impl TcpConfigBuilder {
+    /// Sets the advertised maximum segment size.
+    pub fn advertised_mss(mut self, value: usize) -> Self {
+        self.config = self.config.set_advertised_mss(value);
+        self
+    }
+
+    /// Sets the number of TCP handshake retries.
+    pub fn handshake_retries(mut self, value: usize) -> Self {
+        self.config = self.config.set_handshake_retries(value);
+        self
+    }
+
+    /// Sets the TCP handshake timeout.
+    pub fn handshake_timeout(mut self, value: Duration) -> Self {
+        self.config = self.config.set_handshake_timeout(value);
+        self
+    }
+
+    /// Sets the receive window size.
+    pub fn receive_window_size(mut self, value: u16) -> Self {
+        self.config = self.config.set_receive_window_size(value);
+        self
+    }
+
+    /// Sets the window scale.
+    pub fn window_scale(mut self, value: u8) -> Self {
+        self.config = self.config.set_window_scale(value);
+        self
+    }
+
+    /// Sets the acknowledgement delay timeout.
+    pub fn ack_delay_timeout(mut self, value: Duration) -> Self {
+        self.config = self.config.set_ack_delay_timeout(value);
+        self
+    }
+
+    /// Sets how long an ACK that is due waits for outgoing data to ride on. A zero timeout disables ACK holding.
+    pub fn ack_hold_timeout(mut self, value: Duration) -> Self {
+        self.config = self.config.set_ack_hold_timeout(value);
+        self
+    }
+
+    /// Sets how data that arrives out of order is reassembled.
+    pub fn receive_buffer_mode(mut self, value: ReceiveBufferMode) -> Self {
+        self.config.receive_buffer_mode = value;
+        self
+    }
+
+    /// Sets how long sent data may remain unacknowledged before the connection is aborted.
+    pub fn user_timeout(mut self, value: Duration) -> Self {
+        self.config = self.config.set_user_timeout(value);
+        self
+    }
+
+    /// Sets whether to negotiate explicit congestion notification.
+    pub fn ecn_enabled(mut self, value: bool) -> Self {
+        self.config.ecn_enabled = value;
+        self
+    }
+
+    /// Sets whether to offload the checksum to hardware when receiving.
+    pub fn rx_checksum_offload(mut self, value: bool) -> Self {
+        self.config.rx_checksum_offload = value;
+        self
+    }
+
+    /// Sets whether to offload the checksum to hardware when sending.
+    pub fn tx_checksum_offload(mut self, value: bool) -> Self {
+        self.config.tx_checksum_offload = value;
+        self
+    }
+
+    /// Sets the maximum number of bytes buffered in the receive queue of a connection.
+    pub fn max_receive_buffer_bytes(mut self, value: usize) -> Self {
+        self.config = self.config.set_max_receive_buffer_bytes(value);
+        self
+    }
+
+    /// Sets the maximum number of bytes buffered in the receive queues of all connections.
+    pub fn total_rx_buffer_cap(mut self, value: usize) -> Self {
+        self.config = self.config.set_total_rx_buffer_cap(value);
+        self
+    }
+
+    /// Sets whether to auto-tune the receive window.
+    pub fn receive_window_auto_tuning(mut self, value: bool) -> Self {
+        self.config.receive_window_auto_tuning = value;
+        self
+    }
+
+    /// Sets the upper bound for the auto-tuned receive window size.
+    pub fn max_receive_window_size(mut self, value: u32) -> Self {
+        self.config = self.config.set_max_receive_window_size(value);
+        self
+    }
+
+    /// Sets the lower bound for the interval between zero-window probes.
+    pub fn persist_timeout_min(mut self, value: Duration) -> Self {
+        self.config = self.config.set_persist_timeout_min(value);
+        self
+    }
+
+    /// Sets the upper bound for the interval between zero-window probes.
+    pub fn persist_timeout_max(mut self, value: Duration) -> Self {
+        self.config = self.config.set_persist_timeout_max(value);
+        self
+    }
+
+    /// Sets what to do with incoming connections when the backlog is full.
+    pub fn backlog_full_policy(mut self, value: BacklogPolicy) -> Self {
+        self.config = self.config.set_backlog_full_policy(value);
+        self
+    }
+
+    /// Sets the maximum segment lifetime.
+    pub fn msl(mut self, value: Duration) -> Self {
+        self.config = self.config.set_msl(value);
+        self
+    }
+
+    /// Sets whether to negotiate timestamps.
+    pub fn timestamps_enabled(mut self, value: bool) -> Self {
+        self.config.timestamps_enabled = value;
+        self
+    }
+
+    /// Sets whether to reuse connections in TIME-WAIT for new outgoing connections when ephemeral ports run out.
+    pub fn time_wait_recycling(mut self, value: bool) -> Self {
+        self.config.time_wait_recycling = value;
+        self
+    }
+
+    /// Sets whether to probe for smaller segments when full-sized ones go unacknowledged.
+    pub fn pmtud_probe_enabled(mut self, value: bool) -> Self {
+        self.config.pmtud_probe_enabled = value;
+        self
+    }
+
+    /// Sets the lower bound for the maximum segment size when probing for black holes.
+    pub fn pmtud_min_mss(mut self, value: usize) -> Self {
+        self.config = self.config.set_pmtud_min_mss(value);
+        self
+    }
+
+    /// Sets whether to send keep-alive probes that refresh the mappings of NAT boxes on idle connections.
+    pub fn nat_traversal_keepalive(mut self, value: bool) -> Self {
+        self.config.nat_traversal_keepalive = value;
+        self
+    }
+
+    /// Sets the interval between keep-alive probes when traversing NAT boxes.
+    pub fn keepalive_nat_interval(mut self, value: Duration) -> Self {
+        self.config = self.config.set_keepalive_nat_interval(value);
+        self
+    }
+
+    /// Sets the maximum number of payload bytes in a segment merged by receive coalescing. Zero disables coalescing.
+    pub fn gro_max_size(mut self, value: usize) -> Self {
+        self.config = self.config.set_gro_max_size(value);
+        self
+    }
+
+    /// Sets the maximum number of out-of-order segments buffered per connection.
+    pub fn max_out_of_order_segments(mut self, value: usize) -> Self {
+        self.config = self.config.set_max_out_of_order_segments(value);
+        self
+    }
+
+    /// Sets the time for which telemetry on a remote host is kept after its last update.
+    pub fn peer_telemetry_retention(mut self, value: Duration) -> Self {
+        self.config = self.config.set_peer_telemetry_retention(value);
+        self
+    }
+
+    /// Sets the maximum number of pending connection events. Zero disables the event queue.
+    pub fn conn_event_queue_capacity(mut self, value: usize) -> Self {
+        self.config = self.config.set_conn_event_queue_capacity(value);
+        self
+    }
+
+    /// Builds the TCP Configuration Descriptor.
+    pub fn build(self) -> TcpConfig {
+        assert!(self.config.persist_timeout_min <= self.config.persist_timeout_max);
+        self.config
+    }
 }
 
 //==============================================================================
@@ -181,9 +650,31 @@ impl Default for TcpConfig {
             handshake_timeout: Duration::from_secs(3),
             receive_window_size: 0xffff,
             ack_delay_timeout: Duration::from_millis(5),
+            ack_hold_timeout: Duration::ZERO,
+            receive_buffer_mode: ReceiveBufferMode::Linear,
+            user_timeout: None,
+            ecn_enabled: false,
             window_scale: 0,
             rx_checksum_offload: false,
             tx_checksum_offload: false,
+            max_receive_buffer_bytes: usize::MAX,
+            total_rx_buffer_cap: usize::MAX,
+            receive_window_auto_tuning: false,
+            max_receive_window_size: MAX_WINDOW_SIZE,
+            persist_timeout_min: Duration::from_millis(100),
+            persist_timeout_max: Duration::from_secs(60),
+            backlog_full_policy: BacklogPolicy::Rst,
+            msl: Duration::from_secs(30),
+            timestamps_enabled: false,
+            time_wait_recycling: false,
+            pmtud_probe_enabled: true,
+            pmtud_min_mss: 576,
+            nat_traversal_keepalive: false,
+            keepalive_nat_interval: Duration::from_secs(60),
+            gro_max_size: 0,
+            max_out_of_order_segments: 64,
+            peer_telemetry_retention: Duration::from_secs(300),
+            conn_event_queue_capacity: 1024,
         }
     }
 }
@@ -195,8 +686,16 @@ impl Default for TcpConfig {
 #[cfg(test)]
 mod tests {
     use crate::runtime::network::{
-        config::TcpConfig,
-        consts::DEFAULT_MSS,
+        config::{
+            BacklogPolicy,
+            ReceiveBufferMode,
+            TcpConfig,
+        },
+        consts::{
+            DEFAULT_MSS,
+            MAX_WINDOW_SIZE,
+            MIN_MSS,
+        },
     };
     use ::anyhow::Result;
     use ::std::time::Duration;
@@ -212,6 +711,66 @@ mod tests {
         crate::ensure_eq!(config.get_window_scale(), 0);
         crate::ensure_eq!(config.get_rx_checksum_offload(), false);
         crate::ensure_eq!(config.get_tx_checksum_offload(), false);
+        crate::ensure_eq!(config.get_max_receive_buffer_bytes(), usize::MAX);
+        crate::ensure_eq!(config.get_total_rx_buffer_cap(), usize::MAX);
+        crate::ensure_eq!(config.get_receive_window_auto_tuning(), false);
+        crate::ensure_eq!(config.get_max_receive_window_size(), MAX_WINDOW_SIZE);
+        crate::ensure_eq!(config.get_persist_timeout_min(), Duration::from_millis(100));
+        crate::ensure_eq!(config.get_persist_timeout_max(), Duration::from_secs(60));
+        crate::ensure_eq!(config.get_backlog_full_policy(), BacklogPolicy::Rst);
+        crate::ensure_eq!(config.get_ack_hold_timeout(), Duration::ZERO);
+        crate::ensure_eq!(config.get_receive_buffer_mode(), ReceiveBufferMode::Linear);
+        crate::ensure_eq!(config.get_user_timeout(), None);
+        crate::ensure_eq!(config.get_ecn_enabled(), false);
+        crate::ensure_eq!(config.get_msl(), Duration::from_secs(30));
+        crate::ensure_eq!(config.get_timestamps_enabled(), false);
+        crate::ensure_eq!(config.get_time_wait_recycling(), false);
+        crate::ensure_eq!(config.get_pmtud_probe_enabled(), true);
+        crate::ensure_eq!(config.get_pmtud_min_mss(), 576);
+        crate::ensure_eq!(config.get_nat_traversal_keepalive(), false);
+        crate::ensure_eq!(config.get_keepalive_nat_interval(), Duration::from_secs(60));
+        crate::ensure_eq!(config.get_gro_max_size(), 0);
+        crate::ensure_eq!(config.get_max_out_of_order_segments(), 64);
+        crate::ensure_eq!(config.get_peer_telemetry_retention(), Duration::from_secs(300));
+        crate::ensure_eq!(config.get_conn_event_queue_capacity(), 1024);
+
+        Ok(())
+    }
+
+    /// Tests that the advertised MSS of a [TcpConfig] is clamped to the maximum one, but not below the minimum one.
+    #[test]
+    fn test_tcp_config_clamp_advertised_mss() -> Result<()> {
+        let mut config: TcpConfig = TcpConfig::builder().advertised_mss(9000).build();
+        config.clamp_advertised_mss(1460);
+        crate::ensure_eq!(config.get_advertised_mss(), 1460);
+        config.clamp_advertised_mss(9000);
+        crate::ensure_eq!(config.get_advertised_mss(), 1460);
+        config.clamp_advertised_mss(0);
+        crate::ensure_eq!(config.get_advertised_mss(), MIN_MSS);
+        Ok(())
+    }
+
+    /// Tests instantiation for [TcpConfig] through its builder.
+    #[test]
+    fn test_tcp_config_builder() -> Result<()> {
+        let config: TcpConfig = TcpConfig::builder()
+            .receive_window_size(2048)
+            .window_scale(2)
+            .backlog_full_policy(BacklogPolicy::Drop)
+            .ecn_enabled(true)
+            .persist_timeout_min(Duration::from_secs(1))
+            .persist_timeout_max(Duration::from_secs(2))
+            .build();
+        crate::ensure_eq!(config.get_receive_window_size(), 2048);
+        crate::ensure_eq!(config.get_window_scale(), 2);
+        crate::ensure_eq!(config.get_backlog_full_policy(), BacklogPolicy::Drop);
+        crate::ensure_eq!(config.get_ecn_enabled(), true);
+        crate::ensure_eq!(config.get_persist_timeout_min(), Duration::from_secs(1));
+        crate::ensure_eq!(config.get_persist_timeout_max(), Duration::from_secs(2));
+
+        // Settings that are not given keep their defaults.
+        crate::ensure_eq!(config.get_advertised_mss(), DEFAULT_MSS);
+        crate::ensure_eq!(config.get_msl(), TcpConfig::default().get_msl());
 
         Ok(())
     }
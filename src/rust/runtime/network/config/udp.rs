@@ -12,6 +12,8 @@ pub struct UdpConfig {
     rx_checksum: bool,
     /// Offload Checksum to Hardware When Sending?
     tx_checksum: bool,
+    /// Maximum Number of Datagrams Buffered in the Receive Queue of a Socket
+    recv_queue_depth: usize,
 }
 
 //==============================================================================
@@ -21,7 +23,7 @@ pub struct UdpConfig {
 /// Associate functions for UDP Configuration Descriptor
 impl UdpConfig {
     /// Creates a UDP Configuration Descriptor.
-    pub fn new(rx_checksum: Option<bool>, tx_checksum: Option<bool>) -> Self {
+    pub fn new(rx_checksum: Option<bool>, tx_checksum: Option<bool>, recv_queue_depth: Option<usize>) -> Self {
         let mut config = Self::default();
         if let Some(rx_checksum) = rx_checksum {
             config.set_rx_checksum_offload(rx_checksum);
@@ -29,6 +31,9 @@ impl UdpConfig {
         if let Some(tx_checksum) = tx_checksum {
             config.set_tx_checksum_offload(tx_checksum);
         }
+        if let Some(recv_queue_depth) = recv_queue_depth {
+            config.set_recv_queue_depth(recv_queue_depth);
+        }
         config
     }
 
@@ -42,6 +47,12 @@ impl UdpConfig {
         self.tx_checksum
     }
 
+    /// Gets the maximum number of datagrams buffered in the receive queue of a socket in the target [UdpConfig].
+    /// Datagrams that arrive while the receive queue is full are dropped.
+    pub fn get_recv_queue_depth(&self) -> usize {
+        self.recv_queue_depth
+    }
+
     /// Sets the RX hardware checksum offload option in the target [UdpConfig].
     fn set_rx_checksum_offload(&mut self, rx_checksum: bool) {
         self.rx_checksum = rx_checksum;
@@ -51,6 +62,12 @@ impl UdpConfig {
     fn set_tx_checksum_offload(&mut self, tx_checksum: bool) {
         self.tx_checksum = tx_checksum;
     }
+
+    /// Sets the maximum number of datagrams buffered in the receive queue of a socket in the target [UdpConfig].
+    fn set_recv_queue_depth(&mut self, recv_queue_depth: usize) {
+        assert!(recv_queue_depth > 0);
+        self.recv_queue_depth = recv_queue_depth;
+    }
 }
 
 //==============================================================================
@@ -64,6 +81,7 @@ impl Default for UdpConfig {
         UdpConfig {
             rx_checksum: false,
             tx_checksum: false,
+            recv_queue_depth: 1024,
         }
     }
 }
@@ -83,6 +101,7 @@ mod tests {
         let config: UdpConfig = UdpConfig::default();
         crate::ensure_eq!(config.get_rx_checksum_offload(), false);
         crate::ensure_eq!(config.get_tx_checksum_offload(), false);
+        crate::ensure_eq!(config.get_recv_queue_depth(), 1024);
 
         Ok(())
     }
@@ -90,9 +109,10 @@ mod tests {
     /// Tests custom instantiation for [UdpConfig].
     #[test]
     fn test_udp_config_custom() -> Result<()> {
-        let config: UdpConfig = UdpConfig::new(Some(true), Some(true));
+        let config: UdpConfig = UdpConfig::new(Some(true), Some(true), Some(16));
         crate::ensure_eq!(config.get_rx_checksum_offload(), true);
         crate::ensure_eq!(config.get_tx_checksum_offload(), true);
+        crate::ensure_eq!(config.get_recv_queue_depth(), 16);
 
         Ok(())
     }
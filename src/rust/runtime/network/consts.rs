@@ -15,11 +15,23 @@ pub const MIN_MSS: usize = FALLBACK_MSS;
 pub const MAX_MSS: usize = u16::max_value() as usize;
 
 /// Default MSS Parameter for TCP
-///
-/// TODO: Auto-Discovery MTU Size
 pub const DEFAULT_MSS: usize = 1450;
 
-/// Length of a [crate::memory::DemiBuffer] batch.
-///
-/// TODO: This Should be Generic
+/// Default MTU of a NIC (standard Ethernet)
+pub const DEFAULT_MTU: usize = 1500;
+
+/// Maximum Payload Size for a UDP Datagram (the largest payload that fits in an IPv4 packet)
+pub const UDP_MAX_PAYLOAD_SIZE: usize = 65507;
+
+/// Maximum Number of Payload Bytes in a TCP Segment Merged by Receive Coalescing (leaves room for the headers, so
+/// that the merged frame still fits in a [crate::memory::DemiBuffer])
+pub const MAX_GRO_SIZE: usize = u16::MAX as usize - 128;
+
+/// Maximum Window Size for TCP (1 GiB, as allowed by the window scale option)
+pub const MAX_WINDOW_SIZE: u32 = 1 << 30;
+
+/// Default length of a [crate::memory::DemiBuffer] batch.
 pub const RECEIVE_BATCH_SIZE: usize = 4;
+
+/// Maximum length of a [crate::memory::DemiBuffer] batch.
+pub const MAX_RECEIVE_BATCH_SIZE: usize = 64;
@@ -5,7 +5,21 @@
 // Imports
 //==============================================================================
 
-use crate::runtime::memory::DemiBuffer;
+use crate::runtime::{
+    fail::Fail,
+    memory::DemiBuffer,
+    network::{
+        consts::{
+            DEFAULT_MTU,
+            MAX_RECEIVE_BATCH_SIZE,
+        },
+        types::{
+            LinkStatus,
+            MacAddress,
+            NicStats,
+        },
+    },
+};
 use ::arrayvec::ArrayVec;
 
 //==============================================================================
@@ -16,6 +30,13 @@ pub mod config;
 pub mod consts;
 pub mod types;
 
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Batch of received [DemiBuffer]s.
+pub type ReceiveBatch = ArrayVec<DemiBuffer, MAX_RECEIVE_BATCH_SIZE>;
+
 //==============================================================================
 // Traits
 //==============================================================================
@@ -33,10 +54,117 @@ pub trait PacketBuf {
 }
 
 /// Network Runtime
-pub trait NetworkRuntime<const N: usize> {
+pub trait NetworkRuntime {
     /// Transmits a single [PacketBuf].
     fn transmit(&self, pkt: Box<dyn PacketBuf>);
 
-    /// Receives a batch of [DemiBuffer].
-    fn receive(&self) -> ArrayVec<DemiBuffer, N>;
+    /// Transmits a batch of [PacketBuf]s, in order. Runtimes that can hand over several packets to the device at once
+    /// should override this.
+    fn transmit_batch(&self, pkts: Vec<Box<dyn PacketBuf>>) {
+        for pkt in pkts {
+            self.transmit(pkt);
+        }
+    }
+
+    /// Receives a batch of [DemiBuffer]. The batch holds at most as many buffers as the receive batch size that the
+    /// target [NetworkRuntime] was configured with.
+    fn receive(&self) -> ReceiveBatch;
+
+    /// Returns the identification field for the next IPv4 datagram that the target [NetworkRuntime] transmits.
+    fn next_ipv4_id(&self) -> u16;
+
+    /// Returns the statistics of the frames that the target [NetworkRuntime] has transmitted and received so far.
+    /// Runtimes that do not keep track of these report no traffic at all.
+    fn nic_stats(&self) -> NicStats {
+        NicStats::default()
+    }
+
+    /// Returns the status of the link of the NIC that the target [NetworkRuntime] drives. Runtimes that cannot query
+    /// the link report it as always up, at an unknown speed.
+    fn link_status(&self) -> LinkStatus {
+        LinkStatus::up(0)
+    }
+
+    /// Returns the maximum transmission unit of the NIC that the target [NetworkRuntime] drives, that is, the size of
+    /// the largest IP packet that it sends and receives. Runtimes that cannot query it assume a standard Ethernet MTU.
+    fn mtu(&self) -> usize {
+        DEFAULT_MTU
+    }
+
+    /// Sets the multicast link addresses that the NIC of the target [NetworkRuntime] should accept frames for, replacing
+    /// the previous ones. Runtimes that cannot filter multicast frames in hardware accept all of them, and leave the
+    /// filtering to the stack.
+    fn set_multicast_filter(&self, _addrs: &[MacAddress]) {}
+
+    /// Caps the number of packets that the target [NetworkRuntime] takes from the NIC per receive call to `n`, which may
+    /// not exceed the receive batch size that it was configured with. Runtimes that do not receive in bursts do not
+    /// support this.
+    fn set_rx_burst_size(&self, _n: usize) -> Result<(), Fail> {
+        Err(Fail::new(libc::ENOTSUP, "operation not supported"))
+    }
+}
+
+//==============================================================================
+// Standalone Functions
+//==============================================================================
+
+/// Checks if `batch_size` is a valid receive batch size. Valid sizes are powers of two that do not exceed
+/// [MAX_RECEIVE_BATCH_SIZE].
+pub fn check_receive_batch_size(batch_size: usize) -> Result<usize, Fail> {
+    if !batch_size.is_power_of_two() || batch_size > MAX_RECEIVE_BATCH_SIZE {
+        let cause: String = format!(
+            "invalid receive batch size (batch_size={:?}, max={:?})",
+            batch_size, MAX_RECEIVE_BATCH_SIZE
+        );
+        error!("check_receive_batch_size(): {}", cause);
+        return Err(Fail::new(libc::EINVAL, &cause));
+    }
+    Ok(batch_size)
+}
+
+/// Checks if the link of the NIC that `rt` drives is up, so that packets may be transmitted. If it is down, this fails
+/// with `ENETDOWN`.
+pub fn check_link_up(rt: &dyn NetworkRuntime) -> Result<(), Fail> {
+    if !rt.link_status().up {
+        let cause: &str = "link is down";
+        warn!("check_link_up(): {}", cause);
+        return Err(Fail::new(libc::ENETDOWN, cause));
+    }
+    Ok(())
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_receive_batch_size,
+        consts::{
+            MAX_RECEIVE_BATCH_SIZE,
+            RECEIVE_BATCH_SIZE,
+        },
+    };
+    use ::anyhow::Result;
+
+    /// Tests if valid receive batch sizes are accepted.
+    #[test]
+    fn test_check_receive_batch_size_valid() -> Result<()> {
+        for batch_size in [1, RECEIVE_BATCH_SIZE, MAX_RECEIVE_BATCH_SIZE] {
+            crate::ensure_eq!(check_receive_batch_size(batch_size).ok(), Some(batch_size));
+        }
+
+        Ok(())
+    }
+
+    /// Tests if invalid receive batch sizes are rejected.
+    #[test]
+    fn test_check_receive_batch_size_invalid() -> Result<()> {
+        for batch_size in [0, 3, MAX_RECEIVE_BATCH_SIZE + 1, 2 * MAX_RECEIVE_BATCH_SIZE] {
+            crate::ensure_eq!(check_receive_batch_size(batch_size).is_err(), true);
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,19 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Byte Statistics of a Connection
+///
+/// Running totals of the payload bytes that a connection moved, from the time it was established until it is closed.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct ByteStats {
+    /// Number of bytes sent, retransmissions excluded.
+    pub sent: u64,
+    /// Number of bytes received in order.
+    pub received: u64,
+    /// Number of bytes retransmitted.
+    pub retransmitted: u64,
+}
@@ -0,0 +1,30 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::QDesc;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Connection Event
+///
+/// Change in the state of a TCP connection, as reported through the connection event queue of a LibOS.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum ConnEvent {
+    /// The connection of a socket was established, either because it connected or because it was accepted.
+    Established(QDesc),
+    /// The connection of a socket was torn down abruptly, with the error code that operations on it fail with:
+    /// `ECONNRESET` if our peer reset it, `ETIMEDOUT` if it timed out, or `ECONNREFUSED` if it could not be opened.
+    Reset(QDesc, i32),
+    /// The connection of a socket was closed in an orderly fashion.
+    Closed(QDesc),
+    /// A listening socket turned away a connection because its accept queue was full.
+    AcceptQueueFull(QDesc),
+    /// Events were dropped at this point, because the application did not drain the queue in time.
+    Overflow,
+}
@@ -0,0 +1,27 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Handshake Statistics
+///
+/// Telemetry on the TCP handshakes that remote hosts initiate towards our listening sockets.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct HandshakeStats {
+    /// Number of connection requests (SYN segments) received, including the ones that were turned away.
+    pub syn_received: u64,
+    /// Number of SYN+ACK segments sent, retransmissions included.
+    pub syn_ack_sent: u64,
+    /// Number of handshakes that completed.
+    pub handshakes_completed: u64,
+    /// Number of handshakes that did not complete, because the backlog was full, the remote host did not answer, or
+    /// the listening socket was shut down.
+    pub handshakes_failed: u64,
+    /// Number of handshakes that failed because the remote host did not answer.
+    pub handshake_timeout_count: u64,
+    /// Exponentially weighted moving average of the time from a SYN to the ACK that completes the handshake (in
+    /// microseconds).
+    pub avg_handshake_latency_us: u64,
+}
@@ -0,0 +1,48 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use ::rand::{
+    prelude::SmallRng,
+    Rng,
+};
+use ::std::{
+    cell::Cell,
+    rc::Rc,
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Generator of the identification field of outgoing IPv4 datagrams. Identifiers start at a random value, so that they
+/// do not reveal how many datagrams a host has sent, and then advance by one per datagram, so that fragments of
+/// different datagrams are not mixed up on reassembly (RFC 6864). Clones share the same sequence.
+#[derive(Clone, Debug)]
+pub struct Ipv4IdGenerator {
+    next: Rc<Cell<u16>>,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate Functions for IPv4 Identification Generators
+impl Ipv4IdGenerator {
+    /// Instantiates a generator whose first identifier is drawn from `rng`.
+    pub fn new(rng: &mut SmallRng) -> Self {
+        Self {
+            next: Rc::new(Cell::new(rng.gen())),
+        }
+    }
+
+    /// Returns the identifier of the next datagram.
+    pub fn next_id(&self) -> u16 {
+        let id: u16 = self.next.get();
+        self.next.set(id.wrapping_add(1));
+        id
+    }
+}
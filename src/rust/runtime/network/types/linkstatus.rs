@@ -0,0 +1,37 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Link Status
+///
+/// State of the physical link of a NIC, as last reported by the device.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct LinkStatus {
+    /// Is the link up?
+    pub up: bool,
+    /// Link speed in Mbps, or zero if unknown.
+    pub speed_mbps: u32,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate Functions for Link Status
+impl LinkStatus {
+    /// Creates the status of a link that is up and running at `speed_mbps`.
+    pub fn up(speed_mbps: u32) -> Self {
+        Self { up: true, speed_mbps }
+    }
+
+    /// Creates the status of a link that is down.
+    pub fn down() -> Self {
+        Self {
+            up: false,
+            speed_mbps: 0,
+        }
+    }
+}
@@ -8,7 +8,10 @@
 use crate::runtime::fail::Fail;
 use ::eui48;
 use ::libc::EINVAL;
-use ::std::fmt;
+use ::std::{
+    fmt,
+    net::Ipv4Addr,
+};
 
 //==============================================================================
 // Structures
@@ -39,6 +42,13 @@ impl MacAddress {
         self.0.to_array()
     }
 
+    /// Returns the MAC Address that the IPv4 multicast group `group` maps to, which is made of the low-order 23 bits of
+    /// the group address on top of the `01:00:5e` prefix (RFC 1112, Section 6.4).
+    pub fn from_ipv4_multicast(group: Ipv4Addr) -> MacAddress {
+        let octets: [u8; 4] = group.octets();
+        MacAddress::new([0x01, 0x00, 0x5e, octets[1] & 0x7f, octets[2], octets[3]])
+    }
+
     /// Returns a MAC Address that matches the broadcast one.
     pub fn broadcast() -> MacAddress {
         MacAddress(eui48::MacAddress::broadcast())
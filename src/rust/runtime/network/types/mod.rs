@@ -1,14 +1,34 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod bytestats;
+mod connevent;
+mod handshakestats;
+mod ipv4id;
+mod linkstatus;
 mod macaddr;
+mod nicstats;
+mod peerstats;
 mod portnum;
+mod portstats;
+mod pushcompletion;
+mod socketoption;
 
 //==============================================================================
 // Exports
 //==============================================================================
 
 pub use self::{
+    bytestats::ByteStats,
+    connevent::ConnEvent,
+    handshakestats::HandshakeStats,
+    ipv4id::Ipv4IdGenerator,
+    linkstatus::LinkStatus,
     macaddr::MacAddress,
+    nicstats::NicStats,
+    peerstats::PeerStats,
     portnum::Port16,
+    portstats::DpdkPortStats,
+    pushcompletion::PushCompletion,
+    socketoption::SocketOption,
 };
@@ -0,0 +1,53 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// NIC Statistics
+///
+/// Counters for the frames that a network runtime hands over to and takes from the NIC.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct NicStats {
+    /// Number of frames received.
+    pub rx_packets: u64,
+    /// Number of bytes received.
+    pub rx_bytes: u64,
+    /// Number of frames transmitted.
+    pub tx_packets: u64,
+    /// Number of bytes transmitted.
+    pub tx_bytes: u64,
+    /// Number of frames dropped because the NIC had no room left to transmit them.
+    pub tx_dropped: u64,
+    /// Number of frames that the NIC dropped because it had no receive descriptors left to place them in. Only
+    /// runtimes that can query the NIC for this report it.
+    pub rx_missed: u64,
+    /// Number of receive descriptors that the NIC could not refill because it ran out of buffers. Only runtimes that
+    /// can query the NIC for this report it.
+    pub rx_no_mbuf: u64,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+/// Associate Functions for NIC Statistics
+impl NicStats {
+    /// Accounts for a received frame of `len` bytes.
+    pub fn record_rx(&mut self, len: usize) {
+        self.rx_packets += 1;
+        self.rx_bytes += len as u64;
+    }
+
+    /// Accounts for a transmitted frame of `len` bytes.
+    pub fn record_tx(&mut self, len: usize) {
+        self.tx_packets += 1;
+        self.tx_bytes += len as u64;
+    }
+
+    /// Accounts for a frame that was dropped on transmission.
+    pub fn record_tx_drop(&mut self) {
+        self.tx_dropped += 1;
+    }
+}
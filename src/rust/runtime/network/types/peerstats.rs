@@ -0,0 +1,42 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use ::std::time::Duration;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Peer Statistics
+///
+/// Telemetry on a remote host, aggregated across all TCP connections to it, including the ones that are closed already.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct PeerStats {
+    /// Smoothed round-trip time, over the samples of all connections, if any were taken.
+    pub srtt: Option<Duration>,
+    /// Number of bytes sent, retransmissions excluded.
+    pub bytes_sent: u64,
+    /// Number of bytes retransmitted.
+    pub bytes_retransmitted: u64,
+    /// Number of connection attempts that were refused or timed out.
+    pub connection_failures: u64,
+}
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+/// Associated Functions for Peer Statistics
+impl PeerStats {
+    /// Returns the ratio of retransmitted bytes to bytes sent, or zero if nothing was sent.
+    pub fn retransmit_ratio(&self) -> f64 {
+        if self.bytes_sent == 0 {
+            return 0.0;
+        }
+        self.bytes_retransmitted as f64 / self.bytes_sent as f64
+    }
+}
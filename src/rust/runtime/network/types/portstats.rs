@@ -0,0 +1,28 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// DPDK Port Statistics
+///
+/// Counters that the NIC keeps in hardware for a DPDK port. Unlike [NicStats](super::NicStats), these also account for
+/// frames that the NIC dropped or failed to handle before they ever reached the runtime.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct DpdkPortStats {
+    /// Number of frames successfully received.
+    pub rx_packets: u64,
+    /// Number of bytes successfully received.
+    pub rx_bytes: u64,
+    /// Number of frames dropped because the NIC had no receive descriptors left to place them in.
+    pub rx_missed: u64,
+    /// Number of frames that the NIC received with errors.
+    pub rx_errors: u64,
+    /// Number of frames successfully transmitted.
+    pub tx_packets: u64,
+    /// Number of bytes successfully transmitted.
+    pub tx_bytes: u64,
+    /// Number of frames that the NIC failed to transmit.
+    pub tx_errors: u64,
+}
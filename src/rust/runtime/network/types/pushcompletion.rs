@@ -0,0 +1,18 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Enumerations
+//==============================================================================
+
+/// Push Completion
+///
+/// When a push on a socket completes.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum PushCompletion {
+    /// The push completes as soon as its data is queued for transmission. This has the lowest latency.
+    #[default]
+    Enqueued,
+    /// The push completes once the peer has acknowledged every byte of it. Only stream sockets support this.
+    Acked,
+}
@@ -0,0 +1,17 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Enumerations
+//==============================================================================
+
+/// Socket Option
+///
+/// Options that may be set on an I/O queue.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum SocketOption {
+    /// Largest number of operations of each type (e.g. pushes or pops) that may be outstanding at once on the I/O
+    /// queue. Operations past this limit fail with `EBUSY` right away, instead of piling up. An operation stops counting
+    /// against the limit once its result is taken. Zero lifts the limit, which is the default.
+    MaxOpsPerSec(u32),
+}
@@ -0,0 +1,54 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::QDesc;
+use ::std::net::SocketAddrV4;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// State of an accepted connection.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ConnectionState {
+    /// Connection that transfers data both ways.
+    Established,
+    /// Connection whose remote end closed, so no more data may be popped from it.
+    RemoteClosed,
+}
+
+/// Snapshot of an accepted connection, as reported by connection introspection.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    /// I/O queue descriptor of the connection.
+    pub qd: QDesc,
+    /// Address of the remote end.
+    pub remote: SocketAddrV4,
+    /// State of the connection.
+    pub state: ConnectionState,
+    /// Number of bytes pushed on the connection.
+    pub tx_bytes: u64,
+    /// Number of bytes popped from the connection.
+    pub rx_bytes: u64,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+impl ConnectionInfo {
+    /// Creates a snapshot of a connection that was just accepted.
+    pub fn new(qd: QDesc, remote: SocketAddrV4) -> Self {
+        Self {
+            qd,
+            remote,
+            state: ConnectionState::Established,
+            tx_bytes: 0,
+            rx_bytes: 0,
+        }
+    }
+}
@@ -1,31 +1,43 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-mod qdesc;
+mod connection_info;
 mod operation_result;
+mod qdesc;
 mod qtoken;
 mod qtype;
+mod queue_info;
 
 //======================================================================================================================
 // Imports
 //======================================================================================================================
 
-use crate::scheduler::TaskWithResult;
-use ::slab::{
-    Iter,
-    Slab,
+use crate::{
+    runtime::fail::Fail,
+    scheduler::TaskWithResult,
+};
+use ::std::{
+    future::Future,
+    time::Instant,
 };
-use ::std::future::Future;
 
 //======================================================================================================================
 // Exports
 //======================================================================================================================
 
 pub use self::{
-    qdesc::QDesc,
+    connection_info::{
+        ConnectionInfo,
+        ConnectionState,
+    },
     operation_result::OperationResult,
+    qdesc::QDesc,
     qtoken::QToken,
     qtype::QType,
+    queue_info::{
+        QueueInfo,
+        QueueState,
+    },
 };
 
 // Coroutine for running an operation on an I/O Queue.
@@ -41,11 +53,68 @@ pub type BackgroundTask = TaskWithResult<()>;
 
 pub trait IoQueue {
     fn get_qtype(&self) -> QType;
+
+    /// Fills in the backend-specific details of a snapshot of this queue. The I/O queue descriptors table fills in the
+    /// descriptor, type and age on its own, so queues that have nothing else to report can rely on this default.
+    fn describe(&self, _info: &mut QueueInfo) {}
+}
+
+/// Entry of an I/O queue descriptors table.
+enum Entry<T: IoQueue> {
+    /// An I/O queue, along with the number of I/O queue descriptors that refer to it, whether its own descriptor is
+    /// still open and when it was created. A closed descriptor remains reserved (and refers to the queue) until all of
+    /// its duplicates are closed.
+    Queue {
+        queue: T,
+        refcnt: usize,
+        open: bool,
+        created: Instant,
+    },
+    /// A duplicate of the I/O queue descriptor at the given index.
+    Duplicate(usize),
+}
+
+/// Slot of an I/O queue descriptors table.
+struct Slot<T: IoQueue> {
+    /// Number of times that this slot was handed out. This tells apart the descriptors that reuse the slot.
+    generation: u32,
+    /// Entry in this slot, or the index of the next vacant slot if there is none.
+    state: SlotState<T>,
+}
+
+/// State of a slot of an I/O queue descriptors table.
+enum SlotState<T: IoQueue> {
+    Occupied(Entry<T>),
+    /// A vacant slot, which links to the next vacant one (if any).
+    Vacant(Option<usize>),
+}
+
+/// Statistics of an I/O queue descriptors table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IoQueueTableStats {
+    /// Number of I/O queue descriptors that are in use. Closed descriptors that are kept around for their duplicates
+    /// count as well.
+    pub used: usize,
+    /// Number of slots, both used and vacant. The table does not shrink, so this is the most descriptors that were ever
+    /// in use at once.
+    pub slots: usize,
+    /// Bytes of memory that the table takes, not counting the memory that queues take on their own.
+    pub bytes: usize,
 }
 
 /// I/O queue descriptors table.
+///
+/// Descriptors live in slots that are handed out and taken back in constant time: vacant slots form a free-list, and
+/// the most recently vacated one is reused first. Each slot counts how many times it was handed out, so that a
+/// descriptor that was closed can be told apart from a later one that reuses its slot (see [Self::get_generation]).
 pub struct IoQueueTable<T: IoQueue> {
-    table: Slab<T>,
+    slots: Vec<Slot<T>>,
+    /// First vacant slot.
+    free_head: Option<usize>,
+    /// Number of occupied slots.
+    used: usize,
+    /// Largest number of occupied slots, if any.
+    max_queues: Option<usize>,
 }
 
 //======================================================================================================================
@@ -66,61 +135,308 @@ impl<T: IoQueue> IoQueueTable<T> {
     /// Creates an I/O queue descriptors table.
     pub fn new() -> Self {
         Self {
-            table: Slab::<T>::new(),
+            slots: Vec::new(),
+            free_head: None,
+            used: 0,
+            max_queues: None,
         }
     }
 
+    /// Limits the number of I/O queue descriptors that may be in use at once. Allocations past this limit fail with
+    /// `EMFILE`. Descriptors that are already in use are left alone, even if there are more of them than `max_queues`.
+    pub fn set_max_queues(&mut self, max_queues: Option<usize>) {
+        self.max_queues = max_queues;
+    }
+
     /// Allocates a new entry in the target I/O queue descriptors table.
-    pub fn alloc(&mut self, queue: T) -> QDesc {
-        let index: usize = self.table.insert(queue);
-
-        // Ensure that the allocation would yield to a safe conversion between usize to u32.
-        // Note: This imposes a limit on the number of open queue descriptors in u32::MAX.
-        assert!(
-            (index as u32) + Self::BASE_QD <= u32::MAX,
-            "I/O descriptors table overflow"
-        );
+    pub fn alloc(&mut self, queue: T) -> Result<QDesc, Fail> {
+        self.insert(Entry::Queue {
+            queue,
+            refcnt: 1,
+            open: true,
+            created: Instant::now(),
+        })
+    }
+
+    /// Allocates a new I/O queue descriptor that refers to the same queue as `qd`. The queue is only released once all
+    /// descriptors that refer to it are closed.
+    pub fn dup(&mut self, qd: &QDesc) -> Result<QDesc, Fail> {
+        let index: usize = match self.get_index(qd) {
+            Some(index) => self.resolve(index as usize),
+            None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        };
+        let new_qd: QDesc = self.insert(Entry::Duplicate(index))?;
+        match self.get_entry_mut(index) {
+            Some(Entry::Queue { refcnt, .. }) => *refcnt += 1,
+            _ => unreachable!("duplicates should refer to a queue"),
+        }
+        Ok(new_qd)
+    }
 
-        QDesc::from((index as u32) + Self::BASE_QD)
+    /// Closes an I/O queue descriptor that refers to a queue with other open descriptors, leaving the queue in place.
+    /// Returns `Some(false)` if this is the last open descriptor of the queue, in which case the caller should tear the
+    /// queue down and [Self::free] it, and `None` if `qd` is not an open descriptor.
+    pub fn close_duplicate(&mut self, qd: &QDesc) -> Option<bool> {
+        let index: usize = self.get_index(qd)? as usize;
+        let target: usize = self.resolve(index);
+        match self.get_entry_mut(target) {
+            Some(Entry::Queue { refcnt, open, .. }) => {
+                if *refcnt == 1 {
+                    return Some(false);
+                }
+                *refcnt -= 1;
+                if target == index {
+                    *open = false;
+                }
+            },
+            _ => unreachable!("duplicates should refer to a queue"),
+        }
+        if target != index {
+            self.remove(index);
+        }
+        Some(true)
+    }
+
+    /// Inserts an entry in the target I/O queue descriptors table.
+    fn insert(&mut self, entry: Entry<T>) -> Result<QDesc, Fail> {
+        if let Some(max_queues) = self.max_queues {
+            if self.used >= max_queues {
+                let cause: String = format!("too many open queue descriptors (max_queues={:?})", max_queues);
+                error!("insert(): {}", cause);
+                return Err(Fail::new(libc::EMFILE, &cause));
+            }
+        }
+
+        let index: usize = match self.free_head {
+            Some(index) => {
+                let slot: &mut Slot<T> = &mut self.slots[index];
+                self.free_head = match slot.state {
+                    SlotState::Vacant(next) => next,
+                    SlotState::Occupied(_) => unreachable!("free-list should only link vacant slots"),
+                };
+                slot.generation = slot.generation.wrapping_add(1);
+                slot.state = SlotState::Occupied(entry);
+                index
+            },
+            None => {
+                // Ensure that the allocation would yield to a safe conversion between usize to u32.
+                // Note: This imposes a limit on the number of open queue descriptors in u32::MAX.
+                let index: usize = self.slots.len();
+                assert!(
+                    (index as u64) + (Self::BASE_QD as u64) <= u32::MAX as u64,
+                    "I/O descriptors table overflow"
+                );
+                self.slots.push(Slot {
+                    generation: 0,
+                    state: SlotState::Occupied(entry),
+                });
+                index
+            },
+        };
+        self.used += 1;
+
+        Ok(QDesc::from((index as u32) + Self::BASE_QD))
+    }
+
+    /// Takes the entry out of a slot and puts the slot on the free-list.
+    fn remove(&mut self, index: usize) -> Entry<T> {
+        let state: SlotState<T> = std::mem::replace(&mut self.slots[index].state, SlotState::Vacant(self.free_head));
+        match state {
+            SlotState::Occupied(entry) => {
+                self.free_head = Some(index);
+                self.used -= 1;
+                entry
+            },
+            SlotState::Vacant(_) => unreachable!("cannot remove a vacant slot"),
+        }
     }
 
     /// Gets/borrows a reference to the queue metadata associated with an I/O queue descriptor.
     pub fn get(&self, qd: &QDesc) -> Option<&T> {
-        let index: u32 = self.get_index(qd)?;
-        self.table.get(index as usize)
+        let index: usize = self.resolve(self.get_index(qd)? as usize);
+        match self.get_entry(index) {
+            Some(Entry::Queue { queue, .. }) => Some(queue),
+            _ => None,
+        }
     }
 
     /// Gets/borrows a mutable reference to the queue metadata associated with an I/O queue descriptor
     pub fn get_mut(&mut self, qd: &QDesc) -> Option<&mut T> {
-        let index: u32 = self.get_index(qd)?;
-        self.table.get_mut(index as usize)
+        let index: usize = self.resolve(self.get_index(qd)? as usize);
+        match self.get_entry_mut(index) {
+            Some(Entry::Queue { queue, .. }) => Some(queue),
+            _ => None,
+        }
     }
 
-    /// Releases the entry associated with an I/O queue descriptor.
+    /// Like [Self::get], but also resolves a closed descriptor whose queue is kept around for its duplicates. This is
+    /// meant for the LibOS itself, which keeps referring to a queue by the descriptor that it was opened on.
+    pub fn get_resolved(&self, qd: &QDesc) -> Option<&T> {
+        let index: usize = self.resolve(self.get_slot_index(qd)? as usize);
+        match self.get_entry(index) {
+            Some(Entry::Queue { queue, .. }) => Some(queue),
+            _ => None,
+        }
+    }
+
+    /// Like [Self::get_mut], but also resolves a closed descriptor whose queue is kept around for its duplicates.
+    pub fn get_resolved_mut(&mut self, qd: &QDesc) -> Option<&mut T> {
+        let index: usize = self.resolve(self.get_slot_index(qd)? as usize);
+        match self.get_entry_mut(index) {
+            Some(Entry::Queue { queue, .. }) => Some(queue),
+            _ => None,
+        }
+    }
+
+    /// Gets the generation of the slot that an I/O queue descriptor refers to. A descriptor that is closed and then
+    /// handed out again has a different generation, so callers that remember both can tell whether a descriptor still
+    /// refers to the same queue.
+    pub fn get_generation(&self, qd: &QDesc) -> Option<u32> {
+        let index: usize = self.get_index(qd)? as usize;
+        Some(self.slots[index].generation)
+    }
+
+    /// Releases the entry associated with an I/O queue descriptor, along with the queue it refers to. This is expected
+    /// to be called on the last open descriptor of the queue (see [Self::close_duplicate]), or by the LibOS on the
+    /// descriptor that the queue was opened on.
     pub fn free(&mut self, qd: &QDesc) -> Option<T> {
-        let index: u32 = self.get_index(qd)?;
-        Some(self.table.remove(index as usize))
+        let index: usize = self.get_slot_index(qd)? as usize;
+        let target: usize = self.resolve(index);
+        if target != index {
+            self.remove(index);
+        }
+        // Drop any other duplicates, in case the queue still has some.
+        if let Some(Entry::Queue { refcnt, .. }) = self.get_entry(target) {
+            if *refcnt > 1 {
+                let duplicates: Vec<usize> = (0..self.slots.len())
+                    .filter(|i| matches!(self.get_entry(*i), Some(Entry::Duplicate(t)) if *t == target))
+                    .collect();
+                for i in duplicates {
+                    self.remove(i);
+                }
+            }
+        }
+        match self.remove(target) {
+            Entry::Queue { queue, .. } => Some(queue),
+            Entry::Duplicate(_) => unreachable!("duplicates should refer to a queue"),
+        }
     }
 
     /// Gets an iterator over all registered queues.
-    pub fn get_values(&self) -> Iter<'_, T> {
-        self.table.iter()
+    pub fn get_values(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| match &slot.state {
+                SlotState::Occupied(Entry::Queue { queue, .. }) => Some((index, queue)),
+                _ => None,
+            })
+    }
+
+    /// Takes a snapshot of the queue that an I/O queue descriptor refers to. Returns `None` if `qd` is not an open
+    /// descriptor.
+    pub fn query(&self, qd: &QDesc) -> Option<QueueInfo> {
+        let index: usize = self.get_index(qd)? as usize;
+        self.describe(index)
+    }
+
+    /// Takes a snapshot of every open I/O queue descriptor, duplicates included, in ascending descriptor order.
+    pub fn queues(&self) -> Vec<QueueInfo> {
+        (0..self.slots.len()).filter_map(|index| self.describe(index)).collect()
+    }
+
+    /// Reports how many descriptors are in use and how much memory the table takes.
+    pub fn stats(&self) -> IoQueueTableStats {
+        IoQueueTableStats {
+            used: self.used,
+            slots: self.slots.len(),
+            bytes: std::mem::size_of::<Self>() + self.slots.capacity() * std::mem::size_of::<Slot<T>>(),
+        }
+    }
+
+    /// Takes a snapshot of the queue that an entry of the I/O queue descriptors table refers to, unless the entry is
+    /// vacant or a closed descriptor that is only kept around for its duplicates.
+    fn describe(&self, index: usize) -> Option<QueueInfo> {
+        self.get_entry(index)?;
+        let target: usize = self.resolve(index);
+        match self.get_entry(target) {
+            Some(Entry::Queue {
+                queue, open, created, ..
+            }) => {
+                if target == index && !*open {
+                    return None;
+                }
+                let qd: QDesc = QDesc::from((index as u32) + Self::BASE_QD);
+                let mut info: QueueInfo = QueueInfo::new(qd, queue.get_qtype(), created.elapsed());
+                queue.describe(&mut info);
+                Some(info)
+            },
+            _ => unreachable!("duplicates should refer to a queue"),
+        }
+    }
+
+    /// Gets the entry in a slot, if the slot is occupied.
+    fn get_entry(&self, index: usize) -> Option<&Entry<T>> {
+        match self.slots.get(index) {
+            Some(Slot {
+                state: SlotState::Occupied(entry),
+                ..
+            }) => Some(entry),
+            _ => None,
+        }
     }
 
-    /// Gets the index in the I/O queue descriptors table to which a given I/O queue descriptor refers to.
+    /// Gets a mutable reference to the entry in a slot, if the slot is occupied.
+    fn get_entry_mut(&mut self, index: usize) -> Option<&mut Entry<T>> {
+        match self.slots.get_mut(index) {
+            Some(Slot {
+                state: SlotState::Occupied(entry),
+                ..
+            }) => Some(entry),
+            _ => None,
+        }
+    }
+
+    /// Gets the index of the queue that an entry of the I/O queue descriptors table refers to.
+    fn resolve(&self, index: usize) -> usize {
+        match self.get_entry(index) {
+            Some(Entry::Duplicate(target)) => *target,
+            _ => index,
+        }
+    }
+
+    /// Gets the index in the I/O queue descriptors table to which a given I/O queue descriptor refers to. Closed
+    /// descriptors that are only kept around for their duplicates do not refer to anything.
     fn get_index(&self, qd: &QDesc) -> Option<u32> {
+        let index: u32 = self.get_slot_index(qd)?;
+        match self.get_entry(index as usize)? {
+            Entry::Queue { open: false, .. } => None,
+            _ => Some(index),
+        }
+    }
+
+    /// Gets the index of the occupied slot that holds a given I/O queue descriptor, whether it is open or not.
+    fn get_slot_index(&self, qd: &QDesc) -> Option<u32> {
         if Into::<u32>::into(*qd) < Self::BASE_QD {
             None
         } else {
             let rawqd: u32 = Into::<u32>::into(*qd) - Self::BASE_QD;
-            if !self.table.contains(rawqd as usize) {
-                return None;
-            }
+            self.get_entry(rawqd as usize)?;
             Some(rawqd)
         }
     }
 }
 
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl<T: IoQueue> Default for IoQueueTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 //======================================================================================================================
 // Unit Tests
 //======================================================================================================================
@@ -130,11 +446,18 @@ mod tests {
     use super::{
         IoQueue,
         IoQueueTable,
+        QueueInfo,
     };
     use crate::{
         QDesc,
         QType,
     };
+    use ::rand::{
+        prelude::SmallRng,
+        Rng,
+        SeedableRng,
+    };
+    use ::std::collections::HashMap;
     use ::test::{
         black_box,
         Bencher,
@@ -148,15 +471,185 @@ mod tests {
         }
     }
 
+    /// Queue that carries an identifier, so that tests can check which queue a descriptor resolves to.
+    pub struct TaggedQueue(usize);
+
+    impl IoQueue for TaggedQueue {
+        fn get_qtype(&self) -> QType {
+            QType::TestQueue
+        }
+    }
+
     #[bench]
     fn bench_alloc_free(b: &mut Bencher) {
         let mut ioqueue_table: IoQueueTable<TestQueue> = IoQueueTable::<TestQueue>::new();
 
         b.iter(|| {
-            let qd: QDesc = ioqueue_table.alloc(TestQueue {});
+            let qd: QDesc = ioqueue_table.alloc(TestQueue {}).unwrap();
             black_box(qd);
             let qtype: Option<TestQueue> = ioqueue_table.free(&qd);
             black_box(qtype);
         });
     }
+
+    #[test]
+    fn dup_close() {
+        let mut ioqueue_table: IoQueueTable<TestQueue> = IoQueueTable::<TestQueue>::new();
+
+        // Close the original descriptor first.
+        let qd: QDesc = ioqueue_table.alloc(TestQueue {}).unwrap();
+        let dup_qd: QDesc = ioqueue_table.dup(&qd).unwrap();
+        assert_ne!(qd, dup_qd);
+        assert!(ioqueue_table.get(&dup_qd).is_some());
+        assert_eq!(ioqueue_table.close_duplicate(&qd), Some(true));
+        assert!(ioqueue_table.get(&qd).is_none());
+        assert!(ioqueue_table.get_mut(&qd).is_none());
+        assert!(ioqueue_table.dup(&qd).is_err());
+        assert!(ioqueue_table.query(&qd).is_none());
+        assert!(ioqueue_table.get(&dup_qd).is_some());
+        assert!(ioqueue_table.get_resolved(&qd).is_some());
+        assert_eq!(ioqueue_table.close_duplicate(&qd), None);
+        assert_eq!(ioqueue_table.close_duplicate(&dup_qd), Some(false));
+        assert!(ioqueue_table.free(&dup_qd).is_some());
+        assert!(ioqueue_table.get(&qd).is_none());
+        assert!(ioqueue_table.get(&dup_qd).is_none());
+
+        // Close the duplicate descriptor first.
+        let qd: QDesc = ioqueue_table.alloc(TestQueue {}).unwrap();
+        let dup_qd: QDesc = ioqueue_table.dup(&qd).unwrap();
+        assert_eq!(ioqueue_table.close_duplicate(&dup_qd), Some(true));
+        assert!(ioqueue_table.get(&dup_qd).is_none());
+        assert_eq!(ioqueue_table.close_duplicate(&qd), Some(false));
+        assert!(ioqueue_table.free(&qd).is_some());
+        assert!(ioqueue_table.get(&qd).is_none());
+    }
+
+    #[test]
+    fn queues_query() {
+        let mut ioqueue_table: IoQueueTable<TestQueue> = IoQueueTable::<TestQueue>::new();
+        let qd: QDesc = ioqueue_table.alloc(TestQueue {}).unwrap();
+        let other_qd: QDesc = ioqueue_table.alloc(TestQueue {}).unwrap();
+        let dup_qd: QDesc = ioqueue_table.dup(&qd).unwrap();
+
+        // Duplicates are reported along with the queues, in ascending descriptor order.
+        let queues: Vec<QueueInfo> = ioqueue_table.queues();
+        let qds: Vec<QDesc> = queues.iter().map(|info| info.qd).collect();
+        assert_eq!(qds, vec![qd, other_qd, dup_qd]);
+        assert!(queues.iter().all(|info| info.qtype == QType::TestQueue));
+        assert_eq!(ioqueue_table.query(&dup_qd).map(|info| info.qd), Some(dup_qd));
+
+        // A closed descriptor is not reported, even if its duplicate keeps the queue around.
+        assert_eq!(ioqueue_table.close_duplicate(&qd), Some(true));
+        assert!(ioqueue_table.query(&qd).is_none());
+        let qds: Vec<QDesc> = ioqueue_table.queues().iter().map(|info| info.qd).collect();
+        assert_eq!(qds, vec![other_qd, dup_qd]);
+    }
+
+    #[test]
+    fn alloc_free_stress() {
+        let mut ioqueue_table: IoQueueTable<TestQueue> = IoQueueTable::<TestQueue>::new();
+        let qds: Vec<QDesc> = (0..16).map(|_| ioqueue_table.alloc(TestQueue {}).unwrap()).collect();
+
+        // Slots should be reused, so the table does not grow past its peak occupancy.
+        for i in 0..1_000_000 {
+            let qd: QDesc = qds[i % qds.len()];
+            assert!(ioqueue_table.free(&qd).is_some());
+            assert_eq!(ioqueue_table.alloc(TestQueue {}).unwrap(), qd);
+        }
+        assert_eq!(ioqueue_table.stats().used, qds.len());
+        assert_eq!(ioqueue_table.stats().slots, qds.len());
+
+        for qd in qds.iter() {
+            assert!(ioqueue_table.free(qd).is_some());
+        }
+        assert_eq!(ioqueue_table.stats().used, 0);
+    }
+
+    #[test]
+    fn max_queues() {
+        let mut ioqueue_table: IoQueueTable<TestQueue> = IoQueueTable::<TestQueue>::new();
+        ioqueue_table.set_max_queues(Some(2));
+        let qd: QDesc = ioqueue_table.alloc(TestQueue {}).unwrap();
+        let dup_qd: QDesc = ioqueue_table.dup(&qd).unwrap();
+
+        // Both new queues and duplicates count against the limit.
+        assert_eq!(
+            ioqueue_table.alloc(TestQueue {}).err().map(|e| e.errno),
+            Some(libc::EMFILE)
+        );
+        assert_eq!(ioqueue_table.dup(&qd).err().map(|e| e.errno), Some(libc::EMFILE));
+
+        // Closing a descriptor makes room for a new one.
+        assert_eq!(ioqueue_table.close_duplicate(&dup_qd), Some(true));
+        let other_qd: QDesc = ioqueue_table.alloc(TestQueue {}).unwrap();
+        assert_eq!(other_qd, dup_qd);
+        assert_eq!(
+            ioqueue_table.alloc(TestQueue {}).err().map(|e| e.errno),
+            Some(libc::EMFILE)
+        );
+
+        ioqueue_table.set_max_queues(None);
+        assert!(ioqueue_table.alloc(TestQueue {}).is_ok());
+    }
+
+    #[test]
+    fn generation() {
+        let mut ioqueue_table: IoQueueTable<TestQueue> = IoQueueTable::<TestQueue>::new();
+        let qd: QDesc = ioqueue_table.alloc(TestQueue {}).unwrap();
+        let generation: u32 = ioqueue_table.get_generation(&qd).unwrap();
+        assert!(ioqueue_table.free(&qd).is_some());
+        assert_eq!(ioqueue_table.get_generation(&qd), None);
+
+        // The slot is reused, but under a different generation.
+        assert_eq!(ioqueue_table.alloc(TestQueue {}).unwrap(), qd);
+        assert_ne!(ioqueue_table.get_generation(&qd), Some(generation));
+    }
+
+    /// Tests that live descriptors never alias, under a random sequence of allocations, duplications, and closes.
+    #[test]
+    fn no_aliasing() {
+        let mut rng: SmallRng = SmallRng::seed_from_u64(42);
+        for _ in 0..64 {
+            let mut ioqueue_table: IoQueueTable<TaggedQueue> = IoQueueTable::<TaggedQueue>::new();
+            // Live descriptors and the tag of the queue that each one should resolve to.
+            let mut live: HashMap<QDesc, usize> = HashMap::new();
+            let mut next_tag: usize = 0;
+
+            for _ in 0..1024 {
+                match rng.gen_range(0..3) {
+                    0 => {
+                        let qd: QDesc = ioqueue_table.alloc(TaggedQueue(next_tag)).unwrap();
+                        assert!(live.insert(qd, next_tag).is_none(), "descriptor aliases a live one");
+                        next_tag += 1;
+                    },
+                    1 if !live.is_empty() => {
+                        let qds: Vec<QDesc> = live.keys().copied().collect();
+                        let qd: QDesc = qds[rng.gen_range(0..qds.len())];
+                        let dup_qd: QDesc = ioqueue_table.dup(&qd).unwrap();
+                        let target: usize = live[&qd];
+                        assert!(
+                            live.insert(dup_qd, target).is_none(),
+                            "duplicate aliases a live descriptor"
+                        );
+                    },
+                    2 if !live.is_empty() => {
+                        let qds: Vec<QDesc> = live.keys().copied().collect();
+                        let qd: QDesc = qds[rng.gen_range(0..qds.len())];
+                        let target: usize = live.remove(&qd).unwrap();
+                        if ioqueue_table.close_duplicate(&qd) == Some(false) {
+                            assert!(ioqueue_table.free(&qd).is_some());
+                            assert!(!live.values().any(|t| *t == target));
+                        }
+                    },
+                    _ => (),
+                }
+
+                // Every live descriptor should resolve to the queue it was opened on.
+                for (qd, tag) in live.iter() {
+                    assert_eq!(ioqueue_table.get(qd).map(|queue| queue.0), Some(*tag));
+                }
+                assert_eq!(ioqueue_table.queues().len(), live.len());
+            }
+        }
+    }
 }
@@ -5,6 +5,8 @@
 // Imports
 //==============================================================================
 
+#[cfg(feature = "dhcp")]
+use crate::inetstack::protocols::dhcp::DhcpLease;
 use crate::runtime::{
     fail::Fail,
     memory::DemiBuffer,
@@ -24,8 +26,13 @@ pub enum OperationResult {
     Connect,
     Accept((QDesc, SocketAddrV4)),
     Push,
-    Pop(Option<SocketAddrV4>, DemiBuffer),
+    /// Data that was popped, the address of the remote peer (for datagrams), and whether the end of the stream was
+    /// reached. The data comes before the end of the stream, so it may be empty or carry the final bytes.
+    Pop(Option<SocketAddrV4>, DemiBuffer, bool),
+    PopBatch(Vec<(Option<SocketAddrV4>, DemiBuffer)>),
     Close,
+    #[cfg(feature = "dhcp")]
+    Dhcp(DhcpLease),
     Failed(Fail),
 }
 
@@ -40,7 +47,10 @@ impl fmt::Debug for OperationResult {
             OperationResult::Accept(..) => write!(f, "Accept"),
             OperationResult::Push => write!(f, "Push"),
             OperationResult::Pop(..) => write!(f, "Pop"),
+            OperationResult::PopBatch(ref msgs) => write!(f, "PopBatch({:?})", msgs.len()),
             OperationResult::Close => write!(f, "Close"),
+            #[cfg(feature = "dhcp")]
+            OperationResult::Dhcp(ref lease) => write!(f, "Dhcp({:?})", lease.ip),
             OperationResult::Failed(ref e) => write!(f, "Failed({:?})", e),
         }
     }
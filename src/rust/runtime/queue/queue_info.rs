@@ -0,0 +1,74 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::{
+    QDesc,
+    QType,
+};
+use ::std::{
+    net::SocketAddrV4,
+    time::Duration,
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// State of the socket or pipe behind an I/O queue.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum QueueState {
+    /// Socket that is neither bound nor connected.
+    Unbound,
+    /// Socket that is bound to a local address.
+    Bound,
+    /// Socket that accepts incoming connections.
+    Listening,
+    /// Socket whose connection is being established.
+    Connecting,
+    /// Socket or pipe that transfers data.
+    Established,
+    /// Socket or pipe that is shutting down.
+    Closing,
+}
+
+/// Snapshot of an I/O queue, as reported by queue introspection.
+#[derive(Debug, Clone)]
+pub struct QueueInfo {
+    /// I/O queue descriptor.
+    pub qd: QDesc,
+    /// Type of the I/O queue.
+    pub qtype: QType,
+    /// State of the socket or pipe, if the backend tracks it.
+    pub state: Option<QueueState>,
+    /// Local address, if the queue is bound to one.
+    pub local: Option<SocketAddrV4>,
+    /// Remote address, if the queue is connected to one.
+    pub remote: Option<SocketAddrV4>,
+    /// Number of operations issued on the queue that have not completed yet.
+    pub pending_ops: usize,
+    /// Time elapsed since the queue was created.
+    pub age: Duration,
+}
+
+//==============================================================================
+// Associate Functions
+//==============================================================================
+
+impl QueueInfo {
+    /// Creates a snapshot with no backend-specific details filled in.
+    pub fn new(qd: QDesc, qtype: QType, age: Duration) -> Self {
+        Self {
+            qd,
+            qtype,
+            state: None,
+            local: None,
+            remote: None,
+            pending_ops: 0,
+            age,
+        }
+    }
+}
@@ -5,11 +5,17 @@
 // Imports
 //==============================================================================
 
-use crate::collections::intrusive::pairing_heap::{
-    HeapNode,
-    PairingHeap,
+use crate::{
+    collections::intrusive::pairing_heap::{
+        HeapNode,
+        PairingHeap,
+    },
+    runtime::fail::Fail,
+};
+use ::futures::{
+    future::FusedFuture,
+    FutureExt,
 };
-use ::futures::future::FusedFuture;
 use ::std::{
     cell::RefCell,
     future::Future,
@@ -63,6 +69,8 @@ struct TimerInner {
 
 pub struct Timer<P: TimerPtr> {
     inner: RefCell<TimerInner>,
+    /// Time at which the timer was created.
+    origin: Instant,
     _marker: PhantomData<P>,
 }
 
@@ -86,6 +94,7 @@ impl<P: TimerPtr> Timer<P> {
         };
         Self {
             inner: RefCell::new(inner),
+            origin: now,
             _marker: PhantomData,
         }
     }
@@ -115,6 +124,11 @@ impl<P: TimerPtr> Timer<P> {
         self.inner.borrow().now
     }
 
+    /// Returns how much time passed on this timer since it was created.
+    pub fn elapsed(&self) -> Duration {
+        self.now() - self.origin
+    }
+
     pub fn wait(&self, ptr: P, timeout: Duration) -> WaitFuture<P> {
         self.wait_until(ptr, self.now() + timeout)
     }
@@ -132,6 +146,28 @@ impl<P: TimerPtr> Timer<P> {
     }
 }
 
+impl TimerRc {
+    /// Runs `future` until it completes or until `timeout` expires, whichever comes first. In the latter case, this
+    /// fails with `ETIMEDOUT`. If both happen at the same time, the result of `future` wins. The timeout starts when
+    /// this function is called, not when the returned future is first polled.
+    pub fn timeout<T, F: Future<Output = Result<T, Fail>>>(
+        &self,
+        future: F,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<T, Fail>> {
+        let timer: WaitFuture<TimerRc> = self.wait(self.clone(), timeout);
+        async move {
+            let future = future.fuse();
+            futures::pin_mut!(future);
+            futures::pin_mut!(timer);
+            futures::select_biased! {
+                result = future => result,
+                _ = timer => Err(Fail::new(libc::ETIMEDOUT, "operation timed out")),
+            }
+        }
+    }
+}
+
 //==============================================================================
 // Trait Implementations
 //==============================================================================
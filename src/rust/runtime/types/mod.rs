@@ -20,6 +20,7 @@ pub use self::{
         demi_opcode_t,
         demi_qr_value_t,
         demi_qresult_t,
+        DEMI_QR_EOF,
     },
     queue::demi_qtoken_t,
 };
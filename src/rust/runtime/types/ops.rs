@@ -21,7 +21,7 @@ use crate::{
 
 /// Operation Code
 #[repr(u32)]
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum demi_opcode_t {
     DEMI_OPC_INVALID = 0,
     DEMI_OPC_PUSH,
@@ -46,6 +46,9 @@ pub union demi_qr_value_t {
     pub ares: demi_accept_result_t,
 }
 
+/// Flag of a pop that reached the end of the stream, so no more data follows.
+pub const DEMI_QR_EOF: u64 = 1 << 0;
+
 /// Result
 #[repr(C)]
 pub struct demi_qresult_t {
@@ -54,6 +57,17 @@ pub struct demi_qresult_t {
     pub qr_qt: demi_qtoken_t,
     pub qr_ret: i64,
     pub qr_value: demi_qr_value_t,
+    /// Flags of the completed operation, such as [DEMI_QR_EOF].
+    pub qr_flags: u64,
+    /// When the operation started, in nanoseconds since a process-wide origin of a monotonic clock. Zero if operation
+    /// timing is disabled.
+    pub qr_start_ns: u64,
+    /// When the operation completed, in nanoseconds since the same origin as `qr_start_ns`. Zero if operation timing
+    /// is disabled.
+    pub qr_end_ns: u64,
+    /// When the data that a pop operation returns was received, in nanoseconds since the same origin as
+    /// `qr_start_ns`. Zero for other operations, and for libOSes that do not stamp received data.
+    pub qr_rx_ns: u64,
 }
 
 #[cfg(test)]
@@ -99,10 +113,26 @@ mod test {
         const QR_RET_SIZE: usize = 8;
         // Size of a demi_qr_value_t structure.
         const QR_VALUE_SIZE: usize = mem::size_of::<demi_qr_value_t>();
+        // Size of a u64.
+        const QR_FLAGS_SIZE: usize = 8;
+        // Size of a u64.
+        const QR_START_NS_SIZE: usize = 8;
+        // Size of a u64.
+        const QR_END_NS_SIZE: usize = 8;
+        // Size of a u64.
+        const QR_RX_NS_SIZE: usize = 8;
         // Size of a demi_qresult_t structure.
         crate::ensure_eq!(
             mem::size_of::<demi_qresult_t>(),
-            QR_OPCODE_SIZE + QR_QD_SIZE + QR_QT_SIZE + QR_RET_SIZE + QR_VALUE_SIZE
+            QR_OPCODE_SIZE
+                + QR_QD_SIZE
+                + QR_QT_SIZE
+                + QR_RET_SIZE
+                + QR_VALUE_SIZE
+                + QR_FLAGS_SIZE
+                + QR_START_NS_SIZE
+                + QR_END_NS_SIZE
+                + QR_RX_NS_SIZE
         );
         Ok(())
     }
@@ -9,6 +9,7 @@ use crate::{
     runtime::fail::Fail,
     scheduler::{
         page::WakerPageRef,
+        task::TaskTiming,
         waker64::WAKER_BIT_LENGTH,
     },
 };
@@ -37,6 +38,10 @@ pub struct TaskHandle {
     index: usize,
     /// Reference to this task's status bits.
     page: WakerPageRef,
+    /// Scheduling priority of this task (lower values run first).
+    priority: u8,
+    /// Timestamps of all tasks in the scheduler, indexed by the offset of the task in the slab.
+    timings: Rc<RefCell<Vec<Option<TaskTiming>>>>,
 }
 
 /// Yield Handle
@@ -55,8 +60,20 @@ pub struct YielderHandle {
 /// Associate Functions for Task Handlers
 impl TaskHandle {
     /// Creates a new Task Handle.
-    pub fn new(task_id: u64, index: usize, page: WakerPageRef) -> Self {
-        Self { task_id, index, page }
+    pub fn new(
+        task_id: u64,
+        index: usize,
+        page: WakerPageRef,
+        priority: u8,
+        timings: Rc<RefCell<Vec<Option<TaskTiming>>>>,
+    ) -> Self {
+        Self {
+            task_id,
+            index,
+            page,
+            priority,
+            timings,
+        }
     }
 
     /// Queries whether or not the coroutine in the Task has completed.
@@ -70,6 +87,17 @@ impl TaskHandle {
         self.task_id
     }
 
+    /// Returns the scheduling priority of the target task.
+    pub fn get_priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// Returns the timestamps of the target task, as the scheduler currently has them. Thus, the completion time shows
+    /// up as soon as the task completes. This is only meaningful until the task is removed from the scheduler.
+    pub fn get_timing(&self) -> Option<TaskTiming> {
+        self.timings.borrow().get(self.index).copied().flatten()
+    }
+
     /// Removes the task from the scheduler and keeps it from running again.
     pub fn deschedule(&mut self) {
         let subpage_ix: usize = self.index & (WAKER_BIT_LENGTH - 1);
@@ -50,6 +50,7 @@ pub use self::{
     scheduler::Scheduler,
     task::{
         Task,
+        TaskTiming,
         TaskWithResult,
     },
     yielder::Yielder,
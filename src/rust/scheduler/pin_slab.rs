@@ -96,6 +96,26 @@ impl<T> PinSlab<T> {
         }
     }
 
+    /// Construct a new, empty [PinSlab] with enough slots allocated up front to hold `capacity` entries.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut slab: Self = Self::new();
+        let mut allocated: usize = 0;
+        for len in slot_sizes() {
+            if allocated >= capacity {
+                break;
+            }
+            let slot: NonNull<Entry<T>> = slab.new_slot(len);
+            slab.slots.push(slot);
+            allocated += len;
+        }
+        slab
+    }
+
+    /// Number of entries currently held in the slab.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
     /// Insert a value into the pin slab.
     pub fn insert(&mut self, val: T) -> Option<usize> {
         let key: usize = self.next;
@@ -443,6 +463,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn with_capacity() -> Result<()> {
+        let mut slab: super::PinSlab<u64> = super::PinSlab::with_capacity(100);
+        // 16 + 16 + 32 + 64 entries.
+        crate::ensure_eq!(slab.slots.len(), 4);
+
+        for i in 0..128 {
+            if slab.insert(i).is_none() {
+                anyhow::bail!("insert() failed");
+            }
+        }
+        crate::ensure_eq!(slab.slots.len(), 4);
+        crate::ensure_eq!(slab.len(), 128);
+
+        Ok(())
+    }
+
     #[test]
     fn remove_unpin() -> Result<()> {
         let mut slab: super::PinSlab<i32> = super::PinSlab::new();
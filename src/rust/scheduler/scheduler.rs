@@ -11,18 +11,22 @@
 // Imports
 //======================================================================================================================
 
-use crate::scheduler::{
-    page::{
-        WakerPageRef,
-        WakerRef,
-    },
-    pin_slab::PinSlab,
-    waker64::{
-        WAKER_BIT_LENGTH,
-        WAKER_BIT_LENGTH_SHIFT,
+use crate::{
+    runtime::timer::TimerRc,
+    scheduler::{
+        page::{
+            WakerPageRef,
+            WakerRef,
+        },
+        pin_slab::PinSlab,
+        waker64::{
+            WAKER_BIT_LENGTH,
+            WAKER_BIT_LENGTH_SHIFT,
+        },
+        Task,
+        TaskHandle,
+        TaskTiming,
     },
-    Task,
-    TaskHandle,
 };
 use ::bit_iter::BitIter;
 use ::rand::{
@@ -46,6 +50,7 @@ use ::std::{
         Poll,
         Waker,
     },
+    time::Instant,
 };
 
 //======================================================================================================================
@@ -59,6 +64,9 @@ const SCHEDULER_SEED: u64 = 42;
 const MAX_NUM_TASKS: usize = 16000;
 const MAX_RETRIES_TASK_ID_ALLOC: usize = 500;
 
+/// Priority of tasks that are inserted without an explicit priority. This is the highest priority.
+pub const DEFAULT_TASK_PRIORITY: u8 = 0;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -74,6 +82,21 @@ pub struct Scheduler {
     pages: Rc<RefCell<Vec<WakerPageRef>>>,
     /// Small random number generator for tokens.
     id_gen: Rc<RefCell<SmallRng>>,
+    /// Scheduling priority of each task, indexed by the offset of the task in the slab.
+    priorities: Rc<RefCell<Vec<u8>>>,
+    /// Notified tasks of each page that have a lower than default priority, and thus wait for the tasks of higher
+    /// priorities to be polled first. This is only used within [Scheduler::poll], but kept around so that polling does
+    /// not allocate.
+    deferred: Rc<RefCell<Vec<u64>>>,
+    /// Dropped tasks of each page, which are released once all notified tasks were polled. Like `deferred`, this is
+    /// kept around so that polling does not allocate.
+    dropped: Rc<RefCell<Vec<u64>>>,
+    /// Timestamps of each task, indexed by the offset of the task in the slab.
+    timings: Rc<RefCell<Vec<Option<TaskTiming>>>>,
+    /// Timer of the runtime that tasks are timed against, if the runtime has one.
+    clock: Rc<RefCell<Option<TimerRc>>>,
+    /// Maximum number of tasks, if the scheduler was preallocated.
+    capacity: Option<usize>,
 }
 
 //======================================================================================================================
@@ -82,6 +105,36 @@ pub struct Scheduler {
 
 /// Associate Functions for Scheduler
 impl Scheduler {
+    /// Creates a scheduler with room for `capacity` tasks allocated up front. Inserting tasks does not allocate
+    /// scheduler memory as long as there are at most `capacity` of them, and fails past that.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let num_pages: usize = (capacity + WAKER_BIT_LENGTH - 1) >> WAKER_BIT_LENGTH_SHIFT;
+        Self {
+            tasks: Rc::new(RefCell::new(PinSlab::with_capacity(capacity))),
+            task_ids: Rc::new(RefCell::new(HashMap::<u64, usize>::with_capacity(capacity))),
+            pages: Rc::new(RefCell::new((0..num_pages).map(|_| WakerPageRef::default()).collect())),
+            priorities: Rc::new(RefCell::new(vec![DEFAULT_TASK_PRIORITY; capacity])),
+            deferred: Rc::new(RefCell::new(vec![0; num_pages])),
+            dropped: Rc::new(RefCell::new(vec![0; num_pages])),
+            timings: Rc::new(RefCell::new(vec![None; capacity])),
+            capacity: Some(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// Times tasks against the timer of the runtime, instead of a monotonic clock.
+    pub fn set_clock(&self, clock: TimerRc) {
+        *self.clock.borrow_mut() = Some(clock);
+    }
+
+    /// Reads the clock that tasks are timed against.
+    fn now(&self) -> Instant {
+        match self.clock.borrow().as_ref() {
+            Some(clock) => clock.now(),
+            None => Instant::now(),
+        }
+    }
+
     /// Given a handle to a task, remove it from the scheduler
     pub fn remove(&self, handle: &TaskHandle) -> Option<Box<dyn Task>> {
         let pages: Ref<Vec<WakerPageRef>> = self.pages.borrow();
@@ -124,17 +177,38 @@ impl Scheduler {
             let (pages_ix, _) = self.get_page_indexes(index);
             &pages[pages_ix]
         };
-        let handle: TaskHandle = TaskHandle::new(task_id, index, page.clone());
+        let priority: u8 = self.priorities.borrow()[index];
+        let handle: TaskHandle = TaskHandle::new(task_id, index, page.clone(), priority, self.timings.clone());
         Some(handle)
     }
 
     /// Insert a new task into our scheduler returning a handle corresponding to it.
     pub fn insert<F: Task>(&self, future: F) -> Option<TaskHandle> {
+        self.spawn_with_priority(DEFAULT_TASK_PRIORITY, future)
+    }
+
+    /// Insert a new task with a given priority into our scheduler returning a handle corresponding to it. When
+    /// polling, all ready tasks of priority 0 run before those of priority 1, and so on.
+    pub fn spawn_with_priority<F: Task>(&self, priority: u8, future: F) -> Option<TaskHandle> {
+        self.spawn_boxed_with_priority(priority, Box::new(future))
+    }
+
+    /// Insert an already boxed task with a given priority into our scheduler returning a handle corresponding to it.
+    /// On a preallocated scheduler, this fails if the scheduler is full.
+    pub fn spawn_boxed_with_priority(&self, priority: u8, task: Box<dyn Task>) -> Option<TaskHandle> {
         let mut pages: RefMut<Vec<WakerPageRef>> = self.pages.borrow_mut();
         let mut id_gen: RefMut<SmallRng> = self.id_gen.borrow_mut();
-        let task_name: String = future.get_name();
         // Allocate an offset into the slab and a token for identifying the task.
-        let index: usize = self.tasks.borrow_mut().insert(Box::new(future))?;
+        let index: usize = {
+            let mut tasks: RefMut<PinSlab<Box<dyn Task>>> = self.tasks.borrow_mut();
+            if let Some(capacity) = self.capacity {
+                if tasks.len() >= capacity {
+                    warn!("insert(): scheduler is full (capacity={:?})", capacity);
+                    return None;
+                }
+            }
+            tasks.insert(task)?
+        };
 
         // Generate a new id. If the id is currently in use, keep generating until we find an unused id.
         let mut task_ids: RefMut<HashMap<u64, usize>> = self.task_ids.borrow_mut();
@@ -154,18 +228,49 @@ impl Scheduler {
             panic!("Could not find a valid task id");
         };
 
-        trace!("insert(): name={:?}, id={:?}, index={:?}", task_name, task_id, index);
+        // Only fetch the name if tracing, as doing so allocates.
+        trace!(
+            "insert(): name={:?}, id={:?}, index={:?}, priority={:?}",
+            self.tasks.borrow().get(index).map(|task| task.get_name()),
+            task_id,
+            index,
+            priority
+        );
+
+        // Record the priority of this task.
+        let mut priorities: RefMut<Vec<u8>> = self.priorities.borrow_mut();
+        if index >= priorities.len() {
+            priorities.resize(index + 1, DEFAULT_TASK_PRIORITY);
+        }
+        priorities[index] = priority;
+        drop(priorities);
+
+        // Record when this task was created.
+        let mut timings: RefMut<Vec<Option<TaskTiming>>> = self.timings.borrow_mut();
+        if index >= timings.len() {
+            timings.resize(index + 1, None);
+        }
+        timings[index] = TaskTiming::start(self.now());
+        drop(timings);
 
         // Add a new page to hold this future's status if the current page is filled.
         while index >= pages.len() << WAKER_BIT_LENGTH_SHIFT {
             pages.push(WakerPageRef::default());
+            self.deferred.borrow_mut().push(0);
+            self.dropped.borrow_mut().push(0);
         }
         let (page, subpage_ix): (&WakerPageRef, usize) = {
             let (pages_ix, subpage_ix) = self.get_page_indexes(index);
             (&pages[pages_ix], subpage_ix)
         };
         page.initialize(subpage_ix);
-        Some(TaskHandle::new(task_id, index, page.clone()))
+        Some(TaskHandle::new(
+            task_id,
+            index,
+            page.clone(),
+            priority,
+            self.timings.clone(),
+        ))
     }
 
     /// Computes the page and page offset of a given task based on its total offset.
@@ -175,45 +280,57 @@ impl Scheduler {
 
     /// Poll all futures which are ready to run again. Tasks in our scheduler are notified when
     /// relevant data or events happen. The relevant event have callback function (the waker) which
-    /// they can invoke to notify the scheduler that future should be polled again.
+    /// they can invoke to notify the scheduler that future should be polled again. Ready tasks are
+    /// polled in priority order.
     pub fn poll(&self) {
-        let mut pages: RefMut<Vec<WakerPageRef>> = self.pages.borrow_mut();
-        let mut tasks: RefMut<PinSlab<Box<dyn Task>>> = self.tasks.borrow_mut();
+        let num_pages: usize = self.pages.borrow().len();
+        // Priorities below the default one that some notified task has, one bit per priority.
+        let mut ready_priorities: [u64; 4] = [0; 4];
 
-        // Iterate through pages.
-        for page_ix in 0..pages.len() {
+        // Iterate through pages, polling notified tasks of the default priority right away, as nothing can go before
+        // them. Tasks of lower priorities are deferred until we know about all notified tasks.
+        for page_ix in 0..num_pages {
             let (notified, dropped): (u64, u64) = {
+                let mut pages: RefMut<Vec<WakerPageRef>> = self.pages.borrow_mut();
                 let page: &mut WakerPageRef = &mut pages[page_ix];
                 (page.take_notified(), page.take_dropped())
             };
-            // There is some notified task in this page, so iterate through it.
-            if notified != 0 {
-                for subpage_ix in BitIter::from(notified) {
-                    // Handle notified tasks only.
-                    // Get future using our page indices and poll it!
-                    let ix: usize = (page_ix << WAKER_BIT_LENGTH_SHIFT) + subpage_ix;
-                    let waker: Waker = unsafe {
-                        let raw_waker: NonNull<u8> = pages[page_ix].into_raw_waker_ref(subpage_ix);
-                        Waker::from_raw(WakerRef::new(raw_waker).into())
-                    };
-                    let mut sub_ctx: Context = Context::from_waker(&waker);
-
-                    let pinned_ref: Pin<&mut Box<dyn Task>> = tasks.get_pin_mut(ix).unwrap();
-                    let pinned_ptr = unsafe { Pin::into_inner_unchecked(pinned_ref) as *mut _ };
-
-                    // Poll future.
-                    drop(pages);
-                    drop(tasks);
-                    let pinned_ref = unsafe { Pin::new_unchecked(&mut *pinned_ptr) };
-                    let poll_result: Poll<()> = Future::poll(pinned_ref, &mut sub_ctx);
-                    pages = self.pages.borrow_mut();
-                    tasks = self.tasks.borrow_mut();
-                    match poll_result {
-                        Poll::Ready(()) => pages[page_ix].mark_completed(subpage_ix),
-                        Poll::Pending => (),
+            self.dropped.borrow_mut()[page_ix] = dropped;
+            let mut deferred: u64 = 0;
+            for subpage_ix in BitIter::from(notified) {
+                let ix: usize = (page_ix << WAKER_BIT_LENGTH_SHIFT) + subpage_ix;
+                let priority: u8 = self.priorities.borrow()[ix];
+                if priority == DEFAULT_TASK_PRIORITY {
+                    self.poll_task(page_ix, subpage_ix);
+                } else {
+                    deferred |= 1 << subpage_ix;
+                    ready_priorities[(priority >> 6) as usize] |= 1 << (priority & 63);
+                }
+            }
+            self.deferred.borrow_mut()[page_ix] = deferred;
+        }
+
+        // Iterate through the priorities that have deferred tasks, from highest to lowest.
+        for (word_ix, word) in ready_priorities.into_iter().enumerate() {
+            for bit in BitIter::from(word) {
+                let priority: u8 = ((word_ix << 6) + bit) as u8;
+                for page_ix in 0..num_pages {
+                    let deferred: u64 = self.deferred.borrow()[page_ix];
+                    for subpage_ix in BitIter::from(deferred) {
+                        let ix: usize = (page_ix << WAKER_BIT_LENGTH_SHIFT) + subpage_ix;
+                        if self.priorities.borrow()[ix] == priority {
+                            self.poll_task(page_ix, subpage_ix);
+                        }
                     }
                 }
             }
+        }
+
+        // Iterate through pages.
+        let pages: Ref<Vec<WakerPageRef>> = self.pages.borrow();
+        let mut tasks: RefMut<PinSlab<Box<dyn Task>>> = self.tasks.borrow_mut();
+        for page_ix in 0..num_pages {
+            let dropped: u64 = self.dropped.borrow()[page_ix];
             // There is some dropped task in this page, so iterate through it.
             if dropped != 0 {
                 // Handle dropped tasks only.
@@ -240,6 +357,38 @@ impl Scheduler {
             }
         }
     }
+
+    /// Polls the task at the given page and page offset. The scheduler is not borrowed while the task runs, so that the
+    /// task may insert other tasks.
+    fn poll_task(&self, page_ix: usize, subpage_ix: usize) {
+        let ix: usize = (page_ix << WAKER_BIT_LENGTH_SHIFT) + subpage_ix;
+
+        // Get future using our page indices and poll it!
+        let waker: Waker = unsafe {
+            let raw_waker: NonNull<u8> = self.pages.borrow()[page_ix].into_raw_waker_ref(subpage_ix);
+            Waker::from_raw(WakerRef::new(raw_waker).into())
+        };
+        let mut sub_ctx: Context = Context::from_waker(&waker);
+
+        let pinned_ptr: *mut Box<dyn Task> = {
+            let mut tasks: RefMut<PinSlab<Box<dyn Task>>> = self.tasks.borrow_mut();
+            let pinned_ref: Pin<&mut Box<dyn Task>> = tasks.get_pin_mut(ix).unwrap();
+            unsafe { Pin::into_inner_unchecked(pinned_ref) as *mut _ }
+        };
+
+        // Poll future.
+        let pinned_ref = unsafe { Pin::new_unchecked(&mut *pinned_ptr) };
+        let poll_result: Poll<()> = Future::poll(pinned_ref, &mut sub_ctx);
+        match poll_result {
+            Poll::Ready(()) => {
+                if let Some(timing) = self.timings.borrow_mut()[ix].as_mut() {
+                    timing.completed = Some(self.now());
+                }
+                self.pages.borrow()[page_ix].mark_completed(subpage_ix)
+            },
+            Poll::Pending => (),
+        }
+    }
 }
 
 //======================================================================================================================
@@ -258,6 +407,12 @@ impl Default for Scheduler {
             id_gen: Rc::new(RefCell::new(SmallRng::seed_from_u64(SCHEDULER_SEED))),
             #[cfg(not(debug_assertions))]
             id_gen: Rc::new(RefCell::new(SmallRng::from_entropy())),
+            priorities: Rc::new(RefCell::new(vec![])),
+            deferred: Rc::new(RefCell::new(vec![])),
+            dropped: Rc::new(RefCell::new(vec![])),
+            timings: Rc::new(RefCell::new(vec![])),
+            clock: Rc::new(RefCell::new(None)),
+            capacity: None,
         }
     }
 }
@@ -268,22 +423,42 @@ impl Default for Scheduler {
 
 #[cfg(test)]
 mod tests {
-    use crate::scheduler::{
+    use crate::{
+        runtime::{
+            memory::counting_allocator::num_allocations,
+            timer::{
+                Timer,
+                TimerRc,
+            },
+        },
         scheduler::{
-            Scheduler,
-            TaskHandle,
+            scheduler::{
+                Scheduler,
+                TaskHandle,
+            },
+            task::{
+                Task,
+                TaskTiming,
+                TaskWithResult,
+            },
         },
-        task::TaskWithResult,
     };
     use ::anyhow::Result;
     use ::std::{
+        cell::RefCell,
         future::Future,
         pin::Pin,
+        rc::Rc,
         task::{
             Context,
             Poll,
             Waker,
         },
+        thread,
+        time::{
+            Duration,
+            Instant,
+        },
     };
     use ::test::{
         black_box,
@@ -402,6 +577,89 @@ mod tests {
         Ok(())
     }
 
+    /// Tests if a task records when it was created and when it completed, and if it stops doing so once timing is
+    /// disabled.
+    #[test]
+    fn scheduler_task_timing() -> Result<()> {
+        const DELAY: Duration = Duration::from_millis(10);
+        let scheduler: Scheduler = Scheduler::default();
+
+        // Insert a future that completes on the second poll, and delay that poll.
+        let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(1)));
+        let handle: TaskHandle = match scheduler.insert(task) {
+            Some(handle) => handle,
+            None => anyhow::bail!("insert() failed"),
+        };
+        crate::ensure_eq!(handle.get_timing().map(|timing| timing.completed), Some(None));
+        scheduler.poll();
+        thread::sleep(DELAY);
+        scheduler.poll();
+        crate::ensure_eq!(handle.has_completed(), true);
+
+        // A handle that was obtained before completion sees both timestamps.
+        let timing: TaskTiming = match handle.get_timing() {
+            Some(timing) => timing,
+            None => anyhow::bail!("task should have timestamps"),
+        };
+        match timing.elapsed() {
+            Some(elapsed) => crate::ensure_eq!(elapsed >= DELAY, true),
+            None => anyhow::bail!("task should have a completion timestamp"),
+        }
+        crate::ensure_eq!(
+            TaskTiming::to_nanos(timing.completed.unwrap()) >= TaskTiming::to_nanos(timing.created),
+            true
+        );
+
+        // Tasks that are inserted while timing is disabled carry no timestamps.
+        let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(0)));
+        TaskTiming::set_enabled(false);
+        let handle: Option<TaskHandle> = scheduler.insert(task);
+        TaskTiming::set_enabled(true);
+        let handle: TaskHandle = match handle {
+            Some(handle) => handle,
+            None => anyhow::bail!("insert() failed"),
+        };
+        scheduler.poll();
+        crate::ensure_eq!(handle.has_completed(), true);
+        match scheduler.from_task_id(handle.get_task_id()) {
+            Some(handle) => crate::ensure_eq!(handle.get_timing(), None),
+            None => anyhow::bail!("from_task_id() failed"),
+        }
+
+        Ok(())
+    }
+
+    /// Tests if tasks are timed against the timer of the runtime, once the scheduler is given one.
+    #[test]
+    fn scheduler_task_timing_follows_clock() -> Result<()> {
+        const DELAY: Duration = Duration::from_secs(3);
+        let now: Instant = Instant::now();
+        let clock: TimerRc = TimerRc(Rc::new(Timer::new(now)));
+        let scheduler: Scheduler = Scheduler::default();
+        scheduler.set_clock(clock.clone());
+
+        // Insert a future that completes on the second poll, and advance the timer in between.
+        let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(1)));
+        let handle: TaskHandle = match scheduler.insert(task) {
+            Some(handle) => handle,
+            None => anyhow::bail!("insert() failed"),
+        };
+        scheduler.poll();
+        clock.advance_clock(now + DELAY);
+        scheduler.poll();
+        crate::ensure_eq!(handle.has_completed(), true);
+
+        crate::ensure_eq!(
+            handle.get_timing(),
+            Some(TaskTiming {
+                created: now,
+                completed: Some(now + DELAY),
+            })
+        );
+
+        Ok(())
+    }
+
     /// Tests if consecutive tasks are not assigned the same task id.
     #[test]
     fn test_scheduler_task_ids() -> Result<()> {
@@ -433,6 +691,87 @@ mod tests {
         Ok(())
     }
 
+    /// Tests if high-priority tasks run before low-priority ones within a single poll.
+    #[test]
+    fn test_scheduler_priorities() -> Result<()> {
+        let scheduler: Scheduler = Scheduler::default();
+        let completed: Rc<RefCell<Vec<usize>>> = Rc::new(RefCell::new(Vec::new()));
+
+        // Insert low-priority tasks first, so that they come first in the scheduler.
+        let mut handles: Vec<TaskHandle> = Vec::<TaskHandle>::with_capacity(101);
+        for id in 0..101 {
+            let completed: Rc<RefCell<Vec<usize>>> = completed.clone();
+            let task: DummyTask = DummyTask::new(
+                String::from("testing"),
+                Box::pin(async move { completed.borrow_mut().push(id) }),
+            );
+            let priority: u8 = if id == 100 { 0 } else { 1 };
+            let handle: TaskHandle = match scheduler.spawn_with_priority(priority, task) {
+                Some(handle) => handle,
+                None => anyhow::bail!("spawn_with_priority() failed"),
+            };
+            crate::ensure_eq!(handle.get_priority(), priority);
+            handles.push(handle);
+        }
+
+        // All tasks are ready, so a single poll should complete them all, starting with the high-priority one.
+        scheduler.poll();
+
+        for handle in &handles {
+            crate::ensure_eq!(handle.has_completed(), true);
+        }
+        crate::ensure_eq!(completed.borrow().len(), 101);
+        crate::ensure_eq!(completed.borrow()[0], 100);
+
+        Ok(())
+    }
+
+    /// Tests if a preallocated scheduler inserts tasks up to its capacity without allocating, and refuses more.
+    #[test]
+    fn test_scheduler_with_capacity() -> Result<()> {
+        const CAPACITY: usize = 100;
+        let scheduler: Scheduler = Scheduler::with_capacity(CAPACITY);
+
+        // Box tasks beforehand, as that allocation belongs to the caller.
+        let mut tasks: Vec<Box<dyn Task>> = (0..=CAPACITY)
+            .map(|_| -> Box<dyn Task> {
+                Box::new(DummyTask::new(
+                    String::from("testing"),
+                    Box::pin(DummyCoroutine::new(0)),
+                ))
+            })
+            .collect();
+        let mut handles: Vec<TaskHandle> = Vec::<TaskHandle>::with_capacity(CAPACITY);
+        let last_task: Box<dyn Task> = match tasks.pop() {
+            Some(task) => task,
+            None => anyhow::bail!("there should be one more task than the capacity"),
+        };
+
+        // Fill the scheduler.
+        let num_allocations_before: usize = num_allocations();
+        for task in tasks.drain(..) {
+            match scheduler.spawn_boxed_with_priority(0, task) {
+                Some(handle) => handles.push(handle),
+                None => anyhow::bail!("spawn_boxed_with_priority() failed"),
+            }
+        }
+        crate::ensure_eq!(num_allocations(), num_allocations_before);
+
+        // One more task does not fit.
+        crate::ensure_eq!(scheduler.spawn_boxed_with_priority(0, last_task).is_none(), true);
+
+        // Tasks can be inserted again once others are taken out.
+        let handle: TaskHandle = match handles.pop() {
+            Some(handle) => handle,
+            None => anyhow::bail!("there should be a handle"),
+        };
+        crate::ensure_eq!(scheduler.remove(&handle).is_some(), true);
+        let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(0)));
+        crate::ensure_eq!(scheduler.insert(task).is_some(), true);
+
+        Ok(())
+    }
+
     #[bench]
     fn bench_scheduler_poll(b: &mut Bencher) {
         let scheduler: Scheduler = Scheduler::default();
@@ -7,14 +7,32 @@
 
 use ::std::{
     any::Any,
+    cell::Cell,
     future::Future,
     pin::Pin,
+    sync::OnceLock,
     task::{
         Context,
         Poll,
     },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
+//==============================================================================
+// Static Variables
+//==============================================================================
+
+thread_local! {
+    /// Whether tasks created on this thread record when they start and complete.
+    static TIMING_ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Origin of the clock that task timestamps are reported against, set upon first use.
+static TIMING_ORIGIN: OnceLock<Instant> = OnceLock::new();
+
 //==============================================================================
 // Structures
 //==============================================================================
@@ -26,6 +44,16 @@ pub trait Task: Future<Output = ()> + Unpin + Any {
     fn as_any(self: Box<Self>) -> Box<dyn Any>;
 }
 
+/// Timestamps of a task, taken from the timer of the runtime that the task runs on, or from a monotonic clock if the
+/// runtime has none.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TaskTiming {
+    /// When the task was inserted into the scheduler, which is when its queue token was handed out.
+    pub created: Instant,
+    /// When the coroutine of the task completed, if it did so.
+    pub completed: Option<Instant>,
+}
+
 /// This trait is just for convenience of having defined associated types because we cannot define them on the struct
 /// impl as this feature is unstable in Rust.
 pub trait TaskWith: From<Box<dyn Any>> {
@@ -64,6 +92,46 @@ impl<R: Unpin + Clone + Any> TaskWithResult<R> {
     }
 }
 
+/// Associate Functions for TaskTiming.
+impl TaskTiming {
+    /// Enables or disables timing for the tasks that are inserted into a scheduler on the calling thread from now on.
+    pub fn set_enabled(enabled: bool) {
+        TIMING_ENABLED.with(|timing| timing.set(enabled));
+    }
+
+    /// Starts timing a task that is created at `now`, unless timing is disabled on the calling thread.
+    pub(super) fn start(now: Instant) -> Option<Self> {
+        if !TIMING_ENABLED.with(|enabled| enabled.get()) {
+            return None;
+        }
+        // Make sure that the origin of the clock precedes any timestamp.
+        TIMING_ORIGIN.get_or_init(|| now);
+        Some(Self {
+            created: now,
+            completed: None,
+        })
+    }
+
+    /// Returns how long the task took to complete, or None if it has not completed yet.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.completed.map(|completed| completed.duration_since(self.created))
+    }
+
+    /// Converts a timestamp into nanoseconds since a process-wide origin, as reported in operation results. Zero is
+    /// left out, so that it may stand for a missing timestamp.
+    pub fn to_nanos(instant: Instant) -> u64 {
+        let origin: Instant = *TIMING_ORIGIN.get_or_init(Instant::now);
+        (instant.saturating_duration_since(origin).as_nanos() as u64).max(1)
+    }
+
+    /// Converts nanoseconds since the process-wide origin back into a timestamp. This is the inverse of
+    /// [TaskTiming::to_nanos].
+    pub fn from_nanos(nanos: u64) -> Instant {
+        let origin: Instant = *TIMING_ORIGIN.get_or_init(Instant::now);
+        origin + Duration::from_nanos(nanos)
+    }
+}
+
 //==============================================================================
 // Trait Implementations
 //==============================================================================
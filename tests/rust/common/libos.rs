@@ -27,10 +27,7 @@ use crossbeam_channel::{
     Receiver,
     Sender,
 };
-use demikernel::{
-    runtime::network::consts::RECEIVE_BATCH_SIZE,
-    scheduler::scheduler::Scheduler,
-};
+use demikernel::scheduler::scheduler::Scheduler;
 use std::{
     collections::HashMap,
     net::Ipv4Addr,
@@ -59,7 +56,7 @@ impl DummyLibOS {
         tx: Sender<DemiBuffer>,
         rx: Receiver<DemiBuffer>,
         arp: HashMap<Ipv4Addr, MacAddress>,
-    ) -> Result<InetStack<RECEIVE_BATCH_SIZE>, Fail> {
+    ) -> Result<InetStack, Fail> {
         let now: Instant = Instant::now();
         let rt: Rc<DummyRuntime> = Rc::new(DummyRuntime::new(now, rx, tx));
         let arp_options: ArpConfig = ArpConfig::new(
@@ -85,6 +82,7 @@ impl DummyLibOS {
             tcp_config,
             rng_seed,
             arp_options,
+            None,
         )
     }
 
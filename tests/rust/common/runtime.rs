@@ -5,14 +5,15 @@
 // Imports
 //==============================================================================
 
-use ::arrayvec::ArrayVec;
 use ::crossbeam_channel;
 use ::demikernel::{
     runtime::{
         memory::DemiBuffer,
         network::{
+            types::Ipv4IdGenerator,
             NetworkRuntime,
             PacketBuf,
+            ReceiveBatch,
         },
         timer::{
             Timer,
@@ -21,6 +22,10 @@ use ::demikernel::{
     },
     scheduler::scheduler::Scheduler,
 };
+use ::rand::{
+    prelude::SmallRng,
+    SeedableRng,
+};
 use ::std::{
     cell::RefCell,
     rc::Rc,
@@ -45,6 +50,8 @@ struct SharedDummyRuntime {
 pub struct DummyRuntime {
     /// Shared Member Fields
     inner: Rc<RefCell<SharedDummyRuntime>>,
+    /// Identification of Outgoing IPv4 Datagrams
+    ipv4_id: Ipv4IdGenerator,
     pub scheduler: Scheduler,
     pub clock: TimerRc,
 }
@@ -64,6 +71,7 @@ impl DummyRuntime {
         let inner = SharedDummyRuntime { incoming, outgoing };
         Self {
             inner: Rc::new(RefCell::new(inner)),
+            ipv4_id: Ipv4IdGenerator::new(&mut SmallRng::from_entropy()),
             scheduler: Scheduler::default(),
             clock: TimerRc(Rc::new(Timer::new(now))),
         }
@@ -75,7 +83,7 @@ impl DummyRuntime {
 //==============================================================================
 
 /// Network Runtime Trait Implementation for Dummy Runtime
-impl<const N: usize> NetworkRuntime<N> for DummyRuntime {
+impl NetworkRuntime for DummyRuntime {
     fn transmit(&self, pkt: Box<dyn PacketBuf>) {
         let header_size: usize = pkt.header_size();
         let body_size: usize = pkt.body_size();
@@ -92,8 +100,12 @@ impl<const N: usize> NetworkRuntime<N> for DummyRuntime {
         self.inner.borrow_mut().outgoing.try_send(buf).unwrap();
     }
 
-    fn receive(&self) -> ArrayVec<DemiBuffer, N> {
-        let mut out = ArrayVec::new();
+    fn next_ipv4_id(&self) -> u16 {
+        self.ipv4_id.next_id()
+    }
+
+    fn receive(&self) -> ReceiveBatch {
+        let mut out = ReceiveBatch::new();
         if let Some(buf) = self.inner.borrow_mut().incoming.try_recv().ok() {
             out.push(buf);
         }
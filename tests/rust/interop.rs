@@ -0,0 +1,856 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Interoperability tests against the TCP stack of the Linux kernel. Each test runs in a network namespace of its own,
+// where the catnip-tap LibOS sits on one end of a TAP interface and the kernel on the other. Every frame that crosses
+// the interface is captured, and the capture of a failed test is dumped to a pcap file. These tests must run as root:
+//
+//   sudo -E cargo test --features interop-tests --test interop
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::anyhow::Result;
+use ::demikernel::{
+    demi_sgarray_t,
+    runtime::types::{
+        demi_opcode_t,
+        demi_qresult_t,
+        DEMI_QR_EOF,
+    },
+    LibOS,
+    LibOSName,
+    MacAddress,
+    QDesc,
+    QToken,
+};
+use ::std::{
+    env,
+    fs,
+    io::{
+        Read,
+        Write,
+    },
+    mem,
+    net::{
+        Ipv4Addr,
+        Shutdown,
+        SocketAddrV4,
+        TcpListener,
+        TcpStream,
+    },
+    os::fd::FromRawFd,
+    path::PathBuf,
+    process::Command,
+    slice,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+        Once,
+    },
+    thread::{
+        self,
+        JoinHandle,
+    },
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Name of the TAP interface.
+const TAP: &str = "demitap0";
+
+/// IPv4 address of the kernel end of the TAP interface.
+const HOST_IPV4: Ipv4Addr = Ipv4Addr::new(10, 77, 0, 1);
+
+/// IPv4 address of the Demikernel end of the TAP interface.
+const DEMIKERNEL_IPV4: Ipv4Addr = Ipv4Addr::new(10, 77, 0, 2);
+
+/// Link address of the Demikernel end of the TAP interface.
+const DEMIKERNEL_LINK_ADDR: &str = "02:00:00:00:00:02";
+
+/// Port number that servers listen on.
+const PORT: u16 = 12345;
+
+/// Maximum time that any single operation may take.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Size of the scatter-gather arrays that data is pushed in.
+const PUSH_SIZE: usize = 1024;
+
+/// Maximum segment size that kernel listeners advertise in the MSS test, which is the minimum for IPv4 (RFC 879).
+const SMALL_MSS: u16 = 536;
+
+//======================================================================================================================
+// Testbed
+//======================================================================================================================
+
+/// Moves the calling thread to a network namespace of its own, with a TAP interface whose kernel end has [HOST_IPV4].
+/// Threads and processes that the calling thread spawns afterwards share the namespace, which goes away along with
+/// them.
+fn enter_testbed() -> Result<()> {
+    if unsafe { libc::unshare(libc::CLONE_NEWNET) } != 0 {
+        anyhow::bail!(
+            "failed to create network namespace, interop tests must run as root: {:?}",
+            std::io::Error::last_os_error()
+        );
+    }
+
+    let prefix: String = format!("{}/24", HOST_IPV4);
+    let commands: [&[&str]; 4] = [
+        &["link", "set", "lo", "up"],
+        &["tuntap", "add", "dev", TAP, "mode", "tap"],
+        &["addr", "add", &prefix, "dev", TAP],
+        &["link", "set", TAP, "up"],
+    ];
+    for args in commands {
+        match Command::new("ip").args(args).status() {
+            Ok(status) if status.success() => (),
+            Ok(status) => anyhow::bail!("ip {} has failed: {:?}", args.join(" "), status),
+            Err(e) => anyhow::bail!("failed to run ip: {:?}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Instantiates the catnip-tap LibOS on the Demikernel end of the TAP interface.
+fn new_libos() -> Result<LibOS> {
+    static CONFIG: Once = Once::new();
+    CONFIG.call_once(|| {
+        let path: PathBuf = env::temp_dir().join("demikernel-interop.yaml");
+        let config: String = format!(
+            "catnip:\n  my_ipv4_addr: {}\n  my_link_addr: \"{}\"\n  my_interface_name: \"{}\"\n",
+            DEMIKERNEL_IPV4, DEMIKERNEL_LINK_ADDR, TAP
+        );
+        fs::write(&path, config).expect("should be able to write configuration file");
+        env::set_var("CONFIG_PATH", path);
+    });
+
+    match LibOS::new(LibOSName::CatnipTap) {
+        Ok(libos) => Ok(libos),
+        Err(e) => anyhow::bail!("failed to initialize libos: {:?}", e),
+    }
+}
+
+/// Runs an interoperability test in a testbed of its own. If the test fails, the frames that crossed the TAP interface
+/// are dumped to a pcap file.
+fn run_test(name: &str, test: impl FnOnce(&mut LibOS, &Capture) -> Result<()>) -> Result<()> {
+    enter_testbed()?;
+    let capture: Capture = Capture::start(TAP)?;
+    let mut libos: LibOS = new_libos()?;
+
+    let result: Result<()> = test(&mut libos, &capture);
+    drop(libos);
+    let frames: Vec<Frame> = capture.stop();
+
+    if let Err(e) = result {
+        let path: PathBuf = env::temp_dir().join(format!("demikernel-interop-{}.pcap", name));
+        match write_pcap(&path, &frames) {
+            Ok(()) => anyhow::bail!("{:?} (capture dumped to {})", e, path.display()),
+            Err(pcap_error) => anyhow::bail!("{:?} (failed to dump capture: {:?})", e, pcap_error),
+        }
+    }
+
+    Ok(())
+}
+
+//======================================================================================================================
+// Packet Capture
+//======================================================================================================================
+
+/// Frame that crossed the TAP interface, along with the time at which it did so.
+type Frame = (Duration, Vec<u8>);
+
+/// Captures every frame that crosses an interface, in either direction.
+struct Capture {
+    frames: Arc<Mutex<Vec<Frame>>>,
+    done: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl Capture {
+    /// Time that the capture thread waits for a frame before checking whether it should stop.
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Starts capturing frames on the interface named `ifname`.
+    fn start(ifname: &str) -> Result<Self> {
+        let protocol: libc::c_int = (libc::ETH_P_ALL as u16).to_be() as libc::c_int;
+        let fd: libc::c_int = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, protocol) };
+        if fd == -1 {
+            anyhow::bail!("failed to open packet socket: {:?}", std::io::Error::last_os_error());
+        }
+        // Hand the socket over to a stream, so that it gets closed whatever happens.
+        let socket: TcpStream = unsafe { TcpStream::from_raw_fd(fd) };
+
+        let name: Vec<u8> = ifname.bytes().chain([0]).collect();
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = protocol as u16;
+        addr.sll_ifindex = unsafe { libc::if_nametoindex(name.as_ptr() as *const libc::c_char) } as i32;
+        let addr_ptr: *const libc::sockaddr = &addr as *const libc::sockaddr_ll as *const libc::sockaddr;
+        let addr_len: libc::socklen_t = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+        if addr.sll_ifindex == 0 || unsafe { libc::bind(fd, addr_ptr, addr_len) } != 0 {
+            anyhow::bail!("failed to bind packet socket: {:?}", std::io::Error::last_os_error());
+        }
+        socket.set_read_timeout(Some(Self::POLL_INTERVAL))?;
+
+        let frames: Arc<Mutex<Vec<Frame>>> = Arc::new(Mutex::new(Vec::new()));
+        let done: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let thread: JoinHandle<()> = {
+            let frames: Arc<Mutex<Vec<Frame>>> = frames.clone();
+            let done: Arc<AtomicBool> = done.clone();
+            thread::spawn(move || Self::run(socket, frames, done))
+        };
+
+        Ok(Self { frames, done, thread })
+    }
+
+    /// Reads frames off a packet socket until told to stop, and there are no frames left.
+    fn run(mut socket: TcpStream, frames: Arc<Mutex<Vec<Frame>>>, done: Arc<AtomicBool>) {
+        let mut buf: [u8; 65536] = [0; 65536];
+        loop {
+            match socket.read(&mut buf) {
+                Ok(len) => {
+                    let timestamp: Duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                    frames.lock().unwrap().push((timestamp, buf[..len].to_vec()));
+                },
+                Err(_) if done.load(Ordering::Relaxed) => return,
+                Err(_) => (),
+            }
+        }
+    }
+
+    /// Returns the frames that crossed the interface so far.
+    fn frames(&self) -> Vec<Frame> {
+        // Give the capture thread time to read the frames that are in flight.
+        thread::sleep(2 * Self::POLL_INTERVAL);
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// Stops capturing frames, and returns all frames that crossed the interface.
+    fn stop(self) -> Vec<Frame> {
+        self.done.store(true, Ordering::Relaxed);
+        let _ = self.thread.join();
+        Arc::try_unwrap(self.frames)
+            .map(|frames| frames.into_inner().unwrap())
+            .unwrap_or_default()
+    }
+}
+
+/// Writes frames to a file in the pcap format.
+fn write_pcap(path: &PathBuf, frames: &[Frame]) -> Result<()> {
+    let mut pcap: Vec<u8> = Vec::new();
+
+    // Global header: magic number, version 2.4, UTC, no snapshot limit to speak of, and Ethernet link layer.
+    pcap.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    pcap.extend_from_slice(&2u16.to_le_bytes());
+    pcap.extend_from_slice(&4u16.to_le_bytes());
+    pcap.extend_from_slice(&0i32.to_le_bytes());
+    pcap.extend_from_slice(&0u32.to_le_bytes());
+    pcap.extend_from_slice(&65535u32.to_le_bytes());
+    pcap.extend_from_slice(&1u32.to_le_bytes());
+
+    for (timestamp, frame) in frames {
+        pcap.extend_from_slice(&(timestamp.as_secs() as u32).to_le_bytes());
+        pcap.extend_from_slice(&timestamp.subsec_micros().to_le_bytes());
+        pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        pcap.extend_from_slice(frame);
+    }
+
+    fs::write(path, pcap)?;
+    Ok(())
+}
+
+/// TCP segment in a captured frame.
+struct Segment {
+    /// IPv4 address of the sender.
+    src: Ipv4Addr,
+    /// Is the SYN flag set?
+    syn: bool,
+    /// Maximum segment size option, if any.
+    mss: Option<u16>,
+    /// Size of the payload.
+    payload_len: usize,
+}
+
+/// Parses the TCP segment in a captured frame, if there is one.
+fn parse_segment(frame: &[u8]) -> Option<Segment> {
+    // Ethernet header, then an IPv4 header that carries TCP.
+    if frame.len() < 34 || frame[12..14] != [0x08, 0x00] || frame[23] != libc::IPPROTO_TCP as u8 {
+        return None;
+    }
+    let ipv4: &[u8] = &frame[14..];
+    let ipv4_header_len: usize = ((ipv4[0] & 0xf) as usize) * 4;
+    let total_len: usize = u16::from_be_bytes([ipv4[2], ipv4[3]]) as usize;
+    if total_len > ipv4.len() || ipv4_header_len + 20 > total_len {
+        return None;
+    }
+    let src: Ipv4Addr = Ipv4Addr::new(ipv4[12], ipv4[13], ipv4[14], ipv4[15]);
+
+    let tcp: &[u8] = &ipv4[ipv4_header_len..total_len];
+    let tcp_header_len: usize = ((tcp[12] >> 4) as usize) * 4;
+    if tcp_header_len < 20 || tcp_header_len > tcp.len() {
+        return None;
+    }
+    let syn: bool = tcp[13] & 0x02 != 0;
+
+    // Look for the MSS option.
+    let mut mss: Option<u16> = None;
+    let mut options: &[u8] = &tcp[20..tcp_header_len];
+    while let Some(&kind) = options.first() {
+        match kind {
+            0 => break,
+            1 => options = &options[1..],
+            _ => {
+                let len: usize = match options.get(1) {
+                    Some(&len) if len >= 2 && (len as usize) <= options.len() => len as usize,
+                    _ => break,
+                };
+                if kind == 2 && len == 4 {
+                    mss = Some(u16::from_be_bytes([options[2], options[3]]));
+                }
+                options = &options[len..];
+            },
+        }
+    }
+
+    Some(Segment {
+        src,
+        syn,
+        mss,
+        payload_len: tcp.len() - tcp_header_len,
+    })
+}
+
+/// Checks that each end of the connections in a capture sends no segment larger than the maximum segment size that
+/// the other end advertised. Returns the advertised sizes of the kernel and Demikernel ends, along with the size of the
+/// largest segment that each end sent.
+fn check_mss(frames: &[Frame]) -> Result<((u16, usize), (u16, usize))> {
+    let segments: Vec<Segment> = frames.iter().filter_map(|(_, frame)| parse_segment(frame)).collect();
+    let advertised_mss = |src: Ipv4Addr| -> Result<u16> {
+        match segments.iter().find(|segment| segment.src == src && segment.syn) {
+            Some(Segment { mss: Some(mss), .. }) => Ok(*mss),
+            _ => anyhow::bail!("no SYN with the MSS option from {:?}", src),
+        }
+    };
+    let largest_segment = |src: Ipv4Addr| -> usize {
+        segments
+            .iter()
+            .filter(|segment| segment.src == src)
+            .map(|segment| segment.payload_len)
+            .max()
+            .unwrap_or(0)
+    };
+
+    let host: (u16, usize) = (advertised_mss(HOST_IPV4)?, largest_segment(HOST_IPV4));
+    let demikernel: (u16, usize) = (advertised_mss(DEMIKERNEL_IPV4)?, largest_segment(DEMIKERNEL_IPV4));
+    if host.1 > demikernel.0 as usize {
+        anyhow::bail!(
+            "kernel sent {} bytes in a segment, but Demikernel advertised an MSS of {}",
+            host.1,
+            demikernel.0
+        );
+    }
+    if demikernel.1 > host.0 as usize {
+        anyhow::bail!(
+            "Demikernel sent {} bytes in a segment, but the kernel advertised an MSS of {}",
+            demikernel.1,
+            host.0
+        );
+    }
+
+    Ok((host, demikernel))
+}
+
+//======================================================================================================================
+// Kernel Sockets
+//======================================================================================================================
+
+/// Opens a kernel socket that listens on [PORT], and that advertises `mss` as maximum segment size if any.
+fn kernel_listen(mss: Option<u16>) -> Result<TcpListener> {
+    let fd: libc::c_int = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    if fd == -1 {
+        anyhow::bail!("failed to open socket: {:?}", std::io::Error::last_os_error());
+    }
+    let listener: TcpListener = unsafe { TcpListener::from_raw_fd(fd) };
+
+    if let Some(mss) = mss {
+        let mss: libc::c_int = mss as libc::c_int;
+        let mss_ptr: *const libc::c_void = &mss as *const libc::c_int as *const libc::c_void;
+        let mss_len: libc::socklen_t = mem::size_of::<libc::c_int>() as libc::socklen_t;
+        if unsafe { libc::setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_MAXSEG, mss_ptr, mss_len) } != 0 {
+            anyhow::bail!("failed to set MSS: {:?}", std::io::Error::last_os_error());
+        }
+    }
+
+    let mut addr: libc::sockaddr_in = unsafe { mem::zeroed() };
+    addr.sin_family = libc::AF_INET as libc::sa_family_t;
+    addr.sin_port = PORT.to_be();
+    addr.sin_addr.s_addr = u32::from(HOST_IPV4).to_be();
+    let addr_ptr: *const libc::sockaddr = &addr as *const libc::sockaddr_in as *const libc::sockaddr;
+    let addr_len: libc::socklen_t = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+    if unsafe { libc::bind(fd, addr_ptr, addr_len) } != 0 || unsafe { libc::listen(fd, 16) } != 0 {
+        anyhow::bail!("failed to listen: {:?}", std::io::Error::last_os_error());
+    }
+
+    Ok(listener)
+}
+
+/// Accepts a connection on a kernel socket, giving up after [TIMEOUT].
+fn kernel_accept(listener: &TcpListener) -> Result<TcpStream> {
+    listener.set_nonblocking(true)?;
+    let deadline: SystemTime = SystemTime::now() + TIMEOUT;
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                return Ok(stream);
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock && SystemTime::now() < deadline => {
+                thread::sleep(Duration::from_millis(1))
+            },
+            Err(e) => anyhow::bail!("accept() has failed: {:?}", e),
+        }
+    }
+}
+
+/// Connects a kernel socket to the Demikernel end, retrying until the Demikernel end listens.
+fn kernel_connect() -> Result<TcpStream> {
+    let remote: SocketAddrV4 = SocketAddrV4::new(DEMIKERNEL_IPV4, PORT);
+    let deadline: SystemTime = SystemTime::now() + TIMEOUT;
+    loop {
+        match TcpStream::connect_timeout(&remote.into(), TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                return Ok(stream);
+            },
+            Err(_) if SystemTime::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            Err(e) => anyhow::bail!("connect() has failed: {:?}", e),
+        }
+    }
+}
+
+/// Reads from a kernel socket until the end of the stream.
+fn kernel_read_to_end(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::new();
+    match stream.read_to_end(&mut data) {
+        Ok(_) => Ok(data),
+        Err(e) => anyhow::bail!("read() has failed after {} bytes: {:?}", data.len(), e),
+    }
+}
+
+/// Joins a thread that runs the kernel end of a test.
+fn join(kernel: JoinHandle<Result<()>>) -> Result<()> {
+    match kernel.join() {
+        Ok(result) => result,
+        Err(_) => anyhow::bail!("kernel thread has panicked"),
+    }
+}
+
+//======================================================================================================================
+// Demikernel Sockets
+//======================================================================================================================
+
+/// Waits for an operation on the Demikernel end to complete, giving up after [TIMEOUT].
+fn wait(libos: &mut LibOS, qt: QToken) -> Result<demi_qresult_t> {
+    match libos.wait(qt, Some(TIMEOUT)) {
+        Ok(qr) => Ok(qr),
+        Err(e) => anyhow::bail!("wait() has failed: {:?}", e),
+    }
+}
+
+/// Opens a TCP socket on the Demikernel end.
+fn socket(libos: &mut LibOS) -> Result<QDesc> {
+    match libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0) {
+        Ok(qd) => Ok(qd),
+        Err(e) => anyhow::bail!("socket() has failed: {:?}", e),
+    }
+}
+
+/// Accepts a connection on the Demikernel end, on a socket that listens on [PORT]. Returns the queue descriptor and
+/// remote address of the connection.
+fn accept(libos: &mut LibOS) -> Result<(QDesc, SocketAddrV4)> {
+    let sockqd: QDesc = socket(libos)?;
+    if let Err(e) = libos.bind(sockqd, SocketAddrV4::new(DEMIKERNEL_IPV4, PORT)) {
+        anyhow::bail!("bind() has failed: {:?}", e);
+    }
+    if let Err(e) = libos.listen(sockqd, 16) {
+        anyhow::bail!("listen() has failed: {:?}", e);
+    }
+    let qt: QToken = match libos.accept(sockqd) {
+        Ok(qt) => qt,
+        Err(e) => anyhow::bail!("accept() has failed: {:?}", e),
+    };
+    let qr: demi_qresult_t = wait(libos, qt)?;
+    if qr.qr_opcode != demi_opcode_t::DEMI_OPC_ACCEPT {
+        anyhow::bail!("accept() has failed: {:?}", qr.qr_ret);
+    }
+    let qd: QDesc = unsafe { qr.qr_value.ares.qd.into() };
+    let addr: libc::sockaddr_in = unsafe {
+        std::ptr::addr_of!(qr.qr_value.ares.addr)
+            .cast::<libc::sockaddr_in>()
+            .read_unaligned()
+    };
+    let remote: SocketAddrV4 = SocketAddrV4::new(
+        Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)),
+        u16::from_be(addr.sin_port),
+    );
+    Ok((qd, remote))
+}
+
+/// Connects the Demikernel end to the kernel socket that listens on [PORT]. Returns the queue descriptor of the
+/// connection, or the error number of a connection attempt that failed.
+fn connect(libos: &mut LibOS) -> Result<Result<QDesc, i64>> {
+    let qd: QDesc = socket(libos)?;
+    let qt: QToken = match libos.connect(qd, SocketAddrV4::new(HOST_IPV4, PORT)) {
+        Ok(qt) => qt,
+        Err(e) => anyhow::bail!("connect() has failed: {:?}", e),
+    };
+    let qr: demi_qresult_t = wait(libos, qt)?;
+    match qr.qr_opcode {
+        demi_opcode_t::DEMI_OPC_CONNECT => Ok(Ok(qd)),
+        demi_opcode_t::DEMI_OPC_FAILED => Ok(Err(qr.qr_ret)),
+        opcode => anyhow::bail!("unexpected result of connect(): {:?}", opcode),
+    }
+}
+
+/// Pushes data on the Demikernel end, and waits for the pushes to complete.
+fn push(libos: &mut LibOS, qd: QDesc, data: &[u8]) -> Result<()> {
+    for chunk in data.chunks(PUSH_SIZE) {
+        let sga: demi_sgarray_t = match libos.sgaalloc(chunk.len()) {
+            Ok(sga) => sga,
+            Err(e) => anyhow::bail!("sgaalloc() has failed: {:?}", e),
+        };
+        let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
+        unsafe { slice::from_raw_parts_mut(ptr, chunk.len()) }.copy_from_slice(chunk);
+        let result: Result<QToken, _> = libos.push(qd, &sga);
+        if let Err(e) = libos.sgafree(sga) {
+            anyhow::bail!("sgafree() has failed: {:?}", e);
+        }
+        let qt: QToken = match result {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() has failed: {:?}", e),
+        };
+        let qr: demi_qresult_t = wait(libos, qt)?;
+        if qr.qr_opcode != demi_opcode_t::DEMI_OPC_PUSH {
+            anyhow::bail!("push() has failed: {:?}", qr.qr_ret);
+        }
+    }
+    Ok(())
+}
+
+/// Pops data on the Demikernel end. Returns the data along with whether the end of the stream was reached, or the error
+/// number of a pop that failed.
+fn pop(libos: &mut LibOS, qd: QDesc) -> Result<Result<(Vec<u8>, bool), i64>> {
+    let qt: QToken = match libos.pop(qd, None) {
+        Ok(qt) => qt,
+        Err(e) => anyhow::bail!("pop() has failed: {:?}", e),
+    };
+    let qr: demi_qresult_t = wait(libos, qt)?;
+    match qr.qr_opcode {
+        demi_opcode_t::DEMI_OPC_POP => {
+            let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+            let ptr: *const u8 = sga.sga_segs[0].sgaseg_buf as *const u8;
+            let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+            // The end of the stream comes with an empty buffer, which may not be backed by memory at all.
+            let data: Vec<u8> = if len == 0 {
+                Vec::new()
+            } else {
+                unsafe { slice::from_raw_parts(ptr, len) }.to_vec()
+            };
+            if let Err(e) = libos.sgafree(sga) {
+                anyhow::bail!("sgafree() has failed: {:?}", e);
+            }
+            Ok(Ok((data, qr.qr_flags & DEMI_QR_EOF != 0)))
+        },
+        demi_opcode_t::DEMI_OPC_FAILED => Ok(Err(qr.qr_ret)),
+        opcode => anyhow::bail!("unexpected result of pop(): {:?}", opcode),
+    }
+}
+
+/// Pops data on the Demikernel end until the end of the stream.
+fn pop_to_end(libos: &mut LibOS, qd: QDesc) -> Result<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::new();
+    loop {
+        match pop(libos, qd)? {
+            Ok((_, true)) => return Ok(data),
+            Ok((bytes, false)) => data.extend_from_slice(&bytes),
+            Err(errno) => anyhow::bail!("pop() has failed after {} bytes: {:?}", data.len(), errno),
+        }
+    }
+}
+
+/// Closes a connection on the Demikernel end, and waits for the connection to shut down.
+fn close(libos: &mut LibOS, qd: QDesc) -> Result<()> {
+    let qt: QToken = match libos.async_close(qd) {
+        Ok(qt) => qt,
+        Err(e) => anyhow::bail!("async_close() has failed: {:?}", e),
+    };
+    let qr: demi_qresult_t = wait(libos, qt)?;
+    if qr.qr_opcode != demi_opcode_t::DEMI_OPC_CLOSE {
+        anyhow::bail!("close() has failed: {:?}", qr.qr_ret);
+    }
+    Ok(())
+}
+
+/// Cooks `size` bytes of data that do not repeat too often.
+fn cook_data(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i % 251) as u8).collect()
+}
+
+//======================================================================================================================
+// ARP
+//======================================================================================================================
+
+/// Tests if Demikernel learns the link address of the kernel end through a manual ARP request.
+#[test]
+fn interop_wait_arp() -> Result<()> {
+    run_test("wait_arp", |libos, _| {
+        let output: std::process::Output = Command::new("ip").args(["-o", "link", "show", "dev", TAP]).output()?;
+        let output: String = String::from_utf8_lossy(&output.stdout).into_owned();
+        let host_link_addr: MacAddress = match output.split_whitespace().skip_while(|s| *s != "link/ether").nth(1) {
+            Some(link_addr) => match MacAddress::parse_str(link_addr) {
+                Ok(link_addr) => link_addr,
+                Err(e) => anyhow::bail!("failed to parse link address: {:?}", e),
+            },
+            None => anyhow::bail!("no link address for {}: {}", TAP, output),
+        };
+
+        match libos.wait_arp(HOST_IPV4, TIMEOUT) {
+            Ok(link_addr) => demikernel::ensure_eq!(link_addr, host_link_addr),
+            Err(e) => anyhow::bail!("wait_arp() has failed: {:?}", e),
+        }
+        match libos.send_arp_request(Ipv4Addr::BROADCAST) {
+            Err(e) => demikernel::ensure_eq!(e.errno, libc::EINVAL),
+            Ok(()) => anyhow::bail!("send_arp_request() should have failed"),
+        }
+        Ok(())
+    })
+}
+
+//======================================================================================================================
+// Handshake
+//======================================================================================================================
+
+/// Tests if the kernel establishes and closes a connection to Demikernel.
+#[test]
+fn interop_handshake_to_demikernel() -> Result<()> {
+    run_test("handshake_to_demikernel", |libos, _| {
+        let kernel: JoinHandle<Result<()>> = thread::spawn(|| {
+            let mut stream: TcpStream = kernel_connect()?;
+            demikernel::ensure_eq!(stream.peer_addr()?, SocketAddrV4::new(DEMIKERNEL_IPV4, PORT).into());
+            stream.shutdown(Shutdown::Write)?;
+            demikernel::ensure_eq!(kernel_read_to_end(&mut stream)?.len(), 0);
+            Ok(())
+        });
+
+        let (qd, remote): (QDesc, SocketAddrV4) = accept(libos)?;
+        demikernel::ensure_eq!(remote.ip(), &HOST_IPV4);
+        demikernel::ensure_eq!(pop_to_end(libos, qd)?.len(), 0);
+        close(libos, qd)?;
+        join(kernel)
+    })
+}
+
+/// Tests if Demikernel establishes and closes a connection to the kernel.
+#[test]
+fn interop_handshake_from_demikernel() -> Result<()> {
+    run_test("handshake_from_demikernel", |libos, _| {
+        let listener: TcpListener = kernel_listen(None)?;
+        let kernel: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut stream: TcpStream = kernel_accept(&listener)?;
+            demikernel::ensure_eq!(stream.peer_addr()?.ip(), DEMIKERNEL_IPV4);
+            demikernel::ensure_eq!(kernel_read_to_end(&mut stream)?.len(), 0);
+            Ok(())
+        });
+
+        let qd: QDesc = match connect(libos)? {
+            Ok(qd) => qd,
+            Err(errno) => anyhow::bail!("connect() has failed: {:?}", errno),
+        };
+        close(libos, qd)?;
+        join(kernel)
+    })
+}
+
+//======================================================================================================================
+// Bulk Transfer
+//======================================================================================================================
+
+/// Tests if a large amount of data is echoed back and forth between the kernel and Demikernel, unharmed, with segments
+/// that fit the maximum segment size of each end.
+#[test]
+fn interop_bulk_transfer() -> Result<()> {
+    const SIZE: usize = 1024 * 1024;
+    run_test("bulk_transfer", |libos, capture| {
+        let kernel: JoinHandle<Result<()>> = thread::spawn(|| {
+            let mut stream: TcpStream = kernel_connect()?;
+
+            // Send and receive at once, so that neither end stalls on a full window.
+            let mut writer: TcpStream = stream.try_clone()?;
+            let sender: JoinHandle<Result<()>> = thread::spawn(move || {
+                writer.write_all(&cook_data(SIZE))?;
+                writer.shutdown(Shutdown::Write)?;
+                Ok(())
+            });
+            let echo: Vec<u8> = kernel_read_to_end(&mut stream)?;
+            join(sender)?;
+
+            demikernel::ensure_eq!(echo.len(), SIZE);
+            demikernel::ensure_eq!(echo == cook_data(SIZE), true);
+            Ok(())
+        });
+
+        // Echo everything back.
+        let (qd, _): (QDesc, SocketAddrV4) = accept(libos)?;
+        let mut nbytes: usize = 0;
+        loop {
+            match pop(libos, qd)? {
+                Ok((_, true)) => break,
+                Ok((data, false)) => {
+                    push(libos, qd, &data)?;
+                    nbytes += data.len();
+                },
+                Err(errno) => anyhow::bail!("pop() has failed after {} bytes: {:?}", nbytes, errno),
+            }
+        }
+        demikernel::ensure_eq!(nbytes, SIZE);
+        close(libos, qd)?;
+        join(kernel)?;
+
+        check_mss(&capture.frames())?;
+        Ok(())
+    })
+}
+
+//======================================================================================================================
+// Half-Close
+//======================================================================================================================
+
+/// Tests if Demikernel keeps sending data after the kernel shuts down its sending side of the connection.
+#[test]
+fn interop_half_close() -> Result<()> {
+    const REQUEST_SIZE: usize = 1000;
+    const REPLY_SIZE: usize = 64 * 1024;
+    run_test("half_close", |libos, _| {
+        let kernel: JoinHandle<Result<()>> = thread::spawn(|| {
+            let mut stream: TcpStream = kernel_connect()?;
+            stream.write_all(&cook_data(REQUEST_SIZE))?;
+            stream.shutdown(Shutdown::Write)?;
+            let reply: Vec<u8> = kernel_read_to_end(&mut stream)?;
+            demikernel::ensure_eq!(reply.len(), REPLY_SIZE);
+            demikernel::ensure_eq!(reply == cook_data(REPLY_SIZE), true);
+            Ok(())
+        });
+
+        // The reply only goes out once the request is over.
+        let (qd, _): (QDesc, SocketAddrV4) = accept(libos)?;
+        let request: Vec<u8> = pop_to_end(libos, qd)?;
+        demikernel::ensure_eq!(request == cook_data(REQUEST_SIZE), true);
+        push(libos, qd, &cook_data(REPLY_SIZE))?;
+        close(libos, qd)?;
+        join(kernel)
+    })
+}
+
+//======================================================================================================================
+// Reset
+//======================================================================================================================
+
+/// Tests if a pop on Demikernel fails once the kernel resets the connection.
+#[test]
+fn interop_reset_by_kernel() -> Result<()> {
+    run_test("reset_by_kernel", |libos, _| {
+        let listener: TcpListener = kernel_listen(None)?;
+        let kernel: JoinHandle<Result<()>> = thread::spawn(move || {
+            let stream: TcpStream = kernel_accept(&listener)?;
+
+            // Closing with a zero linger time resets the connection.
+            let linger: libc::linger = libc::linger {
+                l_onoff: 1,
+                l_linger: 0,
+            };
+            let linger_ptr: *const libc::c_void = &linger as *const libc::linger as *const libc::c_void;
+            let linger_len: libc::socklen_t = mem::size_of::<libc::linger>() as libc::socklen_t;
+            let fd: libc::c_int = std::os::fd::AsRawFd::as_raw_fd(&stream);
+            if unsafe { libc::setsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER, linger_ptr, linger_len) } != 0 {
+                anyhow::bail!("failed to set linger time: {:?}", std::io::Error::last_os_error());
+            }
+            drop(stream);
+            Ok(())
+        });
+
+        let qd: QDesc = match connect(libos)? {
+            Ok(qd) => qd,
+            Err(errno) => anyhow::bail!("connect() has failed: {:?}", errno),
+        };
+        match pop(libos, qd)? {
+            Err(errno) => demikernel::ensure_eq!(errno, libc::ECONNRESET as i64),
+            Ok((data, eof)) => anyhow::bail!("pop() should have failed (len={}, eof={})", data.len(), eof),
+        }
+        if let Err(e) = libos.close(qd) {
+            anyhow::bail!("close() has failed: {:?}", e);
+        }
+        join(kernel)
+    })
+}
+
+/// Tests if a connection attempt from Demikernel fails once the kernel refuses it.
+#[test]
+fn interop_connection_refused() -> Result<()> {
+    run_test("connection_refused", |libos, _| {
+        match connect(libos)? {
+            Err(errno) => demikernel::ensure_eq!(errno, libc::ECONNREFUSED as i64),
+            Ok(_) => anyhow::bail!("connect() should have failed"),
+        }
+        Ok(())
+    })
+}
+
+//======================================================================================================================
+// MSS Negotiation
+//======================================================================================================================
+
+/// Tests if Demikernel sends no segment larger than the maximum segment size that the kernel advertises.
+#[test]
+fn interop_mss_negotiation() -> Result<()> {
+    const SIZE: usize = 16 * 1024;
+    run_test("mss_negotiation", |libos, capture| {
+        let listener: TcpListener = kernel_listen(Some(SMALL_MSS))?;
+        let kernel: JoinHandle<Result<()>> = thread::spawn(move || {
+            let mut stream: TcpStream = kernel_accept(&listener)?;
+            let data: Vec<u8> = kernel_read_to_end(&mut stream)?;
+            demikernel::ensure_eq!(data == cook_data(SIZE), true);
+            Ok(())
+        });
+
+        let qd: QDesc = match connect(libos)? {
+            Ok(qd) => qd,
+            Err(errno) => anyhow::bail!("connect() has failed: {:?}", errno),
+        };
+        push(libos, qd, &cook_data(SIZE))?;
+        close(libos, qd)?;
+        join(kernel)?;
+
+        let (host, demikernel): ((u16, usize), (u16, usize)) = check_mss(&capture.frames())?;
+        demikernel::ensure_eq!(host.0, SMALL_MSS);
+        demikernel::ensure_eq!(demikernel.1 > 0, true);
+        Ok(())
+    })
+}
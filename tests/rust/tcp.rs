@@ -12,6 +12,7 @@ use ::demikernel::{
     inetstack::InetStack,
     runtime::{
         memory::DemiBuffer,
+        network::types::ByteStats,
         OperationResult,
         QDesc,
         QToken,
@@ -31,7 +32,6 @@ use crossbeam_channel::{
     Receiver,
     Sender,
 };
-use demikernel::runtime::network::consts::RECEIVE_BATCH_SIZE;
 
 #[cfg(target_os = "windows")]
 pub const AF_INET: i32 = windows::Win32::Networking::WinSock::AF_INET.0 as i32;
@@ -63,7 +63,7 @@ use windows::Win32::Networking::WinSock;
 //======================================================================================================================
 
 /// Opens and closes a passive socket using a non-ephemeral port.
-fn do_passive_connection_setup<const N: usize>(mut libos: &mut InetStack<N>) -> Result<()> {
+fn do_passive_connection_setup(mut libos: &mut InetStack) -> Result<()> {
     let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE);
     let sockqd: QDesc = safe_socket(&mut libos)?;
     safe_bind(&mut libos, sockqd, local)?;
@@ -74,7 +74,7 @@ fn do_passive_connection_setup<const N: usize>(mut libos: &mut InetStack<N>) ->
 }
 
 /// Opens and closes a passive socket using an ephemeral port.
-fn do_passive_connection_setup_ephemeral<const N: usize>(mut libos: &mut InetStack<N>) -> Result<()> {
+fn do_passive_connection_setup_ephemeral(mut libos: &mut InetStack) -> Result<()> {
     pub const PORT_EPHEMERAL_BASE: u16 = 49152;
     let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_EPHEMERAL_BASE);
     let sockqd: QDesc = safe_socket(&mut libos)?;
@@ -86,12 +86,25 @@ fn do_passive_connection_setup_ephemeral<const N: usize>(mut libos: &mut InetSta
 }
 
 /// Opens and closes a passive socket using wildcard ephemeral port.
-fn do_passive_connection_setup_wildcard_ephemeral<const N: usize>(mut libos: &mut InetStack<N>) -> Result<()> {
+fn do_passive_connection_setup_wildcard_ephemeral(mut libos: &mut InetStack) -> Result<()> {
     let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, 0);
     let sockqd: QDesc = safe_socket(&mut libos)?;
     safe_bind(&mut libos, sockqd, local)?;
     safe_listen(&mut libos, sockqd)?;
+
+    // Check that successive binds select different ephemeral ports.
+    let other_sockqd: QDesc = safe_socket(&mut libos)?;
+    safe_bind(&mut libos, other_sockqd, local)?;
+    let port: u16 = libos.getsockname(sockqd)?.port();
+    let other_port: u16 = libos.getsockname(other_sockqd)?.port();
+    demikernel::ensure_neq!(port, 0);
+    demikernel::ensure_neq!(other_port, 0);
+    demikernel::ensure_neq!(port, other_port);
+
     safe_close_passive(&mut libos, sockqd)?;
+    if let Err(e) = libos.close(other_sockqd) {
+        anyhow::bail!("close() failed: {:?}", e);
+    }
 
     Ok(())
 }
@@ -100,7 +113,7 @@ fn do_passive_connection_setup_wildcard_ephemeral<const N: usize>(mut libos: &mu
 #[test]
 fn tcp_connection_setup() -> Result<()> {
     let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
-    let mut libos: InetStack<RECEIVE_BATCH_SIZE> = DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp())?;
+    let mut libos: InetStack = DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp())?;
 
     do_passive_connection_setup(&mut libos)?;
     do_passive_connection_setup_ephemeral(&mut libos)?;
@@ -120,11 +133,10 @@ fn tcp_establish_connection_unbound() -> Result<()> {
     let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE);
 
@@ -152,8 +164,7 @@ fn tcp_establish_connection_unbound() -> Result<()> {
     });
 
     let bob: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp())
-        {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
             Ok(libos) => libos,
             Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
         };
@@ -194,11 +205,10 @@ fn tcp_establish_connection_bound() -> Result<()> {
     let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE);
 
@@ -226,8 +236,7 @@ fn tcp_establish_connection_bound() -> Result<()> {
     });
 
     let bob: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp())
-        {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
             Ok(libos) => libos,
             Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
         };
@@ -274,11 +283,10 @@ fn tcp_push_remote() -> Result<()> {
     let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         let port: u16 = PORT_BASE;
         let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
@@ -302,7 +310,7 @@ fn tcp_push_remote() -> Result<()> {
         let qt: QToken = safe_pop(&mut libos, qd)?;
         let (qd, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Pop(_, _) => (),
+            OperationResult::Pop(..) => (),
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -318,8 +326,7 @@ fn tcp_push_remote() -> Result<()> {
     });
 
     let bob: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp())
-        {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
             Ok(libos) => libos,
             Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
         };
@@ -368,6 +375,213 @@ fn tcp_push_remote() -> Result<()> {
     Ok(())
 }
 
+/// Tests that the byte counters of a connection match the number of bytes that it transferred.
+#[test]
+fn tcp_byte_stats() -> Result<()> {
+    const NUM_PUSHES: usize = 8;
+    const PUSH_SIZE: usize = 1000;
+    let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+    let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
+
+        let port: u16 = PORT_BASE;
+        let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
+
+        // Open connection.
+        let sockqd: QDesc = safe_socket(&mut libos)?;
+        safe_bind(&mut libos, sockqd, local)?;
+        safe_listen(&mut libos, sockqd)?;
+        let qt: QToken = safe_accept(&mut libos, sockqd)?;
+        let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
+        let qd: QDesc = match qr {
+            OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IPV4 => qd,
+            _ => anyhow::bail!("accept() has failed"),
+        };
+
+        // Pop all data.
+        let mut received: usize = 0;
+        while received < NUM_PUSHES * PUSH_SIZE {
+            let qt: QToken = safe_pop(&mut libos, qd)?;
+            match safe_wait2(&mut libos, qt)? {
+                (_, OperationResult::Pop(_, bytes, _)) => received += bytes.len(),
+                (_, qr) => anyhow::bail!("pop() has has failed {:?}", qr),
+            }
+        }
+
+        // Check counters.
+        let stats: ByteStats = match libos.byte_stats(qd) {
+            Ok(stats) => stats,
+            Err(e) => anyhow::bail!("byte_stats() has failed: {:?}", e),
+        };
+        demikernel::ensure_eq!(stats.received, (NUM_PUSHES * PUSH_SIZE) as u64);
+        demikernel::ensure_eq!(stats.sent, 0);
+        demikernel::ensure_eq!(stats.retransmitted, 0);
+
+        // Close connection.
+        safe_close_active(&mut libos, qd)?;
+        safe_close_passive(&mut libos, sockqd)?;
+
+        Ok(())
+    });
+
+    let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
+
+        let port: u16 = PORT_BASE;
+        let remote: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
+
+        // Open connection.
+        let sockqd: QDesc = safe_socket(&mut libos)?;
+        let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+        match safe_wait2(&mut libos, qt)? {
+            (_, OperationResult::Connect) => (),
+            _ => anyhow::bail!("connect() has failed"),
+        }
+
+        // Push data.
+        for _ in 0..NUM_PUSHES {
+            let bytes: DemiBuffer = DummyLibOS::cook_data(PUSH_SIZE);
+            let qt: QToken = safe_push2(&mut libos, sockqd, &bytes)?;
+            match safe_wait2(&mut libos, qt)? {
+                (_, OperationResult::Push) => (),
+                _ => anyhow::bail!("push() has failed"),
+            }
+        }
+
+        // Check counters.
+        let stats: ByteStats = match libos.byte_stats(sockqd) {
+            Ok(stats) => stats,
+            Err(e) => anyhow::bail!("byte_stats() has failed: {:?}", e),
+        };
+        demikernel::ensure_eq!(stats.sent, (NUM_PUSHES * PUSH_SIZE) as u64);
+        demikernel::ensure_eq!(stats.received, 0);
+        demikernel::ensure_eq!(stats.retransmitted, 0);
+
+        // Close connection.
+        safe_close_active(&mut libos, sockqd)?;
+
+        Ok(())
+    });
+    // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+    // is, there is nothing to clean up here on the main thread.
+    alice.join().unwrap()?;
+    bob.join().unwrap()?;
+
+    Ok(())
+}
+
+/// Tests that a pop reports the end of the stream once the remote peer has sent all its data and closed.
+#[test]
+fn tcp_pop_eof() -> Result<()> {
+    const PUSH_SIZE: usize = 32;
+    let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+
+    let alice: JoinHandle<Result<()>> = thread::spawn(move || {
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
+
+        let port: u16 = PORT_BASE;
+        let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
+
+        // Open connection.
+        let sockqd: QDesc = safe_socket(&mut libos)?;
+        safe_bind(&mut libos, sockqd, local)?;
+        safe_listen(&mut libos, sockqd)?;
+        let qt: QToken = safe_accept(&mut libos, sockqd)?;
+        let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
+        let qd: QDesc = match qr {
+            OperationResult::Accept((qd, addr)) if addr.ip() == &BOB_IPV4 => qd,
+            _ => anyhow::bail!("accept() has failed"),
+        };
+
+        // Pop data until the end of the stream. Only the last pop reports it, and it carries no data.
+        let mut received: usize = 0;
+        loop {
+            let qt: QToken = safe_pop(&mut libos, qd)?;
+            match safe_wait2(&mut libos, qt)? {
+                (_, OperationResult::Pop(_, bytes, true)) => {
+                    demikernel::ensure_eq!(bytes.len(), 0);
+                    break;
+                },
+                (_, OperationResult::Pop(_, bytes, false)) => {
+                    demikernel::ensure_eq!(bytes.is_empty(), false);
+                    received += bytes.len();
+                },
+                (_, qr) => anyhow::bail!("pop() has has failed {:?}", qr),
+            }
+        }
+        demikernel::ensure_eq!(received, PUSH_SIZE);
+
+        // Close connection.
+        let qt: QToken = match libos.async_close(qd) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("async_close() has failed: {:?}", e),
+        };
+        match safe_wait2(&mut libos, qt)? {
+            (_, OperationResult::Close) => (),
+            (_, qr) => anyhow::bail!("close() has failed {:?}", qr),
+        }
+        safe_close_passive(&mut libos, sockqd)?;
+
+        Ok(())
+    });
+
+    let bob: JoinHandle<Result<()>> = thread::spawn(move || {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
+
+        let port: u16 = PORT_BASE;
+        let remote: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
+
+        // Open connection.
+        let sockqd: QDesc = safe_socket(&mut libos)?;
+        let qt: QToken = safe_connect(&mut libos, sockqd, remote)?;
+        match safe_wait2(&mut libos, qt)? {
+            (_, OperationResult::Connect) => (),
+            _ => anyhow::bail!("connect() has failed"),
+        }
+
+        // Push data.
+        let bytes: DemiBuffer = DummyLibOS::cook_data(PUSH_SIZE);
+        let qt: QToken = safe_push2(&mut libos, sockqd, &bytes)?;
+        match safe_wait2(&mut libos, qt)? {
+            (_, OperationResult::Push) => (),
+            _ => anyhow::bail!("push() has failed"),
+        }
+
+        // Close connection, and wait for the remote peer to close too, so that our end of the stream gets through.
+        let qt: QToken = match libos.async_close(sockqd) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("async_close() has failed: {:?}", e),
+        };
+        match safe_wait2(&mut libos, qt)? {
+            (_, OperationResult::Close) => (),
+            (_, qr) => anyhow::bail!("close() has failed {:?}", qr),
+        }
+
+        Ok(())
+    });
+    // It is safe to use unwrap here because there should not be any reason that we can't join the thread and if there
+    // is, there is nothing to clean up here on the main thread.
+    alice.join().unwrap()?;
+    bob.join().unwrap()?;
+
+    Ok(())
+}
+
 //======================================================================================================================
 // Bad Socket
 //======================================================================================================================
@@ -376,7 +590,7 @@ fn tcp_push_remote() -> Result<()> {
 #[test]
 fn tcp_bad_socket() -> Result<()> {
     let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
-    let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
+    let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
         Ok(libos) => libos,
         Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
     };
@@ -489,7 +703,7 @@ fn tcp_bad_socket() -> Result<()> {
 #[test]
 fn tcp_bad_listen() -> Result<()> {
     let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
-    let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
+    let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
         Ok(libos) => libos,
         Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
     };
@@ -564,7 +778,7 @@ fn tcp_bad_listen() -> Result<()> {
 #[test]
 fn tcp_bad_accept() -> Result<()> {
     let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
-    let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
+    let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
         Ok(libos) => libos,
         Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
     };
@@ -593,11 +807,10 @@ fn tcp_bad_connect() -> Result<()> {
     let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
         let port: u16 = PORT_BASE;
         let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
 
@@ -624,8 +837,7 @@ fn tcp_bad_connect() -> Result<()> {
     });
 
     let bob: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp())
-        {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
             Ok(libos) => libos,
             Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
         };
@@ -696,11 +908,10 @@ fn tcp_bad_close() -> Result<()> {
     let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         let port: u16 = PORT_BASE;
         let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
@@ -740,8 +951,7 @@ fn tcp_bad_close() -> Result<()> {
     });
 
     let bob: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp())
-        {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
             Ok(libos) => libos,
             Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
         };
@@ -801,11 +1011,10 @@ fn tcp_bad_push() -> Result<()> {
     let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         let port: u16 = PORT_BASE;
         let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
@@ -829,7 +1038,7 @@ fn tcp_bad_push() -> Result<()> {
         let qt: QToken = safe_pop(&mut libos, qd)?;
         let (qd, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Pop(_, _) => (),
+            OperationResult::Pop(..) => (),
             _ => {
                 // Close socket if error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -845,8 +1054,7 @@ fn tcp_bad_push() -> Result<()> {
     });
 
     let bob: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp())
-        {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
             Ok(libos) => libos,
             Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
         };
@@ -933,11 +1141,10 @@ fn tcp_bad_pop() -> Result<()> {
     let (bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         let port: u16 = PORT_BASE;
         let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, port);
@@ -971,7 +1178,7 @@ fn tcp_bad_pop() -> Result<()> {
         let qt: QToken = safe_pop(&mut libos, qd)?;
         let (qd, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Pop(_, _) => (),
+            OperationResult::Pop(..) => (),
             _ => {
                 // Close socket if error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -987,8 +1194,7 @@ fn tcp_bad_pop() -> Result<()> {
     });
 
     let bob: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp())
-        {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
             Ok(libos) => libos,
             Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
         };
@@ -1043,7 +1249,7 @@ fn tcp_bad_pop() -> Result<()> {
 //======================================================================================================================
 
 /// Safe call to `socket()`.
-fn safe_socket<const N: usize>(libos: &mut InetStack<N>) -> Result<QDesc> {
+fn safe_socket(libos: &mut InetStack) -> Result<QDesc> {
     match libos.socket(AF_INET, SOCK_STREAM, 0) {
         Ok(sockqd) => Ok(sockqd),
         Err(e) => anyhow::bail!("failed to create socket: {:?}", e),
@@ -1051,7 +1257,7 @@ fn safe_socket<const N: usize>(libos: &mut InetStack<N>) -> Result<QDesc> {
 }
 
 /// Safe call to `connect()`.
-fn safe_connect<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc, remote: SocketAddrV4) -> Result<QToken> {
+fn safe_connect(libos: &mut InetStack, sockqd: QDesc, remote: SocketAddrV4) -> Result<QToken> {
     match libos.connect(sockqd, remote) {
         Ok(qt) => Ok(qt),
         Err(e) => {
@@ -1063,7 +1269,7 @@ fn safe_connect<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc, remote:
 }
 
 /// Safe call to `bind()`.
-fn safe_bind<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc, local: SocketAddrV4) -> Result<()> {
+fn safe_bind(libos: &mut InetStack, sockqd: QDesc, local: SocketAddrV4) -> Result<()> {
     match libos.bind(sockqd, local) {
         Ok(_) => Ok(()),
         Err(e) => {
@@ -1075,7 +1281,7 @@ fn safe_bind<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc, local: Soc
 }
 
 /// Safe call to `listen()`.
-fn safe_listen<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc) -> Result<()> {
+fn safe_listen(libos: &mut InetStack, sockqd: QDesc) -> Result<()> {
     match libos.listen(sockqd, 8) {
         Ok(_) => Ok(()),
         Err(e) => {
@@ -1087,7 +1293,7 @@ fn safe_listen<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc) -> Resul
 }
 
 /// Safe call to `accept()`.
-fn safe_accept<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc) -> Result<QToken> {
+fn safe_accept(libos: &mut InetStack, sockqd: QDesc) -> Result<QToken> {
     match libos.accept(sockqd) {
         Ok(qt) => Ok(qt),
         Err(e) => {
@@ -1099,7 +1305,7 @@ fn safe_accept<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc) -> Resul
 }
 
 /// Safe call to `pop()`.
-fn safe_pop<const N: usize>(libos: &mut InetStack<N>, qd: QDesc) -> Result<QToken> {
+fn safe_pop(libos: &mut InetStack, qd: QDesc) -> Result<QToken> {
     match libos.pop(qd, None) {
         Ok(qt) => Ok(qt),
         Err(e) => {
@@ -1111,7 +1317,7 @@ fn safe_pop<const N: usize>(libos: &mut InetStack<N>, qd: QDesc) -> Result<QToke
 }
 
 /// Safe call to `push2()`
-fn safe_push2<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc, bytes: &[u8]) -> Result<QToken> {
+fn safe_push2(libos: &mut InetStack, sockqd: QDesc, bytes: &[u8]) -> Result<QToken> {
     match libos.push2(sockqd, bytes) {
         Ok(qt) => Ok(qt),
         Err(e) => {
@@ -1123,7 +1329,7 @@ fn safe_push2<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc, bytes: &[
 }
 
 /// Safe call to `wait2()`.
-fn safe_wait2<const N: usize>(libos: &mut InetStack<N>, qt: QToken) -> Result<(QDesc, OperationResult)> {
+fn safe_wait2(libos: &mut InetStack, qt: QToken) -> Result<(QDesc, OperationResult)> {
     match libos.wait2(qt) {
         Ok((qd, qr)) => Ok((qd, qr)),
         Err(e) => {
@@ -1135,7 +1341,7 @@ fn safe_wait2<const N: usize>(libos: &mut InetStack<N>, qt: QToken) -> Result<(Q
 }
 
 /// Safe call to `close()` on passive socket.
-fn safe_close_passive<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc) -> Result<()> {
+fn safe_close_passive(libos: &mut InetStack, sockqd: QDesc) -> Result<()> {
     match libos.close(sockqd) {
         Ok(_) => anyhow::bail!("close() on listening socket should have failed (this is a known bug)"),
         Err(_) => Ok(()),
@@ -1143,7 +1349,7 @@ fn safe_close_passive<const N: usize>(libos: &mut InetStack<N>, sockqd: QDesc) -
 }
 
 /// Safe call to `close()` on active socket.
-fn safe_close_active<const N: usize>(libos: &mut InetStack<N>, qd: QDesc) -> Result<()> {
+fn safe_close_active(libos: &mut InetStack, qd: QDesc) -> Result<()> {
     match libos.close(qd) {
         Ok(_) => Ok(()),
         Err(_) => anyhow::bail!("close() on active socket has failed"),
@@ -12,6 +12,10 @@ use ::demikernel::{
     inetstack::InetStack,
     runtime::{
         memory::DemiBuffer,
+        network::{
+            types::PushCompletion,
+            NetworkRuntime,
+        },
         OperationResult,
         QDesc,
         QToken,
@@ -20,6 +24,7 @@ use ::demikernel::{
 use common::{
     arp,
     libos::*,
+    runtime::DummyRuntime,
     ALICE_IPV4,
     ALICE_MAC,
     BOB_IPV4,
@@ -31,7 +36,6 @@ use crossbeam_channel::{
     Receiver,
     Sender,
 };
-use demikernel::runtime::network::consts::RECEIVE_BATCH_SIZE;
 
 #[cfg(target_os = "windows")]
 pub const AF_INET: i32 = windows::Win32::Networking::WinSock::AF_INET.0 as i32;
@@ -51,6 +55,10 @@ use std::{
         self,
         JoinHandle,
     },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 //==============================================================================
@@ -58,7 +66,7 @@ use std::{
 //==============================================================================
 
 /// Opens and closes a socket using a non-ephemeral port.
-fn do_udp_setup<const N: usize>(libos: &mut InetStack<N>) -> Result<()> {
+fn do_udp_setup(libos: &mut InetStack) -> Result<()> {
     let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE);
     let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
         Ok(qd) => qd,
@@ -73,6 +81,15 @@ fn do_udp_setup<const N: usize>(libos: &mut InetStack<N>) -> Result<()> {
         },
     };
 
+    // Datagrams are never acknowledged, so pushes can only complete once enqueued.
+    if let Err(e) = libos.set_push_completion(sockfd, PushCompletion::Enqueued) {
+        anyhow::bail!("set_push_completion() failed: {:?}", e)
+    }
+    match libos.set_push_completion(sockfd, PushCompletion::Acked) {
+        Err(e) if e.errno == libc::ENOTSUP => (),
+        _ => anyhow::bail!("set_push_completion() should fail with ENOTSUP"),
+    }
+
     match libos.close(sockfd) {
         Ok(_) => Ok(()),
         Err(e) => anyhow::bail!("close() failed: {:?}", e),
@@ -80,7 +97,7 @@ fn do_udp_setup<const N: usize>(libos: &mut InetStack<N>) -> Result<()> {
 }
 
 /// Opens and closes a socket using an ephemeral port.
-fn do_udp_setup_ephemeral<const N: usize>(libos: &mut InetStack<N>) -> Result<()> {
+fn do_udp_setup_ephemeral(libos: &mut InetStack) -> Result<()> {
     const PORT_EPHEMERAL_BASE: u16 = 49152;
     let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_EPHEMERAL_BASE);
     let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
@@ -103,7 +120,7 @@ fn do_udp_setup_ephemeral<const N: usize>(libos: &mut InetStack<N>) -> Result<()
 }
 
 /// Opens and closes a socket using wildcard ephemeral port.
-fn do_udp_setup_wildcard_ephemeral<const N: usize>(libos: &mut InetStack<N>) -> Result<()> {
+fn do_udp_setup_wildcard_ephemeral(libos: &mut InetStack) -> Result<()> {
     let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, 0);
     let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
         Ok(qd) => qd,
@@ -118,6 +135,13 @@ fn do_udp_setup_wildcard_ephemeral<const N: usize>(libos: &mut InetStack<N>) ->
         },
     };
 
+    // Check that an ephemeral port was selected.
+    match libos.getsockname(sockfd) {
+        Ok(addr) if addr.port() != 0 => (),
+        Ok(addr) => anyhow::bail!("getsockname() returned port zero: {:?}", addr),
+        Err(e) => anyhow::bail!("getsockname() failed: {:?}", e),
+    };
+
     match libos.close(sockfd) {
         Ok(_) => Ok(()),
         Err(e) => anyhow::bail!("close() failed: {:?}", e),
@@ -128,7 +152,7 @@ fn do_udp_setup_wildcard_ephemeral<const N: usize>(libos: &mut InetStack<N>) ->
 #[test]
 fn udp_setup() -> Result<()> {
     let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
-    let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
+    let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
         Ok(libos) => libos,
         Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
     };
@@ -144,7 +168,7 @@ fn udp_setup() -> Result<()> {
 #[test]
 fn udp_connect_loopback() -> Result<()> {
     let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
-    let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
+    let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
         Ok(libos) => libos,
         Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
     };
@@ -189,11 +213,10 @@ fn udp_push_remote() -> Result<()> {
     let alice_addr: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, alice_port);
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         // Open connection.
         let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
@@ -242,7 +265,7 @@ fn udp_push_remote() -> Result<()> {
         };
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Pop(_, _) => (),
+            OperationResult::Pop(..) => (),
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -258,8 +281,7 @@ fn udp_push_remote() -> Result<()> {
     });
 
     let bob: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp())
-        {
+        let mut libos: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, alice_rx, arp()) {
             Ok(libos) => libos,
             Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
         };
@@ -289,7 +311,7 @@ fn udp_push_remote() -> Result<()> {
         };
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         let bytes: DemiBuffer = match qr {
-            OperationResult::Pop(_, bytes) => bytes,
+            OperationResult::Pop(_, bytes, _) => bytes,
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -343,11 +365,10 @@ fn udp_loopback() -> Result<()> {
     let alice_addr: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, alice_port);
 
     let alice: JoinHandle<Result<()>> = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         // Open connection.
         let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
@@ -395,7 +416,7 @@ fn udp_loopback() -> Result<()> {
         };
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         match qr {
-            OperationResult::Pop(_, _) => (),
+            OperationResult::Pop(..) => (),
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -411,11 +432,10 @@ fn udp_loopback() -> Result<()> {
     });
 
     let bob = thread::spawn(move || {
-        let mut libos: InetStack<RECEIVE_BATCH_SIZE> =
-            match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, bob_tx, alice_rx, arp()) {
-                Ok(libos) => libos,
-                Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
-            };
+        let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, bob_tx, alice_rx, arp()) {
+            Ok(libos) => libos,
+            Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+        };
 
         // Open connection.
         let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
@@ -441,7 +461,7 @@ fn udp_loopback() -> Result<()> {
         };
         let (_, qr): (QDesc, OperationResult) = safe_wait2(&mut libos, qt)?;
         let bytes: DemiBuffer = match qr {
-            OperationResult::Pop(_, bytes) => bytes,
+            OperationResult::Pop(_, bytes, _) => bytes,
             _ => {
                 // Close socket on error.
                 // FIXME: https://github.com/demikernel/demikernel/issues/633
@@ -476,12 +496,205 @@ fn udp_loopback() -> Result<()> {
     Ok(())
 }
 
+/// Tests if a zero-length datagram is sent as a header-only datagram, and if it is popped as a zero-length buffer that
+/// is distinct from the datagrams around it.
+#[test]
+fn udp_loopback_empty_datagram() -> Result<()> {
+    let (alice_tx, wire_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let (wire_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let (bob_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let mut alice: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, alice_rx, arp()) {
+        Ok(libos) => libos,
+        Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+    };
+    let mut bob: InetStack = match DummyLibOS::new(BOB_MAC, BOB_IPV4, bob_tx, bob_rx, arp()) {
+        Ok(libos) => libos,
+        Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+    };
+
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(BOB_IPV4, PORT_BASE);
+    let alice_fd: QDesc = match alice.socket(AF_INET, SOCK_DGRAM, 0) {
+        Ok(qd) => qd,
+        Err(e) => anyhow::bail!("failed to create socket: {:?}", e),
+    };
+    if let Err(e) = alice.bind(alice_fd, alice_addr) {
+        anyhow::bail!("bind() failed: {:?}", e)
+    }
+    let bob_fd: QDesc = match bob.socket(AF_INET, SOCK_DGRAM, 0) {
+        Ok(qd) => qd,
+        Err(e) => anyhow::bail!("failed to create socket: {:?}", e),
+    };
+    if let Err(e) = bob.bind(bob_fd, bob_addr) {
+        anyhow::bail!("bind() failed: {:?}", e)
+    }
+
+    // Push an empty datagram between two datagrams that carry data.
+    let bytes: DemiBuffer = DummyLibOS::cook_data(32);
+    for size in [32, 0, 32] {
+        let qt: QToken = match alice.pushto2(alice_fd, &bytes[..size], bob_addr) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        match safe_wait2(&mut alice, qt)? {
+            (_, OperationResult::Push) => (),
+            _ => anyhow::bail!("wait on push() failed"),
+        }
+    }
+
+    // The empty datagram consists of the Ethernet, IPv4 and UDP headers only.
+    let frames: Vec<DemiBuffer> = wire_rx
+        .try_iter()
+        .filter(|frame| frame[12..14] == [0x08, 0x00])
+        .collect();
+    let sizes: Vec<usize> = frames.iter().map(|frame| frame.len()).collect();
+    if sizes != [14 + 20 + 8 + 32, 14 + 20 + 8, 14 + 20 + 8 + 32] {
+        anyhow::bail!("unexpected frame sizes: {:?}", sizes)
+    }
+    for frame in frames {
+        if wire_tx.send(frame).is_err() {
+            anyhow::bail!("could not forward frame")
+        }
+    }
+
+    // Each datagram completes its own pop, and the empty one is not taken for the absence of data.
+    for size in [32, 0, 32] {
+        let qt: QToken = match bob.pop(bob_fd, None) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop() failed: {:?}", e),
+        };
+        match safe_wait2(&mut bob, qt)? {
+            (_, OperationResult::Pop(Some(addr), buf, false)) if addr == alice_addr && buf.len() == size => (),
+            (_, OperationResult::Pop(addr, buf, eof)) => {
+                anyhow::bail!("unexpected pop (addr={:?}, size={:?}, eof={:?})", addr, buf.len(), eof)
+            },
+            _ => anyhow::bail!("wait on pop() failed"),
+        }
+    }
+
+    if let Err(e) = alice.close(alice_fd) {
+        anyhow::bail!("close() failed: {:?}", e)
+    }
+    if let Err(e) = bob.close(bob_fd) {
+        anyhow::bail!("close() failed: {:?}", e)
+    }
+
+    Ok(())
+}
+
+/// Tests if a pop with a timeout fails with ETIMEDOUT when no datagram arrives, and if the socket remains usable.
+#[test]
+fn udp_pop_timeout() -> Result<()> {
+    let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp()) {
+        Ok(libos) => libos,
+        Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+    };
+
+    let local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE);
+    let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
+        Ok(qd) => qd,
+        Err(e) => anyhow::bail!("failed to create socket: {:?}", e),
+    };
+    if let Err(e) = libos.bind(sockfd, local) {
+        anyhow::bail!("bind() failed: {:?}", e)
+    }
+
+    // No datagram arrives, so each pop should time out.
+    for _ in 0..2 {
+        let qt: QToken = match libos.pop_timeout(sockfd, None, Duration::from_millis(10)) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("pop_timeout() failed: {:?}", e),
+        };
+        match safe_wait2(&mut libos, qt)? {
+            (qd, OperationResult::Failed(e)) if qd == sockfd && e.errno == libc::ETIMEDOUT => (),
+            _ => anyhow::bail!("pop_timeout() should have timed out"),
+        }
+    }
+
+    if let Err(e) = libos.close(sockfd) {
+        anyhow::bail!("close() failed: {:?}", e)
+    }
+
+    Ok(())
+}
+
+//==============================================================================
+// IPv4 Identification
+//==============================================================================
+
+/// Tests if successive datagrams carry sequentially-advancing IPv4 identifiers, starting from a random value.
+#[test]
+fn udp_ipv4_identification() -> Result<()> {
+    let (alice_tx, alice_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let (_bob_tx, bob_rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let mut libos: InetStack = match DummyLibOS::new(ALICE_MAC, ALICE_IPV4, alice_tx, bob_rx, arp()) {
+        Ok(libos) => libos,
+        Err(e) => anyhow::bail!("Could not create inetstack: {:?}", e),
+    };
+
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(BOB_IPV4, PORT_BASE);
+    let sockfd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
+        Ok(qd) => qd,
+        Err(e) => anyhow::bail!("failed to create socket: {:?}", e),
+    };
+    if let Err(e) = libos.bind(sockfd, alice_addr) {
+        anyhow::bail!("bind() failed: {:?}", e)
+    }
+
+    // Push a few datagrams.
+    let bytes: DemiBuffer = DummyLibOS::cook_data(32);
+    for _ in 0..4 {
+        let qt: QToken = match libos.pushto2(sockfd, &bytes, bob_addr) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("push() failed: {:?}", e),
+        };
+        match safe_wait2(&mut libos, qt)? {
+            (_, OperationResult::Push) => (),
+            _ => anyhow::bail!("wait on push() failed"),
+        }
+    }
+
+    // The identification field follows the Ethernet header and the first four bytes of the IPv4 header.
+    let ids: Vec<u16> = alice_rx
+        .try_iter()
+        .filter(|frame| frame[12..14] == [0x08, 0x00])
+        .map(|frame| u16::from_be_bytes([frame[18], frame[19]]))
+        .collect();
+    if ids.len() != 4 {
+        anyhow::bail!("expected 4 datagrams, got {:?}", ids.len())
+    }
+    for pair in ids.windows(2) {
+        if pair[1] != pair[0].wrapping_add(1) {
+            anyhow::bail!("identifiers do not advance sequentially: {:?}", ids)
+        }
+    }
+
+    if let Err(e) = libos.close(sockfd) {
+        anyhow::bail!("close() failed: {:?}", e)
+    }
+
+    // Identifiers of different runtimes start at random values, thus a few runtimes should not all agree.
+    let first_ids: Vec<u16> = (0..4)
+        .map(|_| {
+            let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+            DummyRuntime::new(Instant::now(), rx, tx).next_ipv4_id()
+        })
+        .collect();
+    if first_ids.iter().all(|id| *id == first_ids[0]) {
+        anyhow::bail!("initial identifiers are not randomized: {:?}", first_ids)
+    }
+
+    Ok(())
+}
+
 //======================================================================================================================
 // Standalone Functions
 //======================================================================================================================
 
 /// Safe call to `wait2()`.
-fn safe_wait2<const N: usize>(libos: &mut InetStack<N>, qt: QToken) -> Result<(QDesc, OperationResult)> {
+fn safe_wait2(libos: &mut InetStack, qt: QToken) -> Result<(QDesc, OperationResult)> {
     match libos.wait2(qt) {
         Ok((qd, qr)) => Ok((qd, qr)),
         Err(e) => {